@@ -2,22 +2,33 @@
 // Copyright 2019-2022 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use std::io::Read;
+use std::time::Instant;
 
 use anyhow::{anyhow, Result};
 use cid::Cid;
 use fvm_ipld_blockstore::{Blockstore, Buffered};
-use fvm_ipld_encoding::{CBOR, DAG_CBOR, IPLD_RAW};
+use fvm_ipld_encoding::{scan_for_links, CBOR, DAG_CBOR, IPLD_RAW};
 use fvm_shared::commcid::{FIL_COMMITMENT_SEALED, FIL_COMMITMENT_UNSEALED};
 
+use super::{FlushIntent, FlushJournal};
+use crate::kernel::ErrorContext;
+use crate::machine::FlushStats;
+
+/// The default maximum number of blocks written to the backing store per `put_many_keyed` call
+/// when flushing, see [`BufferedBlockstore::with_flush_batch_size`].
+pub const DEFAULT_FLUSH_BATCH_SIZE: usize = 1024;
+
 /// Wrapper around `Blockstore` to limit and have control over when values are written.
 /// This type is not threadsafe and can only be used in synchronous contexts.
 #[derive(Debug)]
 pub struct BufferedBlockstore<BS> {
     base: BS,
     write: RefCell<HashMap<Cid, Vec<u8>>>,
+    gets: Cell<u64>,
+    puts: Cell<u64>,
+    flush_batch_size: usize,
 }
 
 impl<BS> BufferedBlockstore<BS>
@@ -28,12 +39,99 @@ where
         Self {
             base,
             write: Default::default(),
+            gets: Default::default(),
+            puts: Default::default(),
+            flush_batch_size: DEFAULT_FLUSH_BATCH_SIZE,
         }
     }
 
+    /// Writes at most `size` blocks to the backing store per `put_many_keyed` call when flushing,
+    /// so that disk-backed stores commit the new state in bounded write batches.
+    pub fn with_flush_batch_size(mut self, size: usize) -> Self {
+        self.flush_batch_size = size.max(1);
+        self
+    }
+
+    /// Returns the number of blocks read through this blockstore.
+    pub fn gets(&self) -> u64 {
+        self.gets.get()
+    }
+
+    /// Returns the number of blocks written to this blockstore.
+    pub fn puts(&self) -> u64 {
+        self.puts.get()
+    }
+
     pub fn into_inner(self) -> BS {
         self.base
     }
+
+    /// Returns a copy of the blocks that have been written but not yet flushed to the backing
+    /// store.
+    pub fn buffered_blocks(&self) -> Vec<(Cid, Vec<u8>)> {
+        self.write
+            .borrow()
+            .iter()
+            .map(|(k, v)| (*k, v.clone()))
+            .collect()
+    }
+
+    /// Returns a copy of the write buffer.
+    pub(crate) fn write_buffer(&self) -> HashMap<Cid, Vec<u8>> {
+        self.write.borrow().clone()
+    }
+
+    /// Replaces the write buffer, discarding all blocks written since it was copied.
+    pub(crate) fn set_write_buffer(&self, blocks: HashMap<Cid, Vec<u8>>) {
+        *self.write.borrow_mut() = blocks;
+    }
+
+    /// Like [`Buffered::flush`], but also returns statistics about the flush. The `hash_time` is
+    /// left for the caller to fill in.
+    pub fn flush_with_stats(&self, root: &Cid) -> Result<FlushStats> {
+        self.flush_inner(root, None)
+    }
+
+    /// Like [`BufferedBlockstore::flush_with_stats`], but durably records the blocks in the
+    /// journal before writing them, and clears the journal once they're written. See
+    /// [`recover_flush`](super::recover_flush).
+    pub fn flush_journaled(&self, root: &Cid, journal: &dyn FlushJournal) -> Result<FlushStats> {
+        self.flush_inner(root, Some(journal))
+    }
+
+    fn flush_inner(&self, root: &Cid, journal: Option<&dyn FlushJournal>) -> Result<FlushStats> {
+        let mut stats = FlushStats::default();
+
+        let start = Instant::now();
+        let blocks = take_reachable(
+            &mut self.write.borrow_mut(),
+            root,
+            &mut stats.reachable_blocks,
+        )?;
+        stats.traverse_time = start.elapsed();
+        stats.blocks_written = blocks.len();
+        stats.bytes_written = blocks.iter().map(|(_, block)| block.len()).sum();
+
+        let start = Instant::now();
+        let intent = FlushIntent {
+            root: *root,
+            blocks,
+        };
+        if let Some(journal) = journal {
+            journal.record(&intent)?;
+        }
+        for batch in intent.blocks.chunks(self.flush_batch_size) {
+            self.base
+                .put_many_keyed(batch.iter().map(|(k, block)| (*k, block)))?;
+            stats.batches_written += 1;
+        }
+        if let Some(journal) = journal {
+            journal.clear()?;
+        }
+        stats.write_time = start.elapsed();
+
+        Ok(stats)
+    }
 }
 
 impl<BS> Buffered for BufferedBlockstore<BS>
@@ -44,99 +142,17 @@ where
     /// This will recursively traverse the cache and write all data connected by links to this
     /// root Cid, moving the reachable blocks from the write buffer to the backing store.
     fn flush(&self, root: &Cid) -> Result<()> {
-        self.base
-            .put_many_keyed(take_reachable(&mut self.write.borrow_mut(), root)?)
-    }
-}
-
-/// Given a CBOR encoded Buffer, returns a tuple of:
-/// the type of the CBOR object along with extra
-/// elements we expect to read. More info on this can be found in
-/// Appendix C. of RFC 7049 which defines the CBOR specification.
-/// This was implemented because the CBOR library we use does not expose low
-/// methods like this, requiring us to deserialize the whole CBOR payload, which
-/// is unnecessary and quite inefficient for our usecase here.
-fn cbor_read_header_buf<B: Read>(br: &mut B) -> anyhow::Result<(u8, u64)> {
-    #[inline(always)]
-    pub fn read_fixed<const N: usize>(r: &mut impl Read) -> std::io::Result<[u8; N]> {
-        let mut buf = [0; N];
-        r.read_exact(&mut buf).map(|_| buf)
-    }
-
-    let first = read_fixed::<1>(br)?[0];
-    let maj = (first & 0xe0) >> 5;
-    let low = first & 0x1f;
-
-    let val = match low {
-        ..=23 => low.into(),
-        24 => read_fixed::<1>(br)?[0].into(),
-        25 => u16::from_be_bytes(read_fixed(br)?).into(),
-        26 => u32::from_be_bytes(read_fixed(br)?).into(),
-        27 => u64::from_be_bytes(read_fixed(br)?),
-        _ => return Err(anyhow!("invalid header cbor_read_header_buf")),
-    };
-    Ok((maj, val))
-}
-
-/// Given a CBOR serialized IPLD buffer, read through all of it and return all the Links.
-/// This function is useful because it is quite a bit more fast than doing this recursively on a
-/// deserialized IPLD object.
-fn scan_for_links(mut buf: &[u8], out: &mut Vec<Cid>) -> Result<()> {
-    let mut remaining = 1;
-    while remaining > 0 {
-        let (maj, extra) = cbor_read_header_buf(&mut buf)?;
-        match maj {
-            // MajUnsignedInt, MajNegativeInt, MajOther
-            0 | 1 | 7 => {}
-            // MajByteString, MajTextString
-            2 | 3 => {
-                if extra > buf.len() as u64 {
-                    return Err(anyhow!("unexpected end of cbor stream"));
-                }
-                buf = &buf[extra as usize..];
-            }
-            // MajTag
-            6 => {
-                // Check if the tag refers to a CID
-                if extra == 42 {
-                    let (maj, extra) = cbor_read_header_buf(&mut buf)?;
-                    // The actual CID is expected to be a byte string
-                    if maj != 2 {
-                        return Err(anyhow!("expected cbor type byte string in input"));
-                    }
-                    if extra > buf.len() as u64 {
-                        return Err(anyhow!("unexpected end of cbor stream"));
-                    }
-                    if buf.first() != Some(&0u8) {
-                        return Err(anyhow!("DagCBOR CID does not start with a 0x byte"));
-                    }
-                    let cid_buf;
-                    (cid_buf, buf) = buf.split_at(extra as usize);
-                    out.push(Cid::try_from(&cid_buf[1..])?);
-                } else {
-                    remaining += 1;
-                }
-            }
-            // MajArray
-            4 => {
-                remaining += extra;
-            }
-            // MajMap
-            5 => {
-                remaining += extra * 2;
-            }
-            8.. => {
-                // This case is statically impossible unless `cbor_read_header_buf` makes a mistake.
-                return Err(anyhow!("invalid cbor tag exceeds 3 bits: {}", maj));
-            }
-        }
-        remaining -= 1;
+        self.flush_with_stats(root).map(drop)
     }
-    Ok(())
 }
 
-/// Moves the IPLD DAG under `root` from the cache to the base store.
-fn take_reachable(cache: &mut HashMap<Cid, Vec<u8>>, root: &Cid) -> Result<Vec<(Cid, Vec<u8>)>> {
+/// Moves the IPLD DAG under `root` from the cache to the base store, counting the number of
+/// reachable blocks visited in `visited`.
+fn take_reachable(
+    cache: &mut HashMap<Cid, Vec<u8>>,
+    root: &Cid,
+    visited: &mut usize,
+) -> Result<Vec<(Cid, Vec<u8>)>> {
     const BLAKE2B_256: u64 = 0xb220;
     const BLAKE2B_LEN: u8 = 32;
     const IDENTITY: u64 = 0x0;
@@ -158,7 +174,11 @@ fn take_reachable(cache: &mut HashMap<Cid, Vec<u8>>, root: &Cid) -> Result<Vec<(
             // We allow raw, cbor, and dag cbor.
             IPLD_RAW | DAG_CBOR | CBOR => (),
             // Everything else is rejected.
-            codec => return Err(anyhow!("cid {k} has unexpected codec ({codec})")),
+            codec => {
+                return Err(ErrorContext::default()
+                    .with_cid(k)
+                    .attach(anyhow!("cid {k} has unexpected codec ({codec})")))
+            }
         }
         // Check the hash construction.
         match (k.hash().code(), k.hash().size()) {
@@ -166,11 +186,12 @@ fn take_reachable(cache: &mut HashMap<Cid, Vec<u8>>, root: &Cid) -> Result<Vec<(
             (BLAKE2B_256, BLAKE2B_LEN) | (IDENTITY, _) => (),
             // Reject everything else.
             (hash, length) => {
-                return Err(anyhow!(
+                return Err(ErrorContext::default().with_cid(k).attach(anyhow!(
                     "cid {k} has unexpected multihash (code={hash}, len={length})"
-                ))
+                )))
             }
         }
+        *visited += 1;
         if k.hash().code() == IDENTITY {
             if k.codec() == DAG_CBOR {
                 scan_for_links(k.hash().digest(), &mut stack)?;
@@ -201,6 +222,7 @@ where
     BS: Blockstore,
 {
     fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>> {
+        self.gets.set(self.gets.get() + 1);
         Ok(if let Some(data) = self.write.borrow().get(cid) {
             Some(data.clone())
         } else {
@@ -209,6 +231,7 @@ where
     }
 
     fn put_keyed(&self, cid: &Cid, buf: &[u8]) -> Result<()> {
+        self.puts.set(self.puts.get() + 1);
         self.write.borrow_mut().insert(*cid, Vec::from(buf));
         Ok(())
     }
@@ -227,18 +250,50 @@ where
         D: AsRef<[u8]>,
         I: IntoIterator<Item = (Cid, D)>,
     {
+        let mut puts = 0;
         self.write
             .borrow_mut()
-            .extend(blocks.into_iter().map(|(k, v)| (k, v.as_ref().into())));
+            .extend(blocks.into_iter().map(|(k, v)| {
+                puts += 1;
+                (k, v.as_ref().into())
+            }));
+        self.puts.set(self.puts.get() + puts);
         Ok(())
     }
+
+    /// Reads the blocks that aren't buffered from the base store in a single call.
+    fn get_many<I>(&self, keys: I) -> Result<Vec<Option<Vec<u8>>>>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = Cid>,
+    {
+        let write = self.write.borrow();
+        let mut blocks = Vec::new();
+        let mut missing = Vec::new();
+        for k in keys {
+            let block = write.get(&k).cloned();
+            if block.is_none() {
+                missing.push((blocks.len(), k));
+            }
+            blocks.push(block);
+        }
+        self.gets.set(self.gets.get() + blocks.len() as u64);
+        if !missing.is_empty() {
+            let found = self.base.get_many(missing.iter().map(|&(_, k)| k))?;
+            for ((i, _), block) in missing.into_iter().zip(found) {
+                blocks[i] = block;
+            }
+        }
+        Ok(blocks)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use cid::multihash::{Code, Multihash};
+    use cid::multihash::{Code, Multihash, MultihashDigest};
+    use fvm_ipld_blockstore::tracking::TrackingBlockstore;
     use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
-    use fvm_ipld_encoding::CborStore;
+    use fvm_ipld_encoding::{to_vec, CborStore};
     use fvm_shared::{commcid, IDENTITY_HASH};
     use serde::{Deserialize, Serialize};
 
@@ -327,4 +382,73 @@ mod tests {
         assert_eq!(buf_store.get(&sealed_comm_cid).unwrap(), None);
         assert_eq!(mem.get_cbor::<u8>(&unconnected).unwrap(), None);
     }
+
+    #[test]
+    fn buffered_store_flush_stats() {
+        let mem = MemoryBlockstore::default();
+        let buf_store = BufferedBlockstore::new(&mem);
+
+        let leaf = buf_store.put_cbor(&8u8, Code::Blake2b256).unwrap();
+        let root = buf_store.put_cbor(&(leaf, 1u8), Code::Blake2b256).unwrap();
+        buf_store.put_cbor(&27u8, Code::Blake2b256).unwrap();
+
+        let stats = buf_store.flush_with_stats(&root).unwrap();
+        assert_eq!(stats.reachable_blocks, 2);
+        assert_eq!(stats.blocks_written, 2);
+        assert_eq!(stats.batches_written, 1);
+        assert_eq!(
+            stats.bytes_written,
+            mem.get(&leaf).unwrap().unwrap().len() + mem.get(&root).unwrap().unwrap().len()
+        );
+
+        // Flushing again only visits the root, which has already been written.
+        let stats = buf_store.flush_with_stats(&root).unwrap();
+        assert_eq!(stats.reachable_blocks, 1);
+        assert_eq!(stats.blocks_written, 0);
+        assert_eq!(stats.batches_written, 0);
+        assert_eq!(stats.bytes_written, 0);
+    }
+
+    #[test]
+    fn buffered_store_flush_batches() {
+        let mem = TrackingBlockstore::new(MemoryBlockstore::default());
+        let buf_store = BufferedBlockstore::new(&mem).with_flush_batch_size(2);
+
+        let leaves: Vec<Cid> = (0u8..4)
+            .map(|i| buf_store.put_cbor(&i, Code::Blake2b256).unwrap())
+            .collect();
+        let root = buf_store.put_cbor(&leaves, Code::Blake2b256).unwrap();
+
+        let stats = buf_store.flush_with_stats(&root).unwrap();
+        assert_eq!(stats.blocks_written, 5);
+        assert_eq!(stats.batches_written, 3);
+        assert_eq!(mem.stats.borrow().w, 5);
+        for cid in leaves.iter().chain([&root]) {
+            assert!(mem.has(cid).unwrap());
+        }
+    }
+
+    #[test]
+    fn buffered_store_get_many() {
+        let mem = TrackingBlockstore::new(MemoryBlockstore::default());
+        let flushed = mem.put_cbor(&1u8, Code::Blake2b256).unwrap();
+        let buf_store = BufferedBlockstore::new(&mem);
+        let buffered = buf_store.put_cbor(&2u8, Code::Blake2b256).unwrap();
+        let missing = Cid::new_v1(CBOR, Code::Blake2b256.digest(b"missing"));
+
+        let blocks = buf_store
+            .get_many([buffered, flushed, missing, buffered])
+            .unwrap();
+        assert_eq!(
+            blocks,
+            [
+                Some(to_vec(&2u8).unwrap()),
+                Some(to_vec(&1u8).unwrap()),
+                None,
+                Some(to_vec(&2u8).unwrap()),
+            ]
+        );
+        // Only the blocks that aren't buffered are read from the base store.
+        assert_eq!(mem.stats.borrow().r, 2);
+    }
 }
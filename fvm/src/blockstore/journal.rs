@@ -0,0 +1,299 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! An optional write-ahead journal for flushes of the [`BufferedBlockstore`].
+//!
+//! Flushing writes each block reachable from the new state root to the backing store. If the
+//! process crashes half-way through, a store without transactions is left with only some of the
+//! blocks. With a journal, the blocks of each flush are durably recorded before any is written,
+//! and [`recover_flush`] completes the interrupted flush on restart.
+//!
+//! [`BufferedBlockstore`]: super::BufferedBlockstore
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::{from_slice, to_vec, BytesDe, BytesSer};
+use multihash::MultihashDigest;
+
+use crate::kernel::SupportedHashes;
+
+/// The blocks a flush is about to write to the backing store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlushIntent {
+    /// The state root being flushed.
+    pub root: Cid,
+    /// The blocks reachable from the root that weren't yet written.
+    pub blocks: Vec<(Cid, Vec<u8>)>,
+}
+
+/// A durable journal of flush intents. At most one flush is pending at a time.
+pub trait FlushJournal: Send + Sync + 'static {
+    /// Durably records the intent, replacing any pending one. Called before any block of the
+    /// flush is written.
+    fn record(&self, intent: &FlushIntent) -> Result<()>;
+
+    /// Clears the pending intent. Called once all blocks of the flush have been written.
+    fn clear(&self) -> Result<()>;
+
+    /// Returns the pending intent, if a flush was interrupted.
+    fn pending(&self) -> Result<Option<FlushIntent>>;
+}
+
+impl fmt::Debug for dyn FlushJournal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FlushJournal")
+    }
+}
+
+/// The outcome of [`recover_flush`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushRecovery {
+    /// No flush was interrupted.
+    Clean,
+    /// An interrupted flush of the given root was completed.
+    Completed(Cid),
+    /// An interrupted flush of the given root was rolled back because its intent was corrupt.
+    /// The root must not be treated as committed. Any of its blocks that were already written are
+    /// harmless as they're unreachable.
+    RolledBack(Cid),
+}
+
+/// Detects and resolves a flush interrupted by a crash, before the store is used again.
+///
+/// If the journal holds a pending intent, its blocks are verified against their CIDs and written
+/// (again) to the store, completing the flush. If any block fails to verify, the flush is rolled
+/// back instead. Either way, the pending intent is cleared.
+pub fn recover_flush<BS: Blockstore>(
+    journal: &dyn FlushJournal,
+    store: &BS,
+) -> Result<FlushRecovery> {
+    let Some(intent) = journal.pending()? else {
+        return Ok(FlushRecovery::Clean);
+    };
+
+    let outcome = if intent
+        .blocks
+        .iter()
+        .all(|(k, block)| verify_block(k, block))
+    {
+        store
+            .put_many_keyed(intent.blocks.iter().map(|(k, block)| (*k, block)))
+            .context("failed to complete interrupted flush")?;
+        FlushRecovery::Completed(intent.root)
+    } else {
+        log::warn!(
+            "rolling back interrupted flush of {}: corrupt intent",
+            intent.root
+        );
+        FlushRecovery::RolledBack(intent.root)
+    };
+    journal.clear()?;
+    Ok(outcome)
+}
+
+/// Returns true if the block hashes to its CID.
+fn verify_block(k: &Cid, block: &[u8]) -> bool {
+    match SupportedHashes::try_from(k.hash().code()) {
+        Ok(hasher) => hasher.digest(block).digest() == k.hash().digest(),
+        Err(_) => false,
+    }
+}
+
+/// A [`FlushJournal`] backed by a single file.
+///
+/// Intents are written to a temporary file that is synced and then atomically renamed over the
+/// journal file (and the directory is synced), so a crash while recording leaves the previous
+/// state of the journal intact.
+#[derive(Debug, Clone)]
+pub struct FileJournal {
+    path: PathBuf,
+}
+
+impl FileJournal {
+    /// Creates a journal stored at the given path. The file is only created while a flush is
+    /// pending.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn tmp_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".tmp");
+        path.into()
+    }
+
+    /// Syncs the directory containing the journal, making the rename of the journal file durable.
+    #[cfg(unix)]
+    fn sync_dir(&self) -> Result<()> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => std::path::Path::new("."),
+        };
+        File::open(dir)
+            .and_then(|dir| dir.sync_all())
+            .with_context(|| format!("failed to sync flush journal directory {}", dir.display()))
+    }
+
+    /// Directories can't be opened (and synced) like files on this platform.
+    #[cfg(not(unix))]
+    fn sync_dir(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl FlushJournal for FileJournal {
+    fn record(&self, intent: &FlushIntent) -> Result<()> {
+        let blocks: Vec<_> = intent
+            .blocks
+            .iter()
+            .map(|(k, block)| (k, BytesSer(block)))
+            .collect();
+        let data = to_vec(&(&intent.root, blocks))?;
+
+        let tmp = self.tmp_path();
+        let mut file = File::create(&tmp)
+            .with_context(|| format!("failed to create flush journal {}", tmp.display()))?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("failed to record flush intent in {}", self.path.display()))?;
+        self.sync_dir()
+    }
+
+    fn clear(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e)
+                .with_context(|| format!("failed to clear flush journal {}", self.path.display())),
+            _ => Ok(()),
+        }
+    }
+
+    fn pending(&self) -> Result<Option<FlushIntent>> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("failed to read flush journal {}", self.path.display())
+                })
+            }
+        };
+        let (root, blocks): (Cid, Vec<(Cid, BytesDe)>) = from_slice(&data)
+            .with_context(|| format!("invalid flush journal {}", self.path.display()))?;
+        Ok(Some(FlushIntent {
+            root,
+            blocks: blocks.into_iter().map(|(k, block)| (k, block.0)).collect(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use cid::multihash::Code;
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_ipld_encoding::CborStore;
+
+    use super::*;
+    use crate::blockstore::BufferedBlockstore;
+
+    #[derive(Default)]
+    struct MemoryJournal {
+        pending: Mutex<Option<FlushIntent>>,
+        recorded: Mutex<Vec<Cid>>,
+    }
+
+    impl FlushJournal for MemoryJournal {
+        fn record(&self, intent: &FlushIntent) -> Result<()> {
+            self.recorded.lock().unwrap().push(intent.root);
+            *self.pending.lock().unwrap() = Some(intent.clone());
+            Ok(())
+        }
+
+        fn clear(&self) -> Result<()> {
+            *self.pending.lock().unwrap() = None;
+            Ok(())
+        }
+
+        fn pending(&self) -> Result<Option<FlushIntent>> {
+            Ok(self.pending.lock().unwrap().clone())
+        }
+    }
+
+    #[test]
+    fn journaled_flush() {
+        let mem = MemoryBlockstore::default();
+        let buf_store = BufferedBlockstore::new(&mem);
+        let journal = MemoryJournal::default();
+
+        let leaf = buf_store.put_cbor(&"leaf", Code::Blake2b256).unwrap();
+        let root = buf_store.put_cbor(&(leaf, 1u8), Code::Blake2b256).unwrap();
+        let stats = buf_store.flush_journaled(&root, &journal).unwrap();
+        assert_eq!(stats.blocks_written, 2);
+
+        // The intent was recorded, then cleared once the blocks were written.
+        assert_eq!(*journal.recorded.lock().unwrap(), vec![root]);
+        assert_eq!(journal.pending().unwrap(), None);
+        assert!(mem.has(&leaf).unwrap());
+        assert_eq!(recover_flush(&journal, &mem).unwrap(), FlushRecovery::Clean);
+    }
+
+    #[test]
+    fn recover_interrupted_flush() {
+        let mem = MemoryBlockstore::default();
+        let buf_store = BufferedBlockstore::new(MemoryBlockstore::default());
+        let root = buf_store.put_cbor(&"root", Code::Blake2b256).unwrap();
+        let intent = FlushIntent {
+            root,
+            blocks: buf_store.buffered_blocks(),
+        };
+
+        // A flush that was interrupted after recording its intent is completed.
+        let journal = MemoryJournal::default();
+        journal.record(&intent).unwrap();
+        assert_eq!(
+            recover_flush(&journal, &mem).unwrap(),
+            FlushRecovery::Completed(root)
+        );
+        assert_eq!(mem.get_cbor::<String>(&root).unwrap().unwrap(), "root");
+        assert_eq!(journal.pending().unwrap(), None);
+
+        // A corrupt intent is rolled back.
+        let mem = MemoryBlockstore::default();
+        let mut corrupt = intent;
+        corrupt.blocks[0].1.push(0);
+        journal.record(&corrupt).unwrap();
+        assert_eq!(
+            recover_flush(&journal, &mem).unwrap(),
+            FlushRecovery::RolledBack(root)
+        );
+        assert!(!mem.has(&root).unwrap());
+        assert_eq!(journal.pending().unwrap(), None);
+    }
+
+    #[test]
+    fn file_journal() {
+        let path = std::env::temp_dir().join(format!("fvm-flush-journal-{}", std::process::id()));
+        let journal = FileJournal::new(&path);
+        assert_eq!(journal.pending().unwrap(), None);
+        journal.clear().unwrap();
+
+        let buf_store = BufferedBlockstore::new(MemoryBlockstore::default());
+        let root = buf_store.put_cbor(&"root", Code::Blake2b256).unwrap();
+        let intent = FlushIntent {
+            root,
+            blocks: buf_store.buffered_blocks(),
+        };
+        journal.record(&intent).unwrap();
+        assert_eq!(journal.pending().unwrap(), Some(intent));
+
+        journal.clear().unwrap();
+        assert_eq!(journal.pending().unwrap(), None);
+        assert!(!path.exists());
+    }
+}
@@ -3,7 +3,7 @@
 //! Private blockstores for use in the FVM.
 
 mod buffered;
-mod discard;
+mod journal;
 
-pub use buffered::BufferedBlockstore;
-pub(crate) use discard::DiscardBlockstore;
+pub use buffered::{BufferedBlockstore, DEFAULT_FLUSH_BATCH_SIZE};
+pub use journal::{recover_flush, FileJournal, FlushIntent, FlushJournal, FlushRecovery};
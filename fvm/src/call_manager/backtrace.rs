@@ -1,19 +1,27 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::borrow::Cow;
 use std::fmt::Display;
 
+use fvm_ipld_encoding::tuple::*;
 use fvm_shared::address::Address;
 use fvm_shared::error::{ErrorNumber, ExitCode};
 use fvm_shared::ActorID;
+use num_traits::FromPrimitive;
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::kernel::SyscallError;
+use crate::kernel::{ErrorContext, SyscallError};
 
 use super::Entrypoint;
 
 /// A call backtrace records the actors an error was propagated through, from
 /// the moment it was emitted. The original error is the _cause_. Backtraces are
 /// useful for identifying the root cause of an error.
-#[derive(Debug, Default, Clone)]
+///
+/// Backtraces are serialized as `[frames, cause]`, with the frames in propagation order (source
+/// first).
+#[derive(Debug, Default, Clone, Serialize_tuple, Deserialize_tuple)]
 pub struct Backtrace {
     /// The actors through which this error was propagated from bottom (source) to top.
     pub frames: Vec<Frame>,
@@ -73,8 +81,8 @@ impl Backtrace {
     }
 }
 
-/// A "frame" in a call backtrace.
-#[derive(Clone, Debug)]
+/// A "frame" in a call backtrace, serialized as `[source, entrypoint, code, message]`.
+#[derive(Clone, Debug, Serialize_tuple, Deserialize_tuple)]
 pub struct Frame {
     /// The actor that exited with this code.
     pub source: ActorID,
@@ -99,19 +107,30 @@ impl Display for Frame {
     }
 }
 
+/// The maximum number of syscall arguments recorded in a [`Cause::Syscall`].
+pub const MAX_SYSCALL_ARGS: usize = 8;
+
 /// The ultimate "cause" of a failed message.
+///
+/// Syscall causes are serialized as `[0, module, function, error, message, args]` and fatal causes
+/// as `[1, error_msg, context]`. The Rust backtrace of a fatal error is specific to the process
+/// that captured it and is not serialized.
 #[derive(Clone, Debug)]
 pub enum Cause {
     /// The original cause was a syscall error.
     Syscall {
         /// The syscall "module".
-        module: &'static str,
+        module: Cow<'static, str>,
         /// The syscall function name.
-        function: &'static str,
+        function: Cow<'static, str>,
         /// The exact syscall error.
         error: ErrorNumber,
         /// The informational syscall message.
         message: String,
+        /// The (scalar) arguments the syscall was invoked with, formatted as strings. Pointer
+        /// arguments are recorded as offsets into the actor's memory. At most
+        /// [`MAX_SYSCALL_ARGS`] arguments are recorded.
+        args: Vec<String>,
     },
     /// The original cause was a fatal error.
     Fatal {
@@ -120,6 +139,8 @@ pub enum Cause {
         /// The backtrace, captured if the relevant
         /// [environment variables](https://doc.rust-lang.org/std/backtrace/index.html#environment-variables) are enabled.
         backtrace: String,
+        /// The structured context (actor, epoch, CID) attached to the error, if any.
+        context: Option<ErrorContext>,
     },
 }
 
@@ -127,11 +148,20 @@ impl Cause {
     /// Records a failing syscall as the cause of a backtrace.
     pub fn from_syscall(module: &'static str, function: &'static str, err: SyscallError) -> Self {
         Self::Syscall {
-            module,
-            function,
+            module: module.into(),
+            function: function.into(),
             error: err.1,
             message: err.0,
+            args: Vec::new(),
+        }
+    }
+
+    /// Records the arguments of the failing syscall. This has no effect on fatal errors.
+    pub fn with_args(mut self, new_args: impl IntoIterator<Item = String>) -> Self {
+        if let Cause::Syscall { args, .. } = &mut self {
+            *args = new_args.into_iter().take(MAX_SYSCALL_ARGS).collect();
         }
+        self
     }
 
     /// Records a fatal error as the cause of a backtrace.
@@ -139,6 +169,7 @@ impl Cause {
         Self::Fatal {
             error_msg: format!("{:#}", err),
             backtrace: err.backtrace().to_string(),
+            context: ErrorContext::of(&err).cloned(),
         }
     }
 }
@@ -151,19 +182,170 @@ impl Display for Cause {
                 function,
                 error,
                 message,
+                args,
             } => {
                 write!(
                     f,
-                    "{}::{} -- {} ({}: {})",
-                    module, function, &message, *error as u32, error,
+                    "{}::{}({}) -- {} ({}: {})",
+                    module,
+                    function,
+                    args.join(", "),
+                    &message,
+                    *error as u32,
+                    error,
                 )
             }
             Cause::Fatal {
                 error_msg,
                 backtrace,
+                ..
             } => {
                 write!(f, "[FATAL] Error: {}, Backtrace:\n{}", error_msg, backtrace)
             }
         }
     }
 }
+
+const SYSCALL_CAUSE: u8 = 0;
+const FATAL_CAUSE: u8 = 1;
+
+impl Serialize for Cause {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Cause::Syscall {
+                module,
+                function,
+                error,
+                message,
+                args,
+            } => (
+                SYSCALL_CAUSE,
+                module,
+                function,
+                *error as u32,
+                message,
+                args,
+            )
+                .serialize(serializer),
+            Cause::Fatal {
+                error_msg, context, ..
+            } => (FATAL_CAUSE, error_msg, context).serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Cause {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CauseVisitor;
+
+        impl<'de> Visitor<'de> for CauseVisitor {
+            type Value = Cause;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a backtrace cause")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Cause, A::Error> {
+                let cause = match element(&mut seq, 0)? {
+                    SYSCALL_CAUSE => {
+                        let module: String = element(&mut seq, 1)?;
+                        let function: String = element(&mut seq, 2)?;
+                        let error: u32 = element(&mut seq, 3)?;
+                        Cause::Syscall {
+                            module: module.into(),
+                            function: function.into(),
+                            error: ErrorNumber::from_u32(error).ok_or_else(|| {
+                                de::Error::custom(format!("unknown error number {}", error))
+                            })?,
+                            message: element(&mut seq, 4)?,
+                            args: element(&mut seq, 5)?,
+                        }
+                    }
+                    FATAL_CAUSE => Cause::Fatal {
+                        error_msg: element(&mut seq, 1)?,
+                        backtrace: String::new(),
+                        context: element(&mut seq, 2)?,
+                    },
+                    kind => {
+                        return Err(de::Error::custom(format!("unknown cause kind {}", kind)));
+                    }
+                };
+                Ok(cause)
+            }
+        }
+
+        fn element<'de, A: SeqAccess<'de>, T: Deserialize<'de>>(
+            seq: &mut A,
+            idx: usize,
+        ) -> Result<T, A::Error> {
+            seq.next_element()?
+                .ok_or_else(|| de::Error::invalid_length(idx, &"a backtrace cause"))
+        }
+
+        deserializer.deserialize_seq(CauseVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cid::Cid;
+    use fvm_ipld_encoding::{from_slice, to_vec};
+    use fvm_shared::upgrade::UpgradeInfo;
+
+    use super::*;
+    use crate::kernel::SyscallError;
+
+    #[test]
+    fn backtrace_round_trip() {
+        let backtrace = Backtrace {
+            frames: vec![
+                Frame {
+                    source: 1000,
+                    entrypoint: Entrypoint::Invoke(2),
+                    code: ExitCode::USR_ASSERTION_FAILED,
+                    message: "failed".into(),
+                },
+                Frame {
+                    source: 1001,
+                    entrypoint: Entrypoint::Upgrade(UpgradeInfo {
+                        old_code_cid: Default::default(),
+                    }),
+                    code: ExitCode::USR_ASSERTION_FAILED,
+                    message: "upgrade failed".into(),
+                },
+            ],
+            cause: Some(
+                Cause::from_syscall(
+                    "send",
+                    "send",
+                    SyscallError::new(ErrorNumber::NotFound, "actor not found"),
+                )
+                .with_args(["1000".to_string(), "2".to_string()]),
+            ),
+        };
+
+        let encoded = to_vec(&backtrace).unwrap();
+        let decoded: Backtrace = from_slice(&encoded).unwrap();
+        // There's no equality on backtraces, so compare the (deterministic) encodings.
+        assert_eq!(to_vec(&decoded).unwrap(), encoded);
+        assert_eq!(decoded.to_string(), backtrace.to_string());
+
+        assert!(matches!(
+            decoded.frames[1].entrypoint,
+            Entrypoint::Upgrade(ui) if ui.old_code_cid == Cid::default()
+        ));
+        // Upgrade info is only accepted for the upgrade method.
+        let bad_entrypoint = to_vec(&(
+            2u64,
+            Some(UpgradeInfo {
+                old_code_cid: Default::default(),
+            }),
+        ))
+        .unwrap();
+        assert!(from_slice::<Entrypoint>(&bad_entrypoint).is_err());
+
+        let fatal = Cause::from_fatal(anyhow::anyhow!("boom"));
+        let decoded: Cause = from_slice(&to_vec(&fatal).unwrap()).unwrap();
+        assert!(matches!(decoded, Cause::Fatal { error_msg, .. } if error_msg == "boom"));
+    }
+}
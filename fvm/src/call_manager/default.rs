@@ -1,11 +1,12 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::cell::Cell;
 use std::rc::Rc;
+use std::time::Instant;
 
 use anyhow::{anyhow, Context};
 use cid::Cid;
 use derive_more::{Deref, DerefMut};
-use fvm_ipld_amt::Amt;
 use fvm_ipld_encoding::{to_vec, CBOR};
 use fvm_shared::address::{Address, Payload};
 use fvm_shared::econ::TokenAmount;
@@ -16,15 +17,18 @@ use fvm_shared::{ActorID, METHOD_SEND};
 use num_traits::Zero;
 
 use super::state_access_tracker::{ActorAccessState, StateAccessTracker};
-use super::{Backtrace, CallManager, Entrypoint, InvocationResult, NO_DATA_BLOCK_ID};
-use crate::blockstore::DiscardBlockstore;
+use super::{
+    Backtrace, CallManager, DeferredSend, Entrypoint, InterceptedCall, InvocationResult,
+    ReentrancyPolicy, MAX_DEFERRED_SENDS, NO_DATA_BLOCK_ID,
+};
 use crate::call_manager::backtrace::Frame;
 use crate::call_manager::FinishRet;
 use crate::eam_actor::EAM_ACTOR_ID;
 use crate::engine::Engine;
-use crate::gas::{Gas, GasTracker};
+use crate::gas::{ActorGasUsage, Gas, GasTracker};
 use crate::kernel::{
-    Block, BlockRegistry, ClassifyResult, ExecutionError, Kernel, Result, SyscallError,
+    Block, BlockRegistry, ClassifyResult, ErrorContext, ExecutionError, Kernel, Result,
+    SyscallError,
 };
 use crate::machine::limiter::MemoryLimiter;
 use crate::machine::Machine;
@@ -77,6 +81,21 @@ pub struct InnerDefaultCallManager<M: Machine> {
     events: EventsAccumulator,
     /// The actor call stack (ActorID and entrypoint name tuple).
     actor_call_stack: Vec<(ActorID, &'static str)>,
+    /// The Wasm instructions remaining in this message's instruction budget, if any.
+    instructions_available: Option<Rc<Cell<u64>>>,
+    /// When this message's execution timeout expires, if any.
+    deadline: Option<Instant>,
+    /// The gas used by the completed sub-calls of each call on the stack, used to attribute gas
+    /// to individual calls in the execution trace. Only maintained when tracing.
+    child_gas_used: Vec<Gas>,
+    /// The gas used by each actor and method, excluding nested calls. Only maintained when
+    /// tracing.
+    actor_gas: ActorGasUsage,
+    /// Sends to be executed once the call stack has unwound.
+    deferred_sends: Vec<DeferredSend>,
+    /// The number of deferred sends and the gas refund credit at the start of each open
+    /// transaction.
+    transactions: Vec<(usize, Gas)>,
 }
 
 #[doc(hidden)]
@@ -113,8 +132,14 @@ where
         gas_premium: TokenAmount,
     ) -> Self {
         let limits = machine.new_limiter();
-        let gas_tracker =
+        let mut gas_tracker =
             GasTracker::new(Gas::new(gas_limit), Gas::zero(), machine.context().tracing);
+        if machine.context().gas_breakdown {
+            gas_tracker.enable_breakdown();
+        }
+        if let Some(listener) = &machine.context().gas_listener {
+            gas_tracker.set_listener(listener.clone());
+        }
 
         let state_access_tracker =
             StateAccessTracker::new(&machine.context().price_list.preloaded_actors);
@@ -145,6 +170,15 @@ where
         // cannot be triggered by an actor on-chain, so it's not a concern (for now).
         state_access_tracker.record_lookup_address(&receiver_address);
 
+        let instructions_available = machine
+            .context()
+            .instruction_budget
+            .map(|budget| Rc::new(Cell::new(budget)));
+        let deadline = engine
+            .config()
+            .execution_timeout
+            .map(|timeout| Instant::now() + timeout);
+
         DefaultCallManager(Some(Box::new(InnerDefaultCallManager {
             engine: Rc::new(engine),
             machine,
@@ -162,6 +196,12 @@ where
             events: Default::default(),
             state_access_tracker,
             actor_call_stack: vec![],
+            instructions_available,
+            deadline,
+            child_gas_used: vec![],
+            actor_gas: Default::default(),
+            deferred_sends: vec![],
+            transactions: vec![],
         })))
     }
 
@@ -182,61 +222,33 @@ where
     where
         K: Kernel<CallManager = Self>,
     {
-        if self.machine.context().tracing {
-            self.trace(ExecutionEvent::Call {
-                from,
-                to,
-                entrypoint,
-                params: params.as_ref().map(Into::into),
-                value: value.clone(),
-                gas_limit: std::cmp::min(
-                    gas_limit.unwrap_or(Gas::from_milligas(u64::MAX)).round_up(),
-                    self.gas_tracker.gas_available().round_up(),
-                ),
-                read_only,
-            });
-        }
-
-        // If a specific gas limit has been requested, push a new limit into the gas tracker.
-        if let Some(limit) = gas_limit {
-            self.gas_tracker.push_limit(limit);
-        }
-
-        let mut result = self.with_stack_frame(|s| {
-            s.call_actor_unchecked::<K>(from, to, entrypoint, params, value, read_only)
-        });
-
-        // If we pushed a limit, pop it.
-        if gas_limit.is_some() {
-            self.gas_tracker.pop_limit()?;
-        }
-
-        // If we're not out of gas but the error is "out of gas" (e.g., due to a gas limit), replace
-        // the error with an explicit exit code.
-        if !self.gas_tracker.gas_available().is_zero()
-            && matches!(result, Err(ExecutionError::OutOfGas))
-        {
-            result = Ok(InvocationResult {
-                exit_code: ExitCode::SYS_OUT_OF_GAS,
-                value: None,
-            })
-        }
+        let Some(interceptor) = self.machine.context().send_interceptor.clone() else {
+            return self.call_actor_traced::<K>(
+                from, to, entrypoint, params, value, gas_limit, read_only,
+            );
+        };
 
-        if self.machine.context().tracing {
-            self.trace(match &result {
-                Ok(InvocationResult { exit_code, value }) => {
-                    ExecutionEvent::CallReturn(*exit_code, value.as_ref().map(Into::into))
-                }
-                Err(ExecutionError::OutOfGas) => {
-                    ExecutionEvent::CallReturn(ExitCode::SYS_OUT_OF_GAS, None)
-                }
-                Err(ExecutionError::Fatal(_)) => {
-                    ExecutionEvent::CallError(SyscallError::new(ErrorNumber::Forbidden, "fatal"))
-                }
-                Err(ExecutionError::Syscall(s)) => ExecutionEvent::CallError(s.clone()),
-            });
+        let mut call = InterceptedCall {
+            to,
+            entrypoint,
+            value: value.clone(),
+            gas_limit,
+            read_only,
+        };
+        if let Some(res) = interceptor.before_send(from, &mut call)? {
+            return Ok(res);
         }
 
+        let result = self.call_actor_traced::<K>(
+            from,
+            call.to,
+            call.entrypoint,
+            params,
+            &call.value,
+            call.gas_limit,
+            call.read_only,
+        );
+        interceptor.after_send(from, &call, &result);
         result
     }
 
@@ -244,20 +256,77 @@ where
         &mut self,
         f: impl FnOnce(&mut Self) -> Result<InvocationResult>,
     ) -> Result<InvocationResult> {
-        self.state_tree_mut().begin_transaction();
-        self.events.begin_transaction();
-        self.state_access_tracker.begin_transaction();
+        self.begin_transaction();
 
         let (revert, res) = match f(self) {
             Ok(v) => (!v.exit_code.is_success(), Ok(v)),
             Err(e) => (true, Err(e)),
         };
 
+        self.end_transaction(revert)?;
+
+        res
+    }
+
+    fn begin_transaction(&mut self) {
+        self.state_tree_mut().begin_transaction();
+        self.events.begin_transaction();
+        self.state_access_tracker.begin_transaction();
+        let deferred_sends = self.deferred_sends.len();
+        let refund_credit = self.gas_tracker.refund_credit();
+        self.transactions.push((deferred_sends, refund_credit));
+    }
+
+    fn end_transaction(&mut self, revert: bool) -> Result<()> {
+        let (deferred_sends, refund_credit) = self
+            .transactions
+            .pop()
+            .context("call manager not in a transaction")
+            .or_fatal()?;
         self.state_tree_mut().end_transaction(revert)?;
         self.events.end_transaction(revert)?;
         self.state_access_tracker.end_transaction(revert)?;
+        if revert {
+            self.deferred_sends.truncate(deferred_sends);
+            self.gas_tracker.restore_refund_credit(refund_credit);
+        }
+        Ok(())
+    }
 
-        res
+    fn defer_send(&mut self, send: DeferredSend) -> Result<()> {
+        if self.deferred_sends.len() >= MAX_DEFERRED_SENDS {
+            return Err(syscall_error!(LimitExceeded; "too many deferred sends").into());
+        }
+        self.deferred_sends.push(send);
+        Ok(())
+    }
+
+    fn run_deferred_sends<K>(&mut self) -> Result<()>
+    where
+        K: Kernel<CallManager = Self>,
+    {
+        // Sends deferred by deferred sends are queued up for the next round.
+        while !self.deferred_sends.is_empty() {
+            for send in std::mem::take(&mut self.deferred_sends) {
+                let res = self.with_transaction(|cm| {
+                    cm.call_actor::<K>(
+                        send.from,
+                        send.to,
+                        Entrypoint::Invoke(send.method),
+                        send.params,
+                        &send.value,
+                        Some(send.gas_limit),
+                        false,
+                    )
+                });
+                match res {
+                    // Failures are isolated to the deferred send.
+                    Ok(_) | Err(ExecutionError::Syscall(_)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(())
     }
 
     fn finish(mut self) -> (Result<FinishRet>, Self::Machine) {
@@ -267,14 +336,29 @@ where
             gas_tracker,
             mut exec_trace,
             events,
+            actor_gas,
             ..
         } = *self.0.take().expect("call manager is poisoned");
 
+        // Apply any refunds, up to the cap.
         let gas_used = gas_tracker.gas_used().round_up();
+        let refund_credit = gas_tracker
+            .refund_credit()
+            .min(
+                machine
+                    .context()
+                    .price_list
+                    .max_refund(gas_tracker.gas_used()),
+            )
+            .round_down()
+            .min(gas_used);
+        let gas_used = gas_used - refund_credit;
+        let gas_breakdown = gas_tracker.gas_breakdown();
+        let actor_gas = machine.context().tracing.then_some(actor_gas);
 
         // Finalize any trace events, if we're tracing.
         if machine.context().tracing {
-            exec_trace.extend(gas_tracker.drain_trace().map(ExecutionEvent::GasCharge));
+            exec_trace.extend(gas_tracker.drain_trace());
         }
 
         let res = events.finish();
@@ -289,6 +373,9 @@ where
         (
             Ok(FinishRet {
                 gas_used,
+                refund_credit,
+                gas_breakdown,
+                actor_gas,
                 backtrace,
                 exec_trace,
                 events,
@@ -357,6 +444,15 @@ where
         actor_id: ActorID,
         delegated_address: Option<Address>,
     ) -> Result<()> {
+        if self.num_actors_created >= self.context().max_actors_created {
+            return Err(syscall_error!(
+                LimitExceeded;
+                "message may not create more than {} actors",
+                self.context().max_actors_created
+            )
+            .into());
+        }
+
         if self.machine.builtin_actors().is_placeholder_actor(&code_id) {
             return Err(syscall_error!(
                 Forbidden,
@@ -426,6 +522,22 @@ where
             self.gas_tracker
                 .apply_charge(self.price_list().on_resolve_address())?;
         }
+        if let Payload::Delegated(da) = address.payload() {
+            if let Some(resolver) = self
+                .machine
+                .context()
+                .namespace_resolvers
+                .get(&da.namespace())
+            {
+                // Like the init actor's address map, resolvers may only resolve to existing actors.
+                if let Some(id) = resolver.resolve(da.subaddress())? {
+                    if self.state_tree().get_actor(id)?.is_some() {
+                        self.state_access_tracker.record_lookup_address(address);
+                        return Ok(Some(id));
+                    }
+                }
+            }
+        }
         let id = self.state_tree().lookup_id(address)?;
         if id.is_some() {
             self.state_access_tracker.record_lookup_address(address);
@@ -517,13 +629,135 @@ impl<M> DefaultCallManager<M>
 where
     M: Machine,
 {
+    /// Calls an actor, recording the call and its return in the execution trace (if enabled).
+    #[allow(clippy::too_many_arguments)]
+    fn call_actor_traced<K>(
+        &mut self,
+        from: ActorID,
+        to: Address,
+        entrypoint: Entrypoint,
+        params: Option<Block>,
+        value: &TokenAmount,
+        gas_limit: Option<Gas>,
+        read_only: bool,
+    ) -> Result<InvocationResult>
+    where
+        K: Kernel<CallManager = Self>,
+    {
+        if self.machine.context().tracing {
+            self.trace(ExecutionEvent::Call {
+                from,
+                to,
+                entrypoint,
+                params: params.as_ref().map(Into::into),
+                value: value.clone(),
+                gas_limit: std::cmp::min(
+                    gas_limit.unwrap_or(Gas::from_milligas(u64::MAX)).round_up(),
+                    self.gas_tracker.gas_available().round_up(),
+                ),
+                read_only,
+            });
+        }
+
+        // Record the gas used on entry so we can attribute gas to this call.
+        let gas_at_entry = self.gas_tracker.gas_used();
+        if self.machine.context().tracing {
+            self.child_gas_used.push(Gas::zero());
+        }
+
+        // Record the gas and instructions available before the call so we can report how much of
+        // each the call consumed.
+        let usage_start = self
+            .instructions_available
+            .as_ref()
+            .map(|avail| (self.gas_tracker.gas_used(), avail.get()));
+
+        // If a specific gas limit has been requested, push a new limit into the gas tracker.
+        if let Some(limit) = gas_limit {
+            self.gas_tracker.push_limit(limit);
+        }
+
+        let mut result = self.with_stack_frame(|s| {
+            s.call_actor_unchecked::<K>(from, to, entrypoint, params, value, read_only)
+        });
+
+        // If we pushed a limit, pop it.
+        if gas_limit.is_some() {
+            if let Err(e) = self.gas_tracker.pop_limit() {
+                // Keep the child gas stack balanced with the call stack.
+                if self.machine.context().tracing {
+                    self.child_gas_used.pop();
+                }
+                return Err(e);
+            }
+        }
+
+        // If we're not out of gas but the error is "out of gas" (e.g., due to a gas limit), replace
+        // the error with an explicit exit code.
+        if !self.gas_tracker.gas_available().is_zero()
+            && matches!(result, Err(ExecutionError::OutOfGas))
+        {
+            result = Ok(InvocationResult {
+                exit_code: ExitCode::SYS_OUT_OF_GAS,
+                value: None,
+            })
+        }
+
+        if self.machine.context().tracing {
+            let gas_at_exit = self.gas_tracker.gas_used();
+            let gas_used = gas_at_exit - gas_at_entry;
+            let children = self.child_gas_used.pop().unwrap_or_default();
+            if let Some(parent) = self.child_gas_used.last_mut() {
+                *parent += gas_used;
+            }
+            // Calls to actors that don't exist (and couldn't be created) aren't attributed.
+            if let Ok(Some(id)) = self.machine.state_tree().lookup_id(&to) {
+                *self
+                    .actor_gas
+                    .entry((id, entrypoint.method_num()))
+                    .or_default() += gas_used - children;
+            }
+            self.trace(ExecutionEvent::CallGas {
+                gas_at_entry,
+                gas_at_exit,
+                exclusive_gas_used: gas_used - children,
+            });
+
+            if let (Some((gas_start, instructions_start)), Some(avail)) =
+                (usage_start, &self.instructions_available)
+            {
+                let usage = ExecutionEvent::CallUsage {
+                    gas_used: self.gas_tracker.gas_used() - gas_start,
+                    instructions: instructions_start.saturating_sub(avail.get()),
+                };
+                self.trace(usage);
+            }
+            self.trace(match &result {
+                Ok(InvocationResult { exit_code, value }) => {
+                    ExecutionEvent::CallReturn(*exit_code, value.as_ref().map(Into::into))
+                }
+                Err(ExecutionError::OutOfGas) => {
+                    ExecutionEvent::CallReturn(ExitCode::SYS_OUT_OF_GAS, None)
+                }
+                Err(ExecutionError::Fatal(_)) => {
+                    ExecutionEvent::CallError(SyscallError::new(ErrorNumber::Forbidden, "fatal"))
+                }
+                Err(ExecutionError::Timeout) => {
+                    ExecutionEvent::CallError(SyscallError::new(ErrorNumber::Forbidden, "timeout"))
+                }
+                Err(ExecutionError::Syscall(s)) => ExecutionEvent::CallError(s.clone()),
+            });
+        }
+
+        result
+    }
+
     fn trace(&mut self, trace: ExecutionEvent) {
         // The price of deref magic is that you sometimes need to tell the compiler: no, this is
         // fine.
         let s = &mut **self;
 
-        s.exec_trace
-            .extend(s.gas_tracker.drain_trace().map(ExecutionEvent::GasCharge));
+        s.exec_trace.extend(s.gas_tracker.drain_trace());
 
         s.exec_trace.push(trace);
     }
@@ -635,6 +869,25 @@ where
             },
         };
 
+        // Apply the reentrancy policy if the receiver is already executing further up the stack.
+        if !entrypoint.invokes(METHOD_SEND) && self.actor_call_stack.iter().any(|&(id, _)| id == to)
+        {
+            match self.machine.context().reentrancy_policy {
+                ReentrancyPolicy::Allow => {}
+                ReentrancyPolicy::Trace => {
+                    if self.machine.context().tracing {
+                        self.trace(ExecutionEvent::Reentrancy(to));
+                    }
+                }
+                ReentrancyPolicy::Reject => {
+                    return Err(
+                        syscall_error!(Reentrant; "actor {} is already on the call stack", to)
+                            .into(),
+                    );
+                }
+            }
+        }
+
         self.actor_call_stack.push((to, entrypoint.func_name()));
         let res = self.call_actor_resolved::<K>(from, to, entrypoint, params, value, read_only);
         self.actor_call_stack.pop();
@@ -688,7 +941,7 @@ where
         )?;
 
         // Store the parametrs, and initialize the block registry for the target actor.
-        let mut block_registry = BlockRegistry::new();
+        let mut block_registry = BlockRegistry::new(self.machine.context().block_limits());
         let params_id = if let Some(blk) = params {
             block_registry.put_reachable(blk)?
         } else {
@@ -712,6 +965,14 @@ where
                 |_| syscall_error!(NotFound; "actor code cid does not exist {}", &state.code),
             )?;
 
+        let permitted_syscalls = self.machine.context().syscall_allowlist.permitted_syscalls(
+            &state.code,
+            self.machine.builtin_actors().id_by_code(&state.code) != 0,
+        );
+
+        let instructions_available = self.instructions_available.clone();
+        let deadline = self.deadline;
+
         log::trace!("calling {} -> {}::{}", from, to, entrypoint);
         self.map_mut(|cm| {
             let engine = cm.engine.clone(); // reference the RC.
@@ -729,6 +990,18 @@ where
 
             // Make a store.
             let mut store = engine.new_store(kernel);
+            store.data_mut().permitted_syscalls = permitted_syscalls;
+            store.data_mut().instructions_available = instructions_available;
+            if let Some(deadline) = deadline {
+                engine.set_timeout(
+                    &mut store,
+                    deadline.saturating_duration_since(Instant::now()),
+                );
+            }
+
+            // The size of the actor's memory (in pages) when the invocation ends, if it was
+            // instantiated.
+            let mut peak_memory_pages = None;
 
             // From this point on, there are no more syscall errors, only aborts.
             let result: std::result::Result<BlockId, Abort> = (|| {
@@ -767,8 +1040,10 @@ where
                 let mut out = [wasmtime::Val::I32(0)];
                 let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
                     func.call(&mut store, params.as_slice(), &mut out)
-                }))
-                .map_err(|panic| Abort::Fatal(anyhow!("panic within actor: {:?}", panic)))?;
+                }));
+                peak_memory_pages = Some(memory.size(&store));
+                let res =
+                    res.map_err(|panic| Abort::Fatal(anyhow!("panic within actor: {:?}", panic)))?;
 
                 // Charge for any remaining uncharged execution gas, returning an error if we run
                 // out.
@@ -788,6 +1063,12 @@ where
             let last_error = invocation_data.last_error;
             let (mut cm, block_registry) = invocation_data.kernel.into_inner();
 
+            if let Some(peak_pages) = peak_memory_pages {
+                if cm.machine.context().tracing {
+                    cm.trace(ExecutionEvent::InvokeMemory { peak_pages });
+                }
+            }
+
             // Resolve the return block's ID into an actual block, converting to an abort if it
             // doesn't exist.
             let result = result.and_then(|ret_id| {
@@ -840,10 +1121,19 @@ where
                             "out of gas".to_owned(),
                             Err(ExecutionError::OutOfGas),
                         ),
+                        Abort::Timeout => (
+                            ExitCode::SYS_ASSERTION_FAILED,
+                            "execution timed out".to_owned(),
+                            Err(ExecutionError::Timeout),
+                        ),
                         Abort::Fatal(err) => (
                             ExitCode::SYS_ASSERTION_FAILED,
                             "fatal error".to_owned(),
-                            Err(ExecutionError::Fatal(err)),
+                            Err(ExecutionError::Fatal(
+                                ErrorContext::default()
+                                    .with_actor(Address::new_id(to))
+                                    .attach(err),
+                            )),
                         ),
                     };
 
@@ -918,8 +1208,10 @@ where
     where
         F: FnOnce(&mut Self) -> Result<V>,
     {
-        if self.call_stack_depth >= self.machine.context().max_call_depth {
-            let sys_err = syscall_error!(LimitExceeded, "message execution exceeds call depth");
+        let max_call_depth = self.machine.context().max_call_depth;
+        if self.call_stack_depth >= max_call_depth {
+            let sys_err = syscall_error!(LimitExceeded;
+                "message execution exceeds the call depth limit of {}", max_call_depth);
             if self.machine.context().tracing {
                 self.trace(ExecutionEvent::CallError(sys_err.clone()));
             }
@@ -992,19 +1284,9 @@ impl EventsAccumulator {
             )));
         }
 
-        let root = if !self.events.is_empty() {
-            const EVENTS_AMT_BITWIDTH: u32 = 5;
-            let root = Amt::new_from_iter_with_bit_width(
-                DiscardBlockstore,
-                EVENTS_AMT_BITWIDTH,
-                self.events.iter(),
-            )
+        let root = fvm_shared::verify::events_root(&self.events)
             .context("failed to construct events AMT")
             .or_fatal()?;
-            Some(root)
-        } else {
-            None
-        };
 
         Ok(Events {
             root,
@@ -0,0 +1,60 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::ActorID;
+
+use super::{Entrypoint, InvocationResult};
+use crate::gas::Gas;
+use crate::kernel::Result;
+
+/// A call about to be made (or just made) by the [`CallManager`](super::CallManager), as seen by a
+/// [`SendInterceptor`].
+#[derive(Clone, Debug)]
+pub struct InterceptedCall {
+    /// The address of the call's recipient.
+    pub to: Address,
+    /// The entrypoint being invoked on the recipient.
+    pub entrypoint: Entrypoint,
+    /// The value being transferred along with the call.
+    pub value: TokenAmount,
+    /// The gas limit requested by the caller, if any.
+    pub gas_limit: Option<Gas>,
+    /// Whether the call is made in read-only mode.
+    pub read_only: bool,
+}
+
+/// A hook consulted by the [`DefaultCallManager`](super::DefaultCallManager) around each call to
+/// [`call_actor`](super::CallManager::call_actor), including the top-level call made on behalf of
+/// the message itself.
+///
+/// Interceptors are configured with
+/// [`NetworkConfig::set_send_interceptor`](crate::machine::NetworkConfig::set_send_interceptor)
+/// and can be used to implement permissioned networks or to instrument execution. Interceptors are
+/// not metered and, if they veto or rewrite calls, are consensus-critical: all nodes on a network
+/// must be configured with equivalent interceptors.
+pub trait SendInterceptor: Send + Sync + 'static {
+    /// Called before a call is made. The interceptor may rewrite the call by modifying `call`, or
+    /// veto it by returning `Ok(Some(result))`, in which case `result` is returned to the caller
+    /// without invoking the recipient. Returning an error aborts the call with that error.
+    fn before_send(
+        &self,
+        from: ActorID,
+        call: &mut InterceptedCall,
+    ) -> Result<Option<InvocationResult>> {
+        let _ = (from, call);
+        Ok(None)
+    }
+
+    /// Called after a call returns (but not after a call vetoed by
+    /// [`SendInterceptor::before_send`]), with the call as it was made and its result.
+    fn after_send(&self, from: ActorID, call: &InterceptedCall, result: &Result<InvocationResult>) {
+        let _ = (from, call, result);
+    }
+}
+
+impl std::fmt::Debug for dyn SendInterceptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SendInterceptor")
+    }
+}
@@ -7,17 +7,22 @@ use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
 use fvm_shared::upgrade::UpgradeInfo;
 use fvm_shared::{ActorID, MethodNum};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::engine::Engine;
-use crate::gas::{Gas, GasCharge, GasTimer, GasTracker, PriceList};
+use crate::gas::{ActorGasUsage, Gas, GasBreakdown, GasCharge, GasTimer, GasTracker, PriceList};
 use crate::kernel::{self, BlockRegistry, ClassifyResult, Context, Result};
 use crate::machine::{Machine, MachineContext};
 use crate::state_tree::ActorState;
 use crate::Kernel;
 
 pub mod backtrace;
+mod interceptor;
+mod resolver;
 mod state_access_tracker;
 pub use backtrace::Backtrace;
+pub use interceptor::{InterceptedCall, SendInterceptor};
+pub use resolver::{EthMaskedIdResolver, NamespaceResolver};
 
 mod default;
 
@@ -26,9 +31,47 @@ use fvm_shared::event::StampedEvent;
 
 use crate::trace::ExecutionTrace;
 
+/// How the [`DefaultCallManager`] treats calls into actors that are already on the call stack
+/// (reentrant calls). Plain value transfers ([`METHOD_SEND`](fvm_shared::METHOD_SEND)) never
+/// execute actor code and are always allowed.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ReentrancyPolicy {
+    /// Allow reentrant calls.
+    #[default]
+    Allow,
+    /// Allow reentrant calls, but record them in the execution trace (if tracing is enabled) as
+    /// [`ExecutionEvent::Reentrancy`](crate::trace::ExecutionEvent::Reentrancy).
+    Trace,
+    /// Reject reentrant calls with
+    /// [`ErrorNumber::Reentrant`](fvm_shared::error::ErrorNumber::Reentrant) (or
+    /// [`ErrorNumber::Forbidden`](fvm_shared::error::ErrorNumber::Forbidden) before NV22).
+    Reject,
+}
+
 /// BlockID representing nil parameters or return data.
 pub const NO_DATA_BLOCK_ID: u32 = 0;
 
+/// The maximum number of sends that may be deferred at any given time.
+pub const MAX_DEFERRED_SENDS: usize = 1024;
+
+/// A send enqueued with [`CallManager::defer_send`], to be executed once the message's call stack
+/// has unwound.
+#[derive(Clone, Debug)]
+pub struct DeferredSend {
+    /// The actor that deferred the send.
+    pub from: ActorID,
+    /// The recipient.
+    pub to: Address,
+    /// The method to invoke.
+    pub method: MethodNum,
+    /// The method parameters.
+    pub params: Option<kernel::Block>,
+    /// The value to transfer.
+    pub value: TokenAmount,
+    /// The gas the send may use.
+    pub gas_limit: Gas,
+}
+
 /// The `CallManager` manages a single call stack.
 ///
 /// When a top-level message is executed:
@@ -82,6 +125,30 @@ pub trait CallManager: 'static {
         f: impl FnOnce(&mut Self) -> Result<InvocationResult>,
     ) -> Result<InvocationResult>;
 
+    /// Begins a new transaction over the call manager's state: the state-tree, emitted events,
+    /// state-access charges, deferred sends, and gas refund credits. Transactions nest, and each
+    /// must be ended with [`CallManager::end_transaction`] before the enclosing transaction (or the
+    /// call) ends.
+    ///
+    /// Prefer [`CallManager::with_transaction`], which always ends the transaction it begins.
+    fn begin_transaction(&mut self);
+
+    /// Ends the innermost transaction, committing its changes to the enclosing transaction or,
+    /// if `revert` is true, discarding them. Returns a fatal error if there is no open
+    /// transaction.
+    fn end_transaction(&mut self, revert: bool) -> Result<()>;
+
+    /// Enqueues a send to be executed by [`CallManager::run_deferred_sends`] once the message's
+    /// call stack has unwound. Sends deferred within a transaction are discarded if the
+    /// transaction is reverted.
+    fn defer_send(&mut self, send: DeferredSend) -> Result<()>;
+
+    /// Executes the deferred sends in the order in which they were enqueued, including any sends
+    /// deferred while doing so. Each send executes in its own transaction with its own gas limit;
+    /// its failure is reverted and otherwise ignored. Only fatal errors and running out of the
+    /// message's gas are returned.
+    fn run_deferred_sends<K: Kernel<CallManager = Self>>(&mut self) -> Result<()>;
+
     /// Finishes execution, returning the gas used, machine, and exec trace if requested.
     fn finish(self) -> (Result<FinishRet>, Self::Machine);
 
@@ -122,7 +189,9 @@ pub trait CallManager: 'static {
     // returns the actor call stack
     fn get_call_stack(&self) -> &[(ActorID, &'static str)];
 
-    /// Resolve an address into an actor ID, charging gas as appropriate.
+    /// Resolve an address into an actor ID, charging gas as appropriate. Delegated addresses are
+    /// resolved by the [`NamespaceResolver`] registered for their namespace (if any) before
+    /// falling back to the init actor's address map.
     fn resolve_address(&self, address: &Address) -> Result<Option<ActorID>>;
 
     /// Sets an actor in the state-tree, charging gas as appropriate. Use `create_actor` if you want
@@ -132,6 +201,21 @@ pub trait CallManager: 'static {
     /// Looks up an actor in the state-tree, charging gas as appropriate.
     fn get_actor(&self, id: ActorID) -> Result<Option<ActorState>>;
 
+    /// Looks up, mutates, and sets an actor in the state-tree (see [`CallManager::get_actor`] and
+    /// [`CallManager::set_actor`]), returning the result of the closure, or None if the actor
+    /// doesn't exist. If the closure fails, the actor isn't changed.
+    fn mutate_actor<F, R>(&mut self, id: ActorID, mutate: F) -> Result<Option<R>>
+    where
+        F: FnOnce(&mut ActorState) -> Result<R>,
+    {
+        let Some(mut actor) = self.get_actor(id)? else {
+            return Ok(None);
+        };
+        let ret = mutate(&mut actor)?;
+        self.set_actor(id, actor)?;
+        Ok(Some(ret))
+    }
+
     /// Deletes an actor from the state-tree, charging gas as appropriate.
     fn delete_actor(&mut self, id: ActorID) -> Result<()>;
 
@@ -196,7 +280,13 @@ impl Default for InvocationResult {
 
 /// The returned values upon finishing a call manager.
 pub struct FinishRet {
+    /// The gas used, net of `refund_credit`.
     pub gas_used: u64,
+    /// The gas refunded to the message (e.g., for deleting actors), already capped.
+    pub refund_credit: u64,
+    pub gas_breakdown: GasBreakdown,
+    /// The gas used by each actor and method, if tracing.
+    pub actor_gas: Option<ActorGasUsage>,
     pub backtrace: Backtrace,
     pub exec_trace: ExecutionTrace,
     pub events: Vec<StampedEvent>,
@@ -214,6 +304,35 @@ pub static UPGRADE_FUNC_NAME: &str = "upgrade";
 
 const METHOD_UPGRADE: MethodNum = 932083;
 
+/// Entrypoints are serialized as `[method, upgrade_info]`, where `upgrade_info` is null unless the
+/// entrypoint is [`Entrypoint::Upgrade`]. Upgrade info with any method other than the upgrade
+/// method is rejected, so decoding and re-encoding an entrypoint is exact.
+impl Serialize for Entrypoint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let upgrade_info = match self {
+            Entrypoint::Invoke(_) => None,
+            Entrypoint::Upgrade(ui) => Some(ui),
+        };
+        (self.method_num(), upgrade_info).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Entrypoint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let (method, upgrade_info): (MethodNum, Option<UpgradeInfo>) =
+            Deserialize::deserialize(deserializer)?;
+        Ok(match upgrade_info {
+            Some(ui) if method == METHOD_UPGRADE => Entrypoint::Upgrade(ui),
+            Some(_) => {
+                return Err(serde::de::Error::custom(format!(
+                    "upgrade info given for method {method}"
+                )))
+            }
+            None => Entrypoint::Invoke(method),
+        })
+    }
+}
+
 impl std::fmt::Display for Entrypoint {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -224,7 +343,8 @@ impl std::fmt::Display for Entrypoint {
 }
 
 impl Entrypoint {
-    fn method_num(&self) -> MethodNum {
+    /// Returns the method number invoked, or the reserved upgrade method number for upgrades.
+    pub fn method_num(&self) -> MethodNum {
         match self {
             Entrypoint::Invoke(num) => *num,
             Entrypoint::Upgrade(_) => METHOD_UPGRADE,
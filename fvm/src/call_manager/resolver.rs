@@ -0,0 +1,59 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use fvm_shared::ActorID;
+
+use crate::kernel::Result;
+
+/// Resolves delegated (f4) addresses within a single address namespace natively, without
+/// consulting the on-chain address map.
+///
+/// Resolvers are registered per namespace (the ID of the namespace's address manager actor) with
+/// [`NetworkConfig::register_namespace_resolver`](crate::machine::NetworkConfig::register_namespace_resolver)
+/// and consulted by [`CallManager::resolve_address`](super::CallManager::resolve_address) before
+/// falling back to the init actor's address map. Resolvers are consensus-critical: all nodes on a
+/// network must be configured with equivalent resolvers.
+pub trait NamespaceResolver: Send + Sync + 'static {
+    /// Resolve the given sub-address to an actor ID. Returning `Ok(None)`, or the ID of an actor
+    /// that doesn't exist, falls back to the on-chain address map.
+    fn resolve(&self, subaddress: &[u8]) -> Result<Option<ActorID>>;
+}
+
+impl std::fmt::Debug for dyn NamespaceResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("NamespaceResolver")
+    }
+}
+
+/// Natively resolves the "masked" ID addresses of the Ethereum address manager (an `0xff` byte,
+/// followed by 11 zero bytes and the big-endian actor ID) to the actor IDs they embed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EthMaskedIdResolver;
+
+impl NamespaceResolver for EthMaskedIdResolver {
+    fn resolve(&self, subaddress: &[u8]) -> Result<Option<ActorID>> {
+        const PREFIX: [u8; 12] = [0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        Ok(subaddress
+            .strip_prefix(&PREFIX)
+            .and_then(|id| id.try_into().ok())
+            .map(ActorID::from_be_bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eth_masked_id() {
+        let mut addr = [0u8; 20];
+        addr[0] = 0xff;
+        addr[12..].copy_from_slice(&1234u64.to_be_bytes());
+        assert_eq!(EthMaskedIdResolver.resolve(&addr).unwrap(), Some(1234));
+
+        // Not a masked ID address.
+        addr[1] = 1;
+        assert_eq!(EthMaskedIdResolver.resolve(&addr).unwrap(), None);
+        assert_eq!(EthMaskedIdResolver.resolve(&[0xff; 20]).unwrap(), None);
+        assert_eq!(EthMaskedIdResolver.resolve(&addr[..19]).unwrap(), None);
+    }
+}
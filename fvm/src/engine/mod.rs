@@ -3,18 +3,25 @@
 
 mod concurrency;
 mod instance_pool;
+mod module_cache;
 
 use std::any::{Any, TypeId};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::HashMap;
+use std::fs;
 use std::ops::Deref;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::{anyhow, Context};
 use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
 use fvm_shared::error::ExitCode;
+use fvm_shared::version::NetworkVersion;
 use fvm_wasm_instrument::gas_metering::GAS_COUNTER_NAME;
+use minstant::Instant;
 use num_traits::Zero;
 use wasmtime::OptLevel::Speed;
 use wasmtime::{
@@ -24,7 +31,7 @@ use wasmtime::{
 
 use crate::gas::{Gas, GasTimer, WasmGasPrices};
 use crate::machine::limiter::MemoryLimiter;
-use crate::machine::{Machine, NetworkConfig};
+use crate::machine::{Machine, Manifest, MetricEvent, NetworkConfig};
 use crate::syscalls::error::Abort;
 use crate::syscalls::{
     charge_for_exec, charge_for_init, record_init_time, update_gas_available, InvocationData,
@@ -33,15 +40,24 @@ use crate::Kernel;
 
 use self::concurrency::EngineConcurrency;
 use self::instance_pool::InstancePool;
+pub use self::module_cache::ModuleCache;
 
 /// The expected max stack depth used to determine the number of instances needed for a given
 /// concurrency level.
 const EXPECTED_MAX_STACK_DEPTH: u32 = 20;
 
+/// The interval at which the epochs of engines enforcing an execution timeout are incremented,
+/// i.e., the precision of the timeout.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
 /// Container managing engines with different consensus-affecting configurations.
 pub struct MultiEngine {
     engines: Mutex<HashMap<EngineConfig, EnginePool>>,
     concurrency: u32,
+    module_cache: Option<ModuleCache>,
+    pooling: PoolingConfig,
+    execution_timeout: Option<Duration>,
+    gas_metering: GasMetering,
 }
 
 /// The proper way of getting this struct is to convert from `NetworkConfig`
@@ -53,10 +69,105 @@ pub struct EngineConfig {
     pub concurrency: u32,
     pub wasm_prices: &'static WasmGasPrices,
     pub actor_redirect: Vec<(Cid, Cid)>,
+    pub consume_fuel: bool,
+    pub wasm_features: WasmFeatures,
+    pub pooling: PoolingConfig,
+    /// Maximum wall-clock time each message may spend executing actor code, enforced with
+    /// wasmtime's epoch interruption. Messages exceeding it fail with
+    /// [`ApplyFailure::Timeout`](crate::executor::ApplyFailure::Timeout). Time spent in syscalls
+    /// is only checked once control returns to actor code. Like [`PoolingConfig`], this is
+    /// node-local but, as it isn't deterministic, it's only intended for debugging and gas
+    /// estimation, never for applying messages on chain.
+    pub execution_timeout: Option<Duration>,
+    pub gas_metering: GasMetering,
+}
+
+/// How the gas used executing actor code is metered.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum GasMetering {
+    /// Instrument the actor code to charge the price list's per-instruction costs.
+    #[default]
+    Instrumentation,
+    /// Use wasmtime's built-in fuel, converted to gas at the price list's
+    /// [`fuel_price`](WasmGasPrices::fuel_price). Modules aren't instrumented for gas, so they're
+    /// smaller and faster to compile.
+    ///
+    /// This is intended for benchmarking, and for cross-checking the instrumentation: the gas
+    /// charged only matches when the price list can be expressed in fuel, and doesn't include
+    /// the per-byte costs of bulk memory instructions. It must not be used on chain.
+    Fuel,
+}
+
+/// The Wasm proposals actor code may use, beyond the MVP. Modules using other proposals are
+/// rejected when loaded (e.g., when installed), rather than trapping when executed. All nodes of
+/// a network must agree on these.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct WasmFeatures {
+    /// 128-bit SIMD instructions. Not supported yet.
+    pub simd: bool,
+    /// Bulk memory operations (e.g., `memory.copy` and `memory.fill`).
+    pub bulk_memory: bool,
+    /// Functions and blocks returning multiple values. Not supported yet.
+    pub multi_value: bool,
+}
+
+impl Default for WasmFeatures {
+    /// The proposals enabled on all supported network versions: only bulk memory operations.
+    fn default() -> Self {
+        WasmFeatures {
+            simd: false,
+            bulk_memory: true,
+            multi_value: false,
+        }
+    }
+}
+
+impl WasmFeatures {
+    /// Returns an error if any of the proposals isn't supported by the FVM yet: the gas
+    /// instrumentation can't meter SIMD instructions, nor blocks returning multiple values.
+    pub fn check_supported(&self) -> anyhow::Result<()> {
+        if self.simd {
+            return Err(anyhow!("the simd wasm proposal is not supported"));
+        }
+        if self.multi_value {
+            return Err(anyhow!("the multi-value wasm proposal is not supported"));
+        }
+        Ok(())
+    }
+}
+
+/// Settings of wasmtime's pooling instance allocator, which reserves the memory and tables of a
+/// fixed number of instances up-front so that instantiating a module doesn't need to map memory.
+/// These settings are node-local: they don't affect consensus.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct PoolingConfig {
+    /// The number of instances to reserve, at least `max_call_depth`. Defaults to a full call
+    /// stack, plus some instances per concurrent execution.
+    pub max_instances: Option<u32>,
+    /// The maximum number of elements in an instance's table. Defaults to wasmtime's default.
+    pub max_table_elements: Option<u32>,
+    /// The maximum number of unused instance slots kept warm (with their memory still mapped)
+    /// for reuse. Defaults to wasmtime's default.
+    pub max_unused_warm_slots: Option<u32>,
 }
 
 impl EngineConfig {
+    /// Returns this configuration for the given network, replacing the fields that depend on it
+    /// and keeping the node-local ones (e.g., the concurrency, pooling, and execution timeout).
+    pub fn for_network(&self, nc: &NetworkConfig) -> Self {
+        EngineConfig {
+            concurrency: self.concurrency,
+            pooling: self.pooling.clone(),
+            execution_timeout: self.execution_timeout,
+            gas_metering: self.gas_metering,
+            ..nc.into()
+        }
+    }
+
     fn instance_pool_size(&self) -> u32 {
+        if let Some(max_instances) = self.pooling.max_instances {
+            return max_instances;
+        }
         std::cmp::min(
             // Allocate at least one full call depth worth of stack, plus some per concurrent call
             // we allow.
@@ -77,6 +188,11 @@ impl From<&NetworkConfig> for EngineConfig {
             wasm_prices: &nc.price_list.wasm_rules,
             actor_redirect: nc.actor_redirect.clone(),
             concurrency: 1,
+            consume_fuel: nc.instruction_budget.is_some(),
+            wasm_features: nc.wasm_features,
+            pooling: PoolingConfig::default(),
+            execution_timeout: None,
+            gas_metering: GasMetering::default(),
         }
     }
 }
@@ -89,9 +205,39 @@ impl MultiEngine {
         MultiEngine {
             engines: Mutex::new(HashMap::new()),
             concurrency,
+            module_cache: None,
+            pooling: PoolingConfig::default(),
+            execution_timeout: None,
+            gas_metering: GasMetering::default(),
         }
     }
 
+    /// Meters the gas used by actor code with the given [`GasMetering`] backend.
+    pub fn with_gas_metering(mut self, gas_metering: GasMetering) -> Self {
+        self.gas_metering = gas_metering;
+        self
+    }
+
+    /// Configures the pooling instance allocator of the engines.
+    pub fn with_pooling_config(mut self, pooling: PoolingConfig) -> Self {
+        self.pooling = pooling;
+        self
+    }
+
+    /// Interrupts messages executing actor code for longer than the given wall-clock time, see
+    /// [`EngineConfig::execution_timeout`].
+    pub fn with_execution_timeout(mut self, timeout: Duration) -> Self {
+        self.execution_timeout = Some(timeout);
+        self
+    }
+
+    /// Persists the compiled modules of the engines in the given cache, and loads them from it
+    /// instead of compiling them when possible.
+    pub fn with_module_cache(mut self, cache: ModuleCache) -> Self {
+        self.module_cache = Some(cache);
+        self
+    }
+
     pub fn get(&self, nc: &NetworkConfig) -> anyhow::Result<EnginePool> {
         let mut engines = self
             .engines
@@ -100,10 +246,17 @@ impl MultiEngine {
 
         let mut ec: EngineConfig = nc.into();
         ec.concurrency = self.concurrency;
+        ec.pooling = self.pooling.clone();
+        ec.execution_timeout = self.execution_timeout;
+        ec.gas_metering = self.gas_metering;
 
         let pool = match engines.entry(ec.clone()) {
             Occupied(entry) => entry.into_mut(),
-            Vacant(entry) => entry.insert(EnginePool::new_default(ec)?),
+            Vacant(entry) => entry.insert(EnginePool::new_with_module_cache(
+                &wasmtime_config(&ec)?,
+                ec,
+                self.module_cache.clone(),
+            )?),
         };
 
         Ok(pool.clone())
@@ -122,6 +275,13 @@ fn wasmtime_config(ec: &EngineConfig) -> anyhow::Result<wasmtime::Config> {
     }
 
     let instance_count = ec.instance_pool_size();
+    if instance_count < ec.max_call_depth {
+        return Err(anyhow!(
+            "instance pool size {} is smaller than the max call depth {}",
+            instance_count,
+            ec.max_call_depth
+        ));
+    }
     let instance_memory_maximum_size = ec.max_inst_memory_bytes;
     if instance_memory_maximum_size % wasmtime_environ::WASM_PAGE_SIZE as u64 != 0 {
         return Err(anyhow!(
@@ -138,6 +298,12 @@ fn wasmtime_config(ec: &EngineConfig) -> anyhow::Result<wasmtime::Config> {
 
     let mut alloc_strat_cfg = wasmtime::PoolingAllocationConfig::default();
     alloc_strat_cfg.instance_count(instance_count);
+    if let Some(max_table_elements) = ec.pooling.max_table_elements {
+        alloc_strat_cfg.instance_table_elements(max_table_elements);
+    }
+    if let Some(max_unused_warm_slots) = ec.pooling.max_unused_warm_slots {
+        alloc_strat_cfg.max_unused_warm_slots(max_unused_warm_slots);
+    }
 
     // Adjust the maximum amount of host memory that can be committed to an instance to
     // match the static linear memory size we reserve for each slot.
@@ -160,7 +326,7 @@ fn wasmtime_config(ec: &EngineConfig) -> anyhow::Result<wasmtime::Config> {
     // wasmtime default: true
     // simd isn't supported in wasm-instrument, but if we add support there, we can probably enable this.
     // Note: stack limits may need adjusting after this is enabled
-    c.wasm_simd(false);
+    c.wasm_simd(ec.wasm_features.simd);
 
     // wasmtime default: false
     c.wasm_multi_memory(false);
@@ -171,12 +337,12 @@ fn wasmtime_config(ec: &EngineConfig) -> anyhow::Result<wasmtime::Config> {
     // wasmtime default: true
     // Note: wasm-instrument only supports this at a basic level, for M2 we will
     // need to add more advanced support
-    c.wasm_bulk_memory(true);
+    c.wasm_bulk_memory(ec.wasm_features.bulk_memory);
 
     // wasmtime default: true
     // we should be able to enable this for M2, just need to make sure that it's
     // handled correctly in wasm-instrument
-    c.wasm_multi_value(false);
+    c.wasm_multi_value(ec.wasm_features.multi_value);
 
     // wasmtime default: false
     //
@@ -193,9 +359,11 @@ fn wasmtime_config(ec: &EngineConfig) -> anyhow::Result<wasmtime::Config> {
     // Note: This is in bytes, while the instrumented limit is in stack elements
     c.max_wasm_stack(4 << 20);
 
-    // Execution cost accouting is done through wasm instrumentation,
-    c.consume_fuel(false);
-    c.epoch_interruption(false);
+    // Execution cost accouting is done through wasm instrumentation by default, fuel is only used
+    // to enforce the (optional) instruction budget.
+    c.consume_fuel(ec.consume_fuel || ec.gas_metering == GasMetering::Fuel);
+    // Likewise, epochs are only used to enforce the (optional) execution timeout.
+    c.epoch_interruption(ec.execution_timeout.is_some());
 
     // Disable debug-related things, wasm-instrument doesn't fix debug info
     // yet, so those aren't useful, just add overhead
@@ -227,6 +395,22 @@ struct ModuleRecord {
     size: usize,
 }
 
+/// Compilation and instantiation metrics for a single actor code CID. These are node-local
+/// measurements intended for monitoring, and are not consensus-critical.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModuleStats {
+    /// Byte size of the Wasm code, as loaded from the blockstore (or as recorded in the
+    /// precompiled module).
+    pub code_size: usize,
+    /// Time spent validating, instrumenting and compiling the code (or deserializing the
+    /// precompiled module).
+    pub compile_time: Duration,
+    /// The number of times the module has been instantiated.
+    pub instantiations: u64,
+    /// The total time spent instantiating the module.
+    pub instantiation_time: Duration,
+}
+
 struct EngineInner {
     concurrency_limit: EngineConcurrency,
     instance_limit: InstancePool,
@@ -242,8 +426,16 @@ struct EngineInner {
     dummy_memory: Memory,
 
     module_cache: Mutex<HashMap<Cid, ModuleRecord>>,
-    instance_cache: Mutex<HashMap<TypeId, Box<dyn Any + Send>>>,
+    /// The on-disk module cache, if any.
+    disk_cache: Option<ModuleCache>,
+    /// The hash of this engine's configuration, identifying compatible compiled modules.
+    config_hash: u64,
+    module_stats: Mutex<HashMap<Cid, ModuleStats>>,
+    /// Linkers by kernel type and network version, as the syscalls bound depend on both.
+    instance_cache: Mutex<HashMap<(TypeId, NetworkVersion), Box<dyn Any + Send>>>,
     config: EngineConfig,
+    /// Increments the engine's epoch, if enforcing an execution timeout.
+    _epoch_ticker: Option<EpochTicker>,
 
     actor_redirect: HashMap<Cid, Cid>,
 }
@@ -262,13 +454,40 @@ impl EnginePool {
         }
     }
 
+    /// Returns the configuration of the engines in this pool.
+    pub fn config(&self) -> &EngineConfig {
+        &self.0.config
+    }
+
+    /// Creates a new pool with the given configuration, sharing this pool's on-disk module cache,
+    /// if any.
+    pub fn with_config(&self, ec: EngineConfig) -> anyhow::Result<Self> {
+        EnginePool::new_with_module_cache(&wasmtime_config(&ec)?, ec, self.0.disk_cache.clone())
+    }
+
     pub fn new_default(ec: EngineConfig) -> anyhow::Result<Self> {
         EnginePool::new(&wasmtime_config(&ec)?, ec)
     }
 
     /// Create a new Engine from a wasmtime config.
     pub fn new(c: &wasmtime::Config, ec: EngineConfig) -> anyhow::Result<Self> {
+        EnginePool::new_with_module_cache(c, ec, None)
+    }
+
+    /// Create a new Engine from a wasmtime config, caching compiled modules in the given
+    /// on-disk cache.
+    pub fn new_with_module_cache(
+        c: &wasmtime::Config,
+        ec: EngineConfig,
+        cache: Option<ModuleCache>,
+    ) -> anyhow::Result<Self> {
+        if ec.gas_metering == GasMetering::Fuel && ec.wasm_prices.fuel_price().is_none() {
+            return Err(anyhow!("the wasm gas prices can't be expressed in fuel"));
+        }
+        ec.wasm_features.check_supported()?;
+
         let engine = wasmtime::Engine::new(c)?;
+        let config_hash = module_cache::config_hash(&engine, &ec);
 
         let mut dummy_store = wasmtime::Store::new(&engine, ());
         let gg_type = GlobalType::new(ValType::I64, Mutability::Var);
@@ -280,6 +499,12 @@ impl EnginePool {
 
         let actor_redirect = ec.actor_redirect.iter().cloned().collect();
 
+        let epoch_ticker = if ec.execution_timeout.is_some() {
+            Some(EpochTicker::start(engine.clone())?)
+        } else {
+            None
+        };
+
         Ok(EnginePool(Arc::new(EngineInner {
             concurrency_limit: EngineConcurrency::new(ec.concurrency),
             instance_limit: InstancePool::new(ec.instance_pool_size(), ec.max_call_depth),
@@ -287,13 +512,45 @@ impl EnginePool {
             dummy_memory,
             dummy_gas_global: dummy_gg,
             module_cache: Default::default(),
+            disk_cache: cache,
+            config_hash,
+            module_stats: Default::default(),
             instance_cache: Mutex::new(HashMap::new()),
             config: ec,
+            _epoch_ticker: epoch_ticker,
             actor_redirect,
         })))
     }
 }
 
+/// A thread incrementing an engine's epoch every [`EPOCH_TICK`], until dropped.
+struct EpochTicker(Arc<AtomicBool>);
+
+impl EpochTicker {
+    fn start(engine: wasmtime::Engine) -> anyhow::Result<Self> {
+        let stopped = Arc::new(AtomicBool::new(false));
+        std::thread::Builder::new()
+            .name("fvm-epoch-ticker".into())
+            .spawn({
+                let stopped = stopped.clone();
+                move || {
+                    while !stopped.load(Ordering::Relaxed) {
+                        std::thread::sleep(EPOCH_TICK);
+                        engine.increment_epoch();
+                    }
+                }
+            })
+            .context("failed to start the epoch ticker")?;
+        Ok(EpochTicker(stopped))
+    }
+}
+
+impl Drop for EpochTicker {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
 struct Cache<K> {
     linker: wasmtime::Linker<InvocationData<K>>,
 }
@@ -322,6 +579,11 @@ impl Drop for Engine {
 }
 
 impl Engine {
+    /// Returns the configuration of this engine.
+    pub fn config(&self) -> &EngineConfig {
+        &self.inner.config
+    }
+
     /// Loads an actor's Wasm code from the blockstore by CID, and prepares
     /// it for execution by instantiating and caching the Wasm module. This
     /// method errors if the code CID is not found in the store.
@@ -374,6 +636,78 @@ impl Engine {
         Ok(total_size)
     }
 
+    /// Instantiates and caches the Wasm modules of all the builtin actors in the manifest, see
+    /// [`Engine::preload`].
+    ///
+    /// Returns the total original byte size of the modules.
+    pub fn preload_manifest<BS: Blockstore>(
+        &self,
+        blockstore: BS,
+        manifest: &Manifest,
+    ) -> anyhow::Result<usize> {
+        self.preload(blockstore, manifest.builtin_actor_codes())
+    }
+
+    /// Compiles the Wasm modules of all the builtin actors in the manifest, and writes them to
+    /// `output_dir` as `<code CID>.cwasm` files, so that embedders can ship precompiled actors and
+    /// load them with [`Engine::load_precompiled`]. The modules are also cached by this engine.
+    ///
+    /// The files use the same format as the on-disk [`ModuleCache`], and can only be loaded by
+    /// engines with the same [`EngineConfig`] and wasmtime version. Returns the number of modules
+    /// written.
+    pub fn precompile_bundle<BS: Blockstore>(
+        &self,
+        blockstore: BS,
+        manifest: &Manifest,
+        output_dir: &Path,
+    ) -> anyhow::Result<usize> {
+        fs::create_dir_all(output_dir)
+            .with_context(|| format!("failed to create directory {}", output_dir.display()))?;
+        let mut count = 0;
+        for cid in manifest.builtin_actor_codes() {
+            let size = self.prepare_actor_code(cid, &blockstore)?;
+            let module = self
+                .get_module(&blockstore, cid)?
+                .ok_or_else(|| anyhow!("no wasm bytecode in blockstore for CID {}", cid))?;
+            let data = module_cache::encode(self.inner.config_hash, &module, size)?;
+            let path = output_dir.join(format!("{cid}.cwasm"));
+            fs::write(&path, data).with_context(|| {
+                format!("failed to write precompiled module {}", path.display())
+            })?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Loads the modules of all the builtin actors in the manifest from the files written to
+    /// `input_dir` by [`Engine::precompile_bundle`], instead of compiling them.
+    ///
+    /// Returns the number of modules loaded.
+    ///
+    /// # Safety
+    ///
+    /// The files must have been written by [`Engine::precompile_bundle`]. Files written by engines
+    /// with a different [`EngineConfig`], or modified since, are rejected. See
+    /// [`wasmtime::Module::deserialize`] for safety information.
+    pub unsafe fn load_precompiled(
+        &self,
+        manifest: &Manifest,
+        input_dir: &Path,
+    ) -> anyhow::Result<usize> {
+        let mut count = 0;
+        for cid in manifest.builtin_actor_codes() {
+            let path = input_dir.join(format!("{cid}.cwasm"));
+            let data = fs::read(&path)
+                .with_context(|| format!("failed to read precompiled module {}", path.display()))?;
+            self.cache_compiled(cid, || {
+                module_cache::decode(&self.inner.engine, self.inner.config_hash, &data)
+            })
+            .with_context(|| format!("failed to load precompiled module {}", path.display()))?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
     fn with_redirect<'a>(&'a self, k: &'a Cid) -> &'a Cid {
         match &self.inner.actor_redirect.get(k) {
             Some(cid) => cid,
@@ -392,7 +726,7 @@ impl Engine {
         let size = match cache.get(k) {
             Some(item) => item.size,
             None => {
-                let m = self.load_raw(wasm)?;
+                let m = self.load_raw(k, wasm)?;
                 let s = m.size;
                 cache.insert(*k, m);
                 s
@@ -401,7 +735,50 @@ impl Engine {
         Ok(size)
     }
 
-    fn load_raw(&self, raw_wasm: &[u8]) -> anyhow::Result<ModuleRecord> {
+    /// Returns the compilation and instantiation metrics of each module loaded by this engine (or
+    /// any other engine in its [`EnginePool`]), by code CID.
+    pub fn module_stats(&self) -> HashMap<Cid, ModuleStats> {
+        self.inner
+            .module_stats
+            .lock()
+            .expect("module_stats poisoned")
+            .clone()
+    }
+
+    fn record_compile(&self, k: &Cid, code_size: usize, compile_time: Duration) {
+        let mut stats = self
+            .inner
+            .module_stats
+            .lock()
+            .expect("module_stats poisoned");
+        let stats = stats.entry(*k).or_default();
+        stats.code_size = code_size;
+        stats.compile_time = compile_time;
+    }
+
+    fn record_instantiation(&self, k: &Cid, instantiation_time: Duration) {
+        let mut stats = self
+            .inner
+            .module_stats
+            .lock()
+            .expect("module_stats poisoned");
+        let stats = stats.entry(*k).or_default();
+        stats.instantiations += 1;
+        stats.instantiation_time += instantiation_time;
+    }
+
+    fn load_raw(&self, k: &Cid, raw_wasm: &[u8]) -> anyhow::Result<ModuleRecord> {
+        let start = Instant::now();
+        let code_size = raw_wasm.len();
+
+        if let Some(cache) = &self.inner.disk_cache {
+            if let Some((module, size)) = cache.load(&self.inner.engine, k, self.inner.config_hash)
+            {
+                self.record_compile(k, code_size, start.elapsed());
+                return Ok(ModuleRecord { module, size });
+            }
+        }
+
         // First make sure that non-instrumented wasm is valid
         Module::validate(&self.inner.engine, raw_wasm)
             .map_err(anyhow::Error::msg)
@@ -430,10 +807,24 @@ impl Engine {
         //   (code `0xFC 15`) uses what parity-wasm calls the `BULK_PREFIX` but it was added later in
         //   https://github.com/WebAssembly/reference-types/issues/29 and is not recognised by the
         //   parity-wasm module parser, so the contract cannot grow the tables.
-        let raw_wasm = gas_metering::inject(&raw_wasm, self.inner.config.wasm_prices, "gas")
-            .map_err(|_| anyhow::Error::msg("injecting gas counter failed"))?;
+        //
+        // When metering with fuel, wasmtime does the metering instead.
+        let raw_wasm = match self.inner.config.gas_metering {
+            GasMetering::Instrumentation => {
+                gas_metering::inject(&raw_wasm, self.inner.config.wasm_prices, "gas")
+                    .map_err(|_| anyhow::Error::msg("injecting gas counter failed"))?
+            }
+            GasMetering::Fuel => raw_wasm,
+        };
 
         let module = Module::from_binary(&self.inner.engine, &raw_wasm)?;
+        self.record_compile(k, code_size, start.elapsed());
+
+        if let Some(cache) = &self.inner.disk_cache {
+            if let Err(e) = cache.store(k, self.inner.config_hash, &module, raw_wasm.len()) {
+                log::warn!("failed to cache the compiled module for {k}: {e:#}");
+            }
+        }
 
         Ok(ModuleRecord {
             module,
@@ -441,12 +832,33 @@ impl Engine {
         })
     }
 
-    /// Load compiled wasm code into the engine.
+    /// Load compiled wasm code into the engine. `code_size` is the size of the Wasm module it was
+    /// compiled from, as returned by [`Engine::prepare_wasm_bytecode`].
     ///
     /// # Safety
     ///
     /// See [`wasmtime::Module::deserialize`] for safety information.
-    pub unsafe fn load_compiled(&self, k: &Cid, compiled: &[u8]) -> anyhow::Result<Module> {
+    pub unsafe fn load_compiled(
+        &self,
+        k: &Cid,
+        compiled: &[u8],
+        code_size: usize,
+    ) -> anyhow::Result<Module> {
+        self.cache_compiled(k, || {
+            Ok((
+                Module::deserialize(&self.inner.engine, compiled)?,
+                code_size,
+            ))
+        })
+    }
+
+    /// Caches the module (and its code size) returned by `load`, unless a module is already cached
+    /// for the given code CID.
+    fn cache_compiled(
+        &self,
+        k: &Cid,
+        load: impl FnOnce() -> anyhow::Result<(Module, usize)>,
+    ) -> anyhow::Result<Module> {
         let k = self.with_redirect(k);
         let mut cache = self
             .inner
@@ -456,12 +868,14 @@ impl Engine {
         let module = match cache.get(k) {
             Some(m) => m.module.clone(),
             None => {
-                let module = Module::deserialize(&self.inner.engine, compiled)?;
+                let start = Instant::now();
+                let (module, size) = load()?;
+                self.record_compile(k, size, start.elapsed());
                 cache.insert(
                     *k,
                     ModuleRecord {
                         module: module.clone(),
-                        size: compiled.len(),
+                        size,
                     },
                 );
                 module
@@ -488,7 +902,7 @@ impl Engine {
             Vacant(v) => blockstore
                 .get(k)
                 .context("failed to lookup wasm module in blockstore")?
-                .map(|raw_wasm| Ok(v.insert(self.load_raw(&raw_wasm)?).module.clone()))
+                .map(|raw_wasm| Ok(v.insert(self.load_raw(k, &raw_wasm)?).module.clone()))
                 .transpose(),
         }
     }
@@ -506,7 +920,8 @@ impl Engine {
         let mut instance_cache = self.inner.instance_cache.lock().expect("cache poisoned");
 
         let type_id = TypeId::of::<K>();
-        let cache: &mut Cache<K> = match instance_cache.entry(type_id) {
+        let network_version = store.data().kernel.machine().context().network_version;
+        let cache: &mut Cache<K> = match instance_cache.entry((type_id, network_version)) {
             Occupied(e) => &mut *e
                 .into_mut()
                 .downcast_mut()
@@ -541,6 +956,8 @@ impl Engine {
             .expect("module_cache poisoned");
 
         let instantiate = |store: &mut wasmtime::Store<InvocationData<K>>, module| {
+            let start = Instant::now();
+
             // Before we instantiate the module, we should make sure the user has sufficient gas to
             // pay for the minimum memory requirements. The module instrumentation in `inject` only
             // adds code to charge for _growing_ the memory, but not for the amount made accessible
@@ -575,12 +992,23 @@ impl Engine {
             // Note that this does _not_ contain the time it took the load the Wasm file,
             // which could have been cached already.
             record_init_time(store, t);
+            self.record_instantiation(k, start.elapsed());
 
             Ok(Some(inst))
         };
 
+        let record = |store: &wasmtime::Store<InvocationData<K>>, cached| {
+            let machine = store.data().kernel.machine();
+            if machine.collects_metrics() {
+                machine.record_metric(MetricEvent::ModuleInstantiation { cached })
+            }
+        };
+
         match module_cache.entry(*k) {
-            Occupied(v) => instantiate(store, &v.get().module),
+            Occupied(v) => {
+                record(&*store, true);
+                instantiate(store, &v.get().module)
+            }
             Vacant(v) => match store
                 .data()
                 .kernel
@@ -590,11 +1018,14 @@ impl Engine {
                 .context("failed to lookup wasm module in blockstore")
                 .map_err(Abort::Fatal)?
             {
-                Some(raw_wasm) => instantiate(
-                    store,
-                    &v.insert(self.load_raw(&raw_wasm).map_err(Abort::Fatal)?)
-                        .module,
-                ),
+                Some(raw_wasm) => {
+                    record(&*store, false);
+                    instantiate(
+                        store,
+                        &v.insert(self.load_raw(k, &raw_wasm).map_err(Abort::Fatal)?)
+                            .module,
+                    )
+                }
                 None => Ok(None),
             },
         }
@@ -626,6 +1057,14 @@ impl Engine {
             last_memory_bytes: memory_bytes,
             last_charge_time: GasTimer::start(),
             memory: self.inner.dummy_memory,
+            permitted_syscalls: None,
+            instructions_available: None,
+            // Checked when creating the pool.
+            fuel_price: match self.inner.config.gas_metering {
+                GasMetering::Instrumentation => None,
+                GasMetering::Fuel => self.inner.config.wasm_prices.fuel_price(),
+            },
+            last_fuel_consumed: 0,
         };
 
         let mut store = wasmtime::Store::new(&self.inner.engine, id);
@@ -657,6 +1096,18 @@ impl Engine {
 
         store
     }
+
+    /// Makes the store trap once the given (wall-clock) time has elapsed, to within
+    /// [`EPOCH_TICK`]. The engine must enforce an execution timeout (see
+    /// [`EngineConfig::execution_timeout`]).
+    pub fn set_timeout<T>(&self, store: &mut wasmtime::Store<T>, timeout: Duration) {
+        debug_assert!(self.inner.config.execution_timeout.is_some());
+        // The deadline is reached after `ticks - 1` to `ticks` ticks, depending on how far into
+        // the current tick we are.
+        let ticks = timeout.as_nanos() / EPOCH_TICK.as_nanos() + 1;
+        store.set_epoch_deadline(ticks.try_into().unwrap_or(u64::MAX / 2));
+        store.epoch_deadline_trap();
+    }
 }
 
 #[repr(transparent)]
@@ -701,10 +1152,18 @@ impl<L: MemoryLimiter> wasmtime::ResourceLimiter for WasmtimeLimiter<L> {
 
 #[cfg(test)]
 mod tests {
+    use cid::Cid;
+    use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+    use fvm_ipld_encoding::IPLD_RAW;
+    use fvm_shared::version::NetworkVersion;
+    use multihash::{Code, MultihashDigest};
     use wasmtime::ResourceLimiter;
 
-    use crate::engine::WasmtimeLimiter;
+    use crate::engine::{
+        wasmtime_config, EngineConfig, EnginePool, GasMetering, WasmFeatures, WasmtimeLimiter,
+    };
     use crate::machine::limiter::MemoryLimiter;
+    use crate::machine::{Manifest, NetworkConfig};
 
     #[derive(Default)]
     struct Limiter {
@@ -758,4 +1217,160 @@ mod tests {
         assert!(limits.table_growing(2, 4, None).unwrap());
         assert_eq!(limits.0.memory, 5 * 8);
     }
+
+    #[test]
+    fn pooling_config() {
+        let mut ec = EngineConfig::from(&NetworkConfig::new(NetworkVersion::V21));
+        ec.pooling.max_instances = Some(ec.max_call_depth - 1);
+        assert!(wasmtime_config(&ec).is_err());
+
+        ec.pooling.max_instances = Some(ec.max_call_depth);
+        ec.pooling.max_table_elements = Some(1000);
+        ec.pooling.max_unused_warm_slots = Some(10);
+        assert_eq!(ec.instance_pool_size(), ec.max_call_depth);
+        let pool = EnginePool::new_default(ec.clone()).unwrap();
+        assert!(pool.config() == &ec);
+    }
+
+    #[test]
+    fn for_network() {
+        let mut ec = EngineConfig::from(&NetworkConfig::new(NetworkVersion::V21));
+        ec.concurrency = 4;
+        ec.pooling.max_instances = Some(ec.max_call_depth);
+        ec.execution_timeout = Some(std::time::Duration::from_secs(1));
+        ec.gas_metering = GasMetering::Fuel;
+
+        // The node-local fields are kept, the others follow the network.
+        let mut nc = NetworkConfig::new(NetworkVersion::V21);
+        nc.max_call_depth += 1;
+        let upgraded = ec.for_network(&nc);
+        assert_eq!(upgraded.max_call_depth, ec.max_call_depth + 1);
+        assert_eq!(upgraded.concurrency, 4);
+        assert_eq!(upgraded.pooling, ec.pooling);
+        assert_eq!(upgraded.execution_timeout, ec.execution_timeout);
+        assert_eq!(upgraded.gas_metering, GasMetering::Fuel);
+    }
+
+    #[test]
+    fn precompile_bundle() {
+        /// `(module (func))`
+        const WASM: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b,
+        ];
+        let dir = std::env::temp_dir().join(format!("fvm-precompiled-{}", std::process::id()));
+        let ec = EngineConfig::from(&NetworkConfig::new(NetworkVersion::V21));
+        let manifest = Manifest::dummy();
+        let store = MemoryBlockstore::default();
+        for code in manifest.builtin_actor_codes() {
+            store.put_keyed(code, WASM).unwrap();
+        }
+
+        let engine = EnginePool::new_default(ec.clone()).unwrap().acquire();
+        let written = engine.precompile_bundle(&store, &manifest, &dir).unwrap();
+        assert_eq!(written, Manifest::DUMMY_CODES.len());
+
+        // The modules are loaded without the code, and keep the size of the original code.
+        let loaded = EnginePool::new_default(ec.clone()).unwrap().acquire();
+        assert_eq!(
+            unsafe { loaded.load_precompiled(&manifest, &dir) }.unwrap(),
+            written
+        );
+        let empty = MemoryBlockstore::default();
+        for code in manifest.builtin_actor_codes() {
+            assert!(loaded.get_module(&empty, code).unwrap().is_some());
+            assert_eq!(
+                loaded.prepare_actor_code(code, &empty).unwrap(),
+                engine.prepare_actor_code(code, &store).unwrap()
+            );
+        }
+
+        // Engines with a different configuration reject the modules.
+        let mut other = ec.clone();
+        other.max_wasm_stack += 1;
+        let engine = EnginePool::new_default(other).unwrap().acquire();
+        assert!(unsafe { engine.load_precompiled(&manifest, &dir) }.is_err());
+
+        // So do engines loading corrupted modules.
+        let code = manifest.builtin_actor_codes().next().unwrap();
+        let path = dir.join(format!("{code}.cwasm"));
+        let mut data = std::fs::read(&path).unwrap();
+        *data.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&path, data).unwrap();
+        let engine = EnginePool::new_default(ec).unwrap().acquire();
+        assert!(unsafe { engine.load_precompiled(&manifest, &dir) }.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn wasm_features() {
+        /// `(module (memory 1) (func (memory.fill (i32.const 0) (i32.const 0) (i32.const 0))))`
+        const WASM: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x05, 0x03, 0x01, 0x00, 0x01, 0x0a, 0x0d, 0x01, 0x0b, 0x00,
+            0x41, 0x00, 0x41, 0x00, 0x41, 0x00, 0xfc, 0x0b, 0x00, 0x0b,
+        ];
+        let code = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(WASM));
+        let mut ec = EngineConfig::from(&NetworkConfig::new(NetworkVersion::V21));
+        assert_eq!(ec.wasm_features, WasmFeatures::default());
+
+        // All supported network versions enable the same proposals.
+        assert_eq!(
+            NetworkConfig::new(NetworkVersion::V22).wasm_features,
+            ec.wasm_features
+        );
+
+        let engine = EnginePool::new_default(ec.clone()).unwrap().acquire();
+        engine.prepare_wasm_bytecode(&code, WASM).unwrap();
+
+        ec.wasm_features.bulk_memory = false;
+        let engine = EnginePool::new_default(ec.clone()).unwrap().acquire();
+        assert!(engine.prepare_wasm_bytecode(&code, WASM).is_err());
+
+        // Proposals the gas instrumentation can't handle are rejected.
+        for features in [
+            WasmFeatures {
+                simd: true,
+                ..ec.wasm_features
+            },
+            WasmFeatures {
+                multi_value: true,
+                ..ec.wasm_features
+            },
+        ] {
+            let mut nc = NetworkConfig::new(NetworkVersion::V21);
+            assert!(nc.set_wasm_features(features).is_err());
+            assert_eq!(nc.wasm_features, WasmFeatures::default());
+            let ec = EngineConfig {
+                wasm_features: features,
+                ..ec.clone()
+            };
+            assert!(EnginePool::new_default(ec).is_err());
+        }
+    }
+
+    #[test]
+    fn gas_metering() {
+        /// `(module (func))`
+        const WASM: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b,
+        ];
+        let code = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(WASM));
+        let mut ec = EngineConfig::from(&NetworkConfig::new(NetworkVersion::V21));
+        assert_eq!(ec.gas_metering, GasMetering::Instrumentation);
+        let engine = EnginePool::new_default(ec.clone()).unwrap().acquire();
+        let instrumented = engine.prepare_wasm_bytecode(&code, WASM).unwrap();
+
+        // Modules aren't instrumented when metering with fuel.
+        ec.gas_metering = GasMetering::Fuel;
+        let engine = EnginePool::new_default(ec.clone()).unwrap().acquire();
+        assert!(engine.prepare_wasm_bytecode(&code, WASM).unwrap() < instrumented);
+
+        // The prices must be expressible in fuel.
+        let mut prices = ec.wasm_prices.clone();
+        prices.call = prices.instruction_default;
+        ec.wasm_prices = Box::leak(Box::new(prices));
+        assert!(EnginePool::new_default(ec).is_err());
+    }
 }
@@ -0,0 +1,200 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! An on-disk cache of compiled actor modules, so that restarted nodes don't need to recompile
+//! the builtin actors.
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use cid::Cid;
+use wasmtime::Module;
+
+use super::EngineConfig;
+
+/// The length of the digest prefixing each cached module.
+const DIGEST_LEN: usize = 32;
+/// The length of the header (digest, configuration hash, and code size) of each cached module.
+const HEADER_LEN: usize = DIGEST_LEN + 16;
+
+/// A directory of compiled modules, keyed by code CID and by a hash of the engine configuration
+/// (including the wasmtime version and settings, and the FVM's instrumentation parameters), see
+/// [`MultiEngine::with_module_cache`](super::MultiEngine::with_module_cache).
+///
+/// Each cached module is prefixed with a digest of its contents and the hash of the engine
+/// configuration, both checked before loading it. Invalid or incompatible modules are removed and
+/// recompiled.
+#[derive(Debug, Clone)]
+pub struct ModuleCache {
+    dir: PathBuf,
+}
+
+impl ModuleCache {
+    /// Caches compiled modules in the given directory, creating it if needed.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create module cache {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, k: &Cid, config: u64) -> PathBuf {
+        self.dir.join(format!("{k}-{config:016x}.module"))
+    }
+
+    /// Loads a cached module with the code size stored with it, if cached and valid.
+    pub(super) fn load(
+        &self,
+        engine: &wasmtime::Engine,
+        k: &Cid,
+        config: u64,
+    ) -> Option<(Module, usize)> {
+        let path = self.path(k, config);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return None,
+            Err(e) => {
+                log::warn!("failed to read cached module {}: {e}", path.display());
+                return None;
+            }
+        };
+        // SAFETY: the module was written by `store`, and `decode` checks that it's intact.
+        match unsafe { decode(engine, config, &data) } {
+            Ok(res) => Some(res),
+            Err(e) => {
+                log::warn!("removing invalid cached module {}: {e}", path.display());
+                let _ = fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    /// Caches a module, replacing any cached module for the same code and configuration.
+    pub(super) fn store(&self, k: &Cid, config: u64, module: &Module, size: usize) -> Result<()> {
+        let data = encode(config, module, size)?;
+        let path = self.path(k, config);
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        let mut file = File::create(&tmp)
+            .with_context(|| format!("failed to create cached module {}", path.display()))?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &path)
+            .with_context(|| format!("failed to cache module {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Serializes a compiled module, prefixed with a header holding a digest of the rest of the data,
+/// the hash of the configuration of the engine that compiled it, and the code size recorded for
+/// it. Used both by the [`ModuleCache`] and for precompiled bundles.
+pub(super) fn encode(config: u64, module: &Module, size: usize) -> Result<Vec<u8>> {
+    let mut data = vec![0; DIGEST_LEN];
+    data.extend(config.to_le_bytes());
+    data.extend((size as u64).to_le_bytes());
+    data.extend(module.serialize()?);
+    let digest = digest(&data[DIGEST_LEN..]);
+    data[..DIGEST_LEN].copy_from_slice(&digest);
+    Ok(data)
+}
+
+/// Deserializes a module serialized by [`encode`], with its code size, checking that it's intact
+/// and that it was compiled by an engine with the given configuration hash.
+///
+/// # Safety
+///
+/// The data must have been written by [`encode`]. See [`wasmtime::Module::deserialize`].
+pub(super) unsafe fn decode(
+    engine: &wasmtime::Engine,
+    config: u64,
+    data: &[u8],
+) -> Result<(Module, usize)> {
+    if data.len() < HEADER_LEN || data[..DIGEST_LEN] != digest(&data[DIGEST_LEN..]) {
+        return Err(anyhow!("digest mismatch"));
+    }
+    let read_u64 = |at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+    let compiled_with = read_u64(DIGEST_LEN);
+    if compiled_with != config {
+        return Err(anyhow!(
+            "compiled with an incompatible engine configuration ({compiled_with:016x}, expected \
+             {config:016x})"
+        ));
+    }
+    let size = read_u64(DIGEST_LEN + 8);
+    // Wasmtime additionally checks that the module was compiled for a compatible engine.
+    let module = Module::deserialize(engine, &data[HEADER_LEN..])?;
+    Ok((module, size as usize))
+}
+
+fn digest(data: &[u8]) -> [u8; DIGEST_LEN] {
+    let hash = blake2b_simd::Params::new()
+        .hash_length(DIGEST_LEN)
+        .hash(data);
+    hash.as_bytes().try_into().unwrap()
+}
+
+/// Hashes everything that affects the compiled modules of an engine.
+pub(super) fn config_hash(engine: &wasmtime::Engine, ec: &EngineConfig) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+    ec.max_wasm_stack.hash(&mut hasher);
+    ec.wasm_prices.hash(&mut hasher);
+    ec.consume_fuel.hash(&mut hasher);
+    ec.execution_timeout.is_some().hash(&mut hasher);
+    ec.gas_metering.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_shared::version::NetworkVersion;
+    use multihash::{Code, MultihashDigest};
+
+    use super::*;
+    use crate::engine::MultiEngine;
+    use crate::machine::NetworkConfig;
+
+    /// `(module (func))`
+    const WASM: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00, 0x03,
+        0x02, 0x01, 0x00, 0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b,
+    ];
+
+    #[test]
+    fn module_cache() {
+        let dir = std::env::temp_dir().join(format!("fvm-module-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let nc = NetworkConfig::new(NetworkVersion::V21);
+        let k = Cid::new_v1(fvm_ipld_encoding::IPLD_RAW, Code::Blake2b256.digest(WASM));
+        let compile = || {
+            let engines = MultiEngine::default().with_module_cache(ModuleCache::new(&dir).unwrap());
+            let engine = engines.get(&nc).unwrap().acquire();
+            engine.prepare_wasm_bytecode(&k, WASM).unwrap()
+        };
+
+        let size = compile();
+        let cached: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        assert_eq!(cached.len(), 1);
+        let name = cached[0].file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with(&k.to_string()));
+
+        // Loaded from the cache.
+        let data = fs::read(&cached[0]).unwrap();
+        assert_eq!(compile(), size);
+        assert_eq!(fs::read(&cached[0]).unwrap(), data);
+
+        // Corrupt modules are recompiled.
+        let corrupt = &data[..data.len() - 1];
+        fs::write(&cached[0], corrupt).unwrap();
+        assert_eq!(compile(), size);
+        assert_ne!(fs::read(&cached[0]).unwrap(), corrupt);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Block validation, verifying message signatures in parallel with message execution.
+use std::sync::mpsc;
+
+use anyhow::{anyhow, Context as _};
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+use fvm_ipld_encoding::tuple::*;
+use fvm_ipld_encoding::{to_vec, CborStore, DAG_CBOR};
+use fvm_shared::address::{Address, Protocol};
+use fvm_shared::crypto::signature::ops::verify_bls_aggregate;
+use fvm_shared::crypto::signature::{Signature, SignatureType};
+use fvm_shared::message::Message;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use super::{ApplyKind, ApplyRet, DefaultExecutor, Executor};
+use crate::account_actor::State as AccountState;
+use crate::kernel::{Context as _, Kernel};
+use crate::machine::Machine;
+
+/// A message signed by its sender.
+#[derive(Serialize_tuple, Deserialize_tuple, Clone, Debug, PartialEq, Eq)]
+pub struct SignedMessage {
+    pub message: Message,
+    pub signature: Signature,
+}
+
+/// The messages included in a block, in execution order: BLS messages first, followed by
+/// secp256k1 messages.
+#[derive(Clone, Debug, Default)]
+pub struct BlockMessages {
+    /// The BLS messages, whose signatures are aggregated into `bls_aggregate`.
+    pub bls_messages: Vec<Message>,
+    /// The aggregate signature of the BLS messages. May only be omitted if there are no BLS
+    /// messages.
+    pub bls_aggregate: Option<Signature>,
+    /// The secp256k1 messages.
+    pub secp_messages: Vec<SignedMessage>,
+}
+
+/// A signature to verify: the signing bytes of the message and the signer's key address.
+struct SignatureJob {
+    message: Vec<u8>,
+    signer: Address,
+}
+
+impl SignatureJob {
+    /// The bytes signed by the sender: the message's CID.
+    fn signing_bytes(&self) -> Vec<u8> {
+        Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&self.message)).to_bytes()
+    }
+}
+
+impl<K> DefaultExecutor<K>
+where
+    K: Kernel,
+{
+    /// Validates and executes the messages of a block in a single pass.
+    ///
+    /// The senders' signatures are verified on the shared [`rayon`] thread pool while the messages
+    /// are executed (explicitly, in order) on the current thread. Returns the result of applying
+    /// each message or, if any signature is invalid, an error. Validation stops early if an
+    /// invalid signature is detected while messages are still being executed.
+    ///
+    /// The messages are executed before all signatures have been verified, so the state must be
+    /// discarded (not flushed) if this method fails.
+    pub fn validate_block(&mut self, block: &BlockMessages) -> anyhow::Result<Vec<ApplyRet>> {
+        if block.bls_aggregate.is_none() && !block.bls_messages.is_empty() {
+            return Err(anyhow!("missing bls aggregate signature"));
+        }
+
+        // Resolve the signers' keys (and encode the messages) before we start executing.
+        let mut messages = Vec::with_capacity(block.bls_messages.len() + block.secp_messages.len());
+        let mut bls_jobs = Vec::with_capacity(block.bls_messages.len());
+        for msg in &block.bls_messages {
+            let encoded = to_vec(msg)?;
+            messages.push((msg.clone(), encoded.len()));
+            bls_jobs.push(SignatureJob {
+                message: encoded,
+                signer: self.resolve_signer(&msg.from, Protocol::BLS)?,
+            });
+        }
+        let mut secp_jobs = Vec::with_capacity(block.secp_messages.len());
+        for smsg in &block.secp_messages {
+            if smsg.signature.sig_type != SignatureType::Secp256k1 {
+                return Err(anyhow!(
+                    "secp message from {} has a {:?} signature",
+                    smsg.message.from,
+                    smsg.signature.sig_type
+                ));
+            }
+            messages.push((smsg.message.clone(), to_vec(smsg)?.len()));
+            secp_jobs.push((
+                SignatureJob {
+                    message: to_vec(&smsg.message)?,
+                    signer: self.resolve_signer(&smsg.message.from, Protocol::Secp256k1)?,
+                },
+                smsg.signature.clone(),
+            ));
+        }
+
+        let bls_aggregate = block.bls_aggregate.clone();
+        let (tx, rx) = mpsc::sync_channel(1);
+        rayon::spawn(move || {
+            let _ = tx.send(verify_signatures(bls_jobs, bls_aggregate, secp_jobs));
+        });
+
+        let mut verified = false;
+        let mut rets = Vec::with_capacity(messages.len());
+        for (msg, raw_length) in messages {
+            if !verified {
+                if let Ok(res) = rx.try_recv() {
+                    res?;
+                    verified = true;
+                }
+            }
+            rets.push(self.execute_message(msg, ApplyKind::Explicit, raw_length)?);
+        }
+        if !verified {
+            rx.recv()
+                .context("signature verification did not complete")??;
+        }
+        Ok(rets)
+    }
+
+    /// Resolves the key address of a message sender, which must be of the given protocol.
+    fn resolve_signer(&self, from: &Address, protocol: Protocol) -> anyhow::Result<Address> {
+        let key = match from.protocol() {
+            Protocol::BLS | Protocol::Secp256k1 => *from,
+            _ => {
+                let state_tree = self.state_tree();
+                let id = state_tree
+                    .lookup_id(from)
+                    .with_context(|| format!("failed to lookup actor {}", from))?
+                    .with_context(|| format!("sender {} not found", from))?;
+                let actor = state_tree
+                    .get_actor(id)
+                    .with_context(|| format!("failed to lookup actor {}", from))?
+                    .with_context(|| format!("sender {} not found", from))?;
+                if !self.builtin_actors().is_account_actor(&actor.code) {
+                    return Err(anyhow!("sender {} is not an account", from));
+                }
+                let state: AccountState = state_tree
+                    .store()
+                    .get_cbor(&actor.state)?
+                    .with_context(|| format!("failed to load account state of {}", from))?;
+                state.address
+            }
+        };
+        if key.protocol() != protocol {
+            return Err(anyhow!(
+                "sender {} of a {} message has a {} key",
+                from,
+                protocol,
+                key.protocol()
+            ));
+        }
+        Ok(key)
+    }
+}
+
+/// Verifies the aggregate signature of the BLS messages and the signatures of the secp256k1
+/// messages, in parallel.
+fn verify_signatures(
+    bls_jobs: Vec<SignatureJob>,
+    bls_aggregate: Option<Signature>,
+    secp_jobs: Vec<(SignatureJob, Signature)>,
+) -> anyhow::Result<()> {
+    let (bls, secp) = rayon::join(
+        || {
+            let Some(aggregate) = bls_aggregate else {
+                return Ok(());
+            };
+            let data: Vec<_> = bls_jobs.iter().map(SignatureJob::signing_bytes).collect();
+            let keys: Vec<_> = bls_jobs.iter().map(|j| j.signer.payload_bytes()).collect();
+            let data: Vec<_> = data.iter().map(Vec::as_slice).collect();
+            let keys: Vec<_> = keys.iter().map(Vec::as_slice).collect();
+            if verify_bls_aggregate(&data, &keys, &aggregate) {
+                Ok(())
+            } else {
+                Err(anyhow!("bls aggregate signature verification failed"))
+            }
+        },
+        || {
+            secp_jobs.into_par_iter().try_for_each(|(job, signature)| {
+                signature
+                    .verify(&job.signing_bytes(), &job.signer)
+                    .map_err(|e| anyhow!("invalid signature from {}: {}", job.signer, e))
+            })
+        },
+    );
+    bls.and(secp)
+}
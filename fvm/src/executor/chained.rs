@@ -0,0 +1,238 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Applying the messages of a tipset, producing its state and receipts roots.
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
+
+use anyhow::Context as _;
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::message::Message;
+use fvm_shared::verify::receipts_root;
+use fvm_shared::ActorID;
+
+use super::sink::message_cid;
+use super::{ApplyKind, ApplyRet, DefaultExecutor, Executor};
+use crate::call_manager::CallManager;
+use crate::externs::Externs;
+use crate::kernel::Kernel;
+use crate::machine::limiter::NetworkMemoryLimiter;
+use crate::machine::{DefaultMachine, Machine};
+
+/// A message of a tipset, see [`ChainedExecutor::apply_tipset`].
+#[derive(Clone, Debug)]
+pub struct TipsetMessage {
+    pub message: Message,
+    pub apply_kind: ApplyKind,
+    /// The length of the message as it appears on-chain, used to charge explicit messages for
+    /// inclusion.
+    pub raw_length: usize,
+}
+
+impl TipsetMessage {
+    /// An explicit message included in a block of the tipset.
+    pub fn explicit(message: Message, raw_length: usize) -> Self {
+        Self {
+            message,
+            apply_kind: ApplyKind::Explicit,
+            raw_length,
+        }
+    }
+
+    /// An implicit message applied by the node.
+    pub fn implicit(message: Message) -> Self {
+        Self {
+            message,
+            apply_kind: ApplyKind::Implicit,
+            raw_length: 0,
+        }
+    }
+
+    /// A cron message (e.g., a block reward or cron tick) applied by the node, see
+    /// [`DefaultExecutor::apply_implicit_message`].
+    pub fn cron(message: Message) -> Self {
+        Self {
+            message,
+            apply_kind: ApplyKind::Cron,
+            raw_length: 0,
+        }
+    }
+}
+
+/// The outcome of [`ChainedExecutor::apply_tipset`].
+#[derive(Clone, Debug)]
+pub struct TipsetRet {
+    /// The flushed state root.
+    pub state_root: Cid,
+    /// The root of the AMT of the receipts of the explicit messages applied, in order.
+    pub receipts_root: Cid,
+    /// The result of each message applied, in order, with its index in the tipset's messages.
+    pub applied: Vec<(usize, ApplyRet)>,
+    /// The indices of the explicit messages that were skipped.
+    pub skipped: Vec<usize>,
+}
+
+/// An executor that applies all messages of a tipset at once.
+///
+/// Like the Filecoin chain, [`ChainedExecutor::apply_tipset`] only applies the explicit messages
+/// that are valid with respect to the messages before them: messages included more than once
+/// (e.g., by several blocks of the tipset) are applied once, and a sender's messages are skipped
+/// once their nonces aren't consecutive or the sender can't afford them. Skipped messages don't
+/// have receipts.
+pub struct ChainedExecutor<K: Kernel> {
+    executor: DefaultExecutor<K>,
+}
+
+/// The nonce and funds a sender has left for its next message.
+struct SenderBudget {
+    next_nonce: u64,
+    balance: TokenAmount,
+}
+
+impl<K: Kernel> ChainedExecutor<K> {
+    /// Wraps an executor.
+    pub fn new(executor: DefaultExecutor<K>) -> Self {
+        Self { executor }
+    }
+
+    /// Returns the wrapped executor.
+    pub fn into_inner(self) -> DefaultExecutor<K> {
+        self.executor
+    }
+
+    /// Selects the explicit messages to apply, against the current state. Returns, by message,
+    /// whether to apply it.
+    fn select(&self, messages: &[TipsetMessage]) -> anyhow::Result<Vec<bool>> {
+        let state_tree = self.executor.state_tree();
+        let mut seen = HashSet::new();
+        let mut senders: HashMap<ActorID, Option<SenderBudget>> = HashMap::new();
+        messages
+            .iter()
+            .map(
+                |TipsetMessage {
+                     message,
+                     apply_kind,
+                     ..
+                 }|
+                 -> anyhow::Result<bool> {
+                    if *apply_kind != ApplyKind::Explicit {
+                        return Ok(true);
+                    }
+                    if !seen.insert(message_cid(message)?) {
+                        return Ok(false);
+                    }
+                    // Unknown senders are left to fail pre-validation.
+                    let Some(sender) = state_tree.lookup_id(&message.from)? else {
+                        return Ok(true);
+                    };
+                    let budget = match senders.entry(sender) {
+                        Entry::Occupied(e) => e.into_mut(),
+                        Entry::Vacant(e) => {
+                            e.insert(state_tree.get_actor(sender)?.map(|act| SenderBudget {
+                                next_nonce: act.sequence,
+                                balance: act.balance,
+                            }))
+                        }
+                    };
+                    let Some(budget) = budget else {
+                        return Ok(true);
+                    };
+                    let required = &message.gas_fee_cap * message.gas_limit + &message.value;
+                    if message.sequence != budget.next_nonce || budget.balance < required {
+                        return Ok(false);
+                    }
+                    budget.next_nonce += 1;
+                    budget.balance -= required;
+                    Ok(true)
+                },
+            )
+            .collect()
+    }
+}
+
+impl<K, C, B, E, L> ChainedExecutor<K>
+where
+    K: Kernel<CallManager = C>,
+    C: CallManager<Machine = DefaultMachine<B, E, L>>,
+    B: Blockstore + 'static,
+    E: Externs + 'static,
+    L: NetworkMemoryLimiter,
+{
+    /// Applies the messages of a tipset in order, then flushes the state and stores the receipts.
+    /// Read-only machines don't flush (see
+    /// [`MachineContext::read_only`](crate::machine::MachineContext::read_only)), and only compute
+    /// the receipts root.
+    ///
+    /// Which explicit messages are applied is decided up-front, against the state before the
+    /// tipset. Implicit messages are always applied, and don't have receipts.
+    pub fn apply_tipset(&mut self, messages: Vec<TipsetMessage>) -> anyhow::Result<TipsetRet> {
+        let selected = self.select(&messages)?;
+
+        let mut applied = Vec::with_capacity(messages.len());
+        let mut skipped = Vec::new();
+        let mut receipts = Vec::new();
+        for (i, (msg, selected)) in messages.into_iter().zip(selected).enumerate() {
+            if !selected {
+                skipped.push(i);
+                continue;
+            }
+            let ret = self
+                .executor
+                .execute_message(msg.message, msg.apply_kind, msg.raw_length)?;
+            if msg.apply_kind == ApplyKind::Explicit {
+                receipts.push(ret.msg_receipt.clone());
+            }
+            applied.push((i, ret));
+        }
+
+        let state_root = self.executor.flush()?;
+        let receipts_root = receipts_root(self.executor.blockstore(), &receipts)
+            .context("failed to build the receipts AMT")?;
+        if !self.executor.context().read_only {
+            self.executor
+                .blockstore()
+                .flush_with_stats(&receipts_root)
+                .context("failed to flush the receipts AMT")?;
+        }
+
+        Ok(TipsetRet {
+            state_root,
+            receipts_root,
+            applied,
+            skipped,
+        })
+    }
+}
+
+impl<K: Kernel> Executor for ChainedExecutor<K> {
+    type Kernel = K;
+
+    fn execute_message(
+        &mut self,
+        msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+    ) -> anyhow::Result<ApplyRet> {
+        self.executor.execute_message(msg, apply_kind, raw_length)
+    }
+
+    fn flush(&mut self) -> anyhow::Result<Cid> {
+        self.executor.flush()
+    }
+}
+
+impl<K: Kernel> Deref for ChainedExecutor<K> {
+    type Target = DefaultExecutor<K>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.executor
+    }
+}
+
+impl<K: Kernel> DerefMut for ChainedExecutor<K> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.executor
+    }
+}
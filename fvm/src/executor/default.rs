@@ -12,16 +12,23 @@ use fvm_shared::error::{ErrorNumber, ExitCode};
 use fvm_shared::event::StampedEvent;
 use fvm_shared::message::Message;
 use fvm_shared::receipt::Receipt;
-use fvm_shared::{ActorID, IPLD_RAW, METHOD_SEND};
+use fvm_shared::{ActorID, BLOCK_GAS_LIMIT, IPLD_RAW, METHOD_SEND};
 use num_traits::Zero;
 
-use super::{ApplyFailure, ApplyKind, ApplyRet, Executor};
+use super::dump::StateDump;
+use super::sink::message_cid;
+use super::{
+    ApplyFailure, ApplyKind, ApplyRet, ExecutionOptions, Executor, PreValidationFailure,
+    MESSAGE_EXPIRATION_VERSION,
+};
 use crate::call_manager::{backtrace, Backtrace, CallManager, Entrypoint, InvocationResult};
 use crate::eam_actor::EAM_ACTOR_ID;
 use crate::engine::EnginePool;
-use crate::gas::{Gas, GasCharge, GasOutputs};
-use crate::kernel::{Block, ClassifyResult, Context as _, ExecutionError, Kernel};
-use crate::machine::{Machine, BURNT_FUNDS_ACTOR_ID, REWARD_ACTOR_ID};
+use crate::gas::{
+    ActorGasUsage, FilecoinGasOutputs, Gas, GasBreakdown, GasCharge, GasOutputs, GasOutputsStrategy,
+};
+use crate::kernel::{Block, ClassifyResult, Context as _, ErrorContext, ExecutionError, Kernel};
+use crate::machine::{Machine, UpgradeSchedule, BURNT_FUNDS_ACTOR_ID, REWARD_ACTOR_ID};
 use crate::trace::ExecutionTrace;
 
 /// The default [`Executor`].
@@ -32,9 +39,11 @@ use crate::trace::ExecutionTrace;
 /// least 64MiB of stack space. If you can't guarantee 64MiB of stack space, wrap this executor in
 /// a [`ThreadedExecutor`][super::ThreadedExecutor].
 pub struct DefaultExecutor<K: Kernel> {
-    engine_pool: EnginePool,
+    pub(super) engine_pool: EnginePool,
     // If the inner value is `None` it means the machine got poisoned and is unusable.
-    machine: Option<<K::CallManager as CallManager>::Machine>,
+    pub(super) machine: Option<<K::CallManager as CallManager>::Machine>,
+    options: ExecutionOptions,
+    schedule: UpgradeSchedule,
 }
 
 impl<K: Kernel> Deref for DefaultExecutor<K> {
@@ -64,6 +73,78 @@ where
         apply_kind: ApplyKind,
         raw_length: usize,
     ) -> anyhow::Result<ApplyRet> {
+        if self.options.hooks.is_empty() {
+            return self.apply_message(msg, apply_kind, raw_length);
+        }
+
+        let hooks = self.options.hooks.clone();
+        let epoch = self.context().epoch;
+        for hook in &hooks {
+            hook.pre_apply(epoch, &msg, apply_kind);
+        }
+        let ret = self.apply_message(msg.clone(), apply_kind, raw_length)?;
+        for hook in &hooks {
+            hook.post_apply(epoch, &msg, apply_kind, &ret);
+        }
+        Ok(ret)
+    }
+
+    /// Flush the state-tree to the underlying blockstore.
+    fn flush(&mut self) -> anyhow::Result<Cid> {
+        let k = (**self).flush()?;
+        Ok(k)
+    }
+}
+
+impl<K> DefaultExecutor<K>
+where
+    K: Kernel,
+{
+    /// Applies a message, without calling the [`ApplyHook`](super::ApplyHook)s.
+    fn apply_message(
+        &mut self,
+        mut msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+    ) -> anyhow::Result<ApplyRet> {
+        // Messages applied to a read-only machine, or to estimate gas, are applied in a
+        // transaction that's always reverted.
+        if (self.context().read_only || self.options.gas_overestimation.is_some())
+            && !self.state_tree().in_transaction()
+        {
+            return self.execute_reverted(msg, apply_kind, raw_length);
+        }
+
+        // When halting on fatal errors, messages are applied in a transaction so the pre-message
+        // state can be recovered for the dump.
+        if self.options.dump_dir.is_some() && !self.state_tree().in_transaction() {
+            return self.execute_dumping(msg, apply_kind, raw_length);
+        }
+
+        // Record the machine's counters, to report the work done by the message.
+        self.set_collect_metrics(self.options.collect_metrics);
+        let pre_metrics = self.options.collect_metrics.then(|| self.metrics());
+
+        // Identify the message to the event sink, if any.
+        let message_cid = match self.options.event_sink {
+            Some(_) => match message_cid(&msg) {
+                Ok(cid) => Some(cid),
+                Err(e) => {
+                    return Ok(ApplyRet::prevalidation_fail(
+                        PreValidationFailure::Serialization(e.to_string()),
+                        TokenAmount::zero(),
+                    ))
+                }
+            },
+            None => None,
+        };
+
+        // When estimating gas, lift the message's gas limit to the block gas limit. Cron messages
+        // are always metered against the block gas limit.
+        if self.options.gas_overestimation.is_some() || apply_kind == ApplyKind::Cron {
+            msg.gas_limit = BLOCK_GAS_LIMIT;
+        }
+
         // Validate if the message was correct, charge for it, and extract some preliminary data.
         let (sender_id, gas_cost, inclusion_cost) =
             match self.preflight_message(&msg, apply_kind, raw_length)? {
@@ -74,6 +155,9 @@ where
         struct MachineExecRet {
             result: crate::kernel::error::Result<InvocationResult>,
             gas_used: u64,
+            refund_credit: u64,
+            gas_breakdown: GasBreakdown,
+            actor_gas: Option<ActorGasUsage>,
             backtrace: Backtrace,
             exec_trace: ExecutionTrace,
             events_root: Option<Cid>,
@@ -144,7 +228,7 @@ where
             let result = cm.with_transaction(|cm| {
                 // Invoke the message. We charge for the return value internally if the call-stack depth
                 // is 1.
-                cm.call_actor::<K>(
+                let ret = cm.call_actor::<K>(
                     sender_id,
                     msg.to,
                     Entrypoint::Invoke(msg.method_num),
@@ -152,7 +236,20 @@ where
                     &msg.value,
                     None,
                     false,
-                )
+                )?;
+                // Then execute any sends deferred by the message.
+                if ret.exit_code.is_success() {
+                    cm.run_deferred_sends::<K>()?;
+                }
+                // Audit the caches before the message's changes are committed, so a leak fails
+                // the message instead of corrupting the state.
+                if cm.context().state_cache_audit {
+                    cm.machine()
+                        .state_tree()
+                        .audit_caches()
+                        .context("state cache audit failed")?;
+                }
+                Ok(ret)
             });
 
             let (res, machine) = match cm.finish() {
@@ -164,6 +261,9 @@ where
                 Ok(MachineExecRet {
                     result,
                     gas_used: res.gas_used,
+                    refund_credit: res.refund_credit,
+                    gas_breakdown: res.gas_breakdown,
+                    actor_gas: res.actor_gas,
                     backtrace: res.backtrace,
                     exec_trace: res.exec_trace,
                     events_root: res.events_root,
@@ -176,6 +276,9 @@ where
         let MachineExecRet {
             result: res,
             gas_used,
+            mut refund_credit,
+            gas_breakdown,
+            actor_gas,
             mut backtrace,
             exec_trace,
             events_root,
@@ -183,6 +286,7 @@ where
         } = ret;
 
         // Extract the exit code and build the result of the message application.
+        let mut timed_out = false;
         let receipt = match res {
             Ok(InvocationResult { exit_code, value }) => {
                 // Convert back into a top-level return "value". We throw away the codec here,
@@ -225,6 +329,19 @@ where
                     events_root,
                 }
             }
+            Err(ExecutionError::Timeout) => {
+                // The message was interrupted by its (node-local) execution timeout, which isn't a
+                // failure of the message itself: report it without halting, dumping the state, or
+                // charging the full gas limit.
+                timed_out = true;
+                refund_credit = 0;
+                Receipt {
+                    exit_code: ExitCode::SYS_ASSERTION_FAILED,
+                    return_data: Default::default(),
+                    gas_used,
+                    events_root,
+                }
+            }
             Err(ExecutionError::Fatal(err)) => {
                 // We produce a receipt with SYS_ASSERTION_FAILED exit code, and
                 // we consume the full gas amount so that, in case of a network-
@@ -234,6 +351,10 @@ where
                 // Note that we use backtrace#set_cause instead of backtrace#begin
                 // because we want to retain the propagation chain that we've
                 // accumulated on the way out.
+                let err = ErrorContext::default()
+                    .with_actor(msg.to)
+                    .with_epoch(self.context().epoch)
+                    .attach(err);
                 let err = err.context(format!(
                     "[from={}, to={}, seq={}, m={}, h={}]",
                     msg.from,
@@ -242,7 +363,28 @@ where
                     msg.method_num,
                     self.context().epoch,
                 ));
+
+                // When halting on fatal errors, revert the message (see `execute_dumping`), dump
+                // the pre-message state and bail instead of producing a receipt.
+                let dump_dir = self.options.dump_dir.clone();
+                if let Some(dir) = dump_dir {
+                    self.state_tree_mut().end_transaction(true)?;
+                    let dump = StateDump {
+                        epoch: self.context().epoch,
+                        state_root: self.state_tree_mut().flush()?,
+                        message: &msg,
+                        trace: &exec_trace,
+                        overlay: self.buffered_blocks(),
+                        error: &err,
+                    };
+                    return Err(match dump.write(&dir) {
+                        Ok(path) => err.context(format!("wrote state dump to {:?}", path)),
+                        Err(e) => err.context(format!("failed to write state dump: {:#}", e)),
+                    });
+                }
+
                 backtrace.set_cause(backtrace::Cause::from_fatal(err));
+                refund_credit = 0;
                 Receipt {
                     exit_code: ExitCode::SYS_ASSERTION_FAILED,
                     return_data: Default::default(),
@@ -252,23 +394,30 @@ where
             }
         };
 
-        let failure_info = if backtrace.is_empty() || receipt.exit_code.is_success() {
+        let failure_info = if timed_out {
+            self.engine_pool
+                .config()
+                .execution_timeout
+                .map(|limit| ApplyFailure::Timeout { limit })
+        } else if backtrace.is_empty() || receipt.exit_code.is_success() {
             None
         } else {
             Some(ApplyFailure::MessageBacktrace(backtrace))
         };
 
-        match apply_kind {
+        let mut ret = match apply_kind {
             ApplyKind::Explicit => self.finish_message(
                 sender_id,
                 msg,
                 receipt,
                 failure_info,
                 gas_cost,
+                refund_credit,
+                gas_breakdown,
                 exec_trace,
                 events,
             ),
-            ApplyKind::Implicit => Ok(ApplyRet {
+            ApplyKind::Implicit | ApplyKind::Cron => Ok(ApplyRet {
                 msg_receipt: receipt,
                 penalty: TokenAmount::zero(),
                 miner_tip: TokenAmount::zero(),
@@ -277,53 +426,198 @@ where
                 refund: TokenAmount::zero(),
                 gas_refund: 0,
                 gas_burned: 0,
+                refund_credit,
+                gas_breakdown,
+                gas_estimate: None,
+                actor_gas: None,
+                metrics: None,
                 failure_info,
                 exec_trace,
                 events,
             }),
+        }?;
+        ret.actor_gas = actor_gas;
+        ret.metrics = pre_metrics.map(|pre| self.metrics().since(&pre));
+
+        if let Some(overestimation) = self.options.gas_overestimation {
+            if ret.msg_receipt.exit_code.is_success() {
+                // Refunds are only applied once the message completes, so the message needs enough
+                // gas to run before they're deducted.
+                let gas_used = ret.msg_receipt.gas_used + ret.refund_credit;
+                let estimate = (gas_used as f64 * overestimation).ceil() as u64;
+                ret.gas_estimate = Some(estimate.min(BLOCK_GAS_LIMIT));
+            }
         }
-    }
 
-    /// Flush the state-tree to the underlying blockstore.
-    fn flush(&mut self) -> anyhow::Result<Cid> {
-        let k = (**self).flush()?;
-        Ok(k)
+        if let (Some(sink), Some(message_cid)) = (&self.options.event_sink, message_cid) {
+            if !ret.events.is_empty() {
+                sink.publish(self.context().epoch, &message_cid, &ret.events);
+            }
+        }
+
+        Ok(ret)
     }
-}
 
-impl<K> DefaultExecutor<K>
-where
-    K: Kernel,
-{
     /// Create a new [`DefaultExecutor`] for executing messages on the [`Machine`].
     pub fn new(
         engine_pool: EnginePool,
         machine: <K::CallManager as CallManager>::Machine,
     ) -> anyhow::Result<Self> {
-        // Skip preloading all builtin actors when testing.
-        #[cfg(not(any(test, feature = "testing")))]
-        {
-            // Preload any uncached modules.
-            // This interface works for now because we know all actor CIDs
-            // ahead of time, but with user-supplied code, we won't have that
-            // guarantee.
-            engine_pool.acquire().preload(
-                machine.blockstore(),
-                machine.builtin_actors().builtin_actor_codes(),
-            )?;
+        // Preload any uncached modules.
+        // This interface works for now because we know all actor CIDs
+        // ahead of time, but with user-supplied code, we won't have that
+        // guarantee.
+        if machine.context().preload_builtin_actors {
+            engine_pool
+                .acquire()
+                .preload_manifest(machine.blockstore(), machine.builtin_actors())?;
         }
         Ok(Self {
             engine_pool,
             machine: Some(machine),
+            options: ExecutionOptions::default(),
+            schedule: UpgradeSchedule::default(),
         })
     }
 
+    /// Sets the node-local [`ExecutionOptions`].
+    pub fn set_options(&mut self, options: ExecutionOptions) {
+        self.options = options;
+    }
+
+    /// Returns the node-local [`ExecutionOptions`].
+    pub fn options(&self) -> &ExecutionOptions {
+        &self.options
+    }
+
+    /// Sets the network's [`UpgradeSchedule`], applied when advancing epochs (see
+    /// [`DefaultExecutor::advance_epoch`]). This is consensus-critical.
+    pub fn set_upgrade_schedule(&mut self, schedule: UpgradeSchedule) {
+        self.schedule = schedule;
+    }
+
+    /// Returns the network's [`UpgradeSchedule`].
+    pub fn upgrade_schedule(&self) -> &UpgradeSchedule {
+        &self.schedule
+    }
+
     /// Consume consumes the executor and returns the Machine. If the Machine had
     /// been poisoned during execution, the Option will be None.
     pub fn into_machine(self) -> Option<<K::CallManager as CallManager>::Machine> {
         self.machine
     }
 
+    /// Applies a message without persisting its effects, for read-only queries (e.g., the
+    /// `StateCall` RPC).
+    ///
+    /// The message is applied as an implicit message from its sender, so neither its nonce nor
+    /// the sender's balance are checked, and gas is accounted for but not charged. A gas limit of
+    /// zero is lifted to the block gas limit. All state changes are reverted afterwards, events
+    /// aren't published to the event sink, and the [`ApplyHook`](super::ApplyHook)s aren't called.
+    pub fn execute_view(&mut self, mut msg: Message) -> Result<ApplyRet> {
+        if msg.gas_limit == 0 {
+            msg.gas_limit = BLOCK_GAS_LIMIT;
+        }
+
+        let view_options = ExecutionOptions {
+            dump_dir: None,
+            event_sink: None,
+            ..self.options.clone()
+        };
+        let options = std::mem::replace(&mut self.options, view_options);
+        self.state_tree_mut().begin_transaction();
+        let ret = self.apply_message(msg, ApplyKind::Implicit, 0);
+        self.options = options;
+
+        // The machine is poisoned if the message failed fatally.
+        if let Some(machine) = &mut self.machine {
+            machine.state_tree_mut().end_transaction(true)?;
+        }
+        ret
+    }
+
+    /// Commits the result of a message applied elsewhere (e.g., speculatively by the
+    /// [`ParallelExecutor`](super::ParallelExecutor)). `apply` replays its effects on this
+    /// executor's state, and the [`ApplyHook`](super::ApplyHook)s and the event sink see the
+    /// message as if this executor had applied it.
+    pub(super) fn commit_applied(
+        &mut self,
+        msg: &Message,
+        apply_kind: ApplyKind,
+        apply: impl FnOnce(&mut Self) -> Result<ApplyRet>,
+    ) -> Result<ApplyRet> {
+        let hooks = self.options.hooks.clone();
+        let epoch = self.context().epoch;
+        for hook in &hooks {
+            hook.pre_apply(epoch, msg, apply_kind);
+        }
+        let ret = apply(self)?;
+        if let Some(sink) = &self.options.event_sink {
+            if !ret.events.is_empty() {
+                sink.publish(epoch, &message_cid(msg)?, &ret.events);
+            }
+        }
+        for hook in &hooks {
+            hook.post_apply(epoch, msg, apply_kind, &ret);
+        }
+        Ok(ret)
+    }
+
+    /// Applies an implicit message sent by the node (e.g., the cron tick or block rewards, usually
+    /// from the system actor), as [`ApplyKind::Cron`]: the message isn't validated, the sender
+    /// isn't charged for gas, and execution is metered against the block gas limit.
+    pub fn apply_implicit_message(&mut self, msg: Message) -> Result<ApplyRet> {
+        self.execute_message(msg, ApplyKind::Cron, 0)
+    }
+
+    /// Applies a message to a read-only machine (see
+    /// [`MachineContext::read_only`](crate::machine::MachineContext::read_only)), or to estimate
+    /// its gas usage (see [`ExecutionOptions::estimate_gas`]), reverting all of its changes.
+    fn execute_reverted(
+        &mut self,
+        msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+    ) -> anyhow::Result<ApplyRet> {
+        // The state can't be dumped from inside a transaction.
+        let reverted_options = ExecutionOptions {
+            dump_dir: None,
+            ..self.options.clone()
+        };
+        let options = std::mem::replace(&mut self.options, reverted_options);
+        self.state_tree_mut().begin_transaction();
+        let ret = self.apply_message(msg, apply_kind, raw_length);
+        self.options = options;
+
+        // The machine is poisoned if the message failed fatally.
+        if let Some(machine) = &mut self.machine {
+            machine.state_tree_mut().end_transaction(true)?;
+        }
+        ret
+    }
+
+    /// Applies a message when halting on fatal errors, in a transaction that's committed unless
+    /// the message fails. The pre-message state is only flushed (by `apply_message`) if the
+    /// message fails fatally. See [`ExecutionOptions::halt_and_dump`].
+    fn execute_dumping(
+        &mut self,
+        msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+    ) -> anyhow::Result<ApplyRet> {
+        self.state_tree_mut().begin_transaction();
+        let ret = self.apply_message(msg, apply_kind, raw_length);
+
+        // The transaction was already reverted if the state was dumped, and the machine is
+        // poisoned if the message failed fatally otherwise.
+        if let Some(machine) = &mut self.machine {
+            if machine.state_tree().in_transaction() {
+                machine.state_tree_mut().end_transaction(ret.is_err())?;
+            }
+        }
+        ret
+    }
+
     // TODO: The return type here is very strange because we have three cases:
     //  1. Continue: Return sender ID, & gas.
     //  2. Short-circuit: Return ApplyRet.
@@ -335,15 +629,17 @@ where
         apply_kind: ApplyKind,
         raw_length: usize,
     ) -> Result<StdResult<(ActorID, TokenAmount, GasCharge), ApplyRet>> {
-        msg.check().or_fatal()?;
+        if apply_kind != ApplyKind::Cron {
+            msg.check().or_fatal()?;
+        }
 
         // TODO We don't like having price lists _inside_ the FVM, but passing
         //  these across the boundary is also a no-go.
         let pl = &self.context().price_list;
 
         let (inclusion_cost, miner_penalty_amount) = match apply_kind {
-            ApplyKind::Implicit => (
-                GasCharge::new("none", Gas::zero(), Gas::zero()),
+            ApplyKind::Implicit | ApplyKind::Cron => (
+                GasCharge::new("message/inclusion", Gas::zero(), Gas::zero()),
                 Default::default(),
             ),
             ApplyKind::Explicit => {
@@ -353,8 +649,10 @@ where
                 // Verify the cost of the message is not over the message gas limit.
                 if inclusion_total > msg.gas_limit {
                     return Ok(Err(ApplyRet::prevalidation_fail(
-                        ExitCode::SYS_OUT_OF_GAS,
-                        format!("Out of gas ({} > {})", inclusion_total, msg.gas_limit),
+                        PreValidationFailure::InclusionOutOfGas {
+                            inclusion_cost: inclusion_total,
+                            gas_limit: msg.gas_limit,
+                        },
                         &self.context().base_fee * inclusion_total,
                     )));
                 }
@@ -364,6 +662,24 @@ where
             }
         };
 
+        if let (ApplyKind::Explicit, Some(valid_until_epoch)) = (apply_kind, msg.valid_until_epoch)
+        {
+            if self.context().network.network_version < MESSAGE_EXPIRATION_VERSION {
+                return Ok(Err(ApplyRet::prevalidation_fail(
+                    PreValidationFailure::ExpirationNotSupported,
+                    miner_penalty_amount,
+                )));
+            }
+            let epoch = self.context().epoch;
+            if msg.is_expired(epoch) {
+                return Ok(Err(ApplyRet::expired(
+                    valid_until_epoch,
+                    epoch,
+                    miner_penalty_amount,
+                )));
+            }
+        }
+
         // Load sender actor state.
         let sender_id = match self
             .state_tree()
@@ -373,14 +689,13 @@ where
             Some(id) => id,
             None => {
                 return Ok(Err(ApplyRet::prevalidation_fail(
-                    ExitCode::SYS_SENDER_INVALID,
-                    "Sender invalid",
+                    PreValidationFailure::SenderNotFound,
                     miner_penalty_amount,
                 )));
             }
         };
 
-        if apply_kind == ApplyKind::Implicit {
+        if apply_kind != ApplyKind::Explicit {
             return Ok(Ok((sender_id, TokenAmount::zero(), inclusion_cost)));
         }
 
@@ -392,8 +707,7 @@ where
             Some(act) => act,
             None => {
                 return Ok(Err(ApplyRet::prevalidation_fail(
-                    ExitCode::SYS_SENDER_INVALID,
-                    "Sender invalid",
+                    PreValidationFailure::SenderNotFound,
                     miner_penalty_amount,
                 )));
             }
@@ -421,8 +735,7 @@ where
 
         if !sender_is_valid {
             return Ok(Err(ApplyRet::prevalidation_fail(
-                ExitCode::SYS_SENDER_INVALID,
-                "Send not from valid sender",
+                PreValidationFailure::InvalidSender,
                 miner_penalty_amount,
             )));
         };
@@ -430,26 +743,31 @@ where
         // Check sequence is correct
         if msg.sequence != sender_state.sequence {
             return Ok(Err(ApplyRet::prevalidation_fail(
-                ExitCode::SYS_SENDER_STATE_INVALID,
-                format!(
-                    "Actor sequence invalid: {} != {}",
-                    msg.sequence, sender_state.sequence
-                ),
+                PreValidationFailure::NonceMismatch {
+                    expected: sender_state.sequence,
+                    actual: msg.sequence,
+                },
                 miner_penalty_amount,
             )));
         };
 
         sender_state.sequence += 1;
 
+        // When estimating gas, the gas limit was lifted to the block gas limit and the changes
+        // are reverted, so the sender isn't charged for gas (see `finish_message`).
+        if self.options.gas_overestimation.is_some() {
+            self.state_tree_mut().set_actor(sender_id, sender_state);
+            return Ok(Ok((sender_id, TokenAmount::zero(), inclusion_cost)));
+        }
+
         // Ensure from actor has enough balance to cover the gas cost of the message.
         let gas_cost: TokenAmount = msg.gas_fee_cap.clone() * msg.gas_limit;
         if sender_state.balance < gas_cost {
             return Ok(Err(ApplyRet::prevalidation_fail(
-                ExitCode::SYS_SENDER_STATE_INVALID,
-                format!(
-                    "Actor balance less than needed: {} < {}",
-                    sender_state.balance, gas_cost
-                ),
+                PreValidationFailure::InsufficientFunds {
+                    required: gas_cost,
+                    balance: sender_state.balance,
+                },
                 miner_penalty_amount,
             )));
         }
@@ -470,10 +788,16 @@ where
         receipt: Receipt,
         failure_info: Option<ApplyFailure>,
         gas_cost: TokenAmount,
+        refund_credit: u64,
+        gas_breakdown: GasBreakdown,
         exec_trace: ExecutionTrace,
         events: Vec<StampedEvent>,
     ) -> anyhow::Result<ApplyRet> {
         // NOTE: we don't support old network versions in the FVM, so we always burn.
+        let strategy: &dyn GasOutputsStrategy = match &self.context().gas_outputs {
+            Some(strategy) => &**strategy,
+            None => &FilecoinGasOutputs,
+        };
         let GasOutputs {
             base_fee_burn,
             over_estimation_burn,
@@ -482,14 +806,19 @@ where
             refund,
             gas_refund,
             gas_burned,
-        } = GasOutputs::compute(
+            refund_credit,
+        } = strategy.compute(
             receipt.gas_used,
+            refund_credit,
             msg.gas_limit,
             &self.context().base_fee,
             &msg.gas_fee_cap,
             &msg.gas_premium,
         );
 
+        // When estimating gas, the sender wasn't charged (see `preflight_message`), so the fees
+        // are only reported.
+        let charged = self.options.gas_overestimation.is_none();
         let mut transfer_to_actor = |addr: ActorID, amt: &TokenAmount| -> anyhow::Result<()> {
             if amt.is_negative() {
                 return Err(anyhow!("attempted to transfer negative value into actor"));
@@ -498,24 +827,28 @@ where
                 return Ok(());
             }
 
+            // Not tracked as accesses: the sender was already updated when charging for gas, and
+            // fee deposits commute (see the `ParallelExecutor`).
             self.state_tree_mut()
-                .mutate_actor(addr, |act| act.deposit_funds(amt).or_fatal())
+                .untracked(|st| st.mutate_actor(addr, |act| act.deposit_funds(amt).or_fatal()))
                 .context("failed to lookup actor for transfer")?;
             Ok(())
         };
 
-        transfer_to_actor(BURNT_FUNDS_ACTOR_ID, &base_fee_burn)?;
+        if charged {
+            transfer_to_actor(BURNT_FUNDS_ACTOR_ID, &base_fee_burn)?;
 
-        transfer_to_actor(REWARD_ACTOR_ID, &miner_tip)?;
+            transfer_to_actor(REWARD_ACTOR_ID, &miner_tip)?;
 
-        transfer_to_actor(BURNT_FUNDS_ACTOR_ID, &over_estimation_burn)?;
+            transfer_to_actor(BURNT_FUNDS_ACTOR_ID, &over_estimation_burn)?;
 
-        // refund unused gas
-        transfer_to_actor(sender_id, &refund)?;
+            // refund unused gas
+            transfer_to_actor(sender_id, &refund)?;
 
-        if (&base_fee_burn + &over_estimation_burn + &refund + &miner_tip) != gas_cost {
-            // Sanity check. This could be a fatal error.
-            return Err(anyhow!("Gas handling math is wrong"));
+            if (&base_fee_burn + &over_estimation_burn + &refund + &miner_tip) != gas_cost {
+                // Sanity check. This could be a fatal error.
+                return Err(anyhow!("Gas handling math is wrong"));
+            }
         }
         Ok(ApplyRet {
             msg_receipt: receipt,
@@ -526,6 +859,11 @@ where
             refund,
             gas_refund,
             gas_burned,
+            refund_credit,
+            gas_breakdown,
+            gas_estimate: None,
+            actor_gas: None,
+            metrics: None,
             failure_info,
             exec_trace,
             events,
@@ -0,0 +1,155 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! State dumps, written when a message fails with a fatal error.
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+use fvm_ipld_encoding::{to_vec, RawBytes, DAG_CBOR};
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::message::Message;
+
+use super::{ApplyHook, EventSink};
+use crate::trace::ExecutionTrace;
+
+/// Node-local options controlling how the [`DefaultExecutor`](super::DefaultExecutor) executes
+/// messages. None of these options are consensus-critical.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionOptions {
+    pub(super) dump_dir: Option<PathBuf>,
+    pub(super) event_sink: Option<Arc<dyn EventSink>>,
+    pub(super) gas_overestimation: Option<f64>,
+    pub(super) collect_metrics: bool,
+    pub(super) hooks: Vec<Arc<dyn ApplyHook>>,
+}
+
+impl ExecutionOptions {
+    /// The default options: fatal errors are reported in the message receipt.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Halt on the first fatal error, writing a state dump to a new directory under `dir` and
+    /// returning an error instead of a receipt.
+    ///
+    /// The dump directory is named `<epoch>-<message CID>` and contains:
+    ///
+    /// - `state_root`: the state root before the message was applied.
+    /// - `message.cbor`: the CBOR-encoded message.
+    /// - `trace.txt`: the (partial) execution trace, if tracing is enabled.
+    /// - `overlay.cbor`: the CBOR-encoded list of `(CID, block)` pairs buffered by the machine and
+    ///   not yet flushed to the client's blockstore.
+    /// - `error.txt`: the fatal error.
+    ///
+    /// Enabling this mode applies each message in a state-tree transaction. The state-tree (but not
+    /// the blockstore) is only flushed, to record the state root, when a message fails fatally.
+    pub fn halt_and_dump(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dump_dir: Some(dir.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Publish the events emitted by each message to the given [`EventSink`].
+    pub fn with_event_sink(mut self, sink: Arc<dyn EventSink>) -> Self {
+        self.event_sink = Some(sink);
+        self
+    }
+
+    /// Estimate gas instead of enforcing each message's gas limit. Messages are executed with the
+    /// block gas limit and, if they succeed, the gas used multiplied by `overestimation` (e.g.,
+    /// 1.25) is reported as a recommended gas limit in
+    /// [`ApplyRet::gas_estimate`](super::ApplyRet::gas_estimate). Overestimation factors below 1
+    /// are treated as 1.
+    ///
+    /// Each message is applied in a transaction that's always reverted, so estimating leaves the
+    /// state untouched. The sender isn't charged for gas, and so needn't be able to cover the
+    /// `gas_fee_cap` for the block gas limit; the fees are still reported in the [`ApplyRet`].
+    ///
+    /// [`ApplyRet`]: super::ApplyRet
+    pub fn estimate_gas(mut self, overestimation: f64) -> Self {
+        self.gas_overestimation = Some(overestimation.max(1.0));
+        self
+    }
+
+    /// Report the work done by the machine for each message in
+    /// [`ApplyRet::metrics`](super::ApplyRet::metrics). See
+    /// [`Machine::metrics`](crate::machine::Machine::metrics).
+    pub fn with_metrics(mut self) -> Self {
+        self.collect_metrics = true;
+        self
+    }
+
+    /// Call the given [`ApplyHook`] before and after each message is applied, after any hooks
+    /// added before it.
+    pub fn with_hook(mut self, hook: Arc<dyn ApplyHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Returns the directory state dumps are written to, if halting on fatal errors.
+    pub fn dump_dir(&self) -> Option<&Path> {
+        self.dump_dir.as_deref()
+    }
+
+    /// Returns the [`EventSink`] events are published to, if any.
+    pub fn event_sink(&self) -> Option<&Arc<dyn EventSink>> {
+        self.event_sink.as_ref()
+    }
+
+    /// Returns the gas overestimation factor, if estimating gas.
+    pub fn gas_overestimation(&self) -> Option<f64> {
+        self.gas_overestimation
+    }
+
+    /// Returns true if the work done for each message is reported.
+    pub fn collect_metrics(&self) -> bool {
+        self.collect_metrics
+    }
+
+    /// Returns the [`ApplyHook`]s called around each message, in order.
+    pub fn hooks(&self) -> &[Arc<dyn ApplyHook>] {
+        &self.hooks
+    }
+}
+
+/// The state required to reproduce a fatal error.
+pub(super) struct StateDump<'a> {
+    pub epoch: ChainEpoch,
+    pub state_root: Cid,
+    pub message: &'a Message,
+    pub trace: &'a ExecutionTrace,
+    pub overlay: Vec<(Cid, Vec<u8>)>,
+    pub error: &'a anyhow::Error,
+}
+
+impl StateDump<'_> {
+    /// Writes the dump to a new directory under `dir`, returning the directory's path.
+    pub fn write(self, dir: &Path) -> anyhow::Result<PathBuf> {
+        let message = to_vec(self.message)?;
+        let message_cid = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&message));
+        let dir = dir.join(format!("{}-{}", self.epoch, message_cid));
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create state dump directory {:?}", dir))?;
+
+        let overlay: Vec<(Cid, RawBytes)> = self
+            .overlay
+            .into_iter()
+            .map(|(k, v)| (k, RawBytes::new(v)))
+            .collect();
+        let files = [
+            ("state_root", self.state_root.to_string().into_bytes()),
+            ("message.cbor", message),
+            ("trace.txt", format!("{:#?}", self.trace).into_bytes()),
+            ("overlay.cbor", to_vec(&overlay)?),
+            ("error.txt", format!("{:?}", self.error).into_bytes()),
+        ];
+        for (name, data) in files {
+            std::fs::write(dir.join(name), data)
+                .with_context(|| format!("failed to write {} to state dump {:?}", name, dir))?;
+        }
+        Ok(dir)
+    }
+}
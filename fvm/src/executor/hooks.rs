@@ -0,0 +1,35 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Hooks called around the application of each message.
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::message::Message;
+
+use super::{ApplyKind, ApplyRet};
+
+/// A hook called before and after each message is applied, configured with
+/// [`ExecutionOptions::with_hook`](super::ExecutionOptions::with_hook). Hooks let the embedder
+/// observe message application (e.g., for custom accounting, mempool feedback, or logging)
+/// without wrapping the executor.
+///
+/// Hooks are called by the [`DefaultExecutor`](super::DefaultExecutor) in the order in which they
+/// were added. They aren't called for views
+/// ([`DefaultExecutor::execute_view`](super::DefaultExecutor::execute_view)), and
+/// [`ApplyHook::post_apply`] isn't called if applying the message fails with an error (instead of
+/// a receipt).
+pub trait ApplyHook: Send + Sync + 'static {
+    /// Called with a message about to be applied at the given epoch.
+    fn pre_apply(&self, epoch: ChainEpoch, msg: &Message, apply_kind: ApplyKind) {
+        let _ = (epoch, msg, apply_kind);
+    }
+
+    /// Called with a message applied at the given epoch, and the result of applying it.
+    fn post_apply(&self, epoch: ChainEpoch, msg: &Message, apply_kind: ApplyKind, ret: &ApplyRet) {
+        let _ = (epoch, msg, apply_kind, ret);
+    }
+}
+
+impl std::fmt::Debug for dyn ApplyHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ApplyHook")
+    }
+}
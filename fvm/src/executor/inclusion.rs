@@ -0,0 +1,148 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Message inclusion proofs, for light clients that need to verify that a message was included in
+//! a block without downloading the block's messages.
+use anyhow::{anyhow, Context};
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+use fvm_ipld_amt::{AmtProof, Amtv0};
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::tuple::*;
+use fvm_ipld_encoding::{to_vec, CborStore, DAG_CBOR};
+
+/// The messages included in a block, referenced by the `messages` field of the block header.
+///
+/// Both message lists are stored as legacy (v0) AMTs of message CIDs: unsigned message CIDs for
+/// BLS messages, and signed message CIDs for secp256k1 messages.
+#[derive(Serialize_tuple, Deserialize_tuple, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxMeta {
+    pub bls_message_root: Cid,
+    pub secp_message_root: Cid,
+}
+
+/// The kind of message (and therefore the message list) a [`MessageInclusionProof`] refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    Bls,
+    Secp,
+}
+
+impl TxMeta {
+    /// Builds and stores the message AMTs and the [`TxMeta`] for a block with the given (ordered)
+    /// BLS and secp256k1 message CIDs, returning the [`TxMeta`] and its CID.
+    pub fn build<BS: Blockstore>(
+        bs: &BS,
+        bls_messages: &[Cid],
+        secp_messages: &[Cid],
+    ) -> anyhow::Result<(Cid, TxMeta)> {
+        let bls_message_root = Amtv0::new_from_iter(bs, bls_messages)
+            .context("failed to build the bls message AMT")?;
+        let secp_message_root = Amtv0::new_from_iter(bs, secp_messages)
+            .context("failed to build the secp message AMT")?;
+        let meta = TxMeta {
+            bls_message_root,
+            secp_message_root,
+        };
+        let cid = bs
+            .put_cbor(&meta, Code::Blake2b256)
+            .context("failed to store the message metadata")?;
+        Ok((cid, meta))
+    }
+
+    /// Returns the root of the message list of the given kind.
+    pub fn root(&self, kind: MessageKind) -> &Cid {
+        match kind {
+            MessageKind::Bls => &self.bls_message_root,
+            MessageKind::Secp => &self.secp_message_root,
+        }
+    }
+
+    /// Generates a proof that the message at the given index of the given message list is included
+    /// in the block with this [`TxMeta`]. The message AMTs must be in the blockstore.
+    pub fn prove<BS: Blockstore>(
+        &self,
+        bs: &BS,
+        kind: MessageKind,
+        index: u64,
+    ) -> anyhow::Result<MessageInclusionProof> {
+        let amt =
+            Amtv0::<Cid, _>::load(self.root(kind), bs).context("failed to load the message AMT")?;
+        Ok(MessageInclusionProof {
+            tx_meta: *self,
+            kind,
+            proof: amt.prove(index)?,
+        })
+    }
+}
+
+/// A proof that a message is included in a block, verifiable against the `messages` CID in the
+/// block header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageInclusionProof {
+    /// The block's message metadata.
+    pub tx_meta: TxMeta,
+    /// The message list the message is included in.
+    pub kind: MessageKind,
+    /// The proof of the message CID within the message list.
+    pub proof: AmtProof,
+}
+
+impl MessageInclusionProof {
+    /// The index of the message within its message list.
+    pub fn index(&self) -> u64 {
+        self.proof.index
+    }
+
+    /// Verifies the proof against the `messages` CID of a block header, returning the CID of the
+    /// included message.
+    pub fn verify(&self, messages: &Cid) -> anyhow::Result<Cid> {
+        let meta = to_vec(&self.tx_meta)?;
+        if Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&meta)) != *messages {
+            return Err(anyhow!("message metadata does not match {messages}"));
+        }
+        self.proof
+            .verify_v0(self.tx_meta.root(self.kind))?
+            .ok_or_else(|| anyhow!("no message at index {}", self.index()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_ipld_encoding::IPLD_RAW;
+
+    use super::*;
+
+    fn message_cid(i: u8) -> Cid {
+        Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(&[i]))
+    }
+
+    #[test]
+    fn message_inclusion() {
+        let bs = MemoryBlockstore::default();
+        let bls: Vec<_> = (0..20).map(message_cid).collect();
+        let secp: Vec<_> = (20..25).map(message_cid).collect();
+        let (messages, meta) = TxMeta::build(&bs, &bls, &secp).unwrap();
+
+        for (kind, msgs) in [(MessageKind::Bls, &bls), (MessageKind::Secp, &secp)] {
+            for (i, msg) in msgs.iter().enumerate() {
+                let proof = meta.prove(&bs, kind, i as u64).unwrap();
+                assert_eq!(proof.verify(&messages).unwrap(), *msg);
+            }
+        }
+
+        // Messages not in the block.
+        let proof = meta.prove(&bs, MessageKind::Secp, 5).unwrap();
+        assert!(proof.verify(&messages).is_err());
+
+        // Proofs for other blocks.
+        let (other, _) = TxMeta::build(&bs, &bls, &[]).unwrap();
+        let proof = meta.prove(&bs, MessageKind::Bls, 0).unwrap();
+        assert!(proof.verify(&other).is_err());
+
+        // Proofs against the wrong message list.
+        let mut proof = meta.prove(&bs, MessageKind::Bls, 0).unwrap();
+        proof.kind = MessageKind::Secp;
+        assert!(proof.verify(&messages).is_err());
+    }
+}
@@ -1,22 +1,42 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+mod block;
+mod chained;
 mod default;
+mod dump;
+mod hooks;
+mod inclusion;
+mod parallel;
+mod sink;
 mod threaded;
+mod upgrade;
 
 use std::fmt::Display;
+use std::time::Duration;
 
+pub use block::{BlockMessages, SignedMessage};
+pub use chained::{ChainedExecutor, TipsetMessage, TipsetRet};
 use cid::Cid;
 pub use default::DefaultExecutor;
+pub use dump::ExecutionOptions;
 use fvm_ipld_encoding::RawBytes;
+use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
 use fvm_shared::event::StampedEvent;
 use fvm_shared::message::Message;
 use fvm_shared::receipt::Receipt;
+use fvm_shared::version::NetworkVersion;
+pub use hooks::ApplyHook;
+pub use inclusion::{MessageInclusionProof, MessageKind, TxMeta};
 use num_traits::Zero;
+pub use parallel::ParallelExecutor;
+pub use sink::EventSink;
 pub use threaded::ThreadedExecutor;
 
 use crate::call_manager::Backtrace;
+use crate::gas::{ActorGasUsage, GasBreakdown};
+use crate::machine::ExecutionMetrics;
 use crate::trace::ExecutionTrace;
 use crate::Kernel;
 
@@ -51,10 +71,77 @@ pub trait Executor {
 pub enum ApplyFailure {
     /// The backtrace from a message failure.
     MessageBacktrace(Backtrace),
-    /// A message describing a pre-validation failure.
-    PreValidation(String),
+    /// The message failed pre-validation. The miner penalty is reported in [`ApplyRet::penalty`].
+    PreValidation(PreValidationFailure),
+    /// The message expired (see [`Message::valid_until_epoch`]).
+    Expired {
+        valid_until_epoch: ChainEpoch,
+        epoch: ChainEpoch,
+    },
+    /// The message ran for longer than the engine's execution timeout (see
+    /// [`EngineConfig::execution_timeout`](crate::engine::EngineConfig::execution_timeout)), and
+    /// was interrupted. Its changes were reverted.
+    ///
+    /// The timeout is node-local and not deterministic, so the receipt of a timed out message is
+    /// not consensus-valid: it must never be included in (or compared against) the chain.
+    Timeout { limit: Duration },
+}
+
+/// The reason a message failed pre-validation, see [`ApplyFailure::PreValidation`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum PreValidationFailure {
+    /// The message couldn't be serialized (e.g., to compute its CID for the event sink).
+    #[error("Message serialization failed: {0}")]
+    Serialization(String),
+    /// The message's gas limit doesn't cover its inclusion cost.
+    #[error("Out of gas ({inclusion_cost} > {gas_limit})")]
+    InclusionOutOfGas { inclusion_cost: u64, gas_limit: u64 },
+    /// The message has an expiration, but the network version doesn't support expirations.
+    #[error(
+        "Message expiration not supported before network version {}",
+        MESSAGE_EXPIRATION_VERSION
+    )]
+    ExpirationNotSupported,
+    /// The sender doesn't exist.
+    #[error("Sender invalid")]
+    SenderNotFound,
+    /// The sender isn't an account, an Ethereum account, or a placeholder with an address in the
+    /// EAM's namespace.
+    #[error("Send not from valid sender")]
+    InvalidSender,
+    /// The message's nonce isn't the sender's next nonce.
+    #[error("Actor sequence invalid: {actual} != {expected}")]
+    NonceMismatch { expected: u64, actual: u64 },
+    /// The sender's balance doesn't cover the message's gas fee cap times its gas limit.
+    #[error("Actor balance less than needed: {balance} < {required}")]
+    InsufficientFunds {
+        required: TokenAmount,
+        balance: TokenAmount,
+    },
 }
 
+impl PreValidationFailure {
+    /// Returns the exit code of the receipt of a message failing pre-validation for this reason.
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            PreValidationFailure::InclusionOutOfGas { .. } => ExitCode::SYS_OUT_OF_GAS,
+            PreValidationFailure::Serialization(_)
+            | PreValidationFailure::SenderNotFound
+            | PreValidationFailure::InvalidSender => ExitCode::SYS_SENDER_INVALID,
+            // Expired messages are also rejected with `SYS_SENDER_STATE_INVALID`, see
+            // `ApplyRet::expired`.
+            PreValidationFailure::ExpirationNotSupported => ExitCode::SYS_SENDER_STATE_INVALID,
+            PreValidationFailure::NonceMismatch { .. }
+            | PreValidationFailure::InsufficientFunds { .. } => ExitCode::SYS_SENDER_STATE_INVALID,
+        }
+    }
+}
+
+/// The first network version honouring message expirations. Before, messages that expire fail
+/// pre-validation regardless of the epoch. This is the next, unreleased network version: until
+/// it's scheduled, expirations are only usable on test networks.
+pub const MESSAGE_EXPIRATION_VERSION: NetworkVersion = NetworkVersion::V22;
+
 impl Display for ApplyFailure {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -62,8 +149,21 @@ impl Display for ApplyFailure {
                 writeln!(f, "message failed with backtrace:")?;
                 write!(f, "{}", bt)?;
             }
-            ApplyFailure::PreValidation(msg) => {
-                writeln!(f, "pre-validation failed: {}", msg)?;
+            ApplyFailure::PreValidation(reason) => {
+                writeln!(f, "pre-validation failed: {}", reason)?;
+            }
+            ApplyFailure::Expired {
+                valid_until_epoch,
+                epoch,
+            } => {
+                writeln!(
+                    f,
+                    "message expired: valid until epoch {}, executed at epoch {}",
+                    valid_until_epoch, epoch
+                )?;
+            }
+            ApplyFailure::Timeout { limit } => {
+                writeln!(f, "message timed out after {:?}", limit)?;
             }
         }
         Ok(())
@@ -86,22 +186,60 @@ pub struct ApplyRet {
     pub refund: TokenAmount,
     pub gas_refund: u64,
     pub gas_burned: u64,
+    /// The gas refunded for releasing state (e.g., deleting actors), already deducted from the
+    /// receipt's `gas_used`. Refunds are capped at a fraction of the gas used, see
+    /// [`PriceList::max_refund`](crate::gas::PriceList::max_refund).
+    pub refund_credit: u64,
+    /// The gas used by the message, keyed by charge name (syscall, wasm execution, memory, etc.).
+    /// Only recorded if
+    /// [`MachineContext::gas_breakdown`](crate::machine::MachineContext::gas_breakdown) is enabled
+    /// (tracing isn't required).
+    pub gas_breakdown: GasBreakdown,
+    /// The recommended gas limit for the message, if it succeeded while estimating gas (see
+    /// [`ExecutionOptions::estimate_gas`]).
+    pub gas_estimate: Option<u64>,
+    /// The gas used by each actor and method in the call tree, excluding nested calls, if tracing
+    /// is enabled. Gas used outside of any call (e.g., message inclusion) isn't attributed.
+    pub actor_gas: Option<ActorGasUsage>,
+    /// The work done by the machine while applying the message, if requested with
+    /// [`ExecutionOptions::with_metrics`]. Not recorded for messages that fail pre-validation.
+    pub metrics: Option<ExecutionMetrics>,
 
     /// Additional failure information for debugging, if any.
     pub failure_info: Option<ApplyFailure>,
     /// Execution trace information, for debugging.
     pub exec_trace: ExecutionTrace,
-    /// Events generated while applying the message.
+    /// Events generated while applying the message, in order. The root of their AMT is already
+    /// computed in the receipt's `events_root`.
     pub events: Vec<StampedEvent>,
 }
 
 impl ApplyRet {
+    /// Returns the outcome of a message failing pre-validation for the given reason.
     #[inline]
     pub fn prevalidation_fail(
-        code: ExitCode,
-        message: impl Into<String>,
+        reason: PreValidationFailure,
+        miner_penalty: TokenAmount,
+    ) -> ApplyRet {
+        let code = reason.exit_code();
+        Self::rejected(code, ApplyFailure::PreValidation(reason), miner_penalty)
+    }
+
+    /// Returns the outcome of an expired message.
+    pub fn expired(
+        valid_until_epoch: ChainEpoch,
+        epoch: ChainEpoch,
         miner_penalty: TokenAmount,
     ) -> ApplyRet {
+        let failure = ApplyFailure::Expired {
+            valid_until_epoch,
+            epoch,
+        };
+        Self::rejected(ExitCode::SYS_SENDER_STATE_INVALID, failure, miner_penalty)
+    }
+
+    /// Returns the outcome of a message that wasn't applied.
+    fn rejected(code: ExitCode, failure: ApplyFailure, miner_penalty: TokenAmount) -> ApplyRet {
         ApplyRet {
             msg_receipt: Receipt {
                 exit_code: code,
@@ -116,7 +254,12 @@ impl ApplyRet {
             refund: TokenAmount::zero(),
             gas_refund: 0,
             gas_burned: 0,
-            failure_info: Some(ApplyFailure::PreValidation(message.into())),
+            refund_credit: 0,
+            gas_breakdown: GasBreakdown::new(),
+            gas_estimate: None,
+            actor_gas: None,
+            metrics: None,
+            failure_info: Some(failure),
             exec_trace: vec![],
             events: vec![],
         }
@@ -129,8 +272,14 @@ impl ApplyRet {
 /// consumed.
 /// 2. Implicit messages may come from any actor, ignore the nonce, and charge no gas (but still
 /// account for it).
+/// 3. Cron messages are implicit messages applied by the node itself (e.g., the cron tick at the
+/// end of each tipset, see [`DefaultExecutor::apply_implicit_message`]). They aren't validated,
+/// and are metered against the block gas limit instead of their own gas limit. Like implicit
+/// messages, cron messages from senders that don't exist fail with a receipt.
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
+#[non_exhaustive]
 pub enum ApplyKind {
     Explicit,
     Implicit,
+    Cron,
 }
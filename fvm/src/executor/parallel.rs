@@ -0,0 +1,232 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Optimistically parallel execution of the messages of a tipset.
+use std::collections::BTreeSet;
+use std::ops::{Deref, DerefMut};
+
+use anyhow::Context as _;
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::message::Message;
+use fvm_shared::ActorID;
+use lazy_static::lazy_static;
+use num_traits::Zero;
+use rayon::prelude::*;
+
+use super::{ApplyKind, ApplyRet, DefaultExecutor, ExecutionOptions, Executor};
+use crate::kernel::{ClassifyResult, Kernel};
+use crate::machine::{Machine, MachineContext, BURNT_FUNDS_ACTOR_ID, REWARD_ACTOR_ID};
+use crate::state_tree::{ActorState, StateAccesses};
+
+lazy_static! {
+    static ref SPECULATION_POOL: rayon::ThreadPool = rayon::ThreadPoolBuilder::new()
+        .thread_name(|i| format!("fvm-speculate-{i}"))
+        // Message execution needs a 64MiB stack, see the `ThreadedExecutor`.
+        .stack_size(64 << 20)
+        .build()
+        .expect("failed to create the speculation thread pool");
+}
+
+/// Every message deposits fees into these actors, so any other access to them conflicts.
+const FEE_ACTORS: [ActorID; 2] = [BURNT_FUNDS_ACTOR_ID, REWARD_ACTOR_ID];
+
+/// An executor that executes explicit messages concurrently, committing the results in order.
+///
+/// [`ParallelExecutor::execute_messages`] first executes each message speculatively, on its own
+/// executor starting from the current state, tracking the actors it reads and writes. The
+/// speculative results are then committed in order: a message that accessed an actor written by
+/// an earlier message (or to which fees are paid) is re-executed serially on the wrapped executor
+/// instead. The results are identical to executing the messages one after another.
+///
+/// Speculative executors are created by the given function, from the context of the wrapped
+/// executor with its initial state root set to the current state. They must use the same
+/// blockstore, externs, and configuration as the wrapped executor. They run with the wrapped
+/// executor's [`ExecutionOptions`], except that they don't dump state, publish events or call
+/// hooks: committed results go through the wrapped executor, which does.
+pub struct ParallelExecutor<K: Kernel, F> {
+    executor: DefaultExecutor<K>,
+    speculate: F,
+}
+
+/// The outcome of executing a message on its own.
+struct Speculation {
+    ret: ApplyRet,
+    accesses: StateAccesses,
+    /// The final states of the actors written, None if deleted.
+    writes: Vec<(ActorID, Option<ActorState>)>,
+    blocks: Vec<(Cid, Vec<u8>)>,
+}
+
+impl<K, F> ParallelExecutor<K, F>
+where
+    K: Kernel,
+    F: Fn(&MachineContext) -> anyhow::Result<DefaultExecutor<K>> + Sync,
+{
+    /// Wraps an executor, creating speculative executors with the given function.
+    pub fn new(executor: DefaultExecutor<K>, speculate: F) -> Self {
+        Self {
+            executor,
+            speculate,
+        }
+    }
+
+    /// Returns the wrapped executor.
+    pub fn into_inner(self) -> DefaultExecutor<K> {
+        self.executor
+    }
+
+    /// Executes the explicit messages of a tipset (with their raw lengths) in order, returning
+    /// their results. This flushes the state first.
+    pub fn execute_messages(
+        &mut self,
+        messages: Vec<(Message, usize)>,
+    ) -> anyhow::Result<Vec<ApplyRet>> {
+        let speculations = self.speculate_all(&messages)?;
+
+        let mut written: BTreeSet<ActorID> = FEE_ACTORS.into_iter().collect();
+        let mut rets = Vec::with_capacity(messages.len());
+        let mut reexecuted = 0;
+        for ((msg, raw_length), speculation) in messages.into_iter().zip(speculations) {
+            let ret = match speculation {
+                Ok(spec) if !spec.accesses.conflicts_with(&written) => {
+                    written.extend(spec.accesses.writes.iter().copied());
+                    self.executor
+                        .commit_applied(&msg, ApplyKind::Explicit, |executor| {
+                            Self::commit(executor, spec)
+                        })?
+                }
+                _ => {
+                    reexecuted += 1;
+                    self.executor.state_tree_mut().track_accesses();
+                    let ret = self
+                        .executor
+                        .execute_message(msg, ApplyKind::Explicit, raw_length);
+                    let accesses = self.executor.state_tree_mut().take_accesses();
+                    written.extend(accesses.into_iter().flat_map(|a| a.writes));
+                    ret?
+                }
+            };
+            rets.push(ret);
+        }
+        log::debug!(
+            "executed {} messages in parallel, re-executed {} serially",
+            rets.len(),
+            reexecuted
+        );
+        Ok(rets)
+    }
+
+    /// Executes each message on its own speculative executor, concurrently.
+    fn speculate_all(
+        &mut self,
+        messages: &[(Message, usize)],
+    ) -> anyhow::Result<Vec<anyhow::Result<Speculation>>> {
+        let root = self.executor.flush()?;
+        let mut mc = self.executor.context().clone();
+        mc.initial_state_root = root;
+        // Fatal errors are dumped when the message is re-executed serially.
+        let options = ExecutionOptions {
+            dump_dir: None,
+            event_sink: None,
+            hooks: Vec::new(),
+            ..self.executor.options().clone()
+        };
+
+        let speculate = &self.speculate;
+        Ok(SPECULATION_POOL.install(|| {
+            messages
+                .par_iter()
+                .map(|(msg, raw_length)| -> anyhow::Result<Speculation> {
+                    let mut executor = speculate(&mc)?;
+                    executor.set_options(options.clone());
+                    executor.state_tree_mut().track_accesses();
+                    let ret =
+                        executor.execute_message(msg.clone(), ApplyKind::Explicit, *raw_length)?;
+                    let accesses = executor
+                        .state_tree_mut()
+                        .take_accesses()
+                        .context("access tracking stopped")?;
+                    let writes = accesses
+                        .writes
+                        .iter()
+                        .map(|&id| -> anyhow::Result<_> {
+                            Ok((id, executor.state_tree().get_actor(id)?))
+                        })
+                        .collect::<anyhow::Result<_>>()?;
+                    Ok(Speculation {
+                        ret,
+                        accesses,
+                        writes,
+                        blocks: executor.buffered_blocks(),
+                    })
+                })
+                .collect()
+        }))
+    }
+
+    /// Applies a speculative result to the state, replaying its fee deposits.
+    fn commit(executor: &mut DefaultExecutor<K>, spec: Speculation) -> anyhow::Result<ApplyRet> {
+        executor
+            .blockstore()
+            .put_many_keyed(spec.blocks)
+            .context("failed to commit speculative blocks")?;
+
+        let state_tree = executor.state_tree_mut();
+        for (id, actor) in spec.writes {
+            match actor {
+                Some(actor) => state_tree.set_actor(id, actor),
+                None => state_tree.delete_actor(id),
+            }
+        }
+
+        let ret = spec.ret;
+        for (id, amt) in [
+            (
+                BURNT_FUNDS_ACTOR_ID,
+                &ret.base_fee_burn + &ret.over_estimation_burn,
+            ),
+            (REWARD_ACTOR_ID, ret.miner_tip.clone()),
+        ] {
+            if !amt.is_zero() {
+                state_tree.mutate_actor(id, |act| act.deposit_funds(&amt).or_fatal())?;
+            }
+        }
+        Ok(ret)
+    }
+}
+
+impl<K, F> Executor for ParallelExecutor<K, F>
+where
+    K: Kernel,
+    F: Fn(&MachineContext) -> anyhow::Result<DefaultExecutor<K>> + Sync,
+{
+    type Kernel = K;
+
+    /// Executes a single message serially on the wrapped executor.
+    fn execute_message(
+        &mut self,
+        msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+    ) -> anyhow::Result<ApplyRet> {
+        self.executor.execute_message(msg, apply_kind, raw_length)
+    }
+
+    fn flush(&mut self) -> anyhow::Result<Cid> {
+        self.executor.flush()
+    }
+}
+
+impl<K: Kernel, F> Deref for ParallelExecutor<K, F> {
+    type Target = DefaultExecutor<K>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.executor
+    }
+}
+
+impl<K: Kernel, F> DerefMut for ParallelExecutor<K, F> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.executor
+    }
+}
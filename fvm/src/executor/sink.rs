@@ -0,0 +1,31 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Push-style delivery of events to indexers embedded in the node.
+use cid::Cid;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::event::StampedEvent;
+use fvm_shared::message::Message;
+
+/// A receiver of the events emitted by messages, configured with
+/// [`ExecutionOptions::with_event_sink`](super::ExecutionOptions::with_event_sink).
+///
+/// The [`DefaultExecutor`](super::DefaultExecutor) publishes the events of each message that
+/// emitted any as soon as the message completes. Events published for messages that are later
+/// discarded (e.g., because the block failed validation or the state was never flushed) are not
+/// retracted.
+pub trait EventSink: Send + Sync + 'static {
+    /// Called with the events emitted by the message with the given CID, executed at the given
+    /// epoch, in emission order.
+    fn publish(&self, epoch: ChainEpoch, message: &Cid, events: &[StampedEvent]);
+}
+
+impl std::fmt::Debug for dyn EventSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EventSink")
+    }
+}
+
+/// Computes the CID of an (unsigned) message.
+pub(super) fn message_cid(msg: &Message) -> anyhow::Result<Cid> {
+    Ok(fvm_shared::verify::message_cid(msg)?)
+}
@@ -12,7 +12,7 @@ lazy_static! {
         8,
         yastl::ThreadConfig::new()
             .prefix("fvm-executor")
-            // fvm needs more than the deafault available stack (2MiB):
+            // fvm needs more than the default available stack (2MiB):
             // - Max 2048 wasm stack elements, which is 16KiB of 64bit entries
             // - Roughly 20KiB overhead per actor call
             // - max 1024 nested calls, which means that in the worst case we need ~36MiB of stack
@@ -23,6 +23,9 @@ lazy_static! {
 
 /// An executor that executes messages on a separate thread with a 64MiB stack. If you can guarantee
 /// at least 64MiB of stack space, you don't need this executor.
+///
+/// Messages are executed on a pool of 8 threads, spawned once and reused for all messages of all
+/// executors, so executing a message doesn't spawn a thread.
 pub struct ThreadedExecutor<E>(pub E);
 
 impl<E> Executor for ThreadedExecutor<E>
@@ -0,0 +1,90 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Advancing the executor's machine across epochs, applying the network upgrade schedule.
+use anyhow::{anyhow, bail};
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+
+use super::{DefaultExecutor, Executor};
+use crate::call_manager::CallManager;
+use crate::externs::Externs;
+use crate::kernel::Kernel;
+use crate::machine::limiter::NetworkMemoryLimiter;
+use crate::machine::{DefaultMachine, Machine, NetworkUpgrade};
+
+impl<K, C, B, E, L> DefaultExecutor<K>
+where
+    K: Kernel<CallManager = C>,
+    C: CallManager<Machine = DefaultMachine<B, E, L>>,
+    B: Blockstore + 'static,
+    E: Externs + 'static,
+    L: NetworkMemoryLimiter,
+{
+    /// Flushes the state and re-creates the machine for a later epoch, starting from the flushed
+    /// state root. If the [`UpgradeSchedule`](crate::machine::UpgradeSchedule) has an upgrade
+    /// taking effect after the current epoch and at or before the new one, the upgrade's network
+    /// version, actors, and prices are applied (and the engines are re-created if the upgraded
+    /// prices require recompiling actors). Returns the upgrade applied, if any.
+    ///
+    /// All other configuration is carried over from the current machine, including the node-local
+    /// engine configuration (see [`EngineConfig::for_network`]) and the on-disk module cache. If
+    /// re-creating the machine fails, the executor is poisoned.
+    ///
+    /// [`EngineConfig::for_network`]: crate::engine::EngineConfig::for_network
+    pub fn advance_epoch(
+        &mut self,
+        epoch: ChainEpoch,
+        timestamp: u64,
+        base_fee: TokenAmount,
+        circ_supply: TokenAmount,
+    ) -> anyhow::Result<Option<NetworkUpgrade>> {
+        let current = self.context().epoch;
+        if epoch <= current {
+            bail!("cannot advance from epoch {} to epoch {}", current, epoch);
+        }
+
+        let root = self.flush()?;
+        let mut mc = self.context().clone();
+        let upgrade = self
+            .upgrade_schedule()
+            .upgrade_between(current, epoch)
+            .cloned();
+        if let Some(upgrade) = &upgrade {
+            log::info!(
+                "applying upgrade to network version {} at epoch {}",
+                upgrade.network_version,
+                upgrade.epoch
+            );
+            upgrade.apply(&mut mc.network)?;
+        }
+        mc.epoch = epoch;
+        mc.timestamp = timestamp;
+        mc.initial_state_root = root;
+        mc.base_fee = base_fee;
+        mc.circ_supply = circ_supply;
+
+        let (blockstore, externs) = self
+            .machine
+            .take()
+            .ok_or_else(|| anyhow!("machine poisoned"))?
+            .into_parts();
+        let machine = DefaultMachine::with_limiter(&mc, blockstore, externs)?;
+
+        if upgrade.is_some() {
+            // The Wasm instruction costs are baked into compiled actors.
+            let ec = self.engine_pool.config().for_network(&mc.network);
+            if &ec != self.engine_pool.config() {
+                self.engine_pool = self.engine_pool.with_config(ec)?;
+            }
+        }
+        if upgrade.is_some() && mc.preload_builtin_actors {
+            self.engine_pool
+                .acquire()
+                .preload_manifest(machine.blockstore(), machine.builtin_actors())?;
+        }
+
+        self.machine = Some(machine);
+        Ok(upgrade)
+    }
+}
@@ -4,13 +4,26 @@
 
 use std::borrow::Cow;
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
 use super::timer::GasDuration;
-use super::Gas;
+use super::{Gas, GasBreakdown};
+
+/// Separates the segments of hierarchical gas charge names.
+pub const LABEL_SEPARATOR: char = '/';
 
 /// Single gas charge in the VM. Contains information about what gas was for, as well
 /// as the amount of gas needed for computation and storage respectively.
 #[derive(Clone, Debug)]
 pub struct GasCharge {
+    /// The hierarchical name of the charge, from the most general category to the most specific
+    /// operation, separated by [`LABEL_SEPARATOR`]. The top-level categories are:
+    ///
+    /// - `message`: message inclusion and return values.
+    /// - `call`: value transfers and method invocations.
+    /// - `wasm`: Wasm execution and memory/table initialization.
+    /// - `syscall`: syscalls, named `syscall/<module>/<syscall>` (plus `syscall/overhead`).
+    /// - `state`: state-tree access.
     pub name: Cow<'static, str>,
     /// Gas charged for immediate computation.
     pub compute_gas: Gas,
@@ -42,6 +55,22 @@ impl PartialEq for GasCharge {
 #[cfg(feature = "testing")]
 impl Eq for GasCharge {}
 
+/// Gas charges are serialized as `[name, compute_gas, other_gas]`. The elapsed time is specific to
+/// the process that measured it and is not serialized.
+impl Serialize for GasCharge {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&*self.name, self.compute_gas, self.other_gas).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for GasCharge {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (name, compute_gas, other_gas): (String, Gas, Gas) =
+            Deserialize::deserialize(deserializer)?;
+        Ok(GasCharge::new(name, compute_gas, other_gas))
+    }
+}
+
 impl GasCharge {
     pub fn new(name: impl Into<Cow<'static, str>>, compute_gas: Gas, other_gas: Gas) -> Self {
         let name = name.into();
@@ -58,4 +87,63 @@ impl GasCharge {
     pub fn total(&self) -> Gas {
         self.compute_gas + self.other_gas
     }
+
+    /// Returns the top-level category of this charge (e.g., `syscall`).
+    pub fn category(&self) -> &str {
+        truncate_label(&self.name, 1)
+    }
+
+    /// Returns true if this charge is the given label or nested under it. See [`label_is_under`].
+    pub fn is_under(&self, label: &str) -> bool {
+        label_is_under(&self.name, label)
+    }
+}
+
+/// Returns true if `name` is `label` or nested under it. Unlike a string prefix match, this respects
+/// segment boundaries: `syscall/ipld/block_open` is under `syscall/ipld`, but
+/// `syscall/ipld/block_open_base` is not under `syscall/ipld/block_open`.
+pub fn label_is_under(name: &str, label: &str) -> bool {
+    match name.strip_prefix(label) {
+        Some(rest) => rest.is_empty() || rest.starts_with(LABEL_SEPARATOR),
+        None => false,
+    }
+}
+
+/// Truncates `name` to its first `depth` segments (e.g., `syscall/ipld/block_open` to
+/// `syscall/ipld` at depth 2).
+pub fn truncate_label(name: &str, depth: usize) -> &str {
+    if depth == 0 {
+        return "";
+    }
+    match name.match_indices(LABEL_SEPARATOR).nth(depth - 1) {
+        Some((idx, _)) => &name[..idx],
+        None => name,
+    }
+}
+
+/// Sums gas by name, truncated to the first `depth` segments. This works both on charges from an
+/// execution trace and on an existing [`GasBreakdown`]:
+///
+/// ```
+/// # use fvm::gas::{roll_up, Gas, GasBreakdown};
+/// let breakdown = GasBreakdown::from([
+///     ("syscall/ipld/block_open".into(), Gas::new(10)),
+///     ("syscall/ipld/block_read".into(), Gas::new(5)),
+///     ("wasm/exec".into(), Gas::new(1)),
+/// ]);
+/// let by_category = roll_up(breakdown.iter().map(|(name, gas)| (&**name, *gas)), 1);
+/// assert_eq!(by_category["syscall"], Gas::new(15));
+/// assert_eq!(by_category["wasm"], Gas::new(1));
+/// ```
+pub fn roll_up<'a>(
+    charges: impl IntoIterator<Item = (&'a str, Gas)>,
+    depth: usize,
+) -> GasBreakdown {
+    let mut breakdown = GasBreakdown::new();
+    for (name, gas) in charges {
+        *breakdown
+            .entry(truncate_label(name, depth).to_owned())
+            .or_default() += gas;
+    }
+    breakdown
 }
@@ -0,0 +1,24 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use super::Gas;
+
+/// Observes gas charges as they're applied by the [`GasTracker`](super::GasTracker).
+///
+/// Listeners are configured with
+/// [`MachineContext::set_gas_listener`](crate::machine::MachineContext::set_gas_listener) and are
+/// notified of every charge (including charges that run out of gas) for every message executed by
+/// the machine. They're intended for profiling and calibration tooling, can't affect execution,
+/// and aren't consensus-critical. However, they're called synchronously on the execution path so
+/// they should be cheap.
+pub trait GasChargeListener: Send + Sync + 'static {
+    /// Called after a charge is applied, with the charge's name, the compute and other (e.g.,
+    /// storage) components of the charge, and the gas remaining after the charge (zero if the
+    /// charge ran out of gas).
+    fn on_charge(&self, name: &str, compute: Gas, other: Gas, remaining: Gas);
+}
+
+impl std::fmt::Debug for dyn GasChargeListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("GasChargeListener")
+    }
+}
@@ -3,21 +3,33 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Display};
 use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Context;
+use fvm_shared::{ActorID, MethodNum};
 use num_traits::Zero;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-pub use self::charge::GasCharge;
-pub(crate) use self::outputs::GasOutputs;
+pub use self::charge::{label_is_under, roll_up, truncate_label, GasCharge, LABEL_SEPARATOR};
+pub use self::listener::GasChargeListener;
+pub use self::outputs::{FilecoinGasOutputs, GasOutputs, GasOutputsStrategy};
+pub use self::price_document::PRICE_LIST_DOCUMENT_VERSION;
 pub use self::price_list::{price_list_by_network_version, PriceList, WasmGasPrices};
+pub use self::registry::PriceListRegistry;
 pub use self::timer::{GasDuration, GasInstant, GasTimer};
 use crate::kernel::{ClassifyResult, ExecutionError, Result};
+use crate::trace::ExecutionEvent;
 
 mod charge;
+mod listener;
 mod outputs;
+mod price_document;
 mod price_list;
+mod registry;
 mod timer;
 
 pub const MILLIGAS_PRECISION: u64 = 1000;
@@ -90,6 +102,19 @@ impl Gas {
     }
 }
 
+/// Gas is serialized as an integer number of milligas.
+impl Serialize for Gas {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Gas {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        u64::deserialize(deserializer).map(Gas)
+    }
+}
+
 impl num_traits::Zero for Gas {
     fn zero() -> Self {
         Gas(0)
@@ -164,11 +189,21 @@ struct GasSnapshot {
     used: Gas,
 }
 
+/// The gas used by a message, keyed by charge name.
+pub type GasBreakdown = BTreeMap<String, Gas>;
+
+/// The gas used by a message, keyed by the actor ID and method number of the calls that used it.
+/// Each call is attributed the gas it used itself, excluding nested calls.
+pub type ActorGasUsage = BTreeMap<(ActorID, MethodNum), Gas>;
+
 pub struct GasTracker {
     gas_limit: Gas,
     gas_used: Cell<Gas>,
     gas_snapshots: Vec<GasSnapshot>,
-    trace: Option<RefCell<Vec<GasCharge>>>,
+    trace: Option<RefCell<Vec<ExecutionEvent>>>,
+    breakdown: Option<RefCell<GasBreakdown>>,
+    refund_credit: Cell<Gas>,
+    listener: Option<Arc<dyn GasChargeListener>>,
 }
 
 impl GasTracker {
@@ -186,31 +221,65 @@ impl GasTracker {
             gas_used: Cell::new(gas_used),
             gas_snapshots: Vec::new(),
             trace: enable_tracing.then_some(Default::default()),
+            breakdown: None,
+            refund_credit: Default::default(),
+            listener: None,
         }
     }
 
-    fn charge_gas_inner(&self, to_use: Gas) -> Result<()> {
+    /// Record the gas charged, keyed by charge name (see [`GasTracker::gas_breakdown`]),
+    /// independently of tracing.
+    pub fn enable_breakdown(&mut self) {
+        self.breakdown.get_or_insert_with(Default::default);
+    }
+
+    /// Notify the given listener of every subsequent charge.
+    pub fn set_listener(&mut self, listener: Arc<dyn GasChargeListener>) {
+        self.listener = Some(listener);
+    }
+
+    fn notify_listener(&self, name: &str, compute: Gas, other: Gas) {
+        if let Some(listener) = &self.listener {
+            listener.on_charge(name, compute, other, self.gas_available());
+        }
+    }
+
+    fn charge_gas_inner(&self, name: &str, to_use: Gas) -> Result<()> {
         // The gas type uses saturating math.
         let gas_used = self.gas_used.get() + to_use;
-        if gas_used > self.gas_limit {
+        let (charged, res) = if gas_used > self.gas_limit {
             log::trace!("gas limit reached");
+            let charged = self.gas_limit - self.gas_used.get();
             self.gas_used.set(self.gas_limit);
-            Err(ExecutionError::OutOfGas)
+            (charged, Err(ExecutionError::OutOfGas))
         } else {
             self.gas_used.set(gas_used);
-            Ok(())
+            (to_use, Ok(()))
+        };
+
+        // Only record the gas actually used (up to the limit).
+        if let Some(breakdown) = &self.breakdown {
+            let mut breakdown = breakdown.borrow_mut();
+            match breakdown.get_mut(name) {
+                Some(total) => *total += charged,
+                None => {
+                    breakdown.insert(name.to_owned(), charged);
+                }
+            }
         }
+        res
     }
 
     /// Safely consumes gas and returns an out of gas error if there is not sufficient
     /// enough gas remaining for charge.
     pub fn charge_gas(&self, name: &str, to_use: Gas) -> Result<GasTimer> {
         log::trace!("charging gas: {} {}", name, to_use);
-        let res = self.charge_gas_inner(to_use);
+        let res = self.charge_gas_inner(name, to_use);
+        self.notify_listener(name, to_use, Gas::zero());
         if let Some(trace) = &self.trace {
             let mut charge = GasCharge::new(name.to_owned(), to_use, Gas::zero());
             let timer = GasTimer::new(&mut charge.elapsed);
-            trace.borrow_mut().push(charge);
+            trace.borrow_mut().push(ExecutionEvent::GasCharge(charge));
             res.map(|_| timer)
         } else {
             res.map(|_| GasTimer::empty())
@@ -221,16 +290,28 @@ impl GasTracker {
     pub fn apply_charge(&self, mut charge: GasCharge) -> Result<GasTimer> {
         let to_use = charge.total();
         log::trace!("charging gas: {} {}", &charge.name, to_use);
-        let res = self.charge_gas_inner(to_use);
+        let res = self.charge_gas_inner(&charge.name, to_use);
+        self.notify_listener(&charge.name, charge.compute_gas, charge.other_gas);
         if let Some(trace) = &self.trace {
             let timer = GasTimer::new(&mut charge.elapsed);
-            trace.borrow_mut().push(charge);
+            trace.borrow_mut().push(ExecutionEvent::GasCharge(charge));
             res.map(|_| timer)
         } else {
             res.map(|_| GasTimer::empty())
         }
     }
 
+    /// Records the wall-clock time taken by a syscall in the trace, if tracing is enabled.
+    pub fn trace_syscall(&self, module: &'static str, name: &'static str, elapsed: Duration) {
+        if let Some(trace) = &self.trace {
+            trace.borrow_mut().push(ExecutionEvent::Syscall {
+                module,
+                name,
+                elapsed,
+            });
+        }
+    }
+
     /// Push a new gas limit.
     pub fn push_limit(&mut self, new_limit: Gas) {
         self.gas_snapshots.push(GasSnapshot {
@@ -269,7 +350,36 @@ impl GasTracker {
         self.gas_limit - self.gas_used.get()
     }
 
-    pub fn drain_trace(&self) -> impl Iterator<Item = GasCharge> + '_ {
+    /// Credits a gas refund (e.g., for deleting an actor). Refund credits are tracked separately
+    /// from the gas used and are only applied (up to [`PriceList::max_refund`]) once the message
+    /// completes, so they can't be spent on further execution.
+    pub fn credit_refund(&self, amount: Gas) {
+        log::trace!("crediting gas refund: {}", amount);
+        self.refund_credit.set(self.refund_credit.get() + amount);
+    }
+
+    /// Returns the refund credited so far, before applying any cap.
+    pub fn refund_credit(&self) -> Gas {
+        self.refund_credit.get()
+    }
+
+    /// Resets the refund credit to a previously observed value (e.g., when reverting the state
+    /// changes that earned the refunds).
+    pub fn restore_refund_credit(&self, credit: Gas) {
+        self.refund_credit.set(credit);
+    }
+
+    /// Returns the gas charged so far, keyed by charge name. This is only recorded when enabled
+    /// with [`GasTracker::enable_breakdown`], and is empty otherwise.
+    pub fn gas_breakdown(&self) -> GasBreakdown {
+        self.breakdown
+            .as_ref()
+            .map(|b| b.borrow().clone())
+            .unwrap_or_default()
+    }
+
+    /// Takes the gas charges (and syscall timings) traced so far.
+    pub fn drain_trace(&self) -> impl Iterator<Item = ExecutionEvent> + '_ {
         self.trace
             .as_ref()
             .map(|v| v.take().into_iter())
@@ -308,6 +418,73 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn gas_breakdown() {
+        // The breakdown doesn't require tracing.
+        let mut t = GasTracker::new(Gas::new(20), Gas::zero(), false);
+        t.enable_breakdown();
+        t.charge_gas("a", Gas::new(5)).unwrap();
+        t.apply_charge(GasCharge::new("b", Gas::new(3), Gas::new(2)))
+            .unwrap();
+        t.charge_gas("a", Gas::new(5)).unwrap();
+        // Only the gas actually available is recorded.
+        assert!(t.charge_gas("b", Gas::new(10)).is_err());
+
+        let breakdown = t.gas_breakdown();
+        assert_eq!(breakdown.len(), 2);
+        assert_eq!(breakdown["a"], Gas::new(10));
+        assert_eq!(breakdown["b"], Gas::new(10));
+
+        // Nothing is recorded unless enabled, even with tracing.
+        let t = GasTracker::new(Gas::new(20), Gas::zero(), true);
+        t.charge_gas("a", Gas::new(5)).unwrap();
+        assert!(t.gas_breakdown().is_empty());
+    }
+
+    #[test]
+    fn hierarchical_labels() {
+        assert!(label_is_under("syscall/ipld/block_open", "syscall"));
+        assert!(label_is_under(
+            "syscall/ipld/block_open",
+            "syscall/ipld/block_open"
+        ));
+        assert!(!label_is_under(
+            "syscall/ipld/block_open_base",
+            "syscall/ipld/block_open"
+        ));
+        assert!(!label_is_under("syscall", "syscall/ipld"));
+
+        assert_eq!(truncate_label("syscall/ipld/block_open", 0), "");
+        assert_eq!(truncate_label("syscall/ipld/block_open", 1), "syscall");
+        assert_eq!(truncate_label("syscall/ipld/block_open", 2), "syscall/ipld");
+        assert_eq!(
+            truncate_label("syscall/ipld/block_open", 5),
+            "syscall/ipld/block_open"
+        );
+
+        let charge = GasCharge::new("wasm/exec", Gas::new(1), Gas::zero());
+        assert_eq!(charge.category(), "wasm");
+        assert!(charge.is_under("wasm"));
+
+        let rolled_up = roll_up(
+            [
+                ("syscall/ipld/block_open", Gas::new(1)),
+                ("syscall/ipld/block_read", Gas::new(2)),
+                ("syscall/crypto/hash", Gas::new(4)),
+                ("wasm/exec", Gas::new(8)),
+            ],
+            2,
+        );
+        assert_eq!(
+            rolled_up,
+            GasBreakdown::from([
+                ("syscall/ipld".into(), Gas::new(3)),
+                ("syscall/crypto".into(), Gas::new(4)),
+                ("wasm/exec".into(), Gas::new(8)),
+            ])
+        );
+    }
+
     #[test]
     fn milligas_to_gas_round() {
         assert_eq!(milligas_to_gas(100, false), 0);
@@ -3,8 +3,14 @@
 
 use fvm_shared::econ::TokenAmount;
 
-#[derive(Clone, Default)]
-pub(crate) struct GasOutputs {
+/// How the gas fees of a message are split between burning, the block miner, and the sender.
+///
+/// The executor burns the base fee and over-estimation burns, pays the miner tip to the reward
+/// actor, and refunds the sender. These must add up to the funds reserved for gas (`fee_cap *
+/// gas_limit`), or the message fails with a fatal error. The miner penalty isn't transferred but
+/// is reported to the client, which applies it to the block miner.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GasOutputs {
     pub base_fee_burn: TokenAmount,
     pub over_estimation_burn: TokenAmount,
     pub miner_penalty: TokenAmount,
@@ -14,12 +20,69 @@ pub(crate) struct GasOutputs {
     // In whole gas units.
     pub gas_refund: u64,
     pub gas_burned: u64,
+    /// The gas refunded for releasing state (e.g., deleting actors), already deducted from the gas
+    /// used.
+    pub refund_credit: u64,
+}
+
+/// Computes the [`GasOutputs`] of each message, letting networks other than mainnet (e.g.,
+/// subnets) apply their own fee-burning rules.
+///
+/// Strategies are configured with
+/// [`MachineContext::set_gas_outputs_strategy`](crate::machine::MachineContext::set_gas_outputs_strategy).
+/// They're consensus-critical, so all nodes of a network must use the same strategy.
+pub trait GasOutputsStrategy: Send + Sync + 'static {
+    /// Splits the gas fees of a message. The arguments are as in [`GasOutputs::compute`].
+    fn compute(
+        &self,
+        gas_used: u64,
+        refund_credit: u64,
+        gas_limit: u64,
+        base_fee: &TokenAmount,
+        fee_cap: &TokenAmount,
+        gas_premium: &TokenAmount,
+    ) -> GasOutputs;
+}
+
+impl std::fmt::Debug for dyn GasOutputsStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("GasOutputsStrategy")
+    }
+}
+
+/// The Filecoin mainnet fee rules, used unless a [`GasOutputsStrategy`] is configured.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FilecoinGasOutputs;
+
+impl GasOutputsStrategy for FilecoinGasOutputs {
+    fn compute(
+        &self,
+        gas_used: u64,
+        refund_credit: u64,
+        gas_limit: u64,
+        base_fee: &TokenAmount,
+        fee_cap: &TokenAmount,
+        gas_premium: &TokenAmount,
+    ) -> GasOutputs {
+        GasOutputs::compute(
+            gas_used,
+            refund_credit,
+            gas_limit,
+            base_fee,
+            fee_cap,
+            gas_premium,
+        )
+    }
 }
 
 impl GasOutputs {
+    /// Splits the gas fees of a message according to the Filecoin mainnet rules: the base fee is
+    /// burnt, the premium is paid to the miner, and a portion of the over-estimated gas is burnt.
+    /// The rest is refunded.
     pub fn compute(
-        // In whole gas units.
+        // In whole gas units, net of the refund credit.
         gas_used: u64,
+        refund_credit: u64,
         gas_limit: u64,
         base_fee: &TokenAmount,
         fee_cap: &TokenAmount,
@@ -27,7 +90,10 @@ impl GasOutputs {
     ) -> Self {
         let mut base_fee_to_pay = base_fee;
 
-        let mut out = GasOutputs::default();
+        let mut out = GasOutputs {
+            refund_credit,
+            ..GasOutputs::default()
+        };
 
         if base_fee > fee_cap {
             base_fee_to_pay = fee_cap;
@@ -130,6 +196,7 @@ fn gas_outputs_test() {
         let base_fee = TokenAmount::from_atto(10);
         let output = GasOutputs::compute(
             used,
+            0,
             limit,
             &base_fee,
             &TokenAmount::from_atto(fee_cap),
@@ -0,0 +1,303 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Loading of [`PriceList`]s from external JSON or TOML documents.
+//!
+//! Parsing JSON and TOML documents (and loading them from files) requires the `price-documents`
+//! feature.
+#[cfg(feature = "price-documents")]
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, bail, Context};
+use fvm_shared::version::NetworkVersion;
+use fvm_shared::ActorID;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Deserializer};
+
+use super::price_list::{try_price_list_by_network_version, ScalingCost};
+use super::{Gas, PriceList, WasmGasPrices};
+
+/// The current version of the price list document format.
+pub const PRICE_LIST_DOCUMENT_VERSION: u32 = 1;
+
+lazy_static! {
+    /// Price lists that have been leaked by [`PriceList::intern`].
+    static ref INTERNED_PRICE_LISTS: Mutex<Vec<&'static PriceList>> = Mutex::default();
+}
+
+/// Defines a set of optional overrides for the given fields of the target price type.
+macro_rules! overrides {
+    ($name:ident => $target:ty { $($field:ident: $ty:ty),* $(,)? }) => {
+        #[derive(Debug, Default, Deserialize)]
+        #[serde(default, deny_unknown_fields)]
+        struct $name {
+            $($field: Option<$ty>,)*
+        }
+
+        impl $name {
+            fn apply(self, target: &mut $target) {
+                $(if let Some(v) = self.$field {
+                    target.$field = v;
+                })*
+            }
+        }
+    };
+}
+
+overrides!(PriceOverrides => PriceList {
+    on_chain_message_compute: ScalingCost,
+    on_chain_message_storage: ScalingCost,
+    on_chain_return_compute: ScalingCost,
+    on_chain_return_storage: ScalingCost,
+    send_transfer_funds: Gas,
+    send_invoke_method: Gas,
+    address_lookup: Gas,
+    address_assignment: Gas,
+    actor_lookup: Gas,
+    actor_update: Gas,
+    actor_create_storage: Gas,
+    secp256k1_recover_cost: Gas,
+    merkle_proof_node: Gas,
+    merkle_proof_decode: ScalingCost,
+    lookback_cost: ScalingCost,
+    compute_unsealed_sector_cid_base: Gas,
+    verify_seal_base: Gas,
+    verify_consensus_fault: Gas,
+    verify_replica_update: Gas,
+    block_memcpy: ScalingCost,
+    block_allocate: ScalingCost,
+    block_memory_retention_minimum: ScalingCost,
+    block_open: ScalingCost,
+    block_persist_storage: ScalingCost,
+    block_persist_compute: Gas,
+    syscall_cost: Gas,
+    event_per_entry: ScalingCost,
+    builtin_actor_manifest_lookup: Gas,
+    utf8_validation: ScalingCost,
+    network_context: Gas,
+    message_context: Gas,
+    install_wasm_per_byte_cost: Gas,
+    preloaded_actors: Vec<ActorID>,
+    max_call_depth: u32,
+    ipld_cbor_scan_per_field: Gas,
+    ipld_cbor_scan_per_cid: Gas,
+    ipld_link_tracked: Gas,
+    ipld_link_checked: Gas,
+    self_destruct_refund: Gas,
+    max_refund_quotient: u64,
+});
+
+overrides!(WasmPriceOverrides => WasmGasPrices {
+    instruction_default: Gas,
+    math_default: Gas,
+    memory_default: Gas,
+    bulk_memory_default: Gas,
+    jump_unconditional: Gas,
+    jump_conditional: Gas,
+    jump_indirect: Gas,
+    call: Gas,
+    memory_fill_base_cost: Gas,
+    memory_fill_per_byte_cost: Gas,
+    memory_access_cost: Gas,
+    memory_copy_per_byte_cost: Gas,
+});
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PriceListDocument {
+    /// The document format version, must be [`PRICE_LIST_DOCUMENT_VERSION`].
+    version: u32,
+    /// The network version from which all prices not overridden are taken.
+    base: NetworkVersion,
+    #[serde(default)]
+    prices: PriceOverrides,
+    #[serde(default)]
+    wasm_prices: WasmPriceOverrides,
+}
+
+impl PriceListDocument {
+    fn into_price_list(self) -> anyhow::Result<PriceList> {
+        if self.version != PRICE_LIST_DOCUMENT_VERSION {
+            bail!(
+                "unsupported price list document version {} (expected {})",
+                self.version,
+                PRICE_LIST_DOCUMENT_VERSION
+            );
+        }
+        let mut price_list = try_price_list_by_network_version(self.base)
+            .ok_or_else(|| anyhow!("unsupported base network version {}", self.base))?
+            .clone();
+        self.prices.apply(&mut price_list);
+        self.wasm_prices.apply(&mut price_list.wasm_rules);
+
+        if price_list.max_call_depth == 0 {
+            bail!("max_call_depth must be at least 1");
+        }
+        Ok(price_list)
+    }
+}
+
+impl PriceList {
+    /// Loads a price list from a document in any self-describing serde format.
+    ///
+    /// A price list document starts from the prices of a supported network version and overrides
+    /// individual prices. For example (in TOML):
+    ///
+    /// ```toml
+    /// version = 1
+    /// base = 21
+    ///
+    /// [prices]
+    /// send_transfer_funds = 6000000
+    /// on_chain_message_compute = { flat = 38863000, scale = 475000 }
+    ///
+    /// [wasm_prices]
+    /// instruction_default = 4000
+    /// ```
+    ///
+    /// All prices are in milligas. Signature, hashing, and proof verification prices can't
+    /// currently be overridden and are always taken from the base network version. Unknown fields
+    /// are rejected.
+    pub fn from_document<'de, D: Deserializer<'de>>(deserializer: D) -> anyhow::Result<Self>
+    where
+        D::Error: Send + Sync + 'static,
+    {
+        PriceListDocument::deserialize(deserializer)
+            .context("invalid price list document")?
+            .into_price_list()
+    }
+
+    /// Returns a `'static` reference to this price list, as required by
+    /// [`MachineContext::set_price_list`](crate::machine::MachineContext::set_price_list).
+    ///
+    /// The price list is leaked, but only once per distinct set of prices: interning an equal
+    /// price list again returns the previously leaked one.
+    pub fn intern(self) -> &'static PriceList {
+        let mut interned = INTERNED_PRICE_LISTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(existing) = interned.iter().copied().find(|pl| **pl == self) {
+            return existing;
+        }
+        let leaked: &'static PriceList = Box::leak(Box::new(self));
+        interned.push(leaked);
+        leaked
+    }
+
+    /// Loads a price list from a JSON document.
+    #[cfg(feature = "price-documents")]
+    pub fn from_json(document: &str) -> anyhow::Result<Self> {
+        serde_json::from_str::<PriceListDocument>(document)
+            .context("invalid price list document")?
+            .into_price_list()
+    }
+
+    /// Loads a price list from a TOML document.
+    #[cfg(feature = "price-documents")]
+    pub fn from_toml(document: &str) -> anyhow::Result<Self> {
+        toml::from_str::<PriceListDocument>(document)
+            .context("invalid price list document")?
+            .into_price_list()
+    }
+
+    /// Loads a price list from a file, selecting the format by extension (`.json` or `.toml`).
+    #[cfg(feature = "price-documents")]
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read price list {}", path.display()))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            Some("toml") => Self::from_toml(&contents),
+            _ => bail!("unknown price list format: {}", path.display()),
+        }
+        .with_context(|| format!("failed to load price list {}", path.display()))
+    }
+}
+
+#[cfg(all(test, feature = "price-documents"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_document_defaults() {
+        let base = try_price_list_by_network_version(NetworkVersion::V21).unwrap();
+        let loaded = PriceList::from_json(r#"{"version": 1, "base": 21}"#).unwrap();
+        assert_eq!(&loaded, base);
+    }
+
+    #[test]
+    fn test_price_document_overrides() {
+        let mut expected = try_price_list_by_network_version(NetworkVersion::V21)
+            .unwrap()
+            .clone();
+        expected.send_transfer_funds = Gas::from_milligas(1234);
+        expected.block_open = ScalingCost {
+            flat: Gas::from_milligas(10),
+            scale: Gas::from_milligas(20),
+        };
+        expected.max_call_depth = 16;
+        expected.wasm_rules.instruction_default = Gas::from_milligas(5);
+
+        let json = PriceList::from_json(
+            r#"{
+                "version": 1,
+                "base": 21,
+                "prices": {
+                    "send_transfer_funds": 1234,
+                    "block_open": {"flat": 10, "scale": 20},
+                    "max_call_depth": 16
+                },
+                "wasm_prices": {"instruction_default": 5}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(json, expected);
+
+        let toml = PriceList::from_toml(
+            r#"
+                version = 1
+                base = 21
+
+                [prices]
+                send_transfer_funds = 1234
+                block_open = { flat = 10, scale = 20 }
+                max_call_depth = 16
+
+                [wasm_prices]
+                instruction_default = 5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(toml, expected);
+    }
+
+    #[test]
+    fn test_price_document_validation() {
+        for invalid in [
+            // Wrong document version.
+            r#"{"version": 2, "base": 21}"#,
+            // Unsupported base network version.
+            r#"{"version": 1, "base": 1}"#,
+            // Unknown price.
+            r#"{"version": 1, "base": 21, "prices": {"no_such_price": 1}}"#,
+            // Incomplete scaling cost.
+            r#"{"version": 1, "base": 21, "prices": {"block_open": {"flat": 1}}}"#,
+            // Invalid call depth.
+            r#"{"version": 1, "base": 21, "prices": {"max_call_depth": 0}}"#,
+        ] {
+            assert!(PriceList::from_json(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_price_list_intern() {
+        let load = || PriceList::from_json(r#"{"version": 1, "base": 21}"#).unwrap();
+        let first = load().intern();
+        assert!(std::ptr::eq(first, load().intern()));
+
+        let mut repriced = load();
+        repriced.send_transfer_funds = Gas::from_milligas(1);
+        assert!(!std::ptr::eq(first, repriced.intern()));
+    }
+}
@@ -18,6 +18,7 @@ use fvm_shared::ActorID;
 use fvm_wasm_instrument::gas_metering::{InstructionCost, Operator, Rules};
 use lazy_static::lazy_static;
 use num_traits::Zero;
+use serde::Deserialize;
 
 use super::GasCharge;
 use crate::gas::Gas;
@@ -114,6 +115,19 @@ lazy_static! {
                 },
             }
         },
+        sig_batch_cost: total_enum_map!{
+            SignatureType {
+                Secp256k1 => ScalingCost {
+                    flat: Gas::new(1637292),
+                    scale: Gas::new(10),
+                },
+                BLS =>  ScalingCost{
+                    flat: Gas::new(8299302),
+                    scale: Gas::new(26),
+                },
+            }
+        },
+        bls_batch_base: Gas::new(8299303),
         secp256k1_recover_cost: Gas::new(1637292),
         hashing_cost: total_enum_map! {
             SupportedHashes {
@@ -139,6 +153,15 @@ lazy_static! {
                 }
             }
         },
+        // Each node's preimage (usually two 32 byte digests) is copied into a fresh buffer before
+        // being hashed, charged at the `block_allocate` and `block_memcpy` rates. The hashing
+        // itself is charged at the `hashing_cost` rates.
+        merkle_proof_node: Gas::from_milligas(64 * (2000 + 400)),
+        // Decoding copies the proof into the decoded nodes, at the same rates.
+        merkle_proof_decode: ScalingCost {
+            flat: Gas::zero(),
+            scale: Gas::from_milligas(2000 + 400),
+        },
 
         compute_unsealed_sector_cid_base: Gas::new(98647),
         verify_seal_base: Gas::new(2000), // TODO revisit potential removal of this
@@ -274,6 +297,8 @@ lazy_static! {
             // Use the default instruction cost of 4 everywhere.
             instruction_default: Gas::new(4),
             math_default: Gas::new(4),
+            memory_default: Gas::new(4),
+            bulk_memory_default: Gas::new(4),
             jump_unconditional: Gas::new(4),
             jump_conditional: Gas::new(4),
             jump_indirect: Gas::new(4),
@@ -298,6 +323,8 @@ lazy_static! {
             scale: Gas::new(16),
         },
 
+        max_call_depth: 1024,
+
         // Preloaded actor IDs per FIP-0055.
         preloaded_actors: vec![0, 1, 2, 3, 4, 5, 6, 7, 10, 99],
 
@@ -305,10 +332,15 @@ lazy_static! {
         ipld_cbor_scan_per_field: Gas::new(35),
         ipld_link_tracked: Gas::new(300),
         ipld_link_checked: Gas::new(300),
+
+        // No refunds are granted on mainnet (yet).
+        self_destruct_refund: Gas::zero(),
+        max_refund_quotient: 5,
     };
 }
 
-#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct ScalingCost {
     pub flat: Gas,
     pub scale: Gas,
@@ -401,11 +433,26 @@ pub struct PriceList {
     /// Gas cost for verifying a cryptographic signature.
     pub(crate) sig_cost: HashMap<SignatureType, ScalingCost>,
 
+    /// Gas cost for verifying each signature of a batch. BLS signatures are verified as an
+    /// aggregate, sharing a pairing and the final exponentiation between all signatures. Secp256k1
+    /// signatures can't be batched and cost as much as when verified individually.
+    pub(crate) sig_batch_cost: HashMap<SignatureType, ScalingCost>,
+
+    /// Gas cost for verifying the aggregate of the BLS signatures of a batch, charged once per
+    /// batch containing BLS signatures.
+    pub(crate) bls_batch_base: Gas,
+
     /// Gas cost for recovering secp256k1 signer public key
     pub(crate) secp256k1_recover_cost: Gas,
 
     pub(crate) hashing_cost: HashMap<SupportedHashes, ScalingCost>,
 
+    /// Gas cost for decoding a single Merkle proof node, in addition to the cost of hashing it.
+    pub(crate) merkle_proof_node: Gas,
+
+    /// Gas cost for CBOR-decoding a Merkle proof, per byte of the encoded proof.
+    pub(crate) merkle_proof_decode: ScalingCost,
+
     /// Gas cost for walking up the chain.
     /// Applied to operations like getting randomness, tipset CIDs, etc.
     pub(crate) lookback_cost: ScalingCost,
@@ -468,6 +515,9 @@ pub struct PriceList {
     /// Actor IDs that can be updated for free.
     pub(crate) preloaded_actors: Vec<ActorID>,
 
+    /// The maximum call depth.
+    pub(crate) max_call_depth: u32,
+
     /// Gas cost per field encountered when parsing CBOR.
     pub(crate) ipld_cbor_scan_per_field: Gas,
 
@@ -479,14 +529,34 @@ pub struct PriceList {
 
     /// Gas cost for checking if CID is reachable.
     pub(crate) ipld_link_checked: Gas,
+
+    /// Gas refund credited when an actor self-destructs.
+    pub(crate) self_destruct_refund: Gas,
+
+    /// Refunds are capped at `1/max_refund_quotient` of the gas used by the message. Zero disables
+    /// refunds entirely.
+    pub(crate) max_refund_quotient: u64,
 }
 
+/// The costs of executing Wasm instructions, grouped by opcode class:
+///
+/// - Control flow: `jump_unconditional`, `jump_conditional`, `jump_indirect`, and `call`.
+/// - Arithmetic: `math_default`.
+/// - Memory (loads, stores, and size queries): `memory_default`.
+/// - Bulk memory (copies, fills, and table/memory initialization): `bulk_memory_default`, plus
+///   the per-byte costs below.
+/// - Everything else (constants, locals, globals, etc.): `instruction_default`.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct WasmGasPrices {
     /// The default gas cost for instructions.
     pub(crate) instruction_default: Gas,
     /// The default gas cost for math instructions.
     pub(crate) math_default: Gas,
+    /// The default gas cost for memory instructions (not including the memory access/fill costs).
+    pub(crate) memory_default: Gas,
+    /// The default gas cost for bulk memory instructions (not including the memory access/fill
+    /// and per-byte costs).
+    pub(crate) bulk_memory_default: Gas,
     /// The gas cost for unconditional jumps.
     pub(crate) jump_unconditional: Gas,
     /// The gas cost for conditional jumps.
@@ -507,12 +577,18 @@ pub struct WasmGasPrices {
 }
 
 impl PriceList {
+    /// Returns the maximum call depth for this network version. This is the default for
+    /// [`NetworkConfig::max_call_depth`](crate::machine::NetworkConfig::max_call_depth).
+    pub fn max_call_depth(&self) -> u32 {
+        self.max_call_depth
+    }
+
     /// Returns the gas required for storing a message of a given size in the chain, plus the cost
     /// of updating the sending actor's nonce and balance in the state-tree.
     #[inline]
     pub fn on_chain_message(&self, msg_size: usize) -> GasCharge {
         GasCharge::new(
-            "OnChainMessage",
+            "message/inclusion",
             self.on_chain_message_compute.apply(msg_size),
             self.actor_update + self.on_chain_message_storage.apply(msg_size),
         )
@@ -521,14 +597,18 @@ impl PriceList {
     /// Returns the gas required when invoking a method.
     #[inline]
     pub fn on_value_transfer(&self) -> GasCharge {
-        GasCharge::new("OnValueTransfer", self.send_transfer_funds, Zero::zero())
+        GasCharge::new(
+            "call/value_transfer",
+            self.send_transfer_funds,
+            Zero::zero(),
+        )
     }
 
     /// Returns the gas required when invoking a method.
     #[inline]
     pub fn on_method_invocation(&self, _param_size: u32, param_links: usize) -> GasCharge {
         let charge = self.send_invoke_method + self.ipld_link_tracked * param_links;
-        GasCharge::new("OnMethodInvocation", charge, Zero::zero())
+        GasCharge::new("call/invoke", charge, Zero::zero())
     }
 
     /// Returns the gas required for returning a value from a method. At the top-level, this charges
@@ -542,13 +622,13 @@ impl PriceList {
     ) -> GasCharge {
         if call_depth == 1 {
             GasCharge::new(
-                "OnChainReturnValue",
+                "message/return_value",
                 self.on_chain_return_compute.apply(return_size),
                 self.on_chain_return_storage.apply(return_size),
             )
         } else {
             GasCharge::new(
-                "OnReturnValue",
+                "call/return_value",
                 self.ipld_link_tracked * return_links,
                 Zero::zero(),
             )
@@ -557,7 +637,7 @@ impl PriceList {
 
     /// Returns the gas cost to be applied on a syscall.
     pub fn on_syscall(&self) -> GasCharge {
-        GasCharge::new("OnSyscall", self.syscall_cost, Zero::zero())
+        GasCharge::new("syscall/overhead", self.syscall_cost, Zero::zero())
     }
 
     /// Returns the gas required for creating an actor. Pass `true` to when explicitly assigning a
@@ -568,13 +648,28 @@ impl PriceList {
         if new_address {
             gas += self.address_assignment + self.address_lookup;
         }
-        GasCharge::new("OnCreateActor", Zero::zero(), gas)
+        GasCharge::new("syscall/actor/create_actor", Zero::zero(), gas)
     }
 
     /// Returns the gas required for deleting an actor.
     #[inline]
     pub fn on_delete_actor(&self) -> GasCharge {
-        GasCharge::new("OnDeleteActor", Zero::zero(), Zero::zero())
+        GasCharge::new("syscall/self/self_destruct", Zero::zero(), Zero::zero())
+    }
+
+    /// Returns the gas refund credited for deleting an actor.
+    #[inline]
+    pub fn on_delete_actor_refund(&self) -> Gas {
+        self.self_destruct_refund
+    }
+
+    /// Returns the maximum refund that may be applied to a message that used the given gas.
+    #[inline]
+    pub fn max_refund(&self, gas_used: Gas) -> Gas {
+        match self.max_refund_quotient {
+            0 => Gas::zero(),
+            q => Gas::from_milligas(gas_used.as_milligas() / q),
+        }
     }
 
     /// Returns gas required for signature verification.
@@ -582,14 +677,54 @@ impl PriceList {
     pub fn on_verify_signature(&self, sig_type: SignatureType, data_len: usize) -> GasCharge {
         let cost = self.sig_cost[&sig_type];
         let gas = cost.apply(data_len);
-        GasCharge::new("OnVerifySignature", gas, Zero::zero())
+        GasCharge::new("syscall/crypto/verify_signature", gas, Zero::zero())
+    }
+
+    /// Returns gas required for verifying a batch of signatures, given the type and data length
+    /// of each entry.
+    #[inline]
+    pub fn on_verify_signatures_batch(
+        &self,
+        entries: impl IntoIterator<Item = (SignatureType, usize)>,
+    ) -> GasCharge {
+        let mut has_bls = false;
+        let gas = entries
+            .into_iter()
+            .fold(Gas::zero(), |gas, (sig_type, data_len)| {
+                has_bls |= sig_type == SignatureType::BLS;
+                gas + self.sig_batch_cost[&sig_type].apply(data_len)
+            });
+        let base = if has_bls {
+            self.bls_batch_base
+        } else {
+            Gas::zero()
+        };
+        GasCharge::new(
+            "syscall/crypto/verify_signatures_batch",
+            base + gas,
+            Zero::zero(),
+        )
+    }
+
+    /// Returns gas required for individually verifying the BLS signatures of a batch whose
+    /// aggregate failed to verify, given the data length of each signature.
+    #[inline]
+    pub fn on_verify_signatures_batch_fallback(
+        &self,
+        data_lens: impl IntoIterator<Item = usize>,
+    ) -> GasCharge {
+        let cost = self.sig_cost[&SignatureType::BLS];
+        let gas = data_lens
+            .into_iter()
+            .fold(Gas::zero(), |gas, len| gas + cost.apply(len));
+        GasCharge::new("syscall/crypto/verify_signatures_batch", gas, Zero::zero())
     }
 
     /// Returns gas required for recovering signer pubkey from signature
     #[inline]
     pub fn on_recover_secp_public_key(&self) -> GasCharge {
         GasCharge::new(
-            "OnRecoverSecpPublicKey",
+            "syscall/crypto/recover_secp_public_key",
             self.secp256k1_recover_cost,
             Zero::zero(),
         )
@@ -600,13 +735,47 @@ impl PriceList {
     pub fn on_hashing(&self, hasher: SupportedHashes, data_len: usize) -> GasCharge {
         let cost = self.hashing_cost[&hasher];
         let gas = cost.apply(data_len);
-        GasCharge::new("OnHashing", gas, Zero::zero())
+        GasCharge::new("syscall/crypto/hash", gas, Zero::zero())
+    }
+
+    /// Returns the gas required for verifying a Merkle proof, given the sizes of the nodes hashed.
+    #[inline]
+    pub fn on_verify_merkle_proof(
+        &self,
+        hasher: SupportedHashes,
+        node_sizes: &[usize],
+    ) -> GasCharge {
+        let cost = self.hashing_cost[&hasher];
+        let gas = node_sizes.iter().fold(Gas::zero(), |gas, size| {
+            gas + self.merkle_proof_node + cost.apply(*size)
+        });
+        GasCharge::new("syscall/crypto/verify_merkle_proof", gas, Zero::zero())
+    }
+
+    /// Returns the gas required for decoding a CBOR-encoded Merkle proof of the given size.
+    #[inline]
+    pub fn on_decode_merkle_proof(&self, len: usize) -> GasCharge {
+        GasCharge::new(
+            "syscall/crypto/verify_merkle_proof/decode",
+            self.merkle_proof_decode.apply(len),
+            Zero::zero(),
+        )
+    }
+
+    /// Returns the gas required for validating UTF-8 on behalf of an actor.
+    #[inline]
+    pub fn on_validate_utf8(&self, len: usize) -> GasCharge {
+        GasCharge::new(
+            "syscall/util/validate_utf8",
+            self.utf8_validation.apply(len),
+            Zero::zero(),
+        )
     }
 
     #[inline]
     pub fn on_utf8_validation(&self, len: usize) -> GasCharge {
         GasCharge::new(
-            "OnUtf8Validation",
+            "syscall/event/utf8_validation",
             self.utf8_validation.apply(len),
             Zero::zero(),
         )
@@ -620,7 +789,7 @@ impl PriceList {
         _pieces: &[PieceInfo],
     ) -> GasCharge {
         GasCharge::new(
-            "OnComputeUnsealedSectorCid",
+            "syscall/crypto/compute_unsealed_sector_cid",
             self.compute_unsealed_sector_cid_base,
             Zero::zero(),
         )
@@ -629,7 +798,11 @@ impl PriceList {
     /// Returns gas required for seal verification.
     #[inline]
     pub fn on_verify_seal(&self, _info: &SealVerifyInfo) -> GasCharge {
-        GasCharge::new("OnVerifySeal", self.verify_seal_base, Zero::zero())
+        GasCharge::new(
+            "syscall/crypto/verify_seal",
+            self.verify_seal_base,
+            Zero::zero(),
+        )
     }
     #[inline]
     pub fn on_verify_aggregate_seals(
@@ -661,7 +834,7 @@ impl PriceList {
         // Should be safe because there is a limit to how much seals get aggregated
         let num = aggregate.infos.len() as u64;
         GasCharge::new(
-            "OnVerifyAggregateSeals",
+            "syscall/crypto/verify_aggregate_seals",
             per_proof * num + step.lookup(num),
             Zero::zero(),
         )
@@ -671,7 +844,7 @@ impl PriceList {
     #[inline]
     pub fn on_verify_replica_update(&self, _replica: &ReplicaUpdateInfo) -> GasCharge {
         GasCharge::new(
-            "OnVerifyReplicaUpdate",
+            "syscall/crypto/verify_replica_update",
             self.verify_replica_update,
             Zero::zero(),
         )
@@ -693,7 +866,7 @@ impl PriceList {
 
         let gas_used = cost.apply(info.challenged_sectors.len());
 
-        GasCharge::new("OnVerifyPost", gas_used, Zero::zero())
+        GasCharge::new("syscall/crypto/verify_post", gas_used, Zero::zero())
     }
 
     /// Returns gas required for verifying consensus fault.
@@ -705,7 +878,7 @@ impl PriceList {
         _extra_len: usize,
     ) -> GasCharge {
         GasCharge::new(
-            "OnVerifyConsensusFault",
+            "syscall/crypto/verify_consensus_fault",
             Zero::zero(),
             self.verify_consensus_fault,
         )
@@ -715,7 +888,7 @@ impl PriceList {
     #[inline]
     pub fn on_get_randomness(&self, lookback: ChainEpoch) -> GasCharge {
         GasCharge::new(
-            "OnGetRandomness",
+            "syscall/rand/get_randomness",
             Zero::zero(),
             self.lookback_cost.apply(lookback as u64),
         )
@@ -725,7 +898,7 @@ impl PriceList {
     #[inline]
     pub fn on_block_open_base(&self) -> GasCharge {
         GasCharge::new(
-            "OnBlockOpenBase",
+            "syscall/ipld/block_open_base",
             self.ipld_link_checked,
             self.block_open.flat,
         )
@@ -744,7 +917,7 @@ impl PriceList {
         let retention_min = self.block_memory_retention_minimum.apply(data_size);
         let retention_surcharge = (retention_min - (compute + block_open)).max(Gas::zero());
         GasCharge::new(
-            "OnBlockOpen",
+            "syscall/ipld/block_open",
             compute,
             // We charge the `block_open` fee as "extra" to make sure the FVM benchmarks still work.
             block_open + retention_surcharge,
@@ -755,7 +928,7 @@ impl PriceList {
     #[inline]
     pub fn on_block_read(&self, data_size: usize) -> GasCharge {
         GasCharge::new(
-            "OnBlockRead",
+            "syscall/ipld/block_read",
             self.block_memcpy.apply(data_size),
             Zero::zero(),
         )
@@ -773,7 +946,7 @@ impl PriceList {
         let retention_min = self.block_memory_retention_minimum.apply(data_size);
         let retention_surcharge = (retention_min - compute).max(Gas::zero());
 
-        GasCharge::new("OnBlockCreate", compute, retention_surcharge)
+        GasCharge::new("syscall/ipld/block_create", compute, retention_surcharge)
     }
 
     /// Returns the gas required for committing an object to the state blockstore.
@@ -795,45 +968,53 @@ impl PriceList {
         // per-byte charges combined, so we ignore them for simplicity.
         let deferred_compute = self.block_persist_compute;
 
-        GasCharge::new("OnBlockLink", initial_compute, deferred_compute + storage)
+        GasCharge::new(
+            "syscall/ipld/block_link",
+            initial_compute,
+            deferred_compute + storage,
+        )
     }
 
     /// Returns the gas required for storing an object.
     #[inline]
     pub fn on_block_stat(&self) -> GasCharge {
-        GasCharge::new("OnBlockStat", Zero::zero(), Zero::zero())
+        GasCharge::new("syscall/ipld/block_stat", Zero::zero(), Zero::zero())
     }
 
     /// Returns the gas required to lookup an actor in the state-tree.
     #[inline]
     pub fn on_actor_lookup(&self) -> GasCharge {
-        GasCharge::new("OnActorLookup", Zero::zero(), self.actor_lookup)
+        GasCharge::new("state/actor_lookup", Zero::zero(), self.actor_lookup)
     }
 
     /// Returns the gas required to update an actor in the state-tree. Assumes that the actor lookup
     /// fee has already been charged.
     #[inline]
     pub fn on_actor_update(&self) -> GasCharge {
-        GasCharge::new("OnActorUpdate", Zero::zero(), self.actor_update)
+        GasCharge::new("state/actor_update", Zero::zero(), self.actor_update)
     }
 
     /// Returns the gas required to create a new actor in the state-tree. Assumes that the actor
     /// lookup and update fees have already been charged.
     #[inline]
     pub fn on_actor_create(&self) -> GasCharge {
-        GasCharge::new("OnActorCreate", Zero::zero(), self.actor_create_storage)
+        GasCharge::new(
+            "state/actor_create",
+            Zero::zero(),
+            self.actor_create_storage,
+        )
     }
 
     /// Returns the gas required for accessing the balance of the current actor.
     #[inline]
     pub fn on_self_balance(&self) -> GasCharge {
-        GasCharge::new("OnSelfBalance", Zero::zero(), Zero::zero())
+        GasCharge::new("syscall/self/balance", Zero::zero(), Zero::zero())
     }
 
     /// Returns the gas required for accessing the balance of an actor.
     #[inline]
     pub fn on_balance_of(&self) -> GasCharge {
-        GasCharge::new("OnBalanceOf", Zero::zero(), Zero::zero())
+        GasCharge::new("syscall/actor/balance_of", Zero::zero(), Zero::zero())
     }
 
     /// Returns the gas required for resolving an actor address.
@@ -841,13 +1022,17 @@ impl PriceList {
     /// Might require lookup in the state tree as well as loading the state of the init actor.
     #[inline]
     pub fn on_resolve_address(&self) -> GasCharge {
-        GasCharge::new("OnResolveAddress", Zero::zero(), Zero::zero())
+        GasCharge::new("syscall/actor/resolve_address", Zero::zero(), Zero::zero())
     }
 
     /// Returns the gas required for looking up an actor's delegated address.
     #[inline]
     pub fn on_lookup_delegated_address(&self) -> GasCharge {
-        GasCharge::new("OnLookupAddress", Zero::zero(), Zero::zero())
+        GasCharge::new(
+            "syscall/actor/lookup_delegated_address",
+            Zero::zero(),
+            Zero::zero(),
+        )
     }
 
     /// Returns the gas required for getting the CID of the code of an actor.
@@ -855,14 +1040,18 @@ impl PriceList {
     /// Might require looking up the actor in the state tree.
     #[inline]
     pub fn on_get_actor_code_cid(&self) -> GasCharge {
-        GasCharge::new("OnGetActorCodeCid", Zero::zero(), Zero::zero())
+        GasCharge::new(
+            "syscall/actor/get_actor_code_cid",
+            Zero::zero(),
+            Zero::zero(),
+        )
     }
 
     /// Returns the gas required for looking up the type of a builtin actor by CID.
     #[inline]
     pub fn on_get_builtin_actor_type(&self) -> GasCharge {
         GasCharge::new(
-            "OnGetBuiltinActorType",
+            "syscall/actor/get_builtin_actor_type",
             self.builtin_actor_manifest_lookup,
             Zero::zero(),
         )
@@ -872,7 +1061,7 @@ impl PriceList {
     #[inline]
     pub fn on_get_code_cid_for_type(&self) -> GasCharge {
         GasCharge::new(
-            "OnGetCodeCidForType",
+            "syscall/actor/get_code_cid_for_type",
             self.builtin_actor_manifest_lookup,
             Zero::zero(),
         )
@@ -882,7 +1071,7 @@ impl PriceList {
     #[inline]
     pub fn on_tipset_cid(&self, lookback: ChainEpoch) -> GasCharge {
         GasCharge::new(
-            "OnTipsetCid",
+            "syscall/network/tipset_cid",
             Zero::zero(),
             self.lookback_cost.apply(lookback as u64),
         )
@@ -891,19 +1080,27 @@ impl PriceList {
     /// Returns the gas required for accessing the network context.
     #[inline]
     pub fn on_network_context(&self) -> GasCharge {
-        GasCharge::new("OnNetworkContext", self.network_context, Zero::zero())
+        GasCharge::new(
+            "syscall/network/context",
+            self.network_context,
+            Zero::zero(),
+        )
     }
 
     /// Returns the gas required for accessing the message context.
     #[inline]
     pub fn on_message_context(&self) -> GasCharge {
-        GasCharge::new("OnMessageContext", self.message_context, Zero::zero())
+        GasCharge::new(
+            "syscall/vm/message_context",
+            self.message_context,
+            Zero::zero(),
+        )
     }
 
     /// Returns the gas required for installing an actor.
     pub fn on_install_actor(&self, wasm_size: usize) -> GasCharge {
         GasCharge::new(
-            "OnInstallActor",
+            "syscall/actor/install_actor",
             self.install_wasm_per_byte_cost * wasm_size,
             Zero::zero(),
         )
@@ -949,7 +1146,7 @@ impl PriceList {
         let hash = self.hashing_cost[&SupportedHashes::Blake2b256].apply(estimated_size);
 
         GasCharge::new(
-            "OnActorEvent",
+            "syscall/event/emit_event",
             // Charge for validation/storing/serializing events.
             mem * 2u32 + validate_entries + validate_utf8,
             // Charge for forming the AMT and returning the events to the client.
@@ -960,20 +1157,60 @@ impl PriceList {
 
     #[inline]
     pub fn on_get_root(&self) -> GasCharge {
-        GasCharge::new("OnActorGetRoot", self.ipld_link_tracked, Gas::zero())
+        GasCharge::new("syscall/self/root", self.ipld_link_tracked, Gas::zero())
     }
 
     #[inline]
     pub fn on_set_root(&self) -> GasCharge {
-        GasCharge::new("OnActorSetRoot", self.ipld_link_checked, Gas::zero())
+        GasCharge::new("syscall/self/set_root", self.ipld_link_checked, Gas::zero())
     }
 }
 
-/// Returns gas price list by NetworkVersion for gas consumption.
+/// Returns the built-in gas price list by NetworkVersion for gas consumption. Use a
+/// [`PriceListRegistry`](super::PriceListRegistry) to take embedder overrides into account.
 pub fn price_list_by_network_version(network_version: NetworkVersion) -> &'static PriceList {
+    try_price_list_by_network_version(network_version)
+        .unwrap_or_else(|| panic!("network version {nv} not supported", nv = network_version))
+}
+
+/// Returns the gas prices for the given network version, if supported.
+pub(crate) fn try_price_list_by_network_version(
+    network_version: NetworkVersion,
+) -> Option<&'static PriceList> {
     match network_version {
-        NetworkVersion::V21 => &WATERMELON_PRICES,
-        _ => panic!("network version {nv} not supported", nv = network_version),
+        NetworkVersion::V21 | NetworkVersion::V22 => Some(&WATERMELON_PRICES),
+        _ => None,
+    }
+}
+
+impl WasmGasPrices {
+    /// Returns the gas cost of one unit of wasmtime fuel, for metering execution with fuel instead
+    /// of instrumentation (see [`GasMetering::Fuel`](crate::engine::GasMetering::Fuel)).
+    ///
+    /// Wasmtime charges one unit of fuel per instruction, except for the instructions that are
+    /// free under FIP-0032, so the conversion is only exact if all other instructions cost the
+    /// same. Returns `None` if they don't. The per-byte costs of bulk memory instructions can't be
+    /// expressed in fuel, and are ignored.
+    pub fn fuel_price(&self) -> Option<Gas> {
+        let price = self.instruction_default;
+        let uniform = [
+            self.math_default,
+            self.memory_default,
+            self.bulk_memory_default,
+            self.jump_unconditional,
+            self.jump_conditional,
+            self.jump_indirect,
+        ]
+        .iter()
+        .all(|&cost| cost == price);
+        let no_extras = [
+            self.call,
+            self.memory_access_cost,
+            self.memory_fill_base_cost,
+        ]
+        .iter()
+        .all(|cost| cost.is_zero());
+        (uniform && no_extras && !price.is_zero()).then_some(price)
     }
 }
 
@@ -1121,7 +1358,7 @@ impl Rules for WasmGasPrices {
             F32Load, I32Load, I32Load8U, I32Load16U,
             F64Load, I64Load, I64Load8U, I64Load16U, I64Load32U,
             TableGet,
-            => fixed(self.instruction_default + self.memory_access_cost),
+            => fixed(self.memory_default + self.memory_access_cost),
 
             // Sign-extending loads.
             I32Load16S,
@@ -1129,38 +1366,38 @@ impl Rules for WasmGasPrices {
             I64Load8S,
             I64Load16S,
             I64Load32S,
-            => fixed(self.instruction_default + self.memory_access_cost),
+            => fixed(self.memory_default + self.memory_access_cost),
 
             // Stores cost an instruction and a base fill fee.
             F32Store, I32Store, I32Store8, I32Store16,
             F64Store, I64Store, I64Store8, I64Store16, I64Store32,
             TableSet,
-            => fixed(self.instruction_default + self.memory_fill_base_cost),
+            => fixed(self.memory_default + self.memory_fill_base_cost),
 
             // Bulk memory copies & fills
             TableInit, TableCopy => linear(
-                self.instruction_default + self.memory_access_cost,
+                self.bulk_memory_default + self.memory_access_cost,
                 self.memory_copy_per_byte_cost,
                 TABLE_ELEMENT_SIZE,
             ),
             TableFill, TableGrow => linear(
-                self.instruction_default + self.memory_fill_base_cost,
+                self.bulk_memory_default + self.memory_fill_base_cost,
                 self.memory_fill_per_byte_cost,
                 TABLE_ELEMENT_SIZE,
             ),
             MemoryGrow => linear(
-                self.instruction_default + self.memory_fill_base_cost,
+                self.bulk_memory_default + self.memory_fill_base_cost,
                 self.memory_fill_per_byte_cost,
                 // This is the odd-one out because it operates on entire pages.
                 wasmtime_environ::WASM_PAGE_SIZE,
             ),
             MemoryFill => linear(
-                self.instruction_default + self.memory_fill_base_cost,
+                self.bulk_memory_default + self.memory_fill_base_cost,
                 self.memory_fill_per_byte_cost,
                 1,
             ),
             MemoryInit, MemoryCopy => linear(
-                self.instruction_default + self.memory_access_cost,
+                self.bulk_memory_default + self.memory_access_cost,
                 self.memory_copy_per_byte_cost,
                 1,
             ),
@@ -1168,10 +1405,10 @@ impl Rules for WasmGasPrices {
             // Dropping is an optimization hint and probably shouldn't cost anything. But we don't
             // use this right now anyways.
             // TODO(M2.2) consider making this free.
-            DataDrop, ElemDrop => fixed(self.instruction_default),
+            DataDrop, ElemDrop => fixed(self.bulk_memory_default),
 
             // Charge one instruction for getting a table/memory size.
-            MemorySize, TableSize => fixed(self.instruction_default),
+            MemorySize, TableSize => fixed(self.memory_default),
 
             /******************/
             /*  Unsupported   */
@@ -1335,3 +1572,57 @@ fn test_step_cost_zero() {
     assert_eq!(costs.lookup(0), Gas::new(1));
     assert_eq!(costs.lookup(10), Gas::new(1));
 }
+
+#[test]
+fn test_wasm_instruction_classes() {
+    let cost = |prices: &WasmGasPrices, op: &Operator| match prices.instruction_cost(op) {
+        Ok(InstructionCost::Fixed(milligas)) => milligas,
+        _ => panic!("expected a fixed cost"),
+    };
+
+    let mut prices = WATERMELON_PRICES.wasm_rules.clone();
+    prices.math_default = Gas::from_milligas(1);
+    prices.bulk_memory_default = Gas::from_milligas(2);
+    prices.instruction_default = Gas::from_milligas(3);
+
+    assert_eq!(cost(&prices, &Operator::I32Add), 1);
+    assert_eq!(cost(&prices, &Operator::DataDrop { data_index: 0 }), 2);
+    assert_eq!(cost(&prices, &Operator::I32Const { value: 0 }), 3);
+    assert_eq!(cost(&prices, &Operator::Nop), 0);
+}
+
+#[test]
+fn test_fuel_price() {
+    let mut prices = WATERMELON_PRICES.wasm_rules.clone();
+    assert_eq!(prices.fuel_price(), Some(Gas::new(4)));
+    // Per-byte costs are ignored.
+    prices.memory_copy_per_byte_cost = Gas::new(1);
+    assert_eq!(prices.fuel_price(), Some(Gas::new(4)));
+
+    prices.call = Gas::new(1);
+    assert_eq!(prices.fuel_price(), None);
+    prices.call = Gas::zero();
+    prices.math_default = Gas::new(5);
+    assert_eq!(prices.fuel_price(), None);
+}
+
+#[test]
+fn test_verify_signatures_batch() {
+    let single = |sig_type| WATERMELON_PRICES.on_verify_signature(sig_type, 32).total();
+    let batch = |entries: &[(SignatureType, usize)]| {
+        WATERMELON_PRICES
+            .on_verify_signatures_batch(entries.iter().copied())
+            .total()
+    };
+
+    assert!(batch(&[]).is_zero());
+
+    // Secp256k1 signatures cost the same in a batch.
+    let secp = [(SignatureType::Secp256k1, 32); 4];
+    assert_eq!(batch(&secp), single(SignatureType::Secp256k1) * 4u32);
+
+    // A single BLS signature costs the same, but every additional one costs (about) half.
+    let bls = [(SignatureType::BLS, 32); 4];
+    assert_eq!(batch(&bls[..1]), single(SignatureType::BLS));
+    assert!(batch(&bls) < single(SignatureType::BLS) * 3u32);
+}
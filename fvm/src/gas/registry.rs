@@ -0,0 +1,86 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Selection of the [`PriceList`] used by each network version.
+use std::collections::BTreeMap;
+
+use fvm_shared::version::NetworkVersion;
+
+use super::price_list::try_price_list_by_network_version;
+use super::PriceList;
+
+/// The price lists used by each network version: the built-in (mainnet) price lists, unless
+/// overridden by the embedder.
+///
+/// Overrides let networks other than mainnet (e.g., subnets) diverge from mainnet pricing, and
+/// may also supply prices for network versions without built-in prices. Overriding prices is
+/// consensus-critical, so all nodes of a network must register the same overrides.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PriceListRegistry {
+    overrides: BTreeMap<NetworkVersion, &'static PriceList>,
+}
+
+impl PriceListRegistry {
+    /// Creates a registry without any overrides.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the price list of the given network version.
+    pub fn set_override(
+        &mut self,
+        network_version: NetworkVersion,
+        price_list: &'static PriceList,
+    ) -> &mut Self {
+        self.overrides.insert(network_version, price_list);
+        self
+    }
+
+    /// Removes the override of the given network version (if any), returning it.
+    pub fn remove_override(
+        &mut self,
+        network_version: NetworkVersion,
+    ) -> Option<&'static PriceList> {
+        self.overrides.remove(&network_version)
+    }
+
+    /// Returns true if the price list of the given network version is overridden.
+    pub fn is_overridden(&self, network_version: NetworkVersion) -> bool {
+        self.overrides.contains_key(&network_version)
+    }
+
+    /// Returns the price list of the given network version, if overridden or supported.
+    pub fn get(&self, network_version: NetworkVersion) -> Option<&'static PriceList> {
+        self.overrides
+            .get(&network_version)
+            .copied()
+            .or_else(|| try_price_list_by_network_version(network_version))
+    }
+}
+
+#[test]
+fn test_price_list_registry() {
+    let builtin = try_price_list_by_network_version(NetworkVersion::V21).unwrap();
+
+    let mut registry = PriceListRegistry::new();
+    assert_eq!(registry.get(NetworkVersion::V21), Some(builtin));
+    assert_eq!(registry.get(NetworkVersion::V20), None);
+
+    let mut custom = builtin.clone();
+    custom.max_call_depth = 16;
+    let custom: &'static PriceList = Box::leak(Box::new(custom));
+    registry
+        .set_override(NetworkVersion::V21, custom)
+        .set_override(NetworkVersion::V20, custom);
+    assert!(registry.is_overridden(NetworkVersion::V21));
+    assert_eq!(registry.get(NetworkVersion::V21), Some(custom));
+    assert_eq!(registry.get(NetworkVersion::V20), Some(custom));
+
+    // Network configs select their price list from the registry.
+    let nc =
+        crate::machine::NetworkConfig::new_with_price_lists(NetworkVersion::V20, registry.clone());
+    assert_eq!(nc.price_list, custom);
+
+    assert_eq!(registry.remove_override(NetworkVersion::V21), Some(custom));
+    assert!(!registry.is_overridden(NetworkVersion::V21));
+    assert_eq!(registry.get(NetworkVersion::V21), Some(builtin));
+}
@@ -95,6 +95,16 @@ where
         }
     }
 
+    /// Returns the keys changed since the specified point in history, with their values at that
+    /// point (None if they weren't in the map).
+    pub fn changed_since(&self, height: usize) -> HashMap<&K, Option<&V>> {
+        let mut changed = HashMap::new();
+        for (k, v) in &self.history[height.min(self.history.len())..] {
+            changed.entry(k).or_insert(v.as_ref());
+        }
+        changed
+    }
+
     /// Returns the current history length.
     pub fn history_len(&self) -> usize {
         self.history.len()
@@ -105,6 +115,18 @@ where
         self.history.clear();
     }
 
+    /// Removes the entries for which the predicate returns false. Removals aren't recorded, so this
+    /// discards all undo history.
+    pub fn retain(&mut self, f: impl FnMut(&K, &mut V) -> bool) {
+        self.history.clear();
+        self.map.retain(f);
+    }
+
+    /// Iterate over the current map.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map.iter()
+    }
+
     /// Iterate mutably over the current map.
     pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
         self.map.iter_mut()
@@ -40,29 +40,33 @@ pub struct State {
 }
 
 impl State {
-    // ideally we would just #[cfg(test)] this, but it is used by non test-gated code in
-    // integration/tester.
-    #[allow(unused)]
-    pub fn new_test<B: Blockstore>(store: &B) -> Self {
+    /// Creates the init actor state of a new network with the given name, with an empty address
+    /// map. IDs below 100 are reserved for singleton actors.
+    pub fn new<B: Blockstore>(store: &B, network_name: impl Into<String>) -> Result<Self> {
         #[cfg(feature = "m2-native")]
         use cid::multihash::Code::Blake2b256;
 
-        // Empty hamt Cid used for testing
-        let e_cid = Hamt::<_, String>::new_with_bit_width(&store, 5)
+        let address_map = Hamt::<_, String>::new_with_bit_width(&store, HAMT_BIT_WIDTH)
             .flush()
-            .unwrap();
+            .or_fatal()?;
 
-        // Empty list Cid used for testing
         #[cfg(feature = "m2-native")]
-        let el_cid = store.put_cbor(&Vec::<Cid>::new(), Blake2b256).unwrap();
+        let installed_actors = store.put_cbor(&Vec::<Cid>::new(), Blake2b256).or_fatal()?;
 
-        State {
-            address_map: e_cid,
+        Ok(State {
+            address_map,
             next_id: 100,
-            network_name: "test".to_owned(),
+            network_name: network_name.into(),
             #[cfg(feature = "m2-native")]
-            installed_actors: el_cid,
-        }
+            installed_actors,
+        })
+    }
+
+    // ideally we would just #[cfg(test)] this, but it is used by non test-gated code in
+    // integration/tester.
+    #[allow(unused)]
+    pub fn new_test<B: Blockstore>(store: &B) -> Self {
+        Self::new(store, "test").unwrap()
     }
 
     /// Loads the init actor state from the supplied state tree.
@@ -70,19 +74,10 @@ impl State {
     where
         B: Blockstore,
     {
-        let init_act = state_tree
-            .get_actor(INIT_ACTOR_ID)?
+        state_tree
+            .get_actor_state(INIT_ACTOR_ID)?
             .context("init actor address could not be resolved")
-            .or_fatal()?;
-
-        let state = state_tree
-            .store()
-            .get_cbor(&init_act.state)
-            .or_fatal()?
-            .context("init actor state not found")
-            .or_fatal()?;
-
-        Ok((state, init_act))
+            .or_fatal()
     }
 
     /// Allocates a new ID address and stores a mapping of the argument address to it.
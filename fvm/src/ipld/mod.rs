@@ -129,7 +129,7 @@ pub fn scan_for_reachable_links(
     let start = GasTimer::start();
     let mut visitor = LinkVisitor::new(price_list, gas_tracker.gas_available());
     let ret = scan_for_links_inner(&mut visitor, codec, data);
-    let t = gas_tracker.charge_gas("OnScanIpldLinks", visitor.gas_used())?;
+    let t = gas_tracker.charge_gas("syscall/ipld/scan_links", visitor.gas_used())?;
     let ret = ret.map(|_| visitor.finish());
     t.stop_with(start);
     ret
@@ -15,6 +15,28 @@ use crate::syscall_error;
 pub struct BlockRegistry {
     blocks: Vec<Block>,
     reachable: HashSet<Cid>,
+    limits: BlockLimits,
+}
+
+/// Limits on the blocks held by a [`BlockRegistry`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BlockLimits {
+    /// The maximum size of a newly created block, in bytes.
+    pub max_block_size: usize,
+    /// The maximum number of open block handles.
+    pub max_handles: u32,
+    /// The maximum number of links (CIDs) in a newly created block.
+    pub max_links: usize,
+}
+
+impl Default for BlockLimits {
+    fn default() -> Self {
+        BlockLimits {
+            max_block_size: 1 << 20,
+            max_handles: i32::MAX as u32,
+            max_links: usize::MAX,
+        }
+    }
 }
 
 /// Blocks in the block registry are addressed by an ordinal, starting from 1 (`FIRST_ID`).
@@ -23,9 +45,8 @@ pub struct BlockRegistry {
 pub type BlockId = u32;
 
 const FIRST_ID: BlockId = 1;
-const MAX_BLOCKS: u32 = i32::MAX as u32; // TODO(M2): Limit
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct BlockStat {
     pub codec: u64,
     pub size: u32,
@@ -90,8 +111,17 @@ impl From<&Block> for IpldBlock {
 }
 
 impl BlockRegistry {
-    pub(crate) fn new() -> Self {
-        Self::default()
+    /// Creates a new, empty, block registry enforcing the given limits.
+    pub fn new(limits: BlockLimits) -> Self {
+        BlockRegistry {
+            blocks: Vec::new(),
+            reachable: HashSet::new(),
+            // Block IDs must fit in an i32 (they're passed to actors as such).
+            limits: BlockLimits {
+                max_handles: limits.max_handles.min(i32::MAX as u32),
+                ..limits
+            },
+        }
     }
 }
 
@@ -105,11 +135,33 @@ impl BlockRegistry {
     /// Adds a new block to the registry, checking that all children are currently reachable,
     /// returning a handle to refer to it. Use this when creating a _new_ block.
     //
-    //  Returns a `NotFound` error if `block` references any unreachable CIDs.
+    //  Returns a `NotFound` error if `block` references any unreachable CIDs, and a `LimitExceeded`
+    //  error if `block` is too large or has too many links.
     pub fn put_check_reachable(&mut self, block: Block) -> Result<BlockId> {
+        self.check_size(block.size() as usize)?;
+        if block.links().len() > self.limits.max_links {
+            return Err(syscall_error!(LimitExceeded;
+                "blocks may not have more than {} links", self.limits.max_links)
+            .into());
+        }
         self.put_inner(block, true)
     }
 
+    /// Checks that a new block of the given size may be created.
+    pub fn check_size(&self, size: usize) -> Result<()> {
+        if size > self.limits.max_block_size {
+            return Err(syscall_error!(LimitExceeded;
+                "blocks may not be larger than {} bytes", self.limits.max_block_size)
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Returns the limits enforced by this registry.
+    pub fn limits(&self) -> &BlockLimits {
+        &self.limits
+    }
+
     /// Mark a cid as reachable. Call this when a new block is linked into the state.
     pub fn mark_reachable(&mut self, k: &Cid) {
         self.reachable.insert(*k);
@@ -172,6 +224,6 @@ impl BlockRegistry {
     }
 
     pub fn is_full(&self) -> bool {
-        self.blocks.len() as u32 == MAX_BLOCKS
+        self.blocks.len() as u32 >= self.limits.max_handles
     }
 }
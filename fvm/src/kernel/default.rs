@@ -3,6 +3,7 @@
 use std::convert::{TryFrom, TryInto};
 use std::panic::{self, UnwindSafe};
 use std::path::PathBuf;
+use std::time::Duration;
 
 use anyhow::{anyhow, Context as _};
 use cid::Cid;
@@ -17,13 +18,15 @@ use fvm_shared::sys::out::vm::ContextFlags;
 use fvm_shared::upgrade::UpgradeInfo;
 use fvm_shared::ActorID;
 use multihash::MultihashDigest;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use super::blocks::{Block, BlockRegistry};
 use super::error::Result;
 use super::hash::SupportedHashes;
+use super::merkle;
 use super::*;
 use crate::call_manager::{
-    CallManager, Entrypoint, InvocationResult, INVOKE_FUNC_NAME, NO_DATA_BLOCK_ID,
+    CallManager, DeferredSend, Entrypoint, InvocationResult, INVOKE_FUNC_NAME, NO_DATA_BLOCK_ID,
     UPGRADE_FUNC_NAME,
 };
 use crate::externs::{Chain, Rand};
@@ -266,6 +269,34 @@ where
             Err(err) => Err(err),
         }
     }
+
+    fn defer_send(
+        &mut self,
+        recipient: &Address,
+        method: MethodNum,
+        params_id: BlockId,
+        value: &TokenAmount,
+        gas_limit: Gas,
+    ) -> Result<()> {
+        if self.read_only {
+            return Err(syscall_error!(ReadOnly; "cannot defer sends when read-only").into());
+        }
+
+        let params = if params_id == NO_DATA_BLOCK_ID {
+            None
+        } else {
+            Some(self.blocks.get(params_id)?.clone())
+        };
+
+        self.call_manager.defer_send(DeferredSend {
+            from: self.actor_id,
+            to: *recipient,
+            method,
+            params,
+            value: value.clone(),
+            gas_limit,
+        })
+    }
 }
 
 impl<C> DefaultKernel<C>
@@ -316,12 +347,12 @@ where
             return Err(syscall_error!(NotFound; "new root cid not reachable: {new}").into());
         }
 
-        let mut state = self
-            .call_manager
-            .get_actor(self.actor_id)?
+        self.call_manager
+            .mutate_actor(self.actor_id, |state| {
+                state.state = new;
+                Ok(())
+            })?
             .ok_or_else(|| syscall_error!(IllegalOperation; "actor deleted"))?;
-        state.state = new;
-        self.call_manager.set_actor(self.actor_id, state)?;
         Ok(())
     }
 
@@ -364,8 +395,15 @@ where
                 .or_fatal()?;
         }
 
-        // Delete the executing actor.
-        t.record(self.call_manager.delete_actor(self.actor_id))
+        // Delete the executing actor, crediting a refund if it still existed.
+        let existed = self.call_manager.get_actor(self.actor_id)?.is_some();
+        t.record(self.call_manager.delete_actor(self.actor_id))?;
+        if existed {
+            self.call_manager
+                .gas_tracker()
+                .credit_refund(self.call_manager.price_list().on_delete_actor_refund());
+        }
+        Ok(())
     }
 }
 
@@ -416,9 +454,8 @@ where
     }
 
     fn block_create(&mut self, codec: u64, data: &[u8]) -> Result<BlockId> {
-        if data.len() > self.machine().context().max_block_size {
-            return Err(syscall_error!(LimitExceeded; "blocks may not be larger than 1MiB").into());
-        }
+        // Check the size before scanning for links.
+        self.blocks.check_size(data.len())?;
 
         if !ipld::ALLOWED_CODECS.contains(&codec) {
             return Err(syscall_error!(IllegalCodec; "codec {} not allowed", codec).into());
@@ -635,6 +672,109 @@ where
 
         t.record(Ok(hasher.digest(data)))
     }
+
+    fn verify_merkle_proof(&self, proof: &MerkleProof) -> Result<bool> {
+        let hasher = SupportedHashes::try_from(proof.hash_function).map_err(|e| {
+            if let multihash::Error::UnsupportedCode(code) = e {
+                syscall_error!(IllegalArgument; "unsupported hash code {}", code)
+            } else {
+                syscall_error!(AssertionFailed; "hash expected unsupported code, got {}", e)
+            }
+        })?;
+        let digest_len = hasher.digest(&[]).size() as usize;
+
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_verify_merkle_proof(hasher, &merkle::hashed_sizes(proof, digest_len)),
+        )?;
+
+        t.record(Ok(merkle::verify(proof, |data| {
+            hasher.digest(data).digest().to_vec()
+        })))
+    }
+
+    fn verify_signatures_batch(&self, entries: &[SignatureBatchEntry]) -> Result<Vec<bool>> {
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_verify_signatures_batch(entries.iter().map(|e| (e.sig_type, e.digest.len()))),
+        )?;
+
+        // As with `verify_signature`, we only support key addresses (f1/f3).
+        if let Some(e) = entries
+            .iter()
+            .find(|e| !matches!(e.signer.payload(), Payload::BLS(_) | Payload::Secp256k1(_)))
+        {
+            return Err(syscall_error!(IllegalArgument; "address protocol {} not supported", e.signer.protocol()).into());
+        }
+
+        let bls: Vec<_> = (0..entries.len())
+            .filter(|&i| entries[i].sig_type == SignatureType::BLS)
+            .collect();
+
+        // Verify the BLS signatures as a batch while verifying the secp256k1 signatures in
+        // parallel, catching errors. The batch only passes if every BLS signature is valid (see
+        // `verify_bls_batch`), so we don't need to check them individually.
+        let (bls_valid, mut results) =
+            t.record(catch_and_log_panic("verifying signatures", || {
+                Ok(rayon::join(
+                    || {
+                        let sigs: Vec<_> = bls.iter().map(|&i| &*entries[i].signature).collect();
+                        let data: Vec<_> = bls.iter().map(|&i| &*entries[i].digest).collect();
+                        let keys: Vec<_> = bls
+                            .iter()
+                            .map(|&i| entries[i].signer.payload_bytes())
+                            .collect();
+                        let keys: Vec<_> = keys.iter().map(Vec::as_slice).collect();
+                        signature::ops::verify_bls_batch(&sigs, &data, &keys)
+                    },
+                    || {
+                        entries
+                            .par_iter()
+                            .map(|e| {
+                                e.sig_type == SignatureType::Secp256k1
+                                    && signature::verify(
+                                        e.sig_type,
+                                        &e.signature,
+                                        &e.digest,
+                                        &e.signer,
+                                    )
+                                    .is_ok()
+                            })
+                            .collect::<Vec<_>>()
+                    },
+                ))
+            }))?;
+
+        if bls_valid {
+            for &i in &bls {
+                results[i] = true;
+            }
+            return Ok(results);
+        }
+
+        // At least one of the BLS signatures is invalid, so we verify them individually to find
+        // out which. This is charged separately so that valid batches stay cheap.
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_verify_signatures_batch_fallback(bls.iter().map(|&i| entries[i].digest.len())),
+        )?;
+        let bls_results = t.record(catch_and_log_panic("verifying signatures", || {
+            Ok(bls
+                .par_iter()
+                .map(|&i| {
+                    let e = &entries[i];
+                    signature::verify(e.sig_type, &e.signature, &e.digest, &e.signer).is_ok()
+                })
+                .collect::<Vec<_>>())
+        }))?;
+        for (&i, valid) in bls.iter().zip(bls_results) {
+            results[i] = valid;
+        }
+        Ok(results)
+    }
 }
 
 impl<C> GasOps for DefaultKernel<C>
@@ -656,6 +796,12 @@ where
     fn price_list(&self) -> &PriceList {
         self.call_manager.price_list()
     }
+
+    fn trace_syscall(&self, module: &'static str, name: &'static str, elapsed: Duration) {
+        self.call_manager
+            .gas_tracker()
+            .trace_syscall(module, name, elapsed)
+    }
 }
 
 impl<C> NetworkOps for DefaultKernel<C>
@@ -909,7 +1055,23 @@ where
     }
 
     fn debug_enabled(&self) -> bool {
-        self.call_manager.context().actor_debugging
+        let context = self.call_manager.context();
+        if !context.actor_debugging {
+            return false;
+        }
+        let Some(allowlist) = &context.debug_allowlist else {
+            return true;
+        };
+        // Look the code up directly in the state-tree as this must not charge gas.
+        let code = self
+            .call_manager
+            .machine()
+            .state_tree()
+            .get_actor(self.actor_id)
+            .ok()
+            .flatten()
+            .map(|actor| actor.code);
+        allowlist.permits(self.actor_id, code.as_ref())
     }
 
     fn store_artifact(&self, name: &str, data: &[u8]) -> Result<()> {
@@ -974,6 +1136,18 @@ where
     }
 }
 
+impl<C> UtilOps for DefaultKernel<C>
+where
+    C: CallManager,
+{
+    fn validate_utf8(&self, bytes: &[u8]) -> Result<bool> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_validate_utf8(bytes.len()))?;
+        t.record(Ok(std::str::from_utf8(bytes).is_ok()))
+    }
+}
+
 impl<C> EventOps for DefaultKernel<C>
 where
     C: CallManager,
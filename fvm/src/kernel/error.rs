@@ -2,7 +2,11 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::fmt::Display;
 
+use cid::Cid;
 use derive_more::Display;
+use fvm_ipld_encoding::tuple::*;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
 use fvm_shared::error::ErrorNumber;
 
 /// Execution result.
@@ -35,6 +39,12 @@ pub enum ExecutionError {
     OutOfGas,
     Syscall(SyscallError),
     Fatal(anyhow::Error),
+    /// The message ran for longer than the engine's (node-local) execution timeout, see
+    /// [`EngineConfig::execution_timeout`](crate::engine::EngineConfig::execution_timeout). Like
+    /// fatal errors, this aborts the entire call stack. The executor reports it as
+    /// [`ApplyFailure::Timeout`](crate::executor::ApplyFailure::Timeout).
+    #[display(fmt = "execution timed out")]
+    Timeout,
 }
 
 impl ExecutionError {
@@ -51,7 +61,7 @@ impl ExecutionError {
         use ExecutionError::*;
         match self {
             Fatal(_) => true,
-            OutOfGas | Syscall(_) => false,
+            OutOfGas | Syscall(_) | Timeout => false,
         }
     }
 }
@@ -106,6 +116,16 @@ impl<T, E> ClassifyResult for std::result::Result<T, E> {
 /// same time.
 pub trait Context {
     type WithContext;
+    /// Attaches structured [`ErrorContext`] to fatal errors. Syscall and out of gas errors are
+    /// returned unchanged.
+    ///
+    /// By default, the context is attached as a message with [`Context::context`].
+    fn error_context(self, context: ErrorContext) -> Self::WithContext
+    where
+        Self: Sized,
+    {
+        self.context(context)
+    }
     fn context<D>(self, context: D) -> Self::WithContext
     where
         D: Display;
@@ -117,6 +137,10 @@ pub trait Context {
 
 impl<T> Context for Result<T> {
     type WithContext = Result<T>;
+    fn error_context(self, context: ErrorContext) -> Self::WithContext {
+        self.map_err(|e| e.error_context(context))
+    }
+
     fn context<D: Display>(self, context: D) -> Self::WithContext {
         self.map_err(|e| e.context(context))
     }
@@ -132,12 +156,20 @@ impl<T> Context for Result<T> {
 
 impl Context for ExecutionError {
     type WithContext = Self;
+    fn error_context(self, context: ErrorContext) -> Self {
+        match self {
+            ExecutionError::Fatal(e) => ExecutionError::Fatal(context.attach(e)),
+            other => other,
+        }
+    }
+
     fn context<D: Display>(self, context: D) -> Self {
         use ExecutionError::*;
         match self {
             Syscall(e) => Syscall(SyscallError(format!("{}: {}", context, e.0), e.1)),
             Fatal(e) => Fatal(e.context(context.to_string())),
             OutOfGas => OutOfGas, // no reason necessary
+            Timeout => Timeout,
         }
     }
 
@@ -158,10 +190,85 @@ impl From<ExecutionError> for anyhow::Error {
             OutOfGas => anyhow::anyhow!("out of gas"),
             Syscall(err) => anyhow::anyhow!(err.0),
             Fatal(err) => err,
+            Timeout => anyhow::anyhow!("execution timed out"),
         }
     }
 }
 
+/// Structured context attached to fatal errors: the actor, epoch, and CID (if any) involved in the
+/// failure.
+///
+/// At most one `ErrorContext` is attached to a given error: [`ErrorContext::attach`] merges new
+/// fields into an existing context instead of adding another layer, so clients can always
+/// retrieve it with [`ErrorContext::of`], no matter how much textual context has been added since.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize_tuple, Deserialize_tuple)]
+pub struct ErrorContext {
+    /// The actor being executed or accessed when the error occurred.
+    pub actor: Option<Address>,
+    /// The epoch at which the error occurred.
+    pub epoch: Option<ChainEpoch>,
+    /// The CID of the offending block or state root.
+    pub cid: Option<Cid>,
+}
+
+impl ErrorContext {
+    /// Sets the actor involved in the failure.
+    pub fn with_actor(mut self, actor: Address) -> Self {
+        self.actor = Some(actor);
+        self
+    }
+
+    /// Sets the epoch at which the failure occurred.
+    pub fn with_epoch(mut self, epoch: ChainEpoch) -> Self {
+        self.epoch = Some(epoch);
+        self
+    }
+
+    /// Sets the CID of the offending block.
+    pub fn with_cid(mut self, cid: Cid) -> Self {
+        self.cid = Some(cid);
+        self
+    }
+
+    /// Attaches this context to the given error. If the error already carries an `ErrorContext`,
+    /// only the fields it's missing are filled in: the context closest to the failure wins.
+    pub fn attach(self, mut err: anyhow::Error) -> anyhow::Error {
+        match err.downcast_mut::<ErrorContext>() {
+            Some(existing) => {
+                existing.actor = existing.actor.or(self.actor);
+                existing.epoch = existing.epoch.or(self.epoch);
+                existing.cid = existing.cid.or(self.cid);
+                err
+            }
+            None => err.context(self),
+        }
+    }
+
+    /// Returns the context attached to the given error, if any.
+    pub fn of(err: &anyhow::Error) -> Option<&ErrorContext> {
+        err.downcast_ref()
+    }
+}
+
+impl Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut sep = "";
+        f.write_str("[")?;
+        if let Some(actor) = &self.actor {
+            write!(f, "{sep}actor={actor}")?;
+            sep = ", ";
+        }
+        if let Some(epoch) = &self.epoch {
+            write!(f, "{sep}epoch={epoch}")?;
+            sep = ", ";
+        }
+        if let Some(cid) = &self.cid {
+            write!(f, "{sep}cid={cid}")?;
+        }
+        f.write_str("]")
+    }
+}
+
 /// Represents an error from a syscall. It can optionally contain a
 /// syscall-advised exit code for the kind of error that was raised.
 /// We may want to add an optional source error here.
@@ -194,3 +301,37 @@ fn test_syscall_error_formatting() {
         "msg"
     );
 }
+
+#[test]
+fn test_error_context_merge() {
+    use anyhow::anyhow;
+
+    let cid = Cid::default();
+    let err = ErrorContext::default()
+        .with_actor(Address::new_id(100))
+        .attach(anyhow!("boom"))
+        .context("loading state");
+    let err = ErrorContext::default()
+        .with_actor(Address::new_id(1))
+        .with_epoch(10)
+        .with_cid(cid)
+        .attach(err);
+
+    // The innermost actor is retained, the missing fields are filled in.
+    assert_eq!(
+        ErrorContext::of(&err),
+        Some(&ErrorContext {
+            actor: Some(Address::new_id(100)),
+            epoch: Some(10),
+            cid: Some(cid),
+        })
+    );
+    assert_eq!(
+        format!("{:#}", err),
+        format!("loading state: [actor=f0100, epoch=10, cid={cid}]: boom")
+    );
+
+    // Only fatal errors carry context.
+    let err = ExecutionError::OutOfGas.error_context(ErrorContext::default().with_epoch(1));
+    assert!(matches!(err, ExecutionError::OutOfGas));
+}
@@ -4,7 +4,7 @@ use std::collections::BTreeMap;
 use std::convert::TryInto;
 use std::panic::{self, UnwindSafe};
 
-use ambassador::Delegate;
+use ambassador::{delegatable_trait, Delegate};
 use filecoin_proofs_api::{self as proofs, ProverId, PublicReplicaInfo, SectorId};
 
 use fvm_ipld_encoding::bytes_32;
@@ -29,6 +29,7 @@ lazy_static! {
     static ref INITIAL_RESERVE_BALANCE: TokenAmount = TokenAmount::from_whole(300_000_000);
 }
 
+#[delegatable_trait]
 pub trait FilecoinKernel: Kernel {
     /// Computes an unsealed sector CID (CommD) from its constituent piece CIDs (CommPs) and sizes.
     fn compute_unsealed_sector_cid(
@@ -84,6 +85,7 @@ pub trait FilecoinKernel: Kernel {
 #[delegate(NetworkOps)]
 #[delegate(RandomnessOps)]
 #[delegate(SelfOps)]
+#[delegate(UtilOps)]
 #[delegate(LimiterOps)]
 pub struct DefaultFilecoinKernel<K>(pub K)
 where
@@ -255,7 +257,7 @@ where
         flags: SendFlags,
     ) -> Result<CallResult> {
         self.0
-            .send::<Self>(recipient, method, params, value, gas_limit, flags)
+            .send::<K>(recipient, method, params, value, gas_limit, flags)
     }
 
     fn upgrade_actor<K: Kernel<CallManager = Self::CallManager>>(
@@ -263,7 +265,19 @@ where
         new_code_cid: Cid,
         params_id: BlockId,
     ) -> Result<CallResult> {
-        self.0.upgrade_actor::<Self>(new_code_cid, params_id)
+        self.0.upgrade_actor::<K>(new_code_cid, params_id)
+    }
+
+    fn defer_send(
+        &mut self,
+        recipient: &Address,
+        method: u64,
+        params: BlockId,
+        value: &TokenAmount,
+        gas_limit: Gas,
+    ) -> Result<()> {
+        self.0
+            .defer_send(recipient, method, params, value, gas_limit)
     }
 
     fn new(
@@ -351,7 +365,7 @@ fn to_fil_public_replica_infos(
                     return Err("invalid proof type".to_string());
                 }
                 let replica = PublicReplicaInfo::new(typ.try_into()?, commr);
-                Ok((SectorId::from(sector_info.sector_number), replica))
+                Ok((SectorId::from(sector_info.sector_number.value()), replica))
             },
         )
         .collect::<core::result::Result<BTreeMap<SectorId, PublicReplicaInfo>, _>>()
@@ -409,7 +423,7 @@ fn verify_seal(vi: &SealVerifyInfo) -> Result<bool> {
         commr,
         commd,
         prover_id,
-        SectorId::from(vi.sector_id.number),
+        SectorId::from(vi.sector_id.number.value()),
         bytes_32(&vi.randomness.0),
         bytes_32(&vi.interactive_randomness.0),
         &vi.proof,
@@ -448,7 +462,7 @@ fn verify_aggregate_seals(aggregate: &AggregateSealVerifyProofAndInfos) -> Resul
                 commd,
                 ticket: bytes_32(&info.randomness.0),
                 seed: bytes_32(&info.interactive_randomness.0),
-                sector_id: SectorId::from(info.sector_number),
+                sector_id: SectorId::from(info.sector_number.value()),
             })
         })
         .collect::<core::result::Result<Vec<_>, &'static str>>()
@@ -0,0 +1,372 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Verification of Merkle proofs over foreign data structures.
+use fvm_shared::crypto::merkle::{MerkleProof, MerkleScheme};
+
+/// The branch factor of a Merkle-Patricia trie.
+const PATRICIA_BRANCH_FACTOR: usize = 16;
+
+/// Returns the sizes of the nodes hashed when verifying the given proof, used for gas accounting.
+pub(crate) fn hashed_sizes(proof: &MerkleProof, digest_len: usize) -> Vec<usize> {
+    match proof.scheme {
+        MerkleScheme::Tree => {
+            let siblings = (proof.branch_factor as usize).saturating_sub(1).max(1);
+            proof
+                .proof
+                .chunks(siblings)
+                .enumerate()
+                .map(|(i, level)| {
+                    let node = if i == 0 {
+                        proof.value.len()
+                    } else {
+                        digest_len
+                    };
+                    node + level.iter().map(|n| n.len()).sum::<usize>()
+                })
+                .collect()
+        }
+        MerkleScheme::Patricia => proof.proof.iter().map(|n| n.len()).collect(),
+    }
+}
+
+/// Verifies a Merkle proof, hashing nodes with the given hash function. Malformed proofs are
+/// invalid.
+pub(crate) fn verify(proof: &MerkleProof, hash: impl Fn(&[u8]) -> Vec<u8>) -> bool {
+    let nodes: Vec<&[u8]> = proof.proof.iter().map(|n| n.bytes()).collect();
+    match proof.scheme {
+        MerkleScheme::Tree => verify_tree(
+            proof.branch_factor as usize,
+            &proof.root,
+            &proof.key,
+            &proof.value,
+            &nodes,
+            hash,
+        ),
+        MerkleScheme::Patricia => {
+            verify_patricia(&proof.root, &proof.key, &proof.value, &nodes, hash)
+        }
+    }
+}
+
+fn verify_tree(
+    branch_factor: usize,
+    root: &[u8],
+    key: &[u8],
+    leaf: &[u8],
+    nodes: &[&[u8]],
+    hash: impl Fn(&[u8]) -> Vec<u8>,
+) -> bool {
+    if branch_factor < 2 || key.len() > 8 || nodes.len() % (branch_factor - 1) != 0 {
+        return false;
+    }
+    let mut index = key.iter().fold(0u64, |acc, b| acc << 8 | *b as u64);
+
+    let mut node = leaf.to_vec();
+    let mut buf = Vec::new();
+    for siblings in nodes.chunks(branch_factor - 1) {
+        let position = (index % branch_factor as u64) as usize;
+        index /= branch_factor as u64;
+
+        buf.clear();
+        for (i, sibling) in siblings.iter().enumerate() {
+            if i == position {
+                buf.extend_from_slice(&node);
+            }
+            buf.extend_from_slice(sibling);
+        }
+        if position == siblings.len() {
+            buf.extend_from_slice(&node);
+        }
+        node = hash(&buf);
+    }
+    index == 0 && node == root
+}
+
+fn verify_patricia(
+    root: &[u8],
+    key: &[u8],
+    value: &[u8],
+    nodes: &[&[u8]],
+    hash: impl Fn(&[u8]) -> Vec<u8>,
+) -> bool {
+    let path: Vec<u8> = key.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect();
+    let mut path = &path[..];
+
+    let mut nodes = nodes.iter();
+    // The reference to the next node: either its hash, or the node itself if embedded.
+    let mut reference = Reference::Hash(root);
+    loop {
+        let node = match reference {
+            Reference::Hash(expected) => match nodes.next() {
+                Some(node) if hash(node) == expected => *node,
+                _ => return false,
+            },
+            Reference::Embedded(node) => node,
+        };
+        let Some(items) = rlp::list(node) else {
+            return false;
+        };
+        match items.len() {
+            // A branch node.
+            17 => {
+                let Some((&nibble, rest)) = path.split_first() else {
+                    return rlp::bytes(items[PATRICIA_BRANCH_FACTOR]) == Some(value);
+                };
+                path = rest;
+                reference = match Reference::decode(items[nibble as usize]) {
+                    Some(r) => r,
+                    None => return false,
+                };
+            }
+            // A leaf or an extension node.
+            2 => {
+                let Some((is_leaf, partial)) = rlp::bytes(items[0]).and_then(decode_hex_prefix)
+                else {
+                    return false;
+                };
+                if !path.starts_with(&partial) {
+                    return false;
+                }
+                path = &path[partial.len()..];
+                if is_leaf {
+                    return path.is_empty() && rlp::bytes(items[1]) == Some(value);
+                }
+                reference = match Reference::decode(items[1]) {
+                    Some(r) => r,
+                    None => return false,
+                };
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// A reference to a trie node from its parent.
+enum Reference<'a> {
+    Hash(&'a [u8]),
+    Embedded(&'a [u8]),
+}
+
+impl<'a> Reference<'a> {
+    /// Decodes an RLP-encoded node reference. Returns `None` for empty references.
+    fn decode(item: &'a [u8]) -> Option<Self> {
+        match rlp::bytes(item) {
+            Some([]) => None,
+            Some(hash) => Some(Reference::Hash(hash)),
+            None => Some(Reference::Embedded(item)),
+        }
+    }
+}
+
+/// Decodes the hex-prefix encoded partial path of a leaf or extension node into whether the node
+/// is a leaf, and the path's nibbles.
+fn decode_hex_prefix(encoded: &[u8]) -> Option<(bool, Vec<u8>)> {
+    let (&first, rest) = encoded.split_first()?;
+    let is_leaf = match first >> 4 {
+        0 | 1 => false,
+        2 | 3 => true,
+        _ => return None,
+    };
+    let odd = first & 0x10 != 0;
+    if !odd && first & 0x0f != 0 {
+        return None;
+    }
+    let mut nibbles = Vec::with_capacity(rest.len() * 2 + 1);
+    if odd {
+        nibbles.push(first & 0x0f);
+    }
+    nibbles.extend(rest.iter().flat_map(|b| [b >> 4, b & 0x0f]));
+    Some((is_leaf, nibbles))
+}
+
+/// Just enough RLP to walk a Merkle-Patricia trie.
+mod rlp {
+    /// Splits the first RLP item off the input, returning whether it's a list, its payload, its
+    /// full encoding, and the remaining input.
+    fn split(input: &[u8]) -> Option<(bool, &[u8], &[u8], &[u8])> {
+        let (&prefix, rest) = input.split_first()?;
+        let (is_list, header, len) = match prefix {
+            0x00..=0x7f => return Some((false, &input[..1], &input[..1], rest)),
+            0x80..=0xb7 => (false, 1, (prefix - 0x80) as usize),
+            0xb8..=0xbf => {
+                let n = (prefix - 0xb7) as usize;
+                (false, 1 + n, read_len(rest, n)?)
+            }
+            0xc0..=0xf7 => (true, 1, (prefix - 0xc0) as usize),
+            0xf8..=0xff => {
+                let n = (prefix - 0xf7) as usize;
+                (true, 1 + n, read_len(rest, n)?)
+            }
+        };
+        let end = header.checked_add(len)?;
+        if end > input.len() {
+            return None;
+        }
+        Some((is_list, &input[header..end], &input[..end], &input[end..]))
+    }
+
+    fn read_len(input: &[u8], n: usize) -> Option<usize> {
+        if n > std::mem::size_of::<usize>() || input.len() < n {
+            return None;
+        }
+        Some(input[..n].iter().fold(0, |acc, b| acc << 8 | *b as usize))
+    }
+
+    /// Decodes an RLP list, returning the encodings of its items. Returns `None` if the input
+    /// isn't exactly one list.
+    pub fn list(input: &[u8]) -> Option<Vec<&[u8]>> {
+        let (true, mut payload, _, []) = split(input)? else {
+            return None;
+        };
+        let mut items = Vec::new();
+        while !payload.is_empty() {
+            let (_, _, item, rest) = split(payload)?;
+            items.push(item);
+            payload = rest;
+        }
+        Some(items)
+    }
+
+    /// Decodes an RLP byte string. Returns `None` if the input isn't exactly one byte string.
+    pub fn bytes(input: &[u8]) -> Option<&[u8]> {
+        match split(input)? {
+            (false, payload, _, []) => Some(payload),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_encoding::RawBytes;
+    use multihash::MultihashDigest;
+
+    use super::*;
+    use crate::kernel::SupportedHashes;
+
+    fn sha256(data: &[u8]) -> Vec<u8> {
+        SupportedHashes::Sha2_256.digest(data).digest().to_vec()
+    }
+
+    fn keccak(data: &[u8]) -> Vec<u8> {
+        SupportedHashes::Keccak256.digest(data).digest().to_vec()
+    }
+
+    fn proof(scheme: MerkleScheme, branch_factor: u32, root: &[u8], key: &[u8]) -> MerkleProof {
+        MerkleProof {
+            scheme,
+            hash_function: 0,
+            branch_factor,
+            root: root.to_vec(),
+            key: key.to_vec(),
+            value: Vec::new(),
+            proof: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn binary_tree() {
+        let leaves: Vec<_> = (0u8..4).map(|i| sha256(&[i])).collect();
+        let l = sha256(&[leaves[0].clone(), leaves[1].clone()].concat());
+        let r = sha256(&[leaves[2].clone(), leaves[3].clone()].concat());
+        let root = sha256(&[l.clone(), r.clone()].concat());
+
+        let mut p = proof(MerkleScheme::Tree, 2, &root, &[2]);
+        p.value = leaves[2].clone();
+        p.proof = vec![RawBytes::new(leaves[3].clone()), RawBytes::new(l)];
+        assert!(verify(&p, sha256));
+
+        // Wrong index.
+        p.key = vec![3];
+        assert!(!verify(&p, sha256));
+        // Index out of range.
+        p.key = vec![6];
+        assert!(!verify(&p, sha256));
+        // Wrong hash function.
+        p.key = vec![2];
+        assert!(!verify(&p, keccak));
+    }
+
+    #[test]
+    fn ternary_tree() {
+        let leaves: Vec<_> = (0u8..9).map(|i| sha256(&[i])).collect();
+        let level: Vec<_> = leaves.chunks(3).map(|c| sha256(&c.concat())).collect();
+        let root = sha256(&level.concat());
+
+        let mut p = proof(MerkleScheme::Tree, 3, &root, &[5]);
+        p.value = leaves[5].clone();
+        p.proof = [&leaves[3], &leaves[4], &level[0], &level[2]]
+            .into_iter()
+            .map(|n| RawBytes::new(n.clone()))
+            .collect();
+        assert!(verify(&p, sha256));
+        assert_eq!(hashed_sizes(&p, 32), vec![96, 96]);
+
+        // Siblings must fill every level.
+        p.proof.pop();
+        assert!(!verify(&p, sha256));
+    }
+
+    /// RLP-encodes a byte string.
+    fn rlp_bytes(data: &[u8]) -> Vec<u8> {
+        match data {
+            [b] if *b < 0x80 => vec![*b],
+            _ if data.len() < 56 => [&[0x80 + data.len() as u8][..], data].concat(),
+            _ => [&[0xb8, data.len() as u8][..], data].concat(),
+        }
+    }
+
+    /// RLP-encodes a list of (encoded) items.
+    fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+        let payload = items.concat();
+        if payload.len() < 56 {
+            [vec![0xc0 + payload.len() as u8], payload].concat()
+        } else {
+            [vec![0xf8, payload.len() as u8], payload].concat()
+        }
+    }
+
+    #[test]
+    fn patricia_trie() {
+        // A branch at the root, with a hashed leaf for the path 0x1234... and an embedded leaf for
+        // the path 0xa.
+        let value = vec![0x42; 40];
+        let key = [0x12, 0x34];
+        // The branch consumes the first nibble, leaving an odd-length path (2, 3, 4) for the leaf.
+        let leaf = rlp_list(&[rlp_bytes(&[0x32, 0x34]), rlp_bytes(&value)]);
+        let embedded = rlp_list(&[rlp_bytes(&[0x3b]), rlp_bytes(b"hi")]);
+        assert!(embedded.len() < 32);
+
+        let mut items = vec![rlp_bytes(&[]); 17];
+        items[1] = rlp_bytes(&keccak(&leaf));
+        items[0xa] = embedded;
+        let branch = rlp_list(&items);
+        let root = keccak(&branch);
+
+        let mut p = proof(MerkleScheme::Patricia, 0, &root, &key);
+        p.value = value.clone();
+        p.proof = vec![RawBytes::new(branch.clone()), RawBytes::new(leaf.clone())];
+        assert!(verify(&p, keccak));
+        assert_eq!(hashed_sizes(&p, 32), vec![branch.len(), leaf.len()]);
+
+        // Wrong value.
+        p.value = vec![0x43; 40];
+        assert!(!verify(&p, keccak));
+
+        // Missing path (empty branch slot).
+        p.value = value;
+        p.key = vec![0x22, 0x34];
+        assert!(!verify(&p, keccak));
+
+        // Embedded nodes aren't included in the proof.
+        p.key = vec![0xab];
+        p.value = b"hi".to_vec();
+        p.proof = vec![RawBytes::new(branch)];
+        assert!(verify(&p, keccak));
+
+        // Truncated proof.
+        p.key = key.to_vec();
+        p.value = vec![0x42; 40];
+        assert!(!verify(&p, keccak));
+    }
+}
@@ -1,12 +1,16 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
-pub use blocks::{Block, BlockId, BlockRegistry, BlockStat};
+pub use blocks::{Block, BlockId, BlockLimits, BlockRegistry, BlockStat};
+use std::time::Duration;
+
 use cid::Cid;
 use fvm_shared::address::Address;
+use fvm_shared::chainid::ChainID;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::consensus::ConsensusFault;
+use fvm_shared::crypto::merkle::MerkleProof;
 use fvm_shared::crypto::signature::{
-    SignatureType, SECP_PUB_LEN, SECP_SIG_LEN, SECP_SIG_MESSAGE_HASH_SIZE,
+    SignatureBatchEntry, SignatureType, SECP_PUB_LEN, SECP_SIG_LEN, SECP_SIG_MESSAGE_HASH_SIZE,
 };
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
@@ -19,10 +23,12 @@ use fvm_shared::sector::{
 use fvm_shared::sys::out::network::NetworkContext;
 use fvm_shared::sys::out::vm::MessageContext;
 use fvm_shared::sys::SendFlags;
+use fvm_shared::version::NetworkVersion;
 use fvm_shared::{ActorID, MethodNum};
 
 mod blocks;
 mod hash;
+mod merkle;
 
 pub mod default;
 pub mod filecoin;
@@ -30,7 +36,7 @@ pub mod filecoin;
 pub(crate) mod error;
 
 use ambassador::delegatable_trait;
-pub use error::{ClassifyResult, Context, ExecutionError, Result, SyscallError};
+pub use error::{ClassifyResult, Context, ErrorContext, ExecutionError, Result, SyscallError};
 use fvm_shared::event::StampedEvent;
 pub use hash::SupportedHashes;
 use multihash::MultihashGeneric;
@@ -48,6 +54,30 @@ pub struct CallResult {
     pub exit_code: ExitCode,
 }
 
+/// A read-only snapshot of the [`MachineContext`](crate::machine::MachineContext) a kernel is
+/// executing in, returned by [`Kernel::machine_context`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MachineContextSnapshot {
+    /// The current epoch.
+    pub epoch: ChainEpoch,
+    /// The UNIX timestamp (in seconds) of the current tipset.
+    pub timestamp: u64,
+    /// The base fee in effect.
+    pub base_fee: TokenAmount,
+    /// The circulating supply reported by the client.
+    pub circ_supply: TokenAmount,
+    /// The current network version.
+    pub network_version: NetworkVersion,
+    /// The chain ID of the network.
+    pub chain_id: ChainID,
+    /// The state root on which the current block is based.
+    pub initial_state_root: Cid,
+    /// Whether execution traces are being recorded.
+    pub tracing: bool,
+    /// Whether the state tree's caches are audited after each message.
+    pub state_cache_audit: bool,
+}
+
 /// The "kernel" implements the FVM interface as presented to the actors. It:
 ///
 /// - Manages the Actor's state.
@@ -55,6 +85,30 @@ pub struct CallResult {
 ///
 /// Actors may call into the kernel via the syscalls defined in the [`syscalls`][crate::syscalls]
 /// module.
+///
+/// # Wrapping kernels
+///
+/// Embedders can customize individual kernel operations by wrapping an existing kernel (e.g.,
+/// [`DefaultKernel`](default::DefaultKernel)) and delegating everything else to it. All kernel
+/// operation traits are delegatable with [`ambassador`], so only the overridden traits need to be
+/// implemented by hand, along with [`Kernel`] itself and the [`SyscallHandler`]:
+///
+/// ```ignore
+/// #[derive(Delegate)]
+/// #[delegate(ActorOps)]
+/// #[delegate(IpldBlockOps)]
+/// // ... every other operation trait except `RandomnessOps` ...
+/// pub struct MyKernel<K>(pub K)
+/// where
+///     K: Kernel;
+///
+/// impl<K: Kernel> RandomnessOps for MyKernel<K> {
+///     // ...
+/// }
+/// ```
+///
+/// Wrappers must forward the type parameter of [`Kernel::send`] and [`Kernel::upgrade_actor`]
+/// unchanged so that nested calls are made on top of the outermost kernel.
 pub trait Kernel:
     SyscallHandler<Self>
     + ActorOps
@@ -68,6 +122,7 @@ pub trait Kernel:
     + NetworkOps
     + RandomnessOps
     + SelfOps
+    + UtilOps
     + LimiterOps
     + 'static
 {
@@ -102,6 +157,24 @@ pub trait Kernel:
     /// The kernel's underlying "machine".
     fn machine(&self) -> &<Self::CallManager as CallManager>::Machine;
 
+    /// Returns a snapshot of the machine's context for host-side tooling (tracers, debuggers,
+    /// etc.). Unlike [`NetworkOps::network_context`], this doesn't charge gas and must not be
+    /// exposed to actors.
+    fn machine_context(&self) -> MachineContextSnapshot {
+        let ctx = self.machine().context();
+        MachineContextSnapshot {
+            epoch: ctx.epoch,
+            timestamp: ctx.timestamp,
+            base_fee: ctx.base_fee.clone(),
+            circ_supply: ctx.circ_supply.clone(),
+            network_version: ctx.network_version,
+            chain_id: ctx.chain_id,
+            initial_state_root: ctx.initial_state_root,
+            tracing: ctx.tracing,
+            state_cache_audit: ctx.state_cache_audit,
+        }
+    }
+
     /// Sends a message to another actor.
     /// The method type parameter K is the type of the kernel to instantiate for
     /// the receiving actor. This is necessary to support wrapping a kernel, so the outer
@@ -124,8 +197,37 @@ pub trait Kernel:
         new_code_cid: Cid,
         params_id: BlockId,
     ) -> Result<CallResult>;
+
+    /// Defers a send to another actor until the message's call stack has unwound. The deferred
+    /// send executes with the given gas limit, and its failure doesn't affect the rest of the
+    /// message. See [`CallManager::defer_send`].
+    fn defer_send(
+        &mut self,
+        recipient: &Address,
+        method: u64,
+        params: BlockId,
+        value: &TokenAmount,
+        gas_limit: Gas,
+    ) -> Result<()>;
 }
 
+/// Binds the syscalls supported by a kernel to a wasmtime [`Linker`].
+///
+/// Embedders that need additional, network-specific syscalls can wrap an existing kernel and
+/// implement this trait for their wrapper, first delegating to the wrapped kernel and then binding
+/// their own host functions with [`BindSyscall`](crate::syscalls::BindSyscall):
+///
+/// ```ignore
+/// impl<C: CallManager> SyscallHandler<MyKernel<C>> for MyKernel<C> {
+///     fn bind_syscalls(&self, linker: &mut Linker<InvocationData<MyKernel<C>>>) -> anyhow::Result<()> {
+///         self.0.bind_syscalls(linker)?;
+///         linker.bind("my_network", "my_syscall", my_syscalls::my_syscall)?;
+///         Ok(())
+///     }
+/// }
+/// ```
+///
+/// Later bindings shadow earlier ones, so a wrapper may also replace a default syscall.
 pub trait SyscallHandler<K: Kernel>: Sized {
     fn bind_syscalls(&self, linker: &mut Linker<InvocationData<K>>) -> anyhow::Result<()>;
 }
@@ -270,6 +372,10 @@ pub trait GasOps {
 
     /// Returns the currently active gas price list.
     fn price_list(&self) -> &PriceList;
+
+    /// Records the wall-clock time taken by a syscall in the execution trace, if tracing is
+    /// enabled. This doesn't charge gas.
+    fn trace_syscall(&self, module: &'static str, name: &'static str, elapsed: Duration);
 }
 
 /// Cryptographic primitives provided by the kernel.
@@ -296,6 +402,14 @@ pub trait CryptoOps {
     /// to small to fit the entire digest, it will be truncated. If too large, the leftover space
     /// will not be overwritten.
     fn hash(&self, code: u64, data: &[u8]) -> Result<MultihashGeneric<64>>;
+
+    /// Verifies a Merkle proof over a (possibly foreign) data structure, charging gas for each
+    /// node hashed.
+    fn verify_merkle_proof(&self, proof: &MerkleProof) -> Result<bool>;
+
+    /// Verifies a batch of signatures, returning whether each entry's signature is valid.
+    /// Signatures verified together cost less gas than when verified individually.
+    fn verify_signatures_batch(&self, entries: &[SignatureBatchEntry]) -> Result<Vec<bool>>;
 }
 
 /// Randomness queries.
@@ -342,6 +456,14 @@ pub trait LimiterOps {
     fn limiter_mut(&mut self) -> &mut Self::Limiter;
 }
 
+/// General-purpose utilities that are prohibitively expensive to implement in Wasm.
+#[delegatable_trait]
+pub trait UtilOps {
+    /// Returns true if the bytes are valid UTF-8. Strings are deliberately not normalized, see
+    /// the `util::validate_utf8` syscall.
+    fn validate_utf8(&self, bytes: &[u8]) -> Result<bool>;
+}
+
 /// Eventing APIs.
 #[delegatable_trait]
 pub trait EventOps {
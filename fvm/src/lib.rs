@@ -17,10 +17,12 @@ pub mod executor;
 pub mod externs;
 pub mod kernel;
 pub mod machine;
+pub mod migration;
 pub mod syscalls;
 
 pub mod gas;
 pub mod state_tree;
+pub mod walker;
 
 mod blockstore;
 
@@ -56,7 +58,8 @@ mod test {
     use crate::engine::EnginePool;
     use crate::externs::{Chain, Consensus, Externs, Rand};
     use crate::kernel::filecoin::DefaultFilecoinKernel;
-    use crate::machine::{DefaultMachine, Manifest, NetworkConfig};
+    use crate::machine::limiter::{DefaultMemoryLimiter, MemoryLimiter, NetworkMemoryLimiter};
+    use crate::machine::{DefaultMachine, Machine, MachineContext, Manifest, NetworkConfig};
     use crate::state_tree::StateTree;
     use crate::{executor, DefaultKernel};
 
@@ -104,8 +107,9 @@ mod test {
         }
     }
 
-    #[test]
-    fn test_constructor() {
+    /// Builds a blockstore with an empty state tree and builtin-actors manifest, and a machine
+    /// context on top of it.
+    fn new_context() -> (MemoryBlockstore, MachineContext) {
         let mut bs = MemoryBlockstore::default();
         let mut st = StateTree::new(bs, StateTreeVersion::V5).unwrap();
         let root = st.flush().unwrap();
@@ -119,9 +123,17 @@ mod test {
 
         let actors_cid = bs.put_cbor(&(1, manifest_cid), Code::Blake2b256).unwrap();
 
-        let mc = NetworkConfig::new(fvm_shared::version::NetworkVersion::V21)
+        let mut mc = NetworkConfig::new(fvm_shared::version::NetworkVersion::V21)
             .override_actors(actors_cid)
             .for_epoch(0, 0, root);
+        // The dummy actors aren't valid Wasm modules.
+        mc.set_preload_builtin_actors(false);
+        (bs, mc)
+    }
+
+    #[test]
+    fn test_constructor() {
+        let (bs, mc) = new_context();
 
         let machine = DefaultMachine::new(&mc, bs, DummyExterns).unwrap();
         let engine = EnginePool::new_default((&mc.network).into()).unwrap();
@@ -129,4 +141,47 @@ mod test {
             DefaultFilecoinKernel<DefaultKernel<DefaultCallManager<_>>>,
         >::new(engine, Box::new(machine));
     }
+
+    #[test]
+    fn test_custom_limiter() {
+        /// Allows a single byte of memory, regardless of the network config.
+        struct TinyLimiter(DefaultMemoryLimiter);
+
+        impl NetworkMemoryLimiter for TinyLimiter {
+            fn for_network(_: &NetworkConfig) -> Self {
+                TinyLimiter(DefaultMemoryLimiter::new(1))
+            }
+        }
+
+        impl MemoryLimiter for TinyLimiter {
+            fn memory_used(&self) -> usize {
+                self.0.memory_used()
+            }
+
+            fn grow_memory(&mut self, delta: usize) -> bool {
+                self.0.grow_memory(delta)
+            }
+
+            fn with_stack_frame<T, G, F, R>(t: &mut T, g: G, f: F) -> R
+            where
+                G: Fn(&mut T) -> &mut Self,
+                F: FnOnce(&mut T) -> R,
+            {
+                DefaultMemoryLimiter::with_stack_frame(t, |t| &mut g(t).0, f)
+            }
+        }
+
+        let (bs, mc) = new_context();
+
+        let machine =
+            DefaultMachine::<_, _, TinyLimiter>::with_limiter(&mc, bs, DummyExterns).unwrap();
+        let mut limiter = machine.new_limiter();
+        assert!(limiter.grow_memory(1));
+        assert!(!limiter.grow_memory(1));
+
+        let engine = EnginePool::new_default((&mc.network).into()).unwrap();
+        let _ = executor::DefaultExecutor::<
+            DefaultFilecoinKernel<DefaultKernel<DefaultCallManager<_>>>,
+        >::new(engine, Box::new(machine));
+    }
 }
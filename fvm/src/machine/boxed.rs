@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use cid::Cid;
 
-use super::{Machine, MachineContext, Manifest};
+use super::{ExecutionMetrics, Machine, MachineContext, MachineSnapshot, Manifest, MetricEvent};
 use crate::kernel::Result;
 use crate::state_tree::StateTree;
 
@@ -48,6 +48,36 @@ impl<M: Machine> Machine for Box<M> {
         (**self).flush()
     }
 
+    #[inline(always)]
+    fn snapshot(&mut self) -> Result<MachineSnapshot> {
+        (**self).snapshot()
+    }
+
+    #[inline(always)]
+    fn restore(&mut self, snapshot: &MachineSnapshot) -> Result<()> {
+        (**self).restore(snapshot)
+    }
+
+    #[inline(always)]
+    fn metrics(&self) -> ExecutionMetrics {
+        (**self).metrics()
+    }
+
+    #[inline(always)]
+    fn record_metric(&self, event: MetricEvent) {
+        (**self).record_metric(event)
+    }
+
+    #[inline(always)]
+    fn collects_metrics(&self) -> bool {
+        (**self).collects_metrics()
+    }
+
+    #[inline(always)]
+    fn set_collect_metrics(&self, enable: bool) {
+        (**self).set_collect_metrics(enable)
+    }
+
     #[inline(always)]
     fn into_store(self) -> Self::Blockstore {
         (*self).into_store()
@@ -0,0 +1,271 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use cid::Cid;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use num_traits::Zero;
+
+use super::{MachineContext, NetworkConfig};
+
+/// The maximum size of a Wasm (32-bit) memory.
+const MAX_WASM_MEMORY_BYTES: u64 = 1 << 32;
+
+/// The size of a Wasm memory page.
+const WASM_PAGE_SIZE: u64 = wasmtime_environ::WASM_PAGE_SIZE as u64;
+
+/// An invalid [`NetworkConfig`] or [`MachineContext`], see [`MachineContextBuilder::build`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("no initial state root was specified")]
+    MissingInitialState,
+    #[error("the base fee must be non-zero")]
+    ZeroBaseFee,
+    #[error("the circulating supply ({0}) exceeds the total supply of FIL")]
+    ExcessCirculatingSupply(TokenAmount),
+    #[error("a debug allowlist was set, but actor debugging isn't enabled")]
+    DebugAllowlistWithoutDebugging,
+    #[error("the maximum call depth must be at least 1")]
+    ZeroCallDepth,
+    #[error("the maximum wasm stack size must be at least 1")]
+    ZeroWasmStack,
+    #[error("the maximum block size must be at least 1 byte")]
+    ZeroBlockSize,
+    #[error("the per-instance memory limit ({instance} bytes) exceeds the wasm limit of 4GiB")]
+    InstanceMemoryTooLarge { instance: u64 },
+    #[error(
+        "the per-instance memory limit ({instance} bytes) isn't a multiple of the wasm page size \
+         (64KiB)"
+    )]
+    InstanceMemoryNotPageAligned { instance: u64 },
+    #[error(
+        "the per-instance memory limit ({instance} bytes) exceeds the total memory limit \
+         ({total} bytes)"
+    )]
+    InstanceMemoryExceedsTotal { instance: u64, total: u64 },
+}
+
+impl NetworkConfig {
+    /// Checks that the network config is consistent. Called by [`MachineContextBuilder::build`].
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_call_depth == 0 {
+            return Err(ConfigError::ZeroCallDepth);
+        }
+        if self.max_wasm_stack == 0 {
+            return Err(ConfigError::ZeroWasmStack);
+        }
+        if self.max_block_size == 0 {
+            return Err(ConfigError::ZeroBlockSize);
+        }
+        if self.max_inst_memory_bytes > MAX_WASM_MEMORY_BYTES {
+            return Err(ConfigError::InstanceMemoryTooLarge {
+                instance: self.max_inst_memory_bytes,
+            });
+        }
+        if self.max_inst_memory_bytes % WASM_PAGE_SIZE != 0 {
+            return Err(ConfigError::InstanceMemoryNotPageAligned {
+                instance: self.max_inst_memory_bytes,
+            });
+        }
+        if self.max_inst_memory_bytes > self.max_memory_bytes {
+            return Err(ConfigError::InstanceMemoryExceedsTotal {
+                instance: self.max_inst_memory_bytes,
+                total: self.max_memory_bytes,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl MachineContext {
+    /// Returns a builder for a machine context on the given network, see
+    /// [`MachineContextBuilder`].
+    pub fn builder(network: NetworkConfig) -> MachineContextBuilder {
+        MachineContextBuilder {
+            context: network.for_epoch(0, 0, Cid::default()),
+            initial_state_root: None,
+            require_base_fee: false,
+        }
+    }
+
+    /// Checks that the machine context (including its network config) is consistent. Called by
+    /// [`MachineContextBuilder::build`] and when constructing a
+    /// [`DefaultMachine`](super::DefaultMachine).
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.network.validate()?;
+        if self.circ_supply > *fvm_shared::TOTAL_FILECOIN {
+            return Err(ConfigError::ExcessCirculatingSupply(
+                self.circ_supply.clone(),
+            ));
+        }
+        if self.debug_allowlist.is_some() && !self.actor_debugging {
+            return Err(ConfigError::DebugAllowlistWithoutDebugging);
+        }
+        Ok(())
+    }
+}
+
+/// Builds a [`MachineContext`], checking that it's consistent before it can be used to construct
+/// a machine.
+///
+/// The initial state root is required, and a non-zero base fee can be required with
+/// [`MachineContextBuilder::require_base_fee`]. Other settings default as documented on
+/// [`MachineContext`], and can be changed with [`MachineContextBuilder::configure`].
+///
+/// ```ignore
+/// let mut network = NetworkConfig::new(NetworkVersion::V21);
+/// network.chain_id(ChainID::from(314));
+/// let context = MachineContext::builder(network)
+///     .epoch(epoch)
+///     .timestamp(timestamp)
+///     .initial_state_root(state_root)
+///     .base_fee(base_fee)
+///     .require_base_fee()
+///     .configure(|mc| {
+///         mc.enable_tracing();
+///     })
+///     .build()?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct MachineContextBuilder {
+    context: MachineContext,
+    initial_state_root: Option<Cid>,
+    require_base_fee: bool,
+}
+
+impl MachineContextBuilder {
+    /// Sets [`MachineContext::epoch`].
+    pub fn epoch(mut self, epoch: ChainEpoch) -> Self {
+        self.context.epoch = epoch;
+        self
+    }
+
+    /// Sets [`MachineContext::timestamp`].
+    pub fn timestamp(mut self, timestamp: u64) -> Self {
+        self.context.timestamp = timestamp;
+        self
+    }
+
+    /// Sets [`MachineContext::initial_state_root`].
+    pub fn initial_state_root(mut self, root: Cid) -> Self {
+        self.initial_state_root = Some(root);
+        self
+    }
+
+    /// Sets [`MachineContext::base_fee`].
+    pub fn base_fee(mut self, amt: TokenAmount) -> Self {
+        self.context.set_base_fee(amt);
+        self
+    }
+
+    /// Rejects a zero base fee when building. Zero base fees are allowed by default, as they're
+    /// common in tests and devnets.
+    pub fn require_base_fee(mut self) -> Self {
+        self.require_base_fee = true;
+        self
+    }
+
+    /// Sets [`MachineContext::circ_supply`].
+    pub fn circulating_supply(mut self, amt: TokenAmount) -> Self {
+        self.context.set_circulating_supply(amt);
+        self
+    }
+
+    /// Changes any other settings of the machine context (or its network config) with its setters.
+    pub fn configure(mut self, f: impl FnOnce(&mut MachineContext)) -> Self {
+        f(&mut self.context);
+        self
+    }
+
+    /// Checks the machine context, returning it if it's consistent.
+    pub fn build(self) -> Result<MachineContext, ConfigError> {
+        let mut context = self.context;
+        context.initial_state_root = self
+            .initial_state_root
+            .ok_or(ConfigError::MissingInitialState)?;
+        if self.require_base_fee && context.base_fee.is_zero() {
+            return Err(ConfigError::ZeroBaseFee);
+        }
+        context.validate()?;
+        Ok(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_shared::version::NetworkVersion;
+
+    use super::*;
+    use crate::machine::DebugAllowlist;
+
+    fn builder() -> MachineContextBuilder {
+        MachineContext::builder(NetworkConfig::new(NetworkVersion::V21))
+            .initial_state_root(Cid::default())
+            .base_fee(TokenAmount::from_atto(100))
+    }
+
+    #[test]
+    fn build() {
+        let mc = builder().epoch(10).timestamp(20).build().unwrap();
+        assert_eq!((mc.epoch, mc.timestamp), (10, 20));
+        assert_eq!(mc.base_fee, TokenAmount::from_atto(100));
+
+        assert_eq!(
+            MachineContext::builder(NetworkConfig::new(NetworkVersion::V21))
+                .base_fee(TokenAmount::from_atto(100))
+                .build()
+                .unwrap_err(),
+            ConfigError::MissingInitialState
+        );
+        assert!(builder().base_fee(TokenAmount::zero()).build().is_ok());
+        assert_eq!(
+            builder()
+                .base_fee(TokenAmount::zero())
+                .require_base_fee()
+                .build()
+                .unwrap_err(),
+            ConfigError::ZeroBaseFee
+        );
+        assert_eq!(
+            builder()
+                .configure(|mc| {
+                    mc.restrict_actor_debugging(DebugAllowlist::default());
+                })
+                .build()
+                .unwrap_err(),
+            ConfigError::DebugAllowlistWithoutDebugging
+        );
+        assert!(builder()
+            .configure(|mc| {
+                mc.enable_actor_debugging();
+                mc.restrict_actor_debugging(DebugAllowlist::default());
+            })
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn validate_network() {
+        let mut nc = NetworkConfig::new(NetworkVersion::V21);
+        nc.validate().unwrap();
+
+        nc.max_memory_bytes = 1 << 20;
+        assert_eq!(
+            nc.validate().unwrap_err(),
+            ConfigError::InstanceMemoryExceedsTotal {
+                instance: nc.max_inst_memory_bytes,
+                total: 1 << 20
+            }
+        );
+
+        nc.set_max_instance_memory((1 << 20) - 1);
+        assert_eq!(
+            nc.validate().unwrap_err(),
+            ConfigError::InstanceMemoryNotPageAligned {
+                instance: (1 << 20) - 1
+            }
+        );
+
+        nc.max_call_depth = 0;
+        assert_eq!(nc.validate().unwrap_err(), ConfigError::ZeroCallDepth);
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::collections::HashSet;
+
+use cid::Cid;
+use fvm_shared::ActorID;
+
+/// Restricts which actors may use the debugging facilities (logs and artifacts) when actor
+/// debugging is enabled. Actors are permitted if either their ID or their code CID is listed.
+///
+/// An empty allowlist permits no actors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DebugAllowlist {
+    actors: HashSet<ActorID>,
+    codes: HashSet<Cid>,
+}
+
+impl DebugAllowlist {
+    /// Create an empty allowlist.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Permit the actor with the given ID to debug.
+    pub fn allow_actor(&mut self, id: ActorID) -> &mut Self {
+        self.actors.insert(id);
+        self
+    }
+
+    /// Permit all actors with the given code CID to debug.
+    pub fn allow_code(&mut self, code: Cid) -> &mut Self {
+        self.codes.insert(code);
+        self
+    }
+
+    /// Returns true if the actor with the given ID and code (if known) may debug.
+    pub fn permits(&self, id: ActorID, code: Option<&Cid>) -> bool {
+        self.actors.contains(&id) || code.map_or(false, |code| self.codes.contains(code))
+    }
+}
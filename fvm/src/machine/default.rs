@@ -1,20 +1,24 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
 use std::ops::RangeInclusive;
+use std::time::Instant;
 
 use anyhow::{anyhow, Context as _};
 use cid::Cid;
-use fvm_ipld_blockstore::{Block, Blockstore, Buffered};
+use fvm_ipld_blockstore::{Block, Blockstore};
 use fvm_ipld_encoding::{to_vec, CborStore, DAG_CBOR};
 use fvm_shared::version::NetworkVersion;
 use log::debug;
 use multihash::Code::Blake2b256;
 
-use super::{Machine, MachineContext};
+use super::{CacheStats, ExecutionMetrics, Machine, MachineContext, MachineSnapshot, MetricEvent};
 use crate::blockstore::BufferedBlockstore;
 use crate::externs::Externs;
-use crate::kernel::{ClassifyResult, Result};
-use crate::machine::limiter::DefaultMemoryLimiter;
+use crate::kernel::{ClassifyResult, ErrorContext, Result};
+use crate::machine::limiter::{DefaultMemoryLimiter, NetworkMemoryLimiter};
 use crate::machine::Manifest;
 use crate::state_tree::StateTree;
 use crate::system_actor::State as SystemActorState;
@@ -26,7 +30,9 @@ lazy_static::lazy_static! {
     };
 }
 
-pub struct DefaultMachine<B, E> {
+/// The default [`Machine`] implementation. The memory limiter used to track and limit the memory
+/// used by each message execution can be customized with the `L` type parameter.
+pub struct DefaultMachine<B, E, L = DefaultMemoryLimiter> {
     /// The initial execution context for this epoch.
     context: MachineContext,
     /// Boundary A calls are handled through externs. These are calls from the
@@ -42,6 +48,15 @@ pub struct DefaultMachine<B, E> {
     /// Somewhat unique ID of the machine consisting of (epoch, randomness)
     /// randomness is generated with `initial_state_root`
     id: String,
+    /// The type of memory limiter to create for each message execution.
+    limiter: PhantomData<fn() -> L>,
+    /// The module instantiations counted in [`Machine::metrics`]. Blockstore and actor cache
+    /// accesses are counted by the blockstore and state tree.
+    module_cache: Cell<CacheStats>,
+    /// The syscalls counted in [`Machine::metrics`].
+    syscalls: RefCell<BTreeMap<(&'static str, &'static str), u64>>,
+    /// Whether module instantiations and syscalls are currently counted.
+    collect_metrics: Cell<bool>,
 }
 
 impl<B, E> DefaultMachine<B, E>
@@ -58,8 +73,25 @@ where
     /// * `blockstore`: The underlying [blockstore][`Blockstore`] for reading/writing state.
     /// * `externs`: Client-provided ["external"][`Externs`] methods for accessing chain state.
     pub fn new(context: &MachineContext, blockstore: B, externs: E) -> anyhow::Result<Self> {
+        Self::with_limiter(context, blockstore, externs)
+    }
+}
+
+impl<B, E, L> DefaultMachine<B, E, L>
+where
+    B: Blockstore + 'static,
+    E: Externs + 'static,
+    L: NetworkMemoryLimiter,
+{
+    /// Create a new [`DefaultMachine`] limiting the memory used by each message execution with a
+    /// custom [`NetworkMemoryLimiter`]. See [`DefaultMachine::new`].
+    pub fn with_limiter(
+        context: &MachineContext,
+        blockstore: B,
+        externs: E,
+    ) -> anyhow::Result<Self> {
         const SUPPORTED_VERSIONS: RangeInclusive<NetworkVersion> =
-            NetworkVersion::V21..=NetworkVersion::V21;
+            NetworkVersion::V21..=NetworkVersion::V22;
 
         debug!(
             "initializing a new machine, epoch={}, base_fee={}, nv={:?}, root={}",
@@ -73,32 +105,40 @@ where
             ));
         }
 
+        context.validate().context("invalid machine context")?;
+
         // Sanity check that the blockstore contains the supplied state root.
+        let root_context = ErrorContext::default()
+            .with_epoch(context.epoch)
+            .with_cid(context.initial_state_root);
         if !blockstore
             .has(&context.initial_state_root)
-            .context("failed to load initial state-root")?
+            .context("failed to load initial state-root")
+            .map_err(|e| root_context.clone().attach(e))?
         {
-            return Err(anyhow!(
+            return Err(root_context.attach(anyhow!(
                 "blockstore doesn't have the initial state-root {}",
                 &context.initial_state_root
-            ));
+            )));
         }
 
         put_empty_blocks(&blockstore)?;
 
         // Create a new state tree from the supplied root.
-        let state_tree = {
+        let mut state_tree = {
             let bstore = BufferedBlockstore::new(blockstore);
             StateTree::new_from_root(bstore, &context.initial_state_root)?
         };
+        state_tree.set_actor_cache_limit(context.actor_cache_limit);
 
         // Load the built-in actors manifest.
         let (builtin_actors_cid, manifest_version) = match context.builtin_actors_override {
             Some(manifest_cid) => {
                 let (version, cid): (u32, Cid) = state_tree
                     .store()
-                    .get_cbor(&manifest_cid)?
-                    .context("failed to load actor manifest")?;
+                    .get_cbor(&manifest_cid)
+                    .and_then(|m| m.context("failed to load actor manifest"))
+                    .map_err(|e| ErrorContext::default().with_cid(manifest_cid).attach(e))?;
                 (cid, version)
             }
             None => {
@@ -122,18 +162,36 @@ where
                 context.epoch,
                 cid::multibase::encode(cid::multibase::Base::Base32Lower, randomness)
             ),
+            limiter: PhantomData,
+            module_cache: Default::default(),
+            syscalls: Default::default(),
+            collect_metrics: Cell::new(false),
         })
     }
 }
 
-impl<B, E> Machine for DefaultMachine<B, E>
+impl<B, E, L> DefaultMachine<B, E, L>
 where
     B: Blockstore + 'static,
     E: Externs + 'static,
+    L: NetworkMemoryLimiter,
+{
+    /// Consumes the machine, returning the underlying blockstore and the externs. Any blocks that
+    /// weren't flushed are discarded.
+    pub fn into_parts(self) -> (B, E) {
+        (self.state_tree.into_store().into_inner(), self.externs)
+    }
+}
+
+impl<B, E, L> Machine for DefaultMachine<B, E, L>
+where
+    B: Blockstore + 'static,
+    E: Externs + 'static,
+    L: NetworkMemoryLimiter,
 {
     type Blockstore = BufferedBlockstore<B>;
     type Externs = E;
-    type Limiter = DefaultMemoryLimiter;
+    type Limiter = L;
 
     fn blockstore(&self) -> &Self::Blockstore {
         self.state_tree.store()
@@ -165,11 +223,80 @@ where
     /// buffer into the underlying blockstore (the blockstore with which the machine was
     /// constructed).
     fn flush(&mut self) -> Result<Cid> {
+        // Read-only machines can't have state changes, and never write to their blockstore.
+        if self.context.read_only {
+            return Ok(self.context.initial_state_root);
+        }
+        let start = Instant::now();
         let root = self.state_tree_mut().flush()?;
-        self.blockstore().flush(&root).or_fatal()?;
+        let hash_time = start.elapsed();
+
+        let mut stats = match &self.context.flush_journal {
+            Some(journal) => self.blockstore().flush_journaled(&root, &**journal),
+            None => self.blockstore().flush_with_stats(&root),
+        }
+        .or_fatal()?;
+        stats.hash_time = hash_time;
+        if let Some(metrics) = &self.context.metrics {
+            metrics.on_flush(&stats);
+        }
         Ok(root)
     }
 
+    fn buffered_blocks(&self) -> Vec<(Cid, Vec<u8>)> {
+        self.blockstore().buffered_blocks()
+    }
+
+    fn snapshot(&mut self) -> Result<MachineSnapshot> {
+        let state_root = self.state_tree_mut().flush()?;
+        Ok(MachineSnapshot {
+            state_root,
+            buffered_blocks: Some(self.blockstore().write_buffer()),
+        })
+    }
+
+    fn restore(&mut self, snapshot: &MachineSnapshot) -> Result<()> {
+        if let Some(blocks) = &snapshot.buffered_blocks {
+            self.blockstore().set_write_buffer(blocks.clone());
+        }
+        self.state_tree_mut().revert_to(&snapshot.state_root)
+    }
+
+    fn metrics(&self) -> ExecutionMetrics {
+        ExecutionMetrics {
+            blockstore_gets: self.blockstore().gets(),
+            blockstore_puts: self.blockstore().puts(),
+            actor_cache: self.state_tree.actor_cache_stats(),
+            module_cache: self.module_cache.get(),
+            syscalls: self.syscalls.borrow().clone(),
+        }
+    }
+
+    fn record_metric(&self, event: MetricEvent) {
+        match event {
+            MetricEvent::Syscall { module, name } => {
+                *self
+                    .syscalls
+                    .borrow_mut()
+                    .entry((module, name))
+                    .or_default() += 1;
+            }
+            MetricEvent::ModuleInstantiation { cached } => {
+                let mut stats = self.module_cache.get();
+                stats.record(cached);
+                self.module_cache.set(stats);
+            }
+        }
+    }
+
+    fn collects_metrics(&self) -> bool {
+        self.collect_metrics.get()
+    }
+
+    fn set_collect_metrics(&self, enable: bool) {
+        self.collect_metrics.set(enable);
+    }
+
     fn into_store(self) -> Self::Blockstore {
         self.state_tree.into_store()
     }
@@ -179,7 +306,7 @@ where
     }
 
     fn new_limiter(&self) -> Self::Limiter {
-        DefaultMemoryLimiter::for_network(&self.context().network)
+        L::for_network(&self.context().network)
     }
 }
 
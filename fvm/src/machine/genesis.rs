@@ -0,0 +1,304 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Construction of the genesis state of a new chain.
+use anyhow::{anyhow, bail, Context as _};
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::CborStore;
+use fvm_shared::address::{Address, Payload};
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::ActorID;
+use multihash::Code::Blake2b256;
+use num_traits::Zero;
+
+use super::{Manifest, BURNT_FUNDS_ACTOR_ID};
+use crate::account_actor;
+use crate::eam_actor::EAM_ACTOR_ID;
+use crate::init_actor::{self, INIT_ACTOR_ID};
+use crate::state_tree::{ActorState, StateTree};
+use crate::system_actor::{self, SYSTEM_ACTOR_ID};
+
+/// A singleton actor in the genesis state, see [`Genesis::with_actor`].
+#[derive(Debug, Clone)]
+struct SingletonActor {
+    id: ActorID,
+    name: String,
+    state: Cid,
+    balance: TokenAmount,
+}
+
+/// Builds the genesis state tree of a new chain from a builtin actors manifest.
+///
+/// The system, init, burnt funds, and EAM actors are always created. Other singleton actors (e.g.,
+/// the reward actor) are created with [`Genesis::with_actor`], as their state is defined by the
+/// builtin actors, and initial allocations are created with [`Genesis::with_account`].
+///
+/// ```ignore
+/// let state_root = Genesis::new(builtin_actors, "localnet")
+///     .with_actor(REWARD_ACTOR_ID, "reward", reward_state, reward_balance)
+///     .with_account(owner, TokenAmount::from_whole(1000))
+///     .build(&blockstore, StateTreeVersion::V5)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Genesis {
+    builtin_actors: Cid,
+    network_name: String,
+    actors: Vec<SingletonActor>,
+    accounts: Vec<(Address, TokenAmount)>,
+}
+
+impl Genesis {
+    /// Creates the genesis state of a network with the given name and builtin actors (the CID of
+    /// a versioned manifest, as passed to
+    /// [`NetworkConfig::override_actors`](super::NetworkConfig::override_actors)).
+    pub fn new(builtin_actors: Cid, network_name: impl Into<String>) -> Self {
+        Self {
+            builtin_actors,
+            network_name: network_name.into(),
+            actors: Vec::new(),
+            accounts: Vec::new(),
+        }
+    }
+
+    /// Creates a singleton actor with the given ID, the code of the builtin actor with the given
+    /// name in the manifest, and the given (already stored) state. The ID must be below the first
+    /// ID assigned by the init actor (100), and must not be the ID of one of the actors that are
+    /// always created.
+    pub fn with_actor(
+        mut self,
+        id: ActorID,
+        name: impl Into<String>,
+        state: Cid,
+        balance: TokenAmount,
+    ) -> Self {
+        self.actors.push(SingletonActor {
+            id,
+            name: name.into(),
+            state,
+            balance,
+        });
+        self
+    }
+
+    /// Allocates the given balance to an account, assigning it the next ID. Key (secp256k1 and
+    /// BLS) addresses get an account actor, and delegated addresses get a placeholder actor, as if
+    /// the funds had been sent to them.
+    pub fn with_account(mut self, address: Address, balance: TokenAmount) -> Self {
+        self.accounts.push((address, balance));
+        self
+    }
+
+    /// Builds the genesis state tree, without flushing it.
+    pub fn build_state_tree<B: Blockstore>(
+        &self,
+        store: B,
+        version: StateTreeVersion,
+    ) -> anyhow::Result<StateTree<B>> {
+        let (manifest_version, manifest_data): (u32, Cid) =
+            store
+                .get_cbor(&self.builtin_actors)?
+                .with_context(|| format!("cannot find builtin actors {}", self.builtin_actors))?;
+        let manifest = Manifest::load(&store, &manifest_data, manifest_version)?;
+
+        let mut state_tree = StateTree::new(store, version)?;
+        let mut init_state = init_actor::State::new(state_tree.store(), &*self.network_name)?;
+        // Also puts the empty array block, the state of actors created with `new_empty`.
+        let empty_state = state_tree.store().put_cbor(&[(); 0], Blake2b256)?;
+
+        let system_state = system_actor::State {
+            builtin_actors: manifest_data,
+        };
+        let system_state = state_tree.store().put_cbor(&system_state, Blake2b256)?;
+        state_tree.set_actor(
+            SYSTEM_ACTOR_ID,
+            ActorState::new(
+                *manifest.get_system_code(),
+                system_state,
+                TokenAmount::zero(),
+                0,
+                None,
+            ),
+        );
+
+        let burnt_funds_state = account_actor::State {
+            address: Address::new_id(BURNT_FUNDS_ACTOR_ID),
+        };
+        let burnt_funds_state = state_tree
+            .store()
+            .put_cbor(&burnt_funds_state, Blake2b256)?;
+        state_tree.set_actor(
+            BURNT_FUNDS_ACTOR_ID,
+            ActorState::new(
+                *manifest.get_account_code(),
+                burnt_funds_state,
+                TokenAmount::zero(),
+                0,
+                None,
+            ),
+        );
+
+        state_tree.set_actor(
+            EAM_ACTOR_ID,
+            ActorState::new(
+                *manifest.get_eam_code(),
+                empty_state,
+                TokenAmount::zero(),
+                0,
+                None,
+            ),
+        );
+
+        for actor in &self.actors {
+            if actor.id >= init_state.next_id {
+                bail!(
+                    "singleton actor {} (ID {}) must have an ID below {}",
+                    actor.name,
+                    actor.id,
+                    init_state.next_id
+                );
+            }
+            if [
+                SYSTEM_ACTOR_ID,
+                INIT_ACTOR_ID,
+                BURNT_FUNDS_ACTOR_ID,
+                EAM_ACTOR_ID,
+            ]
+            .contains(&actor.id)
+            {
+                bail!(
+                    "cannot replace the system, init, burnt funds, or EAM actor with {}",
+                    actor.name
+                );
+            }
+            let code = manifest
+                .code_by_name(&actor.name)
+                .ok_or_else(|| anyhow!("no builtin actor named {}", actor.name))?;
+            state_tree.set_actor(
+                actor.id,
+                ActorState::new(*code, actor.state, actor.balance.clone(), 0, None),
+            );
+        }
+
+        for (address, balance) in &self.accounts {
+            let actor = match address.payload() {
+                Payload::Secp256k1(_) | Payload::BLS(_) => {
+                    let state = account_actor::State { address: *address };
+                    let state = state_tree.store().put_cbor(&state, Blake2b256)?;
+                    ActorState::new(
+                        *manifest.get_account_code(),
+                        state,
+                        balance.clone(),
+                        0,
+                        None,
+                    )
+                }
+                Payload::Delegated(_) => ActorState {
+                    balance: balance.clone(),
+                    ..ActorState::new_empty(*manifest.get_placeholder_code(), Some(*address))
+                },
+                _ => bail!(
+                    "cannot allocate funds to {}, not a key or delegated address",
+                    address
+                ),
+            };
+            let id = init_state.map_address_to_new_id(state_tree.store(), address)?;
+            state_tree.set_actor(id, actor);
+        }
+
+        let init_state = state_tree.store().put_cbor(&init_state, Blake2b256)?;
+        state_tree.set_actor(
+            INIT_ACTOR_ID,
+            ActorState::new(
+                *manifest.get_init_code(),
+                init_state,
+                TokenAmount::zero(),
+                0,
+                None,
+            ),
+        );
+
+        Ok(state_tree)
+    }
+
+    /// Builds and flushes the genesis state tree, returning the genesis state root.
+    pub fn build<B: Blockstore>(&self, store: B, version: StateTreeVersion) -> anyhow::Result<Cid> {
+        Ok(self.build_state_tree(store, version)?.flush()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_blockstore::MemoryBlockstore;
+
+    use super::*;
+
+    #[test]
+    fn build() {
+        let store = MemoryBlockstore::default();
+        let manifest_data = store
+            .put_cbor(&Manifest::DUMMY_CODES.to_vec(), Blake2b256)
+            .unwrap();
+        let builtin_actors = store.put_cbor(&(1u32, manifest_data), Blake2b256).unwrap();
+        let manifest = Manifest::dummy();
+
+        const CRON_ACTOR_ID: ActorID = 3;
+        let cron_state = store.put_cbor(&"cron", Blake2b256).unwrap();
+        let key = Address::new_secp256k1(&[1; 65]).unwrap();
+        let delegated = Address::new_delegated(EAM_ACTOR_ID, &[2; 20]).unwrap();
+        let genesis = Genesis::new(builtin_actors, "testnet")
+            .with_actor(
+                CRON_ACTOR_ID,
+                "cron",
+                cron_state,
+                TokenAmount::from_whole(10),
+            )
+            .with_account(key, TokenAmount::from_whole(1))
+            .with_account(delegated, TokenAmount::from_whole(2));
+        let root = genesis.build(&store, StateTreeVersion::V5).unwrap();
+
+        let tree = StateTree::new_from_root(&store, &root).unwrap();
+        let (system, _) = system_actor::State::load(&tree).unwrap();
+        assert_eq!(system.builtin_actors, manifest_data);
+        let (init, _) = init_actor::State::load(&tree).unwrap();
+        assert_eq!(init.network_name, "testnet");
+        assert_eq!(init.next_id, 102);
+        for id in [SYSTEM_ACTOR_ID, BURNT_FUNDS_ACTOR_ID, EAM_ACTOR_ID] {
+            assert!(tree.get_actor(id).unwrap().is_some());
+        }
+
+        let cron = tree.get_actor(CRON_ACTOR_ID).unwrap().unwrap();
+        assert_eq!(cron.code, *manifest.code_by_name("cron").unwrap());
+        assert_eq!(cron.state, cron_state);
+        assert_eq!(cron.balance, TokenAmount::from_whole(10));
+
+        let account = tree.get_actor_by_address(&key).unwrap().unwrap();
+        assert_eq!(account.code, *manifest.get_account_code());
+        assert_eq!(account.balance, TokenAmount::from_whole(1));
+        let placeholder = tree.get_actor_by_address(&delegated).unwrap().unwrap();
+        assert_eq!(placeholder.code, *manifest.get_placeholder_code());
+        assert_eq!(placeholder.delegated_address, Some(delegated));
+
+        // The dummy manifest has no reward actor.
+        assert!(Genesis::new(builtin_actors, "testnet")
+            .with_actor(2, "reward", cron_state, TokenAmount::zero())
+            .build(&store, StateTreeVersion::V5)
+            .is_err());
+        // The actors that are always created can't be replaced.
+        for id in [
+            SYSTEM_ACTOR_ID,
+            INIT_ACTOR_ID,
+            BURNT_FUNDS_ACTOR_ID,
+            EAM_ACTOR_ID,
+        ] {
+            assert!(Genesis::new(builtin_actors, "testnet")
+                .with_actor(id, "cron", cron_state, TokenAmount::zero())
+                .build(&store, StateTreeVersion::V5)
+                .is_err());
+        }
+        assert!(Genesis::new(builtin_actors, "testnet")
+            .with_account(Address::new_id(1000), TokenAmount::zero())
+            .build(&store, StateTreeVersion::V5)
+            .is_err());
+    }
+}
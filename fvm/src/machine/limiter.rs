@@ -39,10 +39,19 @@ pub trait MemoryLimiter: Sized {
     }
 }
 
+/// A [`MemoryLimiter`] that can be constructed from the network configuration. Implement this to
+/// use a custom limiter (e.g., to collect telemetry or apply a different growth policy) with the
+/// [`DefaultMachine`](crate::machine::DefaultMachine).
+pub trait NetworkMemoryLimiter: MemoryLimiter + 'static {
+    /// Create a limiter for a single message execution.
+    fn for_network(config: &NetworkConfig) -> Self;
+}
+
 /// Limit resources throughout the whole message execution,
 /// across all Wasm instances.
 pub struct DefaultMemoryLimiter {
     max_memory_bytes: usize,
+    max_inst_memory_bytes: usize,
     curr_memory_bytes: usize,
 }
 
@@ -50,12 +59,26 @@ impl DefaultMemoryLimiter {
     pub fn new(max_memory_bytes: usize) -> Self {
         Self {
             max_memory_bytes,
+            max_inst_memory_bytes: usize::MAX,
             curr_memory_bytes: 0,
         }
     }
 
+    /// Also limits the memory of each instance to the given number of bytes.
+    pub fn with_instance_limit(mut self, max_inst_memory_bytes: usize) -> Self {
+        self.max_inst_memory_bytes = max_inst_memory_bytes;
+        self
+    }
+
     pub fn for_network(config: &NetworkConfig) -> Self {
         Self::new(config.max_memory_bytes as usize)
+            .with_instance_limit(config.max_inst_memory_bytes as usize)
+    }
+}
+
+impl NetworkMemoryLimiter for DefaultMemoryLimiter {
+    fn for_network(config: &NetworkConfig) -> Self {
+        DefaultMemoryLimiter::for_network(config)
     }
 }
 
@@ -75,6 +98,13 @@ impl MemoryLimiter for DefaultMemoryLimiter {
         true
     }
 
+    fn grow_instance_memory(&mut self, from: usize, to: usize) -> bool {
+        if to > self.max_inst_memory_bytes {
+            return false;
+        }
+        self.grow_memory(to.saturating_sub(from))
+    }
+
     fn with_stack_frame<T, G, F, R>(t: &mut T, g: G, f: F) -> R
     where
         G: Fn(&mut T) -> &mut Self,
@@ -130,4 +160,14 @@ mod tests {
         assert!(limits.grow_memory(2)); // 2 bytes
         assert!(!limits.grow_memory(1));
     }
+
+    #[test]
+    fn instance_memory() {
+        let mut limits = DefaultMemoryLimiter::new(10).with_instance_limit(4);
+        assert!(limits.grow_instance_memory(0, 4)); // Ok, just at the instance limit.
+        assert!(!limits.grow_instance_memory(4, 5)); // Fail, over the instance limit.
+        assert!(limits.grow_instance_memory(0, 4)); // Ok, another instance.
+        assert_eq!(limits.memory_used(), 8);
+        assert!(!limits.grow_instance_memory(0, 3)); // Fail, over the total limit.
+    }
 }
@@ -25,6 +25,7 @@ pub struct Manifest {
 
     by_id: HashMap<u32, Cid>,
     by_code: HashMap<Cid, u32>,
+    by_name: HashMap<String, Cid>,
 }
 
 /// Create an "id CID" (for testing).
@@ -133,6 +134,7 @@ impl Manifest {
             ethaccount_code,
             by_id,
             by_code,
+            by_name,
         })
     }
 
@@ -141,6 +143,12 @@ impl Manifest {
         self.by_id.get(&id)
     }
 
+    /// Returns the code CID for a builtin actor, given the actor's name in the manifest (e.g.,
+    /// "reward").
+    pub fn code_by_name(&self, name: &str) -> Option<&Cid> {
+        self.by_name.get(name)
+    }
+
     /// Returns the the actor code's "id" if it's a builtin actor. Otherwise, returns 0.
     pub fn id_by_code(&self, code: &Cid) -> u32 {
         self.by_code.get(code).copied().unwrap_or(0)
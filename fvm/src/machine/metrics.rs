@@ -0,0 +1,124 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Receives node-local metrics from the machine. Metrics are intended for monitoring and capacity
+/// planning, can't affect execution, and aren't consensus-critical.
+///
+/// Metrics are configured with
+/// [`MachineContext::set_metrics`](super::MachineContext::set_metrics). All methods have no-op
+/// default implementations so new metrics can be added without breaking implementations.
+pub trait MachineMetrics: Send + Sync + 'static {
+    /// Called after each successful [`Machine::flush`](super::Machine::flush).
+    fn on_flush(&self, stats: &FlushStats) {
+        let _ = stats;
+    }
+}
+
+impl std::fmt::Debug for dyn MachineMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MachineMetrics")
+    }
+}
+
+/// Statistics about a single flush of the machine's state to the underlying blockstore.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FlushStats {
+    /// The number of blocks reachable from the new state root that were visited while looking for
+    /// new blocks. Blocks that were already in the underlying blockstore (and their children) are
+    /// counted, but not traversed.
+    pub reachable_blocks: usize,
+    /// The number of new blocks written to the underlying blockstore.
+    pub blocks_written: usize,
+    /// The total size of the new blocks written to the underlying blockstore.
+    pub bytes_written: usize,
+    /// The number of batches the new blocks were written in (calls to `put_many_keyed` on the
+    /// underlying blockstore).
+    pub batches_written: usize,
+    /// Time spent serializing and hashing the modified state-tree.
+    pub hash_time: Duration,
+    /// Time spent traversing the new state to find the blocks to write.
+    pub traverse_time: Duration,
+    /// Time spent writing the new blocks to the underlying blockstore.
+    pub write_time: Duration,
+}
+
+/// Counters of the work done by a machine, see [`Machine::metrics`](super::Machine::metrics).
+/// Like [`MachineMetrics`], these are node-local and not consensus-critical.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionMetrics {
+    /// The number of blocks read from the machine's blockstore, including buffered blocks.
+    pub blockstore_gets: u64,
+    /// The number of blocks written to the machine's blockstore (before flushing).
+    pub blockstore_puts: u64,
+    /// Actor state lookups served from, or added to, the state tree's actor cache.
+    pub actor_cache: CacheStats,
+    /// Actor module instantiations whose compiled module was, or wasn't yet, cached by the engine.
+    pub module_cache: CacheStats,
+    /// The number of calls to each syscall, keyed by module and name. Calls to disabled and
+    /// forbidden syscalls are counted too.
+    pub syscalls: BTreeMap<(&'static str, &'static str), u64>,
+}
+
+impl ExecutionMetrics {
+    /// Returns the work done since the given (earlier) metrics of the same machine were taken.
+    pub fn since(&self, earlier: &ExecutionMetrics) -> ExecutionMetrics {
+        let syscalls = self
+            .syscalls
+            .iter()
+            .filter_map(|(&syscall, &count)| {
+                let count = count
+                    .saturating_sub(earlier.syscalls.get(&syscall).copied().unwrap_or_default());
+                (count > 0).then_some((syscall, count))
+            })
+            .collect();
+        ExecutionMetrics {
+            blockstore_gets: self.blockstore_gets.saturating_sub(earlier.blockstore_gets),
+            blockstore_puts: self.blockstore_puts.saturating_sub(earlier.blockstore_puts),
+            actor_cache: self.actor_cache.since(&earlier.actor_cache),
+            module_cache: self.module_cache.since(&earlier.module_cache),
+            syscalls,
+        }
+    }
+}
+
+/// Cache hit and miss counts, see [`ExecutionMetrics`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Counts a cache hit or miss.
+    pub fn record(&mut self, hit: bool) {
+        if hit {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+    }
+
+    fn since(&self, earlier: &CacheStats) -> CacheStats {
+        CacheStats {
+            hits: self.hits.saturating_sub(earlier.hits),
+            misses: self.misses.saturating_sub(earlier.misses),
+        }
+    }
+}
+
+/// An event counted in a machine's [`ExecutionMetrics`], see
+/// [`Machine::record_metric`](super::Machine::record_metric).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MetricEvent {
+    /// An actor called the given syscall.
+    Syscall {
+        module: &'static str,
+        name: &'static str,
+    },
+    /// An actor's module was instantiated, either from the engine's module cache or after
+    /// compiling it.
+    ModuleInstantiation { cached: bool },
+}
@@ -1,28 +1,50 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use cid::Cid;
 use derive_more::{Deref, DerefMut};
 use fvm_ipld_blockstore::Blockstore;
+#[cfg(feature = "snapshot-export")]
+use fvm_ipld_car::CarHeader;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::version::NetworkVersion;
 use fvm_shared::ActorID;
 use num_traits::Zero;
 
+use crate::call_manager::{NamespaceResolver, ReentrancyPolicy, SendInterceptor};
+use crate::engine::WasmFeatures;
 use crate::externs::Externs;
-use crate::gas::{price_list_by_network_version, PriceList};
-use crate::kernel::Result;
+use crate::gas::{GasChargeListener, GasOutputsStrategy, PriceList, PriceListRegistry};
+use crate::kernel::{BlockLimits, ClassifyResult, Context as _, Result};
 use crate::state_tree::StateTree;
+use crate::syscalls::{SyscallAllowlist, SyscallSet};
 
+mod builder;
+mod debugging;
 mod default;
+mod genesis;
 
+pub use builder::{ConfigError, MachineContextBuilder};
+pub use debugging::DebugAllowlist;
 pub use default::DefaultMachine;
 use fvm_shared::chainid::ChainID;
+pub use genesis::Genesis;
 
 pub mod limiter;
 mod manifest;
+mod metrics;
+mod multi;
+mod upgrade;
 
 pub use manifest::Manifest;
+pub use metrics::{CacheStats, ExecutionMetrics, FlushStats, MachineMetrics, MetricEvent};
+pub use multi::MultiMachine;
+pub use upgrade::{NetworkUpgrade, UpgradeSchedule};
+
+pub use crate::blockstore::{recover_flush, FileJournal, FlushIntent, FlushJournal, FlushRecovery};
 
 use self::limiter::MemoryLimiter;
 
@@ -73,6 +95,89 @@ pub trait Machine: 'static {
         self.state_tree_mut().flush()
     }
 
+    /// Returns the blocks written since the state was last flushed to the underlying blockstore,
+    /// if the machine buffers writes. These are included in state dumps.
+    fn buffered_blocks(&self) -> Vec<(Cid, Vec<u8>)> {
+        Vec::new()
+    }
+
+    /// Captures the machine's state, so that it can later be restored with [`Machine::restore`].
+    /// This flushes the state-tree (but not the machine's blockstore), and must not be called
+    /// while executing a message.
+    ///
+    /// Machines that buffer writes also capture the buffered blocks, so restoring discards the
+    /// blocks written since the snapshot. Otherwise, those blocks are left in the blockstore.
+    fn snapshot(&mut self) -> Result<MachineSnapshot> {
+        Ok(MachineSnapshot {
+            state_root: self.state_tree_mut().flush()?,
+            buffered_blocks: None,
+        })
+    }
+
+    /// Restores the state captured by [`Machine::snapshot`], discarding all changes made since.
+    /// The same snapshot may be restored any number of times. Blocks already flushed to the
+    /// underlying blockstore (e.g., by [`Machine::flush`]) aren't removed.
+    fn restore(&mut self, snapshot: &MachineSnapshot) -> Result<()> {
+        self.state_tree_mut().revert_to(&snapshot.state_root)
+    }
+
+    /// Flushes the state-tree and writes the state as a CARv1 file rooted at the new state root to
+    /// `writer`, returning the state root. The file contains every block reachable from the state
+    /// root, including the builtin actors manifest (referenced by the system actor) and the actors'
+    /// code, all of which must be in the machine's blockstore. Wrap synchronous writers with
+    /// [`futures::io::AllowStdIo`].
+    ///
+    /// This must not be called while executing a message. Requires the `snapshot-export` feature.
+    #[cfg(feature = "snapshot-export")]
+    fn export_snapshot<W>(&mut self, writer: &mut W) -> Result<Cid>
+    where
+        Self: Sized,
+        W: futures::AsyncWrite + Send + Unpin,
+    {
+        let root = self.flush()?;
+        let mut error = None;
+        let mut blocks = futures::stream::iter(
+            crate::walker::blocks(self.blockstore(), &root)
+                .map_while(|res| res.map_err(|e| error = Some(e)).ok()),
+        );
+        let res = futures::executor::block_on(
+            CarHeader::from(vec![root]).write_stream_async(writer, &mut blocks),
+        );
+        drop(blocks);
+        // The stream ends early if the walk fails.
+        error
+            .map_or(res.map_err(anyhow::Error::from), Err)
+            .or_fatal()
+            .context("failed to export the state snapshot")?;
+        Ok(root)
+    }
+
+    /// Returns the work done by this machine so far. Subtract earlier metrics with
+    /// [`ExecutionMetrics::since`] to measure the work done in between.
+    ///
+    /// Machines that don't keep counters return empty metrics.
+    fn metrics(&self) -> ExecutionMetrics {
+        ExecutionMetrics::default()
+    }
+
+    /// Counts an event in the machine's metrics. This is called by the engine and the syscall
+    /// bindings, and must not affect execution.
+    fn record_metric(&self, event: MetricEvent) {
+        let _ = event;
+    }
+
+    /// Returns true if events are currently being counted. The engine and the syscall bindings
+    /// only call [`Machine::record_metric`] if this returns true.
+    fn collects_metrics(&self) -> bool {
+        false
+    }
+
+    /// Starts or stops counting events, see [`Machine::collects_metrics`]. Machines that don't
+    /// keep counters ignore this.
+    fn set_collect_metrics(&self, enable: bool) {
+        let _ = enable;
+    }
+
     /// Consumes the machine and returns the owned blockstore.
     fn into_store(self) -> Self::Blockstore;
 
@@ -83,8 +188,25 @@ pub trait Machine: 'static {
     fn new_limiter(&self) -> Self::Limiter;
 }
 
+/// The state of a machine, captured by [`Machine::snapshot`].
+#[derive(Debug, Clone)]
+pub struct MachineSnapshot {
+    state_root: Cid,
+    buffered_blocks: Option<HashMap<Cid, Vec<u8>>>,
+}
+
+impl MachineSnapshot {
+    /// The (unflushed) state root at the time of the snapshot.
+    pub fn state_root(&self) -> Cid {
+        self.state_root
+    }
+}
+
 /// Network-level settings. Except when testing locally, changing any of these likely requires a
 /// network upgrade.
+///
+/// Unless documented otherwise, all of these settings are consensus-critical: every node on a
+/// network must use the same values, or they'll compute different results for the same messages.
 #[derive(Debug, Clone)]
 pub struct NetworkConfig {
     /// The network version at epoch
@@ -95,16 +217,24 @@ pub struct NetworkConfig {
     /// DEFAULT: 0 (Invalid)
     pub chain_id: ChainID,
 
-    /// The maximum call depth.
+    /// The maximum call depth. Calls exceeding this depth fail with
+    /// [`ErrorNumber::LimitExceeded`](fvm_shared::error::ErrorNumber::LimitExceeded).
     ///
-    /// DEFAULT: 1024
+    /// DEFAULT: The network version's [`PriceList::max_call_depth`] (1024 as of NV21)
     pub max_call_depth: u32,
 
     /// The maximum number of elements on wasm stack
     /// DEFAULT: 64Ki (512KiB of u64 elements)
     pub max_wasm_stack: u32,
 
-    /// Maximum size of memory of any Wasm instance, ie. each level of the recursion, in bytes.
+    /// The Wasm proposals actor code may use.
+    ///
+    /// DEFAULT: [`WasmFeatures::default`], the same on all network versions
+    pub wasm_features: WasmFeatures,
+
+    /// Maximum size of memory of any Wasm instance, ie. each level of the recursion, in bytes. Must
+    /// be a multiple of the Wasm page size (64KiB). Engines reserve this much memory per instance,
+    /// and the memory limiter refuses to grow an instance's memory beyond it.
     ///
     /// DEFAULT: 512MiB
     pub max_inst_memory_bytes: u64,
@@ -121,6 +251,16 @@ pub struct NetworkConfig {
     /// DEFAULT: 1MiB
     pub max_block_size: usize,
 
+    /// The maximum number of blocks an actor may have open at once (per invocation).
+    ///
+    /// DEFAULT: `i32::MAX` (the maximum number of block handles)
+    pub max_block_handles: u32,
+
+    /// The maximum number of links (CIDs) a block created in the FVM may contain.
+    ///
+    /// DEFAULT: Unlimited
+    pub max_block_links: usize,
+
     /// An override for builtin-actors. If specified, this should be the CID of a builtin-actors
     /// "manifest".
     ///
@@ -132,35 +272,96 @@ pub struct NetworkConfig {
     /// DEFAULT: `false`
     pub actor_debugging: bool,
 
-    /// The price list.
+    /// The price list. This can be overridden per-machine with [`MachineContext::set_price_list`].
     ///
-    /// DEFAULT: The price-list for the current network version.
+    /// DEFAULT: The price-list for the current network version, selected from
+    /// [`NetworkConfig::price_lists`].
     pub price_list: &'static PriceList,
 
+    /// The price lists of all network versions, including any embedder overrides.
+    ///
+    /// DEFAULT: The built-in price lists, without overrides.
+    pub price_lists: PriceListRegistry,
+
     /// Actor redirects for debug execution
     pub actor_redirect: Vec<(Cid, Cid)>,
+
+    /// A hook consulted before and after each call made by the call manager.
+    ///
+    /// DEFAULT: `None`
+    pub send_interceptor: Option<Arc<dyn SendInterceptor>>,
+
+    /// Restrictions on the syscalls available to actors, by code CID.
+    ///
+    /// DEFAULT: No restrictions.
+    pub syscall_allowlist: SyscallAllowlist,
+
+    /// Native resolvers for delegated addresses, keyed by namespace (address manager actor ID).
+    ///
+    /// DEFAULT: None (all delegated addresses are resolved through the init actor).
+    pub namespace_resolvers: HashMap<ActorID, Arc<dyn NamespaceResolver>>,
+
+    /// Maximum number of Wasm instructions (measured in wasmtime fuel) each message may execute
+    /// across its entire call stack, independently of gas. Intended for research networks.
+    ///
+    /// DEFAULT: `None` (no instruction budget)
+    pub instruction_budget: Option<u64>,
+
+    /// How calls into actors that are already on the call stack are treated.
+    ///
+    /// DEFAULT: [`ReentrancyPolicy::Allow`]
+    pub reentrancy_policy: ReentrancyPolicy,
+
+    /// The maximum number of actors a single message may create (across its entire call stack).
+    /// Further attempts fail with
+    /// [`ErrorNumber::LimitExceeded`](fvm_shared::error::ErrorNumber::LimitExceeded).
+    ///
+    /// DEFAULT: Unlimited
+    pub max_actors_created: u64,
 }
 
 impl NetworkConfig {
     /// Create a new network config for the given network version.
     pub fn new(network_version: NetworkVersion) -> Self {
+        Self::new_with_price_lists(network_version, PriceListRegistry::default())
+    }
+
+    /// Create a new network config for the given network version, selecting the price list from
+    /// the given registry. Panics if the registry has no price list for the network version.
+    pub fn new_with_price_lists(
+        network_version: NetworkVersion,
+        price_lists: PriceListRegistry,
+    ) -> Self {
+        let price_list = price_lists
+            .get(network_version)
+            .unwrap_or_else(|| panic!("network version {nv} not supported", nv = network_version));
         NetworkConfig {
             chain_id: ChainID::from(0u64),
             network_version,
-            max_call_depth: 1024,
+            max_call_depth: price_list.max_call_depth(),
             max_wasm_stack: 2048,
+            wasm_features: WasmFeatures::default(),
             max_inst_memory_bytes: 512 * (1 << 20),
             max_memory_bytes: 2 * (1 << 30),
             actor_debugging: false,
             builtin_actors_override: None,
-            price_list: price_list_by_network_version(network_version),
+            price_list,
+            price_lists,
             actor_redirect: vec![],
             max_block_size: 1 << 20,
+            max_block_handles: i32::MAX as u32,
+            max_block_links: usize::MAX,
+            send_interceptor: None,
+            syscall_allowlist: SyscallAllowlist::default(),
+            namespace_resolvers: HashMap::new(),
+            instruction_budget: None,
+            reentrancy_policy: ReentrancyPolicy::Allow,
+            max_actors_created: u64::MAX,
         }
     }
 
-    /// Enable actor debugging. This is a consensus-critical option (affects gas usage) so it should
-    /// only be enabled for local testing or as a network-wide parameter.
+    /// Enable actor debugging. This affects gas usage, so it should only be enabled for local
+    /// testing or as a network-wide parameter.
     pub fn enable_actor_debugging(&mut self) -> &mut Self {
         self.actor_debugging = true;
         self
@@ -179,6 +380,83 @@ impl NetworkConfig {
         self
     }
 
+    /// Set a [`SendInterceptor`] to be consulted around each call. Interceptors that only observe
+    /// calls (without vetoing or rewriting them) don't affect consensus.
+    pub fn set_send_interceptor(&mut self, interceptor: Arc<dyn SendInterceptor>) -> &mut Self {
+        self.send_interceptor = Some(interceptor);
+        self
+    }
+
+    /// Restrict the syscalls available to actors.
+    pub fn restrict_syscalls(&mut self, allowlist: SyscallAllowlist) -> &mut Self {
+        self.syscall_allowlist = allowlist;
+        self
+    }
+
+    /// Resolve delegated addresses in the given namespace with the given [`NamespaceResolver`]
+    /// before falling back to the init actor.
+    pub fn register_namespace_resolver(
+        &mut self,
+        namespace: ActorID,
+        resolver: Arc<dyn NamespaceResolver>,
+    ) -> &mut Self {
+        self.namespace_resolvers.insert(namespace, resolver);
+        self
+    }
+
+    /// Limit the number of Wasm instructions each message may execute, independently of gas.
+    /// Messages exceeding this budget fail as if they had run out of gas. This is intended for
+    /// research networks only.
+    pub fn set_instruction_budget(&mut self, instructions: u64) -> &mut Self {
+        self.instruction_budget = Some(instructions);
+        self
+    }
+
+    /// Set the [`ReentrancyPolicy`] applied to calls into actors that are already on the call
+    /// stack.
+    pub fn set_reentrancy_policy(&mut self, policy: ReentrancyPolicy) -> &mut Self {
+        self.reentrancy_policy = policy;
+        self
+    }
+
+    /// Limit the number of actors each message may create.
+    pub fn set_max_actors_created(&mut self, max: u64) -> &mut Self {
+        self.max_actors_created = max;
+        self
+    }
+
+    /// Set the Wasm proposals actor code may use. Fails if any of them isn't supported yet, see
+    /// [`WasmFeatures::check_supported`].
+    pub fn set_wasm_features(&mut self, features: WasmFeatures) -> anyhow::Result<&mut Self> {
+        features.check_supported()?;
+        self.wasm_features = features;
+        Ok(self)
+    }
+
+    /// Limit the memory of each Wasm instance, in bytes, see
+    /// [`NetworkConfig::max_inst_memory_bytes`].
+    pub fn set_max_instance_memory(&mut self, bytes: u64) -> &mut Self {
+        self.max_inst_memory_bytes = bytes;
+        self
+    }
+
+    /// Set the limits on blocks created and opened by actors.
+    pub fn set_block_limits(&mut self, limits: BlockLimits) -> &mut Self {
+        self.max_block_size = limits.max_block_size;
+        self.max_block_handles = limits.max_handles;
+        self.max_block_links = limits.max_links;
+        self
+    }
+
+    /// Returns the limits on blocks created and opened by actors.
+    pub fn block_limits(&self) -> BlockLimits {
+        BlockLimits {
+            max_block_size: self.max_block_size,
+            max_handles: self.max_block_handles,
+            max_links: self.max_block_links,
+        }
+    }
+
     /// Create a ['MachineContext'] for a given epoch, timestamp, and initial state.
     pub fn for_epoch(
         &self,
@@ -194,6 +472,17 @@ impl NetworkConfig {
             initial_state_root: initial_state,
             circ_supply: fvm_shared::TOTAL_FILECOIN.clone(),
             tracing: false,
+            gas_breakdown: false,
+            state_cache_audit: false,
+            debug_allowlist: None,
+            gas_listener: None,
+            metrics: None,
+            flush_journal: None,
+            gas_outputs: None,
+            disabled_syscalls: SyscallSet::new(),
+            read_only: false,
+            preload_builtin_actors: true,
+            actor_cache_limit: None,
         }
     }
 
@@ -240,6 +529,93 @@ pub struct MachineContext {
     /// Whether or not to produce execution traces in the returned result.
     /// Not consensus-critical, but has a performance impact.
     pub tracing: bool,
+
+    /// Whether or not to record the gas used by each message, keyed by charge name (see
+    /// [`ApplyRet::gas_breakdown`](crate::executor::ApplyRet::gas_breakdown)). Independent of
+    /// [`MachineContext::tracing`], and cheaper. Not consensus-critical.
+    pub gas_breakdown: bool,
+
+    /// Whether or not to audit the state tree's caches (the actor cache and the address resolution
+    /// cache, see [`StateTree::audit_caches`](crate::state_tree::StateTree::audit_caches)) after
+    /// each message executes, before its changes are committed. The message fails with a fatal
+    /// error if they contain data that doesn't belong to this machine's state (e.g., leaked from
+    /// another machine running concurrently on a different state root).
+    ///
+    /// Only the state tree's caches are audited: the engine's module caches and the blockstore
+    /// caches aren't.
+    ///
+    /// Not consensus-critical, but has a significant performance impact.
+    ///
+    /// DEFAULT: `false`
+    pub state_cache_audit: bool,
+
+    /// Restricts which actors may emit debug logs and artifacts when
+    /// [`NetworkConfig::actor_debugging`] is enabled. Has no effect otherwise.
+    ///
+    /// DEFAULT: `None` (all actors may debug)
+    pub debug_allowlist: Option<DebugAllowlist>,
+
+    /// A listener notified of every gas charge. Not consensus-critical, but has a performance
+    /// impact proportional to the listener's cost.
+    ///
+    /// DEFAULT: `None`
+    pub gas_listener: Option<Arc<dyn GasChargeListener>>,
+
+    /// Receives node-local machine metrics (e.g., flush statistics). Not consensus-critical.
+    ///
+    /// DEFAULT: `None`
+    pub metrics: Option<Arc<dyn MachineMetrics>>,
+
+    /// A write-ahead journal recording the blocks of each flush before they're written, for
+    /// embedders whose blockstores aren't transactional. Not consensus-critical. Embedders must
+    /// call [`recover_flush`] with the same journal before
+    /// reusing the blockstore after a crash.
+    ///
+    /// DEFAULT: `None`
+    pub flush_journal: Option<Arc<dyn FlushJournal>>,
+
+    /// Splits the gas fees of each message between burning, the block miner, and the sender.
+    /// Consensus-critical. If `None`, the Filecoin mainnet rules
+    /// ([`FilecoinGasOutputs`](crate::gas::FilecoinGasOutputs)) are applied.
+    ///
+    /// DEFAULT: `None`
+    pub gas_outputs: Option<Arc<dyn GasOutputsStrategy>>,
+
+    /// Syscalls disabled on this machine, for all actors. Calling a disabled syscall fails with
+    /// [`ErrorNumber::NotSupported`](fvm_shared::error::ErrorNumber::NotSupported) (or
+    /// [`ErrorNumber::Forbidden`](fvm_shared::error::ErrorNumber::Forbidden) before NV22) after
+    /// charging the usual syscall gas, as if it were bound to a stub. Consensus-critical: this
+    /// lets forks and test networks stage syscall rollouts per network version, or disable a
+    /// buggy syscall, without patching the FVM.
+    ///
+    /// DEFAULT: No syscalls are disabled.
+    pub disabled_syscalls: SyscallSet,
+
+    /// Whether the machine is read-only, for serving queries (e.g., gas estimation or
+    /// `StateCall`) from a snapshot shared with other machines. A read-only machine never writes
+    /// to its blockstore: [`Machine::flush`] returns the initial state root, and every message
+    /// (implicit or explicit) is applied in a transaction that's reverted once it has executed,
+    /// so its receipt is returned but its changes (including the sender's nonce and gas fees) are
+    /// discarded.
+    ///
+    /// DEFAULT: `false`
+    pub read_only: bool,
+
+    /// Whether to compile all builtin actors in the manifest when the machine is handed to an
+    /// executor (which owns the engine), instead of compiling each actor the first time it's
+    /// called. This moves the compilation latency out of the first messages of a block.
+    /// Already compiled actors are taken from the engine's cache.
+    ///
+    /// DEFAULT: `true`
+    pub preload_builtin_actors: bool,
+
+    /// The maximum number of unmodified actors the state tree keeps decoded in memory (see
+    /// [`StateTree::set_actor_cache_limit`](crate::state_tree::StateTree::set_actor_cache_limit)).
+    /// This bounds the memory used by machines that execute many messages, at the cost of
+    /// reloading actors that haven't been used recently.
+    ///
+    /// DEFAULT: Unbounded.
+    pub actor_cache_limit: Option<usize>,
 }
 
 impl MachineContext {
@@ -255,9 +631,101 @@ impl MachineContext {
         self
     }
 
+    /// Override the [`PriceList`] used to charge gas, instead of the one selected by network
+    /// version. This is a consensus-critical option so it should only be used for local testing,
+    /// benchmarking, or devnets.
+    ///
+    /// The engine used to execute messages on this machine must be constructed from the updated
+    /// [`MachineContext::network`] as the Wasm instruction costs are baked into compiled actors.
+    pub fn set_price_list(&mut self, price_list: &'static PriceList) -> &mut Self {
+        self.network.price_list = price_list;
+        self
+    }
+
+    /// Override the [`PriceList`] with one loaded from a JSON or TOML file (see
+    /// [`PriceList::from_document`] for the format). The same caveats as
+    /// [`MachineContext::set_price_list`] apply.
+    ///
+    /// The loaded price list is [interned](PriceList::intern), so loading the same prices
+    /// repeatedly doesn't leak additional memory.
+    #[cfg(feature = "price-documents")]
+    pub fn load_price_list(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> anyhow::Result<&mut Self> {
+        let price_list = PriceList::from_file(path)?;
+        Ok(self.set_price_list(price_list.intern()))
+    }
+
     /// Enable execution traces. [`MachineContext::tracing`].
     pub fn enable_tracing(&mut self) -> &mut Self {
         self.tracing = true;
         self
     }
+
+    /// Enable gas breakdowns. [`MachineContext::gas_breakdown`].
+    pub fn enable_gas_breakdown(&mut self) -> &mut Self {
+        self.gas_breakdown = true;
+        self
+    }
+
+    /// Enable state cache audits. [`MachineContext::state_cache_audit`].
+    pub fn enable_state_cache_audit(&mut self) -> &mut Self {
+        self.state_cache_audit = true;
+        self
+    }
+
+    /// Restrict actor debugging to the given actors. [`MachineContext::debug_allowlist`].
+    pub fn restrict_actor_debugging(&mut self, allowlist: DebugAllowlist) -> &mut Self {
+        self.debug_allowlist = Some(allowlist);
+        self
+    }
+
+    /// Set a listener to be notified of every gas charge. [`MachineContext::gas_listener`].
+    pub fn set_gas_listener(&mut self, listener: Arc<dyn GasChargeListener>) -> &mut Self {
+        self.gas_listener = Some(listener);
+        self
+    }
+
+    /// Set the receiver of machine metrics. [`MachineContext::metrics`].
+    pub fn set_metrics(&mut self, metrics: Arc<dyn MachineMetrics>) -> &mut Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Journal flushes to the given journal. [`MachineContext::flush_journal`].
+    pub fn set_flush_journal(&mut self, journal: Arc<dyn FlushJournal>) -> &mut Self {
+        self.flush_journal = Some(journal);
+        self
+    }
+
+    /// Set the gas fee rules. [`MachineContext::gas_outputs`].
+    pub fn set_gas_outputs_strategy(&mut self, strategy: Arc<dyn GasOutputsStrategy>) -> &mut Self {
+        self.gas_outputs = Some(strategy);
+        self
+    }
+
+    /// Disable the given syscalls. [`MachineContext::disabled_syscalls`].
+    pub fn disable_syscalls(&mut self, syscalls: SyscallSet) -> &mut Self {
+        self.disabled_syscalls = syscalls;
+        self
+    }
+
+    /// Make the machine read-only. [`MachineContext::read_only`].
+    pub fn enable_read_only(&mut self) -> &mut Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Set whether to preload the builtin actors. [`MachineContext::preload_builtin_actors`].
+    pub fn set_preload_builtin_actors(&mut self, preload: bool) -> &mut Self {
+        self.preload_builtin_actors = preload;
+        self
+    }
+
+    /// Bound the actor cache. [`MachineContext::actor_cache_limit`].
+    pub fn set_actor_cache_limit(&mut self, limit: usize) -> &mut Self {
+        self.actor_cache_limit = Some(limit);
+        self
+    }
 }
@@ -0,0 +1,170 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Machines configured for the network version in effect at any epoch of a chain.
+use std::sync::Arc;
+
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::clock::ChainEpoch;
+
+use super::{
+    DefaultMachine, MachineContext, MachineContextBuilder, NetworkConfig, UpgradeSchedule,
+};
+use crate::call_manager::CallManager;
+use crate::engine::{EnginePool, MultiEngine};
+use crate::executor::DefaultExecutor;
+use crate::externs::Externs;
+use crate::kernel::Kernel;
+
+type ConfigureFn = Arc<dyn Fn(&mut MachineContext) + Send + Sync + 'static>;
+
+/// Creates machines and executors for any epoch of a chain, selecting the network version,
+/// builtin actors, and price list in effect at that epoch from the chain's [`UpgradeSchedule`],
+/// and an engine compiled for them from a [`MultiEngine`]. This is intended for clients that
+/// execute messages across network upgrades (e.g., when syncing the chain's history).
+///
+/// ```ignore
+/// let machines = MultiMachine::new(network, schedule, MultiEngine::new(concurrency))
+///     .configure(|mc| {
+///         mc.enable_tracing();
+///     });
+/// let context = machines
+///     .context(epoch)?
+///     .timestamp(timestamp)
+///     .initial_state_root(state_root)
+///     .base_fee(base_fee)
+///     .circulating_supply(circ_supply)
+///     .build()?;
+/// let mut executor: DefaultExecutor<MyKernel> = machines.executor(context, blockstore, externs)?;
+/// ```
+pub struct MultiMachine {
+    network: NetworkConfig,
+    schedule: UpgradeSchedule,
+    engines: MultiEngine,
+    configure: Option<ConfigureFn>,
+}
+
+impl MultiMachine {
+    /// Creates machines for the chain with the given base network config (the config before the
+    /// first upgrade in the schedule) and upgrade schedule.
+    pub fn new(network: NetworkConfig, schedule: UpgradeSchedule, engines: MultiEngine) -> Self {
+        Self {
+            network,
+            schedule,
+            engines,
+            configure: None,
+        }
+    }
+
+    /// Applies node-local settings (e.g., tracing or metrics) to each machine context, after the
+    /// upgrade in effect has been applied.
+    pub fn configure(mut self, f: impl Fn(&mut MachineContext) + Send + Sync + 'static) -> Self {
+        self.configure = Some(Arc::new(f));
+        self
+    }
+
+    /// Returns the chain's upgrade schedule.
+    pub fn upgrade_schedule(&self) -> &UpgradeSchedule {
+        &self.schedule
+    }
+
+    /// Returns the engines, by engine config.
+    pub fn engines(&self) -> &MultiEngine {
+        &self.engines
+    }
+
+    /// Returns the network config in effect at the given epoch.
+    pub fn network_config(&self, epoch: ChainEpoch) -> anyhow::Result<NetworkConfig> {
+        self.schedule.network_config(&self.network, epoch)
+    }
+
+    /// Returns a builder for a machine context at the given epoch, with the network config in
+    /// effect at that epoch and the node-local settings applied. The caller must at least set the
+    /// initial state root and base fee.
+    pub fn context(&self, epoch: ChainEpoch) -> anyhow::Result<MachineContextBuilder> {
+        let builder = MachineContext::builder(self.network_config(epoch)?).epoch(epoch);
+        Ok(match &self.configure {
+            Some(configure) => builder.configure(&**configure),
+            None => builder,
+        })
+    }
+
+    /// Returns an engine pool for the given machine context's network config.
+    pub fn engine(&self, context: &MachineContext) -> anyhow::Result<EnginePool> {
+        self.engines.get(&context.network)
+    }
+
+    /// Creates an executor over a new machine with the given context (see
+    /// [`MultiMachine::context`]), using the upgrade schedule to advance epochs (see
+    /// [`DefaultExecutor::advance_epoch`]).
+    pub fn executor<K, B, E>(
+        &self,
+        context: MachineContext,
+        blockstore: B,
+        externs: E,
+    ) -> anyhow::Result<DefaultExecutor<K>>
+    where
+        K: Kernel,
+        K::CallManager: CallManager<Machine = DefaultMachine<B, E>>,
+        B: Blockstore + 'static,
+        E: Externs + 'static,
+    {
+        let engine = self.engine(&context)?;
+        let machine = DefaultMachine::new(&context, blockstore, externs)?;
+        let mut executor = DefaultExecutor::new(engine, machine)?;
+        executor.set_upgrade_schedule(self.schedule.clone());
+        Ok(executor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cid::Cid;
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::version::NetworkVersion;
+
+    use super::*;
+    use crate::gas::PriceList;
+    use crate::machine::NetworkUpgrade;
+
+    #[test]
+    fn context_per_epoch() {
+        let custom: &'static PriceList = Box::leak(Box::new(
+            crate::gas::price_list_by_network_version(NetworkVersion::V21).clone(),
+        ));
+        let manifest = Cid::default();
+        let schedule = UpgradeSchedule::new([NetworkUpgrade::new(100, NetworkVersion::V21)
+            .with_price_list(custom)
+            .with_actors(manifest)])
+        .unwrap();
+        let machines = MultiMachine::new(
+            NetworkConfig::new(NetworkVersion::V21),
+            schedule,
+            MultiEngine::default(),
+        )
+        .configure(|mc| {
+            mc.enable_tracing();
+        });
+
+        let context = |epoch| {
+            machines
+                .context(epoch)
+                .unwrap()
+                .initial_state_root(Cid::default())
+                .base_fee(TokenAmount::from_atto(100))
+                .build()
+                .unwrap()
+        };
+
+        let before = context(99);
+        assert_eq!(before.epoch, 99);
+        assert!(before.tracing);
+        assert_eq!(before.builtin_actors_override, None);
+        assert!(!std::ptr::eq(before.price_list, custom));
+
+        let after = context(100);
+        assert_eq!(after.epoch, 100);
+        assert!(after.tracing);
+        assert_eq!(after.builtin_actors_override, Some(manifest));
+        assert!(std::ptr::eq(after.price_list, custom));
+    }
+}
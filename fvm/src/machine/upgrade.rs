@@ -0,0 +1,192 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Network upgrade schedules, switching network versions, actor bundles, and pricing at fixed
+//! epochs.
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail};
+use cid::Cid;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::version::NetworkVersion;
+
+use super::NetworkConfig;
+use crate::gas::PriceList;
+
+/// A network upgrade, taking effect at a given epoch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkUpgrade {
+    /// The first epoch at which the upgrade is in effect.
+    pub epoch: ChainEpoch,
+    /// The network version from the upgrade on.
+    pub network_version: NetworkVersion,
+    /// The builtin-actors manifest to use from the upgrade on (see
+    /// [`NetworkConfig::override_actors`]), or `None` to load the manifest from the system actor.
+    pub actors: Option<Cid>,
+    /// The price list to use from the upgrade on, or `None` to select the network version's price
+    /// list from [`NetworkConfig::price_lists`].
+    pub price_list: Option<&'static PriceList>,
+}
+
+impl NetworkUpgrade {
+    /// Creates an upgrade to the given network version, with its default actors and prices.
+    pub fn new(epoch: ChainEpoch, network_version: NetworkVersion) -> Self {
+        Self {
+            epoch,
+            network_version,
+            actors: None,
+            price_list: None,
+        }
+    }
+
+    /// Switches to the given builtin-actors manifest. [`NetworkUpgrade::actors`].
+    pub fn with_actors(mut self, manifest: Cid) -> Self {
+        self.actors = Some(manifest);
+        self
+    }
+
+    /// Switches to the given price list. [`NetworkUpgrade::price_list`].
+    pub fn with_price_list(mut self, price_list: &'static PriceList) -> Self {
+        self.price_list = Some(price_list);
+        self
+    }
+
+    /// Applies the upgrade to the network config. Fails if no price list is known for the network
+    /// version.
+    pub fn apply(&self, nc: &mut NetworkConfig) -> anyhow::Result<()> {
+        let price_list = match self.price_list {
+            Some(price_list) => price_list,
+            None => nc.price_lists.get(self.network_version).ok_or_else(|| {
+                anyhow!("no price list for network version {}", self.network_version)
+            })?,
+        };
+        nc.network_version = self.network_version;
+        nc.builtin_actors_override = self.actors;
+        nc.price_list = price_list;
+        nc.max_call_depth = price_list.max_call_depth();
+        Ok(())
+    }
+}
+
+/// The network upgrades of a chain, by epoch. This is consensus-critical: all nodes of a network
+/// must use the same schedule.
+///
+/// The schedule only switches the machine's configuration. Any state migrations an upgrade
+/// requires must be run by the embedder before executing messages at the upgrade epoch.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpgradeSchedule {
+    upgrades: BTreeMap<ChainEpoch, NetworkUpgrade>,
+}
+
+impl UpgradeSchedule {
+    /// Creates a schedule from the given upgrades. Fails if two upgrades share an epoch or if the
+    /// network version ever decreases.
+    pub fn new(upgrades: impl IntoIterator<Item = NetworkUpgrade>) -> anyhow::Result<Self> {
+        let mut schedule = Self::default();
+        for upgrade in upgrades {
+            if schedule.upgrades.contains_key(&upgrade.epoch) {
+                bail!("multiple upgrades at epoch {}", upgrade.epoch);
+            }
+            schedule.upgrades.insert(upgrade.epoch, upgrade);
+        }
+        let versions: Vec<_> = schedule
+            .upgrades
+            .values()
+            .map(|u| u.network_version)
+            .collect();
+        if let Some(w) = versions.windows(2).find(|w| w[1] < w[0]) {
+            bail!("network version decreases from {} to {}", w[0], w[1]);
+        }
+        Ok(schedule)
+    }
+
+    /// Returns the upgrade in effect at the given epoch (the last one at or before it), if any.
+    pub fn upgrade_at(&self, epoch: ChainEpoch) -> Option<&NetworkUpgrade> {
+        self.upgrades.range(..=epoch).next_back().map(|(_, u)| u)
+    }
+
+    /// Returns the last upgrade taking effect after `from` and at or before `to`, if any. This is
+    /// the upgrade to apply when advancing from epoch `from` to epoch `to`.
+    pub fn upgrade_between(&self, from: ChainEpoch, to: ChainEpoch) -> Option<&NetworkUpgrade> {
+        if to <= from {
+            return None;
+        }
+        self.upgrades
+            .range(from + 1..=to)
+            .next_back()
+            .map(|(_, u)| u)
+    }
+
+    /// Returns the network config in effect at the given epoch: `base` with the upgrade in effect
+    /// applied, if any.
+    pub fn network_config(
+        &self,
+        base: &NetworkConfig,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<NetworkConfig> {
+        let mut nc = base.clone();
+        if let Some(upgrade) = self.upgrade_at(epoch) {
+            upgrade.apply(&mut nc)?;
+        }
+        Ok(nc)
+    }
+
+    /// Iterates over the upgrades in epoch order.
+    pub fn iter(&self) -> impl Iterator<Item = &NetworkUpgrade> {
+        self.upgrades.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrade_schedule() {
+        let custom: &'static PriceList = Box::leak(Box::new(
+            crate::gas::price_list_by_network_version(NetworkVersion::V21).clone(),
+        ));
+        let schedule = UpgradeSchedule::new([
+            NetworkUpgrade::new(100, NetworkVersion::V21).with_price_list(custom),
+            NetworkUpgrade::new(10, NetworkVersion::V21),
+        ])
+        .unwrap();
+        assert_eq!(
+            schedule.iter().map(|u| u.epoch).collect::<Vec<_>>(),
+            [10, 100]
+        );
+
+        assert_eq!(schedule.upgrade_at(9), None);
+        assert_eq!(schedule.upgrade_at(10).unwrap().epoch, 10);
+        assert_eq!(schedule.upgrade_at(99).unwrap().epoch, 10);
+        assert_eq!(schedule.upgrade_at(1000).unwrap().epoch, 100);
+
+        assert_eq!(schedule.upgrade_between(10, 99), None);
+        assert_eq!(schedule.upgrade_between(9, 10).unwrap().epoch, 10);
+        assert_eq!(schedule.upgrade_between(0, 100).unwrap().epoch, 100);
+        assert_eq!(schedule.upgrade_between(100, 100), None);
+
+        let base = NetworkConfig::new(NetworkVersion::V21);
+        let nc = schedule.network_config(&base, 100).unwrap();
+        assert!(std::ptr::eq(nc.price_list, custom));
+        let nc = schedule.network_config(&base, 99).unwrap();
+        assert!(std::ptr::eq(nc.price_list, base.price_list));
+
+        // Upgrades must not share epochs or downgrade.
+        assert!(UpgradeSchedule::new([
+            NetworkUpgrade::new(10, NetworkVersion::V21),
+            NetworkUpgrade::new(10, NetworkVersion::V21),
+        ])
+        .is_err());
+        assert!(UpgradeSchedule::new([
+            NetworkUpgrade::new(10, NetworkVersion::V21),
+            NetworkUpgrade::new(20, NetworkVersion::V20),
+        ])
+        .is_err());
+
+        // Upgrades to network versions without prices fail to apply.
+        let mut nc = base.clone();
+        assert!(NetworkUpgrade::new(10, NetworkVersion::V20)
+            .apply(&mut nc)
+            .is_err());
+    }
+}
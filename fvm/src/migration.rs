@@ -0,0 +1,346 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! State migrations between network versions, run by the embedder at network upgrades before
+//! executing the first message of the new network version (see
+//! [`UpgradeSchedule`](crate::machine::UpgradeSchedule)).
+//!
+//! A [`Migration`] migrates each actor in the state tree with the [`ActorMigration`] registered
+//! for the actor's code, in parallel, and then verifies the migrated state tree. Actors with codes
+//! that have no registered migration are carried over unchanged.
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context as _};
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::ActorID;
+use rayon::prelude::*;
+
+use crate::state_tree::{ActorState, StateTree};
+
+/// Migrates a state tree from one network version to the next.
+pub trait StateMigration<BS: Blockstore>: Send + Sync {
+    /// Migrates the state tree with the given root at the given upgrade epoch, writing the new
+    /// state to the blockstore and returning the new state root.
+    fn migrate_state(&self, store: &BS, root: &Cid, epoch: ChainEpoch) -> anyhow::Result<Cid>;
+}
+
+/// An actor to migrate, see [`ActorMigration`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActorMigrationInput {
+    pub id: ActorID,
+    /// The actor's state before the migration.
+    pub actor: ActorState,
+    /// The upgrade epoch.
+    pub epoch: ChainEpoch,
+}
+
+/// Migrates the actors with a given code, see [`Migration::register`].
+pub trait ActorMigration<BS: Blockstore>: Send + Sync {
+    /// Migrates an actor, returning its new state (usually with a new code CID and state root),
+    /// or `None` to delete the actor. Actors are migrated concurrently, so a migration must only
+    /// read the actor's own state, and write new blocks to the blockstore.
+    fn migrate_actor(
+        &self,
+        store: &BS,
+        input: &ActorMigrationInput,
+    ) -> anyhow::Result<Option<ActorState>>;
+
+    /// Checks an actor's migrated state in the verification pass, once the migrated state tree
+    /// has been written. By default, nothing is checked.
+    fn verify_actor(
+        &self,
+        store: &BS,
+        input: &ActorMigrationInput,
+        migrated: Option<&ActorState>,
+    ) -> anyhow::Result<()> {
+        let _ = (store, input, migrated);
+        Ok(())
+    }
+}
+
+/// An actor migration that only changes the actors' code, keeping their state as is (e.g., for
+/// builtin actors whose state didn't change between bundles).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeMigration {
+    pub new_code: Cid,
+}
+
+impl<BS: Blockstore> ActorMigration<BS> for CodeMigration {
+    fn migrate_actor(
+        &self,
+        _store: &BS,
+        input: &ActorMigrationInput,
+    ) -> anyhow::Result<Option<ActorState>> {
+        Ok(Some(ActorState {
+            code: self.new_code,
+            ..input.actor.clone()
+        }))
+    }
+
+    fn verify_actor(
+        &self,
+        _store: &BS,
+        input: &ActorMigrationInput,
+        migrated: Option<&ActorState>,
+    ) -> anyhow::Result<()> {
+        let expected = ActorState {
+            code: self.new_code,
+            ..input.actor.clone()
+        };
+        if migrated != Some(&expected) {
+            bail!("expected {:?}, found {:?}", expected, migrated);
+        }
+        Ok(())
+    }
+}
+
+/// Receives the progress of a [`Migration`].
+pub trait MigrationProgress: Send + Sync {
+    /// Called as actors are migrated (from the migration's worker threads), with the number of
+    /// actors processed so far and the total number of actors.
+    fn on_progress(&self, processed: usize, total: usize);
+}
+
+/// A [`StateMigration`] migrating each actor with the [`ActorMigration`] registered for its code.
+pub struct Migration<BS: Blockstore> {
+    actors: HashMap<Cid, Arc<dyn ActorMigration<BS>>>,
+    progress: Option<(Arc<dyn MigrationProgress>, usize)>,
+    verify: bool,
+}
+
+impl<BS: Blockstore> Default for Migration<BS> {
+    fn default() -> Self {
+        Self {
+            actors: HashMap::new(),
+            progress: None,
+            verify: true,
+        }
+    }
+}
+
+impl<BS: Blockstore> Migration<BS> {
+    /// Creates a migration that carries over all actors unchanged, and verifies the migrated state.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Migrates the actors with the given code with the given migration. Registering a code twice
+    /// replaces its migration.
+    pub fn register(mut self, code: Cid, migration: impl ActorMigration<BS> + 'static) -> Self {
+        self.actors.insert(code, Arc::new(migration));
+        self
+    }
+
+    /// Reports the progress of the migration every `interval` actors, and once all actors have
+    /// been processed.
+    pub fn with_progress(mut self, progress: Arc<dyn MigrationProgress>, interval: usize) -> Self {
+        self.progress = Some((progress, interval.max(1)));
+        self
+    }
+
+    /// Skips the verification pass (see [`Migration::verify`]) after migrating.
+    pub fn without_verification(mut self) -> Self {
+        self.verify = false;
+        self
+    }
+
+    /// Verifies a migrated state tree against the state tree it was migrated from:
+    ///
+    /// 1. Every actor with a registered migration passes [`ActorMigration::verify_actor`].
+    /// 2. Every other actor is unchanged.
+    /// 3. The migrated state tree has no other actors.
+    pub fn verify(
+        &self,
+        store: &BS,
+        root: &Cid,
+        migrated_root: &Cid,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<()> {
+        let migrated = StateTree::new_from_root(store, migrated_root)?;
+        let mut expected_actors = 0;
+        for (id, actor) in load_actors(store, root)? {
+            let new = migrated.get_actor(id)?;
+            match self.actors.get(&actor.code) {
+                Some(migration) => {
+                    let input = ActorMigrationInput { id, actor, epoch };
+                    migration
+                        .verify_actor(store, &input, new.as_ref())
+                        .with_context(|| format!("failed to verify migrated actor {}", id))?;
+                }
+                None if new.as_ref() != Some(&actor) => {
+                    bail!("actor {} changed without a migration", id);
+                }
+                None => {}
+            }
+            if new.is_some() {
+                expected_actors += 1;
+            }
+        }
+
+        let mut actors = 0;
+        migrated.for_each(|_, _| {
+            actors += 1;
+            Ok(())
+        })?;
+        if actors != expected_actors {
+            bail!(
+                "migrated state tree has {} actors, expected {}",
+                actors,
+                expected_actors
+            );
+        }
+        Ok(())
+    }
+}
+
+impl<BS: Blockstore + Sync> StateMigration<BS> for Migration<BS> {
+    fn migrate_state(&self, store: &BS, root: &Cid, epoch: ChainEpoch) -> anyhow::Result<Cid> {
+        let actors = load_actors(store, root)?;
+        let total = actors.len();
+        let processed = AtomicUsize::new(0);
+        let report = || {
+            if let Some((progress, interval)) = &self.progress {
+                let n = processed.fetch_add(1, Ordering::Relaxed) + 1;
+                if n % interval == 0 || n == total {
+                    progress.on_progress(n, total);
+                }
+            }
+        };
+
+        log::info!("migrating {} actors at epoch {}", total, epoch);
+        let migrated = actors
+            .into_par_iter()
+            .filter_map(|(id, actor)| {
+                let res = self.actors.get(&actor.code).map(|migration| {
+                    let input = ActorMigrationInput { id, actor, epoch };
+                    migration
+                        .migrate_actor(store, &input)
+                        .with_context(|| format!("failed to migrate actor {}", id))
+                        .map(|new| (id, new))
+                });
+                report();
+                res
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut tree = StateTree::new_from_root(store, root)?;
+        for (id, actor) in migrated {
+            match actor {
+                Some(actor) => tree.set_actor(id, actor),
+                None => tree.delete_actor(id),
+            }
+        }
+        let migrated_root = tree.flush()?;
+
+        if self.verify {
+            self.verify(store, root, &migrated_root, epoch)
+                .context("migration verification failed")?;
+        }
+        Ok(migrated_root)
+    }
+}
+
+/// Loads all actors in the state tree with the given root.
+fn load_actors<BS: Blockstore>(
+    store: &BS,
+    root: &Cid,
+) -> anyhow::Result<Vec<(ActorID, ActorState)>> {
+    let tree = StateTree::new_from_root(store, root)?;
+    let mut actors = Vec::new();
+    tree.for_each(|addr, actor| {
+        let id = addr
+            .id()
+            .map_err(|_| anyhow!("non-id address {} in the state tree", addr))?;
+        actors.push((id, actor.clone()));
+        Ok(())
+    })?;
+    Ok(actors)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_ipld_encoding::DAG_CBOR;
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::state::StateTreeVersion;
+    use fvm_shared::IDENTITY_HASH;
+    use multihash::Multihash;
+
+    use super::*;
+
+    fn code(name: &[u8]) -> Cid {
+        Cid::new_v1(DAG_CBOR, Multihash::wrap(IDENTITY_HASH, name).unwrap())
+    }
+
+    struct Delete;
+
+    impl ActorMigration<MemoryBlockstore> for Delete {
+        fn migrate_actor(
+            &self,
+            _: &MemoryBlockstore,
+            _: &ActorMigrationInput,
+        ) -> anyhow::Result<Option<ActorState>> {
+            Ok(None)
+        }
+    }
+
+    #[derive(Default)]
+    struct Progress(Mutex<Vec<(usize, usize)>>);
+
+    impl MigrationProgress for Progress {
+        fn on_progress(&self, processed: usize, total: usize) {
+            self.0.lock().unwrap().push((processed, total));
+        }
+    }
+
+    #[test]
+    fn migrate_state() {
+        let store = MemoryBlockstore::default();
+        let actor = |c: &[u8]| ActorState::new_empty(code(c), None);
+        let mut tree = StateTree::new(&store, StateTreeVersion::V5).unwrap();
+        tree.set_actor(1, actor(b"old"));
+        tree.set_actor(2, actor(b"old"));
+        tree.set_actor(3, actor(b"gone"));
+        tree.set_actor(4, actor(b"other"));
+        let root = tree.flush().unwrap();
+
+        let progress = Arc::new(Progress::default());
+        let migration = Migration::new()
+            .register(
+                code(b"old"),
+                CodeMigration {
+                    new_code: code(b"new"),
+                },
+            )
+            .register(code(b"gone"), Delete)
+            .with_progress(progress.clone(), 3);
+        let new_root = migration.migrate_state(&store, &root, 100).unwrap();
+
+        let tree = StateTree::new_from_root(&store, &new_root).unwrap();
+        assert_eq!(tree.get_actor(1).unwrap(), Some(actor(b"new")));
+        assert_eq!(tree.get_actor(2).unwrap(), Some(actor(b"new")));
+        assert_eq!(tree.get_actor(3).unwrap(), None);
+        assert_eq!(tree.get_actor(4).unwrap(), Some(actor(b"other")));
+        // Actors are migrated concurrently, so progress may be reported out of order.
+        let mut reported = progress.0.lock().unwrap().clone();
+        reported.sort();
+        assert_eq!(reported, [(3, 4), (4, 4)]);
+
+        // Unmigrated actors must not change.
+        let mut tree = StateTree::new_from_root(&store, &new_root).unwrap();
+        tree.set_actor(
+            4,
+            ActorState {
+                balance: TokenAmount::from_atto(1),
+                ..actor(b"other")
+            },
+        );
+        let bad_root = tree.flush().unwrap();
+        assert!(migration.verify(&store, &root, &bad_root, 100).is_err());
+    }
+}
@@ -2,22 +2,28 @@
 // Copyright 2019-2022 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use anyhow::{anyhow, Context as _};
 use cid::{multihash, Cid};
 use fvm_ipld_blockstore::Blockstore;
-use fvm_ipld_encoding::CborStore;
-use fvm_ipld_hamt::Hamt;
+use fvm_ipld_encoding::{scan_for_links, CborStore, DAG_CBOR};
+use fvm_ipld_hamt::{Change, Hamt};
 use fvm_shared::address::{Address, Payload};
+use fvm_shared::commcid::{FIL_COMMITMENT_SEALED, FIL_COMMITMENT_UNSEALED};
+use fvm_shared::econ::TokenAmount;
 use fvm_shared::state::{StateInfo0, StateRoot};
-use fvm_shared::{ActorID, HAMT_BIT_WIDTH};
+use fvm_shared::{ActorID, HAMT_BIT_WIDTH, IDENTITY_HASH};
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer};
+use serde::Serialize;
 
 pub use fvm_shared::state::{ActorState, StateTreeVersion};
 
 use crate::history_map::HistoryMap;
 use crate::init_actor::State as InitActorState;
-use crate::kernel::{ClassifyResult, ExecutionError, Result};
+use crate::kernel::{ClassifyResult, Context as _, ErrorContext, ExecutionError, Result};
+use crate::machine::CacheStats;
 
 /// State tree implementation using hamt. This structure is not threadsafe and should only be used
 /// in sync contexts.
@@ -34,6 +40,46 @@ pub struct StateTree<S> {
     /// Snapshot layers. Each layer contains points in the actor/resolve cache histories to which
     /// said caches will be reverted on revert.
     layers: Vec<StateSnapLayer>,
+    /// Per-actor state sizes, computed on demand by [`StateTree::actor_size`].
+    sizes: RefCell<SizeCache>,
+    /// The actors accessed since [`StateTree::track_accesses`] was called, if tracking.
+    accesses: RefCell<Option<StateAccesses>>,
+    /// Actor cache hits and misses, see [`StateTree::actor_cache_stats`].
+    actor_cache_stats: Cell<CacheStats>,
+    /// The maximum number of clean actors kept in the actor cache, see
+    /// [`StateTree::set_actor_cache_limit`].
+    actor_cache_limit: Option<usize>,
+    /// When each cached actor was last used, to evict the least recently used actors.
+    actor_cache_lru: RefCell<LruClock>,
+}
+
+/// The actors read and written through a state tree, see [`StateTree::track_accesses`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateAccesses {
+    /// The actors looked up, including the init actor when resolving addresses.
+    pub reads: BTreeSet<ActorID>,
+    /// The actors set or deleted.
+    pub writes: BTreeSet<ActorID>,
+}
+
+impl StateAccesses {
+    /// Returns true if any actor was either read or written here and written there.
+    pub fn conflicts_with(&self, writes: &BTreeSet<ActorID>) -> bool {
+        self.reads
+            .iter()
+            .chain(&self.writes)
+            .any(|id| writes.contains(id))
+    }
+}
+
+/// Memoized state sizes, for storage accounting.
+#[derive(Default)]
+struct SizeCache {
+    /// The size of the DAG under each visited block that links to other blocks. Leaves aren't
+    /// memoized as reading them costs as much as looking them up.
+    blocks: HashMap<Cid, u64>,
+    /// The state sizes of the actors queried so far. These are kept up-to-date on flush.
+    actors: HashMap<ActorID, u64>,
 }
 
 /// An entry in the actor cache.
@@ -45,8 +91,76 @@ struct ActorCacheEntry {
     actor: Option<ActorState>,
 }
 
+/// Tracks when each cached actor was last used.
+#[derive(Default)]
+struct LruClock {
+    tick: u64,
+    last_used: HashMap<ActorID, u64>,
+}
+
+impl LruClock {
+    fn touch(&mut self, id: ActorID) {
+        self.tick += 1;
+        self.last_used.insert(id, self.tick);
+    }
+}
+
+/// The result of [`StateTree::validate`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The number of actors in the state tree.
+    pub actors: usize,
+    /// The number of distinct blocks found in the blockstore.
+    pub blocks: usize,
+    /// The problems found, if any.
+    pub problems: Vec<ValidationProblem>,
+}
+
+impl ValidationReport {
+    /// Returns true if no problems were found.
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// A problem found by [`StateTree::validate`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationProblem {
+    #[error("block {cid} (linked from {parent}) is missing")]
+    MissingBlock { cid: Cid, parent: Cid },
+    #[error("block {0} isn't valid DAG-CBOR")]
+    InvalidBlock(Cid),
+    /// An actor is keyed by something other than an ID address in the actors HAMT.
+    #[error("actor key {0:?} isn't an ID address")]
+    InvalidKey(Vec<u8>),
+    /// The actors HAMT couldn't be iterated, e.g., because an actor doesn't decode. Actors after
+    /// the failure aren't validated.
+    #[error("failed to iterate over the actors: {0}")]
+    InvalidActors(String),
+    #[error("actor {id} has a negative balance ({balance})")]
+    NegativeBalance { id: ActorID, balance: TokenAmount },
+}
+
+/// Why a state root couldn't be loaded. Returned (as a fatal error) by
+/// [`StateTree::new_from_root`] and [`StateTree::revert_to`], so callers can tell state trees
+/// they don't support (e.g., written by a future network version) apart from missing or corrupt
+/// ones, with `anyhow::Error::downcast_ref`.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum StateRootError {
+    #[error("state tree {0} not found")]
+    NotFound(Cid),
+    /// The state root isn't a versioned [`StateRoot`] (e.g., a version 0 state tree, which is a
+    /// bare HAMT).
+    #[error("state tree {0} isn't a versioned state root")]
+    Unversioned(Cid),
+    #[error("unsupported state tree version: {0}")]
+    UnsupportedVersion(u64),
+}
+
 /// State snap shot layer.
 struct StateSnapLayer {
+    /// The layer's name, if begun with [`StateTree::begin_layer`].
+    name: Option<String>,
     /// The actor-cache height at which this snapshot was taken.
     actor_cache_height: usize,
     /// The resolve-cache height at which this snapshot was taken.
@@ -64,10 +178,9 @@ where
             | StateTreeVersion::V2
             | StateTreeVersion::V3
             | StateTreeVersion::V4 => {
-                return Err(ExecutionError::Fatal(anyhow!(
-                    "unsupported state tree version: {:?}",
-                    version
-                )))
+                return Err(ExecutionError::Fatal(
+                    StateRootError::UnsupportedVersion(version as u64).into(),
+                ))
             }
             StateTreeVersion::V5 => {
                 let cid = store
@@ -87,43 +200,31 @@ where
             actor_cache: Default::default(),
             resolve_cache: Default::default(),
             layers: Vec::new(),
+            sizes: Default::default(),
+            accesses: Default::default(),
+            actor_cache_stats: Default::default(),
+            actor_cache_limit: None,
+            actor_cache_lru: Default::default(),
         })
     }
 
     /// Constructor for a hamt state tree given an IPLD store
     pub fn new_from_root(store: S, c: &Cid) -> Result<Self> {
-        // Try to load state root, if versioned
-        let (version, info, actors) = match store.get_cbor(c) {
-            Ok(Some(StateRoot {
-                version,
-                info,
-                actors,
-            })) => (version, Some(info), actors),
-            Ok(None) => {
-                return Err(ExecutionError::Fatal(anyhow!(
-                    "failed to find state tree {}",
-                    c
-                )))
-            }
-            Err(e) => {
-                return Err(ExecutionError::Fatal(anyhow!(
-                    "failed to load state tree {}: {}",
-                    c,
-                    e
-                )))
-            }
-        };
+        Self::load_root(store, c).error_context(ErrorContext::default().with_cid(*c))
+    }
 
+    fn load_root(store: S, c: &Cid) -> Result<Self> {
+        let (version, info, actors) = read_state_root(&store, c)?;
         match version {
             StateTreeVersion::V0
             | StateTreeVersion::V1
             | StateTreeVersion::V2
             | StateTreeVersion::V3
-            | StateTreeVersion::V4 => Err(ExecutionError::Fatal(anyhow!(
-                "unsupported state tree version: {:?}",
-                version
-            ))),
+            | StateTreeVersion::V4 => Err(ExecutionError::Fatal(
+                StateRootError::UnsupportedVersion(version as u64).into(),
+            )),
 
+            // V5 state trees are HAMTs (v3) with a bit-width of 5.
             StateTreeVersion::V5 => {
                 let hamt = Hamt::load_with_bit_width(&actors, store, HAMT_BIT_WIDTH)
                     .context("failed to load state tree")
@@ -132,10 +233,15 @@ where
                 Ok(Self {
                     hamt,
                     version,
-                    info,
+                    info: Some(info),
                     actor_cache: Default::default(),
                     resolve_cache: Default::default(),
                     layers: Vec::new(),
+                    sizes: Default::default(),
+                    accesses: Default::default(),
+                    actor_cache_stats: Default::default(),
+                    actor_cache_limit: None,
+                    actor_cache_lru: Default::default(),
                 })
             }
         }
@@ -158,10 +264,16 @@ where
 
     /// Get actor state from an actor ID.
     pub fn get_actor(&self, id: ActorID) -> Result<Option<ActorState>> {
-        self.actor_cache
+        if let Some(accesses) = &mut *self.accesses.borrow_mut() {
+            accesses.reads.insert(id);
+        }
+        let mut cached = true;
+        let actor = self
+            .actor_cache
             .borrow_mut()
             .get_or_try_insert_with(id, || {
                 // It's not cached/dirty, so we look it up and cache it.
+                cached = false;
                 let key = Address::new_id(id).to_bytes();
                 Ok(ActorCacheEntry {
                     dirty: false,
@@ -169,15 +281,77 @@ where
                         .hamt
                         .get(&key)
                         .with_context(|| format!("failed to lookup actor {}", id))
-                        .or_fatal()?
+                        .or_fatal()
+                        .error_context(ErrorContext::default().with_actor(Address::new_id(id)))?
                         .cloned(),
                 })
             })
-            .map(|ActorCacheEntry { actor, .. }| actor.clone())
+            .map(|ActorCacheEntry { actor, .. }| actor.clone());
+        let mut stats = self.actor_cache_stats.get();
+        stats.record(cached);
+        self.actor_cache_stats.set(stats);
+        self.touch_actor(id);
+        actor
+    }
+
+    /// Records that the given actor was used, if the actor cache is bounded.
+    fn touch_actor(&self, id: ActorID) {
+        if self.actor_cache_limit.is_some() {
+            self.actor_cache_lru.borrow_mut().touch(id);
+        }
+    }
+
+    /// Returns the number of actor lookups served from the actor cache (hits), and the number
+    /// that had to load the actor from the state tree (misses).
+    pub fn actor_cache_stats(&self) -> CacheStats {
+        self.actor_cache_stats.get()
+    }
+
+    /// Bounds the number of clean (unmodified since the last flush) actors kept in the actor
+    /// cache, evicting the least recently used ones at the end of each top-level transaction and
+    /// on flush. Modified actors are always kept until they're flushed. By default, the cache is
+    /// unbounded.
+    ///
+    /// Actor usage is only tracked while the cache is bounded, so actors cached before the limit
+    /// was set are evicted first.
+    pub fn set_actor_cache_limit(&mut self, limit: Option<usize>) {
+        self.actor_cache_limit = limit;
+        if !self.in_transaction() {
+            self.evict_actors();
+        }
+    }
+
+    /// Evicts the least recently used clean actors from the actor cache, down to the limit. Must
+    /// not be called inside of a transaction, as evictions can't be rolled back.
+    fn evict_actors(&mut self) {
+        let Some(limit) = self.actor_cache_limit else {
+            return;
+        };
+        let cache = self.actor_cache.get_mut();
+        let lru = self.actor_cache_lru.get_mut();
+        let mut clean: Vec<_> = cache
+            .iter()
+            .filter(|(_, entry)| !entry.dirty)
+            .map(|(&id, _)| (lru.last_used.get(&id).copied().unwrap_or_default(), id))
+            .collect();
+        if clean.len() <= limit {
+            return;
+        }
+        clean.sort_unstable();
+        let evicted: HashSet<ActorID> = clean[..clean.len() - limit]
+            .iter()
+            .map(|&(_, id)| id)
+            .collect();
+        cache.retain(|id, _| !evicted.contains(id));
+        lru.last_used.retain(|id, _| !evicted.contains(id));
     }
 
     /// Set actor state with an actor ID.
     pub fn set_actor(&mut self, id: ActorID, actor: ActorState) {
+        if let Some(accesses) = self.accesses.get_mut() {
+            accesses.writes.insert(id);
+        }
+        self.touch_actor(id);
         self.actor_cache.borrow_mut().insert(
             id,
             ActorCacheEntry {
@@ -211,6 +385,10 @@ where
 
     /// Delete actor identified by the supplied ID.
     pub fn delete_actor(&mut self, id: ActorID) {
+        if let Some(accesses) = self.accesses.get_mut() {
+            accesses.writes.insert(id);
+        }
+        self.touch_actor(id);
         // Record that we've deleted the actor.
         self.actor_cache.borrow_mut().insert(
             id,
@@ -221,19 +399,17 @@ where
         );
     }
 
-    /// Mutate and set actor state identified by the supplied ID. Returns a fatal error if the actor
-    /// doesn't exist.
-    pub fn mutate_actor<F>(&mut self, id: ActorID, mutate: F) -> Result<()>
+    /// Mutate and set actor state identified by the supplied ID, returning the result of the
+    /// closure. Returns a fatal error if the actor doesn't exist. If the closure fails, the actor
+    /// isn't changed.
+    pub fn mutate_actor<F, R>(&mut self, id: ActorID, mutate: F) -> Result<R>
     where
-        F: FnOnce(&mut ActorState) -> Result<()>,
+        F: FnOnce(&mut ActorState) -> Result<R>,
     {
-        self.maybe_mutate_actor_id(id, mutate).and_then(|found| {
-            if found {
-                Ok(())
-            } else {
-                Err(anyhow!("failed to lookup actor {}", id)).or_fatal()
-            }
-        })
+        self.try_mutate_actor(id, mutate)?
+            .with_context(|| format!("failed to lookup actor {}", id))
+            .or_fatal()
+            .error_context(ErrorContext::default().with_actor(Address::new_id(id)))
     }
 
     /// Try to mutate the actor state identified by the supplied ID, returning false if the actor
@@ -242,32 +418,130 @@ where
     where
         F: FnOnce(&mut ActorState) -> Result<()>,
     {
-        // Retrieve actor state from address
-        let mut act = match self.get_actor(id)? {
-            Some(act) => act,
-            None => return Ok(false),
-        };
+        Ok(self.try_mutate_actor(id, mutate)?.is_some())
+    }
 
-        // Apply function of actor state
-        mutate(&mut act)?;
-        // Set the actor
+    fn try_mutate_actor<F, R>(&mut self, id: ActorID, mutate: F) -> Result<Option<R>>
+    where
+        F: FnOnce(&mut ActorState) -> Result<R>,
+    {
+        let Some(mut act) = self.get_actor(id)? else {
+            return Ok(None);
+        };
+        let ret = mutate(&mut act)?;
         self.set_actor(id, act);
-        Ok(true)
+        Ok(Some(ret))
     }
 
-    /// Register a new address through the init actor.
-    pub fn register_new_address(&mut self, addr: &Address) -> Result<ActorID> {
-        let (mut state, mut actor) = InitActorState::load(self)?;
-
-        let new_id = state.map_address_to_new_id(self.store(), addr)?;
+    /// Loads the actor identified by the supplied ID along with its decoded state, or returns None
+    /// if the actor doesn't exist. Returns a fatal error if the actor's state can't be loaded as
+    /// a `T`.
+    pub fn get_actor_state<T>(&self, id: ActorID) -> Result<Option<(T, ActorState)>>
+    where
+        T: DeserializeOwned,
+    {
+        let Some(actor) = self.get_actor(id)? else {
+            return Ok(None);
+        };
+        let context = ErrorContext::default()
+            .with_actor(Address::new_id(id))
+            .with_cid(actor.state);
+        let state = self
+            .store()
+            .get_cbor(&actor.state)
+            .or_fatal()
+            .error_context(context.clone())?
+            .with_context(|| format!("state of actor {} not found", id))
+            .or_fatal()
+            .error_context(context)?;
+        Ok(Some((state, actor)))
+    }
 
-        // Set state for init actor in store and update root Cid
+    /// Loads, mutates, and stores the state of the actor identified by the supplied ID, updating
+    /// the actor's state root and returning the result of the closure. The closure is also passed
+    /// the state tree's store (e.g., to update the HAMTs in the state). Returns a fatal error if
+    /// the actor doesn't exist or its state can't be loaded as a `T`. If the closure fails, the
+    /// actor isn't changed.
+    pub fn mutate_actor_state<T, F, R>(&mut self, id: ActorID, mutate: F) -> Result<R>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce(&mut T, &S) -> Result<R>,
+    {
+        let (mut state, mut actor) = self
+            .get_actor_state(id)?
+            .with_context(|| format!("failed to lookup actor {}", id))
+            .or_fatal()
+            .error_context(ErrorContext::default().with_actor(Address::new_id(id)))?;
+        let ret = mutate(&mut state, self.store())?;
         actor.state = self
             .store()
             .put_cbor(&state, multihash::Code::Blake2b256)
             .or_fatal()?;
+        self.set_actor(id, actor);
+        Ok(ret)
+    }
+
+    /// Returns the size in bytes of the state reachable from the actor's state root, or None if
+    /// the actor doesn't exist. This reflects the current (possibly unflushed) state.
+    ///
+    /// Sizes are memoized by block, so the first query of a large actor is expensive but
+    /// subsequent queries only visit blocks that changed in the meantime. Once queried, the
+    /// actor's size is kept up-to-date on each flush and reported by [`StateTree::actor_sizes`].
+    ///
+    /// Blocks reachable through multiple links are counted once per link, and inlined (identity)
+    /// blocks and piece commitments count as zero bytes. This is node-local accounting and is
+    /// not part of consensus.
+    pub fn actor_size(&self, addr: &Address) -> Result<Option<u64>> {
+        let Some(id) = self.lookup_id(addr)? else {
+            return Ok(None);
+        };
+        let Some(actor) = self.get_actor(id)? else {
+            return Ok(None);
+        };
+        let mut sizes = self.sizes.borrow_mut();
+        let size = dag_size(self.store(), &actor.state, &mut sizes.blocks)
+            .error_context(ErrorContext::default().with_actor(Address::new_id(id)))?;
+        sizes.actors.insert(id, size);
+        Ok(Some(size))
+    }
+
+    /// Returns the state sizes of all actors queried through [`StateTree::actor_size`], as of the
+    /// last flush or query.
+    pub fn actor_sizes(&self) -> HashMap<ActorID, u64> {
+        self.sizes.borrow().actors.clone()
+    }
 
-        self.set_actor(crate::init_actor::INIT_ACTOR_ID, actor);
+    /// Discards all memoized state sizes and stops tracking the sizes of queried actors.
+    pub fn clear_size_cache(&mut self) {
+        *self.sizes.get_mut() = Default::default();
+    }
+
+    /// Starts recording the actors read and written, discarding anything recorded so far. Reverted
+    /// transactions aren't un-recorded.
+    pub fn track_accesses(&mut self) {
+        *self.accesses.get_mut() = Some(Default::default());
+    }
+
+    /// Stops recording accesses, returning the actors accessed since [`StateTree::track_accesses`]
+    /// (or None if not tracking).
+    pub fn take_accesses(&mut self) -> Option<StateAccesses> {
+        self.accesses.get_mut().take()
+    }
+
+    /// Runs the given function without recording the accesses it makes.
+    pub fn untracked<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let accesses = self.accesses.get_mut().take();
+        let ret = f(self);
+        *self.accesses.get_mut() = accesses;
+        ret
+    }
+
+    /// Register a new address through the init actor.
+    pub fn register_new_address(&mut self, addr: &Address) -> Result<ActorID> {
+        let new_id = self.mutate_actor_state(
+            crate::init_actor::INIT_ACTOR_ID,
+            |state: &mut InitActorState, store| state.map_address_to_new_id(store, addr),
+        )?;
         self.resolve_cache.borrow_mut().insert(*addr, new_id);
 
         Ok(new_id)
@@ -275,7 +549,19 @@ where
 
     /// Begin a new state transaction. Transactions stack.
     pub fn begin_transaction(&mut self) {
+        self.push_layer(None)
+    }
+
+    /// Begins a new named transaction layer (e.g., a debugger checkpoint or a speculative
+    /// execution). Named layers stack with transactions, and can be ended by name with
+    /// [`StateTree::commit_layer`] or [`StateTree::revert_layer`].
+    pub fn begin_layer(&mut self, name: impl Into<String>) {
+        self.push_layer(Some(name.into()))
+    }
+
+    fn push_layer(&mut self, name: Option<String>) {
         self.layers.push(StateSnapLayer {
+            name,
             actor_cache_height: self.actor_cache.get_mut().history_len(),
             resolve_cache_height: self.resolve_cache.get_mut().history_len(),
         })
@@ -283,12 +569,65 @@ where
 
     /// End a transaction, reverting if requested.
     pub fn end_transaction(&mut self, revert: bool) -> Result<()> {
-        let layer = self
-            .layers
-            .pop()
-            .context("state snapshots empty")
-            .or_fatal()?;
+        if self.layers.is_empty() {
+            return Err(anyhow!("state snapshots empty")).or_fatal();
+        }
+        self.end_layers(self.layers.len() - 1, revert);
+        Ok(())
+    }
+
+    /// Commits the innermost layer with the given name, along with all named layers begun after
+    /// it, keeping their changes in the enclosing layer (if any). Fails if a transaction begun
+    /// after the layer is still open.
+    pub fn commit_layer(&mut self, name: &str) -> Result<()> {
+        let idx = self.endable_layer_index(name)?;
+        self.end_layers(idx, false);
+        Ok(())
+    }
+
+    /// Reverts the innermost layer with the given name, along with all named layers begun after
+    /// it, discarding their changes. Fails if a transaction begun after the layer is still open.
+    pub fn revert_layer(&mut self, name: &str) -> Result<()> {
+        let idx = self.endable_layer_index(name)?;
+        self.end_layers(idx, true);
+        Ok(())
+    }
+
+    /// Returns the names of the current layers, from outermost to innermost (None for
+    /// transactions begun with [`StateTree::begin_transaction`]).
+    pub fn layers(&self) -> impl Iterator<Item = Option<&str>> {
+        self.layers.iter().map(|layer| layer.name.as_deref())
+    }
+
+    fn layer_index(&self, name: &str) -> Result<usize> {
+        self.layers
+            .iter()
+            .rposition(|layer| layer.name.as_deref() == Some(name))
+            .with_context(|| format!("no state tree layer named {}", name))
+            .or_fatal()
+    }
+
+    /// Returns the index of the innermost layer with the given name, checking that no
+    /// transactions were begun after it (those must be ended by whoever began them).
+    fn endable_layer_index(&self, name: &str) -> Result<usize> {
+        let idx = self.layer_index(name)?;
+        if self.layers[idx + 1..]
+            .iter()
+            .any(|layer| layer.name.is_none())
+        {
+            return Err(anyhow!(
+                "state tree layer {} has open transactions above it",
+                name
+            ))
+            .or_fatal();
+        }
+        Ok(idx)
+    }
+
+    /// Ends the layer at the given index, and all layers above it.
+    fn end_layers(&mut self, idx: usize, revert: bool) {
         if revert {
+            let layer = &self.layers[idx];
             self.actor_cache
                 .get_mut()
                 .rollback(layer.actor_cache_height);
@@ -296,12 +635,45 @@ where
                 .get_mut()
                 .rollback(layer.resolve_cache_height);
         }
+        self.layers.truncate(idx);
         // When we end the last transaction, discard the undo history.
         if !self.in_transaction() {
             self.actor_cache.get_mut().discard_history();
             self.resolve_cache.get_mut().discard_history();
+            self.evict_actors();
         }
-        Ok(())
+    }
+
+    /// Returns the actors set (Some) or deleted (None) since the state tree was last flushed,
+    /// including in the current layers.
+    pub fn pending_mutations(&self) -> BTreeMap<ActorID, Option<ActorState>> {
+        self.actor_cache
+            .borrow()
+            .iter()
+            .filter(|(_, entry)| entry.dirty)
+            .map(|(&id, entry)| (id, entry.actor.clone()))
+            .collect()
+    }
+
+    /// Returns the actors set (Some) or deleted (None) since the innermost layer with the given
+    /// name began, including in the layers begun after it. Actors set back to their state at the
+    /// start of the layer aren't included.
+    pub fn layer_mutations(&self, name: &str) -> Result<BTreeMap<ActorID, Option<ActorState>>> {
+        let height = self.layers[self.layer_index(name)?].actor_cache_height;
+        let cache = self.actor_cache.borrow();
+        Ok(cache
+            .changed_since(height)
+            .into_iter()
+            .filter_map(|(&id, before)| {
+                let after = cache.get(&id)?;
+                let changed = match before {
+                    // Merely loading an actor isn't a mutation.
+                    None => after.dirty,
+                    Some(before) => before.actor != after.actor,
+                };
+                changed.then(|| (id, after.actor.clone()))
+            })
+            .collect())
     }
 
     /// Returns true if we're inside of a transaction.
@@ -309,6 +681,14 @@ where
         !self.layers.is_empty()
     }
 
+    /// Returns true if any actor has been set or deleted since the state tree was last flushed.
+    pub fn is_dirty(&self) -> bool {
+        self.actor_cache
+            .borrow()
+            .iter()
+            .any(|(_, entry)| entry.dirty)
+    }
+
     /// Flush state tree and return Cid root.
     pub fn flush(&mut self) -> Result<Cid> {
         if self.in_transaction() {
@@ -316,11 +696,15 @@ where
                 "cannot flush while inside of a transaction",
             )));
         }
+        let mut resized = Vec::new();
         for (&id, entry) in self.actor_cache.get_mut().iter_mut() {
             if !entry.dirty {
                 continue;
             }
             entry.dirty = false;
+            if self.sizes.get_mut().actors.contains_key(&id) {
+                resized.push((id, entry.actor.as_ref().map(|act| act.state)));
+            }
             let addr = Address::new_id(id);
             match entry.actor {
                 None => {
@@ -335,6 +719,26 @@ where
         }
 
         let root = self.hamt.flush().or_fatal()?;
+        self.evict_actors();
+
+        // Update the sizes of tracked actors. Only blocks written since the last query are
+        // visited. Size accounting is best-effort and never fails the flush.
+        let sizes = self.sizes.get_mut();
+        for (id, state) in resized {
+            let Some(state) = state else {
+                sizes.actors.remove(&id);
+                continue;
+            };
+            match dag_size(self.hamt.store(), &state, &mut sizes.blocks) {
+                Ok(size) => {
+                    sizes.actors.insert(id, size);
+                }
+                Err(e) => {
+                    log::warn!("failed to update the state size of actor {}: {}", id, e);
+                    sizes.actors.remove(&id);
+                }
+            }
+        }
 
         match self.version {
             StateTreeVersion::V0 => Ok(root),
@@ -356,6 +760,173 @@ where
         }
     }
 
+    /// Discards all changes made since the state tree was last flushed to the given root, which
+    /// must be a state root of the same version stored in the state tree's blockstore. This
+    /// clears the state tree's caches.
+    pub fn revert_to(&mut self, root: &Cid) -> Result<()> {
+        if self.in_transaction() {
+            return Err(ExecutionError::Fatal(anyhow!(
+                "cannot revert while inside of a transaction",
+            )));
+        }
+        let (version, info, actors) = read_state_root(self.store(), root)?;
+        if version != self.version || Some(info) != self.info {
+            return Err(ExecutionError::Fatal(anyhow!(
+                "cannot revert to state tree {} of a different version",
+                root
+            )));
+        }
+        self.hamt
+            .set_root(&actors)
+            .context("failed to load state tree")
+            .or_fatal()?;
+        *self.actor_cache.get_mut() = Default::default();
+        *self.actor_cache_lru.get_mut() = Default::default();
+        *self.resolve_cache.get_mut() = Default::default();
+        *self.sizes.get_mut() = Default::default();
+        Ok(())
+    }
+
+    /// Audits the state tree's caches, returning a fatal error if any cached data is inconsistent
+    /// with the state tree's own state and blockstore. Specifically, this checks that:
+    ///
+    /// 1. Every clean entry in the actor cache matches the actor in the underlying HAMT.
+    /// 2. Every cached address resolution matches the init actor's address map.
+    /// 3. The code of every cached actor is present in the blockstore.
+    ///
+    /// This is expensive and is only meant to catch caches leaking data between state trees (e.g.,
+    /// across machines executing concurrently over different state roots).
+    pub fn audit_caches(&self) -> Result<()> {
+        for (&id, entry) in self.actor_cache.borrow().iter() {
+            if let Some(actor) = &entry.actor {
+                if !self.store().has(&actor.code).or_fatal()? {
+                    return Err(ExecutionError::Fatal(anyhow!(
+                        "code {} of cached actor {} is missing from the blockstore",
+                        actor.code,
+                        id
+                    ))
+                    .error_context(
+                        ErrorContext::default()
+                            .with_actor(Address::new_id(id))
+                            .with_cid(actor.code),
+                    ));
+                }
+            }
+            if entry.dirty {
+                continue;
+            }
+            let key = Address::new_id(id).to_bytes();
+            let actual = self
+                .hamt
+                .get(&key)
+                .with_context(|| format!("failed to lookup actor {}", id))
+                .or_fatal()?;
+            if actual != entry.actor.as_ref() {
+                return Err(ExecutionError::Fatal(anyhow!(
+                    "cached state of actor {} doesn't match the state tree",
+                    id
+                )));
+            }
+        }
+
+        let (state, _) = InitActorState::load(self)?;
+        for (addr, &id) in self.resolve_cache.borrow().iter() {
+            let actual = state.resolve_address(self.store(), addr)?;
+            if actual != Some(id) {
+                return Err(ExecutionError::Fatal(anyhow!(
+                    "cached resolution of {} to actor {} doesn't match the init actor ({:?})",
+                    addr,
+                    id,
+                    actual
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates the state tree with the given root, checking that:
+    ///
+    /// 1. Every block reachable from the root (including the actors' code and state) is in the
+    ///    blockstore, and every DAG-CBOR block is well-formed.
+    /// 2. Every entry of the actors HAMT decodes to an actor keyed by an ID address.
+    /// 3. No actor has a negative balance.
+    ///
+    /// Problems are collected in the returned report rather than failing the validation, so a
+    /// single pass finds all of them. This returns an error only if the state root itself can't
+    /// be loaded. This visits the entire state, so it's intended for tests and for checking the
+    /// result of state migrations, not for use on the hot path.
+    pub fn validate(store: S, root: &Cid) -> Result<ValidationReport> {
+        let tree = Self::new_from_root(store, root)?;
+        let mut report = ValidationReport::default();
+
+        let mut visited = HashSet::new();
+        let mut stack = vec![(*root, *root)];
+        while let Some((cid, parent)) = stack.pop() {
+            if matches!(cid.codec(), FIL_COMMITMENT_UNSEALED | FIL_COMMITMENT_SEALED)
+                || !visited.insert(cid)
+            {
+                continue;
+            }
+            let mut links = Vec::new();
+            let scanned = if cid.hash().code() == IDENTITY_HASH {
+                cid.codec() != DAG_CBOR || scan_for_links(cid.hash().digest(), &mut links).is_ok()
+            } else if cid.codec() == DAG_CBOR {
+                match tree.store().get(&cid).or_fatal()? {
+                    Some(block) => {
+                        report.blocks += 1;
+                        scan_for_links(&block, &mut links).is_ok()
+                    }
+                    None => {
+                        report
+                            .problems
+                            .push(ValidationProblem::MissingBlock { cid, parent });
+                        true
+                    }
+                }
+            } else {
+                if tree.store().has(&cid).or_fatal()? {
+                    report.blocks += 1;
+                } else {
+                    report
+                        .problems
+                        .push(ValidationProblem::MissingBlock { cid, parent });
+                }
+                true
+            };
+            if !scanned {
+                report.problems.push(ValidationProblem::InvalidBlock(cid));
+            }
+            stack.extend(links.into_iter().map(|link| (link, cid)));
+        }
+
+        let res = tree.hamt.for_each(|key, actor| {
+            report.actors += 1;
+            match Address::from_bytes(key)
+                .ok()
+                .and_then(|addr| addr.id().ok())
+            {
+                Some(id) if actor.balance.is_negative() => {
+                    report.problems.push(ValidationProblem::NegativeBalance {
+                        id,
+                        balance: actor.balance.clone(),
+                    })
+                }
+                Some(_) => {}
+                None => report
+                    .problems
+                    .push(ValidationProblem::InvalidKey(key.0.clone())),
+            }
+            Ok(())
+        });
+        if let Err(e) = res {
+            report
+                .problems
+                .push(ValidationProblem::InvalidActors(e.to_string()));
+        }
+        Ok(report)
+    }
+
     /// Consumes this StateTree and returns the Blockstore it owns via the HAMT.
     pub fn into_store(self) -> S {
         self.hamt.into_store()
@@ -371,4 +942,550 @@ where
         })?;
         Ok(())
     }
+
+    /// Like [`StateTree::for_each`], but visits the actors in parallel, calling the function
+    /// concurrently from multiple threads, in no particular order. This is intended for full scans
+    /// of large state trees (e.g., migrations and audits).
+    pub fn par_for_each<F>(&self, f: F) -> anyhow::Result<()>
+    where
+        S: Sync,
+        F: Fn(Address, &ActorState) -> anyhow::Result<()> + Sync,
+    {
+        self.hamt.par_for_each(|k, v| {
+            let addr = Address::from_bytes(&k.0)?;
+            f(addr, v)
+        })?;
+        Ok(())
+    }
+}
+
+/// The differences between two state trees, see [`diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateTreeDiff {
+    /// Actors only in the second state tree.
+    pub added: BTreeMap<ActorID, ActorState>,
+    /// Actors only in the first state tree.
+    pub removed: BTreeMap<ActorID, ActorState>,
+    /// Actors in both state trees with different states, as `(before, after)`.
+    pub modified: BTreeMap<ActorID, (ActorState, ActorState)>,
+}
+
+impl StateTreeDiff {
+    /// Returns true if the state trees have the same actors.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Diffs the state trees with the given roots (e.g., before and after a tipset or a migration).
+/// This diffs the underlying HAMTs structurally, only loading the nodes that differ, so it's
+/// proportional to the number of changed actors, not the size of the state trees.
+pub fn diff<S: Blockstore>(store: &S, before: &Cid, after: &Cid) -> Result<StateTreeDiff> {
+    let before = StateTree::new_from_root(store, before)?;
+    let after = StateTree::new_from_root(store, after)?;
+
+    let id = |key: &[u8]| -> Result<ActorID> {
+        let addr = Address::from_bytes(key).or_fatal()?;
+        addr.id()
+            .map_err(|_| anyhow!("non-id address {} in the state tree", addr))
+            .or_fatal()
+    };
+    let mut diff = StateTreeDiff::default();
+    for change in before.hamt.diff(&after.hamt).or_fatal()? {
+        match change {
+            Change::Added(key, actor) => {
+                diff.added.insert(id(&key)?, actor);
+            }
+            Change::Removed(key, actor) => {
+                diff.removed.insert(id(&key)?, actor);
+            }
+            Change::Modified { key, before, after } => {
+                diff.modified.insert(id(&key)?, (before, after));
+            }
+        }
+    }
+    Ok(diff)
+}
+
+/// The version of a versioned state root (a tuple starting with the version), ignoring the rest
+/// of the root.
+struct StateRootVersion(u64);
+
+impl<'de> Deserialize<'de> for StateRootVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = StateRootVersion;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a versioned state root")
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                let version = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                while seq.next_element::<de::IgnoredAny>()?.is_some() {}
+                Ok(StateRootVersion(version))
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// Reads a state root, returning its state tree version, info, and actors HAMT. The version is
+/// checked before decoding anything else, so unknown versions fail with
+/// [`StateRootError::UnsupportedVersion`] whatever the layout of the rest of the root.
+fn read_state_root<S: Blockstore>(store: &S, root: &Cid) -> Result<(StateTreeVersion, Cid, Cid)> {
+    let block = store
+        .get(root)
+        .or_fatal()?
+        .ok_or_else(|| ExecutionError::Fatal(StateRootError::NotFound(*root).into()))?;
+    let StateRootVersion(version) = fvm_ipld_encoding::from_slice(&block)
+        .map_err(|_| ExecutionError::Fatal(StateRootError::Unversioned(*root).into()))?;
+    if version > StateTreeVersion::V5 as u64 {
+        return Err(ExecutionError::Fatal(
+            StateRootError::UnsupportedVersion(version).into(),
+        ));
+    }
+    let StateRoot {
+        version,
+        actors,
+        info,
+    } = fvm_ipld_encoding::from_slice(&block)
+        .context("failed to decode state root")
+        .or_fatal()?;
+    Ok((version, info, actors))
+}
+
+/// Computes the size of the DAG under `root`, memoizing the sizes of blocks with links in `memo`.
+fn dag_size<S: Blockstore>(store: &S, root: &Cid, memo: &mut HashMap<Cid, u64>) -> Result<u64> {
+    enum Visit {
+        Enter(Cid),
+        /// All links of the block have been visited: sum their sizes.
+        Exit(Cid, u64, usize),
+    }
+
+    // Iterative to bound the stack usage on deep DAGs.
+    let mut stack = vec![Visit::Enter(*root)];
+    let mut sizes: Vec<u64> = Vec::new();
+    while let Some(visit) = stack.pop() {
+        match visit {
+            Visit::Enter(k) => {
+                if let Some(&size) = memo.get(&k) {
+                    sizes.push(size);
+                    continue;
+                }
+                if matches!(k.codec(), FIL_COMMITMENT_UNSEALED | FIL_COMMITMENT_SEALED) {
+                    sizes.push(0);
+                    continue;
+                }
+                let (block, size) = if k.hash().code() == IDENTITY_HASH {
+                    (k.hash().digest().to_vec(), 0)
+                } else {
+                    let block = store
+                        .get(&k)
+                        .or_fatal()?
+                        .with_context(|| format!("missing state block {}", k))
+                        .or_fatal()
+                        .error_context(ErrorContext::default().with_cid(k))?;
+                    let size = block.len() as u64;
+                    (block, size)
+                };
+                let mut links = Vec::new();
+                if k.codec() == DAG_CBOR {
+                    scan_for_links(&block, &mut links)
+                        .or_fatal()
+                        .error_context(ErrorContext::default().with_cid(k))?;
+                }
+                stack.push(Visit::Exit(k, size, links.len()));
+                stack.extend(links.into_iter().map(Visit::Enter));
+            }
+            Visit::Exit(k, size, links) => {
+                let total = size + sizes.drain(sizes.len() - links..).sum::<u64>();
+                if links > 0 {
+                    memo.insert(k, total);
+                }
+                sizes.push(total);
+            }
+        }
+    }
+    Ok(sizes.pop().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use cid::multihash::Code;
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use num_traits::Zero;
+
+    use super::*;
+
+    #[test]
+    fn actor_size() {
+        let mut st = StateTree::new(MemoryBlockstore::default(), StateTreeVersion::V5).unwrap();
+        let block_len = |k: &Cid| st.store().get(k).unwrap().unwrap().len() as u64;
+        let leaf = st.store().put_cbor(&"leaf", Code::Blake2b256).unwrap();
+        let root = st
+            .store()
+            .put_cbor(&(leaf, leaf), Code::Blake2b256)
+            .unwrap();
+        let other = st.store().put_cbor(&"other", Code::Blake2b256).unwrap();
+        let (leaf_len, root_len, other_len) =
+            (block_len(&leaf), block_len(&root), block_len(&other));
+
+        let addr = Address::new_id(100);
+        assert_eq!(st.actor_size(&addr).unwrap(), None);
+        let mut actor = ActorState::new_empty(leaf, None);
+        actor.state = root;
+        st.set_actor(100, actor);
+
+        // The leaf is linked twice, so it's counted twice.
+        let expected = root_len + 2 * leaf_len;
+        assert_eq!(st.actor_size(&addr).unwrap(), Some(expected));
+        assert_eq!(st.actor_sizes(), HashMap::from([(100, expected)]));
+
+        // Tracked sizes are updated on flush.
+        st.mutate_actor(100, |act| {
+            act.state = other;
+            Ok(())
+        })
+        .unwrap();
+        st.flush().unwrap();
+        assert_eq!(st.actor_sizes(), HashMap::from([(100, other_len)]));
+
+        // And deleted actors are no longer tracked.
+        st.delete_actor(100);
+        st.flush().unwrap();
+        assert!(st.actor_sizes().is_empty());
+    }
+
+    #[test]
+    fn revert_to() {
+        let mut st = StateTree::new(MemoryBlockstore::default(), StateTreeVersion::V5).unwrap();
+        let code = st.store().put_cbor(&"code", Code::Blake2b256).unwrap();
+        st.set_actor(100, ActorState::new_empty(code, None));
+        let root = st.flush().unwrap();
+
+        st.delete_actor(100);
+        st.set_actor(101, ActorState::new_empty(code, None));
+        st.flush().unwrap();
+        st.set_actor(102, ActorState::new_empty(code, None));
+
+        st.revert_to(&root).unwrap();
+        assert!(st.get_actor(100).unwrap().is_some());
+        assert!(st.get_actor(101).unwrap().is_none());
+        assert!(st.get_actor(102).unwrap().is_none());
+        assert_eq!(st.flush().unwrap(), root);
+
+        st.begin_transaction();
+        assert!(st.revert_to(&root).is_err());
+    }
+
+    #[test]
+    fn diff() {
+        let store = MemoryBlockstore::default();
+        let mut st = StateTree::new(&store, StateTreeVersion::V5).unwrap();
+        let code = st.store().put_cbor(&"code", Code::Blake2b256).unwrap();
+        let actor = |balance| ActorState {
+            balance: TokenAmount::from_atto(balance),
+            ..ActorState::new_empty(code, None)
+        };
+        for id in 100..200 {
+            st.set_actor(id, actor(id));
+        }
+        let before = st.flush().unwrap();
+        assert!(super::diff(&store, &before, &before).unwrap().is_empty());
+
+        st.delete_actor(100);
+        st.set_actor(101, actor(0));
+        st.set_actor(200, actor(200));
+        let after = st.flush().unwrap();
+
+        let diff = super::diff(&store, &before, &after).unwrap();
+        assert_eq!(diff.added, BTreeMap::from([(200, actor(200))]));
+        assert_eq!(diff.removed, BTreeMap::from([(100, actor(100))]));
+        assert_eq!(
+            diff.modified,
+            BTreeMap::from([(101, (actor(101), actor(0)))])
+        );
+
+        let reversed = super::diff(&store, &after, &before).unwrap();
+        assert_eq!(reversed.added, diff.removed);
+        assert_eq!(reversed.removed, diff.added);
+    }
+
+    #[test]
+    fn par_for_each() {
+        let store = MemoryBlockstore::default();
+        let mut st = StateTree::new(&store, StateTreeVersion::V5).unwrap();
+        let code = st.store().put_cbor(&"code", Code::Blake2b256).unwrap();
+        for id in 100..1100 {
+            st.set_actor(id, ActorState::new_empty(code, None));
+        }
+        st.flush().unwrap();
+        st.delete_actor(100);
+        st.set_actor(1100, ActorState::new_empty(code, None));
+        st.flush().unwrap();
+
+        let visited = Mutex::new(BTreeSet::new());
+        st.par_for_each(|addr, _| {
+            assert!(visited.lock().unwrap().insert(addr.id().unwrap()));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(visited.into_inner().unwrap(), (101..1101).collect());
+
+        assert!(st
+            .par_for_each(|addr, _| match addr.id().unwrap() {
+                500 => Err(anyhow!("stop")),
+                _ => Ok(()),
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn actor_cache_limit() {
+        let mut st = StateTree::new(MemoryBlockstore::default(), StateTreeVersion::V5).unwrap();
+        let code = st.store().put_cbor(&"code", Code::Blake2b256).unwrap();
+        st.set_actor_cache_limit(Some(2));
+        for id in 100..105 {
+            st.set_actor(id, ActorState::new_empty(code, None));
+        }
+        // Modified actors aren't evicted.
+        st.get_actor(100).unwrap();
+        assert_eq!(st.actor_cache_stats().hits, 1);
+        st.flush().unwrap();
+
+        let hit = |st: &StateTree<_>, id| {
+            let before = st.actor_cache_stats();
+            assert!(st.get_actor(id).unwrap().is_some());
+            st.actor_cache_stats().hits > before.hits
+        };
+        // 100 and 104 were the most recently used.
+        assert!(hit(&st, 100));
+        assert!(hit(&st, 104));
+        assert!(!hit(&st, 101));
+
+        // Evictions are deferred to the end of the top-level transaction.
+        st.begin_transaction();
+        assert!(!hit(&st, 102));
+        assert!(hit(&st, 100));
+        st.end_transaction(false).unwrap();
+        assert!(hit(&st, 102));
+        assert!(!hit(&st, 104));
+
+        // Writes replace cached actors.
+        let mut actor = ActorState::new_empty(code, None);
+        actor.sequence = 1;
+        st.set_actor(100, actor.clone());
+        assert_eq!(st.get_actor(100).unwrap(), Some(actor));
+    }
+
+    #[test]
+    fn state_root_versions() {
+        let store = MemoryBlockstore::default();
+        let error = |root: &Cid| match StateTree::new_from_root(&store, root) {
+            Err(ExecutionError::Fatal(e)) => e.downcast_ref::<StateRootError>().cloned(),
+            _ => None,
+        };
+
+        let root = StateTree::new(&store, StateTreeVersion::V5)
+            .unwrap()
+            .flush()
+            .unwrap();
+        assert_eq!(
+            StateTree::new_from_root(&store, &root).unwrap().version,
+            StateTreeVersion::V5
+        );
+
+        let missing = Cid::default();
+        assert_eq!(error(&missing), Some(StateRootError::NotFound(missing)));
+
+        // A future version with a different layout.
+        let future = store.put_cbor(&(6u64, "actors"), Code::Blake2b256).unwrap();
+        assert_eq!(error(&future), Some(StateRootError::UnsupportedVersion(6)));
+        let old = store
+            .put_cbor(&(4u64, missing, missing), Code::Blake2b256)
+            .unwrap();
+        assert_eq!(error(&old), Some(StateRootError::UnsupportedVersion(4)));
+
+        let unversioned = store.put_cbor(&"hamt", Code::Blake2b256).unwrap();
+        assert_eq!(
+            error(&unversioned),
+            Some(StateRootError::Unversioned(unversioned))
+        );
+    }
+
+    #[test]
+    fn named_layers() {
+        let mut st = StateTree::new(MemoryBlockstore::default(), StateTreeVersion::V5).unwrap();
+        let code = st.store().put_cbor(&"code", Code::Blake2b256).unwrap();
+        let actor = ActorState::new_empty(code, None);
+        st.set_actor(100, actor.clone());
+
+        st.begin_layer("outer");
+        st.set_actor(101, actor.clone());
+        st.get_actor(102).unwrap();
+        st.begin_transaction();
+        st.begin_layer("inner");
+        st.delete_actor(100);
+        assert_eq!(
+            st.layers().collect::<Vec<_>>(),
+            [Some("outer"), None, Some("inner")]
+        );
+        assert_eq!(
+            st.layer_mutations("inner").unwrap(),
+            BTreeMap::from([(100, None)])
+        );
+        assert_eq!(
+            st.layer_mutations("outer").unwrap(),
+            BTreeMap::from([(100, None), (101, Some(actor.clone()))])
+        );
+        assert_eq!(
+            st.pending_mutations(),
+            BTreeMap::from([(100, None), (101, Some(actor.clone()))])
+        );
+
+        st.commit_layer("inner").unwrap();
+        assert_eq!(st.layers().collect::<Vec<_>>(), [Some("outer"), None]);
+        assert!(st.get_actor(100).unwrap().is_none());
+
+        // Layers can't be ended while transactions begun after them are still open.
+        assert!(st.revert_layer("outer").is_err());
+        assert!(st.commit_layer("outer").is_err());
+        st.end_transaction(false).unwrap();
+        assert!(st.get_actor(100).unwrap().is_none());
+
+        // Reverting a layer reverts the named layers begun after it.
+        st.begin_layer("inner");
+        st.revert_layer("outer").unwrap();
+        assert!(!st.in_transaction());
+        assert_eq!(st.pending_mutations(), BTreeMap::from([(100, Some(actor))]));
+        assert!(st.revert_layer("outer").is_err());
+        assert!(st.end_transaction(false).is_err());
+    }
+
+    #[test]
+    fn mutate_actor_state() {
+        let mut st = StateTree::new(MemoryBlockstore::default(), StateTreeVersion::V5).unwrap();
+        let code = st.store().put_cbor(&"code", Code::Blake2b256).unwrap();
+        let state = st.store().put_cbor(&1u64, Code::Blake2b256).unwrap();
+        st.set_actor(
+            100,
+            ActorState::new(code, state, TokenAmount::zero(), 0, None),
+        );
+
+        let old = st
+            .mutate_actor_state(100, |count: &mut u64, _| {
+                *count += 1;
+                Ok(*count - 1)
+            })
+            .unwrap();
+        assert_eq!(old, 1);
+        let (count, actor) = st.get_actor_state::<u64>(100).unwrap().unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(st.store().get_cbor::<u64>(&actor.state).unwrap(), Some(2));
+
+        // Failed mutations change nothing.
+        assert!(st
+            .mutate_actor_state(100, |count: &mut u64, _| {
+                *count += 1;
+                Err::<(), _>(anyhow!("failed")).or_fatal()
+            })
+            .is_err());
+        assert_eq!(st.get_actor(100).unwrap(), Some(actor));
+
+        // Missing actors and undecodable states are fatal errors.
+        assert!(st.get_actor_state::<u64>(101).unwrap().is_none());
+        assert!(st.mutate_actor_state(101, |_: &mut u64, _| Ok(())).is_err());
+        assert!(st.get_actor_state::<String>(100).is_err());
+        assert_eq!(
+            st.mutate_actor(100, |actor| {
+                actor.sequence += 1;
+                Ok(actor.sequence)
+            })
+            .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn track_accesses() {
+        let mut st = StateTree::new(MemoryBlockstore::default(), StateTreeVersion::V5).unwrap();
+        let code = st.store().put_cbor(&"code", Code::Blake2b256).unwrap();
+        st.set_actor(100, ActorState::new_empty(code, None));
+        assert_eq!(st.take_accesses(), None);
+
+        st.track_accesses();
+        st.mutate_actor(100, |_| Ok(())).unwrap();
+        st.get_actor(101).unwrap();
+        st.delete_actor(102);
+        st.untracked(|st| st.set_actor(103, ActorState::new_empty(code, None)));
+        let accesses = st.take_accesses().unwrap();
+        assert_eq!(accesses.reads, BTreeSet::from([100, 101]));
+        assert_eq!(accesses.writes, BTreeSet::from([100, 102]));
+
+        assert!(accesses.conflicts_with(&BTreeSet::from([101])));
+        assert!(accesses.conflicts_with(&BTreeSet::from([102])));
+        assert!(!accesses.conflicts_with(&BTreeSet::from([103])));
+
+        // Tracking stopped.
+        st.get_actor(100).unwrap();
+        assert_eq!(st.take_accesses(), None);
+    }
+
+    #[test]
+    fn validate() {
+        use cid::multihash::MultihashDigest;
+
+        let store = MemoryBlockstore::default();
+        let code = store.put_cbor(&"code", Code::Blake2b256).unwrap();
+        let state = store.put_cbor(&(code,), Code::Blake2b256).unwrap();
+        let actor = |state, balance| ActorState::new(code, state, balance, 0, None);
+
+        let mut tree = StateTree::new(&store, StateTreeVersion::V5).unwrap();
+        tree.set_actor(100, actor(state, TokenAmount::from_atto(1)));
+        let root = tree.flush().unwrap();
+        let report = StateTree::validate(&store, &root).unwrap();
+        assert!(report.is_valid(), "{:?}", report.problems);
+        assert_eq!(report.actors, 1);
+        // The state root, info, actors HAMT, state, and code.
+        assert_eq!(report.blocks, 5);
+
+        let missing = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(b"missing"));
+        let mut tree = StateTree::new_from_root(&store, &root).unwrap();
+        tree.set_actor(101, actor(missing, TokenAmount::from_atto(-1)));
+        let key = Address::new_secp256k1(&[1; 65]).unwrap().to_bytes();
+        tree.hamt
+            .set(key.clone().into(), actor(state, TokenAmount::zero()))
+            .unwrap();
+        let root = tree.flush().unwrap();
+        let report = StateTree::validate(&store, &root).unwrap();
+        assert_eq!(report.actors, 3);
+        let hamt = tree.hamt.flush().unwrap();
+        assert!(report.problems.contains(&ValidationProblem::MissingBlock {
+            cid: missing,
+            parent: hamt,
+        }));
+        assert!(report
+            .problems
+            .contains(&ValidationProblem::NegativeBalance {
+                id: 101,
+                balance: TokenAmount::from_atto(-1),
+            }));
+        assert!(report
+            .problems
+            .contains(&ValidationProblem::InvalidKey(key)));
+        assert_eq!(report.problems.len(), 3);
+
+        assert!(StateTree::validate(&store, &missing).is_err());
+    }
 }
@@ -0,0 +1,84 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use cid::Cid;
+
+/// A set of syscalls, identified by their module and function names (e.g., `actor::create_actor`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyscallSet(HashMap<String, HashSet<String>>);
+
+impl SyscallSet {
+    /// Create an empty syscall set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the syscall `module::name` to the set.
+    pub fn allow(&mut self, module: impl Into<String>, name: impl Into<String>) -> &mut Self {
+        self.0.entry(module.into()).or_default().insert(name.into());
+        self
+    }
+
+    /// Returns true if the syscall `module::name` is in the set.
+    pub fn contains(&self, module: &str, name: &str) -> bool {
+        self.0
+            .get(module)
+            .map_or(false, |names| names.contains(name))
+    }
+}
+
+impl<M, N> FromIterator<(M, N)> for SyscallSet
+where
+    M: Into<String>,
+    N: Into<String>,
+{
+    fn from_iter<T: IntoIterator<Item = (M, N)>>(iter: T) -> Self {
+        let mut set = Self::new();
+        for (module, name) in iter {
+            set.allow(module, name);
+        }
+        set
+    }
+}
+
+/// Restricts the syscalls actors may call, based on their code CIDs.
+///
+/// Restrictions are enforced when the syscall is invoked: calling a syscall outside of the
+/// permitted set fails with [`ErrorNumber::Forbidden`](fvm_shared::error::ErrorNumber::Forbidden)
+/// (after charging the usual syscall gas). Note that restricted actors likely need to be permitted
+/// to call at least `vm::exit` and `vm::message_context`.
+///
+/// By default, no actor is restricted.
+#[derive(Debug, Clone, Default)]
+pub struct SyscallAllowlist {
+    by_code: HashMap<Cid, Arc<SyscallSet>>,
+    non_builtin: Option<Arc<SyscallSet>>,
+}
+
+impl SyscallAllowlist {
+    /// Restrict actors with the given code CID to the given syscalls. This takes precedence over
+    /// all other restrictions, and applies to builtin actors as well.
+    pub fn restrict_code(&mut self, code: Cid, syscalls: SyscallSet) -> &mut Self {
+        self.by_code.insert(code, Arc::new(syscalls));
+        self
+    }
+
+    /// Restrict all non-builtin actors without an explicit [`SyscallAllowlist::restrict_code`]
+    /// entry to the given syscalls. Builtin actors retain access to all syscalls.
+    pub fn restrict_non_builtin(&mut self, syscalls: SyscallSet) -> &mut Self {
+        self.non_builtin = Some(Arc::new(syscalls));
+        self
+    }
+
+    /// Returns the syscalls permitted to an actor with the given code, or `None` if the actor is
+    /// unrestricted.
+    pub fn permitted_syscalls(&self, code: &Cid, builtin: bool) -> Option<Arc<SyscallSet>> {
+        match self.by_code.get(code) {
+            Some(syscalls) => Some(syscalls.clone()),
+            None if builtin => None,
+            None => self.non_builtin.clone(),
+        }
+    }
+}
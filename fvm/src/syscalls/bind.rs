@@ -1,22 +1,32 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::fmt::Debug;
 use std::mem;
 
 use fvm_shared::error::ErrorNumber;
 use fvm_shared::sys::SyscallSafe;
+use fvm_shared::version::NetworkVersion;
 use wasmtime::{Caller, Linker, WasmTy};
 
 use super::context::Memory;
 use super::error::Abort;
 use super::{charge_for_exec, update_gas_available, Context, InvocationData};
 use crate::call_manager::backtrace;
+use crate::gas::GasTimer;
 use crate::kernel::{self, ExecutionError, Kernel, SyscallError};
+use crate::machine::{Machine, MetricEvent};
 
 /// Binds syscalls to a linker, converting the returned error according to the syscall convention:
 ///
 /// 1. If the error is a syscall error, it's returned as the first return value.
 /// 2. If the error is a fatal error, a Trap is returned.
-pub(super) trait BindSyscall<Args, Ret, Func> {
+///
+/// This trait is implemented for [`Linker`]s over [`InvocationData`], and can be used by embedders
+/// to register additional host functions from their own [`SyscallHandler`] implementations. Gas
+/// for wasm execution and for the syscall itself is charged before the function is invoked.
+///
+/// [`SyscallHandler`]: crate::kernel::SyscallHandler
+pub trait BindSyscall<Args, Ret, Func> {
     /// Bind a syscall to the linker.
     ///
     /// 1. The return type will be automatically adjusted to return `Result<u32, Trap>` where
@@ -63,6 +73,7 @@ impl<T> From<ExecutionError> for ControlFlow<T> {
             ExecutionError::Syscall(err) => ControlFlow::Error(err),
             ExecutionError::Fatal(err) => ControlFlow::Abort(Abort::Fatal(err)),
             ExecutionError::OutOfGas => ControlFlow::Abort(Abort::OutOfGas),
+            ExecutionError::Timeout => ControlFlow::Abort(Abort::Timeout),
         }
     }
 }
@@ -114,6 +125,7 @@ where
                 ExecutionError::Syscall(err) => ControlFlow::Error(err),
                 ExecutionError::OutOfGas => ControlFlow::Abort(Abort::OutOfGas),
                 ExecutionError::Fatal(err) => ControlFlow::Abort(Abort::Fatal(err)),
+                ExecutionError::Timeout => ControlFlow::Abort(Abort::Timeout),
             },
         }
     }
@@ -127,6 +139,61 @@ fn memory_and_data<'a, K: Kernel>(
     (Memory::new(mem), data)
 }
 
+/// The first network version on which actors may receive [`ErrorNumber::Reentrant`] and
+/// [`ErrorNumber::NotSupported`]. Before, both are reported as [`ErrorNumber::Forbidden`], which
+/// deployed actors already handle.
+const NEW_ERROR_NUMBERS_VERSION: NetworkVersion = NetworkVersion::V22;
+
+/// Returns the error number reported to actors for the given error on the given network version.
+fn reported_error_number(code: ErrorNumber, network_version: NetworkVersion) -> u32 {
+    let code = match code {
+        ErrorNumber::Reentrant | ErrorNumber::NotSupported
+            if network_version < NEW_ERROR_NUMBERS_VERSION =>
+        {
+            ErrorNumber::Forbidden
+        }
+        code => code,
+    };
+    code as u32
+}
+
+/// Checks whether the syscall `module::name` is enabled on this machine, and whether the currently
+/// executing actor is permitted to call it, recording the error and returning the error number if
+/// it can't be called.
+fn check_permitted<K: Kernel>(
+    data: &mut InvocationData<K>,
+    module: &'static str,
+    name: &'static str,
+    args: impl FnOnce() -> Vec<String>,
+) -> Option<ErrorNumber> {
+    let (code, message) = if data
+        .kernel
+        .machine()
+        .context()
+        .disabled_syscalls
+        .contains(module, name)
+    {
+        (ErrorNumber::NotSupported, "not supported")
+    } else {
+        match &data.permitted_syscalls {
+            Some(permitted) if !permitted.contains(module, name) => {
+                (ErrorNumber::Forbidden, "not permitted")
+            }
+            _ => return None,
+        }
+    };
+    log::trace!("syscall {}::{}: {}", module, name, message);
+    data.last_error = Some(
+        backtrace::Cause::from_syscall(
+            module,
+            name,
+            SyscallError(format!("syscall {module}::{name} {message}"), code),
+        )
+        .with_args(args()),
+    );
+    Some(code)
+}
+
 macro_rules! charge_syscall_gas {
     ($kernel:expr) => {
         let charge = $kernel.price_list().on_syscall();
@@ -145,7 +212,7 @@ macro_rules! impl_bind_syscalls {
             K: Kernel,
             Func: Fn(Context<'_, K> $(, $t)*) -> Ret + Send + Sync + 'static,
             Ret: IntoControlFlow,
-           $($t: WasmTy+SyscallSafe,)*
+           $($t: WasmTy+SyscallSafe+Debug,)*
         {
             fn bind(
                 &mut self,
@@ -157,9 +224,22 @@ macro_rules! impl_bind_syscalls {
                     // If we're returning a zero-sized "value", we return no value therefore and expect no out pointer.
                     self.func_wrap(module, name, move |mut caller: Caller<'_, InvocationData<K>> $(, $t: $t)*| {
                         charge_for_exec(&mut caller)?;
+                        let start = GasTimer::start();
+
+                        // Snapshot the arguments for the backtrace, in case the syscall fails.
+                        let args = move || vec![$(format!("{:?}", $t)),*];
 
                         let (mut memory, mut data) = memory_and_data(&mut caller);
                         charge_syscall_gas!(data.kernel);
+                        if data.kernel.machine().collects_metrics() {
+                            data.kernel.machine().record_metric(MetricEvent::Syscall { module, name });
+                        }
+
+                        if let Some(code) = check_permitted(data, module, name, args) {
+                            let nv = data.kernel.machine().context().network_version;
+                            update_gas_available(&mut caller)?;
+                            return Ok(reported_error_number(code, nv));
+                        }
 
                         let ctx = Context{kernel: &mut data.kernel, memory: &mut memory};
                         let out = syscall(ctx $(, $t)*).into_control_flow();
@@ -173,11 +253,12 @@ macro_rules! impl_bind_syscalls {
                             ControlFlow::Error(err) => {
                                 let code = err.1;
                                 log::trace!("syscall {}::{}: fail ({})", module, name, code as u32);
-                                data.last_error = Some(backtrace::Cause::from_syscall(module, name, err));
-                                Ok(code as u32)
+                                data.last_error = Some(backtrace::Cause::from_syscall(module, name, err).with_args(args()));
+                                Ok(reported_error_number(code, data.kernel.machine().context().network_version))
                             },
                             ControlFlow::Abort(abort) => Err(abort.into()),
                         };
+                        data.kernel.trace_syscall(module, name, start.elapsed());
 
                         update_gas_available(&mut caller)?;
 
@@ -187,18 +268,31 @@ macro_rules! impl_bind_syscalls {
                     // If we're returning an actual value, we need to write it back into the wasm module's memory.
                     self.func_wrap(module, name, move |mut caller: Caller<'_, InvocationData<K>>, ret: u32 $(, $t: $t)*| {
                         charge_for_exec(&mut caller)?;
+                        let start = GasTimer::start();
+
+                        // Snapshot the arguments for the backtrace, in case the syscall fails.
+                        let args = move || vec![$(format!("{:?}", $t)),*];
 
                         let (mut memory, mut data) = memory_and_data(&mut caller);
                         charge_syscall_gas!(data.kernel);
+                        if data.kernel.machine().collects_metrics() {
+                            data.kernel.machine().record_metric(MetricEvent::Syscall { module, name });
+                        }
 
                         // We need to check to make sure we can store the return value _before_ we do anything.
                         if (ret as u64) > (memory.len() as u64)
                             || memory.len() - (ret as usize) < mem::size_of::<Ret::Value>() {
                             let code = ErrorNumber::IllegalArgument;
-                            data.last_error = Some(backtrace::Cause::from_syscall(module, name, SyscallError(format!("no space for return value"), code)));
+                            data.last_error = Some(backtrace::Cause::from_syscall(module, name, SyscallError(format!("no space for return value"), code)).with_args(args()));
                             return Ok(code as u32);
                         }
 
+                        if let Some(code) = check_permitted(data, module, name, args) {
+                            let nv = data.kernel.machine().context().network_version;
+                            update_gas_available(&mut caller)?;
+                            return Ok(reported_error_number(code, nv));
+                        }
+
                         let ctx = Context{kernel: &mut data.kernel, memory: &mut memory};
                         let result = match syscall(ctx $(, $t)*).into_control_flow() {
                             ControlFlow::Return(value) => {
@@ -214,11 +308,12 @@ macro_rules! impl_bind_syscalls {
                             ControlFlow::Error(err) => {
                                 let code = err.1;
                                 log::trace!("syscall {}::{}: fail ({})", module, name, code as u32);
-                                data.last_error = Some(backtrace::Cause::from_syscall(module, name, err));
-                                Ok(code as u32)
+                                data.last_error = Some(backtrace::Cause::from_syscall(module, name, err).with_args(args()));
+                                Ok(reported_error_number(code, data.kernel.machine().context().network_version))
                             },
                             ControlFlow::Abort(abort) => Err(abort.into()),
                         };
+                        data.kernel.trace_syscall(module, name, start.elapsed());
 
                         update_gas_available(&mut caller)?;
 
@@ -3,14 +3,15 @@
 use std::cmp;
 
 use anyhow::Context as _;
+use fvm_shared::crypto::merkle::MerkleProof;
 use fvm_shared::crypto::signature::{
-    SignatureType, SECP_PUB_LEN, SECP_SIG_LEN, SECP_SIG_MESSAGE_HASH_SIZE,
+    SignatureBatchEntry, SignatureType, SECP_PUB_LEN, SECP_SIG_LEN, SECP_SIG_MESSAGE_HASH_SIZE,
 };
 use num_traits::FromPrimitive;
 
 use super::Context;
 use crate::kernel::{ClassifyResult, Result};
-use crate::Kernel;
+use crate::{syscall_error, Kernel};
 
 /// Verifies that a signature is valid for an address and plaintext.
 ///
@@ -88,3 +89,62 @@ pub fn hash(
     digest_out[..length].copy_from_slice(&digest.digest()[..length]);
     Ok(length as u32)
 }
+
+/// Verifies a Merkle proof over a (possibly foreign) data structure.
+///
+/// The return i32 indicates the status code of the verification:
+///  - 0: verification ok.
+///  - -1: verification failed.
+pub fn verify_merkle_proof(
+    context: Context<'_, impl Kernel>,
+    proof_off: u32,
+    proof_len: u32,
+) -> Result<i32> {
+    // Charge for decoding the proof before decoding it.
+    let charge = context
+        .kernel
+        .price_list()
+        .on_decode_merkle_proof(proof_len as usize);
+    let t = context
+        .kernel
+        .charge_gas(&charge.name, charge.compute_gas)?;
+    let proof = t.record(
+        context
+            .memory
+            .read_cbor::<MerkleProof>(proof_off, proof_len),
+    )?;
+    context
+        .kernel
+        .verify_merkle_proof(&proof)
+        .map(|v| if v { 0 } else { -1 })
+}
+
+/// Verifies a batch of signatures, writing one byte per entry (1 if the signature is valid, 0
+/// otherwise) into the results buffer.
+///
+/// Returns the number of valid signatures.
+pub fn verify_signatures_batch(
+    context: Context<'_, impl Kernel>,
+    entries_off: u32,
+    entries_len: u32,
+    results_off: u32,
+    results_len: u32,
+) -> Result<u32> {
+    // Check the results bounds first so we don't do any work if they're incorrect.
+    context.memory.check_bounds(results_off, results_len)?;
+
+    let entries = context
+        .memory
+        .read_cbor::<Vec<SignatureBatchEntry>>(entries_off, entries_len)?;
+    if entries.len() > results_len as usize {
+        return Err(syscall_error!(BufferTooSmall; "results buffer is too small for {} entries", entries.len()).into());
+    }
+
+    let results = context.kernel.verify_signatures_batch(&entries)?;
+
+    let results_out = context.memory.try_slice_mut(results_off, results_len)?;
+    for (out, valid) in results_out.iter_mut().zip(&results) {
+        *out = *valid as u8;
+    }
+    Ok(results.iter().filter(|valid| **valid).count() as u32)
+}
@@ -20,6 +20,9 @@ pub enum Abort {
     /// The system failed with a fatal error.
     #[error("fatal error: {0}")]
     Fatal(anyhow::Error),
+    /// The message ran for longer than the engine's execution timeout.
+    #[error("execution timed out")]
+    Timeout,
 }
 
 impl Abort {
@@ -37,6 +40,7 @@ impl Abort {
             ),
             ExecutionError::OutOfGas => Abort::OutOfGas,
             ExecutionError::Fatal(err) => Abort::Fatal(err),
+            ExecutionError::Timeout => Abort::Timeout,
         }
     }
 
@@ -45,6 +49,7 @@ impl Abort {
         match e {
             ExecutionError::OutOfGas => Abort::OutOfGas,
             ExecutionError::Fatal(e) => Abort::Fatal(e),
+            ExecutionError::Timeout => Abort::Timeout,
             ExecutionError::Syscall(e) => Abort::Fatal(anyhow!("unexpected syscall error: {}", e)),
         }
     }
@@ -75,6 +80,11 @@ impl From<anyhow::Error> for Abort {
                     trap.to_string(),
                     NO_DATA_BLOCK_ID,
                 ),
+                // Fuel is only consumed when enforcing an instruction budget, which is treated like
+                // running out of gas, or when metering gas with fuel.
+                Trap::OutOfFuel => Abort::OutOfGas,
+                // The epoch deadline is only set when enforcing an execution timeout.
+                Trap::Interrupt => Abort::Timeout,
                 _ => Abort::Fatal(anyhow!("unexpected wasmtime trap: {}", trap)),
             };
         };
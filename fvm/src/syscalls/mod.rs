@@ -1,20 +1,27 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::Arc;
+
 use anyhow::{anyhow, Context as _};
+use fvm_shared::version::NetworkVersion;
 use num_traits::Zero;
-use wasmtime::{AsContextMut, ExternType, Global, Linker, Memory, Module, Val};
+use wasmtime::{AsContextMut, ExternType, Global, Linker, Module, Val};
 
-use crate::call_manager::{backtrace, CallManager};
+use crate::call_manager::backtrace;
 use crate::gas::{Gas, GasInstant, GasTimer};
-use crate::kernel::filecoin::DefaultFilecoinKernel;
+use crate::kernel::filecoin::{DefaultFilecoinKernel, FilecoinKernel};
 use crate::kernel::{ExecutionError, SyscallHandler};
 
 use crate::machine::limiter::MemoryLimiter;
+use crate::machine::Machine;
 use crate::{DefaultKernel, Kernel};
 
 pub(crate) mod error;
 
 mod actor;
+mod allowlist;
 mod bind;
 mod context;
 mod crypto;
@@ -27,9 +34,12 @@ mod network;
 mod rand;
 mod send;
 mod sself;
+mod util;
 mod vm;
 
-pub(self) use context::Context;
+pub use allowlist::{SyscallAllowlist, SyscallSet};
+pub use bind::{BindSyscall, IntoControlFlow};
+pub use context::{Context, Memory};
 
 /// Invocation data attached to a wasm "store" and available to the syscall binding.
 pub struct InvocationData<K> {
@@ -57,7 +67,21 @@ pub struct InvocationData<K> {
     pub last_charge_time: GasInstant,
 
     /// The invocation's imported "memory".
-    pub memory: Memory,
+    pub memory: wasmtime::Memory,
+
+    /// The syscalls the actor is permitted to call, or `None` if the actor is unrestricted.
+    pub permitted_syscalls: Option<Arc<SyscallSet>>,
+
+    /// The Wasm instructions (in wasmtime fuel) remaining in the message's instruction budget,
+    /// shared by all invocations on the call stack, or `None` if no budget is enforced.
+    pub instructions_available: Option<Rc<Cell<u64>>>,
+
+    /// The gas cost of one unit of fuel, if gas is metered with fuel (see
+    /// [`GasMetering::Fuel`](crate::engine::GasMetering::Fuel)) instead of instrumentation.
+    pub fuel_price: Option<Gas>,
+
+    /// The fuel consumed by this invocation the last time we updated `instructions_available`.
+    pub last_fuel_consumed: u64,
 }
 
 /// Updates the global available gas in the Wasm module after a syscall, to account for any
@@ -88,60 +112,128 @@ pub fn update_gas_available(
     data.last_memory_bytes = data.kernel.limiter_mut().memory_used();
     data.last_charge_time = GasTimer::start();
 
-    Ok(())
+    update_fuel_available(&mut ctx)
 }
 
-/// Updates the FVM-side gas tracker with newly accrued execution gas charges.
-pub fn charge_for_exec<K: Kernel>(
-    ctx: &mut impl AsContextMut<Data = InvocationData<K>>,
+/// Sets the Wasm fuel to the instructions remaining in the message's instruction budget (if any)
+/// or, when metering gas with fuel, to the fuel the available gas pays for (whichever is lower), to
+/// account for instructions executed and gas charged by other invocations (e.g., nested sends).
+pub fn update_fuel_available(
+    ctx: &mut impl AsContextMut<Data = InvocationData<impl Kernel>>,
 ) -> Result<(), Abort> {
     let mut ctx = ctx.as_context_mut();
-    let global = ctx.data_mut().avail_gas_global;
+    let data = ctx.data();
+    let budget = data.instructions_available.as_ref().map(|a| a.get());
+    let paid = data
+        .fuel_price
+        .map(|price| data.last_gas_available.as_milligas() / price.as_milligas());
+    let Some(avail) = budget.into_iter().chain(paid).min() else {
+        return Ok(());
+    };
 
-    // Get the remaining milligas. This will go _negative_ if we run out.
-    let milligas_available_wasm = global
-        .get(&mut ctx)
-        .i64()
-        .context("failed to get wasm gas")
-        .map_err(Abort::Fatal)?;
+    // Consuming zero fuel is the only way to query the remaining fuel.
+    let remaining = ctx
+        .consume_fuel(0)
+        .map_err(|e| Abort::Fatal(anyhow!("failed to get remaining fuel: {}", e)))?;
+    if avail > remaining {
+        ctx.add_fuel(avail - remaining)
+            .map_err(|e| Abort::Fatal(anyhow!("failed to add fuel: {}", e)))?;
+    } else {
+        ctx.consume_fuel(remaining - avail)
+            .map_err(|e| Abort::Fatal(anyhow!("failed to consume fuel: {}", e)))?;
+    }
 
+    ctx.data_mut().last_fuel_consumed = ctx.fuel_consumed().unwrap_or_default();
+    Ok(())
+}
+
+/// Deducts the fuel consumed since the last update from the message's instruction budget (if any),
+/// returning the fuel consumed.
+fn charge_for_fuel<K: Kernel>(ctx: &mut impl AsContextMut<Data = InvocationData<K>>) -> u64 {
+    let mut ctx = ctx.as_context_mut();
+    let consumed = ctx.fuel_consumed().unwrap_or_default();
     let data = ctx.data_mut();
+    let delta = consumed.saturating_sub(data.last_fuel_consumed);
+    if let Some(avail) = &data.instructions_available {
+        avail.set(avail.get().saturating_sub(delta));
+    }
+    data.last_fuel_consumed = consumed;
+    delta
+}
 
-    // abs_diff(0) is the simplest way to get the absolute value of an i64 as a u64 without
-    // overflows.
-    let milligas_available_wasm_abs = milligas_available_wasm.abs_diff(0);
+/// Updates the FVM-side gas tracker with newly accrued execution gas charges.
+pub fn charge_for_exec<K: Kernel>(
+    ctx: &mut impl AsContextMut<Data = InvocationData<K>>,
+) -> Result<(), Abort> {
+    let fuel_consumed = charge_for_fuel(ctx);
 
-    // Get the exec gas to charge, taking negatives into account.
-    let mut exec_gas_charge = if milligas_available_wasm < 0 {
-        // If the gas remaining is negative, we charge for all remaining gas, plus `-remaining_gas`.
-        // That way we actually run out.
-        data.last_gas_available + Gas::from_milligas(milligas_available_wasm_abs)
-    } else {
-        // If it's non-negative, we charge for up-to all remaining gas. This subtraction saturates
-        // at zero.
-        data.last_gas_available - Gas::from_milligas(milligas_available_wasm_abs)
+    let mut ctx = ctx.as_context_mut();
+    let fuel_price = ctx.data().fuel_price;
+
+    let mut exec_gas_charge = match fuel_price {
+        // When metering gas with fuel, wasmtime only traps once the fuel consumed exceeds the fuel
+        // the available gas pays for, so this charge makes us run out.
+        Some(price) => price * fuel_consumed,
+        None => {
+            let global = ctx.data_mut().avail_gas_global;
+
+            // Get the remaining milligas. This will go _negative_ if we run out.
+            let milligas_available_wasm = global
+                .get(&mut ctx)
+                .i64()
+                .context("failed to get wasm gas")
+                .map_err(Abort::Fatal)?;
+
+            let data = ctx.data_mut();
+
+            // abs_diff(0) is the simplest way to get the absolute value of an i64 as a u64
+            // without overflows.
+            let milligas_available_wasm_abs = milligas_available_wasm.abs_diff(0);
+
+            // Get the exec gas to charge, taking negatives into account.
+            if milligas_available_wasm < 0 {
+                // If the gas remaining is negative, we charge for all remaining gas, plus
+                // `-remaining_gas`. That way we actually run out.
+                data.last_gas_available + Gas::from_milligas(milligas_available_wasm_abs)
+            } else {
+                // If it's non-negative, we charge for up-to all remaining gas. This subtraction
+                // saturates at zero.
+                data.last_gas_available - Gas::from_milligas(milligas_available_wasm_abs)
+            }
+        }
     };
 
+    let data = ctx.data_mut();
+
     // Now we separate the amount of gas charged for memory; this is only makes a difference in
     // tracing. `exec_gas_charge` is the number we want to charge. If, for some reason,
     // `memory_gas_charge` exceeds `exec_gas_charge`, we just set `memory_gas_charge` to
     // `exec_gas_charge`, and set `exec_gas_charge` to zero.
+    //
+    // When metering gas with fuel, growing the memory isn't charged by the module, so we charge
+    // for it on top of the execution gas instead.
+    //
+    // The memory limits (total and per-instance) don't need to be checked here: the memory limiter
+    // is consulted before each grow, and refused grows fail without changing `memory_used`, so
+    // we only ever charge for memory that was actually granted.
     let memory_bytes = data.kernel.limiter_mut().memory_used();
     let memory_delta_bytes = memory_bytes.saturating_sub(data.last_memory_bytes);
 
     let mut memory_gas_charge = data.kernel.price_list().grow_memory_gas(memory_delta_bytes);
-    if memory_gas_charge <= exec_gas_charge {
-        exec_gas_charge -= memory_gas_charge;
-    } else {
-        memory_gas_charge = exec_gas_charge;
-        exec_gas_charge = Gas::zero();
+    if fuel_price.is_none() {
+        if memory_gas_charge <= exec_gas_charge {
+            exec_gas_charge -= memory_gas_charge;
+        } else {
+            memory_gas_charge = exec_gas_charge;
+            exec_gas_charge = Gas::zero();
+        }
     }
 
     // Now we actually charge. If we go below 0, we run out of gas.
 
     let t = data
         .kernel
-        .charge_gas("wasm_exec", exec_gas_charge)
+        .charge_gas("wasm/exec", exec_gas_charge)
         .map_err(Abort::from_error_as_fatal)?;
 
     // It should be okay to record time associated with Wasm execution because `charge_for_exec` is
@@ -155,7 +247,7 @@ pub fn charge_for_exec<K: Kernel>(
         // could perform stomething like a multi-variate linear regression to see if the amount of
         // memory explains any of the exectuion time.
         data.kernel
-            .charge_gas("wasm_memory_grow", memory_gas_charge)
+            .charge_gas("wasm/memory_grow", memory_gas_charge)
             .map_err(Abort::from_error_as_fatal)?;
     }
 
@@ -181,10 +273,10 @@ pub fn charge_for_init<K: Kernel>(
 
     if let Some(min_table_elements) = min_table_elements(module) {
         let table_gas = data.kernel.price_list().init_table_gas(min_table_elements);
-        data.kernel.charge_gas("wasm_table_init", table_gas)?;
+        data.kernel.charge_gas("wasm/table_init", table_gas)?;
     }
 
-    data.kernel.charge_gas("wasm_memory_init", memory_gas)
+    data.kernel.charge_gas("wasm/memory_init", memory_gas)
 }
 
 /// Record the time it took to initialize a module.
@@ -233,7 +325,6 @@ fn min_table_elements(module: &Module) -> Option<u32> {
     }
 }
 
-use self::bind::BindSyscall;
 use self::error::Abort;
 
 impl<K> SyscallHandler<K> for DefaultKernel<K::CallManager>
@@ -304,6 +395,18 @@ where
             crypto::recover_secp_public_key,
         )?;
         linker.bind("crypto", "hash", crypto::hash)?;
+        if self.machine().context().network_version >= NetworkVersion::V22 {
+            linker.bind("crypto", "verify_merkle_proof", crypto::verify_merkle_proof)?;
+            linker.bind(
+                "crypto",
+                "verify_signatures_batch",
+                crypto::verify_signatures_batch,
+            )?;
+        }
+
+        if self.machine().context().network_version >= NetworkVersion::V22 {
+            linker.bind("util", "validate_utf8", util::validate_utf8)?;
+        }
 
         linker.bind("event", "emit_event", event::emit_event)?;
 
@@ -315,6 +418,9 @@ where
 
         // Ok, this singled-out syscall should probably be in another category.
         linker.bind("send", "send", send::send)?;
+        if self.machine().context().network_version >= NetworkVersion::V22 {
+            linker.bind("send", "defer_send", send::defer_send)?;
+        }
 
         linker.bind("debug", "log", debug::log)?;
         linker.bind("debug", "enabled", debug::enabled)?;
@@ -324,15 +430,11 @@ where
     }
 }
 
-impl<C> SyscallHandler<DefaultFilecoinKernel<DefaultKernel<C>>>
-    for DefaultFilecoinKernel<DefaultKernel<C>>
+impl<K> SyscallHandler<K> for DefaultFilecoinKernel<DefaultKernel<K::CallManager>>
 where
-    C: CallManager,
+    K: FilecoinKernel,
 {
-    fn bind_syscalls(
-        &self,
-        linker: &mut Linker<InvocationData<DefaultFilecoinKernel<DefaultKernel<C>>>>,
-    ) -> anyhow::Result<()> {
+    fn bind_syscalls(&self, linker: &mut Linker<InvocationData<K>>) -> anyhow::Result<()> {
         self.0.bind_syscalls(linker)?;
 
         // Now bind the crypto syscalls.
@@ -52,3 +52,24 @@ pub fn send<K: Kernel>(
         return_size: block_stat.size,
     })
 }
+
+/// Defer a message to another actor until the current message's call stack has unwound. The
+/// deferred send executes with its own gas limit, and its outcome isn't reported to the caller.
+#[allow(clippy::too_many_arguments)]
+pub fn defer_send<K: Kernel>(
+    context: Context<'_, K>,
+    recipient_off: u32,
+    recipient_len: u32,
+    method: u64,
+    params_id: u32,
+    value_hi: u64,
+    value_lo: u64,
+    gas_limit: u64,
+) -> Result<()> {
+    let recipient: Address = context.memory.read_address(recipient_off, recipient_len)?;
+    let value = TokenAmount::from_atto((value_hi as u128) << 64 | value_lo as u128);
+
+    context
+        .kernel
+        .defer_send(&recipient, method, params_id, &value, Gas::new(gas_limit))
+}
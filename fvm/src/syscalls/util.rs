@@ -0,0 +1,18 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use super::Context;
+use crate::kernel::Result;
+use crate::Kernel;
+
+/// Validates that a string is UTF-8, charging gas per byte.
+///
+/// The return i32 indicates the result of the validation:
+///  - 0: the string is valid UTF-8.
+///  - -1: the string isn't valid UTF-8.
+pub fn validate_utf8(context: Context<'_, impl Kernel>, str_off: u32, str_len: u32) -> Result<i32> {
+    let bytes = context.memory.try_slice(str_off, str_len)?;
+    context
+        .kernel
+        .validate_utf8(bytes)
+        .map(|v| if v { 0 } else { -1 })
+}
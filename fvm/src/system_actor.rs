@@ -4,7 +4,6 @@ use anyhow::Context;
 use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::tuple::{Deserialize_tuple, Serialize_tuple};
-use fvm_ipld_encoding::CborStore;
 use fvm_shared::ActorID;
 
 use crate::kernel::{ClassifyResult, Result};
@@ -23,18 +22,9 @@ impl State {
     where
         B: Blockstore,
     {
-        let system_act = state_tree
-            .get_actor(SYSTEM_ACTOR_ID)?
+        state_tree
+            .get_actor_state(SYSTEM_ACTOR_ID)?
             .context("system actor address could not be resolved")
-            .or_fatal()?;
-
-        let state = state_tree
-            .store()
-            .get_cbor(&system_act.state)
-            .or_fatal()?
-            .context("system actor state not found")
-            .or_fatal()?;
-
-        Ok((state, system_act))
+            .or_fatal()
     }
 }
@@ -1,6 +1,8 @@
-use cid::Cid;
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::time::Duration;
+
+use cid::Cid;
 use fvm_ipld_encoding::ipld_block::IpldBlock;
 use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
@@ -8,7 +10,7 @@ use fvm_shared::error::ExitCode;
 use fvm_shared::ActorID;
 
 use crate::call_manager::Entrypoint;
-use crate::gas::GasCharge;
+use crate::gas::{Gas, GasCharge};
 use crate::kernel::SyscallError;
 
 /// Execution Trace, only for informational and debugging purposes.
@@ -20,7 +22,17 @@ pub type ExecutionTrace = Vec<ExecutionEvent>;
 #[derive(Clone, Debug)]
 #[non_exhaustive]
 pub enum ExecutionEvent {
+    /// A gas charge. When tracing, the charge also records the wall-clock time of the work it
+    /// pays for (see [`GasCharge::elapsed`]).
     GasCharge(GasCharge),
+    /// Emitted after each syscall made by an actor (right after the syscall's gas charges), with
+    /// the wall-clock time the syscall took, including the syscall overhead. Unlike gas, the time
+    /// is specific to the machine that measured it.
+    Syscall {
+        module: &'static str,
+        name: &'static str,
+        elapsed: Duration,
+    },
     /// Emitted on each send call regardless whether we actually end up invoking the
     /// actor or not (e.g. if we don't have enough gas or if the actor does not exist)
     Call {
@@ -32,8 +44,34 @@ pub enum ExecutionEvent {
         gas_limit: u64,
         read_only: bool,
     },
+    /// Emitted when a call finishes (right before the corresponding `CallUsage`, `CallReturn`, or
+    /// `CallError`) with the message's total gas used when the call was made and when it
+    /// returned, and the gas used by the call itself, excluding any nested calls.
+    CallGas {
+        gas_at_entry: Gas,
+        gas_at_exit: Gas,
+        exclusive_gas_used: Gas,
+    },
+    /// Emitted right before the corresponding `CallReturn` or `CallError` when an instruction
+    /// budget is configured, with the gas and Wasm instructions consumed by the call (including any
+    /// nested calls).
+    CallUsage {
+        gas_used: Gas,
+        instructions: u64,
+    },
+    /// Emitted when an actor invocation finishes (right before the corresponding `CallGas`), with
+    /// the peak size of the actor's Wasm memory in 64KiB pages. Wasm memories never shrink, so this
+    /// is the size of the memory when the invocation ends. Not emitted if the actor could not be
+    /// instantiated.
+    InvokeMemory {
+        peak_pages: u64,
+    },
     CallReturn(ExitCode, Option<IpldBlock>),
     CallError(SyscallError),
+    /// Emitted under [`ReentrancyPolicy::Trace`](crate::call_manager::ReentrancyPolicy::Trace)
+    /// when a call re-enters an actor that is already on the call stack (right after the
+    /// corresponding `Call`).
+    Reentrancy(ActorID),
     /// Emitted every time we successfully invoke an actor
     InvokeActor(Cid),
 }
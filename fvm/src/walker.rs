@@ -0,0 +1,327 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Traversal of the blocks reachable from a state root, as needed to garbage collect or export
+//! the state (e.g., for snapshots).
+//!
+//! The walker follows all links from each block: the state root links to the actors HAMT, whose
+//! nodes link to the actors' code and state roots, which in turn link to the actors' own HAMTs,
+//! AMTs, and other blocks. Blocks are visited in parallel, and each block is visited once per
+//! [`VisitedSet`], so walking multiple (e.g., consecutive) state roots with the same set only
+//! visits the blocks they don't share once. [`blocks`] instead iterates over the blocks and their
+//! data on the calling thread, e.g., to export them.
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use anyhow::anyhow;
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::{scan_for_links, DAG_CBOR};
+use fvm_shared::commcid::{FIL_COMMITMENT_SEALED, FIL_COMMITMENT_UNSEALED};
+use fvm_shared::IDENTITY_HASH;
+
+use crate::kernel::ErrorContext;
+
+/// The set of CIDs already visited by a walk, see [`walk`].
+pub trait VisitedSet: Sync {
+    /// Marks a CID as visited, returning false if it was already visited.
+    fn insert(&self, cid: Cid) -> bool;
+}
+
+impl VisitedSet for Mutex<HashSet<Cid>> {
+    fn insert(&self, cid: Cid) -> bool {
+        self.lock().unwrap().insert(cid)
+    }
+}
+
+/// Walks the blocks reachable from `root`, calling `f` with the CID of each block that isn't
+/// already in `visited` (adding it to `visited`), concurrently from multiple threads, in no
+/// particular order. The callback is only called with CIDs of stored blocks: inlined (identity)
+/// blocks are traversed but not reported, and piece commitments are skipped.
+///
+/// Only DAG-CBOR blocks can link to other blocks, so other blocks (e.g., actor code) are reported
+/// without being loaded. The walk fails if a DAG-CBOR block is missing from the store, or if the
+/// callback fails, stopping as soon as possible (blocks may still be reported in the meantime).
+pub fn walk<BS, V, F>(store: &BS, root: &Cid, visited: &V, f: F) -> anyhow::Result<()>
+where
+    BS: Blockstore + Sync,
+    V: VisitedSet + ?Sized,
+    F: Fn(&Cid) -> anyhow::Result<()> + Sync,
+{
+    let error = Mutex::new(None);
+    let walk = Walk {
+        store,
+        visited,
+        f: &f,
+        error: &error,
+    };
+    rayon::scope(|scope| walk.visit(scope, *root));
+    match error.into_inner().unwrap() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Returns the CIDs of all blocks reachable from `root`, see [`walk`].
+pub fn reachable<BS: Blockstore + Sync>(store: &BS, root: &Cid) -> anyhow::Result<HashSet<Cid>> {
+    let reachable = Mutex::new(HashSet::new());
+    walk(store, root, &Mutex::new(HashSet::new()), |cid| {
+        reachable.lock().unwrap().insert(*cid);
+        Ok(())
+    })?;
+    Ok(reachable.into_inner().unwrap())
+}
+
+/// Returns an iterator over the blocks reachable from `root`, each with its data, on the calling
+/// thread (so the store needn't be `Sync`). Blocks are visited depth-first, each block before the
+/// blocks it links to, and are skipped like in [`walk`]. Unlike [`walk`], every block must be in
+/// the store (including, e.g., actor code), as its data is returned.
+///
+/// The iterator returns an error and stops if a block is missing or malformed.
+pub fn blocks<'a, BS: Blockstore>(store: &'a BS, root: &Cid) -> Blocks<'a, BS> {
+    Blocks {
+        store,
+        stack: vec![*root],
+        visited: HashSet::new(),
+    }
+}
+
+/// An iterator over the blocks reachable from a root, see [`blocks`].
+pub struct Blocks<'a, BS> {
+    store: &'a BS,
+    stack: Vec<Cid>,
+    visited: HashSet<Cid>,
+}
+
+impl<BS: Blockstore> Blocks<'_, BS> {
+    /// Loads the block (unless it's inlined), and queues its links.
+    fn load(&mut self, cid: Cid) -> anyhow::Result<Option<Vec<u8>>> {
+        let mut links = Vec::new();
+        let block = if cid.hash().code() == IDENTITY_HASH {
+            if cid.codec() == DAG_CBOR {
+                scan_for_links(cid.hash().digest(), &mut links)?;
+            }
+            None
+        } else {
+            let block = self.store.get(&cid)?.ok_or_else(|| {
+                ErrorContext::default()
+                    .with_cid(cid)
+                    .attach(anyhow!("missing block {}", cid))
+            })?;
+            if cid.codec() == DAG_CBOR {
+                scan_for_links(&block, &mut links)
+                    .map_err(|e| ErrorContext::default().with_cid(cid).attach(e))?;
+            }
+            Some(block)
+        };
+        // Reversed, to visit links in order.
+        self.stack.extend(links.into_iter().rev());
+        Ok(block)
+    }
+}
+
+impl<BS: Blockstore> Iterator for Blocks<'_, BS> {
+    type Item = anyhow::Result<(Cid, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(cid) = self.stack.pop() {
+            if matches!(cid.codec(), FIL_COMMITMENT_UNSEALED | FIL_COMMITMENT_SEALED)
+                || !self.visited.insert(cid)
+            {
+                continue;
+            }
+            match self.load(cid) {
+                Ok(Some(block)) => return Some(Ok((cid, block))),
+                Ok(None) => {}
+                Err(e) => {
+                    self.stack.clear();
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+struct Walk<'a, BS, V: ?Sized, F> {
+    store: &'a BS,
+    visited: &'a V,
+    f: &'a F,
+    /// The first error, which stops the walk.
+    error: &'a Mutex<Option<anyhow::Error>>,
+}
+
+// Derived impls would require the type parameters to be `Copy`.
+impl<BS, V: ?Sized, F> Clone for Walk<'_, BS, V, F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<BS, V: ?Sized, F> Copy for Walk<'_, BS, V, F> {}
+
+impl<'a, BS, V, F> Walk<'a, BS, V, F>
+where
+    BS: Blockstore + Sync,
+    V: VisitedSet + ?Sized,
+    F: Fn(&Cid) -> anyhow::Result<()> + Sync,
+{
+    fn visit<'s>(self, scope: &rayon::Scope<'s>, cid: Cid)
+    where
+        'a: 's,
+    {
+        if matches!(cid.codec(), FIL_COMMITMENT_UNSEALED | FIL_COMMITMENT_SEALED)
+            || self.error.lock().unwrap().is_some()
+            || !self.visited.insert(cid)
+        {
+            return;
+        }
+        let mut links = Vec::new();
+        match self.links(&cid, &mut links) {
+            Ok(()) => {
+                for link in links {
+                    scope.spawn(move |scope| self.visit(scope, link));
+                }
+            }
+            Err(e) => {
+                self.error.lock().unwrap().get_or_insert(e);
+            }
+        }
+    }
+
+    /// Reports the block (unless it's inlined) and collects its links.
+    fn links(&self, cid: &Cid, links: &mut Vec<Cid>) -> anyhow::Result<()> {
+        if cid.hash().code() == IDENTITY_HASH {
+            if cid.codec() == DAG_CBOR {
+                scan_for_links(cid.hash().digest(), links)?;
+            }
+            return Ok(());
+        }
+        (self.f)(cid)?;
+        if cid.codec() == DAG_CBOR {
+            let block = self.store.get(cid)?.ok_or_else(|| {
+                ErrorContext::default()
+                    .with_cid(*cid)
+                    .attach(anyhow!("missing block {}", cid))
+            })?;
+            scan_for_links(&block, links)
+                .map_err(|e| ErrorContext::default().with_cid(*cid).attach(e))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use fvm_ipld_blockstore::tracking::TrackingBlockstore;
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_ipld_encoding::{to_vec, CborStore, IPLD_RAW};
+    use fvm_shared::state::StateTreeVersion;
+    use multihash::{Code, Multihash, MultihashDigest};
+
+    use super::*;
+    use crate::state_tree::{ActorState, StateTree};
+
+    /// Puts the "leaf" block into the store (unless `with_leaf` is false, in which case only
+    /// its CID is computed).
+    fn leaf(store: &impl Blockstore, with_leaf: bool) -> Cid {
+        let block = to_vec(&"leaf").unwrap();
+        let leaf = Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&block));
+        if with_leaf {
+            store.put_keyed(&leaf, &block).unwrap();
+        }
+        leaf
+    }
+
+    /// Builds a state tree with an actor whose state is the leaf, and one whose state links to
+    /// the leaf directly and through an inlined block. Returns the state root, the leaf, and the
+    /// CIDs of the stored blocks.
+    fn build_state(store: &MemoryBlockstore, with_leaf: bool) -> (Cid, Cid, BTreeSet<Cid>) {
+        let store = TrackingBlockstore::new(store);
+        let code = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"wasm"));
+        let leaf = leaf(&store, with_leaf);
+        let inlined = Cid::new_v1(
+            DAG_CBOR,
+            Multihash::wrap(IDENTITY_HASH, &to_vec(&(leaf,)).unwrap()).unwrap(),
+        );
+        let state = store.put_cbor(&(inlined, leaf), Code::Blake2b256).unwrap();
+
+        let mut tree = StateTree::new(&store, StateTreeVersion::V5).unwrap();
+        let actor = |state| ActorState {
+            state,
+            ..ActorState::new_empty(code, None)
+        };
+        tree.set_actor(100, actor(leaf));
+        tree.set_actor(101, actor(state));
+        let root = tree.flush().unwrap();
+        drop(tree);
+        (root, code, store.accesses().writes)
+    }
+
+    #[test]
+    fn walk_state() {
+        let store = MemoryBlockstore::default();
+        let (root, code, written) = build_state(&store, true);
+
+        // All stored blocks are reachable, and so is the code.
+        let mut expected: HashSet<_> = written.into_iter().collect();
+        expected.insert(code);
+        assert_eq!(reachable(&store, &root).unwrap(), expected);
+
+        // Nothing is visited twice.
+        let fail = |_: &Cid| Err(anyhow!("visited"));
+        let visited = Mutex::new(expected);
+        walk(&store, &root, &visited, fail).unwrap();
+
+        // Missing blocks fail the walk, as does the callback.
+        assert!(walk(&store, &root, &Mutex::new(HashSet::new()), fail).is_err());
+        let missing_leaf = MemoryBlockstore::default();
+        let (missing_leaf_root, _, _) = build_state(&missing_leaf, false);
+        assert_eq!(missing_leaf_root, root);
+        assert!(reachable(&missing_leaf, &root).is_err());
+    }
+
+    /// Builds a state tree with an actor whose (stored) code is "wasm" and whose state links to
+    /// the leaf twice. Returns the state root.
+    fn build_blocks(store: &MemoryBlockstore, with_leaf: bool) -> Cid {
+        let code = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"wasm"));
+        store.put_keyed(&code, b"wasm").unwrap();
+        let leaf = leaf(store, with_leaf);
+        let state = store.put_cbor(&(leaf, leaf), Code::Blake2b256).unwrap();
+        let mut tree = StateTree::new(store, StateTreeVersion::V5).unwrap();
+        tree.set_actor(
+            100,
+            ActorState {
+                state,
+                ..ActorState::new_empty(code, None)
+            },
+        );
+        tree.flush().unwrap()
+    }
+
+    #[test]
+    fn iterate_blocks() {
+        let store = MemoryBlockstore::default();
+        let root = build_blocks(&store, true);
+
+        // Every reachable block is returned once, with its data, starting with the root.
+        let all: Vec<_> = blocks(&store, &root).map(Result::unwrap).collect();
+        assert_eq!(all[0].0, root);
+        assert_eq!(
+            all.iter().map(|(cid, _)| *cid).collect::<HashSet<_>>(),
+            reachable(&store, &root).unwrap()
+        );
+        assert_eq!(all.len(), 6);
+        for (cid, block) in &all {
+            assert_eq!(store.get(cid).unwrap().as_ref(), Some(block));
+        }
+
+        // The iterator stops after the first error.
+        let missing_leaf = MemoryBlockstore::default();
+        assert_eq!(build_blocks(&missing_leaf, false), root);
+        let mut iter = blocks(&missing_leaf, &root);
+        assert!(iter.any(|res| res.is_err()));
+        assert!(iter.next().is_none());
+    }
+}
@@ -13,6 +13,7 @@ use num_traits::Zero;
 use super::*;
 
 mod ops;
+mod wrapper;
 
 type TestingKernel = DefaultKernel<DummyCallManager>;
 
@@ -76,6 +77,9 @@ macro_rules! expect_syscall_err {
             ::fvm::kernel::ExecutionError::OutOfGas => {
                 panic!("got unexpected out of gas")
             }
+            ::fvm::kernel::ExecutionError::Timeout => {
+                panic!("got unexpected timeout")
+            }
         }
     };
 }
@@ -91,6 +95,9 @@ macro_rules! expect_out_of_gas {
             ::fvm::kernel::ExecutionError::Fatal(err) => {
                 panic!("got unexpected fatal error: {}", err)
             }
+            ::fvm::kernel::ExecutionError::Timeout => {
+                panic!("got unexpected timeout")
+            }
         }
     };
 }
@@ -5,7 +5,7 @@ use super::*;
 mod ipld {
 
     use cid::Cid;
-    use fvm::kernel::{IpldBlockOps, SupportedHashes};
+    use fvm::kernel::{BlockLimits, IpldBlockOps, SupportedHashes};
     use fvm::machine::Machine;
     use fvm_ipld_blockstore::Blockstore;
     use fvm_ipld_encoding::{DAG_CBOR, IPLD_RAW};
@@ -129,6 +129,31 @@ mod ipld {
         Ok(())
     }
 
+    #[test]
+    fn create_limits() -> anyhow::Result<()> {
+        let (call_manager, _) = dummy::DummyCallManager::new_stub();
+        let limits = BlockLimits {
+            max_block_size: 4,
+            max_handles: 1,
+            ..BlockLimits::default()
+        };
+        let mut kern = TestingKernel::new(
+            call_manager,
+            BlockRegistry::new(limits),
+            0,
+            0,
+            0,
+            Zero::zero(),
+            false,
+        );
+
+        expect_syscall_err!(LimitExceeded, kern.block_create(IPLD_RAW, b"foobar"));
+        assert_eq!(kern.block_create(IPLD_RAW, b"foo")?, 1);
+        expect_syscall_err!(LimitExceeded, kern.block_create(IPLD_RAW, b"bar"));
+
+        Ok(())
+    }
+
     #[test]
     fn link() -> anyhow::Result<()> {
         let (mut kern, _) = build_inspecting_test()?;
@@ -527,3 +552,137 @@ mod gas {
         Ok(())
     }
 }
+
+mod util {
+    use fvm::gas::Gas;
+    use fvm::kernel::{GasOps, UtilOps};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn validate_utf8() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+
+        assert!(kern.validate_utf8("ünïcödé".as_bytes())?);
+        assert!(kern.validate_utf8(b"")?);
+        assert!(!kern.validate_utf8(b"\xff\xfe")?);
+        // Truncated multi-byte sequence.
+        assert!(!kern.validate_utf8(&"é".as_bytes()[..1])?);
+
+        // Validation is charged per byte.
+        let (kern, _) = build_inspecting_test()?;
+        let input = [b'a'; 100];
+        kern.validate_utf8(&input)?;
+        assert_eq!(
+            kern.gas_used(),
+            kern.price_list().on_validate_utf8(input.len()).total()
+        );
+        assert!(kern.gas_used() > Gas::new(0));
+
+        Ok(())
+    }
+}
+
+mod machine {
+    use fvm::gas::Gas;
+    use fvm::kernel::GasOps;
+    use fvm::machine::Machine;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn context() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+
+        let snapshot = kern.machine_context();
+        let ctx = kern.machine().context();
+        assert_eq!(snapshot.epoch, ctx.epoch);
+        assert_eq!(snapshot.base_fee, ctx.base_fee);
+        assert_eq!(snapshot.circ_supply, ctx.circ_supply);
+        assert_eq!(snapshot.network_version, STUB_NETWORK_VER);
+        assert_eq!(snapshot.tracing, ctx.tracing);
+
+        // Reading the context is free.
+        assert_eq!(kern.gas_used(), Gas::new(0));
+
+        Ok(())
+    }
+}
+
+mod debug {
+    use cid::Cid;
+    use fvm::kernel::DebugOps;
+    use fvm::machine::DebugAllowlist;
+    use fvm_ipld_encoding::IPLD_RAW;
+    use fvm_shared::state::ActorState;
+    use fvm_shared::{ActorID, IDENTITY_HASH};
+    use multihash::Multihash;
+
+    use super::*;
+
+    const ACTOR_ID: ActorID = 100;
+
+    /// Build a kernel for [`ACTOR_ID`] (with the code [`test_code`]) with actor debugging enabled
+    /// and restricted to the given allowlist.
+    fn build_debug_test(allowlist: Option<DebugAllowlist>) -> anyhow::Result<TestingKernel> {
+        let (mut call_manager, _) = dummy::DummyCallManager::new_stub();
+        call_manager.machine.ctx.actor_debugging = true;
+        call_manager.machine.ctx.debug_allowlist = allowlist;
+        call_manager
+            .machine
+            .state_tree
+            .set_actor(ACTOR_ID, ActorState::new_empty(test_code(), None));
+
+        Ok(TestingKernel::new(
+            call_manager,
+            BlockRegistry::default(),
+            0,
+            ACTOR_ID,
+            0,
+            Zero::zero(),
+            false,
+        ))
+    }
+
+    fn test_code() -> Cid {
+        Cid::new_v1(
+            IPLD_RAW,
+            Multihash::wrap(IDENTITY_HASH, b"debug-test").unwrap(),
+        )
+    }
+
+    #[test]
+    fn unrestricted() -> anyhow::Result<()> {
+        let kern = build_debug_test(None)?;
+        assert!(kern.debug_enabled());
+        Ok(())
+    }
+
+    #[test]
+    fn allowed() -> anyhow::Result<()> {
+        let kern = build_debug_test(Some(DebugAllowlist::new().allow_actor(ACTOR_ID).clone()))?;
+        assert!(kern.debug_enabled());
+
+        let kern = build_debug_test(Some(DebugAllowlist::new().allow_code(test_code()).clone()))?;
+        assert!(kern.debug_enabled());
+        Ok(())
+    }
+
+    #[test]
+    fn denied() -> anyhow::Result<()> {
+        // An empty allowlist permits no actors.
+        let kern = build_debug_test(Some(DebugAllowlist::new()))?;
+        assert!(!kern.debug_enabled());
+
+        let kern = build_debug_test(Some(
+            DebugAllowlist::new()
+                .allow_actor(ACTOR_ID + 1)
+                .allow_code(Cid::default())
+                .clone(),
+        ))?;
+        assert!(!kern.debug_enabled());
+        Ok(())
+    }
+}
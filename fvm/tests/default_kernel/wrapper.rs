@@ -0,0 +1,159 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use ambassador::Delegate;
+use cid::Cid;
+use fvm::call_manager::CallManager;
+use fvm::gas::Gas;
+use fvm::kernel::*;
+use fvm::syscalls::InvocationData;
+use fvm_ipld_encoding::IPLD_RAW;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::randomness::RANDOMNESS_LENGTH;
+use fvm_shared::sys::SendFlags;
+use fvm_shared::{ActorID, MethodNum};
+use wasmtime::Linker;
+
+use super::*;
+
+/// Wraps a kernel, overriding randomness only.
+#[derive(Delegate)]
+#[delegate(IpldBlockOps)]
+#[delegate(ActorOps)]
+#[delegate(CircSupplyOps)]
+#[delegate(CryptoOps)]
+#[delegate(DebugOps)]
+#[delegate(EventOps)]
+#[delegate(GasOps)]
+#[delegate(MessageOps)]
+#[delegate(NetworkOps)]
+#[delegate(SelfOps)]
+#[delegate(UtilOps)]
+#[delegate(LimiterOps)]
+struct FixedRandomnessKernel<K>(K)
+where
+    K: Kernel;
+
+impl<K> Kernel for FixedRandomnessKernel<K>
+where
+    K: Kernel,
+    Self: SyscallHandler<Self>,
+{
+    type CallManager = K::CallManager;
+
+    fn into_inner(self) -> (Self::CallManager, BlockRegistry) {
+        self.0.into_inner()
+    }
+
+    fn new(
+        mgr: Self::CallManager,
+        blocks: BlockRegistry,
+        caller: ActorID,
+        actor_id: ActorID,
+        method: MethodNum,
+        value_received: TokenAmount,
+        read_only: bool,
+    ) -> Self {
+        FixedRandomnessKernel(K::new(
+            mgr,
+            blocks,
+            caller,
+            actor_id,
+            method,
+            value_received,
+            read_only,
+        ))
+    }
+
+    fn machine(&self) -> &<Self::CallManager as CallManager>::Machine {
+        self.0.machine()
+    }
+
+    fn send<KK: Kernel<CallManager = Self::CallManager>>(
+        &mut self,
+        recipient: &Address,
+        method: u64,
+        params: BlockId,
+        value: &TokenAmount,
+        gas_limit: Option<Gas>,
+        flags: SendFlags,
+    ) -> Result<CallResult> {
+        self.0
+            .send::<KK>(recipient, method, params, value, gas_limit, flags)
+    }
+
+    fn upgrade_actor<KK: Kernel<CallManager = Self::CallManager>>(
+        &mut self,
+        new_code_cid: Cid,
+        params_id: BlockId,
+    ) -> Result<CallResult> {
+        self.0.upgrade_actor::<KK>(new_code_cid, params_id)
+    }
+
+    fn defer_send(
+        &mut self,
+        recipient: &Address,
+        method: u64,
+        params: BlockId,
+        value: &TokenAmount,
+        gas_limit: Gas,
+    ) -> Result<()> {
+        self.0
+            .defer_send(recipient, method, params, value, gas_limit)
+    }
+}
+
+impl<C: CallManager> SyscallHandler<FixedRandomnessKernel<DefaultKernel<C>>>
+    for FixedRandomnessKernel<DefaultKernel<C>>
+{
+    fn bind_syscalls(
+        &self,
+        linker: &mut Linker<InvocationData<FixedRandomnessKernel<DefaultKernel<C>>>>,
+    ) -> anyhow::Result<()> {
+        self.0.bind_syscalls(linker)
+    }
+}
+
+impl<K: Kernel> RandomnessOps for FixedRandomnessKernel<K> {
+    fn get_randomness_from_tickets(
+        &self,
+        rand_epoch: ChainEpoch,
+    ) -> Result<[u8; RANDOMNESS_LENGTH]> {
+        Ok([rand_epoch as u8; RANDOMNESS_LENGTH])
+    }
+
+    fn get_randomness_from_beacon(
+        &self,
+        rand_epoch: ChainEpoch,
+    ) -> Result<[u8; RANDOMNESS_LENGTH]> {
+        Ok([!(rand_epoch as u8); RANDOMNESS_LENGTH])
+    }
+}
+
+#[test]
+fn override_randomness() -> anyhow::Result<()> {
+    let (call_manager, _) = dummy::DummyCallManager::new_stub();
+    let mut kern = FixedRandomnessKernel::<TestingKernel>::new(
+        call_manager,
+        BlockRegistry::default(),
+        0,
+        0,
+        0,
+        Zero::zero(),
+        false,
+    );
+
+    // Overridden.
+    assert_eq!(kern.get_randomness_from_tickets(3)?, [3; RANDOMNESS_LENGTH]);
+    assert_eq!(kern.get_randomness_from_beacon(3)?, [!3; RANDOMNESS_LENGTH]);
+
+    // Delegated to the default kernel.
+    let id = kern.block_create(IPLD_RAW, b"foo")?;
+    assert_eq!(kern.block_stat(id)?.size, 3);
+    assert_eq!(kern.resolve_address(&Address::new_id(5))?, 5);
+
+    let (call_manager, _) = kern.into_inner();
+    assert!(call_manager.test_data.borrow().charge_gas_calls > 0);
+    Ok(())
+}
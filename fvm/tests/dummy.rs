@@ -6,7 +6,9 @@ use std::rc::Rc;
 
 use anyhow::Context;
 use cid::Cid;
-use fvm::call_manager::{Backtrace, CallManager, Entrypoint, FinishRet, InvocationResult};
+use fvm::call_manager::{
+    Backtrace, CallManager, DeferredSend, Entrypoint, FinishRet, InvocationResult,
+};
 use fvm::engine::Engine;
 use fvm::externs::{Chain, Consensus, Externs, Rand};
 use fvm::gas::{Gas, GasCharge, GasTimer, GasTracker};
@@ -296,10 +298,29 @@ impl CallManager for DummyCallManager {
         todo!()
     }
 
+    fn begin_transaction(&mut self) {
+        todo!()
+    }
+
+    fn end_transaction(&mut self, _revert: bool) -> kernel::Result<()> {
+        todo!()
+    }
+
+    fn defer_send(&mut self, _send: DeferredSend) -> kernel::Result<()> {
+        todo!()
+    }
+
+    fn run_deferred_sends<K: Kernel<CallManager = Self>>(&mut self) -> kernel::Result<()> {
+        todo!()
+    }
+
     fn finish(self) -> (kernel::Result<FinishRet>, Self::Machine) {
         (
             Ok(FinishRet {
                 gas_used: 0,
+                refund_credit: 0,
+                gas_breakdown: Default::default(),
+                actor_gas: None,
                 backtrace: Backtrace {
                     frames: Vec::new(),
                     cause: None,
@@ -119,7 +119,7 @@ pub struct AmtImpl<V, BS, Ver> {
     pub(crate) root: RootImpl<V, Ver>,
     pub(crate) block_store: BS,
     /// Remember the last flushed CID until it changes.
-    flushed_cid: Option<Cid>,
+    pub(crate) flushed_cid: Option<Cid>,
 }
 
 /// Array Mapped Trie allows for the insertion and persistence of data, serializable to a CID.
@@ -256,6 +256,53 @@ where
         })
     }
 
+    /// Constructs an AMT from `(index, value)` pairs, building the tree bottom-up instead of
+    /// inserting the values one by one. Nothing is written to the blockstore until the AMT is
+    /// flushed.
+    ///
+    /// This is much faster than repeated calls to [`set`](Self::set) when rebuilding large sparse
+    /// arrays (e.g., in migrations), and produces the same AMT. Pairs sorted by index are cheapest
+    /// to build from, but any order is accepted. Returns an error if an index is out of range or
+    /// supplied more than once.
+    pub fn from_pairs(
+        block_store: BS,
+        pairs: impl IntoIterator<Item = (u64, V)>,
+    ) -> Result<Self, Error> {
+        Self::from_pairs_with_bit_width(block_store, DEFAULT_BIT_WIDTH, pairs)
+    }
+
+    /// Constructs an AMT with the requested bitwidth from `(index, value)` pairs. See
+    /// [`from_pairs`](Self::from_pairs).
+    pub fn from_pairs_with_bit_width(
+        block_store: BS,
+        bit_width: u32,
+        pairs: impl IntoIterator<Item = (u64, V)>,
+    ) -> Result<Self, Error> {
+        let mut pairs: Vec<_> = pairs.into_iter().collect();
+        // A stable sort is linear on already sorted input.
+        pairs.sort_by_key(|(i, _)| *i);
+        if let Some(w) = pairs.windows(2).find(|w| w[0].0 == w[1].0) {
+            return Err(anyhow!("duplicate index {} in Amt pairs", w[0].0).into());
+        }
+
+        let mut amt = Self::new_with_bit_width(block_store, bit_width);
+        let Some(&(max, _)) = pairs.last() else {
+            return Ok(amt);
+        };
+        if max > MAX_INDEX {
+            return Err(Error::OutOfRange(max));
+        }
+
+        let mut height = 0;
+        while max >= nodes_for_height(bit_width, height + 1) {
+            height += 1;
+        }
+        amt.root.height = height;
+        amt.root.count = pairs.len() as u64;
+        amt.root.node = Node::from_sorted(height, bit_width, pairs);
+        Ok(amt)
+    }
+
     /// Get value at index of AMT
     pub fn get(&self, i: u64) -> Result<Option<&V>, Error> {
         if i > MAX_INDEX {
@@ -33,6 +33,9 @@ pub enum Error {
     /// Cid not found in store error
     #[error("Cid ({0}) did not match any in database")]
     CidNotFound(String),
+    /// A Merkle proof doesn't match the AMT root it's verified against.
+    #[error("invalid AMT proof: {0}")]
+    InvalidProof(String),
     /// Dynamic error for when the error needs to be forwarded as is.
     #[error("{0}")]
     Dynamic(anyhow::Error),
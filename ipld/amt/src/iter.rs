@@ -53,6 +53,14 @@ where
             key: 0,
         }
     }
+
+    /// Iterate over the AMT in its canonical order, i.e., by ascending index.
+    ///
+    /// This order is stable across versions of this crate and across platforms, and is the same
+    /// order as `iter` and `for_each`.
+    pub fn canonical_iter(&self) -> Iter<'_, V, &BS, Ver> {
+        self.iter()
+    }
 }
 
 impl<'a, V, BS, Ver> IntoIterator for &'a crate::AmtImpl<V, BS, Ver>
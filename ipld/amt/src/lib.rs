@@ -12,6 +12,7 @@ mod diff;
 mod error;
 mod iter;
 mod node;
+mod proof;
 mod root;
 mod value_mut;
 
@@ -20,6 +21,7 @@ pub use self::amt::{Amt, Amtv0};
 pub use self::diff::{diff, Change, ChangeType};
 pub use self::error::Error;
 pub(crate) use self::node::Node;
+pub use self::proof::AmtProof;
 pub use self::value_mut::ValueMut;
 
 const DEFAULT_BIT_WIDTH: u32 = 3;
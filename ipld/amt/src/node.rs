@@ -180,6 +180,33 @@ where
         }
     }
 
+    /// Builds a node of the given height bottom-up from non-empty, sorted and deduplicated
+    /// `(index, value)` pairs, with indices relative to the start of this node. Only the nodes
+    /// covering at least one value are created, as with [`Node::set`].
+    pub(super) fn from_sorted(height: u32, bit_width: u32, pairs: Vec<(u64, V)>) -> Self {
+        if height == 0 {
+            let mut vals = init_sized_vec(bit_width);
+            for (i, val) in pairs {
+                vals[i as usize] = Some(val);
+            }
+            return Node::Leaf { vals };
+        }
+
+        let nfh = nodes_for_height(bit_width, height);
+        let mut links = init_sized_vec(bit_width);
+        let mut pairs = pairs.into_iter().peekable();
+        while let Some((first, val)) = pairs.next() {
+            let idx = first / nfh;
+            let mut group = vec![(first % nfh, val)];
+            while let Some((i, val)) = pairs.next_if(|(i, _)| i / nfh == idx) {
+                group.push((i % nfh, val));
+            }
+            let node = Self::from_sorted(height - 1, bit_width, group);
+            links[idx as usize] = Some(Link::Dirty(Box::new(node)));
+        }
+        Node::Link { links }
+    }
+
     /// Flushes cache for node, replacing any cached values with a Cid variant
     pub(super) fn flush<DB: Blockstore>(&mut self, bs: &DB) -> Result<(), Error> {
         if let Node::Link { links } = self {
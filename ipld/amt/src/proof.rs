@@ -0,0 +1,157 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::{from_slice, DAG_CBOR};
+use serde::de::{DeserializeOwned, IgnoredAny};
+
+use crate::node::{CollapsedNode, Link};
+use crate::root::version::{Version as AmtVersion, V0, V3};
+use crate::root::RootImpl;
+use crate::{nodes_for_height, AmtImpl, Error, Node, MAX_HEIGHT, MAX_INDEX};
+
+/// A Merkle proof of the value stored at (or the absence of a value at) an index of an AMT.
+///
+/// The proof consists of the encoded blocks on the path from the AMT's root to the node covering
+/// the index, in that order, and can be verified against the AMT's root CID without access to the
+/// blockstore.
+///
+/// ```
+/// use fvm_ipld_amt::Amt;
+///
+/// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+/// let mut amt = Amt::new(&store);
+/// amt.set(1, "foo".to_owned()).unwrap();
+/// amt.set(100, "bar".to_owned()).unwrap();
+/// let root = amt.flush().unwrap();
+///
+/// let proof = amt.prove(100).unwrap();
+/// assert_eq!(proof.verify::<String>(&root).unwrap(), Some("bar".to_owned()));
+///
+/// let proof = amt.prove(2).unwrap();
+/// assert_eq!(proof.verify::<String>(&root).unwrap(), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmtProof {
+    /// The proven index.
+    pub index: u64,
+    /// The encoded blocks on the path from the root to the index.
+    pub blocks: Vec<Vec<u8>>,
+}
+
+impl AmtProof {
+    /// Verifies the proof against the root of an AMT, returning the value stored at the proven
+    /// index, if any. Returns an [`Error::InvalidProof`] if the proof doesn't match the root.
+    pub fn verify<V: DeserializeOwned>(&self, root: &Cid) -> Result<Option<V>, Error> {
+        self.verify_impl::<V, V3>(root)
+    }
+
+    /// Like [`AmtProof::verify`], but for a legacy (v0) AMT.
+    pub fn verify_v0<V: DeserializeOwned>(&self, root: &Cid) -> Result<Option<V>, Error> {
+        self.verify_impl::<V, V0>(root)
+    }
+
+    fn verify_impl<V, Ver>(&self, root: &Cid) -> Result<Option<V>, Error>
+    where
+        V: DeserializeOwned,
+        Ver: AmtVersion,
+    {
+        let mut blocks = self.blocks.iter();
+        let value = walk::<V, Ver>(root, self.index, |cid| {
+            let block = blocks
+                .next()
+                .ok_or_else(|| Error::InvalidProof(format!("missing block {cid}")))?;
+            check_block(cid, block)?;
+            Ok(block.clone())
+        })?;
+        if blocks.next().is_some() {
+            return Err(Error::InvalidProof("unexpected trailing blocks".into()));
+        }
+        Ok(value)
+    }
+}
+
+impl<V, BS, Ver> AmtImpl<V, BS, Ver>
+where
+    BS: Blockstore,
+    Ver: AmtVersion,
+{
+    /// Generates a proof of the value stored at (or the absence of a value at) the given index.
+    /// The AMT must have been flushed, otherwise this returns [`Error::Cached`].
+    pub fn prove(&self, i: u64) -> Result<AmtProof, Error> {
+        let root = self.flushed_cid.ok_or(Error::Cached)?;
+        let mut blocks = Vec::new();
+        walk::<IgnoredAny, Ver>(&root, i, |cid| {
+            let block = self
+                .block_store
+                .get(cid)?
+                .ok_or_else(|| Error::CidNotFound(cid.to_string()))?;
+            blocks.push(block.clone());
+            Ok(block)
+        })?;
+        Ok(AmtProof { index: i, blocks })
+    }
+}
+
+/// Checks that the block matches the given CID.
+fn check_block(cid: &Cid, block: &[u8]) -> Result<(), Error> {
+    if cid.codec() != DAG_CBOR {
+        return Err(Error::InvalidProof(format!("unexpected codec in {cid}")));
+    }
+    let code = Code::try_from(cid.hash().code())
+        .map_err(|_| Error::InvalidProof(format!("unsupported hash function in {cid}")))?;
+    if code.digest(block) != *cid.hash() {
+        return Err(Error::InvalidProof(format!("block does not match {cid}")));
+    }
+    Ok(())
+}
+
+/// Walks the path from the root of an AMT to the given index, loading each block on the path with
+/// `load_block`, and returns the value at the index (if any).
+fn walk<V, Ver>(
+    root: &Cid,
+    i: u64,
+    mut load_block: impl FnMut(&Cid) -> Result<Vec<u8>, Error>,
+) -> Result<Option<V>, Error>
+where
+    V: DeserializeOwned,
+    Ver: AmtVersion,
+{
+    if i > MAX_INDEX {
+        return Err(Error::OutOfRange(i));
+    }
+
+    let root: RootImpl<V, Ver> = from_slice(&load_block(root)?)?;
+    if root.height > MAX_HEIGHT {
+        return Err(Error::MaxHeight(root.height, MAX_HEIGHT));
+    }
+    if i >= nodes_for_height(root.bit_width, root.height + 1) {
+        return Ok(None);
+    }
+
+    let bit_width = root.bit_width;
+    let mut height = root.height;
+    let mut node = root.node;
+    let mut i = i;
+    loop {
+        match node {
+            Node::Leaf { mut vals } => return Ok(vals.get_mut(i as usize).and_then(Option::take)),
+            Node::Link { links } => {
+                if height == 0 {
+                    return Err(anyhow::anyhow!("link node at height 0").into());
+                }
+                let per_child = nodes_for_height(bit_width, height);
+                let cid = match links.get((i / per_child) as usize) {
+                    Some(Some(Link::Cid { cid, .. })) => *cid,
+                    Some(Some(Link::Dirty(_))) => return Err(Error::Cached),
+                    _ => return Ok(None),
+                };
+                node = from_slice::<CollapsedNode<V>>(&load_block(&cid)?)?.expand(bit_width)?;
+                i %= per_child;
+                height -= 1;
+            }
+        }
+    }
+}
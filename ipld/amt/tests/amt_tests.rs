@@ -634,3 +634,145 @@ fn new_from_iter() {
     let expected: Vec<_> = data.into_iter().enumerate().collect();
     assert_eq!(expected, restored);
 }
+
+#[test]
+fn from_pairs() {
+    let mem = MemoryBlockstore::default();
+    // Sparse indices, spanning several heights, supplied out of order.
+    let indices = [3u64, 1 << 20, 7, 0, 64, 5_000_000_000, 65, 513];
+
+    for bit_width in [1, 3, 5] {
+        let mut expected = Amt::new_with_bit_width(&mem, bit_width);
+        for i in indices {
+            expected.set(i, i * 2).unwrap();
+        }
+
+        let mut a =
+            Amt::from_pairs_with_bit_width(&mem, bit_width, indices.map(|i| (i, i * 2))).unwrap();
+        assert_eq!(a.count(), indices.len() as u64);
+        assert_eq!(a.height(), expected.height());
+        assert_eq!(a.get(1 << 20).unwrap(), Some(&(2 << 20)));
+        assert_eq!(a.get(8).unwrap(), None);
+        assert_eq!(a.flush().unwrap(), expected.flush().unwrap());
+    }
+
+    // Empty.
+    let mut a = Amt::<u64, _>::from_pairs(&mem, []).unwrap();
+    assert_eq!(
+        a.flush().unwrap(),
+        Amt::<u64, _>::new(&mem).flush().unwrap()
+    );
+
+    // Duplicate and out of range indices.
+    assert!(Amt::from_pairs(&mem, [(1, 1), (2, 2), (1, 3)]).is_err());
+    assert!(matches!(
+        Amt::from_pairs(&mem, [(MAX_INDEX + 1, 1)]),
+        Err(Error::OutOfRange(_))
+    ));
+}
+
+#[test]
+fn canonical_iteration_order() {
+    let mem = MemoryBlockstore::default();
+    let indices: [u64; 10] = [1000, 3, 0, 64, 7, 1 << 20, 9, 512, 2, 65];
+
+    let mut a = Amt::new_with_bit_width(&mem, 3);
+    for i in indices {
+        a.set(i, i.to_string()).unwrap();
+    }
+
+    let mut expected = indices.to_vec();
+    expected.sort_unstable();
+
+    // Iteration over dirty nodes.
+    let dirty: Vec<u64> = a
+        .canonical_iter()
+        .map(|kv| kv.map(|(k, _)| k))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(dirty, expected);
+
+    // Iteration over a freshly loaded AMT, then over its cached nodes.
+    let c = a.flush().unwrap();
+    let a: Amt<String, _> = Amt::load(&c, &mem).unwrap();
+    for _ in 0..2 {
+        let loaded: Vec<(u64, String)> = a
+            .canonical_iter()
+            .map(|kv| kv.map(|(k, v)| (k, v.clone())))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let golden: Vec<(u64, String)> = expected.iter().map(|i| (*i, i.to_string())).collect();
+        assert_eq!(loaded, golden);
+    }
+}
+
+#[test]
+fn inclusion_proofs() {
+    let mem = MemoryBlockstore::default();
+    let indices = [0, 5, 64, 1000, 1 << 20];
+
+    let mut a = Amt::new(&mem);
+    for i in indices {
+        a.set(i, i.to_string()).unwrap();
+    }
+    assert!(matches!(a.prove(0), Err(Error::Cached)));
+    let root = a.flush().unwrap();
+
+    for i in indices {
+        let proof = a.prove(i).unwrap();
+        assert_eq!(proof.blocks.len() as u32, a.height() + 1);
+        assert_eq!(proof.verify::<String>(&root).unwrap(), Some(i.to_string()));
+    }
+
+    // Absent indices, both within and beyond the AMT's current range.
+    for i in [1, 63, 1001, 1 << 40] {
+        assert_eq!(a.prove(i).unwrap().verify::<String>(&root).unwrap(), None);
+    }
+
+    // Tampered proofs must not verify.
+    let mut proof = a.prove(1000).unwrap();
+    proof.index = 64;
+    assert!(matches!(
+        proof.verify::<String>(&root),
+        Err(Error::InvalidProof(_))
+    ));
+
+    let mut proof = a.prove(1000).unwrap();
+    proof.blocks.pop();
+    assert!(matches!(
+        proof.verify::<String>(&root),
+        Err(Error::InvalidProof(_))
+    ));
+
+    let mut proof = a.prove(1000).unwrap();
+    let last = proof.blocks.last_mut().unwrap();
+    *last.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        proof.verify::<String>(&root),
+        Err(Error::InvalidProof(_))
+    ));
+
+    let mut other = Amt::new(&mem);
+    other.set(1000, "1000".to_owned()).unwrap();
+    let other_root = other.flush().unwrap();
+    assert!(matches!(
+        a.prove(1000).unwrap().verify::<String>(&other_root),
+        Err(Error::InvalidProof(_))
+    ));
+}
+
+#[test]
+fn legacy_amtv0_inclusion_proofs() {
+    let mem = MemoryBlockstore::default();
+    let mut a = Amtv0::new(&mem);
+    a.set(3, "three".to_owned()).unwrap();
+    a.set(300, "three hundred".to_owned()).unwrap();
+    let root = a.flush().unwrap();
+
+    let proof = a.prove(300).unwrap();
+    assert_eq!(
+        proof.verify_v0::<String>(&root).unwrap(),
+        Some("three hundred".to_owned())
+    );
+    assert!(proof.verify::<String>(&root).is_err());
+}
@@ -0,0 +1,177 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::future::Future;
+use std::sync::Arc;
+
+use anyhow::Result;
+use cid::Cid;
+use futures::future::BoxFuture;
+
+use super::Blockstore;
+
+/// An IPLD blockstore with an asynchronous storage backend (e.g., a network store).
+///
+/// The FVM itself is synchronous: wrap async blockstores in a [`BlockingBlockstore`] to use them
+/// in the FVM.
+pub trait AsyncBlockstore: Send + Sync {
+    /// Gets the block from the blockstore.
+    fn get<'a>(&'a self, k: &'a Cid) -> BoxFuture<'a, Result<Option<Vec<u8>>>>;
+
+    /// Put a block with a pre-computed cid.
+    fn put_keyed<'a>(&'a self, k: &'a Cid, block: &'a [u8]) -> BoxFuture<'a, Result<()>>;
+
+    /// Checks if the blockstore has the specified block.
+    fn has<'a>(&'a self, k: &'a Cid) -> BoxFuture<'a, Result<bool>> {
+        Box::pin(async move { Ok(self.get(k).await?.is_some()) })
+    }
+
+    /// Bulk-put pre-keyed blocks into the blockstore.
+    ///
+    /// By default, this defers to put_keyed.
+    fn put_many_keyed<'a>(&'a self, blocks: Vec<(Cid, Vec<u8>)>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            for (c, b) in &blocks {
+                self.put_keyed(c, b).await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl<BS: AsyncBlockstore + ?Sized> AsyncBlockstore for Arc<BS> {
+    fn get<'a>(&'a self, k: &'a Cid) -> BoxFuture<'a, Result<Option<Vec<u8>>>> {
+        (**self).get(k)
+    }
+
+    fn put_keyed<'a>(&'a self, k: &'a Cid, block: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        (**self).put_keyed(k, block)
+    }
+
+    fn has<'a>(&'a self, k: &'a Cid) -> BoxFuture<'a, Result<bool>> {
+        (**self).has(k)
+    }
+
+    fn put_many_keyed<'a>(&'a self, blocks: Vec<(Cid, Vec<u8>)>) -> BoxFuture<'a, Result<()>> {
+        (**self).put_many_keyed(blocks)
+    }
+}
+
+/// Runs futures to completion on the calling thread, see [`BlockingBlockstore`].
+///
+/// Implement this for a handle to the embedder's async runtime, e.g., a newtype around
+/// `tokio::runtime::Handle` calling `Handle::block_on`.
+pub trait BlockOn {
+    /// Blocks the calling thread until the future completes.
+    fn block_on<F: Future>(&self, future: F) -> F::Output;
+}
+
+/// Runs futures with the executor from the `futures` crate, which drives them on the calling
+/// thread. This only works with futures that don't depend on a specific runtime.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LocalExecutor;
+
+impl BlockOn for LocalExecutor {
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        futures::executor::block_on(future)
+    }
+}
+
+/// Adapts an [`AsyncBlockstore`] to the synchronous [`Blockstore`] trait, blocking on each
+/// operation with the given runtime handle.
+///
+/// The FVM must run outside of the runtime's worker threads (e.g., on a blocking thread spawned
+/// by the runtime), so that it only blocks its own thread and not the runtime.
+#[derive(Debug, Clone)]
+pub struct BlockingBlockstore<BS, R = LocalExecutor> {
+    store: BS,
+    runtime: R,
+}
+
+impl<BS, R> BlockingBlockstore<BS, R> {
+    pub fn new(store: BS, runtime: R) -> Self {
+        Self { store, runtime }
+    }
+
+    /// Returns the wrapped async blockstore.
+    pub fn store(&self) -> &BS {
+        &self.store
+    }
+
+    /// Consumes the adapter, returning the wrapped async blockstore.
+    pub fn into_inner(self) -> BS {
+        self.store
+    }
+}
+
+impl<BS, R> Blockstore for BlockingBlockstore<BS, R>
+where
+    BS: AsyncBlockstore,
+    R: BlockOn,
+{
+    fn get(&self, k: &Cid) -> Result<Option<Vec<u8>>> {
+        self.runtime.block_on(self.store.get(k))
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> Result<()> {
+        self.runtime.block_on(self.store.put_keyed(k, block))
+    }
+
+    fn has(&self, k: &Cid) -> Result<bool> {
+        self.runtime.block_on(self.store.has(k))
+    }
+
+    fn put_many_keyed<D, I>(&self, blocks: I) -> Result<()>
+    where
+        Self: Sized,
+        D: AsRef<[u8]>,
+        I: IntoIterator<Item = (Cid, D)>,
+    {
+        let blocks = blocks
+            .into_iter()
+            .map(|(c, b)| (c, b.as_ref().to_vec()))
+            .collect();
+        self.runtime.block_on(self.store.put_many_keyed(blocks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use multihash::Code::Blake2b256;
+
+    use super::*;
+    use crate::{Block, MemoryBlockstore};
+
+    /// An asynchronous view of the (thread-safe) [`MemoryBlockstore`].
+    #[derive(Default)]
+    struct AsyncMemoryBlockstore(MemoryBlockstore);
+
+    impl AsyncBlockstore for AsyncMemoryBlockstore {
+        fn get<'a>(&'a self, k: &'a Cid) -> BoxFuture<'a, Result<Option<Vec<u8>>>> {
+            Box::pin(async move { self.0.get(k) })
+        }
+
+        fn put_keyed<'a>(&'a self, k: &'a Cid, block: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move { self.0.put_keyed(k, block) })
+        }
+    }
+
+    #[test]
+    fn blocking() {
+        let store = Arc::new(AsyncMemoryBlockstore::default());
+        let bs = BlockingBlockstore::new(store.clone(), LocalExecutor);
+
+        let block = Block::new(0x55, b"foo");
+        let cid = bs.put(Blake2b256, &block).unwrap();
+        assert_eq!(bs.get(&cid).unwrap().as_deref(), Some(&b"foo"[..]));
+        assert!(bs.has(&cid).unwrap());
+
+        let blocks = [Block::new(0x55, b"bar"), Block::new(0x55, b"baz")];
+        bs.put_many(blocks.iter().map(|b| (Blake2b256, b.into())))
+            .unwrap();
+        for block in &blocks {
+            assert!(store.0.has(&block.cid(Blake2b256)).unwrap());
+        }
+        let missing = Block::new(0x55, b"qux").cid(Blake2b256);
+        assert!(!LocalExecutor.block_on(store.has(&missing)).unwrap());
+    }
+}
@@ -0,0 +1,324 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use cid::Cid;
+
+use super::Blockstore;
+
+/// Decides which blocks a [CachedBlockstore] caches.
+pub trait AdmissionPolicy: Send + Sync {
+    /// Returns true if the block, just read from or written to the inner store, should be cached.
+    fn admit(&self, cid: &Cid, block: &[u8]) -> bool;
+}
+
+/// Caches all blocks (that fit in the cache).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AdmitAll;
+
+impl AdmissionPolicy for AdmitAll {
+    fn admit(&self, _: &Cid, _: &[u8]) -> bool {
+        true
+    }
+}
+
+/// Caches the blocks of at most the given size, so that a few large blocks (e.g., actor code)
+/// don't evict many small ones.
+#[derive(Debug, Clone, Copy)]
+pub struct MaxBlockSize(pub usize);
+
+impl AdmissionPolicy for MaxBlockSize {
+    fn admit(&self, _: &Cid, block: &[u8]) -> bool {
+        block.len() <= self.0
+    }
+}
+
+impl<F> AdmissionPolicy for F
+where
+    F: Fn(&Cid, &[u8]) -> bool + Send + Sync,
+{
+    fn admit(&self, cid: &Cid, block: &[u8]) -> bool {
+        self(cid, block)
+    }
+}
+
+/// Stats for a [CachedBlockstore].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of `get`s served from the cache.
+    pub hits: u64,
+    /// Number of `get`s forwarded to the inner store.
+    pub misses: u64,
+    /// Number of blocks currently cached.
+    pub blocks: usize,
+    /// Total size of the blocks currently cached.
+    pub bytes: usize,
+}
+
+/// The cached blocks, with their last use.
+#[derive(Debug, Default)]
+struct Lru {
+    budget: usize,
+    tick: u64,
+    blocks: HashMap<Cid, (Vec<u8>, u64)>,
+    /// The cached CIDs, by last use.
+    order: BTreeMap<u64, Cid>,
+    stats: CacheStats,
+}
+
+impl Lru {
+    fn get(&mut self, k: &Cid) -> Option<Vec<u8>> {
+        self.tick += 1;
+        let (block, last_used) = self.blocks.get_mut(k)?;
+        self.order.remove(last_used);
+        self.order.insert(self.tick, *k);
+        *last_used = self.tick;
+        Some(block.clone())
+    }
+
+    fn insert(&mut self, k: Cid, block: &[u8]) {
+        if block.len() > self.budget {
+            return;
+        }
+        self.remove(&k);
+        self.tick += 1;
+        self.order.insert(self.tick, k);
+        self.blocks.insert(k, (block.to_vec(), self.tick));
+        self.stats.blocks += 1;
+        self.stats.bytes += block.len();
+        while self.stats.bytes > self.budget {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+    }
+
+    fn remove(&mut self, k: &Cid) {
+        if let Some((block, last_used)) = self.blocks.remove(k) {
+            self.order.remove(&last_used);
+            self.stats.blocks -= 1;
+            self.stats.bytes -= block.len();
+        }
+    }
+}
+
+/// Wrapper around `Blockstore` caching recently used blocks in memory, up to a budget in bytes,
+/// evicting the least recently used blocks first. Blocks are cached when they're read from, or
+/// written (through) to, the inner store, if the [AdmissionPolicy] admits them.
+///
+/// The cache is shared between threads, so the store is `Sync` if the inner store is.
+#[derive(Debug)]
+pub struct CachedBlockstore<BS, P = AdmitAll> {
+    base: BS,
+    policy: P,
+    cache: Mutex<Lru>,
+}
+
+impl<BS> CachedBlockstore<BS>
+where
+    BS: Blockstore,
+{
+    /// Caches up to `budget` bytes of blocks, admitting all blocks.
+    pub fn new(base: BS, budget: usize) -> Self {
+        Self {
+            base,
+            policy: AdmitAll,
+            cache: Mutex::new(Lru {
+                budget,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+impl<BS, P> CachedBlockstore<BS, P>
+where
+    BS: Blockstore,
+    P: AdmissionPolicy,
+{
+    /// Only caches the blocks admitted by the given policy. Blocks already cached are kept.
+    pub fn with_policy<Q: AdmissionPolicy>(self, policy: Q) -> CachedBlockstore<BS, Q> {
+        CachedBlockstore {
+            base: self.base,
+            policy,
+            cache: self.cache,
+        }
+    }
+
+    /// Returns the stats of the cache.
+    pub fn stats(&self) -> CacheStats {
+        self.cache.lock().unwrap().stats
+    }
+
+    /// Evicts all blocks from the cache, e.g., after modifying the inner store directly.
+    pub fn clear(&self) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.blocks.clear();
+        cache.order.clear();
+        cache.stats.blocks = 0;
+        cache.stats.bytes = 0;
+    }
+
+    pub fn into_inner(self) -> BS {
+        self.base
+    }
+
+    fn admit(&self, k: &Cid, block: &[u8]) {
+        if self.policy.admit(k, block) {
+            self.cache.lock().unwrap().insert(*k, block);
+        }
+    }
+}
+
+impl<BS, P> Blockstore for CachedBlockstore<BS, P>
+where
+    BS: Blockstore,
+    P: AdmissionPolicy,
+{
+    fn get(&self, k: &Cid) -> Result<Option<Vec<u8>>> {
+        {
+            let mut cache = self.cache.lock().unwrap();
+            if let Some(block) = cache.get(k) {
+                cache.stats.hits += 1;
+                return Ok(Some(block));
+            }
+            cache.stats.misses += 1;
+        }
+        let block = self.base.get(k)?;
+        if let Some(block) = &block {
+            self.admit(k, block);
+        }
+        Ok(block)
+    }
+
+    /// Serves the cached blocks, and gets the others from the inner store at once.
+    fn get_many<I>(&self, keys: I) -> Result<Vec<Option<Vec<u8>>>>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = Cid>,
+    {
+        let keys: Vec<_> = keys.into_iter().collect();
+        let mut blocks = Vec::with_capacity(keys.len());
+        let mut misses = Vec::new();
+        {
+            let mut cache = self.cache.lock().unwrap();
+            for (i, k) in keys.iter().enumerate() {
+                let block = cache.get(k);
+                if block.is_some() {
+                    cache.stats.hits += 1;
+                } else {
+                    cache.stats.misses += 1;
+                    misses.push(i);
+                }
+                blocks.push(block);
+            }
+        }
+        if !misses.is_empty() {
+            let found = self.base.get_many(misses.iter().map(|&i| keys[i]))?;
+            for (i, block) in misses.into_iter().zip(found) {
+                if let Some(block) = &block {
+                    self.admit(&keys[i], block);
+                }
+                blocks[i] = block;
+            }
+        }
+        Ok(blocks)
+    }
+
+    fn has(&self, k: &Cid) -> Result<bool> {
+        if self.cache.lock().unwrap().blocks.contains_key(k) {
+            return Ok(true);
+        }
+        self.base.has(k)
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> Result<()> {
+        self.base.put_keyed(k, block)?;
+        self.admit(k, block);
+        Ok(())
+    }
+
+    fn put_many_keyed<D, I>(&self, blocks: I) -> Result<()>
+    where
+        Self: Sized,
+        D: AsRef<[u8]>,
+        I: IntoIterator<Item = (Cid, D)>,
+    {
+        let blocks: Vec<_> = blocks.into_iter().collect();
+        self.base
+            .put_many_keyed(blocks.iter().map(|(k, block)| (*k, block.as_ref())))?;
+        for (k, block) in &blocks {
+            self.admit(k, block.as_ref());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use multihash::Code;
+
+    use super::*;
+    use crate::tracking::TrackingBlockstore;
+    use crate::{Block, MemoryBlockstore};
+
+    #[test]
+    fn cached_store() {
+        let mem = TrackingBlockstore::new(MemoryBlockstore::default());
+        let blocks: Vec<_> = [&b"foo"[..], b"bar", b"quux"]
+            .into_iter()
+            .map(|data| {
+                let block = Block::new(0x55, data);
+                mem.put(Code::Blake2b256, &block).unwrap()
+            })
+            .collect();
+
+        // Fits "foo" and "bar", or "quux".
+        let store = CachedBlockstore::new(&mem, 6);
+        store.get(&blocks[0]).unwrap();
+        store.get(&blocks[1]).unwrap();
+        // Served from the cache.
+        assert_eq!(store.get(&blocks[0]).unwrap().as_deref(), Some(&b"foo"[..]));
+        assert_eq!(mem.stats.borrow().r, 2);
+        assert_eq!(
+            store.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 2,
+                blocks: 2,
+                bytes: 6,
+            }
+        );
+
+        // Evicts "bar" (the least recently used), then "foo".
+        store.get(&blocks[2]).unwrap();
+        assert_eq!(store.stats().blocks, 1);
+        store.get(&blocks[1]).unwrap();
+        assert_eq!(mem.stats.borrow().r, 4);
+        assert_eq!(store.stats().bytes, 3);
+
+        // Writes go through the cache.
+        let store = store.with_policy(MaxBlockSize(3));
+        store.clear();
+        let cid = store
+            .put(Code::Blake2b256, &Block::new(0x55, b"baz"))
+            .unwrap();
+        assert!(mem.has(&cid).unwrap());
+        store.put_keyed(&blocks[2], b"quux").unwrap();
+        assert_eq!(store.stats().blocks, 1);
+        store.get(&cid).unwrap();
+        assert_eq!(store.stats().hits, 2);
+
+        // Bulk reads only forward the misses, and cache them.
+        let reads = mem.stats.borrow().r;
+        let got = store.get_many([cid, blocks[0]]).unwrap();
+        assert_eq!(got, [Some(b"baz".to_vec()), Some(b"foo".to_vec())]);
+        assert_eq!(mem.stats.borrow().r, reads + 1);
+        assert_eq!((store.stats().hits, store.stats().misses), (3, 5));
+        assert_eq!(store.stats().blocks, 2);
+    }
+}
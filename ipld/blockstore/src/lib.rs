@@ -6,6 +6,9 @@ use std::sync::Arc;
 use anyhow::Result;
 use cid::{multihash, Cid};
 
+pub mod cached;
+pub mod metered;
+pub mod rc;
 pub mod tracking;
 
 mod memory;
@@ -14,6 +17,14 @@ pub use memory::MemoryBlockstore;
 mod block;
 pub use block::*;
 
+mod links;
+pub use links::{scan_for_links, IDENTITY_HASH};
+
+#[cfg(feature = "async")]
+mod asynchronous;
+#[cfg(feature = "async")]
+pub use asynchronous::{AsyncBlockstore, BlockOn, BlockingBlockstore, LocalExecutor};
+
 /// An IPLD blockstore suitable for injection into the FVM.
 ///
 /// The cgo blockstore adapter implements this trait.
@@ -82,6 +93,17 @@ pub trait Blockstore {
         }
         Ok(())
     }
+
+    /// Bulk-get blocks from the blockstore, in the order of the keys.
+    ///
+    /// By default, this defers to get.
+    fn get_many<I>(&self, keys: I) -> Result<Vec<Option<Vec<u8>>>>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = Cid>,
+    {
+        keys.into_iter().map(|k| self.get(&k)).collect()
+    }
 }
 
 pub trait Buffered: Blockstore {
@@ -130,6 +152,14 @@ macro_rules! impl_blockstore {
                 {
                     (**self).put_many_keyed(blocks)
                 }
+
+                fn get_many<I>(&self, keys: I) -> Result<Vec<Option<Vec<u8>>>>
+                where
+                    Self: Sized,
+                    I: IntoIterator<Item = Cid>,
+                {
+                    (**self).get_many(keys)
+                }
             }
         )+
     }
@@ -0,0 +1,120 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Scanning of DAG-CBOR blocks for links, without decoding them.
+
+use anyhow::{anyhow, Result};
+use cid::Cid;
+
+/// Multihash code for the identity hash function. Blocks hashed with it are inlined in their CIDs.
+pub const IDENTITY_HASH: u64 = 0x0;
+
+/// Given a CBOR encoded buffer, returns a tuple of the major type of the next CBOR object, and
+/// its argument (the value, the length, or the number of elements to read). More info on this
+/// can be found in Appendix C. of RFC 7049 which defines the CBOR specification.
+fn read_header(buf: &mut &[u8]) -> Result<(u8, u64)> {
+    fn take<'a>(buf: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+        if buf.len() < n {
+            return Err(anyhow!("unexpected end of cbor stream"));
+        }
+        let (taken, rest) = buf.split_at(n);
+        *buf = rest;
+        Ok(taken)
+    }
+
+    let first = take(buf, 1)?[0];
+    let maj = first >> 5;
+    let val = match first & 0x1f {
+        low @ ..=23 => low.into(),
+        24 => take(buf, 1)?[0].into(),
+        25 => u16::from_be_bytes(take(buf, 2)?.try_into().unwrap()).into(),
+        26 => u32::from_be_bytes(take(buf, 4)?.try_into().unwrap()).into(),
+        27 => u64::from_be_bytes(take(buf, 8)?.try_into().unwrap()),
+        _ => return Err(anyhow!("invalid cbor header")),
+    };
+    Ok((maj, val))
+}
+
+/// Collects the links (tag 42 CIDs) in a DAG-CBOR block into `out`, in order. This is much faster
+/// than decoding the block and walking the decoded IPLD.
+///
+/// Links to blocks inlined with [`IDENTITY_HASH`] are collected like any other link, it's up to
+/// the caller to follow them (or not).
+pub fn scan_for_links(mut buf: &[u8], out: &mut Vec<Cid>) -> Result<()> {
+    let mut remaining = 1u64;
+    while remaining > 0 {
+        let (maj, extra) = read_header(&mut buf)?;
+        match maj {
+            // MajUnsignedInt, MajNegativeInt, MajOther
+            0 | 1 | 7 => {}
+            // MajByteString, MajTextString
+            2 | 3 => {
+                if extra > buf.len() as u64 {
+                    return Err(anyhow!("unexpected end of cbor stream"));
+                }
+                buf = &buf[extra as usize..];
+            }
+            // MajTag, referring to a CID
+            6 if extra == 42 => {
+                let (maj, extra) = read_header(&mut buf)?;
+                // The actual CID is expected to be a byte string
+                if maj != 2 {
+                    return Err(anyhow!("expected cbor type byte string in input"));
+                }
+                if extra > buf.len() as u64 {
+                    return Err(anyhow!("unexpected end of cbor stream"));
+                }
+                if buf.first() != Some(&0u8) {
+                    return Err(anyhow!("DagCBOR CID does not start with a 0x byte"));
+                }
+                let cid_buf;
+                (cid_buf, buf) = buf.split_at(extra as usize);
+                out.push(Cid::try_from(&cid_buf[1..])?);
+            }
+            // Other tags are followed by the tagged value.
+            6 => remaining += 1,
+            // MajArray
+            4 => remaining = remaining.saturating_add(extra),
+            // MajMap
+            _ => remaining = remaining.saturating_add(extra.saturating_mul(2)),
+        }
+        remaining -= 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use multihash::{Code, MultihashDigest};
+
+    use super::*;
+
+    #[test]
+    fn scan_links() {
+        let a = Cid::new_v1(0x55, Code::Blake2b256.digest(b"a"));
+        let b = Cid::new_v1(0x71, Code::Blake2b256.digest(b"b"));
+        let link = |cid: &Cid| {
+            let cid = cid.to_bytes();
+            let mut data = vec![0xd8, 42, 0x58, cid.len() as u8 + 1, 0];
+            data.extend(cid);
+            data
+        };
+        // [a, 1, "text", h'bytes', [b, -1], {"key": a}]
+        let mut block = vec![0x86];
+        block.extend(link(&a));
+        block.push(0x01);
+        block.extend(b"\x64text");
+        block.extend(b"\x45bytes");
+        block.push(0x82);
+        block.extend(link(&b));
+        block.push(0x20);
+        block.extend(b"\xa1\x63key");
+        block.extend(link(&a));
+
+        let mut links = Vec::new();
+        scan_for_links(&block, &mut links).unwrap();
+        assert_eq!(links, [a, b, a]);
+
+        // Truncated blocks are rejected.
+        assert!(scan_for_links(&block[..block.len() - 1], &mut Vec::new()).is_err());
+    }
+}
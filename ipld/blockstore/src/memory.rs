@@ -1,16 +1,41 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
-use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::RwLock;
 
 use anyhow::Result;
 use cid::Cid;
 
 use super::Blockstore;
 
-#[derive(Debug, Default, Clone)]
+/// The number of shards of a [MemoryBlockstore].
+const SHARDS: usize = 16;
+
+type Shard = RwLock<HashMap<Cid, Vec<u8>>>;
+
+/// An in-memory blockstore, which can be shared between threads. Blocks are sharded by CID, each
+/// shard having its own lock, so that threads accessing different blocks rarely contend.
+#[derive(Debug)]
 pub struct MemoryBlockstore {
-    blocks: RefCell<HashMap<Cid, Vec<u8>>>,
+    shards: [Shard; SHARDS],
+}
+
+impl Default for MemoryBlockstore {
+    fn default() -> Self {
+        Self {
+            shards: std::array::from_fn(|_| Default::default()),
+        }
+    }
+}
+
+impl Clone for MemoryBlockstore {
+    fn clone(&self) -> Self {
+        Self {
+            shards: std::array::from_fn(|i| RwLock::new(self.shards[i].read().unwrap().clone())),
+        }
+    }
 }
 
 impl MemoryBlockstore {
@@ -20,21 +45,135 @@ impl MemoryBlockstore {
 
     /// Copy all blocks from this blockstore into the target blockstore.
     pub fn copy_to(&self, other: &impl Blockstore) -> Result<()> {
-        other.put_many_keyed(self.blocks.borrow().iter().map(|(&k, v)| (k, v)))
+        for shard in &self.shards {
+            other.put_many_keyed(shard.read().unwrap().iter().map(|(&k, v)| (k, v)))?;
+        }
+        Ok(())
+    }
+
+    fn shard(&self, k: &Cid) -> &Shard {
+        &self.shards[shard_index(k)]
     }
 }
 
+fn shard_index(k: &Cid) -> usize {
+    let mut hasher = DefaultHasher::new();
+    k.hash(&mut hasher);
+    hasher.finish() as usize % SHARDS
+}
+
 impl Blockstore for MemoryBlockstore {
     fn has(&self, k: &Cid) -> Result<bool> {
-        Ok(self.blocks.borrow().contains_key(k))
+        Ok(self.shard(k).read().unwrap().contains_key(k))
     }
 
     fn get(&self, k: &Cid) -> Result<Option<Vec<u8>>> {
-        Ok(self.blocks.borrow().get(k).cloned())
+        Ok(self.shard(k).read().unwrap().get(k).cloned())
     }
 
     fn put_keyed(&self, k: &Cid, block: &[u8]) -> Result<()> {
-        self.blocks.borrow_mut().insert(*k, block.into());
+        self.shard(k).write().unwrap().insert(*k, block.into());
         Ok(())
     }
+
+    /// Locks each shard once.
+    fn put_many_keyed<D, I>(&self, blocks: I) -> Result<()>
+    where
+        Self: Sized,
+        D: AsRef<[u8]>,
+        I: IntoIterator<Item = (Cid, D)>,
+    {
+        let mut batches: [Vec<(Cid, D)>; SHARDS] = std::array::from_fn(|_| Vec::new());
+        for (k, block) in blocks {
+            batches[shard_index(&k)].push((k, block));
+        }
+        for (shard, batch) in self.shards.iter().zip(batches) {
+            if !batch.is_empty() {
+                let mut shard = shard.write().unwrap();
+                shard.extend(batch.into_iter().map(|(k, b)| (k, b.as_ref().into())));
+            }
+        }
+        Ok(())
+    }
+
+    /// Locks each shard once.
+    fn get_many<I>(&self, keys: I) -> Result<Vec<Option<Vec<u8>>>>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = Cid>,
+    {
+        let keys: Vec<_> = keys.into_iter().collect();
+        let mut batches: [Vec<usize>; SHARDS] = std::array::from_fn(|_| Vec::new());
+        for (i, k) in keys.iter().enumerate() {
+            batches[shard_index(k)].push(i);
+        }
+        let mut blocks = vec![None; keys.len()];
+        for (shard, batch) in self.shards.iter().zip(batches) {
+            if !batch.is_empty() {
+                let shard = shard.read().unwrap();
+                for i in batch {
+                    blocks[i] = shard.get(&keys[i]).cloned();
+                }
+            }
+        }
+        Ok(blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use multihash::Code;
+
+    use super::*;
+    use crate::Block;
+
+    #[test]
+    fn concurrent() {
+        let store = Arc::new(MemoryBlockstore::new());
+        let threads: Vec<_> = (0..4u8)
+            .map(|i| {
+                let store = store.clone();
+                std::thread::spawn(move || {
+                    (0..100u8)
+                        .map(|j| store.put(Code::Blake2b256, &Block::new(0x55, [i, j])))
+                        .collect::<Result<Vec<_>>>()
+                        .unwrap()
+                })
+            })
+            .collect();
+        let cids: Vec<_> = threads
+            .into_iter()
+            .flat_map(|t| t.join().unwrap())
+            .collect();
+
+        let copy = MemoryBlockstore::new();
+        store.clone().copy_to(&copy).unwrap();
+        for cid in &cids {
+            assert!(copy.has(cid).unwrap());
+        }
+        assert_eq!(
+            copy.shards
+                .iter()
+                .map(|s| s.read().unwrap().len())
+                .sum::<usize>(),
+            400
+        );
+    }
+
+    #[test]
+    fn bulk() {
+        let store = MemoryBlockstore::new();
+        let blocks: Vec<_> = (0..10u8).map(|i| Block::new(0x55, [i])).collect();
+        store
+            .put_many(blocks.iter().map(|b| (Code::Blake2b256, b.into())))
+            .unwrap();
+        let missing = Block::new(0x55, [10]).cid(Code::Blake2b256);
+        let keys = blocks.iter().map(|b| b.cid(Code::Blake2b256));
+        let found = store.get_many(keys.chain([missing])).unwrap();
+        assert_eq!(found.len(), 11);
+        assert_eq!(found[3].as_deref(), Some(&[3][..]));
+        assert_eq!(found[10], None);
+    }
 }
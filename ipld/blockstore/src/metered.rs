@@ -0,0 +1,379 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use cid::multihash::{self, Code};
+use cid::Cid;
+
+use super::{Block, Blockstore};
+
+/// The upper bounds of the latency histogram buckets, see [Histogram].
+pub const LATENCY_BUCKETS: [Duration; 11] = [
+    Duration::from_micros(10),
+    Duration::from_micros(50),
+    Duration::from_micros(100),
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+];
+
+/// A blockstore operation, see [MeteredBlockstore].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    Get,
+    GetMany,
+    Has,
+    /// `put` or `put_keyed`.
+    Put,
+    /// `put_many` or `put_many_keyed`.
+    PutMany,
+}
+
+/// A latency histogram, in the format expected by prometheus.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Histogram {
+    /// The cumulative bucket counts: `buckets[i]` is the number of calls that took at most
+    /// `LATENCY_BUCKETS[i]`. The total number of calls is in [OpStats::calls].
+    pub buckets: [u64; LATENCY_BUCKETS.len()],
+    /// The total time spent in all calls.
+    pub sum: Duration,
+}
+
+/// Stats for one kind of operation, see [MeteredStats].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OpStats {
+    /// Number of calls, including failed calls.
+    pub calls: u64,
+    /// Number of blocks found (for reads) or written (for writes).
+    pub blocks: u64,
+    /// Bytes read or written.
+    pub bytes: u64,
+    pub latency: Histogram,
+}
+
+/// A snapshot of the stats of a [MeteredBlockstore].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MeteredStats {
+    pub get: OpStats,
+    pub get_many: OpStats,
+    pub has: OpStats,
+    pub put: OpStats,
+    pub put_many: OpStats,
+}
+
+#[derive(Debug, Default)]
+struct OpCounters {
+    calls: AtomicU64,
+    blocks: AtomicU64,
+    bytes: AtomicU64,
+    /// Non-cumulative bucket counts, the last bucket counting the calls above all bounds.
+    buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    nanos: AtomicU64,
+}
+
+impl OpCounters {
+    fn record(&self, blocks: usize, bytes: usize, latency: Duration) {
+        let bucket = LATENCY_BUCKETS.partition_point(|bound| *bound < latency);
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.blocks.fetch_add(blocks as u64, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.nanos
+            .fetch_add(latency.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> OpStats {
+        let mut latency = Histogram {
+            sum: Duration::from_nanos(self.nanos.load(Ordering::Relaxed)),
+            ..Default::default()
+        };
+        let mut total = 0;
+        for (cumulative, count) in latency.buckets.iter_mut().zip(&self.buckets) {
+            total += count.load(Ordering::Relaxed);
+            *cumulative = total;
+        }
+        OpStats {
+            calls: self.calls.load(Ordering::Relaxed),
+            blocks: self.blocks.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            latency,
+        }
+    }
+}
+
+/// The counters shared by a [MeteredBlockstore] and its clones. Counters are updated
+/// independently, so a snapshot taken during an operation may be slightly inconsistent.
+#[derive(Debug, Default)]
+pub struct BlockstoreMetrics {
+    get: OpCounters,
+    get_many: OpCounters,
+    has: OpCounters,
+    put: OpCounters,
+    put_many: OpCounters,
+}
+
+impl BlockstoreMetrics {
+    /// Returns the stats recorded so far.
+    pub fn snapshot(&self) -> MeteredStats {
+        MeteredStats {
+            get: self.get.snapshot(),
+            get_many: self.get_many.snapshot(),
+            has: self.has.snapshot(),
+            put: self.put.snapshot(),
+            put_many: self.put_many.snapshot(),
+        }
+    }
+
+    fn counters(&self, op: Op) -> &OpCounters {
+        match op {
+            Op::Get => &self.get,
+            Op::GetMany => &self.get_many,
+            Op::Has => &self.has,
+            Op::Put => &self.put,
+            Op::PutMany => &self.put_many,
+        }
+    }
+}
+
+/// Called after each operation of a [MeteredBlockstore] with the operation, the number of blocks
+/// and bytes read or written, and the time the operation took.
+pub type Callback = dyn Fn(Op, usize, usize, Duration) + Send + Sync;
+
+/// Wrapper around `Blockstore` counting the operations on, and the data read from and written
+/// to, the wrapped store, and the time spent in each operation, so operators can monitor the
+/// storage load. Stats are read with [MeteredBlockstore::metrics] (which can be kept after moving
+/// the store into the FVM), or reported as they happen with [MeteredBlockstore::with_callback].
+#[derive(Clone)]
+pub struct MeteredBlockstore<BS> {
+    base: BS,
+    metrics: Arc<BlockstoreMetrics>,
+    callback: Option<Arc<Callback>>,
+}
+
+impl<BS> std::fmt::Debug for MeteredBlockstore<BS>
+where
+    BS: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MeteredBlockstore")
+            .field("base", &self.base)
+            .field("metrics", &self.metrics)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<BS> MeteredBlockstore<BS>
+where
+    BS: Blockstore,
+{
+    pub fn new(base: BS) -> Self {
+        Self {
+            base,
+            metrics: Default::default(),
+            callback: None,
+        }
+    }
+
+    /// Calls the given callback after each operation.
+    pub fn with_callback(
+        mut self,
+        callback: impl Fn(Op, usize, usize, Duration) + Send + Sync + 'static,
+    ) -> Self {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Returns the (shared) counters of this store.
+    pub fn metrics(&self) -> Arc<BlockstoreMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Returns the stats recorded so far.
+    pub fn stats(&self) -> MeteredStats {
+        self.metrics.snapshot()
+    }
+
+    pub fn into_inner(self) -> BS {
+        self.base
+    }
+
+    /// Runs and records an operation. `size` returns the number of blocks and bytes read or
+    /// written by a successful operation.
+    fn record<T>(
+        &self,
+        op: Op,
+        f: impl FnOnce() -> Result<T>,
+        size: impl FnOnce(&T) -> (usize, usize),
+    ) -> Result<T> {
+        let start = Instant::now();
+        let res = f();
+        let latency = start.elapsed();
+        let (blocks, bytes) = res.as_ref().map(size).unwrap_or_default();
+        self.metrics.counters(op).record(blocks, bytes, latency);
+        if let Some(callback) = &self.callback {
+            callback(op, blocks, bytes, latency);
+        }
+        res
+    }
+}
+
+impl<BS> Blockstore for MeteredBlockstore<BS>
+where
+    BS: Blockstore,
+{
+    fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>> {
+        self.record(
+            Op::Get,
+            || self.base.get(cid),
+            |block| block.as_ref().map_or((0, 0), |block| (1, block.len())),
+        )
+    }
+
+    fn get_many<I>(&self, keys: I) -> Result<Vec<Option<Vec<u8>>>>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = Cid>,
+    {
+        self.record(
+            Op::GetMany,
+            || self.base.get_many(keys),
+            |blocks| {
+                blocks
+                    .iter()
+                    .flatten()
+                    .fold((0, 0), |(count, bytes), block| {
+                        (count + 1, bytes + block.len())
+                    })
+            },
+        )
+    }
+
+    fn has(&self, cid: &Cid) -> Result<bool> {
+        self.record(Op::Has, || self.base.has(cid), |&has| (has as usize, 0))
+    }
+
+    fn put<D>(&self, code: Code, block: &Block<D>) -> Result<Cid>
+    where
+        D: AsRef<[u8]>,
+    {
+        self.record(Op::Put, || self.base.put(code, block), |_| (1, block.len()))
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> Result<()> {
+        self.record(
+            Op::Put,
+            || self.base.put_keyed(k, block),
+            |_| (1, block.len()),
+        )
+    }
+
+    fn put_many<D, I>(&self, blocks: I) -> Result<()>
+    where
+        Self: Sized,
+        D: AsRef<[u8]>,
+        I: IntoIterator<Item = (multihash::Code, Block<D>)>,
+    {
+        let (count, bytes) = (Cell::new(0), Cell::new(0));
+        let blocks = blocks.into_iter().inspect(|(_, b)| {
+            count.set(count.get() + 1);
+            bytes.set(bytes.get() + b.len());
+        });
+        self.record(
+            Op::PutMany,
+            || self.base.put_many(blocks),
+            |_| (count.get(), bytes.get()),
+        )
+    }
+
+    fn put_many_keyed<D, I>(&self, blocks: I) -> Result<()>
+    where
+        Self: Sized,
+        D: AsRef<[u8]>,
+        I: IntoIterator<Item = (Cid, D)>,
+    {
+        let (count, bytes) = (Cell::new(0), Cell::new(0));
+        let blocks = blocks.into_iter().inspect(|(_, b)| {
+            count.set(count.get() + 1);
+            bytes.set(bytes.get() + b.as_ref().len());
+        });
+        self.record(
+            Op::PutMany,
+            || self.base.put_many_keyed(blocks),
+            |_| (count.get(), bytes.get()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::MemoryBlockstore;
+
+    #[test]
+    fn metered_store() {
+        let ops = Arc::new(Mutex::new(Vec::new()));
+        let store = MeteredBlockstore::new(MemoryBlockstore::default()).with_callback({
+            let ops = ops.clone();
+            move |op, blocks, bytes, _| ops.lock().unwrap().push((op, blocks, bytes))
+        });
+        let metrics = store.metrics();
+
+        let block = Block::new(0x55, &b"foobar"[..]);
+        assert_eq!(store.get(&block.cid(Code::Blake2b256)).unwrap(), None);
+        let cid = store.put(Code::Blake2b256, &block).unwrap();
+        assert!(store.has(&cid).unwrap());
+        store.get(&cid).unwrap();
+        assert_eq!(
+            store.get_many([cid, block.cid(Code::Sha2_256)]).unwrap(),
+            [Some(block.data.to_vec()), None]
+        );
+        store
+            .put_many([
+                (Code::Blake2b256, Block::new(0x55, &b"foo"[..])),
+                (Code::Blake2b256, block),
+            ])
+            .unwrap();
+
+        assert_eq!(
+            *ops.lock().unwrap(),
+            [
+                (Op::Get, 0, 0),
+                (Op::Put, 1, 6),
+                (Op::Has, 1, 0),
+                (Op::Get, 1, 6),
+                (Op::GetMany, 1, 6),
+                (Op::PutMany, 2, 9),
+            ]
+        );
+        let stats = metrics.snapshot();
+        assert_eq!(stats, store.stats());
+        assert_eq!(
+            (stats.get.calls, stats.get.blocks, stats.get.bytes),
+            (2, 1, 6)
+        );
+        assert_eq!(
+            (
+                stats.get_many.calls,
+                stats.get_many.blocks,
+                stats.get_many.bytes
+            ),
+            (1, 1, 6)
+        );
+        assert_eq!((stats.put_many.calls, stats.put_many.blocks), (1, 2));
+        // Both gets took at most a second, and the buckets are cumulative.
+        assert_eq!(stats.get.latency.buckets[LATENCY_BUCKETS.len() - 1], 2);
+        assert!(stats.get.latency.buckets.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
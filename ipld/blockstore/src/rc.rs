@@ -0,0 +1,280 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{anyhow, Result};
+use cid::Cid;
+
+use super::{Blockstore, IDENTITY_HASH};
+
+/// The DAG-CBOR codec, the only codec whose links are counted.
+const DAG_CBOR: u64 = 0x71;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Refs {
+    /// The number of roots and stored blocks referencing the block.
+    count: u64,
+    /// Whether the block was written through the store.
+    stored: bool,
+}
+
+/// Wrapper around `Blockstore` counting the references to the blocks written through it: one
+/// per root (see [RcBlockstore::add_root]) and one per link from another written block. Blocks
+/// without references are garbage, and [RcBlockstore::sweep] deletes them, releasing their own
+/// links, so embedders can collect the old state incrementally: add the new state root, remove
+/// the old one, and sweep.
+///
+/// Only the links of DAG-CBOR blocks are counted, as the FVM only stores links in DAG-CBOR. Links
+/// in inlined (identity hash) blocks count as links from the block inlining them. Blocks already
+/// in the inner store before it was wrapped aren't counted, and are never swept.
+#[derive(Debug)]
+pub struct RcBlockstore<BS> {
+    base: BS,
+    refs: RefCell<HashMap<Cid, Refs>>,
+    /// The stored blocks without references.
+    garbage: RefCell<BTreeSet<Cid>>,
+}
+
+impl<BS> RcBlockstore<BS>
+where
+    BS: Blockstore,
+{
+    pub fn new(base: BS) -> Self {
+        Self {
+            base,
+            refs: Default::default(),
+            garbage: Default::default(),
+        }
+    }
+
+    /// Returns the number of references to the block.
+    pub fn ref_count(&self, k: &Cid) -> u64 {
+        self.refs.borrow().get(k).map_or(0, |refs| refs.count)
+    }
+
+    /// Returns the number of blocks that would be (directly) deleted by a sweep.
+    pub fn garbage_len(&self) -> usize {
+        self.garbage.borrow().len()
+    }
+
+    /// Adds a root, referencing the block (which doesn't need to be written yet) until the root
+    /// is removed. A block can be added as a root several times.
+    pub fn add_root(&self, root: &Cid) {
+        self.incref(*root);
+    }
+
+    /// Removes a root added with [RcBlockstore::add_root]. The blocks only reachable from it are
+    /// deleted by the next sweep.
+    pub fn remove_root(&self, root: &Cid) -> Result<()> {
+        match self.refs.borrow().get(root) {
+            Some(refs) if refs.count > 0 => {}
+            _ => return Err(anyhow!("{} is not referenced", root)),
+        }
+        self.decref(root);
+        Ok(())
+    }
+
+    /// Deletes the blocks without references, with the given function, and the blocks only
+    /// they referenced, returning the number of blocks deleted. Blocks are deleted before the
+    /// blocks they link to.
+    ///
+    /// The inner `Blockstore` can't delete blocks, hence the function. If it fails, the sweep
+    /// stops, and the block and all the remaining garbage are kept until the next sweep.
+    pub fn sweep(&self, mut delete: impl FnMut(&Cid) -> Result<()>) -> Result<usize> {
+        let mut deleted = 0;
+        loop {
+            let Some(k) = self.garbage.borrow().first().copied() else {
+                return Ok(deleted);
+            };
+            let mut links = Vec::new();
+            if k.codec() == DAG_CBOR {
+                let block = self
+                    .base
+                    .get(&k)?
+                    .ok_or_else(|| anyhow!("missing garbage block {}", k))?;
+                scan_for_links(&block, &mut links)?;
+            }
+            delete(&k)?;
+            deleted += 1;
+            self.garbage.borrow_mut().remove(&k);
+            self.refs.borrow_mut().remove(&k);
+            for link in &links {
+                self.decref(link);
+            }
+        }
+    }
+
+    pub fn into_inner(self) -> BS {
+        self.base
+    }
+
+    fn incref(&self, k: Cid) {
+        let mut refs = self.refs.borrow_mut();
+        let refs = refs.entry(k).or_default();
+        if refs.count == 0 && refs.stored {
+            self.garbage.borrow_mut().remove(&k);
+        }
+        refs.count += 1;
+    }
+
+    fn decref(&self, k: &Cid) {
+        let mut all = self.refs.borrow_mut();
+        let Some(refs) = all.get_mut(k) else { return };
+        refs.count -= 1;
+        if refs.count == 0 {
+            if refs.stored {
+                self.garbage.borrow_mut().insert(*k);
+            } else {
+                all.remove(k);
+            }
+        }
+    }
+
+    /// Starts counting a block written through the store, and its links.
+    fn track(&self, k: &Cid, block: &[u8]) -> Result<()> {
+        let mut links = Vec::new();
+        if k.codec() == DAG_CBOR {
+            scan_for_links(block, &mut links)?;
+        }
+        {
+            let mut refs = self.refs.borrow_mut();
+            let refs = refs.entry(*k).or_default();
+            if refs.stored {
+                return Ok(());
+            }
+            refs.stored = true;
+            if refs.count == 0 {
+                self.garbage.borrow_mut().insert(*k);
+            }
+        }
+        for link in links {
+            self.incref(link);
+        }
+        Ok(())
+    }
+}
+
+impl<BS> Blockstore for RcBlockstore<BS>
+where
+    BS: Blockstore,
+{
+    fn get(&self, k: &Cid) -> Result<Option<Vec<u8>>> {
+        self.base.get(k)
+    }
+
+    fn get_many<I>(&self, keys: I) -> Result<Vec<Option<Vec<u8>>>>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = Cid>,
+    {
+        self.base.get_many(keys)
+    }
+
+    fn has(&self, k: &Cid) -> Result<bool> {
+        self.base.has(k)
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> Result<()> {
+        self.base.put_keyed(k, block)?;
+        self.track(k, block)
+    }
+
+    fn put_many_keyed<D, I>(&self, blocks: I) -> Result<()>
+    where
+        Self: Sized,
+        D: AsRef<[u8]>,
+        I: IntoIterator<Item = (Cid, D)>,
+    {
+        let blocks: Vec<_> = blocks.into_iter().collect();
+        self.base
+            .put_many_keyed(blocks.iter().map(|(k, block)| (*k, block.as_ref())))?;
+        for (k, block) in &blocks {
+            self.track(k, block.as_ref())?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects the links in a DAG-CBOR block, replacing links to inlined DAG-CBOR blocks with their
+/// own links.
+fn scan_for_links(block: &[u8], out: &mut Vec<Cid>) -> Result<()> {
+    let mut links = Vec::new();
+    crate::scan_for_links(block, &mut links)?;
+    for link in links {
+        if link.hash().code() != IDENTITY_HASH {
+            out.push(link);
+        } else if link.codec() == DAG_CBOR {
+            scan_for_links(link.hash().digest(), out)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use multihash::Code;
+
+    use super::*;
+    use crate::{Block, MemoryBlockstore};
+
+    /// Encodes a DAG-CBOR list of links.
+    fn node(links: &[Cid]) -> Block<Vec<u8>> {
+        let mut data = vec![0x80 | links.len() as u8];
+        for link in links {
+            let cid = link.to_bytes();
+            data.extend([0xd8, 42, 0x58, cid.len() as u8 + 1, 0]);
+            data.extend(cid);
+        }
+        Block::new(DAG_CBOR, data)
+    }
+
+    #[test]
+    fn rc_store() {
+        let store = RcBlockstore::new(MemoryBlockstore::default());
+        let leaf = store
+            .put(Code::Blake2b256, &Block::new(0x55, b"leaf"))
+            .unwrap();
+        let shared = store.put(Code::Blake2b256, &node(&[leaf])).unwrap();
+        let old = store.put(Code::Blake2b256, &node(&[shared, leaf])).unwrap();
+        store.add_root(&old);
+        assert_eq!(store.ref_count(&leaf), 2);
+        assert_eq!(store.garbage_len(), 0);
+
+        // The new root is added before being written, and links to the shared block twice.
+        let new = node(&[shared, shared]).cid(Code::Blake2b256);
+        store.add_root(&new);
+        store
+            .put(Code::Blake2b256, &node(&[shared, shared]))
+            .unwrap();
+        assert_eq!(store.ref_count(&shared), 3);
+
+        store.remove_root(&old).unwrap();
+        assert!(store.remove_root(&old).is_err());
+        let mut deleted = Vec::new();
+        let count = store
+            .sweep(|k| {
+                deleted.push(*k);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!((count, &*deleted), (1, &[old][..]));
+        assert_eq!(store.ref_count(&shared), 2);
+        assert_eq!(store.ref_count(&leaf), 1);
+
+        // A failed deletion keeps the garbage.
+        store.remove_root(&new).unwrap();
+        assert!(store.sweep(|_| Err(anyhow!("failed"))).is_err());
+        assert_eq!(store.garbage_len(), 1);
+        assert_eq!(store.sweep(|_| Ok(())).unwrap(), 3);
+        assert_eq!(store.ref_count(&leaf), 0);
+        assert_eq!(store.garbage_len(), 0);
+
+        // Blocks written without a root are garbage.
+        store
+            .put(Code::Blake2b256, &Block::new(0x55, b"leaf"))
+            .unwrap();
+        assert_eq!(store.garbage_len(), 1);
+    }
+}
@@ -3,6 +3,7 @@
 //#![cfg(feature = "tracking")]
 
 use std::cell::RefCell;
+use std::collections::BTreeSet;
 
 use anyhow::Result;
 use cid::multihash::{self, Code};
@@ -24,12 +25,23 @@ pub struct BSStats {
     pub bw: usize,
 }
 
+/// The CIDs read and written through a [TrackingBlockstore].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AccessSet {
+    /// CIDs read (with `get` or `has`), including CIDs of blocks that weren't found.
+    pub reads: BTreeSet<Cid>,
+    /// CIDs written.
+    pub writes: BTreeSet<Cid>,
+}
+
 /// Wrapper around `Blockstore` to tracking reads and writes for verification.
-/// This struct should only be used for testing.
+/// This struct should only be used for testing, or to capture the blocks accessed by an execution
+/// (e.g., to detect conflicts or generate witnesses), see [TrackingBlockstore::accesses].
 #[derive(Debug)]
 pub struct TrackingBlockstore<BS> {
     base: BS,
     pub stats: RefCell<BSStats>,
+    accesses: RefCell<AccessSet>,
 }
 
 impl<BS> TrackingBlockstore<BS>
@@ -40,8 +52,23 @@ where
         Self {
             base,
             stats: Default::default(),
+            accesses: Default::default(),
         }
     }
+
+    /// Returns the CIDs read and written so far.
+    pub fn accesses(&self) -> AccessSet {
+        self.accesses.borrow().clone()
+    }
+
+    /// Returns the CIDs read and written so far, and starts recording a new set.
+    pub fn take_accesses(&self) -> AccessSet {
+        self.accesses.take()
+    }
+
+    pub fn into_inner(self) -> BS {
+        self.base
+    }
 }
 
 impl<BS> Blockstore for TrackingBlockstore<BS>
@@ -51,14 +78,32 @@ where
     fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>> {
         let mut stats = self.stats.borrow_mut();
         stats.r += 1;
+        self.accesses.borrow_mut().reads.insert(*cid);
         let bytes = self.base.get(cid)?;
         if let Some(bytes) = &bytes {
             stats.br += bytes.len();
         }
         Ok(bytes)
     }
+
+    fn get_many<I>(&self, keys: I) -> Result<Vec<Option<Vec<u8>>>>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = Cid>,
+    {
+        let mut stats = self.stats.borrow_mut();
+        let mut accesses = self.accesses.borrow_mut();
+        let blocks = self.base.get_many(keys.into_iter().inspect(|k| {
+            stats.r += 1;
+            accesses.reads.insert(*k);
+        }))?;
+        stats.br += blocks.iter().flatten().map(Vec::len).sum::<usize>();
+        Ok(blocks)
+    }
+
     fn has(&self, cid: &Cid) -> Result<bool> {
         self.stats.borrow_mut().r += 1;
+        self.accesses.borrow_mut().reads.insert(*cid);
         self.base.has(cid)
     }
 
@@ -69,13 +114,16 @@ where
         let mut stats = self.stats.borrow_mut();
         stats.w += 1;
         stats.bw += block.as_ref().len();
-        self.base.put(code, block)
+        let cid = self.base.put(code, block)?;
+        self.accesses.borrow_mut().writes.insert(cid);
+        Ok(cid)
     }
 
     fn put_keyed(&self, k: &Cid, block: &[u8]) -> Result<()> {
         let mut stats = self.stats.borrow_mut();
         stats.w += 1;
         stats.bw += block.len();
+        self.accesses.borrow_mut().writes.insert(*k);
         self.base.put_keyed(k, block)
     }
 
@@ -86,9 +134,11 @@ where
         I: IntoIterator<Item = (multihash::Code, Block<D>)>,
     {
         let mut stats = self.stats.borrow_mut();
-        self.base.put_many(blocks.into_iter().inspect(|(_, b)| {
+        let mut accesses = self.accesses.borrow_mut();
+        self.base.put_many(blocks.into_iter().inspect(|(mc, b)| {
             stats.w += 1;
             stats.bw += b.as_ref().len();
+            accesses.writes.insert(b.cid(*mc));
         }))?;
         Ok(())
     }
@@ -100,10 +150,12 @@ where
         I: IntoIterator<Item = (Cid, D)>,
     {
         let mut stats = self.stats.borrow_mut();
+        let mut accesses = self.accesses.borrow_mut();
         self.base
-            .put_many_keyed(blocks.into_iter().inspect(|(_, b)| {
+            .put_many_keyed(blocks.into_iter().inspect(|(k, b)| {
                 stats.w += 1;
                 stats.bw += b.as_ref().len();
+                accesses.writes.insert(*k);
             }))?;
         Ok(())
     }
@@ -142,4 +194,45 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn tracking_store_accesses() {
+        let mem = MemoryBlockstore::default();
+        let tr_store = TrackingBlockstore::new(&mem);
+
+        let missing = Block::new(0x55, &b"missing"[..]).cid(Code::Blake2b256);
+        let block = Block::new(0x55, &b"foobar"[..]);
+        let other = Block::new(0x55, &b"baz"[..]);
+        let put_cid = tr_store.put(Code::Blake2b256, &block).unwrap();
+        tr_store
+            .put_many_keyed([(other.cid(Code::Blake2b256), other.data)])
+            .unwrap();
+        tr_store.get(&put_cid).unwrap();
+        assert!(!tr_store.has(&missing).unwrap());
+
+        let accesses = tr_store.take_accesses();
+        assert_eq!(accesses.reads, BTreeSet::from([put_cid, missing]));
+        assert_eq!(
+            accesses.writes,
+            BTreeSet::from([put_cid, other.cid(Code::Blake2b256)])
+        );
+        assert_eq!(tr_store.accesses(), AccessSet::default());
+
+        // Bulk reads record each block.
+        let stats = *tr_store.stats.borrow();
+        let blocks = tr_store.get_many([put_cid, missing]).unwrap();
+        assert_eq!(blocks, [Some(block.data.to_vec()), None]);
+        assert_eq!(
+            tr_store.accesses().reads,
+            BTreeSet::from([put_cid, missing])
+        );
+        assert_eq!(
+            *tr_store.stats.borrow(),
+            BSStats {
+                r: stats.r + 2,
+                br: stats.br + block.len(),
+                ..stats
+            }
+        );
+    }
 }
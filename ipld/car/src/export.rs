@@ -0,0 +1,278 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Export of (part of) the DAG under a root from a blockstore into a CAR file.
+
+use std::collections::HashSet;
+
+use cid::Cid;
+use futures::AsyncWrite;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::{DAG_CBOR, IDENTITY_HASH};
+
+use crate::{CarHeader, Error};
+
+/// What to do with a block reached by an export, see [Selector].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// Export the block and follow its links.
+    All,
+    /// Export the block, but don't follow its links.
+    BlockOnly,
+    /// Follow the block's links without exporting it.
+    LinksOnly,
+    /// Neither export the block nor follow its links. If the block is reachable through other
+    /// paths, it's selected again when reached through them.
+    Skip,
+}
+
+impl Selection {
+    fn exports(self) -> bool {
+        matches!(self, Selection::All | Selection::BlockOnly)
+    }
+
+    fn follows(self) -> bool {
+        matches!(self, Selection::All | Selection::LinksOnly)
+    }
+}
+
+/// Selects the blocks exported by [export].
+pub trait Selector {
+    /// Selects a block, given its CID and its depth (the number of links from the root to the
+    /// block, through the path it was first reached by).
+    fn select(&self, cid: &Cid, depth: usize) -> Selection;
+}
+
+impl<F> Selector for F
+where
+    F: Fn(&Cid, usize) -> Selection,
+{
+    fn select(&self, cid: &Cid, depth: usize) -> Selection {
+        self(cid, depth)
+    }
+}
+
+/// Selects all blocks.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SelectAll;
+
+impl Selector for SelectAll {
+    fn select(&self, _: &Cid, _: usize) -> Selection {
+        Selection::All
+    }
+}
+
+/// Selects the blocks at most the given number of links away from the root.
+#[derive(Debug, Clone, Copy)]
+pub struct MaxDepth(pub usize);
+
+impl Selector for MaxDepth {
+    fn select(&self, _: &Cid, depth: usize) -> Selection {
+        if depth < self.0 {
+            Selection::All
+        } else if depth == self.0 {
+            Selection::BlockOnly
+        } else {
+            Selection::Skip
+        }
+    }
+}
+
+/// Skips the blocks with the given codecs (e.g., `IPLD_RAW` to skip the actors' code in a state
+/// snapshot), and selects all other blocks.
+#[derive(Debug, Clone)]
+pub struct SkipCodecs(pub Vec<u64>);
+
+impl Selector for SkipCodecs {
+    fn select(&self, cid: &Cid, _: usize) -> Selection {
+        if self.0.contains(&cid.codec()) {
+            Selection::Skip
+        } else {
+            Selection::All
+        }
+    }
+}
+
+/// Exports the blocks under `root` chosen by the selector to a CARv1 file with that root, in
+/// depth-first order, returning the number of blocks written. Each block is written at most once.
+///
+/// Only DAG-CBOR blocks can link to other blocks, so blocks with other codecs are exported, if
+/// selected, without being decoded. Inlined (identity hash) blocks are never exported, but their
+/// links are followed. The export fails if a selected block is missing from the store.
+pub async fn export<BS, S, W>(
+    store: &BS,
+    root: &Cid,
+    selector: &S,
+    writer: &mut W,
+) -> Result<usize, Error>
+where
+    BS: Blockstore,
+    S: Selector + ?Sized,
+    W: AsyncWrite + Send + Unpin,
+{
+    let mut walk = Walk {
+        store,
+        selector,
+        stack: vec![(*root, 0)],
+        visited: HashSet::new(),
+        written: 0,
+        error: None,
+    };
+    let mut blocks = futures::stream::iter(std::iter::from_fn(|| walk.next()));
+    let res = CarHeader::from(vec![*root])
+        .write_stream_async(writer, &mut blocks)
+        .await;
+    drop(blocks);
+    match walk.error {
+        // The stream ends early if the walk fails.
+        Some(e) => Err(e),
+        None => res.map(|()| walk.written),
+    }
+}
+
+struct Walk<'a, BS, S: ?Sized> {
+    store: &'a BS,
+    selector: &'a S,
+    stack: Vec<(Cid, usize)>,
+    visited: HashSet<Cid>,
+    written: usize,
+    error: Option<Error>,
+}
+
+impl<BS, S> Walk<'_, BS, S>
+where
+    BS: Blockstore,
+    S: Selector + ?Sized,
+{
+    /// Returns the next selected block, or `None` once done or if the walk fails.
+    fn next(&mut self) -> Option<(Cid, Vec<u8>)> {
+        while let Some((cid, depth)) = self.stack.pop() {
+            match self.visit(cid, depth) {
+                Ok(Some(block)) => {
+                    self.written += 1;
+                    return Some((cid, block));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    self.error = Some(e);
+                    return None;
+                }
+            }
+        }
+        None
+    }
+
+    /// Visits a block, queuing its links, and returns its data if it's to be exported.
+    fn visit(&mut self, cid: Cid, depth: usize) -> Result<Option<Vec<u8>>, Error> {
+        let selection = self.selector.select(&cid, depth);
+        if selection == Selection::Skip || !self.visited.insert(cid) {
+            return Ok(None);
+        }
+        let mut links = Vec::new();
+        if cid.hash().code() == IDENTITY_HASH {
+            if cid.codec() == DAG_CBOR && selection.follows() {
+                scan_for_links(cid.hash().digest(), &mut links)?;
+            }
+            self.queue(links, depth);
+            return Ok(None);
+        }
+        let block = self
+            .store
+            .get(&cid)
+            .map_err(|e| Error::Other(e.to_string()))?
+            .ok_or_else(|| Error::Other(format!("missing block {}", cid)))?;
+        if cid.codec() == DAG_CBOR && selection.follows() {
+            scan_for_links(&block, &mut links)?;
+        }
+        self.queue(links, depth);
+        Ok(selection.exports().then_some(block))
+    }
+
+    fn queue(&mut self, links: Vec<Cid>, depth: usize) {
+        // Reversed, to visit links in order.
+        self.stack
+            .extend(links.into_iter().rev().map(|link| (link, depth + 1)));
+    }
+}
+
+/// Collects the links in a DAG-CBOR block.
+fn scan_for_links(block: &[u8], out: &mut Vec<Cid>) -> Result<(), Error> {
+    fvm_ipld_encoding::scan_for_links(block, out).map_err(|e| Error::ParsingError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use cid::multihash::Code::Blake2b256;
+    use cid::multihash::{Multihash, MultihashDigest};
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_ipld_encoding::{CborStore, IPLD_RAW};
+
+    use super::*;
+    use crate::CarReader;
+
+    async fn exported(store: &MemoryBlockstore, root: &Cid, selector: &dyn Selector) -> Vec<Cid> {
+        let mut buf = Vec::new();
+        let written = export(store, root, selector, &mut buf).await.unwrap();
+        let mut reader = CarReader::new(futures::io::Cursor::new(buf)).await.unwrap();
+        assert_eq!(reader.header.roots, [*root]);
+        let mut cids = Vec::new();
+        while let Some(block) = reader.next_block().await.unwrap() {
+            cids.push(block.cid);
+        }
+        assert_eq!(cids.len(), written);
+        cids
+    }
+
+    #[async_std::test]
+    async fn selective_export() {
+        let store = MemoryBlockstore::default();
+        let code = Cid::new_v1(IPLD_RAW, Blake2b256.digest(b"wasm"));
+        store.put_keyed(&code, b"wasm").unwrap();
+        let leaf = store.put_cbor(&"leaf", Blake2b256).unwrap();
+        let inlined = Cid::new_v1(
+            DAG_CBOR,
+            Multihash::wrap(IDENTITY_HASH, &fvm_ipld_encoding::to_vec(&(leaf,)).unwrap()).unwrap(),
+        );
+        let state = store.put_cbor(&(inlined, 1u8), Blake2b256).unwrap();
+        let root = store.put_cbor(&(code, state, leaf), Blake2b256).unwrap();
+
+        assert_eq!(
+            exported(&store, &root, &SelectAll).await,
+            [root, code, state, leaf]
+        );
+        assert_eq!(
+            exported(&store, &root, &SkipCodecs(vec![IPLD_RAW])).await,
+            [root, state, leaf]
+        );
+        // The leaf is also reachable through the root.
+        assert_eq!(
+            exported(&store, &root, &MaxDepth(1)).await,
+            [root, code, state, leaf]
+        );
+        assert_eq!(exported(&store, &root, &MaxDepth(0)).await, [root]);
+        let not_leaf = |cid: &Cid, _: usize| {
+            if *cid == leaf {
+                Selection::Skip
+            } else {
+                Selection::All
+            }
+        };
+        assert_eq!(
+            exported(&store, &root, &not_leaf).await,
+            [root, code, state]
+        );
+
+        // Selected blocks must be in the store.
+        let missing = store
+            .put_cbor(
+                &(Cid::new_v1(DAG_CBOR, Blake2b256.digest(b"missing")),),
+                Blake2b256,
+            )
+            .unwrap();
+        assert!(export(&store, &missing, &SelectAll, &mut Vec::new())
+            .await
+            .is_err());
+        assert!(export(&store, &missing, &MaxDepth(0), &mut Vec::new())
+            .await
+            .is_ok());
+    }
+}
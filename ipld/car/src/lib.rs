@@ -3,17 +3,21 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 mod error;
+mod export;
 mod util;
+mod v2;
 
 use std::convert::TryFrom;
 
 use cid::Cid;
 pub use error::*;
-use futures::{AsyncRead, AsyncWrite, Stream, StreamExt};
+pub use export::{export, MaxDepth, SelectAll, Selection, Selector, SkipCodecs};
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, Stream, StreamExt};
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::{from_slice, to_vec};
 use serde::{Deserialize, Serialize};
-use util::{ld_read, ld_write, read_node};
+use util::{ld_len, ld_read, ld_write, read_node};
+pub use v2::{CarV2Header, Index, IndexedCarReader, PRAGMA};
 
 /// CAR file header
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
@@ -57,11 +61,14 @@ impl From<Vec<Cid>> for CarHeader {
     }
 }
 
-/// Reads CAR files that are in a BufReader
+/// Reads CAR files that are in a BufReader. CARv2 files are read as their CARv1 payload, ignoring
+/// the index.
 pub struct CarReader<R> {
     pub reader: R,
     pub header: CarHeader,
     pub validate: bool,
+    /// The number of payload bytes left to read, for CARv2 files.
+    remaining: Option<u64>,
 }
 
 impl<R> CarReader<R>
@@ -70,9 +77,27 @@ where
 {
     /// Creates a new CarReader and parses the Car
     pub async fn new(mut reader: R) -> Result<Self, Error> {
-        let buf = ld_read(&mut reader)
-            .await?
-            .ok_or_else(|| Error::ParsingError("failed to parse uvarint for header".to_string()))?;
+        let read_header = |buf: Option<Vec<u8>>| {
+            buf.ok_or_else(|| Error::ParsingError("failed to parse uvarint for header".to_string()))
+        };
+        let mut buf = read_header(ld_read(&mut reader).await?)?;
+        let mut remaining = None;
+        if buf == PRAGMA[1..] {
+            let v2_header = CarV2Header::read(&mut reader).await?;
+            let padding = v2_header.data_offset - v2::PREFIX_LEN;
+            let skipped =
+                futures::io::copy((&mut reader).take(padding), &mut futures::io::sink()).await?;
+            if skipped != padding {
+                return Err(Error::InvalidFile("CARv2 payload is missing".into()));
+            }
+            buf = read_header(ld_read(&mut reader).await?)?;
+            remaining = Some(
+                v2_header
+                    .data_size
+                    .checked_sub(ld_len(buf.len()))
+                    .ok_or_else(|| Error::InvalidFile("CARv2 header exceeds the payload".into()))?,
+            );
+        }
         let header: CarHeader = from_slice(&buf).map_err(|e| Error::ParsingError(e.to_string()))?;
         if header.roots.is_empty() {
             return Err(Error::ParsingError("empty CAR file".to_owned()));
@@ -84,6 +109,7 @@ where
             reader,
             header,
             validate: true,
+            remaining,
         })
     }
 
@@ -97,8 +123,18 @@ where
     /// Returns the next IPLD Block in the buffer
     pub async fn next_block(&mut self) -> Result<Option<Block>, Error> {
         use cid::multihash::{self, MultihashDigest};
+        if self.remaining == Some(0) {
+            return Ok(None);
+        }
         // Read node -> cid, bytes
         if let Some((cid, data)) = read_node(&mut self.reader).await? {
+            if let Some(remaining) = &mut self.remaining {
+                *remaining = remaining
+                    .checked_sub(ld_len(cid.to_bytes().len() + data.len()))
+                    .ok_or_else(|| {
+                        Error::InvalidFile("CARv2 section exceeds the payload".into())
+                    })?;
+            }
             if self.validate {
                 match cid.hash().code() {
                     0x0 => {
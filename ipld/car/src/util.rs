@@ -55,6 +55,12 @@ where
     Ok(())
 }
 
+/// Returns the length of a length-prefixed buffer of the given length, as written by [ld_write].
+pub(crate) fn ld_len(len: usize) -> u64 {
+    let mut buff = unsigned_varint::encode::usize_buffer();
+    (unsigned_varint::encode::usize(len, &mut buff).len() + len) as u64
+}
+
 pub(crate) async fn read_node<R>(buf_reader: &mut R) -> Result<Option<(Cid, Vec<u8>)>, Error>
 where
     R: AsyncRead + Send + Unpin,
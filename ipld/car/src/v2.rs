@@ -0,0 +1,404 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! CARv2 files: a CARv1 payload, preceded by a fixed-size header and followed by an index of the
+//! payload's blocks allowing random access by CID. See
+//! <https://ipld.io/specs/transport/car/carv2/>.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::SeekFrom;
+
+use cid::Cid;
+use futures::{
+    AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt, Stream, StreamExt,
+};
+use fvm_ipld_encoding::{from_slice, to_vec};
+
+use crate::util::{ld_len, ld_read, ld_write, read_node};
+use crate::{CarHeader, Error};
+
+/// The CARv2 pragma: a length-prefixed CARv1 header with version 2 and no roots, so that CARv1
+/// readers reject CARv2 files.
+pub const PRAGMA: [u8; 11] = [
+    0x0a, 0xa1, 0x67, 0x76, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x02,
+];
+
+/// The length of the pragma and the header, i.e., the offset of the payload when there's no
+/// padding.
+pub(crate) const PREFIX_LEN: u64 = PRAGMA.len() as u64 + 40;
+
+/// The multicodecs of the index formats.
+const INDEX_SORTED: u64 = 0x0400;
+const MULTIHASH_INDEX_SORTED: u64 = 0x0401;
+
+/// The fixed-size CARv2 header, following the pragma.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CarV2Header {
+    /// A bitfield of the file's characteristics. None are set when writing.
+    pub characteristics: [u8; 16],
+    /// The offset of the CARv1 payload from the start of the file.
+    pub data_offset: u64,
+    /// The size of the CARv1 payload.
+    pub data_size: u64,
+    /// The offset of the index from the start of the file, or 0 if there's no index.
+    pub index_offset: u64,
+}
+
+impl CarV2Header {
+    fn to_bytes(self) -> Vec<u8> {
+        [
+            &self.characteristics[..],
+            &self.data_offset.to_le_bytes(),
+            &self.data_size.to_le_bytes(),
+            &self.index_offset.to_le_bytes(),
+        ]
+        .concat()
+    }
+
+    /// Reads the header following the pragma.
+    pub(crate) async fn read<R>(reader: &mut R) -> Result<Self, Error>
+    where
+        R: AsyncRead + Send + Unpin,
+    {
+        let mut buf = [0; 40];
+        reader.read_exact(&mut buf).await?;
+        let mut bytes = Bytes(&buf);
+        let header = Self {
+            characteristics: bytes.take(16)?.try_into().unwrap(),
+            data_offset: bytes.u64()?,
+            data_size: bytes.u64()?,
+            index_offset: bytes.u64()?,
+        };
+        if header.data_offset < PREFIX_LEN {
+            return Err(Error::InvalidFile(format!(
+                "CARv2 data offset {} overlaps the header",
+                header.data_offset
+            )));
+        }
+        Ok(header)
+    }
+
+    /// Reads the pragma and the header.
+    async fn read_with_pragma<R>(reader: &mut R) -> Result<Self, Error>
+    where
+        R: AsyncRead + Send + Unpin,
+    {
+        let mut pragma = [0; PRAGMA.len()];
+        reader.read_exact(&mut pragma).await?;
+        if pragma != PRAGMA {
+            return Err(Error::InvalidFile("not a CARv2 file".into()));
+        }
+        Self::read(reader).await
+    }
+}
+
+impl CarHeader {
+    /// Writes a CARv2 file with this header's roots, the blocks from the stream, and an index of
+    /// the blocks (in the `MultihashIndexSorted` format). The header's version is ignored.
+    ///
+    /// The CARv2 header is written last, once the size of the payload is known, so the writer
+    /// must be seekable. The file starts at the writer's current position, and the writer is left
+    /// at the end of the file.
+    pub async fn write_v2_async<W, S>(&self, writer: &mut W, stream: &mut S) -> Result<(), Error>
+    where
+        W: AsyncWrite + AsyncSeek + Send + Unpin,
+        S: Stream<Item = (Cid, Vec<u8>)> + Unpin,
+    {
+        let start = writer.seek(SeekFrom::Current(0)).await?;
+        writer.write_all(&PRAGMA).await?;
+        writer.write_all(&[0; 40]).await?;
+
+        let header_bytes = to_vec(&CarHeader::new(self.roots.clone(), 1))?;
+        ld_write(writer, &header_bytes).await?;
+        let mut data_size = ld_len(header_bytes.len());
+
+        let mut records = Vec::new();
+        while let Some((cid, bytes)) = stream.next().await {
+            let section = [cid.to_bytes(), bytes].concat();
+            ld_write(writer, &section).await?;
+            records.push((cid, data_size));
+            data_size += ld_len(section.len());
+        }
+        writer.write_all(&encode_index(&records)).await?;
+        let end = writer.seek(SeekFrom::Current(0)).await?;
+
+        let header = CarV2Header {
+            characteristics: [0; 16],
+            data_offset: PREFIX_LEN,
+            data_size,
+            index_offset: PREFIX_LEN + data_size,
+        };
+        writer
+            .seek(SeekFrom::Start(start + PRAGMA.len() as u64))
+            .await?;
+        writer.write_all(&header.to_bytes()).await?;
+        writer.seek(SeekFrom::Start(end)).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+/// Encodes a `MultihashIndexSorted` index of the given blocks and their offsets in the payload:
+/// buckets by multihash code, each made of buckets by entry width (digest length + 8), each made
+/// of entries (the digest and the offset) sorted by digest.
+fn encode_index(records: &[(Cid, u64)]) -> Vec<u8> {
+    let mut buckets: BTreeMap<u64, BTreeMap<u32, Vec<(&[u8], u64)>>> = BTreeMap::new();
+    for (cid, offset) in records {
+        let digest = cid.hash().digest();
+        buckets
+            .entry(cid.hash().code())
+            .or_default()
+            .entry(digest.len() as u32 + 8)
+            .or_default()
+            .push((digest, *offset));
+    }
+
+    let mut buf = unsigned_varint::encode::u64(
+        MULTIHASH_INDEX_SORTED,
+        &mut unsigned_varint::encode::u64_buffer(),
+    )
+    .to_vec();
+    buf.extend((buckets.len() as u32).to_le_bytes());
+    for (code, widths) in buckets {
+        buf.extend(code.to_le_bytes());
+        buf.extend((widths.len() as u32).to_le_bytes());
+        for (width, mut entries) in widths {
+            entries.sort_unstable();
+            buf.extend(width.to_le_bytes());
+            buf.extend((entries.len() as u64 * width as u64).to_le_bytes());
+            for (digest, offset) in entries {
+                buf.extend(digest);
+                buf.extend(offset.to_le_bytes());
+            }
+        }
+    }
+    buf
+}
+
+/// A CARv2 index: the offsets of the payload's blocks, by multihash.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Index {
+    /// The offsets (in the payload) of the blocks with each digest, with the digest's multihash
+    /// code if the index records it.
+    entries: HashMap<Vec<u8>, Vec<(Option<u64>, u64)>>,
+}
+
+impl Index {
+    /// Decodes an `IndexSorted` or `MultihashIndexSorted` index.
+    pub fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let (codec, rest) =
+            unsigned_varint::decode::u64(buf).map_err(|e| Error::ParsingError(e.to_string()))?;
+        let mut bytes = Bytes(rest);
+        let mut index = Self::default();
+        match codec {
+            INDEX_SORTED => index.decode_sorted(&mut bytes, None)?,
+            MULTIHASH_INDEX_SORTED => {
+                for _ in 0..bytes.u32()? {
+                    let code = bytes.u64()?;
+                    index.decode_sorted(&mut bytes, Some(code))?;
+                }
+            }
+            codec => {
+                return Err(Error::InvalidFile(format!(
+                    "unsupported CARv2 index format {:#x}",
+                    codec
+                )))
+            }
+        }
+        Ok(index)
+    }
+
+    fn decode_sorted(&mut self, bytes: &mut Bytes, code: Option<u64>) -> Result<(), Error> {
+        for _ in 0..bytes.u32()? {
+            let width = bytes.u32()? as usize;
+            let len = bytes.u64()?;
+            if width <= 8 || len % width as u64 != 0 {
+                return Err(Error::InvalidFile(format!(
+                    "invalid CARv2 index bucket (width {}, length {})",
+                    width, len
+                )));
+            }
+            let entries = bytes.take(len.try_into().unwrap_or(usize::MAX))?;
+            for entry in entries.chunks_exact(width) {
+                let (digest, offset) = entry.split_at(width - 8);
+                let offset = u64::from_le_bytes(offset.try_into().unwrap());
+                self.entries
+                    .entry(digest.to_vec())
+                    .or_default()
+                    .push((code, offset));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the offsets (in the payload) of the blocks which may have the given CID: the
+    /// blocks with the same multihash, but possibly a different codec.
+    pub fn offsets<'a>(&'a self, cid: &Cid) -> impl Iterator<Item = u64> + 'a {
+        let code = cid.hash().code();
+        self.entries
+            .get(cid.hash().digest())
+            .into_iter()
+            .flatten()
+            .filter(move |(c, _)| c.map_or(true, |c| c == code))
+            .map(|(_, offset)| *offset)
+    }
+}
+
+/// Reads blocks by CID from an indexed CARv2 file.
+pub struct IndexedCarReader<R> {
+    reader: R,
+    /// The position of the start of the file in the reader.
+    start: u64,
+    pub header: CarHeader,
+    pub v2_header: CarV2Header,
+    pub index: Index,
+}
+
+impl<R> IndexedCarReader<R>
+where
+    R: AsyncRead + AsyncSeek + Send + Unpin,
+{
+    /// Reads the headers and the index of the CARv2 file starting at the reader's current
+    /// position. Fails if the file has no index.
+    pub async fn new(mut reader: R) -> Result<Self, Error> {
+        let start = reader.seek(SeekFrom::Current(0)).await?;
+        let v2_header = CarV2Header::read_with_pragma(&mut reader).await?;
+        if v2_header.index_offset == 0 {
+            return Err(Error::InvalidFile("CARv2 file has no index".into()));
+        }
+
+        reader
+            .seek(SeekFrom::Start(start + v2_header.data_offset))
+            .await?;
+        let buf = ld_read(&mut reader)
+            .await?
+            .ok_or_else(|| Error::ParsingError("failed to parse uvarint for header".to_string()))?;
+        let header: CarHeader = from_slice(&buf).map_err(|e| Error::ParsingError(e.to_string()))?;
+
+        reader
+            .seek(SeekFrom::Start(start + v2_header.index_offset))
+            .await?;
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await?;
+        let index = Index::decode(&buf)?;
+
+        Ok(Self {
+            reader,
+            start,
+            header,
+            v2_header,
+            index,
+        })
+    }
+
+    /// Returns the data of the block with the given CID, or `None` if the index doesn't have it.
+    /// The CID of the block found at the indexed offset is checked, but its data isn't hashed.
+    pub async fn get(&mut self, cid: &Cid) -> Result<Option<Vec<u8>>, Error> {
+        let offsets: Vec<u64> = self.index.offsets(cid).collect();
+        for offset in offsets {
+            if offset >= self.v2_header.data_size {
+                return Err(Error::InvalidFile(format!(
+                    "CARv2 index offset {} is beyond the payload",
+                    offset
+                )));
+            }
+            let position = self.start + self.v2_header.data_offset + offset;
+            self.reader.seek(SeekFrom::Start(position)).await?;
+            match read_node(&mut self.reader).await? {
+                Some((found, data)) if found == *cid => return Ok(Some(data)),
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// A little-endian reader over a byte slice.
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Bytes<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if n > self.0.len() {
+            return Err(Error::ParsingError("unexpected end of CARv2 data".into()));
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cid::multihash::Code::{Blake2b256, Sha2_256};
+    use cid::multihash::MultihashDigest;
+    use futures::io::Cursor;
+    use futures::stream;
+    use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+    use fvm_ipld_encoding::{DAG_CBOR, IPLD_RAW};
+
+    use super::*;
+    use crate::{load_car, CarReader};
+
+    #[async_std::test]
+    async fn car_v2_write_read() {
+        let blocks: Vec<(Cid, Vec<u8>)> = [
+            (DAG_CBOR, Blake2b256, &b"foo"[..]),
+            (IPLD_RAW, Blake2b256, b"bar"),
+            (IPLD_RAW, Sha2_256, b"baz"),
+        ]
+        .into_iter()
+        .map(|(codec, code, data)| (Cid::new_v1(codec, code.digest(data)), data.to_vec()))
+        .collect();
+        let header = CarHeader::new(vec![blocks[0].0], 1);
+
+        let mut writer = Cursor::new(Vec::new());
+        header
+            .write_v2_async(&mut writer, &mut stream::iter(blocks.clone()))
+            .await
+            .unwrap();
+        let buffer = writer.into_inner();
+        assert_eq!(buffer[..PRAGMA.len()], PRAGMA);
+
+        // The payload can be streamed.
+        let reader = CarReader::new(Cursor::new(&buffer)).await.unwrap();
+        assert_eq!(reader.header, header);
+        let bs = MemoryBlockstore::default();
+        assert_eq!(reader.read_into(&bs).await.unwrap(), header.roots);
+        for (cid, data) in &blocks {
+            assert_eq!(bs.get(cid).unwrap().as_ref(), Some(data));
+        }
+
+        // Or read by CID.
+        let mut reader = IndexedCarReader::new(Cursor::new(&buffer)).await.unwrap();
+        assert_eq!(reader.header, header);
+        assert_eq!(reader.v2_header.data_offset, PREFIX_LEN);
+        for (cid, data) in &blocks {
+            assert_eq!(reader.get(cid).await.unwrap().as_ref(), Some(data));
+        }
+        // Same multihash, different codec.
+        let other = Cid::new_v1(IPLD_RAW, blocks[0].0.hash().to_owned());
+        assert_eq!(reader.get(&other).await.unwrap(), None);
+
+        // CARv1 files have no CARv2 header.
+        let mut v1 = Vec::new();
+        header
+            .write_stream_async(&mut v1, &mut stream::iter(blocks.clone()))
+            .await
+            .unwrap();
+        assert!(IndexedCarReader::new(Cursor::new(&v1)).await.is_err());
+        let bs = MemoryBlockstore::default();
+        load_car(&bs, Cursor::new(&v1)).await.unwrap();
+        assert!(bs.has(&blocks[2].0).unwrap());
+    }
+}
@@ -11,6 +11,9 @@ mod raw;
 mod vec;
 use std::io;
 
+// The link scanner lives in the blockstore crate so blockstores can follow links, as that crate
+// can't depend on this one.
+pub use fvm_ipld_blockstore::{scan_for_links, IDENTITY_HASH};
 pub use serde::{self, de, ser};
 
 pub use self::bytes::*;
@@ -0,0 +1,192 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::cmp::Ordering;
+
+use fvm_ipld_blockstore::Blockstore;
+use serde::de::DeserializeOwned;
+
+use crate::node::Node;
+use crate::pointer::version::Version;
+use crate::pointer::Pointer;
+use crate::{Config, Error};
+
+/// A difference between two HAMTs, as returned by `Hamt::diff`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Change<K, V> {
+    /// The key is only in the new HAMT.
+    Added(K, V),
+    /// The key is only in the old HAMT.
+    Removed(K, V),
+    /// The key is in both HAMTs, with different values.
+    Modified { key: K, before: V, after: V },
+}
+
+/// One side of a diff: a HAMT's store and config.
+pub(crate) struct Side<'a, BS> {
+    pub store: &'a BS,
+    pub conf: &'a Config,
+}
+
+/// Diffs two nodes at the given depth, skipping identical subtrees (links with the same CID).
+pub(crate) fn diff_nodes<'a, K, V, H, Ver, BS>(
+    old: (&Side<'a, BS>, &'a Node<K, V, H, Ver>),
+    new: (&Side<'a, BS>, &'a Node<K, V, H, Ver>),
+    depth: u32,
+    changes: &mut Vec<Change<K, V>>,
+) -> Result<(), Error>
+where
+    K: PartialOrd + DeserializeOwned + Clone,
+    V: PartialEq + DeserializeOwned + Clone,
+    Ver: Version,
+    BS: Blockstore,
+{
+    let (old_side, old_node) = old;
+    let (new_side, new_node) = new;
+    let slots = 1u32 << old_side.conf.bit_width;
+    for idx in 0..slots {
+        let idx = idx as u8;
+        let pointer = |node: &'a Node<K, V, H, Ver>| {
+            node.bitfield
+                .test_bit(idx)
+                .then(|| &node.pointers[node.index_for_bit_pos(idx)])
+        };
+        match (pointer(old_node), pointer(new_node)) {
+            (None, None) => {}
+            (Some(old), None) => {
+                let mut entries = Vec::new();
+                collect(old_side, old, depth + 1, &mut entries)?;
+                changes.extend(
+                    entries
+                        .into_iter()
+                        .map(|(k, v)| Change::Removed(k.clone(), v.clone())),
+                );
+            }
+            (None, Some(new)) => {
+                let mut entries = Vec::new();
+                collect(new_side, new, depth + 1, &mut entries)?;
+                changes.extend(
+                    entries
+                        .into_iter()
+                        .map(|(k, v)| Change::Added(k.clone(), v.clone())),
+                );
+            }
+            (Some(Pointer::Link { cid: a, .. }), Some(Pointer::Link { cid: b, .. })) if a == b => {}
+            (Some(old), Some(new)) => match (
+                child(old_side, old, depth + 1)?,
+                child(new_side, new, depth + 1)?,
+            ) {
+                (Some(old), Some(new)) => {
+                    diff_nodes((old_side, old), (new_side, new), depth + 1, changes)?
+                }
+                // At least one side is a bucket of values, so the subtrees are small: compare
+                // their entries.
+                _ => {
+                    let mut old_entries = Vec::new();
+                    collect(old_side, old, depth + 1, &mut old_entries)?;
+                    let mut new_entries = Vec::new();
+                    collect(new_side, new, depth + 1, &mut new_entries)?;
+                    diff_entries(old_entries, new_entries, changes);
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Returns the node a pointer links to, or `None` if it's a bucket of values.
+fn child<'a, K, V, H, Ver, BS>(
+    side: &Side<'a, BS>,
+    pointer: &'a Pointer<K, V, H, Ver>,
+    depth: u32,
+) -> Result<Option<&'a Node<K, V, H, Ver>>, Error>
+where
+    K: PartialOrd + DeserializeOwned,
+    V: DeserializeOwned,
+    Ver: Version,
+    BS: Blockstore,
+{
+    Ok(match pointer {
+        Pointer::Link { cid, cache } => Some(
+            &**cache
+                .get_or_try_init(|| Node::load(side.conf, side.store, cid, depth).map(Box::new))?,
+        ),
+        Pointer::Dirty(node) => Some(&**node),
+        Pointer::Values(_) => None,
+    })
+}
+
+/// Collects all entries under a pointer.
+fn collect<'a, K, V, H, Ver, BS>(
+    side: &Side<'a, BS>,
+    pointer: &'a Pointer<K, V, H, Ver>,
+    depth: u32,
+    entries: &mut Vec<(&'a K, &'a V)>,
+) -> Result<(), Error>
+where
+    K: PartialOrd + DeserializeOwned,
+    V: DeserializeOwned,
+    Ver: Version,
+    BS: Blockstore,
+{
+    match child(side, pointer, depth)? {
+        Some(node) => {
+            for pointer in &node.pointers {
+                collect(side, pointer, depth + 1, entries)?;
+            }
+        }
+        None => {
+            if let Pointer::Values(kvs) = pointer {
+                entries.extend(kvs.iter().map(|kv| (kv.key(), kv.value())));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Diffs two sets of entries, by key.
+fn diff_entries<K, V>(
+    mut old: Vec<(&K, &V)>,
+    mut new: Vec<(&K, &V)>,
+    changes: &mut Vec<Change<K, V>>,
+) where
+    K: PartialOrd + Clone,
+    V: PartialEq + Clone,
+{
+    fn by_key<K: PartialOrd, V>(a: &(&K, &V), b: &(&K, &V)) -> Ordering {
+        a.0.partial_cmp(b.0).unwrap_or(Ordering::Equal)
+    }
+    old.sort_unstable_by(by_key);
+    new.sort_unstable_by(by_key);
+
+    let mut old = old.into_iter().peekable();
+    let mut new = new.into_iter().peekable();
+    loop {
+        let order = match (old.peek(), new.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(a), Some(b)) => by_key(a, b),
+        };
+        match order {
+            Ordering::Less => {
+                let (k, v) = old.next().unwrap();
+                changes.push(Change::Removed(k.clone(), v.clone()));
+            }
+            Ordering::Greater => {
+                let (k, v) = new.next().unwrap();
+                changes.push(Change::Added(k.clone(), v.clone()));
+            }
+            Ordering::Equal => {
+                let (k, before) = old.next().unwrap();
+                let (_, after) = new.next().unwrap();
+                if before != after {
+                    changes.push(Change::Modified {
+                        key: k.clone(),
+                        before: before.clone(),
+                        after: after.clone(),
+                    });
+                }
+            }
+        }
+    }
+}
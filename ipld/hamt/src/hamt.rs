@@ -13,6 +13,7 @@ use multihash::Code;
 use serde::de::DeserializeOwned;
 use serde::{Serialize, Serializer};
 
+use crate::diff::{diff_nodes, Change, Side};
 use crate::iter::IterImpl;
 use crate::node::Node;
 use crate::pointer::version::Version;
@@ -134,6 +135,57 @@ where
         )
     }
 
+    /// Builds a HAMT from the given entries in a single pass, hashing each key once and
+    /// constructing the tree bottom-up instead of inserting the entries one by one. This is much
+    /// faster than repeated calls to [`set`](Self::set) when building large maps from scratch
+    /// (e.g., in migrations or at genesis), and produces the same HAMT.
+    ///
+    /// The entries should be supplied in the HAMT's canonical order (see
+    /// [`canonical_iter`](Self::canonical_iter)), e.g. when transforming the entries of another
+    /// HAMT with the same hash algorithm. Entries in any other order are accepted, at the cost of
+    /// a full sort. Returns an error if the same key is supplied more than once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::{Config, Hamt};
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut map: Hamt<_, _, usize> =
+    ///     Hamt::from_sorted_iter(&store, Config::default(), (0..100).map(|i| (i, i * 2))).unwrap();
+    /// assert_eq!(map.get(&37).unwrap(), Some(&74));
+    /// let cid = map.flush().unwrap();
+    /// ```
+    pub fn from_sorted_iter<I>(store: BS, conf: Config, entries: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut entries: Vec<_> = entries
+            .into_iter()
+            .map(|(k, v)| (H::hash(&k), k, v))
+            .collect();
+        // A stable sort is close to linear on nearly sorted input, such as the canonical order.
+        entries.sort_by(|(ha, ka, _), (hb, kb, _)| {
+            ha.cmp(hb)
+                .then_with(|| ka.partial_cmp(kb).unwrap_or(std::cmp::Ordering::Equal))
+        });
+        if entries
+            .windows(2)
+            .any(|w| w[0].0 == w[1].0 && w[0].1 == w[1].1)
+        {
+            return Err("duplicate key in HAMT entries".into());
+        }
+
+        Ok(Self {
+            root: Node::from_sorted(entries, &conf, 0)?,
+            store,
+            conf,
+            hash: Default::default(),
+            flushed_cid: None,
+        })
+    }
+
     /// Sets the root based on the Cid of the root node using the Hamt store
     pub fn set_root(&mut self, cid: &Cid) -> Result<(), Error> {
         self.root = Node::load(&self.conf, &self.store, cid, 0)?;
@@ -371,6 +423,52 @@ where
         Ok(())
     }
 
+    /// Like [`Hamt::for_each`], but iterates over the subtrees of the root node in parallel (with
+    /// rayon), calling the function concurrently from multiple threads, in no particular order.
+    /// Subtrees are loaded on the worker threads, bypassing (and not filling) the HAMT's cache,
+    /// but unflushed changes are included.
+    #[cfg(feature = "rayon")]
+    pub fn par_for_each<F>(&self, f: F) -> Result<(), Error>
+    where
+        V: DeserializeOwned,
+        BS: Sync,
+        F: Fn(&K, &V) -> anyhow::Result<()> + Sync,
+    {
+        use rayon::prelude::*;
+
+        use crate::pointer::Pointer;
+
+        // Unflushed subtrees are cached in the (thread-local) root node, so they're iterated
+        // first, on this thread.
+        let mut links = Vec::new();
+        for pointer in &self.root.pointers {
+            match pointer {
+                Pointer::Link { cid, .. } => links.push(*cid),
+                Pointer::Dirty(node) => {
+                    for res in IterImpl::new(&self.store, &**node, &self.conf) {
+                        let (k, v) = res?;
+                        (f)(k, v)?;
+                    }
+                }
+                Pointer::Values(kvs) => {
+                    for kv in kvs {
+                        (f)(kv.key(), kv.value())?;
+                    }
+                }
+            }
+        }
+
+        let (store, conf) = (&self.store, &self.conf);
+        links.into_par_iter().try_for_each(|cid| {
+            let node: Node<K, V, H, Ver> = Node::load(conf, store, &cid, 1)?;
+            for res in IterImpl::new(store, &node, conf) {
+                let (k, v) = res?;
+                (f)(k, v)?;
+            }
+            Ok(())
+        })
+    }
+
     /// Iterates over each KV in the Hamt and runs a function on the values. If starting key is
     /// provided, iteration will start from that key. If max is provided, iteration will stop after
     /// max number of items have been traversed. The number of items that were traversed is
@@ -450,6 +548,62 @@ where
     Ver: Version,
     BS: Blockstore,
 {
+    /// Returns the differences from this HAMT to `other` (e.g., a later version of it), in no
+    /// particular order. Subtrees that are identical in both HAMTs (i.e., that have the same CID)
+    /// are skipped without being loaded, so diffing two versions of a large HAMT only loads the
+    /// nodes that changed. Both HAMTs must have the same bit width.
+    ///
+    /// ```
+    /// use fvm_ipld_hamt::{Change, Hamt};
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut old: Hamt<_, u64, u64> = Hamt::new_with_bit_width(&store, 5);
+    /// old.set(1, 1).unwrap();
+    /// old.set(2, 2).unwrap();
+    /// let root = old.flush().unwrap();
+    ///
+    /// let mut new: Hamt<_, u64, u64> = Hamt::load_with_bit_width(&root, &store, 5).unwrap();
+    /// new.set(2, 3).unwrap();
+    /// new.set(4, 4).unwrap();
+    ///
+    /// let mut changes = old.diff(&new).unwrap();
+    /// changes.sort_by_key(|c| match c {
+    ///     Change::Added(k, _) | Change::Removed(k, _) | Change::Modified { key: k, .. } => *k,
+    /// });
+    /// assert_eq!(
+    ///     changes,
+    ///     [
+    ///         Change::Modified { key: 2, before: 2, after: 3 },
+    ///         Change::Added(4, 4),
+    ///     ]
+    /// );
+    /// ```
+    pub fn diff(&self, other: &Self) -> Result<Vec<Change<K, V>>, Error>
+    where
+        K: Clone,
+        V: PartialEq + Clone,
+    {
+        if self.conf.bit_width != other.conf.bit_width {
+            return Err(Error::Dynamic(anyhow::anyhow!(
+                "cannot diff HAMTs with different bit widths ({} and {})",
+                self.conf.bit_width,
+                other.conf.bit_width
+            )));
+        }
+        let old = Side {
+            store: &self.store,
+            conf: &self.conf,
+        };
+        let new = Side {
+            store: &other.store,
+            conf: &other.conf,
+        };
+        let mut changes = Vec::new();
+        diff_nodes((&old, &self.root), (&new, &other.root), 0, &mut changes)?;
+        Ok(changes)
+    }
+
     /// Iterate over the HAMT. Alternatively, you can directly iterate over the HAMT without calling
     /// this method:
     ///
@@ -474,6 +628,17 @@ where
         IterImpl::new(&self.store, &self.root, &self.conf)
     }
 
+    /// Iterate over the HAMT in its canonical order.
+    ///
+    /// Entries are ordered by the hashes of their keys, consumed `bit_width` bits at a time, most
+    /// significant bits first. Entries sharing a bucket are ordered by key. The order only depends
+    /// on the set of keys in the HAMT (not on insertion order or caching) and is stable across
+    /// versions of this crate and across platforms, as long as the key's [`Hash`] implementation
+    /// is. It is the same order as `iter` and `for_each`.
+    pub fn canonical_iter(&self) -> IterImpl<BS, V, K, H, Ver> {
+        self.iter()
+    }
+
     /// Iterate over the HAMT starting at the given key. This can be used to implement "ranged"
     /// iteration:
     ///
@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::borrow::Borrow;
+use std::cmp::Ordering;
 use std::fmt::Debug;
 use std::marker::PhantomData;
 
@@ -17,7 +18,7 @@ use serde::{Serialize, Serializer};
 use super::bitfield::Bitfield;
 use super::hash_bits::HashBits;
 use super::pointer::Pointer;
-use super::{Error, Hash, HashAlgorithm, KeyValuePair};
+use super::{Error, Hash, HashAlgorithm, HashedKey, KeyValuePair};
 use crate::pointer::version::{self, Version};
 use crate::Config;
 
@@ -209,6 +210,44 @@ where
         self.pointers.is_empty()
     }
 
+    /// Builds a node at `depth` from entries sorted by hash, all
+    /// of which share the hash bits consumed by the ancestors of this node. Each child slot
+    /// becomes a bucket if it holds at most `max_array_width` entries (and the node is deep
+    /// enough to hold values), matching the structure produced by inserting the same entries.
+    pub(crate) fn from_sorted(
+        entries: Vec<(HashedKey, K, V)>,
+        conf: &Config,
+        depth: u32,
+    ) -> Result<Self, Error> {
+        let mut node = Node::default();
+        let consumed = depth * conf.bit_width;
+        let index = |hash: &HashedKey| HashBits::new_at_index(hash, consumed).next(conf.bit_width);
+
+        let mut entries = entries.into_iter().peekable();
+        while let Some(first) = entries.next() {
+            let idx = index(&first.0)?;
+            let mut group = vec![first];
+            while let Some(entry) = entries.next_if(|(hash, ..)| index(hash).ok() == Some(idx)) {
+                group.push(entry);
+            }
+
+            let pointer = if depth >= conf.min_data_depth && group.len() <= conf.max_array_width {
+                // Buckets are ordered by key, not by hash.
+                let mut kvs: Vec<_> = group
+                    .into_iter()
+                    .map(|(_, k, v)| KeyValuePair::new(k, v))
+                    .collect();
+                kvs.sort_by(|a, b| a.key().partial_cmp(b.key()).unwrap_or(Ordering::Equal));
+                Pointer::Values(kvs)
+            } else {
+                Pointer::Dirty(Box::new(Self::from_sorted(group, conf, depth + 1)?))
+            };
+            node.bitfield.set_bit(idx);
+            node.pointers.push(pointer);
+        }
+        Ok(node)
+    }
+
     /// Search for a key.
     fn search<Q: ?Sized, S: Blockstore>(
         &self,
@@ -13,7 +13,7 @@ use fvm_ipld_encoding::strict_bytes::ByteBuf;
 use fvm_ipld_encoding::CborStore;
 #[cfg(feature = "identity")]
 use fvm_ipld_hamt::Identity;
-use fvm_ipld_hamt::{BytesKey, Config, Error, Hamt, Hash};
+use fvm_ipld_hamt::{BytesKey, Change, Config, Error, Hamt, Hash};
 use multihash::Code;
 use quickcheck::Arbitrary;
 use rand::seq::SliceRandom;
@@ -417,6 +417,47 @@ fn for_each(
     }
 }
 
+#[cfg(feature = "rayon")]
+fn par_for_each(size_factor: usize, factory: HamtFactory, mut cids: CidChecker) {
+    use std::collections::BTreeSet;
+    use std::sync::Mutex;
+
+    let store = MemoryBlockstore::default();
+
+    let mut hamt: Hamt<_, BytesKey> = factory.new_with_bit_width(&store, 5);
+
+    for i in 0..size_factor {
+        hamt.set(tstring(i), tstring(i)).unwrap();
+    }
+    let expected: BTreeSet<_> = (0..size_factor).map(tstring).collect();
+
+    let visit = |hamt: &Hamt<_, BytesKey>| {
+        let seen = Mutex::new(BTreeSet::new());
+        hamt.par_for_each(|k, v| {
+            assert_eq!(k, v);
+            assert!(seen.lock().unwrap().insert(k.clone()), "visited twice");
+            Ok(())
+        })
+        .unwrap();
+        seen.into_inner().unwrap()
+    };
+
+    // Iterating through hamt with dirty caches.
+    assert_eq!(visit(&hamt), expected);
+
+    let c = hamt.flush().unwrap();
+    cids.check_next(c);
+
+    // Iterating through hamt with no cache.
+    let hamt: Hamt<_, BytesKey> = factory.load_with_bit_width(&c, &store, 5).unwrap();
+    assert_eq!(visit(&hamt), expected);
+
+    // Errors returned by the callback are propagated.
+    assert!(hamt
+        .par_for_each(|_, _| Err(anyhow::anyhow!("stop")))
+        .is_err());
+}
+
 fn for_each_ranged(
     size_factor: usize,
     factory: HamtFactory,
@@ -715,6 +756,108 @@ fn canonical_structure_alt_bit_width() {
     }
 }
 
+#[test]
+fn canonical_iteration_order() {
+    let store = MemoryBlockstore::default();
+    let mut hamt: Hamt<_, u8> = Hamt::new_with_bit_width(&store, 8);
+    for (i, k) in ('a'..='p').enumerate() {
+        hamt.set(tstring(k), i as u8).unwrap();
+    }
+
+    // Ordered by the first byte of the SHA-256 of each key; "d" and "j" share a bucket.
+    let golden = [
+        "p", "d", "j", "n", "f", "c", "b", "e", "m", "o", "k", "h", "l", "a", "g", "i",
+    ]
+    .map(tstring);
+
+    let collect_keys = |hamt: &Hamt<_, u8>| -> Vec<BytesKey> {
+        hamt.canonical_iter()
+            .map(|kv| kv.map(|(k, _)| k.clone()))
+            .collect::<Result<_, _>>()
+            .unwrap()
+    };
+    assert_eq!(collect_keys(&hamt), golden);
+
+    let c = hamt.flush().unwrap();
+    let hamt: Hamt<_, u8> = Hamt::load_with_bit_width(&c, &store, 8).unwrap();
+    assert_eq!(collect_keys(&hamt), golden);
+    assert_eq!(collect_keys(&hamt), golden);
+}
+
+/// Test that insertion order doesn't matter, iteration yields the same sequence.
+fn prop_iter_order_indep_of_insert_order(
+    factory: HamtFactory,
+    kvs: UniqueKeyValuePairs<u8, i64>,
+    seed: u64,
+) -> bool {
+    let store = MemoryBlockstore::default();
+    let kvs1 = kvs.0;
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    let mut kvs2 = kvs1.clone();
+    kvs2.shuffle(&mut rng);
+
+    let mut hamt1 = factory.new(&store);
+    let mut hamt2 = factory.new(&store);
+
+    for (k, v) in kvs1 {
+        hamt1.set(k, v).unwrap();
+    }
+    for (k, v) in kvs2 {
+        hamt2.set(k, v).unwrap();
+    }
+
+    let entries1: Vec<(u8, i64)> = hamt1
+        .canonical_iter()
+        .map(|kv| kv.map(|(k, v)| (*k, *v)))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let entries2: Vec<(u8, i64)> = hamt2
+        .canonical_iter()
+        .map(|kv| kv.map(|(k, v)| (*k, *v)))
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    entries1 == entries2
+}
+
+/// Test that building a HAMT in bulk yields the same HAMT as inserting the entries, in any order.
+fn prop_from_sorted_iter_matches_set(
+    factory: HamtFactory,
+    kvs: UniqueKeyValuePairs<u8, i64>,
+    seed: u64,
+) -> bool {
+    let store = MemoryBlockstore::default();
+
+    let mut hamt1 = factory.new(&store);
+    for (k, v) in kvs.0.iter() {
+        hamt1.set(*k, *v).unwrap();
+    }
+    let canonical: Vec<(u8, i64)> = hamt1
+        .canonical_iter()
+        .map(|kv| kv.map(|(k, v)| (*k, *v)))
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    let mut shuffled = kvs.0;
+    shuffled.shuffle(&mut rng);
+
+    let mut hamt2 = Hamt::from_sorted_iter(&store, factory.conf.clone(), canonical).unwrap();
+    let mut hamt3 = Hamt::from_sorted_iter(&store, factory.conf.clone(), shuffled).unwrap();
+
+    let cid1 = hamt1.flush().unwrap();
+    cid1 == hamt2.flush().unwrap() && cid1 == hamt3.flush().unwrap()
+}
+
+#[test]
+fn from_sorted_iter_duplicates() {
+    let store = MemoryBlockstore::default();
+    let res: Result<Hamt<_, u8, u8>, _> =
+        Hamt::from_sorted_iter(&store, Config::default(), [(1, 1), (2, 2), (1, 3)]);
+    assert!(res.is_err());
+}
+
 fn clean_child_ordering(factory: HamtFactory, stats: Option<BSStats>, mut cids: CidChecker) {
     let make_key = |i: u64| -> BytesKey {
         let mut key = unsigned_varint::encode::u64_buffer();
@@ -749,6 +892,69 @@ fn clean_child_ordering(factory: HamtFactory, stats: Option<BSStats>, mut cids:
     }
 }
 
+fn diff(size_factor: usize, factory: HamtFactory) {
+    let mem = MemoryBlockstore::default();
+    let store = TrackingBlockstore::new(&mem);
+
+    let mut old: Hamt<_, BytesKey> = factory.new_with_bit_width(&store, 5);
+    for i in 0..size_factor {
+        old.set(tstring(i), tstring(i)).unwrap();
+    }
+    let root = old.flush().unwrap();
+
+    // Identical HAMTs have no differences, and diffing them loads nothing beyond the roots.
+    let new: Hamt<_, BytesKey> = factory.load_with_bit_width(&root, &store, 5).unwrap();
+    let reads = store.stats.borrow().r;
+    assert!(old.diff(&new).unwrap().is_empty());
+    assert_eq!(store.stats.borrow().r, reads);
+
+    let mut new = new;
+    let mut expected = HashSet::new();
+    for i in (0..size_factor).step_by(7) {
+        new.delete(&tstring(i)).unwrap();
+        expected.insert(Change::Removed(tstring(i), tstring(i)));
+    }
+    for i in (3..size_factor).step_by(7) {
+        new.set(tstring(i), tstring(i + 1)).unwrap();
+        expected.insert(Change::Modified {
+            key: tstring(i),
+            before: tstring(i),
+            after: tstring(i + 1),
+        });
+    }
+    for i in size_factor..size_factor + size_factor / 3 + 1 {
+        new.set(tstring(i), tstring(i)).unwrap();
+        expected.insert(Change::Added(tstring(i), tstring(i)));
+    }
+
+    // With dirty nodes.
+    let changes = old.diff(&new).unwrap();
+    assert_eq!(changes.len(), expected.len());
+    assert_eq!(changes.into_iter().collect::<HashSet<_>>(), expected);
+
+    // With flushed nodes, in both directions.
+    let new_root = new.flush().unwrap();
+    let old: Hamt<_, BytesKey> = factory.load_with_bit_width(&root, &store, 5).unwrap();
+    let new: Hamt<_, BytesKey> = factory.load_with_bit_width(&new_root, &store, 5).unwrap();
+    let changes = old.diff(&new).unwrap();
+    assert_eq!(changes.into_iter().collect::<HashSet<_>>(), expected);
+
+    let reversed: HashSet<_> = expected
+        .into_iter()
+        .map(|change| match change {
+            Change::Added(k, v) => Change::Removed(k, v),
+            Change::Removed(k, v) => Change::Added(k, v),
+            Change::Modified { key, before, after } => Change::Modified {
+                key,
+                before: after,
+                after: before,
+            },
+        })
+        .collect();
+    let changes = new.diff(&old).unwrap();
+    assert_eq!(changes.into_iter().collect::<HashSet<_>>(), reversed);
+}
+
 /// Test that a HAMT produced by `factory1` has a larger root size than one produced by `factory2`
 /// after inserting the same data into both versions.
 fn test_reduced_root_size(factory1: HamtFactory, factory2: HamtFactory) {
@@ -1054,6 +1260,11 @@ mod test_default {
         super::clean_child_ordering(HamtFactory::default(), Some(stats), cids);
     }
 
+    #[test]
+    fn diff() {
+        super::diff(200, HamtFactory::default());
+    }
+
     #[test]
     fn test_hamtv0() {
         let config = Config {
@@ -1078,6 +1289,16 @@ mod test_default {
     fn prop_cid_ops_reduced(ops: LimitedKeyOps<10>) -> bool {
         super::prop_cid_ops_reduced(HamtFactory::default(), ops)
     }
+
+    #[quickcheck]
+    fn prop_iter_order_indep_of_insert_order(kvs: UniqueKeyValuePairs<u8, i64>, seed: u64) -> bool {
+        super::prop_iter_order_indep_of_insert_order(HamtFactory::default(), kvs, seed)
+    }
+
+    #[quickcheck]
+    fn prop_from_sorted_iter_matches_set(kvs: UniqueKeyValuePairs<u8, i64>, seed: u64) -> bool {
+        super::prop_from_sorted_iter_matches_set(HamtFactory::default(), kvs, seed)
+    }
 }
 
 /// Run all the tests with a different configuration.
@@ -1152,6 +1373,14 @@ macro_rules! test_hamt_mod {
                 }
             }
 
+            #[test]
+            #[cfg(feature = "rayon")]
+            fn par_for_each() {
+                for s in super::SIZE_FACTORS {
+                    super::par_for_each(*s, $factory, CidChecker::empty())
+                }
+            }
+
             #[test]
             fn for_each_ranged() {
                 for s in super::SIZE_FACTORS {
@@ -1164,6 +1393,13 @@ macro_rules! test_hamt_mod {
                 super::clean_child_ordering($factory, None, CidChecker::empty())
             }
 
+            #[test]
+            fn diff() {
+                for s in super::SIZE_FACTORS {
+                    super::diff(*s, $factory)
+                }
+            }
+
             #[quickcheck]
             fn prop_cid_indep_of_insert_order(
                 kvs: UniqueKeyValuePairs<u8, i64>,
@@ -1176,6 +1412,22 @@ macro_rules! test_hamt_mod {
             fn prop_cid_ops_reduced(ops: LimitedKeyOps<10>) -> bool {
                 super::prop_cid_ops_reduced($factory, ops)
             }
+
+            #[quickcheck]
+            fn prop_iter_order_indep_of_insert_order(
+                kvs: UniqueKeyValuePairs<u8, i64>,
+                seed: u64,
+            ) -> bool {
+                super::prop_iter_order_indep_of_insert_order($factory, kvs, seed)
+            }
+
+            #[quickcheck]
+            fn prop_from_sorted_iter_matches_set(
+                kvs: UniqueKeyValuePairs<u8, i64>,
+                seed: u64,
+            ) -> bool {
+                super::prop_from_sorted_iter_matches_set($factory, kvs, seed)
+            }
         }
     };
 }
@@ -0,0 +1,101 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::iter::FusedIterator;
+
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::CborStore;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::node::Node;
+use crate::pointer::Pointer;
+use crate::{Error, Kamt, KeyValuePair};
+
+/// Iterator over KAMT Key/Value tuples.
+///
+/// Entries are yielded in the order of their hashed keys (most significant bits first), which
+/// only depends on the set of keys stored in the KAMT and not on the order in which they were
+/// inserted, nor on which nodes happen to be cached.
+pub struct Iter<'a, BS, K, V, H, const N: usize = 32> {
+    store: &'a BS,
+    stack: Vec<std::slice::Iter<'a, Pointer<K, V, H, N>>>,
+    current: std::slice::Iter<'a, KeyValuePair<K, V>>,
+}
+
+impl<'a, BS, K, V, H, const N: usize> Iter<'a, BS, K, V, H, N> {
+    pub(crate) fn new(store: &'a BS, root: &'a Node<K, V, H, N>) -> Self {
+        Self {
+            store,
+            stack: vec![root.pointers.iter()],
+            current: [].iter(),
+        }
+    }
+}
+
+impl<'a, BS, K, V, H, const N: usize> Iterator for Iter<'a, BS, K, V, H, N>
+where
+    BS: Blockstore,
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+{
+    type Item = Result<(&'a K, &'a V), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(v) = self.current.next() {
+            return Some(Ok((v.key(), v.value())));
+        }
+        loop {
+            let Some(next) = self.stack.last_mut()?.next() else {
+                self.stack.pop();
+                continue;
+            };
+            match next {
+                Pointer::Link { cid, cache, .. } => {
+                    let node = if let Some(cached_node) = cache.get() {
+                        cached_node
+                    } else {
+                        match self.store.get_cbor(cid) {
+                            // Ignore error intentionally, the cache value will always be the same
+                            Ok(Some(node)) => cache.get_or_init(|| node),
+                            #[cfg(not(feature = "ignore-dead-links"))]
+                            Ok(None) => return Some(Err(Error::CidNotFound(cid.to_string()))),
+                            #[cfg(feature = "ignore-dead-links")]
+                            Ok(None) => continue,
+                            Err(e) => return Some(Err(e.into())),
+                        }
+                    };
+                    self.stack.push(node.pointers.iter())
+                }
+                Pointer::Dirty { node, .. } => self.stack.push(node.pointers.iter()),
+                Pointer::Values(kvs) => {
+                    self.current = kvs.iter();
+                    if let Some(v) = self.current.next() {
+                        return Some(Ok((v.key(), v.value())));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a, BS, K, V, H, const N: usize> FusedIterator for Iter<'a, BS, K, V, H, N>
+where
+    BS: Blockstore,
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+{
+}
+
+impl<'a, BS, K, V, H, const N: usize> IntoIterator for &'a Kamt<BS, K, V, H, N>
+where
+    BS: Blockstore,
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    type Item = Result<(&'a K, &'a V), Error>;
+    type IntoIter = Iter<'a, BS, K, V, H, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -11,6 +11,7 @@ use multihash::Code;
 use serde::de::DeserializeOwned;
 use serde::{Serialize, Serializer};
 
+use crate::iter::Iter;
 use crate::node::Node;
 use crate::{AsHashedKey, Config, Error};
 
@@ -138,6 +139,39 @@ where
     pub fn is_empty(&self) -> bool {
         self.root.is_empty()
     }
+
+    /// Iterate over the KAMT. Alternatively, you can directly iterate over the KAMT without calling
+    /// this method:
+    ///
+    /// ```rust
+    /// use fvm_ipld_kamt::Kamt;
+    /// use fvm_ipld_kamt::id::Identity;
+    ///
+    /// let store = fvm_ipld_blockstore::MemoryBlockstore::default();
+    ///
+    /// let mut map: Kamt<_, u32, _, Identity> = Kamt::new(store);
+    /// map.set(1, "a".to_string()).unwrap();
+    ///
+    /// for kv in &map {
+    ///     let (k, v) = kv?;
+    ///     println!("{k:?}: {v}");
+    /// }
+    ///
+    /// # anyhow::Ok(())
+    /// ```
+    pub fn iter(&self) -> Iter<BS, K, V, H, N> {
+        Iter::new(&self.store, &self.root)
+    }
+
+    /// Iterate over the KAMT in its canonical order.
+    ///
+    /// Entries are ordered by their hashed keys, most significant bits first. Entries sharing a
+    /// bucket are ordered by key. The order only depends on the set of keys in the KAMT and is
+    /// stable across versions of this crate and across platforms; it is the same order as
+    /// [`Kamt::iter`] and [`Kamt::for_each`] which callers may rely on.
+    pub fn canonical_iter(&self) -> Iter<BS, K, V, H, N> {
+        self.iter()
+    }
 }
 
 impl<BS, K, V, H, const N: usize> Kamt<BS, K, V, H, N>
@@ -19,6 +19,7 @@ mod error;
 mod ext;
 mod hash_bits;
 pub mod id;
+mod iter;
 mod kamt;
 mod node;
 mod pointer;
@@ -28,6 +29,7 @@ use std::borrow::Cow;
 use serde::{Deserialize, Serialize};
 
 pub use self::error::Error;
+pub use self::iter::Iter;
 pub use self::kamt::Kamt;
 
 /// Default bit width for indexing a hash at each depth level
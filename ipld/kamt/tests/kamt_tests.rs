@@ -171,6 +171,46 @@ fn for_each(factory: KamtFactory) {
     assert_eq!(sum, expected_sum);
 }
 
+fn canonical_iter(factory: KamtFactory) {
+    let store = MemoryBlockstore::default();
+
+    // With the identity hash, the canonical order is the byte order of the keys.
+    let keys = ["7", "aa", "10", "b", "1", "zz", "a", "100", "c", "0"].map(kstring);
+    let mut expected = keys.to_vec();
+    expected.sort_unstable();
+
+    let mut kamt: HKamt<_, u32, HashedKey<32>> = factory.new(&store);
+    for (i, k) in keys.iter().enumerate() {
+        kamt.set(*k, i as u32).unwrap();
+    }
+
+    let collect_keys = |kamt: &HKamt<_, u32, HashedKey<32>>| -> Vec<HashedKey<32>> {
+        kamt.canonical_iter()
+            .map(|kv| kv.map(|(k, _)| *k))
+            .collect::<Result<_, _>>()
+            .unwrap()
+    };
+
+    // Iterating through kamt with dirty caches.
+    assert_eq!(collect_keys(&kamt), expected);
+
+    let c = kamt.flush().unwrap();
+    let kamt: HKamt<_, u32, HashedKey<32>> = factory.load(&c, &store).unwrap();
+
+    // Iterating through kamt with no cache, then with cached nodes.
+    assert_eq!(collect_keys(&kamt), expected);
+    assert_eq!(collect_keys(&kamt), expected);
+
+    // The iterator and `for_each` agree.
+    let mut visited = Vec::new();
+    kamt.for_each(|k, _| {
+        visited.push(*k);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(visited, expected);
+}
+
 /// List of key value pairs with unique keys.
 ///
 /// Uniqueness is used so insert order doesn't cause overwrites.
@@ -358,6 +398,11 @@ macro_rules! test_kamt_mod {
                 super::for_each($factory)
             }
 
+            #[test]
+            fn canonical_iter() {
+                super::canonical_iter($factory)
+            }
+
             #[quickcheck]
             fn prop_cid_indep_of_insert_order(
                 kvs: UniqueKeyValuePairs<u8, i64>,
@@ -5,8 +5,9 @@ use fvm_ipld_encoding::to_vec;
 use fvm_shared::address::Address;
 use fvm_shared::consensus::ConsensusFault;
 use fvm_shared::crypto::hash::SupportedHashes;
+use fvm_shared::crypto::merkle::MerkleProof;
 use fvm_shared::crypto::signature::{
-    Signature, SECP_PUB_LEN, SECP_SIG_LEN, SECP_SIG_MESSAGE_HASH_SIZE,
+    Signature, SignatureBatchEntry, SECP_PUB_LEN, SECP_SIG_LEN, SECP_SIG_MESSAGE_HASH_SIZE,
 };
 use fvm_shared::piece::PieceInfo;
 use fvm_shared::sector::{
@@ -106,6 +107,34 @@ pub fn hash_into(hasher: SupportedHashes, data: &[u8], digest: &mut [u8]) -> usi
     }
 }
 
+/// Verifies a Merkle proof over a (possibly foreign) data structure.
+///
+/// NOTE: This is only available from network version 22.
+pub fn verify_merkle_proof(proof: &MerkleProof) -> SyscallResult<bool> {
+    let proof = to_vec(proof).expect("failed to marshal merkle proof");
+    unsafe {
+        sys::crypto::verify_merkle_proof(proof.as_ptr(), proof.len() as u32)
+            .map(status_code_to_bool)
+    }
+}
+
+/// Verifies a batch of signatures, returning whether each entry's signature is valid.
+///
+/// NOTE: This only supports f1 and f3 addresses, and is only available from network version 22.
+pub fn verify_signatures_batch(entries: &[SignatureBatchEntry]) -> SyscallResult<Vec<bool>> {
+    let entries_buf = to_vec(entries).expect("failed to marshal signature batch");
+    let mut results = vec![0u8; entries.len()];
+    unsafe {
+        sys::crypto::verify_signatures_batch(
+            entries_buf.as_ptr(),
+            entries_buf.len() as u32,
+            results.as_mut_ptr(),
+            results.len() as u32,
+        )?;
+    }
+    Ok(results.into_iter().map(|valid| valid == 1).collect())
+}
+
 /// Computes an unsealed sector CID (CommD) from its constituent piece CIDs (CommPs) and sizes.
 pub fn compute_unsealed_sector_cid(
     proof_type: RegisteredSealProof,
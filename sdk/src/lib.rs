@@ -13,6 +13,7 @@ pub mod rand;
 pub mod send;
 pub mod sself;
 pub mod sys;
+pub mod util;
 pub mod vm;
 
 /// BlockID representing nil parameters or return data.
@@ -47,3 +47,36 @@ pub fn send(
         build_response(send)
     }
 }
+
+/// Defers a message to another actor until the current message's call stack has unwound. The
+/// deferred send executes with the given gas limit; its outcome is not reported back.
+///
+/// NOTE: This is only available from network version 22.
+pub fn defer_send(
+    to: &Address,
+    method: MethodNum,
+    params: Option<IpldBlock>,
+    value: TokenAmount,
+    gas_limit: u64,
+) -> SyscallResult<()> {
+    let recipient = to.to_bytes();
+    let value: sys::TokenAmount = value
+        .try_into()
+        .map_err(|_| ErrorNumber::InsufficientFunds)?;
+    unsafe {
+        let params_id = match params {
+            Some(p) => sys::ipld::block_create(p.codec, p.data.as_ptr(), p.data.len() as u32)?,
+            None => NO_DATA_BLOCK_ID,
+        };
+
+        sys::send::defer_send(
+            recipient.as_ptr(),
+            recipient.len() as u32,
+            method,
+            params_id,
+            value.hi,
+            value.lo,
+            gas_limit,
+        )
+    }
+}
@@ -83,6 +83,53 @@ super::fvm_syscalls! {
         digest_len: u32,
     ) -> Result<u32>;
 
+    /// Verifies a Merkle proof over a (possibly foreign) data structure, such as a binary SHA-256
+    /// tree or a Keccak-256 Merkle-Patricia trie.
+    ///
+    /// Available from network version 22.
+    ///
+    /// Returns 0 to indicate that the proof was valid, -1 otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// `proof_off` and `proof_len` specify the location and length of a cbor-encoded
+    /// [`MerkleProof`][fvm_shared::crypto::merkle::MerkleProof] in tuple representation.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                        |
+    /// |---------------------|-----------------------------------------------|
+    /// | [`IllegalArgument`] | the proof is malformed or the hash is unknown |
+    pub fn verify_merkle_proof(proof_off: *const u8, proof_len: u32) -> Result<i32>;
+
+    /// Verifies a batch of signatures for f1 or f3 addresses. Batches of BLS signatures are
+    /// verified together, costing less gas than verifying each signature individually.
+    ///
+    /// Available from network version 22.
+    ///
+    /// Writes one byte per entry into the results buffer (1 if the entry's signature is valid, 0
+    /// otherwise), and returns the number of valid signatures.
+    ///
+    /// # Arguments
+    ///
+    /// - `entries_off` and `entries_len` specify the location and length of a cbor-encoded list of
+    ///   [`SignatureBatchEntry`][fvm_shared::crypto::signature::SignatureBatchEntry] in tuple
+    ///   representation.
+    /// - `results_off` and `results_len` specify the location and length of the results buffer.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                    |
+    /// |---------------------|-----------------------------------------------------------|
+    /// | [`IllegalArgument`] | the entries are malformed or contain a non-key address    |
+    /// | [`BufferTooSmall`]  | the results buffer is shorter than the number of entries  |
+    pub fn verify_signatures_batch(
+        entries_off: *const u8,
+        entries_len: u32,
+        results_off: *mut u8,
+        results_len: u32,
+    ) -> Result<u32>;
+
     /// Computes an unsealed sector CID (CommD) from its constituent piece CIDs
     /// (CommPs) and sizes.
     ///
@@ -66,6 +66,7 @@ pub mod network;
 pub mod rand;
 pub mod send;
 pub mod sself;
+pub mod util;
 pub mod vm;
 
 /// Generate a set of FVM syscall shims.
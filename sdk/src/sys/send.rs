@@ -24,7 +24,7 @@ super::fvm_syscalls! {
     /// - `method` is the method number to invoke.
     /// - `params` is the IPLD block handle of the method parameters.
     /// - `value_hi` are the "high" bits of the token value to send (little-endian) in attoFIL.
-    /// - `value_lo` are the "high" bits of the token value to send (little-endian) in attoFIL.
+    /// - `value_lo` are the "low" bits of the token value to send (little-endian) in attoFIL.
     /// - `gas_limit` is the gas this send is allowed to use. Zero means "all available gas".
     /// - `send_flags` are additional send flags.
     ///
@@ -45,6 +45,7 @@ super::fvm_syscalls! {
     /// | [`LimitExceeded`]     | recursion limit reached.                             |
     /// | [`IllegalArgument`]   | invalid recipient address buffer.                    |
     /// | [`ReadOnly`]          | the send would mutate state in read-only mode.       |
+    /// | [`Reentrant`]         | the recipient is already on the call stack (NV22+).  |
     pub fn send(
         recipient_off: *const u8,
         recipient_len: u32,
@@ -55,4 +56,41 @@ super::fvm_syscalls! {
         gas_limit: u64,
         flags: SendFlags,
     ) -> Result<Send>;
+
+    /// Defers a message to another actor until the current message's call stack has unwound.
+    ///
+    /// Deferred sends are executed in the order in which they were deferred, after the top-level
+    /// call returns successfully. Each executes in its own transaction, with its own gas limit:
+    /// its failure is reverted but doesn't affect the rest of the message, and its result isn't
+    /// reported back. Sends deferred by a call that subsequently fails are discarded.
+    ///
+    /// Available from network version 22.
+    ///
+    /// # Arguments
+    ///
+    /// - `recipient_off` and `recipient_len` specify the location and length of the recipient's
+    ///   address (in wasm memory).
+    /// - `method` is the method number to invoke.
+    /// - `params` is the IPLD block handle of the method parameters.
+    /// - `value_hi` are the "high" bits of the token value to send (little-endian) in attoFIL.
+    /// - `value_lo` are the "low" bits of the token value to send (little-endian) in attoFIL.
+    /// - `gas_limit` is the gas the deferred send is allowed to use.
+    ///
+    /// # Errors
+    ///
+    /// | Error                 | Reason                                               |
+    /// |-----------------------|------------------------------------------------------|
+    /// | [`InvalidHandle`]     | parameters block not found.                          |
+    /// | [`LimitExceeded`]     | too many sends have been deferred.                   |
+    /// | [`IllegalArgument`]   | invalid recipient address buffer.                    |
+    /// | [`ReadOnly`]          | the caller is executing in read-only mode.           |
+    pub fn defer_send(
+        recipient_off: *const u8,
+        recipient_len: u32,
+        method: u64,
+        params: u32,
+        value_hi: u64,
+        value_lo: u64,
+        gas_limit: u64,
+    ) -> Result<()>;
 }
@@ -0,0 +1,33 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Syscalls for general-purpose utilities.
+
+// for documentation links
+#[cfg(doc)]
+use crate::sys::ErrorNumber::*;
+
+super::fvm_syscalls! {
+    module = "util";
+
+    /// Validates that a string is UTF-8. This is much cheaper than validating in Wasm, with a
+    /// small per-byte gas charge.
+    ///
+    /// Available from network version 22.
+    ///
+    /// Returns 0 if the string is valid UTF-8, or -1 if it isn't.
+    ///
+    /// The string is not normalized. Unicode normalization depends on the version of the Unicode
+    /// tables, so exposing it here would make those tables part of consensus; actors that need
+    /// canonical strings should restrict them to a normalization-stable subset (e.g., ASCII).
+    ///
+    /// # Arguments
+    ///
+    /// - `str_off` and `str_len` specify location and length of the string.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                          |
+    /// |---------------------|---------------------------------|
+    /// | [`IllegalArgument`] | the string buffer is invalid    |
+    pub fn validate_utf8(str_off: *const u8, str_len: u32) -> Result<i32>;
+}
@@ -0,0 +1,19 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use crate::{status_code_to_bool, sys};
+
+/// Validates that the bytes are UTF-8, returning them as a string if so.
+///
+/// This is much cheaper than [`std::str::from_utf8`] in Wasm, so it should be preferred for
+/// validating user-supplied strings (e.g., names and labels).
+///
+/// NOTE: This is only available from network version 22.
+pub fn validate_utf8(bytes: &[u8]) -> Option<&str> {
+    let valid = unsafe {
+        sys::util::validate_utf8(bytes.as_ptr(), bytes.len() as u32)
+            .map(status_code_to_bool)
+            .expect("failed to validate utf8")
+    };
+    // SAFETY: the FVM validated the bytes.
+    valid.then(|| unsafe { std::str::from_utf8_unchecked(bytes) })
+}
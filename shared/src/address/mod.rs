@@ -84,11 +84,12 @@ impl Address {
 
     /// Creates address from encoded bytes.
     pub fn from_bytes(bz: &[u8]) -> Result<Self, Error> {
-        if bz.len() < 2 {
-            Err(Error::InvalidLength)
-        } else {
-            let protocol = Protocol::from_byte(bz[0]).ok_or(Error::UnknownProtocol)?;
-            Self::new(protocol, &bz[1..])
+        match bz {
+            [protocol, payload @ ..] if (2..=MAX_ADDRESS_LEN).contains(&bz.len()) => {
+                let protocol = Protocol::from_byte(*protocol).ok_or(Error::UnknownProtocol)?;
+                Self::new(protocol, payload)
+            }
+            _ => Err(Error::InvalidLength),
         }
     }
 
@@ -176,6 +177,22 @@ impl Address {
             _ => Err(Error::NonIDAddress),
         }
     }
+
+    /// Compares two addresses in constant time with respect to their payloads. Only the protocols
+    /// and encoded lengths of the addresses may affect the time taken.
+    pub fn ct_eq(&self, other: &Address) -> bool {
+        constant_time_eq(&self.to_bytes(), &other.to_bytes())
+    }
+}
+
+/// Compares two byte slices in constant time with respect to their contents (but not their
+/// lengths).
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
 }
 
 impl fmt::Display for Address {
@@ -247,19 +264,37 @@ impl quickcheck::Arbitrary for Address {
     }
 }
 
+/// Parses a decimal actor ID. Only plain ASCII digits are accepted (no signs or whitespace).
+fn parse_id(s: &str) -> Result<ActorID, Error> {
+    // 20 is max u64 as string
+    if s.len() > 20 {
+        return Err(Error::InvalidLength);
+    }
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(Error::InvalidPayload);
+    }
+    // This can still overflow if the ID is 20 digits long.
+    s.parse::<u64>().map_err(|_| Error::InvalidPayload)
+}
+
 pub(self) fn parse_address(addr: &str) -> Result<(Address, Network), Error> {
     if addr.len() > MAX_ADDRRESS_TEXT_LEN || addr.len() < 3 {
         return Err(Error::InvalidLength);
     }
-    let network = Network::from_prefix(addr.get(0..1).ok_or(Error::UnknownNetwork)?)?;
+    // The network and protocol are single ASCII characters. Check the bytes directly so we never
+    // slice on a non-char boundary.
+    let network = match addr.as_bytes()[0] {
+        b if b.is_ascii() => Network::from_prefix(&addr[0..1])?,
+        _ => return Err(Error::UnknownNetwork),
+    };
 
     // get protocol from second character
-    let protocol: Protocol = match addr.get(1..2).ok_or(Error::UnknownProtocol)? {
-        "0" => Protocol::ID,
-        "1" => Protocol::Secp256k1,
-        "2" => Protocol::Actor,
-        "3" => Protocol::BLS,
-        "4" => Protocol::Delegated,
+    let protocol: Protocol = match addr.as_bytes()[1] {
+        b'0' => Protocol::ID,
+        b'1' => Protocol::Secp256k1,
+        b'2' => Protocol::Actor,
+        b'3' => Protocol::BLS,
+        b'4' => Protocol::Delegated,
         _ => {
             return Err(Error::UnknownProtocol);
         }
@@ -282,32 +317,21 @@ pub(self) fn parse_address(addr: &str) -> Result<(Address, Network), Error> {
             hasher.update(prefix);
         }
         hasher.update(payload);
-        if hasher.finalize().as_bytes() != csum {
+        if !constant_time_eq(hasher.finalize().as_bytes(), csum) {
             return Err(Error::InvalidChecksum);
         }
         Ok(payload)
     }
 
     // bytes after the protocol character is the data payload of the address
-    let raw = addr.get(2..).ok_or(Error::InvalidPayload)?;
+    let raw = &addr[2..];
     let addr = match protocol {
-        Protocol::ID => {
-            if raw.len() > 20 {
-                // 20 is max u64 as string
-                return Err(Error::InvalidLength);
-            }
-            let id = raw.parse::<u64>()?;
-            Address {
-                payload: Payload::ID(id),
-            }
-        }
+        Protocol::ID => Address {
+            payload: Payload::ID(parse_id(raw)?),
+        },
         Protocol::Delegated => {
             let (id, subaddr) = raw.split_once('f').ok_or(Error::InvalidPayload)?;
-            if id.len() > 20 {
-                // 20 is max u64 as string
-                return Err(Error::InvalidLength);
-            }
-            let id = id.parse::<u64>()?;
+            let id = parse_id(id)?;
             // decode subaddr
             let subaddr_csum = ADDRESS_ENCODER.decode(subaddr.as_bytes())?;
             // validate and split subaddr.
@@ -0,0 +1,51 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use fvm_ipld_encoding::repr::*;
+use fvm_ipld_encoding::{strict_bytes, RawBytes};
+use num_derive::FromPrimitive;
+use serde_tuple::*;
+
+/// The structure of the data proven by a [`MerkleProof`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromPrimitive, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum MerkleScheme {
+    /// A complete k-ary Merkle tree, where each inner node is the hash of the concatenation of its
+    /// children's hashes.
+    ///
+    /// - `key` is the big-endian index of the leaf (at most 8 bytes).
+    /// - `value` is the leaf node itself (usually the hash of the leaf data).
+    /// - `proof` contains, for each level from the leaves up, the `branch_factor - 1` siblings of
+    ///   the node being proven, in order.
+    Tree = 0,
+    /// An Ethereum-style Merkle-Patricia trie with RLP-encoded nodes and a fixed branch factor of
+    /// 16. Nodes are referenced by their hash, or embedded in their parent if their encoding is
+    /// shorter than the hash.
+    ///
+    /// - `key` is the trie path (e.g., the hash of an account address).
+    /// - `value` is the (RLP-encoded) value stored at that path.
+    /// - `proof` contains the RLP-encoded nodes on the path from the root, excluding embedded
+    ///   nodes.
+    Patricia = 1,
+}
+
+/// A proof that a value is included in a Merkle structure with the given root.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize_tuple, Deserialize_tuple)]
+pub struct MerkleProof {
+    /// The structure of the proven data.
+    pub scheme: MerkleScheme,
+    /// The multihash code of the hash function used to hash nodes (e.g., SHA2-256 or Keccak-256).
+    pub hash_function: u64,
+    /// The number of children of each inner node. Ignored by the Patricia scheme.
+    pub branch_factor: u32,
+    /// The expected root hash.
+    #[serde(with = "strict_bytes")]
+    pub root: Vec<u8>,
+    /// The key (or index) of the proven value.
+    #[serde(with = "strict_bytes")]
+    pub key: Vec<u8>,
+    /// The proven value.
+    #[serde(with = "strict_bytes")]
+    pub value: Vec<u8>,
+    /// The proof nodes. See [`MerkleScheme`] for their layout.
+    pub proof: Vec<RawBytes>,
+}
@@ -9,9 +9,10 @@ use fvm_ipld_encoding::repr::*;
 use fvm_ipld_encoding::{de, ser, strict_bytes, Error as EncodingError};
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
+use serde_tuple::*;
 use thiserror::Error;
 
-use crate::address::Error as AddressError;
+use crate::address::{Address, Error as AddressError};
 
 /// BLS signature length in bytes.
 pub const BLS_SIG_LEN: usize = 96;
@@ -105,6 +106,20 @@ impl Signature {
     }
 }
 
+/// A single entry of a batch signature verification.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize_tuple, Deserialize_tuple)]
+pub struct SignatureBatchEntry {
+    pub sig_type: SignatureType,
+    /// The signature bytes, without the signature type byte.
+    #[serde(with = "strict_bytes")]
+    pub signature: Vec<u8>,
+    /// The expected signer, which must be an f1 or f3 address.
+    pub signer: Address,
+    /// The signed data (usually a digest of the message).
+    #[serde(with = "strict_bytes")]
+    pub digest: Vec<u8>,
+}
+
 #[cfg(feature = "arb")]
 impl quickcheck::Arbitrary for SignatureType {
     fn arbitrary(g: &mut quickcheck::Gen) -> Self {
@@ -260,6 +275,93 @@ pub mod ops {
         verify_messages(&sig, data, &pks[..])
     }
 
+    /// The domain separation tag of Filecoin's BLS signatures.
+    #[cfg(feature = "blst")]
+    const BLS_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
+
+    /// Verifies a batch of independent bls signatures. Returns false if any signature is malformed
+    /// or invalid.
+    ///
+    /// Unlike verifying their aggregate, this can't be fooled by invalid signatures cancelling
+    /// each other out: each signature (and the matching pairing) is weighted by a 64-bit scalar
+    /// derived from the hash of the whole batch, so a batch of invalid signatures only passes
+    /// with negligible probability. The scalars are deterministic, so all nodes agree on the
+    /// result.
+    pub fn verify_bls_batch(signatures: &[&[u8]], data: &[&[u8]], pub_keys: &[&[u8]]) -> bool {
+        if signatures.len() != data.len() || data.len() != pub_keys.len() {
+            return false;
+        }
+        if signatures.is_empty() {
+            return true;
+        }
+
+        verify_bls_batch_weighted(signatures, data, pub_keys)
+    }
+
+    #[cfg(feature = "blst")]
+    fn verify_bls_batch_weighted(signatures: &[&[u8]], data: &[&[u8]], pub_keys: &[&[u8]]) -> bool {
+        use blst::min_pk::{PublicKey as BlstPubKey, Signature as BlstSignature};
+        use blst::{blst_scalar, BLST_ERROR};
+
+        let (Ok(sigs), Ok(pks)) = (
+            signatures
+                .iter()
+                .map(|x| BlstSignature::from_bytes(x))
+                .collect::<Result<Vec<_>, _>>(),
+            pub_keys
+                .iter()
+                .map(|x| BlstPubKey::from_bytes(x))
+                .collect::<Result<Vec<_>, _>>(),
+        ) else {
+            return false;
+        };
+
+        // Commit to the whole batch, so the scalars can't be predicted when choosing the
+        // signatures.
+        let mut batch = blake2b_simd::Params::new().hash_length(32).to_state();
+        for part in signatures.iter().chain(data).chain(pub_keys) {
+            batch.update(&(part.len() as u64).to_le_bytes());
+            batch.update(part);
+        }
+        let batch = batch.finalize();
+        let rands: Vec<_> = (0..signatures.len() as u64)
+            .map(|i| {
+                let hash = blake2b_simd::Params::new()
+                    .hash_length(32)
+                    .to_state()
+                    .update(batch.as_bytes())
+                    .update(&i.to_le_bytes())
+                    .finalize();
+                let mut b = [0u8; 32];
+                b[..8].copy_from_slice(&hash.as_bytes()[..8]);
+                // Never zero, which would drop the signature from the batch.
+                b[0] |= 1;
+                blst_scalar { b }
+            })
+            .collect();
+
+        let sigs: Vec<_> = sigs.iter().collect();
+        let pks: Vec<_> = pks.iter().collect();
+        BlstSignature::verify_multiple_aggregate_signatures(
+            data, BLS_DST, &pks, true, &sigs, true, &rands, 64,
+        ) == BLST_ERROR::BLST_SUCCESS
+    }
+
+    /// Without blst, we can't weight the signatures, so we verify them one by one.
+    #[cfg(not(feature = "blst"))]
+    fn verify_bls_batch_weighted(signatures: &[&[u8]], data: &[&[u8]], pub_keys: &[&[u8]]) -> bool {
+        signatures
+            .iter()
+            .zip(data)
+            .zip(pub_keys)
+            .all(|((sig, data), pk)| {
+                match (BlsSignature::from_bytes(sig), BlsPubKey::from_bytes(pk)) {
+                    (Ok(sig), Ok(pk)) => verify_messages(&sig, &[data], &[pk]),
+                    _ => false,
+                }
+            })
+    }
+
     /// Return the public key used for signing a message given it's signing bytes hash and signature.
     pub fn recover_secp_public_key(
         hash: &[u8; SECP_SIG_MESSAGE_HASH_SIZE],
@@ -306,8 +408,7 @@ mod tests {
 
     use super::ops::recover_secp_public_key;
     use super::*;
-    use crate::crypto::signature::ops::{ecrecover, verify_bls_aggregate};
-    use crate::Address;
+    use crate::crypto::signature::ops::{ecrecover, verify_bls_aggregate, verify_bls_batch};
 
     #[test]
     fn bls_agg_verify() {
@@ -342,6 +443,59 @@ mod tests {
         ),);
     }
 
+    #[test]
+    fn bls_batch_verify() {
+        let num_sigs = 4;
+        let rng = &mut ChaCha8Rng::seed_from_u64(12);
+
+        let private_keys: Vec<PrivateKey> =
+            (0..num_sigs).map(|_| PrivateKey::generate(rng)).collect();
+        let public_keys: Vec<_> = private_keys
+            .iter()
+            .map(|x| x.public_key().as_bytes())
+            .collect();
+        let data: Vec<Vec<u8>> = (0..num_sigs).map(|x| vec![x as u8; 32]).collect();
+        let mut signatures: Vec<_> = (0..num_sigs)
+            .map(|x| private_keys[x].sign(&data[x]).as_bytes())
+            .collect();
+
+        let public_keys_slice: Vec<&[u8]> = public_keys.iter().map(|x| &**x).collect();
+        let data_slice: Vec<&[u8]> = data.iter().map(|x| &**x).collect();
+        let verify = |signatures: &[Vec<u8>]| {
+            let sigs: Vec<&[u8]> = signatures.iter().map(|x| &**x).collect();
+            verify_bls_batch(&sigs, &data_slice, &public_keys_slice)
+        };
+
+        assert!(verify(&signatures));
+        assert!(verify_bls_batch(&[], &[], &[]));
+
+        // Replacing one signature with a signature over different data fails the batch.
+        signatures[1] = private_keys[1].sign(&data[0]).as_bytes();
+        assert!(!verify(&signatures));
+
+        // As does a malformed signature.
+        signatures[1] = vec![0; BLS_SIG_LEN];
+        assert!(!verify(&signatures));
+
+        // Invalid signatures whose aggregate is valid fail the batch: here, the first two
+        // signatures are swapped.
+        signatures = (0..num_sigs)
+            .map(|x| private_keys[x].sign(&data[x]).as_bytes())
+            .collect();
+        signatures.swap(0, 1);
+        let sigs: Vec<_> = signatures
+            .iter()
+            .map(|x| BlsSignature::from_bytes(x).unwrap())
+            .collect();
+        let aggregate = bls_signatures::aggregate(&sigs).unwrap();
+        assert!(verify_bls_aggregate(
+            &data_slice,
+            &public_keys_slice,
+            &Signature::new_bls(aggregate.as_bytes())
+        ));
+        assert!(!verify(&signatures));
+    }
+
     #[test]
     fn recover_pubkey() {
         let rng = &mut ChaCha8Rng::seed_from_u64(8);
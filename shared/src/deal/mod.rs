@@ -1,3 +1,32 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
-pub type DealID = u64;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// DealID is a numeric identifier for a storage deal, assigned by the storage market actor.
+///
+/// Deal IDs are encoded as plain integers.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct DealID(pub u64);
+
+impl From<u64> for DealID {
+    fn from(id: u64) -> Self {
+        DealID(id)
+    }
+}
+
+impl From<DealID> for u64 {
+    fn from(id: DealID) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for DealID {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
@@ -154,6 +154,12 @@ pub enum ErrorNumber {
     BufferTooSmall = 12,
     /// The actor is executing in a read-only context.
     ReadOnly = 13,
+    /// The call would re-enter an actor that is already on the call stack. Reported as
+    /// [`ErrorNumber::Forbidden`] before NV22.
+    Reentrant = 14,
+    /// The syscall isn't supported on this network (e.g., it has been disabled). Reported as
+    /// [`ErrorNumber::Forbidden`] before NV22.
+    NotSupported = 15,
 }
 
 impl std::fmt::Display for ErrorNumber {
@@ -173,6 +179,8 @@ impl std::fmt::Display for ErrorNumber {
             Forbidden => "operation forbidden",
             BufferTooSmall => "buffer too small",
             ReadOnly => "execution context is read-only",
+            Reentrant => "call would re-enter an active actor",
+            NotSupported => "syscall not supported",
         })
     }
 }
@@ -31,6 +31,8 @@ pub mod smooth;
 pub mod state;
 pub mod sys;
 pub mod upgrade;
+#[cfg(feature = "verify")]
+pub mod verify;
 pub mod version;
 
 use crypto::hash::SupportedHashes;
@@ -61,6 +63,10 @@ pub const IDENTITY_HASH: u64 = 0x0;
 pub const MAX_CID_LEN: usize = 100;
 
 /// Identifier for Actors, includes builtin and initialized actors
+///
+/// Unlike [`SectorNumber`](sector::SectorNumber) and [`DealID`](deal::DealID), this is a plain
+/// integer: actor IDs are passed as raw integers across the syscall ABI, and every `u64` is a valid
+/// actor ID.
 pub type ActorID = u64;
 
 /// Default bit width for the hamt in the filecoin protocol.
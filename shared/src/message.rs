@@ -2,12 +2,15 @@
 // Copyright 2019-2022 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::fmt;
+
 use anyhow::anyhow;
-use fvm_ipld_encoding::de::{Deserialize, Deserializer};
+use fvm_ipld_encoding::de::{self, Deserialize, Deserializer, IgnoredAny, SeqAccess, Visitor};
 use fvm_ipld_encoding::ser::{Serialize, Serializer};
 use fvm_ipld_encoding::RawBytes;
 
 use crate::address::Address;
+use crate::clock::ChainEpoch;
 use crate::econ::TokenAmount;
 use crate::MethodNum;
 
@@ -25,6 +28,14 @@ pub struct Message {
     pub gas_limit: u64,
     pub gas_fee_cap: TokenAmount,
     pub gas_premium: TokenAmount,
+    /// The last epoch at which the message may be executed, if it expires. Expired messages fail
+    /// pre-validation. Only encoded (as a trailing field) when set, so messages without an
+    /// expiration keep their encoding and CID.
+    ///
+    /// Expirations are only honoured from
+    /// [`NetworkVersion::V22`](crate::version::NetworkVersion::V22): before, messages with an
+    /// expiration fail pre-validation.
+    pub valid_until_epoch: Option<ChainEpoch>,
 }
 
 impl Message {
@@ -39,6 +50,11 @@ impl Message {
         }
         Ok(())
     }
+
+    /// Returns true if the message expired before the given epoch.
+    pub fn is_expired(&self, epoch: ChainEpoch) -> bool {
+        matches!(self.valid_until_epoch, Some(valid_until) if epoch > valid_until)
+    }
 }
 
 impl Serialize for Message {
@@ -46,6 +62,22 @@ impl Serialize for Message {
     where
         S: Serializer,
     {
+        if let Some(valid_until_epoch) = &self.valid_until_epoch {
+            return (
+                &self.version,
+                &self.to,
+                &self.from,
+                &self.sequence,
+                &self.value,
+                &self.gas_limit,
+                &self.gas_fee_cap,
+                &self.gas_premium,
+                &self.method_num,
+                &self.params,
+                valid_until_epoch,
+            )
+                .serialize(s);
+        }
         (
             &self.version,
             &self.to,
@@ -67,30 +99,57 @@ impl<'de> Deserialize<'de> for Message {
     where
         D: Deserializer<'de>,
     {
-        let (
-            version,
-            to,
-            from,
-            sequence,
-            value,
-            gas_limit,
-            gas_fee_cap,
-            gas_premium,
-            method_num,
-            params,
-        ) = Deserialize::deserialize(deserializer)?;
-        Ok(Self {
-            version,
-            from,
-            to,
-            sequence,
-            value,
-            method_num,
-            params,
-            gas_limit,
-            gas_fee_cap,
-            gas_premium,
-        })
+        struct MessageVisitor;
+
+        impl<'de> Visitor<'de> for MessageVisitor {
+            type Value = Message;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a message tuple of 10 or 11 fields")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Message, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                macro_rules! field {
+                    ($idx:expr) => {
+                        seq.next_element()?
+                            .ok_or_else(|| de::Error::invalid_length($idx, &self))?
+                    };
+                }
+                let version = field!(0);
+                let to = field!(1);
+                let from = field!(2);
+                let sequence = field!(3);
+                let value = field!(4);
+                let gas_limit = field!(5);
+                let gas_fee_cap = field!(6);
+                let gas_premium = field!(7);
+                let method_num = field!(8);
+                let params = field!(9);
+                // The expiration is omitted (not null) when unset, keeping the encoding canonical.
+                let valid_until_epoch = seq.next_element()?;
+                if seq.next_element::<IgnoredAny>()?.is_some() {
+                    return Err(de::Error::invalid_length(12, &self));
+                }
+                Ok(Message {
+                    version,
+                    from,
+                    to,
+                    sequence,
+                    value,
+                    method_num,
+                    params,
+                    gas_limit,
+                    gas_fee_cap,
+                    gas_premium,
+                    valid_until_epoch,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(MessageVisitor)
     }
 }
 
@@ -108,6 +167,7 @@ impl quickcheck::Arbitrary for Message {
             gas_limit: u64::arbitrary(g),
             gas_fee_cap: TokenAmount::arbitrary(g),
             gas_premium: TokenAmount::arbitrary(g),
+            valid_until_epoch: Option::arbitrary(g),
         }
     }
 }
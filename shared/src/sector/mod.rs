@@ -12,6 +12,7 @@ use fvm_ipld_encoding::repr::*;
 use fvm_ipld_encoding::tuple::*;
 use num_bigint::BigInt;
 use num_derive::FromPrimitive;
+use serde::{de, Deserialize, Deserializer, Serialize};
 
 pub use self::post::*;
 pub use self::registered_proof::*;
@@ -19,11 +20,73 @@ pub use self::seal::*;
 use crate::ActorID;
 
 /// SectorNumber is a numeric identifier for a sector. It is usually relative to a miner.
-pub type SectorNumber = u64;
+///
+/// Sector numbers may not exceed [`MAX_SECTOR_NUMBER`]. This is checked on construction and when
+/// decoding, and sector numbers are otherwise encoded as plain integers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(transparent)]
+pub struct SectorNumber(u64);
 
 /// The maximum assignable sector number.
 /// Raising this would require modifying our AMT implementation.
-pub const MAX_SECTOR_NUMBER: SectorNumber = i64::MAX as u64;
+pub const MAX_SECTOR_NUMBER: SectorNumber = SectorNumber(i64::MAX as u64);
+
+/// Error returned when constructing a [`SectorNumber`] above [`MAX_SECTOR_NUMBER`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("sector number {0} exceeds the maximum sector number")]
+pub struct SectorNumberOutOfRange(pub u64);
+
+impl SectorNumber {
+    /// Creates a new sector number, checking that it doesn't exceed [`MAX_SECTOR_NUMBER`].
+    pub const fn new(number: u64) -> Result<Self, SectorNumberOutOfRange> {
+        if number > MAX_SECTOR_NUMBER.0 {
+            return Err(SectorNumberOutOfRange(number));
+        }
+        Ok(SectorNumber(number))
+    }
+
+    /// Returns the sector number as an integer.
+    pub const fn value(self) -> u64 {
+        self.0
+    }
+}
+
+impl TryFrom<u64> for SectorNumber {
+    type Error = SectorNumberOutOfRange;
+
+    fn try_from(number: u64) -> Result<Self, Self::Error> {
+        SectorNumber::new(number)
+    }
+}
+
+impl From<SectorNumber> for u64 {
+    fn from(number: SectorNumber) -> Self {
+        number.0
+    }
+}
+
+impl fmt::Display for SectorNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<'de> Deserialize<'de> for SectorNumber {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let number = u64::deserialize(deserializer)?;
+        SectorNumber::new(number).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(feature = "arb")]
+impl quickcheck::Arbitrary for SectorNumber {
+    fn arbitrary(g: &mut quickcheck::Gen) -> Self {
+        SectorNumber(u64::arbitrary(g) % (MAX_SECTOR_NUMBER.0 + 1))
+    }
+}
 
 /// Unit of storage power (measured in bytes)
 pub type StoragePower = BigInt;
@@ -57,3 +120,39 @@ pub struct SectorID {
     pub miner: ActorID,
     pub number: SectorNumber,
 }
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_encoding::{from_slice, to_vec};
+
+    use super::*;
+
+    #[test]
+    fn sector_number_range() {
+        assert_eq!(SectorNumber::new(0).unwrap().value(), 0);
+        assert_eq!(SectorNumber::new(i64::MAX as u64), Ok(MAX_SECTOR_NUMBER));
+        assert_eq!(
+            SectorNumber::try_from(i64::MAX as u64 + 1),
+            Err(SectorNumberOutOfRange(i64::MAX as u64 + 1))
+        );
+    }
+
+    #[test]
+    fn sector_number_encoding() {
+        // Sector numbers are encoded as plain integers.
+        let number = SectorNumber::new(1234).unwrap();
+        let encoded = to_vec(&number).unwrap();
+        assert_eq!(encoded, to_vec(&1234u64).unwrap());
+        assert_eq!(from_slice::<SectorNumber>(&encoded).unwrap(), number);
+
+        // Out of range sector numbers are rejected when decoding.
+        let encoded = to_vec(&u64::MAX).unwrap();
+        assert!(from_slice::<SectorNumber>(&encoded).is_err());
+        let encoded = to_vec(&SectorID {
+            miner: 1,
+            number: MAX_SECTOR_NUMBER,
+        })
+        .unwrap();
+        assert!(from_slice::<SectorID>(&encoded).is_ok());
+    }
+}
@@ -69,6 +69,7 @@ impl SendFlags {
 
 /// A fixed sized struct for serializing an [event `Entry`](crate::event::Entry) separately from the
 /// key/value bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(C, packed)]
 pub struct EventEntry {
     pub flags: crate::event::Flags,
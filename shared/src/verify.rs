@@ -0,0 +1,121 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Routines to recompute and verify the roots the FVM commits to: message CIDs, receipt roots,
+//! and event roots. The FVM produces these roots with the same functions, so anything verified
+//! here (e.g., by a light client or an actor on another chain) matches what the FVM computed.
+//!
+//! This module is enabled by the `verify` feature. Like the rest of this crate it isn't `no_std`:
+//! it also uses `thiserror`, and `anyhow` through the blockstore and AMT crates. It doesn't need a
+//! blockstore implementation, the syscalls, or any of the crypto features, and doesn't use any OS
+//! services, so it can be compiled into actors and other Wasm contracts that link `std`.
+use cid::Cid;
+use fvm_ipld_amt::{Amt, Amtv0};
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::{to_vec, DAG_CBOR};
+use multihash::Multihash;
+
+use crate::crypto::hash::SupportedHashes;
+use crate::event::StampedEvent;
+use crate::message::Message;
+use crate::receipt::Receipt;
+
+/// The bit width of the AMT of the events emitted by a message.
+pub const EVENTS_AMT_BITWIDTH: u32 = 5;
+
+/// An error verifying a root or CID.
+#[derive(thiserror::Error, Debug)]
+pub enum VerifyError {
+    #[error("expected {expected:?}, computed {actual:?}")]
+    Mismatch {
+        expected: Option<Cid>,
+        actual: Option<Cid>,
+    },
+    #[error("failed to encode: {0}")]
+    Encoding(#[from] fvm_ipld_encoding::Error),
+    #[error("failed to build the AMT: {0}")]
+    Amt(#[from] fvm_ipld_amt::Error),
+}
+
+// A blockstore that accepts but discards all insertions, and returns errors on reads.
+// Useful for computing the roots of ephemeral data structures without persisting them, like the
+// events AMT.
+struct DiscardBlockstore;
+
+impl Blockstore for DiscardBlockstore {
+    fn get(&self, _: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+        Err(anyhow::anyhow!(
+            "Blockstore#get not supported with DiscardBlockstore"
+        ))
+    }
+
+    fn put_keyed(&self, _: &Cid, _: &[u8]) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+fn check(expected: Option<&Cid>, actual: Option<Cid>) -> Result<(), VerifyError> {
+    if expected.copied() == actual {
+        Ok(())
+    } else {
+        Err(VerifyError::Mismatch {
+            expected: expected.copied(),
+            actual,
+        })
+    }
+}
+
+/// Computes the CID of an unsigned message (the CID BLS messages are included by).
+pub fn message_cid(msg: &Message) -> Result<Cid, VerifyError> {
+    let digest = blake2b_simd::Params::new()
+        .hash_length(32)
+        .to_state()
+        .update(&to_vec(msg)?)
+        .finalize();
+    let mh = Multihash::wrap(SupportedHashes::Blake2b256 as u64, digest.as_bytes())
+        .expect("blake2b digests fit in a multihash");
+    Ok(Cid::new_v1(DAG_CBOR, mh))
+}
+
+/// Verifies that the message has the given CID.
+pub fn verify_message_cid(msg: &Message, cid: &Cid) -> Result<(), VerifyError> {
+    check(Some(cid), Some(message_cid(msg)?))
+}
+
+/// Builds the (legacy v0) AMT of the receipts of a tipset's messages, storing it in the given
+/// blockstore, and returns its root.
+pub fn receipts_root<'a, BS: Blockstore>(
+    bs: BS,
+    receipts: impl IntoIterator<Item = &'a Receipt>,
+) -> Result<Cid, VerifyError> {
+    Ok(Amtv0::new_from_iter(bs, receipts)?)
+}
+
+/// Verifies that the receipts, in order, have the given receipts root.
+pub fn verify_receipts_root<'a>(
+    receipts: impl IntoIterator<Item = &'a Receipt>,
+    root: &Cid,
+) -> Result<(), VerifyError> {
+    check(
+        Some(root),
+        Some(receipts_root(DiscardBlockstore, receipts)?),
+    )
+}
+
+/// Computes the root of the AMT of the events emitted by a message, or `None` if it emitted no
+/// events.
+pub fn events_root(events: &[StampedEvent]) -> Result<Option<Cid>, VerifyError> {
+    if events.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Amt::new_from_iter_with_bit_width(
+        DiscardBlockstore,
+        EVENTS_AMT_BITWIDTH,
+        events,
+    )?))
+}
+
+/// Verifies that the events, in order, are the events emitted by the message with the given
+/// receipt.
+pub fn verify_events(receipt: &Receipt, events: &[StampedEvent]) -> Result<(), VerifyError> {
+    check(receipt.events_root.as_ref(), events_root(events)?)
+}
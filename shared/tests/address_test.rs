@@ -8,7 +8,8 @@ use std::str::FromStr;
 use data_encoding::{DecodeError, DecodeKind};
 use fvm_ipld_encoding::{from_slice, to_vec};
 use fvm_shared::address::{
-    Address, Error, Protocol, BLS_PUB_LEN, MAX_SUBADDRESS_LEN, PAYLOAD_HASH_LEN, SECP_PUB_LEN,
+    Address, Error, Network, Protocol, BLS_PUB_LEN, MAX_ADDRESS_LEN, MAX_SUBADDRESS_LEN,
+    PAYLOAD_HASH_LEN, SECP_PUB_LEN,
 };
 use quickcheck_macros::quickcheck;
 
@@ -351,6 +352,22 @@ fn invalid_string_addresses() {
             input: "f1mzxqu",
             expected: Error::InvalidLength,
         },
+        StringAddrVec {
+            input: "f0+1",
+            expected: Error::InvalidPayload,
+        },
+        StringAddrVec {
+            input: "f018446744073709551616",
+            expected: Error::InvalidPayload,
+        },
+        StringAddrVec {
+            input: "f4+10faaaaaaaa",
+            expected: Error::InvalidPayload,
+        },
+        StringAddrVec {
+            input: "f4faaaaaaaa",
+            expected: Error::InvalidPayload,
+        },
     ];
 
     for (i, t) in test_vectors.iter().enumerate() {
@@ -441,6 +458,14 @@ fn invalid_byte_addresses() {
             input: vec![4, 0xff],
             expected: Error::InvalidPayload,
         },
+        // Too long for any protocol.
+        StringAddrVec {
+            input: [4, 0]
+                .into_iter()
+                .chain(iter::repeat(0).take(MAX_ADDRESS_LEN))
+                .collect(),
+            expected: Error::InvalidLength,
+        },
     ];
 
     for t in test_vectors.iter() {
@@ -620,3 +645,39 @@ fn prop_address_roundtrip(addr0: Address) -> Result<(), String> {
     }
     Ok(())
 }
+
+#[quickcheck]
+fn prop_address_string_roundtrip(addr0: Address) -> Result<(), String> {
+    let s = addr0.to_string();
+    let addr1 = Network::Mainnet
+        .parse_address(&s)
+        .map_err(|e| format!("error parsing address {s}: {e}"))?;
+    if addr1 != addr0 {
+        return Err(format!("address {s} differs after roundtrip"));
+    }
+    Ok(())
+}
+
+#[quickcheck]
+fn prop_address_bytes_canonical(bz: Vec<u8>) -> bool {
+    // Any bytes that decode must be the canonical encoding of the decoded address.
+    match Address::from_bytes(&bz) {
+        Ok(addr) => addr.to_bytes() == bz,
+        Err(_) => true,
+    }
+}
+
+#[quickcheck]
+fn prop_address_string_parse(s: String) -> bool {
+    // Parsing arbitrary strings must fail cleanly, and anything that parses must re-parse to the
+    // same address.
+    match Network::Mainnet.parse_address(&s) {
+        Ok(addr) => Network::Mainnet.parse_address(&addr.to_string()) == Ok(addr),
+        Err(_) => true,
+    }
+}
+
+#[quickcheck]
+fn prop_address_ct_eq(a: Address, b: Address) -> bool {
+    a.ct_eq(&a) && a.ct_eq(&b) == (a == b)
+}
@@ -0,0 +1,72 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use fvm_ipld_encoding::{from_slice, to_vec};
+use fvm_shared::message::Message;
+use quickcheck_macros::quickcheck;
+
+fn message(valid_until_epoch: Option<i64>) -> Message {
+    use fvm_ipld_encoding::RawBytes;
+    use fvm_shared::address::Address;
+    use fvm_shared::econ::TokenAmount;
+
+    Message {
+        version: 0,
+        from: Address::new_id(100),
+        to: Address::new_id(101),
+        sequence: 1,
+        value: TokenAmount::from_atto(10),
+        method_num: 2,
+        params: RawBytes::new(vec![1, 2, 3]),
+        gas_limit: 1000,
+        gas_fee_cap: TokenAmount::from_atto(3),
+        gas_premium: TokenAmount::from_atto(4),
+        valid_until_epoch,
+    }
+}
+
+#[quickcheck]
+fn message_roundtrip(msg: Message) {
+    let bytes = to_vec(&msg).unwrap();
+    assert_eq!(from_slice::<Message>(&bytes).unwrap(), msg);
+}
+
+#[test]
+fn message_expiration_encoding() {
+    // Messages without an expiration keep the 10 field encoding.
+    let msg = message(None);
+    let legacy = to_vec(&(
+        &msg.version,
+        &msg.to,
+        &msg.from,
+        &msg.sequence,
+        &msg.value,
+        &msg.gas_limit,
+        &msg.gas_fee_cap,
+        &msg.gas_premium,
+        &msg.method_num,
+        &msg.params,
+    ))
+    .unwrap();
+    assert_eq!(to_vec(&msg).unwrap(), legacy);
+
+    // The expiration is appended.
+    let expiring = message(Some(50));
+    let bytes = to_vec(&expiring).unwrap();
+    assert_eq!(bytes[0], legacy[0] + 1);
+    assert_eq!(from_slice::<Message>(&bytes).unwrap(), expiring);
+
+    // Explicit nulls and extra fields are rejected.
+    let mut null = legacy.clone();
+    null[0] += 1;
+    null.push(0xf6);
+    assert!(from_slice::<Message>(&null).is_err());
+    let mut extra = bytes;
+    extra[0] += 1;
+    extra.push(0x00);
+    assert!(from_slice::<Message>(&extra).is_err());
+
+    assert!(!expiring.is_expired(50));
+    assert!(expiring.is_expired(51));
+    assert!(!msg.is_expired(i64::MAX));
+}
@@ -0,0 +1,88 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+use fvm_ipld_amt::Amtv0;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_ipld_encoding::{to_vec, RawBytes, DAG_CBOR};
+use fvm_shared::address::Address;
+use fvm_shared::error::ExitCode;
+use fvm_shared::event::{ActorEvent, Entry, Flags, StampedEvent};
+use fvm_shared::message::Message;
+use fvm_shared::receipt::Receipt;
+use fvm_shared::verify::*;
+use fvm_shared::IPLD_RAW;
+
+#[test]
+fn message_cids() {
+    let msg = Message {
+        from: Address::new_id(100),
+        to: Address::new_id(101),
+        sequence: 1,
+        ..Message::default()
+    };
+    let cid = message_cid(&msg).unwrap();
+    assert_eq!(
+        cid,
+        Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&to_vec(&msg).unwrap()))
+    );
+    verify_message_cid(&msg, &cid).unwrap();
+
+    let other = Message { sequence: 2, ..msg };
+    assert!(matches!(
+        verify_message_cid(&other, &cid),
+        Err(VerifyError::Mismatch { .. })
+    ));
+}
+
+#[test]
+fn receipts_roots() {
+    let receipts: Vec<_> = (0..3)
+        .map(|i| Receipt {
+            exit_code: ExitCode::new(i),
+            return_data: RawBytes::default(),
+            gas_used: 100 * i as u64,
+            events_root: None,
+        })
+        .collect();
+
+    let bs = MemoryBlockstore::default();
+    let root = receipts_root(&bs, &receipts).unwrap();
+    let amt = Amtv0::<Receipt, _>::load(&root, &bs).unwrap();
+    assert_eq!(amt.get(2).unwrap(), Some(&receipts[2]));
+
+    verify_receipts_root(&receipts, &root).unwrap();
+    assert!(verify_receipts_root(&receipts[..2], &root).is_err());
+}
+
+#[test]
+fn events_roots() {
+    let receipt = Receipt {
+        exit_code: ExitCode::OK,
+        return_data: RawBytes::default(),
+        gas_used: 0,
+        events_root: None,
+    };
+    assert_eq!(events_root(&[]).unwrap(), None);
+    verify_events(&receipt, &[]).unwrap();
+
+    let events = vec![StampedEvent::new(
+        100,
+        ActorEvent::from(vec![Entry {
+            flags: Flags::FLAG_INDEXED_ALL,
+            key: "foo".into(),
+            codec: IPLD_RAW,
+            value: vec![1, 2, 3],
+        }]),
+    )];
+    assert!(verify_events(&receipt, &events).is_err());
+
+    let receipt = Receipt {
+        events_root: events_root(&events).unwrap(),
+        ..receipt
+    };
+    assert!(receipt.events_root.is_some());
+    verify_events(&receipt, &events).unwrap();
+    assert!(verify_events(&receipt, &[]).is_err());
+}
@@ -0,0 +1,28 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+#![no_main]
+
+use fvm_shared::address::{Address, Network};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Binary addresses (as read by the `read_address` syscall helper) must round-trip exactly.
+    if let Ok(addr) = Address::from_bytes(data) {
+        assert_eq!(addr.to_bytes(), data, "decoded address must be canonical");
+        let s = addr.to_string();
+        let addr2 = Network::Mainnet
+            .parse_address(&s)
+            .expect("formatted address must parse");
+        assert_eq!(addr, addr2, "formatted address must round-trip");
+    }
+
+    // Textual addresses must either fail cleanly or re-parse to the same address.
+    if let Ok(s) = std::str::from_utf8(data) {
+        if let Ok(addr) = Network::Mainnet.parse_address(s) {
+            let addr2 = Network::Mainnet
+                .parse_address(&addr.to_string())
+                .expect("formatted address must parse");
+            assert_eq!(addr, addr2, "parsed address must round-trip");
+        }
+    }
+});
@@ -63,6 +63,7 @@ fn bench_500_simple_state_access(
                 gas_limit: 5000000000,
                 gas_fee_cap: TokenAmount::zero(),
                 gas_premium: TokenAmount::zero(),
+                valid_until_epoch: None,
             })
             .unwrap(),
             epoch_offset: None,
@@ -51,8 +51,8 @@ pub fn bench_vector_variant(
                 let machine =
                     TestMachine::new_for_vector(vector, variant, bs, None, false, None).unwrap();
                 let engine = engines.get(&machine.context().network).unwrap();
-                // Preload the actors. We don't usually preload actors when testing, so we're going
-                // to do this explicitly.
+                // Preload the actors. The test machines don't preload actors, so we're going to do
+                // this explicitly.
                 engine
                     .acquire()
                     .preload(
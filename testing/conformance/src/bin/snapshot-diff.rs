@@ -0,0 +1,208 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::io::{BufRead, BufReader, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::{env, fs, io};
+
+use anyhow::{anyhow, Context};
+use fvm::engine::MultiEngine;
+use fvm_conformance_tests::gas_trace::{first_divergence, trace_vector_message, CallFrame};
+use fvm_conformance_tests::replay::{compare_outcomes, replay_vector, MessageOutcome};
+use fvm_conformance_tests::vector::MessageVector;
+
+const USAGE: &str = "usage:
+    snapshot-diff replay <vector.json> [<start>..<end>]
+    snapshot-diff compare <snapshot-diff-a> <snapshot-diff-b> <vector.json> [<start>..<end>]
+    snapshot-diff gas-trace <vector.json> <index> <lotus-trace.json>";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mode = Mode::build(args).unwrap_or_else(|err| {
+        println!("Invalid args: {err}\n{USAGE}");
+        process::exit(2)
+    });
+
+    match mode.run() {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            println!("Error running {mode:?}: {err:#}");
+            process::exit(2)
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Mode {
+    /// Replay the vector, writing the outcome of each message to stdout as a line of JSON.
+    Replay {
+        vector: PathBuf,
+        range: Range<usize>,
+    },
+    /// Replay the vector with two builds of this binary (linked against different versions of the
+    /// FVM) and report where their outcomes diverge.
+    Compare {
+        a: PathBuf,
+        b: PathBuf,
+        vector: PathBuf,
+        range: Range<usize>,
+    },
+    /// Replay a message of the vector with tracing enabled, and report where its gas trace
+    /// diverges from a trace recorded by Lotus.
+    GasTrace {
+        vector: PathBuf,
+        index: usize,
+        trace: PathBuf,
+    },
+}
+
+impl Mode {
+    pub fn build(args: Vec<String>) -> anyhow::Result<Self> {
+        let parse_range = |arg: Option<&String>| match arg {
+            None => Ok(0..usize::MAX),
+            Some(range) => {
+                let (start, end) = range
+                    .split_once("..")
+                    .ok_or_else(|| anyhow!("invalid message range {range}"))?;
+                let start = if start.is_empty() {
+                    0
+                } else {
+                    start.parse::<usize>()?
+                };
+                let end = if end.is_empty() {
+                    usize::MAX
+                } else {
+                    end.parse::<usize>()?
+                };
+                anyhow::Ok(start..end)
+            }
+        };
+
+        match args.get(1).map(String::as_str) {
+            Some("replay") if (3..=4).contains(&args.len()) => Ok(Self::Replay {
+                vector: PathBuf::from(&args[2]),
+                range: parse_range(args.get(3))?,
+            }),
+            Some("compare") if (5..=6).contains(&args.len()) => Ok(Self::Compare {
+                a: PathBuf::from(&args[2]),
+                b: PathBuf::from(&args[3]),
+                vector: PathBuf::from(&args[4]),
+                range: parse_range(args.get(5))?,
+            }),
+            Some("gas-trace") if args.len() == 5 => Ok(Self::GasTrace {
+                vector: PathBuf::from(&args[2]),
+                index: args[3].parse()?,
+                trace: PathBuf::from(&args[4]),
+            }),
+            _ => Err(anyhow!("unexpected arguments")),
+        }
+    }
+
+    /// Runs the command, returning false if the builds (or traces) diverged.
+    fn run(&self) -> anyhow::Result<bool> {
+        match self {
+            Self::Replay { vector, range } => {
+                let v = load_vector(vector)?;
+                let (bs, _) = async_std::task::block_on(v.seed_blockstore())?;
+                let outcomes = replay_vector(&bs, &v, &MultiEngine::new(1), range.clone())?;
+
+                let mut stdout = io::stdout().lock();
+                for outcome in outcomes {
+                    serde_json::to_writer(&mut stdout, &outcome)?;
+                    writeln!(stdout)?;
+                }
+                Ok(true)
+            }
+            Self::Compare {
+                a,
+                b,
+                vector,
+                range,
+            } => {
+                let range = format!("{}..{}", range.start, range.end);
+                let a_outcomes = replay_with(a, vector, &range)?;
+                let b_outcomes = replay_with(b, vector, &range)?;
+
+                let divergences = compare_outcomes(&a_outcomes, &b_outcomes);
+                println!("a: {}", a.display());
+                println!("b: {}", b.display());
+                println!("vector: {} (messages {range})", vector.display());
+                for divergence in &divergences {
+                    println!("\t|> {divergence}");
+                }
+                println!(
+                    "{} messages compared, {} divergences",
+                    a_outcomes.len().max(b_outcomes.len()),
+                    divergences.len()
+                );
+                Ok(divergences.is_empty())
+            }
+            Self::GasTrace {
+                vector,
+                index,
+                trace,
+            } => {
+                let v = load_vector(vector)?;
+                let (bs, _) = async_std::task::block_on(v.seed_blockstore())?;
+                let actual = trace_vector_message(&bs, &v, &MultiEngine::new(1), *index)?;
+                let expected = CallFrame::from_lotus_json(
+                    &fs::read_to_string(trace)
+                        .with_context(|| format!("failed to read {}", trace.display()))?,
+                )?;
+
+                println!("vector: {} (message {index})", vector.display());
+                println!("trace: {}", trace.display());
+                match first_divergence(&expected, &actual) {
+                    Some(divergence) => {
+                        println!("\t|> {divergence}");
+                        Ok(false)
+                    }
+                    None => {
+                        println!("traces match");
+                        Ok(true)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Loads a vector, checking that it's supported.
+fn load_vector(path: &Path) -> anyhow::Result<MessageVector> {
+    let v = MessageVector::from_file(path)?;
+    if !v.is_supported() {
+        return Err(anyhow!("vector selector not supported"));
+    }
+    Ok(v)
+}
+
+/// Replays the vector with the given build of this binary, collecting the outcomes.
+fn replay_with(bin: &Path, vector: &Path, range: &str) -> anyhow::Result<Vec<MessageOutcome>> {
+    let output = Command::new(bin)
+        .arg("replay")
+        .arg(vector)
+        .arg(range)
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("failed to run {}", bin.display()))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} failed with {}: {}",
+            bin.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stdout)
+        ));
+    }
+
+    BufReader::new(output.stdout.as_slice())
+        .lines()
+        .map(|line| {
+            let line = line?;
+            serde_json::from_str(&line)
+                .with_context(|| format!("invalid outcome from {}: {line}", bin.display()))
+        })
+        .collect()
+}
@@ -229,8 +229,8 @@ pub fn run_variant(
         .get(&machine.context().network)
         .map_err(|e| anyhow!(e))?;
 
-    // Preload the actors. We don't usually preload actors when testing, so we're going to do
-    // this explicitly.
+    // Preload the actors. The test machines don't preload actors, so we're going to do this
+    // explicitly.
     engine
         .acquire()
         .preload(
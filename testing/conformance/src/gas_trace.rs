@@ -0,0 +1,547 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Compares this FVM's gas traces with the traces recorded by other clients.
+//!
+//! Traces are reduced to a tree of [`CallFrame`]s, each with the gas charges made by the call
+//! itself. Foreign traces are imported with [`CallFrame::from_lotus_json`], and this FVM's traces
+//! are converted with [`CallFrame::from_execution_trace`] (see [`trace_vector_message`]). The two
+//! trees are then aligned frame by frame by [`first_divergence`].
+use std::fmt;
+
+use anyhow::{anyhow, Context};
+use fvm::engine::MultiEngine;
+use fvm::executor::{ApplyKind, DefaultExecutor, Executor};
+use fvm::machine::Machine;
+use fvm::trace::ExecutionEvent;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::address::{Address, Network};
+use fvm_shared::error::ExitCode;
+use fvm_shared::MethodNum;
+use serde::Deserialize;
+
+use crate::replay::decode_message;
+use crate::vector::MessageVector;
+use crate::vm::{TestKernel, TestMachine};
+
+/// A gas charge, in whole gas units.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceCharge {
+    pub name: String,
+    pub gas: i64,
+}
+
+impl fmt::Display for TraceCharge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} gas)", self.name, self.gas)
+    }
+}
+
+/// A call in a gas trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallFrame {
+    pub to: Address,
+    pub method: MethodNum,
+    /// The exit code of the call, if it returned.
+    pub exit_code: Option<ExitCode>,
+    /// The gas charges made by the call, excluding its subcalls, in order.
+    pub charges: Vec<TraceCharge>,
+    pub subcalls: Vec<CallFrame>,
+}
+
+/// The names Lotus (and this FVM before hierarchical charge names) gives to gas charges, with the
+/// corresponding FVM names.
+const LOTUS_CHARGE_NAMES: &[(&str, &str)] = &[
+    ("OnChainMessage", "message/inclusion"),
+    ("OnChainReturnValue", "message/return_value"),
+    ("OnMethodInvocation", "call/invoke"),
+    ("OnValueTransfer", "call/value_transfer"),
+    ("OnReturnValue", "call/return_value"),
+    ("OnSyscall", "syscall/overhead"),
+    ("OnCreateActor", "syscall/actor/create_actor"),
+    ("OnBalanceOf", "syscall/actor/balance_of"),
+    ("OnResolveAddress", "syscall/actor/resolve_address"),
+    ("OnLookupAddress", "syscall/actor/lookup_delegated_address"),
+    ("OnGetActorCodeCid", "syscall/actor/get_actor_code_cid"),
+    (
+        "OnGetBuiltinActorType",
+        "syscall/actor/get_builtin_actor_type",
+    ),
+    ("OnGetCodeCidForType", "syscall/actor/get_code_cid_for_type"),
+    ("OnInstallActor", "syscall/actor/install_actor"),
+    ("OnVerifySignature", "syscall/crypto/verify_signature"),
+    (
+        "OnRecoverSecpPublicKey",
+        "syscall/crypto/recover_secp_public_key",
+    ),
+    ("OnHashing", "syscall/crypto/hash"),
+    ("OnVerifyMerkleProof", "syscall/crypto/verify_merkle_proof"),
+    (
+        "OnComputeUnsealedSectorCid",
+        "syscall/crypto/compute_unsealed_sector_cid",
+    ),
+    ("OnVerifySeal", "syscall/crypto/verify_seal"),
+    (
+        "OnVerifyAggregateSeals",
+        "syscall/crypto/verify_aggregate_seals",
+    ),
+    (
+        "OnVerifyReplicaUpdate",
+        "syscall/crypto/verify_replica_update",
+    ),
+    ("OnVerifyPost", "syscall/crypto/verify_post"),
+    (
+        "OnVerifyConsensusFault",
+        "syscall/crypto/verify_consensus_fault",
+    ),
+    ("OnActorEvent", "syscall/event/emit_event"),
+    ("OnBlockOpenBase", "syscall/ipld/block_open_base"),
+    ("OnBlockOpen", "syscall/ipld/block_open"),
+    ("OnBlockRead", "syscall/ipld/block_read"),
+    ("OnBlockCreate", "syscall/ipld/block_create"),
+    ("OnBlockLink", "syscall/ipld/block_link"),
+    ("OnBlockStat", "syscall/ipld/block_stat"),
+    ("OnScanIpldLinks", "syscall/ipld/scan_links"),
+    ("OnGetRandomness", "syscall/rand/get_randomness"),
+    ("OnNetworkContext", "syscall/network/context"),
+    ("OnTipsetCid", "syscall/network/tipset_cid"),
+    ("OnMessageContext", "syscall/vm/message_context"),
+    ("OnSelfBalance", "syscall/self/balance"),
+    ("OnActorGetRoot", "syscall/self/root"),
+    ("OnActorSetRoot", "syscall/self/set_root"),
+    ("OnDeleteActor", "syscall/self/self_destruct"),
+    ("OnActorLookup", "state/actor_lookup"),
+    ("OnActorUpdate", "state/actor_update"),
+    ("OnActorCreate", "state/actor_create"),
+    ("wasm_exec", "wasm/exec"),
+    ("wasm_memory_grow", "wasm/memory_grow"),
+    ("wasm_memory_init", "wasm/memory_init"),
+    ("wasm_table_init", "wasm/table_init"),
+];
+
+/// Returns the FVM name of a charge named by Lotus. Unknown names are kept as is.
+fn fvm_charge_name(lotus_name: String) -> String {
+    LOTUS_CHARGE_NAMES
+        .iter()
+        .find(|(lotus, _)| *lotus == lotus_name)
+        .map(|(_, fvm)| (*fvm).to_owned())
+        .unwrap_or(lotus_name)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LotusTrace {
+    msg: LotusMessage,
+    msg_rct: LotusReceipt,
+    gas_charges: Option<Vec<LotusGasCharge>>,
+    subcalls: Option<Vec<LotusTrace>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LotusMessage {
+    to: String,
+    method: MethodNum,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LotusReceipt {
+    exit_code: u32,
+}
+
+#[derive(Deserialize)]
+struct LotusGasCharge {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "tg")]
+    total_gas: i64,
+}
+
+impl LotusTrace {
+    fn into_frame(self) -> anyhow::Result<CallFrame> {
+        let to = Network::Mainnet
+            .parse_address(&self.msg.to)
+            .or_else(|_| Network::Testnet.parse_address(&self.msg.to))
+            .with_context(|| format!("invalid address {}", self.msg.to))?;
+        Ok(CallFrame {
+            to,
+            method: self.msg.method,
+            exit_code: Some(ExitCode::new(self.msg_rct.exit_code)),
+            charges: self
+                .gas_charges
+                .unwrap_or_default()
+                .into_iter()
+                .map(|c| TraceCharge {
+                    name: fvm_charge_name(c.name),
+                    gas: c.total_gas,
+                })
+                .collect(),
+            subcalls: self
+                .subcalls
+                .unwrap_or_default()
+                .into_iter()
+                .map(LotusTrace::into_frame)
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+}
+
+impl CallFrame {
+    /// Imports a Lotus execution trace, as returned by the `StateReplay` and `StateCompute` APIs.
+    /// Either the trace itself or an invocation result with an `ExecutionTrace` is accepted.
+    ///
+    /// Charge names are translated to this FVM's names (e.g., `OnBlockOpen` to
+    /// `syscall/ipld/block_open`), so the traces can be compared.
+    pub fn from_lotus_json(json: &str) -> anyhow::Result<Self> {
+        let mut value: serde_json::Value =
+            serde_json::from_str(json).context("invalid lotus trace")?;
+        if let Some(trace) = value.get_mut("ExecutionTrace") {
+            value = trace.take();
+        }
+        serde_json::from_value::<LotusTrace>(value)
+            .context("invalid lotus trace")?
+            .into_frame()
+    }
+
+    /// Converts an execution trace (recorded with tracing enabled) to a call tree. Charges made
+    /// outside of any call (e.g., message inclusion) are attributed to the top-level call, like
+    /// Lotus does.
+    pub fn from_execution_trace(trace: &[ExecutionEvent]) -> anyhow::Result<Self> {
+        let mut outside = Vec::new();
+        let mut stack: Vec<CallFrame> = Vec::new();
+        let mut root: Option<CallFrame> = None;
+        for event in trace {
+            match event {
+                ExecutionEvent::GasCharge(charge) => {
+                    let charge = TraceCharge {
+                        name: charge.name.to_string(),
+                        gas: charge.total().round_up() as i64,
+                    };
+                    match stack.last_mut() {
+                        Some(frame) => frame.charges.push(charge),
+                        None => outside.push(charge),
+                    }
+                }
+                ExecutionEvent::Call { to, entrypoint, .. } => stack.push(CallFrame {
+                    to: *to,
+                    method: entrypoint.method_num(),
+                    exit_code: None,
+                    charges: Vec::new(),
+                    subcalls: Vec::new(),
+                }),
+                ExecutionEvent::CallReturn(..) | ExecutionEvent::CallError(..) => {
+                    let mut frame = stack
+                        .pop()
+                        .ok_or_else(|| anyhow!("call returned without being made"))?;
+                    if let ExecutionEvent::CallReturn(exit_code, _) = event {
+                        frame.exit_code = Some(*exit_code);
+                    }
+                    match stack.last_mut() {
+                        Some(parent) => parent.subcalls.push(frame),
+                        None if root.is_none() => root = Some(frame),
+                        None => return Err(anyhow!("trace has multiple top-level calls")),
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !stack.is_empty() {
+            return Err(anyhow!("trace ends inside a call"));
+        }
+        let mut root = root.ok_or_else(|| anyhow!("trace has no calls"))?;
+        outside.append(&mut root.charges);
+        root.charges = outside;
+        Ok(root)
+    }
+}
+
+/// What differs between two call frames, see [`Divergence`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// The frames call different actors or methods.
+    Call {
+        expected: (Address, MethodNum),
+        actual: (Address, MethodNum),
+    },
+    /// The frames' charges differ at the given index. A charge is missing if one frame has fewer
+    /// charges.
+    Charge {
+        index: usize,
+        expected: Option<TraceCharge>,
+        actual: Option<TraceCharge>,
+    },
+    /// The frames make different numbers of subcalls.
+    Subcalls { expected: usize, actual: usize },
+    /// The frames exit differently.
+    ExitCode {
+        expected: ExitCode,
+        actual: ExitCode,
+    },
+}
+
+/// The first difference between two gas traces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The indices of the subcalls leading from the top-level call to the diverging frame.
+    pub path: Vec<usize>,
+    pub kind: DivergenceKind,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "call /")?;
+        for i in &self.path {
+            write!(f, "{i}/")?;
+        }
+        let missing = |charge: &Option<TraceCharge>| match charge {
+            Some(charge) => charge.to_string(),
+            None => "nothing".into(),
+        };
+        match &self.kind {
+            DivergenceKind::Call { expected, actual } => write!(
+                f,
+                ": expected a call to {} method {}, got {} method {}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            DivergenceKind::Charge {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                ": charge {index}: expected {}, got {}",
+                missing(expected),
+                missing(actual)
+            ),
+            DivergenceKind::Subcalls { expected, actual } => {
+                write!(f, ": expected {expected} subcalls, got {actual}")
+            }
+            DivergenceKind::ExitCode { expected, actual } => {
+                write!(f, ": expected exit code {expected}, got {actual}")
+            }
+        }
+    }
+}
+
+/// Aligns two gas traces frame by frame, returning the first divergence, if any.
+///
+/// The traces are walked depth-first. Within a frame, the call itself is compared first, then its
+/// charges in order, then its subcalls, and finally its exit code. Neither trace records how
+/// charges interleave with subcalls, so a frame's charges are compared before its subcalls.
+pub fn first_divergence(expected: &CallFrame, actual: &CallFrame) -> Option<Divergence> {
+    let mut path = Vec::new();
+    diverge(expected, actual, &mut path).map(|kind| Divergence { path, kind })
+}
+
+fn diverge(
+    expected: &CallFrame,
+    actual: &CallFrame,
+    path: &mut Vec<usize>,
+) -> Option<DivergenceKind> {
+    if (expected.to, expected.method) != (actual.to, actual.method) {
+        return Some(DivergenceKind::Call {
+            expected: (expected.to, expected.method),
+            actual: (actual.to, actual.method),
+        });
+    }
+
+    let charges = expected.charges.len().max(actual.charges.len());
+    for index in 0..charges {
+        let (e, a) = (expected.charges.get(index), actual.charges.get(index));
+        if e != a {
+            return Some(DivergenceKind::Charge {
+                index,
+                expected: e.cloned(),
+                actual: a.cloned(),
+            });
+        }
+    }
+
+    for (i, (e, a)) in expected.subcalls.iter().zip(&actual.subcalls).enumerate() {
+        path.push(i);
+        if let Some(kind) = diverge(e, a, path) {
+            return Some(kind);
+        }
+        path.pop();
+    }
+    if expected.subcalls.len() != actual.subcalls.len() {
+        return Some(DivergenceKind::Subcalls {
+            expected: expected.subcalls.len(),
+            actual: actual.subcalls.len(),
+        });
+    }
+
+    match (expected.exit_code, actual.exit_code) {
+        (Some(expected), Some(actual)) if expected != actual => {
+            Some(DivergenceKind::ExitCode { expected, actual })
+        }
+        _ => None,
+    }
+}
+
+/// Applies the messages of the vector's first variant up to the given one, with tracing enabled,
+/// returning the call tree of the given message.
+pub fn trace_vector_message(
+    bs: &MemoryBlockstore,
+    v: &MessageVector,
+    engines: &MultiEngine,
+    index: usize,
+) -> anyhow::Result<CallFrame> {
+    let variant = v
+        .preconditions
+        .variants
+        .first()
+        .ok_or_else(|| anyhow!("vector has no variants"))?;
+    let machine = TestMachine::new_for_vector(v, variant, bs.clone(), None, true, None)?;
+    let engine = engines
+        .get(&machine.context().network)
+        .map_err(|e| anyhow!(e))?;
+    let mut exec: DefaultExecutor<TestKernel> = DefaultExecutor::new(engine, machine)?;
+
+    for (i, m) in v.apply_messages.iter().enumerate() {
+        let (msg, raw_length) = decode_message(m)?;
+        let ret = exec.execute_message(msg, ApplyKind::Explicit, raw_length)?;
+        if i == index {
+            return CallFrame::from_execution_trace(&ret.exec_trace);
+        }
+    }
+    Err(anyhow!(
+        "vector has {} messages, no message {index}",
+        v.apply_messages.len()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(charges: &[(&str, i64)], subcalls: Vec<CallFrame>) -> CallFrame {
+        CallFrame {
+            to: Address::new_id(100),
+            method: 2,
+            exit_code: Some(ExitCode::OK),
+            charges: charges
+                .iter()
+                .map(|&(name, gas)| TraceCharge {
+                    name: name.into(),
+                    gas,
+                })
+                .collect(),
+            subcalls,
+        }
+    }
+
+    #[test]
+    fn lotus_json() {
+        let json = r#"{
+            "ExecutionTrace": {
+                "Msg": { "To": "f0100", "Method": 2 },
+                "MsgRct": { "ExitCode": 0 },
+                "GasCharges": [
+                    { "Name": "OnChainMessage", "tg": 10 },
+                    { "Name": "OnBlockOpen", "tg": 5 },
+                    { "Name": "SomethingNew", "tg": 1 }
+                ],
+                "Subcalls": [{
+                    "Msg": { "To": "t0101", "Method": 3 },
+                    "MsgRct": { "ExitCode": 16 },
+                    "GasCharges": null,
+                    "Subcalls": null
+                }]
+            }
+        }"#;
+        let mut subcall = frame(&[], vec![]);
+        subcall.to = Address::new_id(101);
+        subcall.method = 3;
+        subcall.exit_code = Some(ExitCode::USR_ILLEGAL_ARGUMENT);
+        let expected = frame(
+            &[
+                ("message/inclusion", 10),
+                ("syscall/ipld/block_open", 5),
+                ("SomethingNew", 1),
+            ],
+            vec![subcall],
+        );
+        assert_eq!(CallFrame::from_lotus_json(json).unwrap(), expected);
+
+        assert!(CallFrame::from_lotus_json("{}").is_err());
+        let bad_address = json.replace("f0100", "not an address");
+        assert!(CallFrame::from_lotus_json(&bad_address).is_err());
+    }
+
+    #[test]
+    fn divergence() {
+        let trace = frame(
+            &[("call/invoke", 10)],
+            vec![frame(&[("wasm/exec", 5)], vec![])],
+        );
+        assert_eq!(first_divergence(&trace, &trace), None);
+
+        // A charge differs in a subcall.
+        let mut actual = trace.clone();
+        actual.subcalls[0].charges[0].gas = 6;
+        assert_eq!(
+            first_divergence(&trace, &actual),
+            Some(Divergence {
+                path: vec![0],
+                kind: DivergenceKind::Charge {
+                    index: 0,
+                    expected: Some(trace.subcalls[0].charges[0].clone()),
+                    actual: Some(actual.subcalls[0].charges[0].clone()),
+                },
+            })
+        );
+
+        // A charge is missing.
+        let mut actual = trace.clone();
+        actual.charges.clear();
+        assert_eq!(
+            first_divergence(&trace, &actual).unwrap().kind,
+            DivergenceKind::Charge {
+                index: 0,
+                expected: Some(trace.charges[0].clone()),
+                actual: None,
+            }
+        );
+
+        // A subcall is missing.
+        let mut actual = trace.clone();
+        actual.subcalls.clear();
+        assert_eq!(
+            first_divergence(&trace, &actual).unwrap().kind,
+            DivergenceKind::Subcalls {
+                expected: 1,
+                actual: 0
+            }
+        );
+
+        // The call exits differently.
+        let mut actual = trace.clone();
+        actual.exit_code = Some(ExitCode::USR_FORBIDDEN);
+        assert_eq!(
+            first_divergence(&trace, &actual).unwrap().kind,
+            DivergenceKind::ExitCode {
+                expected: ExitCode::OK,
+                actual: ExitCode::USR_FORBIDDEN
+            }
+        );
+
+        // Unknown exit codes (calls that didn't return) aren't compared.
+        actual.exit_code = None;
+        assert_eq!(first_divergence(&trace, &actual), None);
+
+        // The first divergence is reported.
+        let mut actual = trace;
+        actual.subcalls[0].method = 4;
+        actual.exit_code = Some(ExitCode::USR_FORBIDDEN);
+        assert_eq!(
+            first_divergence(
+                &frame(&[("call/invoke", 10)], vec![frame(&[], vec![])]),
+                &actual
+            )
+            .unwrap()
+            .to_string(),
+            "call /0/: expected a call to f0100 method 2, got f0100 method 4"
+        );
+    }
+}
@@ -6,7 +6,9 @@ pub mod actors;
 pub mod cidjson;
 pub mod driver;
 pub mod externs;
+pub mod gas_trace;
 pub mod rand;
+pub mod replay;
 pub mod tracing;
 pub mod vector;
 pub mod vm;
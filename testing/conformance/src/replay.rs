@@ -0,0 +1,273 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Replays test vectors and compares the outcomes recorded by different builds of the FVM.
+//!
+//! Two builds can't be linked into the same binary, so each build replays the vector in its own
+//! process (see the `snapshot-diff` binary) and writes one [`MessageOutcome`] per message as a line
+//! of JSON. The outcomes are then compared with [`compare_outcomes`].
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::Range;
+
+use anyhow::anyhow;
+use base64::Engine;
+use fvm::engine::MultiEngine;
+use fvm::executor::{ApplyKind, DefaultExecutor, Executor};
+use fvm::machine::Machine;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_ipld_encoding::from_slice;
+use fvm_shared::address::Protocol;
+use fvm_shared::crypto::signature::SECP_SIG_LEN;
+use fvm_shared::error::ExitCode;
+use fvm_shared::message::Message;
+use serde::{Deserialize, Serialize};
+
+use crate::vector::{ApplyMessage, MessageVector};
+use crate::vm::{TestKernel, TestMachine};
+
+/// The outcome of applying a single message of a test vector.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageOutcome {
+    /// The ID of the vector variant.
+    pub variant: String,
+    /// The index of the message in the vector.
+    pub index: usize,
+    pub exit_code: ExitCode,
+    pub gas_used: u64,
+    /// The base64 encoded return value.
+    pub return_data: String,
+    /// The gas used (in milligas) keyed by charge name.
+    #[serde(default)]
+    pub gas_breakdown: BTreeMap<String, u64>,
+    /// The state root after applying the message.
+    pub state_root: String,
+}
+
+/// Decodes a message of a vector, returning it with its on-chain length.
+pub fn decode_message(m: &ApplyMessage) -> anyhow::Result<(Message, usize)> {
+    let msg: Message = from_slice(&m.bytes)?;
+
+    let mut raw_length = m.bytes.len();
+    if msg.from.protocol() == Protocol::Secp256k1 {
+        // 65 bytes signature + 1 byte type + 3 bytes for field info.
+        raw_length += SECP_SIG_LEN + 4;
+    }
+    Ok((msg, raw_length))
+}
+
+/// Applies the messages of every variant of the vector, recording the outcomes of the messages in
+/// the given range. Messages before the range are applied, but not recorded.
+pub fn replay_vector(
+    bs: &MemoryBlockstore,
+    v: &MessageVector,
+    engines: &MultiEngine,
+    range: Range<usize>,
+) -> anyhow::Result<Vec<MessageOutcome>> {
+    let mut outcomes = Vec::new();
+    for variant in &v.preconditions.variants {
+        let machine = TestMachine::new_for_vector(v, variant, bs.clone(), None, false, None)?;
+        let engine = engines
+            .get(&machine.context().network)
+            .map_err(|e| anyhow!(e))?;
+        let mut exec: DefaultExecutor<TestKernel> = DefaultExecutor::new(engine, machine)?;
+
+        for (index, m) in v.apply_messages.iter().enumerate().take(range.end) {
+            let (msg, raw_length) = decode_message(m)?;
+            let ret = exec.execute_message(msg, ApplyKind::Explicit, raw_length)?;
+            if index < range.start {
+                continue;
+            }
+
+            outcomes.push(MessageOutcome {
+                variant: variant.id.clone(),
+                index,
+                exit_code: ret.msg_receipt.exit_code,
+                gas_used: ret.msg_receipt.gas_used,
+                return_data: base64::engine::general_purpose::STANDARD
+                    .encode(ret.msg_receipt.return_data.bytes()),
+                gas_breakdown: ret
+                    .gas_breakdown
+                    .into_iter()
+                    .map(|(name, gas)| (name, gas.as_milligas()))
+                    .collect(),
+                state_root: exec.flush()?.to_string(),
+            });
+        }
+    }
+    Ok(outcomes)
+}
+
+/// A difference between the outcomes of a message recorded by two builds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// Only one of the builds recorded an outcome for the message.
+    Missing {
+        variant: String,
+        index: usize,
+        /// The build that recorded the message ("a" or "b").
+        recorded_by: &'static str,
+    },
+    /// Both builds recorded an outcome for the message, but they differ in the given field.
+    Mismatch {
+        variant: String,
+        index: usize,
+        field: String,
+        a: String,
+        b: String,
+    },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::Missing {
+                variant,
+                index,
+                recorded_by,
+            } => write!(
+                f,
+                "variant {variant} msg {index}: only recorded by {recorded_by}"
+            ),
+            Divergence::Mismatch {
+                variant,
+                index,
+                field,
+                a,
+                b,
+            } => write!(
+                f,
+                "variant {variant} msg {index}: {field} differs: {a} != {b}"
+            ),
+        }
+    }
+}
+
+/// Compares the outcomes recorded by two builds, returning all divergences ordered by variant and
+/// message index.
+pub fn compare_outcomes(a: &[MessageOutcome], b: &[MessageOutcome]) -> Vec<Divergence> {
+    fn by_message(outcomes: &[MessageOutcome]) -> BTreeMap<(&str, usize), &MessageOutcome> {
+        outcomes
+            .iter()
+            .map(|o| ((o.variant.as_str(), o.index), o))
+            .collect()
+    }
+    let (a, b) = (by_message(a), by_message(b));
+    let keys: BTreeSet<_> = a.keys().chain(b.keys()).copied().collect();
+
+    let mut divergences = Vec::new();
+    for key @ (variant, index) in keys {
+        let (a, b) = match (a.get(&key), b.get(&key)) {
+            (Some(a), Some(b)) => (a, b),
+            (a, _) => {
+                divergences.push(Divergence::Missing {
+                    variant: variant.into(),
+                    index,
+                    recorded_by: if a.is_some() { "a" } else { "b" },
+                });
+                continue;
+            }
+        };
+
+        let mut mismatch = |field: &str, a: String, b: String| {
+            if a != b {
+                divergences.push(Divergence::Mismatch {
+                    variant: variant.into(),
+                    index,
+                    field: field.into(),
+                    a,
+                    b,
+                })
+            }
+        };
+        mismatch(
+            "exit code",
+            a.exit_code.to_string(),
+            b.exit_code.to_string(),
+        );
+        mismatch("return data", a.return_data.clone(), b.return_data.clone());
+        mismatch("gas used", a.gas_used.to_string(), b.gas_used.to_string());
+        let charges: BTreeSet<_> = a
+            .gas_breakdown
+            .keys()
+            .chain(b.gas_breakdown.keys())
+            .collect();
+        for name in charges {
+            let gas = |o: &MessageOutcome| o.gas_breakdown.get(name).copied().unwrap_or_default();
+            mismatch(
+                &format!("{name} milligas"),
+                gas(a).to_string(),
+                gas(b).to_string(),
+            );
+        }
+        mismatch("state root", a.state_root.clone(), b.state_root.clone());
+    }
+    divergences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(variant: &str, index: usize) -> MessageOutcome {
+        MessageOutcome {
+            variant: variant.into(),
+            index,
+            exit_code: ExitCode::OK,
+            gas_used: 100,
+            return_data: String::new(),
+            gas_breakdown: [
+                ("OnChainMessage".to_owned(), 60_000),
+                ("OnMethodInvocation".to_owned(), 40_000),
+            ]
+            .into_iter()
+            .collect(),
+            state_root: "bafy-root".into(),
+        }
+    }
+
+    #[test]
+    fn matching_outcomes() {
+        let a = vec![outcome("v1", 0), outcome("v1", 1), outcome("v2", 0)];
+        // The order in which the outcomes were recorded doesn't matter.
+        let b: Vec<_> = a.iter().rev().cloned().collect();
+        assert_eq!(compare_outcomes(&a, &b), vec![]);
+    }
+
+    #[test]
+    fn diverging_outcomes() {
+        let a = vec![outcome("v1", 0), outcome("v1", 1), outcome("v1", 2)];
+        let mut b = vec![outcome("v1", 0), outcome("v1", 1), outcome("v2", 0)];
+        b[0].exit_code = ExitCode::USR_ILLEGAL_ARGUMENT;
+        b[1].gas_used = 101;
+        b[1].gas_breakdown
+            .insert("OnMethodInvocation".to_owned(), 41_000);
+        b[1].gas_breakdown.insert("OnHashing".to_owned(), 1_000);
+
+        let mismatch = |index, field: &str, a: &str, b: &str| Divergence::Mismatch {
+            variant: "v1".into(),
+            index,
+            field: field.into(),
+            a: a.into(),
+            b: b.into(),
+        };
+        assert_eq!(
+            compare_outcomes(&a, &b),
+            vec![
+                mismatch(0, "exit code", "0", "16"),
+                mismatch(1, "gas used", "100", "101"),
+                mismatch(1, "OnHashing milligas", "0", "1000"),
+                mismatch(1, "OnMethodInvocation milligas", "40000", "41000"),
+                Divergence::Missing {
+                    variant: "v1".into(),
+                    index: 2,
+                    recorded_by: "a",
+                },
+                Divergence::Missing {
+                    variant: "v2".into(),
+                    index: 0,
+                    recorded_by: "b",
+                },
+            ]
+        );
+    }
+}
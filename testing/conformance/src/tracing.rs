@@ -24,6 +24,7 @@ pub struct TestMessageTombstone {
     /// Path to the detailed execution trace.
     ///
     /// The path includes the name of the test, the ID of the variant, and the index of the message.
+    /// The syscall timings are exported next to it, with a `.syscalls.jsonline` extension.
     pub trace_path: PathBuf,
     /// Overall gas burned.
     pub gas_burned: u64,
@@ -43,6 +44,15 @@ pub struct TestGasCharge {
     pub elapsed_nanos: Option<u128>,
 }
 
+/// Information we export about syscalls, to correlate the gas charged by each syscall with the
+/// time it took.
+#[derive(Serialize, Deserialize)]
+pub struct TestSyscall {
+    pub module: String,
+    pub name: String,
+    pub elapsed_nanos: u128,
+}
+
 /// Export gas traces as we complete tests, while collecting tombstones.
 pub struct TestTraceExporter {
     /// Root directory of where to put the exports.
@@ -93,31 +103,41 @@ impl TestTraceExporter {
                     elapsed_nanos: elapsed.as_nanos(),
                 };
 
-                let charges = ret
-                    .exec_trace
-                    .into_iter()
-                    .filter_map(|event| match event {
+                let mut charges = Vec::new();
+                let mut syscalls = Vec::new();
+                for event in ret.exec_trace {
+                    match event {
                         ExecutionEvent::GasCharge(charge) => {
                             let elapsed_nanos = charge.elapsed.get().map(|e| e.as_nanos());
-                            Some(TestGasCharge {
+                            charges.push(TestGasCharge {
                                 name: charge.name.into(),
                                 compute_gas: charge.compute_gas.as_milligas(),
                                 other_gas: charge.other_gas.as_milligas(),
                                 elapsed_nanos,
                             })
                         }
-                        _ => None,
-                    })
-                    .collect::<Vec<_>>();
-
-                (ts, charges)
+                        ExecutionEvent::Syscall {
+                            module,
+                            name,
+                            elapsed,
+                        } => syscalls.push(TestSyscall {
+                            module: module.into(),
+                            name: name.into(),
+                            elapsed_nanos: elapsed.as_nanos(),
+                        }),
+                        _ => {}
+                    }
+                }
+
+                (ts, charges, syscalls)
             })
             .collect::<Vec<_>>();
 
         let mut ts = Vec::new();
 
-        for (t, cs) in results {
+        for (t, cs, ss) in results {
             Self::export_json(&t.trace_path, cs)?;
+            Self::export_json(&t.trace_path.with_extension("syscalls.jsonline"), ss)?;
             ts.push(t);
         }
 
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::convert::TryFrom;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::anyhow;
 use cid::Cid;
@@ -13,15 +14,19 @@ use fvm::call_manager::{CallManager, DefaultCallManager};
 use fvm::gas::{price_list_by_network_version, Gas, GasTimer, PriceList};
 use fvm::kernel::*;
 use fvm::machine::limiter::MemoryLimiter;
-use fvm::machine::{DefaultMachine, Machine, MachineContext, Manifest, NetworkConfig};
+use fvm::machine::{
+    DefaultMachine, ExecutionMetrics, Machine, MachineContext, MachineSnapshot, Manifest,
+    MetricEvent, NetworkConfig,
+};
 use fvm::state_tree::StateTree;
 use fvm::DefaultKernel;
 use fvm_ipld_blockstore::MemoryBlockstore;
 use fvm_shared::address::Address;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::consensus::ConsensusFault;
+use fvm_shared::crypto::merkle::MerkleProof;
 use fvm_shared::crypto::signature::{
-    SignatureType, SECP_PUB_LEN, SECP_SIG_LEN, SECP_SIG_MESSAGE_HASH_SIZE,
+    SignatureBatchEntry, SignatureType, SECP_PUB_LEN, SECP_SIG_LEN, SECP_SIG_MESSAGE_HASH_SIZE,
 };
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::piece::PieceInfo;
@@ -107,6 +112,10 @@ impl TestMachine<Box<DefaultMachine<MemoryBlockstore, TestExterns>>> {
         }
         mc.set_base_fee(base_fee);
         mc.tracing = tracing;
+        // Cheap, and compared across builds when replaying vectors.
+        mc.enable_gas_breakdown();
+        // The drivers preload the actors themselves, if needed.
+        mc.set_preload_builtin_actors(false);
 
         let machine = DefaultMachine::new(&mc, blockstore, externs).unwrap();
 
@@ -161,6 +170,30 @@ where
         self.machine.state_tree_mut()
     }
 
+    fn snapshot(&mut self) -> Result<MachineSnapshot> {
+        self.machine.snapshot()
+    }
+
+    fn restore(&mut self, snapshot: &MachineSnapshot) -> Result<()> {
+        self.machine.restore(snapshot)
+    }
+
+    fn metrics(&self) -> ExecutionMetrics {
+        self.machine.metrics()
+    }
+
+    fn record_metric(&self, event: MetricEvent) {
+        self.machine.record_metric(event)
+    }
+
+    fn collects_metrics(&self) -> bool {
+        self.machine.collects_metrics()
+    }
+
+    fn set_collect_metrics(&self, enable: bool) {
+        self.machine.set_collect_metrics(enable)
+    }
+
     fn into_store(self) -> Self::Blockstore {
         self.machine.into_store()
     }
@@ -258,6 +291,18 @@ where
     fn upgrade_actor<KK>(&mut self, new_code_cid: Cid, params_id: BlockId) -> Result<CallResult> {
         self.0.upgrade_actor::<Self>(new_code_cid, params_id)
     }
+
+    fn defer_send(
+        &mut self,
+        recipient: &Address,
+        method: u64,
+        params: BlockId,
+        value: &TokenAmount,
+        gas_limit: Gas,
+    ) -> Result<()> {
+        self.0
+            .defer_send(recipient, method, params, value, gas_limit)
+    }
 }
 
 impl<M, C, K> SyscallHandler<TestKernel<K>> for TestKernel<K>
@@ -444,6 +489,16 @@ where
     ) -> Result<[u8; SECP_PUB_LEN]> {
         self.0.recover_secp_public_key(hash, signature)
     }
+
+    // forwarded
+    fn verify_merkle_proof(&self, proof: &MerkleProof) -> Result<bool> {
+        self.0.verify_merkle_proof(proof)
+    }
+
+    // forwarded
+    fn verify_signatures_batch(&self, entries: &[SignatureBatchEntry]) -> Result<Vec<bool>> {
+        self.0.verify_signatures_batch(entries)
+    }
 }
 
 impl<M, C, K> DebugOps for TestKernel<K>
@@ -486,6 +541,10 @@ where
     fn gas_available(&self) -> Gas {
         self.0.gas_available()
     }
+
+    fn trace_syscall(&self, module: &'static str, name: &'static str, elapsed: Duration) {
+        self.0.trace_syscall(module, name, elapsed)
+    }
 }
 
 impl<M, C, K> MessageOps for TestKernel<K>
@@ -569,6 +628,17 @@ where
     }
 }
 
+impl<M, C, K> UtilOps for TestKernel<K>
+where
+    M: Machine,
+    C: CallManager<Machine = TestMachine<M>>,
+    K: Kernel<CallManager = C>,
+{
+    fn validate_utf8(&self, bytes: &[u8]) -> Result<bool> {
+        self.0.validate_utf8(bytes)
+    }
+}
+
 impl<M, C, K> EventOps for TestKernel<K>
 where
     M: Machine,
@@ -9,8 +9,6 @@ pub(crate) enum Error {
     NoManifestInformation(Cid),
     #[error("could not load builtin manifest")]
     FailedToLoadManifest,
-    #[error("could not set state in tree for: {0}")]
-    FailedToSetState(String),
     #[error("failed to flush tree")]
     FailedToFlushTree,
 }
@@ -4,5 +4,6 @@ mod builtin;
 pub mod bundle;
 pub mod dummy;
 pub mod error;
+pub mod recording;
 pub mod tester;
 pub mod testkit;
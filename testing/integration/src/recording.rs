@@ -0,0 +1,954 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! A [`Kernel`] wrapper that records every kernel operation, for unit testing syscall bindings and
+//! actor code without executing messages on a full machine.
+use std::cell::{Ref, RefCell};
+use std::time::Duration;
+
+use cid::Cid;
+use fvm::call_manager::CallManager;
+use fvm::gas::{Gas, GasTimer, PriceList};
+use fvm::kernel::default::DefaultKernel;
+use fvm::kernel::{
+    ActorOps, BlockId, BlockRegistry, BlockStat, CallResult, CircSupplyOps, CryptoOps, DebugOps,
+    EventOps, ExecutionError, GasOps, IpldBlockOps, LimiterOps, MessageOps, NetworkOps,
+    RandomnessOps, Result, SelfOps, SyscallError, SyscallHandler, UtilOps,
+};
+use fvm::syscalls::InvocationData;
+use fvm::Kernel;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::crypto::merkle::MerkleProof;
+use fvm_shared::crypto::signature::{
+    SignatureBatchEntry, SignatureType, SECP_PUB_LEN, SECP_SIG_LEN, SECP_SIG_MESSAGE_HASH_SIZE,
+};
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::{ErrorNumber, ExitCode};
+use fvm_shared::randomness::RANDOMNESS_LENGTH;
+use fvm_shared::sys::out::network::NetworkContext;
+use fvm_shared::sys::out::vm::MessageContext;
+use fvm_shared::sys::{EventEntry, SendFlags};
+use fvm_shared::{ActorID, MethodNum};
+use multihash::MultihashGeneric;
+use wasmtime::Linker;
+
+/// A kernel operation, with its arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KernelOp {
+    Send {
+        recipient: Address,
+        method: MethodNum,
+        params: BlockId,
+        value: TokenAmount,
+        gas_limit: Option<Gas>,
+        flags: SendFlags,
+    },
+    UpgradeActor {
+        new_code_cid: Cid,
+        params: BlockId,
+    },
+    DeferSend {
+        recipient: Address,
+        method: MethodNum,
+        params: BlockId,
+        value: TokenAmount,
+        gas_limit: Gas,
+    },
+    ResolveAddress(Address),
+    LookupDelegatedAddress(ActorID),
+    GetActorCodeCid(ActorID),
+    NextActorAddress,
+    CreateActor {
+        code_cid: Cid,
+        actor_id: ActorID,
+        delegated_address: Option<Address>,
+    },
+    InstallActor(Cid),
+    GetBuiltinActorType(Cid),
+    GetCodeCidForType(u32),
+    BalanceOf(ActorID),
+    BlockOpen(Cid),
+    BlockCreate {
+        codec: u64,
+        data: Vec<u8>,
+    },
+    BlockLink {
+        id: BlockId,
+        hash_fun: u64,
+        hash_len: u32,
+    },
+    BlockRead {
+        id: BlockId,
+        offset: u32,
+        len: usize,
+    },
+    BlockStat(BlockId),
+    TotalFilCircSupply,
+    VerifySignature {
+        sig_type: SignatureType,
+        signature: Vec<u8>,
+        signer: Address,
+        plaintext: Vec<u8>,
+    },
+    RecoverSecpPublicKey {
+        hash: [u8; SECP_SIG_MESSAGE_HASH_SIZE],
+        signature: [u8; SECP_SIG_LEN],
+    },
+    Hash {
+        code: u64,
+        data: Vec<u8>,
+    },
+    VerifyMerkleProof(MerkleProof),
+    VerifySignaturesBatch(Vec<SignatureBatchEntry>),
+    Log(String),
+    DebugEnabled,
+    StoreArtifact {
+        name: String,
+        data: Vec<u8>,
+    },
+    EmitEvent {
+        event_headers: Vec<EventEntry>,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    ValidateUtf8(Vec<u8>),
+    GasUsed,
+    GasAvailable,
+    ChargeGas {
+        name: String,
+        compute: Gas,
+    },
+    MsgContext,
+    NetworkContext,
+    TipsetCid(ChainEpoch),
+    GetRandomnessFromTickets(ChainEpoch),
+    GetRandomnessFromBeacon(ChainEpoch),
+    Root,
+    SetRoot(Cid),
+    CurrentBalance,
+    SelfDestruct {
+        burn_unspent: bool,
+    },
+}
+
+/// The value returned by a successful kernel operation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpValue {
+    Unit,
+    Bool(bool),
+    Bools(Vec<bool>),
+    ActorId(ActorID),
+    ActorType(u32),
+    Address(Address),
+    DelegatedAddress(Option<Address>),
+    Cid(Cid),
+    TokenAmount(TokenAmount),
+    Gas(Gas),
+    Block(BlockId),
+    BlockStat(BlockStat),
+    OpenedBlock(BlockId, BlockStat),
+    /// The value returned by `block_read`, along with the contents of the buffer after the read.
+    BlockRead {
+        remaining: i32,
+        data: Vec<u8>,
+    },
+    Multihash(MultihashGeneric<64>),
+    PublicKey([u8; SECP_PUB_LEN]),
+    Randomness([u8; RANDOMNESS_LENGTH]),
+    MessageContext(MessageContext),
+    NetworkContext(NetworkContext),
+    Call {
+        block_id: BlockId,
+        block_stat: BlockStat,
+        exit_code: ExitCode,
+    },
+}
+
+/// The error returned by a failed kernel operation. Error messages of syscall errors are not
+/// recorded, as they're not part of the kernel's interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpError {
+    OutOfGas,
+    Syscall(ErrorNumber),
+    Fatal(String),
+    Timeout,
+}
+
+impl From<&ExecutionError> for OpError {
+    fn from(e: &ExecutionError) -> Self {
+        match e {
+            ExecutionError::OutOfGas => OpError::OutOfGas,
+            ExecutionError::Syscall(SyscallError(_, code)) => OpError::Syscall(*code),
+            ExecutionError::Fatal(e) => OpError::Fatal(e.to_string()),
+            ExecutionError::Timeout => OpError::Timeout,
+        }
+    }
+}
+
+/// The outcome of a kernel operation.
+pub type OpResult = std::result::Result<OpValue, OpError>;
+
+/// A kernel operation and its outcome, as recorded by a [`RecordingKernel`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedOp {
+    pub op: KernelOp,
+    pub result: OpResult,
+}
+
+/// Returned by [`replay`] when a kernel operation doesn't reproduce its recorded outcome.
+#[derive(thiserror::Error, Debug)]
+#[error("kernel op {index} ({op:?}) returned {actual:?}, expected {expected:?}")]
+pub struct ReplayMismatch {
+    /// The index of the operation in the replayed sequence.
+    pub index: usize,
+    pub op: KernelOp,
+    pub expected: OpResult,
+    pub actual: OpResult,
+}
+
+/// Wraps a [`Kernel`], recording every kernel operation (along with its arguments and outcome)
+/// made through it. The recorded operations can be inspected with [`RecordingKernel::ops`], and
+/// replayed against another kernel with [`replay`].
+///
+/// Each kernel records the operations made by a single actor invocation: kernels created for
+/// nested calls record their operations separately. Actors running on a recording kernel have
+/// access to the syscalls of the [`DefaultKernel`].
+pub struct RecordingKernel<K> {
+    inner: K,
+    ops: RefCell<Vec<RecordedOp>>,
+}
+
+impl<K> RecordingKernel<K> {
+    /// Wrap an existing kernel.
+    pub fn wrap(inner: K) -> Self {
+        RecordingKernel {
+            inner,
+            ops: RefCell::default(),
+        }
+    }
+
+    /// The wrapped kernel.
+    pub fn inner(&self) -> &K {
+        &self.inner
+    }
+
+    /// The operations recorded so far, in order.
+    pub fn ops(&self) -> Ref<'_, [RecordedOp]> {
+        Ref::map(self.ops.borrow(), Vec::as_slice)
+    }
+
+    /// Take the operations recorded so far, clearing the record.
+    pub fn take_ops(&self) -> Vec<RecordedOp> {
+        self.ops.take()
+    }
+
+    fn record<T>(&self, op: KernelOp, result: &Result<T>, value: impl FnOnce(&T) -> OpValue) {
+        let result = match result {
+            Ok(v) => Ok(value(v)),
+            Err(e) => Err(e.into()),
+        };
+        self.ops.borrow_mut().push(RecordedOp { op, result });
+    }
+}
+
+/// Replays the recorded operations against the given kernel, checking that each operation
+/// reproduces its recorded outcome. Replay stops at the first mismatch.
+///
+/// Sends and upgrades are replayed against the given kernel type.
+pub fn replay<K: Kernel>(
+    kernel: &mut K,
+    ops: &[RecordedOp],
+) -> std::result::Result<(), ReplayMismatch> {
+    for (index, recorded) in ops.iter().enumerate() {
+        let actual = apply(kernel, &recorded.op);
+        if actual != recorded.result {
+            return Err(ReplayMismatch {
+                index,
+                op: recorded.op.clone(),
+                expected: recorded.result.clone(),
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Performs a single operation on the given kernel.
+fn apply<K: Kernel>(k: &mut K, op: &KernelOp) -> OpResult {
+    fn outcome<T>(result: Result<T>, value: impl FnOnce(T) -> OpValue) -> OpResult {
+        result.map(value).map_err(|e| (&e).into())
+    }
+
+    fn call_result(res: CallResult) -> OpValue {
+        OpValue::Call {
+            block_id: res.block_id,
+            block_stat: res.block_stat,
+            exit_code: res.exit_code,
+        }
+    }
+
+    match op {
+        KernelOp::Send {
+            recipient,
+            method,
+            params,
+            value,
+            gas_limit,
+            flags,
+        } => outcome(
+            k.send::<K>(recipient, *method, *params, value, *gas_limit, *flags),
+            call_result,
+        ),
+        KernelOp::UpgradeActor {
+            new_code_cid,
+            params,
+        } => outcome(k.upgrade_actor::<K>(*new_code_cid, *params), call_result),
+        KernelOp::DeferSend {
+            recipient,
+            method,
+            params,
+            value,
+            gas_limit,
+        } => outcome(
+            k.defer_send(recipient, *method, *params, value, *gas_limit),
+            |_| OpValue::Unit,
+        ),
+        KernelOp::ResolveAddress(address) => outcome(k.resolve_address(address), OpValue::ActorId),
+        KernelOp::LookupDelegatedAddress(id) => {
+            outcome(k.lookup_delegated_address(*id), OpValue::DelegatedAddress)
+        }
+        KernelOp::GetActorCodeCid(id) => outcome(k.get_actor_code_cid(*id), OpValue::Cid),
+        KernelOp::NextActorAddress => outcome(k.next_actor_address(), OpValue::Address),
+        KernelOp::CreateActor {
+            code_cid,
+            actor_id,
+            delegated_address,
+        } => outcome(
+            k.create_actor(*code_cid, *actor_id, *delegated_address),
+            |_| OpValue::Unit,
+        ),
+        KernelOp::InstallActor(code_cid) => outcome(k.install_actor(*code_cid), |_| OpValue::Unit),
+        KernelOp::GetBuiltinActorType(code_cid) => {
+            outcome(k.get_builtin_actor_type(code_cid), OpValue::ActorType)
+        }
+        KernelOp::GetCodeCidForType(typ) => outcome(k.get_code_cid_for_type(*typ), OpValue::Cid),
+        KernelOp::BalanceOf(id) => outcome(k.balance_of(*id), OpValue::TokenAmount),
+        KernelOp::BlockOpen(cid) => outcome(k.block_open(cid), |(id, stat)| {
+            OpValue::OpenedBlock(id, stat)
+        }),
+        KernelOp::BlockCreate { codec, data } => {
+            outcome(k.block_create(*codec, data), OpValue::Block)
+        }
+        KernelOp::BlockLink {
+            id,
+            hash_fun,
+            hash_len,
+        } => outcome(k.block_link(*id, *hash_fun, *hash_len), OpValue::Cid),
+        KernelOp::BlockRead { id, offset, len } => {
+            let mut data = vec![0; *len];
+            outcome(k.block_read(*id, *offset, &mut data), |remaining| {
+                OpValue::BlockRead { remaining, data }
+            })
+        }
+        KernelOp::BlockStat(id) => outcome(k.block_stat(*id), OpValue::BlockStat),
+        KernelOp::TotalFilCircSupply => outcome(k.total_fil_circ_supply(), OpValue::TokenAmount),
+        KernelOp::VerifySignature {
+            sig_type,
+            signature,
+            signer,
+            plaintext,
+        } => outcome(
+            k.verify_signature(*sig_type, signature, signer, plaintext),
+            OpValue::Bool,
+        ),
+        KernelOp::RecoverSecpPublicKey { hash, signature } => outcome(
+            k.recover_secp_public_key(hash, signature),
+            OpValue::PublicKey,
+        ),
+        KernelOp::Hash { code, data } => outcome(k.hash(*code, data), OpValue::Multihash),
+        KernelOp::VerifyMerkleProof(proof) => outcome(k.verify_merkle_proof(proof), OpValue::Bool),
+        KernelOp::VerifySignaturesBatch(entries) => {
+            outcome(k.verify_signatures_batch(entries), OpValue::Bools)
+        }
+        KernelOp::Log(msg) => {
+            k.log(msg.clone());
+            Ok(OpValue::Unit)
+        }
+        KernelOp::DebugEnabled => Ok(OpValue::Bool(k.debug_enabled())),
+        KernelOp::StoreArtifact { name, data } => {
+            outcome(k.store_artifact(name, data), |_| OpValue::Unit)
+        }
+        KernelOp::EmitEvent {
+            event_headers,
+            key,
+            value,
+        } => outcome(k.emit_event(event_headers, key, value), |_| OpValue::Unit),
+        KernelOp::ValidateUtf8(bytes) => outcome(k.validate_utf8(bytes), OpValue::Bool),
+        KernelOp::GasUsed => Ok(OpValue::Gas(k.gas_used())),
+        KernelOp::GasAvailable => Ok(OpValue::Gas(k.gas_available())),
+        KernelOp::ChargeGas { name, compute } => {
+            outcome(k.charge_gas(name, *compute), |_| OpValue::Unit)
+        }
+        KernelOp::MsgContext => outcome(k.msg_context(), OpValue::MessageContext),
+        KernelOp::NetworkContext => outcome(k.network_context(), OpValue::NetworkContext),
+        KernelOp::TipsetCid(epoch) => outcome(k.tipset_cid(*epoch), OpValue::Cid),
+        KernelOp::GetRandomnessFromTickets(epoch) => {
+            outcome(k.get_randomness_from_tickets(*epoch), OpValue::Randomness)
+        }
+        KernelOp::GetRandomnessFromBeacon(epoch) => {
+            outcome(k.get_randomness_from_beacon(*epoch), OpValue::Randomness)
+        }
+        KernelOp::Root => outcome(k.root(), OpValue::Cid),
+        KernelOp::SetRoot(root) => outcome(k.set_root(*root), |_| OpValue::Unit),
+        KernelOp::CurrentBalance => outcome(k.current_balance(), OpValue::TokenAmount),
+        KernelOp::SelfDestruct { burn_unspent } => {
+            outcome(k.self_destruct(*burn_unspent), |_| OpValue::Unit)
+        }
+    }
+}
+
+impl<K> Kernel for RecordingKernel<K>
+where
+    K: Kernel,
+    Self: SyscallHandler<Self>,
+{
+    type CallManager = K::CallManager;
+
+    fn into_inner(self) -> (Self::CallManager, BlockRegistry)
+    where
+        Self: Sized,
+    {
+        self.inner.into_inner()
+    }
+
+    fn new(
+        mgr: Self::CallManager,
+        blocks: BlockRegistry,
+        caller: ActorID,
+        actor_id: ActorID,
+        method: MethodNum,
+        value_received: TokenAmount,
+        read_only: bool,
+    ) -> Self
+    where
+        Self: Sized,
+    {
+        RecordingKernel::wrap(K::new(
+            mgr,
+            blocks,
+            caller,
+            actor_id,
+            method,
+            value_received,
+            read_only,
+        ))
+    }
+
+    fn machine(&self) -> &<Self::CallManager as CallManager>::Machine {
+        self.inner.machine()
+    }
+
+    fn send<KK: Kernel<CallManager = Self::CallManager>>(
+        &mut self,
+        recipient: &Address,
+        method: u64,
+        params: BlockId,
+        value: &TokenAmount,
+        gas_limit: Option<Gas>,
+        flags: SendFlags,
+    ) -> Result<CallResult> {
+        let res = self
+            .inner
+            .send::<KK>(recipient, method, params, value, gas_limit, flags);
+        self.record(
+            KernelOp::Send {
+                recipient: *recipient,
+                method,
+                params,
+                value: value.clone(),
+                gas_limit,
+                flags,
+            },
+            &res,
+            |r| OpValue::Call {
+                block_id: r.block_id,
+                block_stat: r.block_stat,
+                exit_code: r.exit_code,
+            },
+        );
+        res
+    }
+
+    fn upgrade_actor<KK: Kernel<CallManager = Self::CallManager>>(
+        &mut self,
+        new_code_cid: Cid,
+        params_id: BlockId,
+    ) -> Result<CallResult> {
+        let res = self.inner.upgrade_actor::<KK>(new_code_cid, params_id);
+        self.record(
+            KernelOp::UpgradeActor {
+                new_code_cid,
+                params: params_id,
+            },
+            &res,
+            |r| OpValue::Call {
+                block_id: r.block_id,
+                block_stat: r.block_stat,
+                exit_code: r.exit_code,
+            },
+        );
+        res
+    }
+
+    fn defer_send(
+        &mut self,
+        recipient: &Address,
+        method: u64,
+        params: BlockId,
+        value: &TokenAmount,
+        gas_limit: Gas,
+    ) -> Result<()> {
+        let res = self
+            .inner
+            .defer_send(recipient, method, params, value, gas_limit);
+        self.record(
+            KernelOp::DeferSend {
+                recipient: *recipient,
+                method,
+                params,
+                value: value.clone(),
+                gas_limit,
+            },
+            &res,
+            |_| OpValue::Unit,
+        );
+        res
+    }
+}
+
+impl<C> SyscallHandler<RecordingKernel<DefaultKernel<C>>> for RecordingKernel<DefaultKernel<C>>
+where
+    C: CallManager,
+{
+    fn bind_syscalls(
+        &self,
+        linker: &mut Linker<InvocationData<RecordingKernel<DefaultKernel<C>>>>,
+    ) -> anyhow::Result<()> {
+        self.inner.bind_syscalls(linker)
+    }
+}
+
+impl<K: ActorOps> ActorOps for RecordingKernel<K> {
+    fn resolve_address(&self, address: &Address) -> Result<ActorID> {
+        let res = self.inner.resolve_address(address);
+        self.record(KernelOp::ResolveAddress(*address), &res, |id| {
+            OpValue::ActorId(*id)
+        });
+        res
+    }
+
+    fn lookup_delegated_address(&self, actor_id: ActorID) -> Result<Option<Address>> {
+        let res = self.inner.lookup_delegated_address(actor_id);
+        self.record(KernelOp::LookupDelegatedAddress(actor_id), &res, |addr| {
+            OpValue::DelegatedAddress(*addr)
+        });
+        res
+    }
+
+    fn get_actor_code_cid(&self, id: ActorID) -> Result<Cid> {
+        let res = self.inner.get_actor_code_cid(id);
+        self.record(KernelOp::GetActorCodeCid(id), &res, |cid| {
+            OpValue::Cid(*cid)
+        });
+        res
+    }
+
+    fn next_actor_address(&self) -> Result<Address> {
+        let res = self.inner.next_actor_address();
+        self.record(KernelOp::NextActorAddress, &res, |addr| {
+            OpValue::Address(*addr)
+        });
+        res
+    }
+
+    fn create_actor(
+        &mut self,
+        code_cid: Cid,
+        actor_id: ActorID,
+        delegated_address: Option<Address>,
+    ) -> Result<()> {
+        let res = self
+            .inner
+            .create_actor(code_cid, actor_id, delegated_address);
+        self.record(
+            KernelOp::CreateActor {
+                code_cid,
+                actor_id,
+                delegated_address,
+            },
+            &res,
+            |_| OpValue::Unit,
+        );
+        res
+    }
+
+    fn install_actor(&mut self, code_cid: Cid) -> Result<()> {
+        let res = self.inner.install_actor(code_cid);
+        self.record(KernelOp::InstallActor(code_cid), &res, |_| OpValue::Unit);
+        res
+    }
+
+    fn get_builtin_actor_type(&self, code_cid: &Cid) -> Result<u32> {
+        let res = self.inner.get_builtin_actor_type(code_cid);
+        self.record(KernelOp::GetBuiltinActorType(*code_cid), &res, |typ| {
+            OpValue::ActorType(*typ)
+        });
+        res
+    }
+
+    fn get_code_cid_for_type(&self, typ: u32) -> Result<Cid> {
+        let res = self.inner.get_code_cid_for_type(typ);
+        self.record(KernelOp::GetCodeCidForType(typ), &res, |cid| {
+            OpValue::Cid(*cid)
+        });
+        res
+    }
+
+    fn balance_of(&self, actor_id: ActorID) -> Result<TokenAmount> {
+        let res = self.inner.balance_of(actor_id);
+        self.record(KernelOp::BalanceOf(actor_id), &res, |balance| {
+            OpValue::TokenAmount(balance.clone())
+        });
+        res
+    }
+}
+
+impl<K: IpldBlockOps> IpldBlockOps for RecordingKernel<K> {
+    fn block_open(&mut self, cid: &Cid) -> Result<(BlockId, BlockStat)> {
+        let res = self.inner.block_open(cid);
+        self.record(KernelOp::BlockOpen(*cid), &res, |(id, stat)| {
+            OpValue::OpenedBlock(*id, *stat)
+        });
+        res
+    }
+
+    fn block_create(&mut self, codec: u64, data: &[u8]) -> Result<BlockId> {
+        let res = self.inner.block_create(codec, data);
+        self.record(
+            KernelOp::BlockCreate {
+                codec,
+                data: data.to_vec(),
+            },
+            &res,
+            |id| OpValue::Block(*id),
+        );
+        res
+    }
+
+    fn block_link(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid> {
+        let res = self.inner.block_link(id, hash_fun, hash_len);
+        self.record(
+            KernelOp::BlockLink {
+                id,
+                hash_fun,
+                hash_len,
+            },
+            &res,
+            |cid| OpValue::Cid(*cid),
+        );
+        res
+    }
+
+    fn block_read(&self, id: BlockId, offset: u32, buf: &mut [u8]) -> Result<i32> {
+        let res = self.inner.block_read(id, offset, buf);
+        self.record(
+            KernelOp::BlockRead {
+                id,
+                offset,
+                len: buf.len(),
+            },
+            &res,
+            |remaining| OpValue::BlockRead {
+                remaining: *remaining,
+                data: buf.to_vec(),
+            },
+        );
+        res
+    }
+
+    fn block_stat(&self, id: BlockId) -> Result<BlockStat> {
+        let res = self.inner.block_stat(id);
+        self.record(KernelOp::BlockStat(id), &res, |stat| {
+            OpValue::BlockStat(*stat)
+        });
+        res
+    }
+}
+
+impl<K: CircSupplyOps> CircSupplyOps for RecordingKernel<K> {
+    fn total_fil_circ_supply(&self) -> Result<TokenAmount> {
+        let res = self.inner.total_fil_circ_supply();
+        self.record(KernelOp::TotalFilCircSupply, &res, |supply| {
+            OpValue::TokenAmount(supply.clone())
+        });
+        res
+    }
+}
+
+impl<K: CryptoOps> CryptoOps for RecordingKernel<K> {
+    fn verify_signature(
+        &self,
+        sig_type: SignatureType,
+        signature: &[u8],
+        signer: &Address,
+        plaintext: &[u8],
+    ) -> Result<bool> {
+        let res = self
+            .inner
+            .verify_signature(sig_type, signature, signer, plaintext);
+        self.record(
+            KernelOp::VerifySignature {
+                sig_type,
+                signature: signature.to_vec(),
+                signer: *signer,
+                plaintext: plaintext.to_vec(),
+            },
+            &res,
+            |valid| OpValue::Bool(*valid),
+        );
+        res
+    }
+
+    fn recover_secp_public_key(
+        &self,
+        hash: &[u8; SECP_SIG_MESSAGE_HASH_SIZE],
+        signature: &[u8; SECP_SIG_LEN],
+    ) -> Result<[u8; SECP_PUB_LEN]> {
+        let res = self.inner.recover_secp_public_key(hash, signature);
+        self.record(
+            KernelOp::RecoverSecpPublicKey {
+                hash: *hash,
+                signature: *signature,
+            },
+            &res,
+            |key| OpValue::PublicKey(*key),
+        );
+        res
+    }
+
+    fn hash(&self, code: u64, data: &[u8]) -> Result<MultihashGeneric<64>> {
+        let res = self.inner.hash(code, data);
+        self.record(
+            KernelOp::Hash {
+                code,
+                data: data.to_vec(),
+            },
+            &res,
+            |digest| OpValue::Multihash(*digest),
+        );
+        res
+    }
+
+    fn verify_merkle_proof(&self, proof: &MerkleProof) -> Result<bool> {
+        let res = self.inner.verify_merkle_proof(proof);
+        self.record(KernelOp::VerifyMerkleProof(proof.clone()), &res, |valid| {
+            OpValue::Bool(*valid)
+        });
+        res
+    }
+
+    fn verify_signatures_batch(&self, entries: &[SignatureBatchEntry]) -> Result<Vec<bool>> {
+        let res = self.inner.verify_signatures_batch(entries);
+        self.record(
+            KernelOp::VerifySignaturesBatch(entries.to_vec()),
+            &res,
+            |valid| OpValue::Bools(valid.clone()),
+        );
+        res
+    }
+}
+
+impl<K: DebugOps> DebugOps for RecordingKernel<K> {
+    fn log(&self, msg: String) {
+        self.record(KernelOp::Log(msg.clone()), &Ok(()), |_| OpValue::Unit);
+        self.inner.log(msg)
+    }
+
+    fn debug_enabled(&self) -> bool {
+        let enabled = self.inner.debug_enabled();
+        self.record(KernelOp::DebugEnabled, &Ok(enabled), |enabled| {
+            OpValue::Bool(*enabled)
+        });
+        enabled
+    }
+
+    fn store_artifact(&self, name: &str, data: &[u8]) -> Result<()> {
+        let res = self.inner.store_artifact(name, data);
+        self.record(
+            KernelOp::StoreArtifact {
+                name: name.to_owned(),
+                data: data.to_vec(),
+            },
+            &res,
+            |_| OpValue::Unit,
+        );
+        res
+    }
+}
+
+impl<K: EventOps> EventOps for RecordingKernel<K> {
+    fn emit_event(
+        &mut self,
+        event_headers: &[EventEntry],
+        raw_key: &[u8],
+        raw_val: &[u8],
+    ) -> Result<()> {
+        let res = self.inner.emit_event(event_headers, raw_key, raw_val);
+        self.record(
+            KernelOp::EmitEvent {
+                event_headers: event_headers.to_vec(),
+                key: raw_key.to_vec(),
+                value: raw_val.to_vec(),
+            },
+            &res,
+            |_| OpValue::Unit,
+        );
+        res
+    }
+}
+
+impl<K: UtilOps> UtilOps for RecordingKernel<K> {
+    fn validate_utf8(&self, bytes: &[u8]) -> Result<bool> {
+        let res = self.inner.validate_utf8(bytes);
+        self.record(KernelOp::ValidateUtf8(bytes.to_vec()), &res, |valid| {
+            OpValue::Bool(*valid)
+        });
+        res
+    }
+}
+
+impl<K: GasOps> GasOps for RecordingKernel<K> {
+    fn gas_used(&self) -> Gas {
+        let gas = self.inner.gas_used();
+        self.record(KernelOp::GasUsed, &Ok(gas), |gas| OpValue::Gas(*gas));
+        gas
+    }
+
+    fn gas_available(&self) -> Gas {
+        let gas = self.inner.gas_available();
+        self.record(KernelOp::GasAvailable, &Ok(gas), |gas| OpValue::Gas(*gas));
+        gas
+    }
+
+    fn charge_gas(&self, name: &str, compute: Gas) -> Result<GasTimer> {
+        let res = self.inner.charge_gas(name, compute);
+        self.record(
+            KernelOp::ChargeGas {
+                name: name.to_owned(),
+                compute,
+            },
+            &res,
+            |_| OpValue::Unit,
+        );
+        res
+    }
+
+    // Not recorded: this isn't a kernel operation.
+    fn price_list(&self) -> &PriceList {
+        self.inner.price_list()
+    }
+
+    // Not recorded: timings differ between runs.
+    fn trace_syscall(&self, module: &'static str, name: &'static str, elapsed: Duration) {
+        self.inner.trace_syscall(module, name, elapsed)
+    }
+}
+
+impl<K: MessageOps> MessageOps for RecordingKernel<K> {
+    fn msg_context(&self) -> Result<MessageContext> {
+        let res = self.inner.msg_context();
+        self.record(KernelOp::MsgContext, &res, |ctx| {
+            OpValue::MessageContext(*ctx)
+        });
+        res
+    }
+}
+
+impl<K: NetworkOps> NetworkOps for RecordingKernel<K> {
+    fn network_context(&self) -> Result<NetworkContext> {
+        let res = self.inner.network_context();
+        self.record(KernelOp::NetworkContext, &res, |ctx| {
+            OpValue::NetworkContext(*ctx)
+        });
+        res
+    }
+
+    fn tipset_cid(&self, epoch: ChainEpoch) -> Result<Cid> {
+        let res = self.inner.tipset_cid(epoch);
+        self.record(KernelOp::TipsetCid(epoch), &res, |cid| OpValue::Cid(*cid));
+        res
+    }
+}
+
+impl<K: RandomnessOps> RandomnessOps for RecordingKernel<K> {
+    fn get_randomness_from_tickets(
+        &self,
+        rand_epoch: ChainEpoch,
+    ) -> Result<[u8; RANDOMNESS_LENGTH]> {
+        let res = self.inner.get_randomness_from_tickets(rand_epoch);
+        self.record(KernelOp::GetRandomnessFromTickets(rand_epoch), &res, |r| {
+            OpValue::Randomness(*r)
+        });
+        res
+    }
+
+    fn get_randomness_from_beacon(
+        &self,
+        rand_epoch: ChainEpoch,
+    ) -> Result<[u8; RANDOMNESS_LENGTH]> {
+        let res = self.inner.get_randomness_from_beacon(rand_epoch);
+        self.record(KernelOp::GetRandomnessFromBeacon(rand_epoch), &res, |r| {
+            OpValue::Randomness(*r)
+        });
+        res
+    }
+}
+
+impl<K: SelfOps> SelfOps for RecordingKernel<K> {
+    fn root(&mut self) -> Result<Cid> {
+        let res = self.inner.root();
+        self.record(KernelOp::Root, &res, |cid| OpValue::Cid(*cid));
+        res
+    }
+
+    fn set_root(&mut self, root: Cid) -> Result<()> {
+        let res = self.inner.set_root(root);
+        self.record(KernelOp::SetRoot(root), &res, |_| OpValue::Unit);
+        res
+    }
+
+    fn current_balance(&self) -> Result<TokenAmount> {
+        let res = self.inner.current_balance();
+        self.record(KernelOp::CurrentBalance, &res, |balance| {
+            OpValue::TokenAmount(balance.clone())
+        });
+        res
+    }
+
+    fn self_destruct(&mut self, burn_unspent: bool) -> Result<()> {
+        let res = self.inner.self_destruct(burn_unspent);
+        self.record(KernelOp::SelfDestruct { burn_unspent }, &res, |_| {
+            OpValue::Unit
+        });
+        res
+    }
+}
+
+// Not recorded: the limiter isn't exposed to actors.
+impl<K: LimiterOps> LimiterOps for RecordingKernel<K> {
+    type Limiter = K::Limiter;
+
+    fn limiter_mut(&mut self) -> &mut Self::Limiter {
+        self.inner.limiter_mut()
+    }
+}
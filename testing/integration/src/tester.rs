@@ -3,13 +3,13 @@
 use anyhow::{anyhow, Context, Result};
 use cid::Cid;
 use fvm::call_manager::DefaultCallManager;
-use fvm::engine::EnginePool;
+use fvm::engine::{EngineConfig, EnginePool};
 use fvm::executor::DefaultExecutor;
 use fvm::externs::Externs;
 use fvm::kernel::filecoin::DefaultFilecoinKernel;
-use fvm::machine::{DefaultMachine, Machine, MachineContext, NetworkConfig};
+use fvm::machine::{DefaultMachine, Genesis, Machine, MachineContext, NetworkConfig};
 use fvm::state_tree::{ActorState, StateTree};
-use fvm::{init_actor, system_actor, DefaultKernel};
+use fvm::DefaultKernel;
 use fvm_ipld_blockstore::{Block, Blockstore, MemoryBlockstore};
 use fvm_ipld_encoding::{ser, CborStore};
 use fvm_shared::address::{Address, Protocol};
@@ -21,9 +21,7 @@ use lazy_static::lazy_static;
 use libsecp256k1::{PublicKey, SecretKey};
 use multihash::Code;
 
-use crate::builtin::{
-    fetch_builtin_code_cid, set_burnt_funds_account, set_eam_actor, set_init_actor, set_sys_actor,
-};
+use crate::builtin::fetch_builtin_code_cid;
 use crate::dummy::DummyExterns;
 use crate::error::Error::{FailedToFlushTree, NoManifestInformation};
 
@@ -91,20 +89,11 @@ where
                 None => return Err(NoManifestInformation(builtin_actors).into()),
             };
 
-        // Get sys and init actors code cid
-        let (sys_code_cid, init_code_cid, accounts_code_cid, placeholder_code_cid, eam_code_cid) =
+        let (accounts_code_cid, placeholder_code_cid) =
             fetch_builtin_code_cid(&blockstore, &manifest_data_cid, manifest_version)?;
 
-        // Initialize state tree
-        let init_state = init_actor::State::new_test(&blockstore);
-        let mut state_tree = StateTree::new(blockstore, stv).map_err(anyhow::Error::from)?;
-
-        // Deploy init, sys, burn, and eam actors
-        let sys_state = system_actor::State { builtin_actors };
-        set_sys_actor(&mut state_tree, sys_state, sys_code_cid)?;
-        set_init_actor(&mut state_tree, init_code_cid, init_state)?;
-        set_burnt_funds_account(&mut state_tree, accounts_code_cid)?;
-        set_eam_actor(&mut state_tree, eam_code_cid)?;
+        // Initialize the state tree with the init, sys, burn, and eam actors
+        let state_tree = Genesis::new(builtin_actors, "test").build_state_tree(blockstore, stv)?;
 
         Ok(Tester {
             nv,
@@ -244,6 +233,31 @@ where
         Ok(code_cid)
     }
 
+    /// Replace the code of an existing actor with a new wasm binary, preserving its state, balance
+    /// and sequence, and returns the CodeCID of the new code. This may be called between messages
+    /// once the machine has been instantiated, but only if actor debugging is enabled.
+    pub fn reload_actor_from_bin(
+        &mut self,
+        wasm_bin: &[u8],
+        actor_address: Address,
+    ) -> Result<Cid> {
+        let code_cid = match self.executor.as_mut() {
+            Some(executor) => {
+                if !executor.context().actor_debugging {
+                    return Err(anyhow!("actors can only be reloaded in debug mode"));
+                }
+                replace_actor_code(executor.state_tree_mut(), wasm_bin, &actor_address)?
+            }
+            None => {
+                replace_actor_code(self.state_tree.as_mut().unwrap(), wasm_bin, &actor_address)?
+            }
+        };
+
+        self.code_cids.push(code_cid);
+
+        Ok(code_cid)
+    }
+
     /// Sets the Machine and the Executor in our Tester structure.
     pub fn instantiate_machine(&mut self, externs: E) -> Result<()> {
         self.instantiate_machine_with_config(externs, |_| (), |_| ())?;
@@ -265,6 +279,23 @@ where
     where
         F: FnOnce(&mut NetworkConfig),
         G: FnOnce(&mut MachineContext),
+    {
+        self.instantiate_machine_with_engine_config(externs, configure_nc, configure_mc, |_| ())
+    }
+
+    /// Like [`Tester::instantiate_machine_with_config`], also letting the caller adjust the
+    /// `EngineConfig` (e.g., node-local engine options) derived from the `NetworkConfig`.
+    pub fn instantiate_machine_with_engine_config<F, G, H>(
+        &mut self,
+        externs: E,
+        configure_nc: F,
+        configure_mc: G,
+        configure_ec: H,
+    ) -> Result<()>
+    where
+        F: FnOnce(&mut NetworkConfig),
+        G: FnOnce(&mut MachineContext),
+        H: FnOnce(&mut EngineConfig),
     {
         // Take the state tree and leave None behind.
         let mut state_tree = self.state_tree.take().unwrap();
@@ -286,13 +317,18 @@ where
         configure_nc(&mut nc);
 
         let mut mc = nc.for_epoch(0, 0, state_root);
+        // The actors are preloaded below, along with any test actors.
         mc.set_base_fee(TokenAmount::from_atto(DEFAULT_BASE_FEE))
-            .enable_tracing();
+            .enable_tracing()
+            .enable_gas_breakdown()
+            .set_preload_builtin_actors(false);
 
         // Custom configuration.
         configure_mc(&mut mc);
 
-        let engine = EnginePool::new_default((&mc.network.clone()).into())?;
+        let mut ec: EngineConfig = (&mc.network.clone()).into();
+        configure_ec(&mut ec);
+        let engine = EnginePool::new_default(ec)?;
         engine.acquire().preload(&blockstore, &self.code_cids)?;
 
         let machine = DefaultMachine::new(&mc, blockstore, externs)?;
@@ -422,6 +458,28 @@ impl BasicTester {
     }
 }
 
+/// Inserts the WASM code into the state tree's blockstore and replaces the given actor's code with
+/// it. The engine compiles the new code the next time the actor is invoked.
+fn replace_actor_code<S: Blockstore>(
+    state_tree: &mut StateTree<S>,
+    wasm_binary: &[u8],
+    actor_address: &Address,
+) -> Result<Cid> {
+    let actor_id = state_tree
+        .lookup_id(actor_address)
+        .map_err(anyhow::Error::from)?
+        .ok_or_else(|| anyhow!("actor {} not found", actor_address))?;
+    let mut actor_state = state_tree
+        .get_actor(actor_id)
+        .map_err(anyhow::Error::from)?
+        .ok_or_else(|| anyhow!("actor {} not found", actor_address))?;
+
+    let code_cid = put_wasm_code(state_tree.store(), wasm_binary)?;
+    actor_state.code = code_cid;
+    state_tree.set_actor(actor_id, actor_state);
+    Ok(code_cid)
+}
+
 /// Inserts the WASM code for the actor into the blockstore.
 fn put_wasm_code(blockstore: &impl Blockstore, wasm_binary: &[u8]) -> Result<Cid> {
     let cid = blockstore.put(
@@ -5,7 +5,7 @@
 mod bundles;
 use bundles::*;
 use fvm::executor::{ApplyKind, Executor};
-use fvm::gas::GasCharge;
+use fvm::gas::{price_list_by_network_version, GasBreakdown, GasCharge, PriceList};
 use fvm::machine::Machine;
 use fvm_integration_tests::dummy::DummyExterns;
 use fvm_ipld_blockstore::MemoryBlockstore;
@@ -108,6 +108,13 @@ fn basic_send() {
             .unwrap();
         assert!(res.msg_receipt.exit_code.is_success());
 
+        // The breakdown aggregates the charges by name.
+        let mut breakdown = GasBreakdown::new();
+        for charge in &case.trace {
+            *breakdown.entry(charge.name.to_string()).or_default() += charge.total();
+        }
+        assert_eq!(res.gas_breakdown, breakdown);
+
         let charges: Vec<_> = res
             .exec_trace
             .into_iter()
@@ -120,3 +127,437 @@ fn basic_send() {
         assert_eq!(charges, case.trace);
     }
 }
+
+#[test]
+fn send_interceptor() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use fvm::call_manager::{InterceptedCall, InvocationResult, SendInterceptor};
+    use fvm_shared::error::ExitCode;
+    use fvm_shared::ActorID;
+
+    /// Forbids transferring value to the given address, and counts completed calls.
+    struct NoValueTo {
+        forbidden: Address,
+        completed: AtomicUsize,
+    }
+
+    impl SendInterceptor for NoValueTo {
+        fn before_send(
+            &self,
+            _from: ActorID,
+            call: &mut InterceptedCall,
+        ) -> fvm::kernel::Result<Option<InvocationResult>> {
+            if call.to == self.forbidden && !call.value.is_zero() {
+                return Ok(Some(InvocationResult {
+                    exit_code: ExitCode::USR_FORBIDDEN,
+                    value: None,
+                }));
+            }
+            Ok(None)
+        }
+
+        fn after_send(
+            &self,
+            _from: ActorID,
+            _call: &InterceptedCall,
+            _result: &fvm::kernel::Result<InvocationResult>,
+        ) {
+            self.completed.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let (_, sender) = tester.create_account().unwrap();
+    let receiver = Address::new_delegated(10, b"foobar").expect("failed to construct f4 address");
+
+    let interceptor = Arc::new(NoValueTo {
+        forbidden: receiver,
+        completed: AtomicUsize::new(0),
+    });
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |nc| {
+                nc.set_send_interceptor(interceptor.clone());
+            },
+            |_| (),
+        )
+        .unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    for (i, (value, exit_code)) in [(0, ExitCode::OK), (1, ExitCode::USR_FORBIDDEN)]
+        .into_iter()
+        .enumerate()
+    {
+        let message = Message {
+            from: sender,
+            to: receiver,
+            gas_limit: 1000000000,
+            method_num: METHOD_SEND,
+            sequence: i as u64,
+            value: TokenAmount::from_atto(value),
+            ..Message::default()
+        };
+
+        let res = executor
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert_eq!(res.msg_receipt.exit_code, exit_code);
+    }
+
+    // Only the first call went through.
+    assert_eq!(interceptor.completed.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn halt_and_dump() {
+    use std::sync::Arc;
+
+    use anyhow::anyhow;
+    use fvm::call_manager::{InterceptedCall, InvocationResult, SendInterceptor};
+    use fvm::executor::ExecutionOptions;
+    use fvm::kernel::ExecutionError;
+    use fvm_shared::ActorID;
+
+    /// Fails every call with a fatal error.
+    struct Explode;
+
+    impl SendInterceptor for Explode {
+        fn before_send(
+            &self,
+            _from: ActorID,
+            _call: &mut InterceptedCall,
+        ) -> fvm::kernel::Result<Option<InvocationResult>> {
+            Err(ExecutionError::Fatal(anyhow!("boom")))
+        }
+    }
+
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let (_, sender) = tester.create_account().unwrap();
+    let receiver = Address::new_delegated(10, b"foobar").expect("failed to construct f4 address");
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |nc| {
+                nc.set_send_interceptor(Arc::new(Explode));
+            },
+            |_| (),
+        )
+        .unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let dir = std::env::temp_dir().join(format!("fvm-halt-and-dump-{}", std::process::id()));
+    executor
+        .0
+        .set_options(ExecutionOptions::halt_and_dump(&dir));
+    let state_root = executor.0.state_tree_mut().flush().unwrap();
+
+    let message = Message {
+        from: sender,
+        to: receiver,
+        gas_limit: 1000000000,
+        method_num: METHOD_SEND,
+        ..Message::default()
+    };
+    let err = executor
+        .execute_message(message.clone(), ApplyKind::Explicit, 100)
+        .unwrap_err();
+    assert!(format!("{:#}", err).contains("boom"));
+
+    // The failed message was reverted.
+    assert_eq!(executor.0.state_tree_mut().flush().unwrap(), state_root);
+
+    let dumps: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(dumps.len(), 1);
+    let dump = &dumps[0];
+    assert_eq!(
+        std::fs::read_to_string(dump.join("state_root")).unwrap(),
+        state_root.to_string()
+    );
+    assert_eq!(
+        std::fs::read(dump.join("message.cbor")).unwrap(),
+        fvm_ipld_encoding::to_vec(&message).unwrap()
+    );
+    assert!(std::fs::read_to_string(dump.join("trace.txt"))
+        .unwrap()
+        .contains("GasCharge"));
+    assert!(std::fs::read_to_string(dump.join("error.txt"))
+        .unwrap()
+        .contains("boom"));
+    let overlay: Vec<(cid::Cid, fvm_ipld_encoding::RawBytes)> =
+        fvm_ipld_encoding::from_slice(&std::fs::read(dump.join("overlay.cbor")).unwrap()).unwrap();
+    assert!(overlay.iter().any(|(k, _)| *k == state_root));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn state_cache_audit() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let (_, sender) = tester.create_account().unwrap();
+    let receiver = Address::new_delegated(10, b"foobar").expect("failed to construct f4 address");
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |_| (),
+            |mc| {
+                mc.enable_state_cache_audit();
+            },
+        )
+        .unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    // Create the receiver, then transfer some value to it. The audit passes after each message.
+    for (i, value) in [0, 1].into_iter().enumerate() {
+        let message = Message {
+            from: sender,
+            to: receiver,
+            gas_limit: 1000000000,
+            method_num: METHOD_SEND,
+            sequence: i as u64,
+            value: TokenAmount::from_atto(value),
+            ..Message::default()
+        };
+
+        let res = executor
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert!(res.msg_receipt.exit_code.is_success());
+    }
+}
+
+#[test]
+fn custom_price_list() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let (_, sender) = tester.create_account().unwrap();
+    let receiver = Address::new_delegated(10, b"foobar").expect("failed to construct f4 address");
+
+    let price_list: &'static PriceList = Box::leak(Box::new(
+        price_list_by_network_version(NetworkVersion::V21).clone(),
+    ));
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |_| (),
+            |mc| {
+                mc.set_price_list(price_list);
+            },
+        )
+        .unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+    assert!(std::ptr::eq(executor.context().price_list, price_list));
+
+    let message = Message {
+        from: sender,
+        to: receiver,
+        gas_limit: 1000000000,
+        method_num: METHOD_SEND,
+        ..Message::default()
+    };
+
+    let res = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+}
+
+#[test]
+fn namespace_resolver() {
+    use std::sync::Arc;
+
+    use fvm::call_manager::EthMaskedIdResolver;
+
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_, sender), (receiver_id, _)] = tester.create_accounts().unwrap();
+
+    // The masked ID address of the receiver, which isn't registered with the init actor.
+    let mut subaddress = [0u8; 20];
+    subaddress[0] = 0xff;
+    subaddress[12..].copy_from_slice(&receiver_id.to_be_bytes());
+    let receiver = Address::new_delegated(10, &subaddress).unwrap();
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |nc| {
+                nc.register_namespace_resolver(10, Arc::new(EthMaskedIdResolver));
+            },
+            |_| (),
+        )
+        .unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let balance_before = executor
+        .state_tree()
+        .get_actor(receiver_id)
+        .unwrap()
+        .unwrap()
+        .balance;
+
+    let message = Message {
+        from: sender,
+        to: receiver,
+        gas_limit: 1000000000,
+        method_num: METHOD_SEND,
+        value: TokenAmount::from_atto(1),
+        ..Message::default()
+    };
+
+    let res = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+
+    // The value was transferred to the existing account instead of a new placeholder.
+    assert_eq!(
+        executor
+            .state_tree()
+            .get_actor(receiver_id)
+            .unwrap()
+            .unwrap()
+            .balance,
+        balance_before + TokenAmount::from_atto(1)
+    );
+    assert_eq!(executor.state_tree().lookup_id(&receiver).unwrap(), None);
+
+    // Masked IDs of missing actors fall back to the init actor, creating a placeholder.
+    let missing_id = 9999u64;
+    subaddress[12..].copy_from_slice(&missing_id.to_be_bytes());
+    let missing = Address::new_delegated(10, &subaddress).unwrap();
+    let message = Message {
+        from: sender,
+        to: missing,
+        gas_limit: 1000000000,
+        method_num: METHOD_SEND,
+        sequence: 1,
+        value: TokenAmount::from_atto(1),
+        ..Message::default()
+    };
+
+    let res = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+    let placeholder_id = executor.state_tree().lookup_id(&missing).unwrap().unwrap();
+    assert_ne!(placeholder_id, missing_id);
+    assert!(executor
+        .state_tree()
+        .get_actor(missing_id)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn validate_block() {
+    use cid::Cid;
+    use fvm::executor::{BlockMessages, SignedMessage};
+    use fvm_ipld_encoding::{to_vec, DAG_CBOR};
+    use fvm_shared::crypto::signature::Signature;
+    use multihash::{Code, MultihashDigest};
+
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let key = libsecp256k1::SecretKey::parse(&[1; 32]).unwrap();
+    let (sender_id, _) = tester
+        .make_secp256k1_account(key, TokenAmount::from_whole(100))
+        .unwrap();
+    let receiver = Address::new_delegated(10, b"foobar").expect("failed to construct f4 address");
+
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let sign = |message: Message| {
+        let cid = Cid::new_v1(
+            DAG_CBOR,
+            Code::Blake2b256.digest(&to_vec(&message).unwrap()),
+        );
+        let hash = blake2b_simd::Params::new()
+            .hash_length(32)
+            .hash(&cid.to_bytes());
+        let (sig, recovery_id) = libsecp256k1::sign(
+            &libsecp256k1::Message::parse_slice(hash.as_bytes()).unwrap(),
+            &key,
+        );
+        let mut bytes = sig.serialize().to_vec();
+        bytes.push(recovery_id.serialize());
+        SignedMessage {
+            message,
+            signature: Signature::new_secp256k1(bytes),
+        }
+    };
+
+    // Signed by the sender's key, but sent from its ID address.
+    let messages: Vec<_> = (0..3)
+        .map(|sequence| {
+            sign(Message {
+                from: Address::new_id(sender_id),
+                to: receiver,
+                gas_limit: 1000000000,
+                method_num: METHOD_SEND,
+                sequence,
+                value: TokenAmount::from_atto(1),
+                ..Message::default()
+            })
+        })
+        .collect();
+
+    let block = BlockMessages {
+        secp_messages: messages[..2].to_vec(),
+        ..BlockMessages::default()
+    };
+    let rets = executor.validate_block(&block).unwrap();
+    assert_eq!(rets.len(), 2);
+    assert!(rets.iter().all(|r| r.msg_receipt.exit_code.is_success()));
+
+    // A tampered message fails validation.
+    let mut tampered = messages[2].clone();
+    tampered.message.value = TokenAmount::from_atto(2);
+    let block = BlockMessages {
+        secp_messages: vec![tampered],
+        ..BlockMessages::default()
+    };
+    assert!(executor.validate_block(&block).is_err());
+}
@@ -13,9 +13,13 @@ use lazy_static::lazy_static;
 
 lazy_static! {
     static ref BUNDLES: BTreeMap<NetworkVersion, &'static [u8]> =
-        [(NetworkVersion::V21, actors_v12::BUNDLE_CAR),]
-            .into_iter()
-            .collect();
+        [
+            (NetworkVersion::V21, actors_v12::BUNDLE_CAR),
+            // The next network version, for testing features gated on it.
+            (NetworkVersion::V22, actors_v12::BUNDLE_CAR),
+        ]
+        .into_iter()
+        .collect();
 }
 
 #[allow(dead_code)]
@@ -6,7 +6,7 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use fvm::executor::{ApplyKind, ApplyRet, Executor};
-use fvm::gas::Gas;
+use fvm::gas::{Gas, GasCharge};
 use fvm::trace::ExecutionEvent;
 use fvm_integration_tests::bundle;
 use fvm_integration_tests::dummy::DummyExterns;
@@ -286,6 +286,30 @@ pub fn collect_obs(ret: &ApplyRet, name: &str, label: &str, size: usize) -> Vec<
         .collect()
 }
 
+/// Time `iterations` calls to a native (host) function, outside of any actor, recording one
+/// observation per call against the given charge.
+pub fn collect_native_obs<T>(
+    charge: &GasCharge,
+    label: &str,
+    size: usize,
+    iterations: usize,
+    mut f: impl FnMut() -> T,
+) -> Vec<Obs> {
+    (0..iterations)
+        .map(|_| {
+            let start = minstant::Instant::now();
+            std::hint::black_box(f());
+            Obs {
+                charge: charge.name.to_string(),
+                label: label.to_owned(),
+                elapsed_nanos: start.elapsed().as_nanos(),
+                variables: vec![size],
+                compute_gas: charge.compute_gas.as_milligas(),
+            }
+        })
+        .collect()
+}
+
 /// Drop a certain fraction of the observations with the highest time as outliers.
 pub fn eliminate_outliers(mut obs: Vec<Obs>, drop: f32, eliminate: Eliminate) -> Vec<Obs> {
     obs.sort_by_key(|obs| obs.elapsed_nanos);
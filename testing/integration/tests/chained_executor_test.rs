@@ -0,0 +1,88 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::executor::{ChainedExecutor, TipsetMessage};
+use fvm::machine::Machine;
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_integration_tests::tester::INITIAL_ACCOUNT_BALANCE;
+use fvm_ipld_amt::Amtv0;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::message::Message;
+use fvm_shared::receipt::Receipt;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+
+#[test]
+fn chained_executor() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_, a), (_, b)] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let mut executor = ChainedExecutor::new(tester.executor.take().unwrap());
+
+    let send = |from: Address, to: Address, sequence: u64, value: TokenAmount| Message {
+        from,
+        to,
+        sequence,
+        value,
+        gas_limit: 10_000_000,
+        ..Message::default()
+    };
+    let one = TokenAmount::from_atto(1);
+    let first = send(a, b, 0, one.clone());
+    let ret = executor
+        .apply_tipset(vec![
+            TipsetMessage::explicit(first.clone(), 100),
+            // Included again by another block.
+            TipsetMessage::explicit(first, 100),
+            // Skips a nonce.
+            TipsetMessage::explicit(send(a, b, 2, one.clone()), 100),
+            TipsetMessage::explicit(send(a, b, 1, one.clone()), 100),
+            // More than b can afford after its first message.
+            TipsetMessage::explicit(send(b, a, 0, INITIAL_ACCOUNT_BALANCE.clone()), 100),
+            TipsetMessage::explicit(send(b, a, 1, INITIAL_ACCOUNT_BALANCE.clone()), 100),
+            // Implicit messages ignore nonces, and have no receipts.
+            TipsetMessage::implicit(send(Address::new_id(0), b, 0, TokenAmount::from_atto(0))),
+        ])
+        .unwrap();
+
+    assert_eq!(ret.skipped, [1, 2, 5]);
+    assert_eq!(
+        ret.applied.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+        [0, 3, 4, 6]
+    );
+    assert!(ret
+        .applied
+        .iter()
+        .all(|(_, ret)| ret.msg_receipt.exit_code.is_success()));
+
+    // The state and receipts are persisted.
+    let expected: Vec<Receipt> = ret.applied[..3]
+        .iter()
+        .map(|(_, ret)| ret.msg_receipt.clone())
+        .collect();
+    let store = executor
+        .into_inner()
+        .into_machine()
+        .unwrap()
+        .into_store()
+        .into_inner();
+    let receipts = Amtv0::<Receipt, _>::load(&ret.receipts_root, &store).unwrap();
+    let mut actual = Vec::new();
+    receipts
+        .for_each(|_, receipt| {
+            actual.push(receipt.clone());
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(actual, expected);
+    assert!(fvm_ipld_blockstore::Blockstore::has(&store, &ret.state_root).unwrap());
+}
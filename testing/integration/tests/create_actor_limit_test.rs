@@ -0,0 +1,60 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::call_manager::{CallManager, DefaultCallManager};
+use fvm::engine::EnginePool;
+use fvm::kernel::ExecutionError;
+use fvm::machine::Machine;
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ErrorNumber;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+
+#[test]
+fn create_actor_limit() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender)] = tester.create_accounts().unwrap();
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |nc| {
+                nc.set_max_actors_created(2);
+            },
+            |_| (),
+        )
+        .unwrap();
+
+    let machine = tester.executor.take().unwrap().into_machine().unwrap();
+    let code = *machine.builtin_actors().get_account_code();
+    let engine = EnginePool::new_default((&machine.context().network).into())
+        .unwrap()
+        .acquire();
+    let mut cm = DefaultCallManager::new(
+        machine,
+        engine,
+        1_000_000_000,
+        sender_id,
+        sender,
+        None,
+        sender,
+        0,
+        TokenAmount::default(),
+    );
+
+    cm.create_actor(code, 10000, None).unwrap();
+    cm.create_actor(code, 10001, None).unwrap();
+    assert!(matches!(
+        cm.create_actor(code, 10002, None),
+        Err(ExecutionError::Syscall(e)) if e.1 == ErrorNumber::LimitExceeded
+    ));
+    assert!(cm.get_actor(10002).unwrap().is_none());
+}
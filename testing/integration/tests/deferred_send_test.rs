@@ -0,0 +1,137 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::call_manager::{
+    CallManager, DefaultCallManager, DeferredSend, InvocationResult, NO_DATA_BLOCK_ID,
+};
+use fvm::engine::EnginePool;
+use fvm::gas::Gas;
+use fvm::kernel::default::DefaultKernel;
+use fvm::kernel::{BlockRegistry, ExecutionError};
+use fvm::machine::Machine;
+use fvm::Kernel;
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::{ErrorNumber, ExitCode};
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+use fvm_shared::METHOD_SEND;
+
+#[test]
+fn deferred_sends() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender), (receiver_id, receiver)] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    let machine = tester.executor.take().unwrap().into_machine().unwrap();
+    let engine = EnginePool::new_default((&machine.context().network).into())
+        .unwrap()
+        .acquire();
+    let call_manager = DefaultCallManager::new(
+        machine,
+        engine,
+        1_000_000_000,
+        sender_id,
+        sender,
+        Some(receiver_id),
+        receiver,
+        0,
+        TokenAmount::default(),
+    );
+    let mut kernel = DefaultKernel::new(
+        call_manager,
+        BlockRegistry::default(),
+        sender_id,
+        sender_id,
+        0,
+        TokenAmount::default(),
+        false,
+    );
+
+    let gas_limit = Gas::new(10_000_000);
+    let value = TokenAmount::from_atto(100);
+    // Succeeds.
+    kernel
+        .defer_send(&receiver, METHOD_SEND, NO_DATA_BLOCK_ID, &value, gas_limit)
+        .unwrap();
+    // Fails (insufficient funds), without affecting the other sends.
+    kernel
+        .defer_send(
+            &receiver,
+            METHOD_SEND,
+            NO_DATA_BLOCK_ID,
+            &TokenAmount::from_whole(1_000_000),
+            gas_limit,
+        )
+        .unwrap();
+    // Bad parameters are rejected immediately.
+    assert!(matches!(
+        kernel.defer_send(&receiver, METHOD_SEND, 42, &value, gas_limit),
+        Err(ExecutionError::Syscall(e)) if e.1 == ErrorNumber::InvalidHandle
+    ));
+
+    let (mut call_manager, _) = kernel.into_inner();
+
+    // Sends deferred within a reverted transaction are discarded.
+    call_manager
+        .with_transaction(|cm| {
+            cm.defer_send(DeferredSend {
+                from: sender_id,
+                to: receiver,
+                method: METHOD_SEND,
+                params: None,
+                value: value.clone(),
+                gas_limit,
+            })?;
+            Ok(InvocationResult {
+                exit_code: ExitCode::USR_ASSERTION_FAILED,
+                value: None,
+            })
+        })
+        .unwrap();
+
+    let balance_before = call_manager
+        .get_actor(receiver_id)
+        .unwrap()
+        .unwrap()
+        .balance;
+    call_manager
+        .run_deferred_sends::<DefaultKernel<_>>()
+        .unwrap();
+    let balance_after = call_manager
+        .get_actor(receiver_id)
+        .unwrap()
+        .unwrap()
+        .balance;
+    assert_eq!(balance_after - balance_before, value);
+
+    // Read-only callers can't defer sends.
+    let mut kernel = DefaultKernel::new(
+        call_manager,
+        BlockRegistry::default(),
+        sender_id,
+        sender_id,
+        0,
+        TokenAmount::default(),
+        true,
+    );
+    assert!(matches!(
+        kernel.defer_send(
+            &Address::new_id(receiver_id),
+            METHOD_SEND,
+            NO_DATA_BLOCK_ID,
+            &value,
+            gas_limit
+        ),
+        Err(ExecutionError::Syscall(e)) if e.1 == ErrorNumber::ReadOnly
+    ));
+}
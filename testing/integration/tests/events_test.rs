@@ -2,13 +2,18 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 mod bundles;
 use bundles::*;
-use fvm::executor::{ApplyKind, Executor};
+use std::sync::{Arc, Mutex};
+
+use cid::multihash::{Code, MultihashDigest};
+use cid::Cid;
+use fvm::executor::{ApplyKind, EventSink, ExecutionOptions, Executor};
 use fvm::machine::Machine;
 use fvm_integration_tests::dummy::DummyExterns;
 use fvm_integration_tests::tester::IntegrationExecutor;
 use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
-use fvm_ipld_encoding::{to_vec, IPLD_RAW};
+use fvm_ipld_encoding::{to_vec, DAG_CBOR, IPLD_RAW};
 use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
 use fvm_shared::event::{Entry, Flags, StampedEvent};
@@ -83,6 +88,7 @@ fn events_test() {
 
     // Check the events AMT.
     assert!(res.msg_receipt.events_root.is_some());
+    fvm_shared::verify::verify_events(&res.msg_receipt, &res.events).unwrap();
     // Check that we haven't inserted the events AMT in the blockstore.
     assert!(!executor
         .blockstore()
@@ -157,6 +163,100 @@ fn events_test() {
     assert_eq!(0, res.events.len());
 }
 
+#[test]
+fn event_sink() {
+    #[derive(Default)]
+    struct Collector(Mutex<Vec<(ChainEpoch, Cid, Vec<StampedEvent>)>>);
+
+    impl EventSink for Collector {
+        fn publish(&self, epoch: ChainEpoch, message: &Cid, events: &[StampedEvent]) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((epoch, *message, events.to_vec()));
+        }
+    }
+
+    let (mut executor, sender_address, actor_address) = setup();
+    let collector = Arc::new(Collector::default());
+    executor.set_options(ExecutionOptions::new().with_event_sink(collector.clone()));
+
+    // Emits two events.
+    let message = Message {
+        from: sender_address,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 2,
+        sequence: 0,
+        ..Message::default()
+    };
+    let res = executor
+        .execute_message(message.clone(), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+
+    // Emits no events, so nothing is published.
+    let res = executor
+        .execute_message(
+            Message {
+                method_num: 3,
+                sequence: 1,
+                ..message.clone()
+            },
+            ApplyKind::Explicit,
+            100,
+        )
+        .unwrap();
+    assert!(res.msg_receipt.events_root.is_none());
+
+    let published = collector.0.lock().unwrap();
+    assert_eq!(published.len(), 1);
+    let (epoch, message_cid, events) = &published[0];
+    assert_eq!(*epoch, executor.context().epoch);
+    assert_eq!(
+        *message_cid,
+        Cid::new_v1(
+            DAG_CBOR,
+            Code::Blake2b256.digest(&to_vec(&message).unwrap())
+        )
+    );
+    assert_eq!(events, &expected_events(&actor_address));
+}
+
+fn expected_events(actor_address: &Address) -> Vec<StampedEvent> {
+    let actor_id = actor_address.id().unwrap();
+    vec![
+        StampedEvent {
+            emitter: actor_id,
+            event: vec![Entry {
+                flags: Flags::all(),
+                key: "foo".to_owned(),
+                codec: IPLD_RAW,
+                value: "abc".into(),
+            }]
+            .into(),
+        },
+        StampedEvent {
+            emitter: actor_id,
+            event: vec![
+                Entry {
+                    flags: Flags::all(),
+                    key: "bar".to_owned(),
+                    codec: IPLD_RAW,
+                    value: "def".into(),
+                },
+                Entry {
+                    flags: Flags::FLAG_INDEXED_KEY | Flags::FLAG_INDEXED_VALUE,
+                    key: "👱".to_string(),
+                    codec: IPLD_RAW,
+                    value: "123456789 abcdefg 123456789".into(),
+                },
+            ]
+            .into(),
+        },
+    ]
+}
+
 fn setup() -> (
     IntegrationExecutor<MemoryBlockstore, DummyExterns>,
     Address,
@@ -0,0 +1,48 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::executor::Executor;
+use fvm::machine::Machine;
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_integration_tests::tester::INITIAL_ACCOUNT_BALANCE;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+
+#[test]
+fn execute_view() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender), (_, receiver)] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+    let root = executor.flush().unwrap();
+
+    // A wrong nonce, a fee cap the sender can't afford, and no gas limit.
+    let message = Message {
+        from: sender,
+        to: receiver,
+        sequence: 42,
+        value: TokenAmount::from_atto(1),
+        gas_fee_cap: INITIAL_ACCOUNT_BALANCE.clone(),
+        ..Message::default()
+    };
+    let res = executor.execute_view(message).unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+    assert!(res.msg_receipt.gas_used > 0);
+
+    // Nothing was persisted.
+    let sender_state = executor.state_tree().get_actor(sender_id).unwrap().unwrap();
+    assert_eq!(sender_state.sequence, 0);
+    assert_eq!(sender_state.balance, *INITIAL_ACCOUNT_BALANCE);
+    assert!(!executor.state_tree().in_transaction());
+    assert_eq!(executor.flush().unwrap(), root);
+}
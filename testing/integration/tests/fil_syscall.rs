@@ -160,8 +160,19 @@ fn malformed_syscall_parameter() {
     // Should have failed with an invalid proof type message.
     match res.failure_info.as_ref().unwrap() {
         ApplyFailure::MessageBacktrace(backtrace) => match backtrace.cause.as_ref().unwrap() {
-            Cause::Syscall { error, message, .. } => {
+            Cause::Syscall {
+                error,
+                message,
+                args,
+                ..
+            } => {
                 assert!(message.contains("invalid proof type"));
+                // The syscall's arguments are recorded: the proof type and the lengths passed by
+                // the actor (the offsets point at empty vectors, so they aren't checked).
+                assert_eq!(args.len(), 5);
+                assert_eq!(args[0], "100000");
+                assert_eq!(args[2], "100000");
+                assert_eq!(args[4], "100000");
 
                 match error {
                     ErrorNumber::IllegalArgument => {}
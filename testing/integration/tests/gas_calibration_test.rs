@@ -48,7 +48,7 @@ fn on_block() {
 
         for event in ret.exec_trace {
             if let ExecutionEvent::GasCharge(charge) = event {
-                if charge.name.starts_with("OnBlock") {
+                if charge.is_under("syscall/ipld") {
                     if let Some(t) = charge.elapsed.get() {
                         let ob = Obs {
                             charge: charge.name.to_string(),
@@ -62,9 +62,15 @@ fn on_block() {
                 }
             }
         }
-        // The first OnBlockRead is for reading the parameters. From OnBlockStat that's the only record.
-        iter_obs.get_mut("OnBlockRead").unwrap().remove(0);
-        iter_obs.get_mut("OnBlockStat").unwrap().remove(0);
+        // The first block read is for reading the parameters. For block stats that's the only record.
+        iter_obs
+            .get_mut("syscall/ipld/block_read")
+            .unwrap()
+            .remove(0);
+        iter_obs
+            .get_mut("syscall/ipld/block_stat")
+            .unwrap()
+            .remove(0);
 
         for (name, mut obs) in iter_obs {
             if !obs.is_empty() {
@@ -88,7 +94,7 @@ fn on_event_by_value_size() {
     use fvm_shared::event::Flags;
     use rand::{thread_rng, Rng};
 
-    const CHARGE: &str = "OnActorEvent";
+    const CHARGE: &str = "syscall/event/emit_event";
     const METHOD: Method = Method::OnEvent;
 
     let iterations = 500;
@@ -120,7 +126,7 @@ fn on_event_by_value_size() {
 
     let regression = run_linear_regression(&obs);
 
-    export("OnActorEventValue", &obs, &regression).unwrap();
+    export("syscall/event/emit_event/value_size", &obs, &regression).unwrap();
 }
 
 #[test]
@@ -129,7 +135,7 @@ fn on_event_by_entry_count() {
     use fvm_shared::event::Flags;
     use rand::{thread_rng, Rng};
 
-    const CHARGE: &str = "OnActorEvent";
+    const CHARGE: &str = "syscall/event/emit_event";
     const METHOD: Method = Method::OnEvent;
 
     let iterations = 500;
@@ -161,7 +167,7 @@ fn on_event_by_entry_count() {
 
     let regression = run_linear_regression(&obs);
 
-    export("OnActorEventEntries", &obs, &regression).unwrap();
+    export("syscall/event/emit_event/entries", &obs, &regression).unwrap();
 }
 
 #[test]
@@ -172,7 +178,7 @@ fn utf8_validation() {
     use rand::{distributions::Standard, thread_rng, Rng};
 
     let mut chars = thread_rng().sample_iter(Standard);
-    const CHARGE: &str = "OnUtf8Validate";
+    const CHARGE: &str = "syscall/event/utf8_validation";
 
     let iterations = 500;
     let price_list = price_list_by_network_version(NetworkVersion::V21);
@@ -232,7 +238,7 @@ fn on_hashing() {
     use fvm_shared::crypto::hash::SupportedHashes;
     use rand::{thread_rng, Rng};
 
-    const CHARGE_NAME: &str = "OnHashing";
+    const CHARGE_NAME: &str = "syscall/crypto/hash";
     const METHOD: Method = Method::OnHashing;
 
     let hashers = vec![
@@ -283,7 +289,7 @@ fn on_hashing() {
 fn on_recover_secp_public_key() {
     use rand::{thread_rng, Rng, RngCore};
 
-    const CHARGE_NAME: &str = "OnRecoverSecpPublicKey";
+    const CHARGE_NAME: &str = "syscall/crypto/recover_secp_public_key";
     const METHOD: Method = Method::OnRecoverSecpPublicKey;
 
     // Just doing it for uniformity.
@@ -325,8 +331,8 @@ fn on_recover_secp_public_key() {
 #[test]
 #[cfg(feature = "calibration")]
 fn on_send() {
-    const TRANSFER_CHARGE_NAME: &str = "OnValueTransfer";
-    const INVOKE_CHARGE_NAME: &str = "OnMethodInvocation";
+    const TRANSFER_CHARGE_NAME: &str = "call/value_transfer";
+    const INVOKE_CHARGE_NAME: &str = "call/invoke";
     const METHOD: Method = Method::OnSend;
 
     let iterations = 100;
@@ -381,7 +387,7 @@ fn on_verify_signature() {
     use fvm_shared::crypto::signature::SignatureType;
     use rand::{thread_rng, Rng, RngCore};
 
-    const CHARGE_NAME: &str = "OnVerifySignature";
+    const CHARGE_NAME: &str = "syscall/crypto/verify_signature";
     const METHOD: Method = Method::OnVerifySignature;
 
     let sig_types = vec![SignatureType::BLS, SignatureType::Secp256k1];
@@ -442,6 +448,149 @@ fn on_verify_signature() {
     export(CHARGE_NAME, &obs, &regression).unwrap();
 }
 
+// Hash random data natively (outside of the actor) with every supported hasher, to separate the
+// cost of hashing from the cost of the syscall.
+#[test]
+#[cfg(feature = "calibration")]
+fn native_hashing() {
+    use fvm::gas::price_list_by_network_version;
+    use fvm::kernel::SupportedHashes;
+    use fvm_shared::version::NetworkVersion;
+    use multihash::MultihashDigest;
+    use rand::{thread_rng, RngCore};
+
+    let hashers = [
+        SupportedHashes::Sha2_256,
+        SupportedHashes::Blake2b256,
+        SupportedHashes::Blake2b512,
+        SupportedHashes::Keccak256,
+        SupportedHashes::Ripemd160,
+    ];
+
+    let iterations = 100;
+    let price_list = price_list_by_network_version(NetworkVersion::V21);
+    let mut rng = thread_rng();
+    let mut obs = Vec::new();
+
+    for hasher in hashers {
+        let label = format!("{hasher:?}-native");
+        for size in common_sizes() {
+            let mut data = vec![0u8; size];
+            rng.fill_bytes(&mut data);
+
+            let charge = price_list.on_hashing(hasher, size);
+            let series = collect_native_obs(&charge, &label, size, iterations, || {
+                hasher.digest(std::hint::black_box(&data))
+            });
+            obs.extend(eliminate_outliers(series, 0.02, Eliminate::Top));
+        }
+    }
+
+    let regression = run_linear_regression(&obs);
+
+    export("syscall/crypto/hash/native", &obs, &regression).unwrap();
+}
+
+// Verify valid signatures natively (outside of the actor), to separate the cost of verification
+// from the cost of the syscall.
+#[test]
+#[cfg(feature = "calibration")]
+fn native_verify_signature() {
+    use bls_signatures::Serialize;
+    use fvm::gas::price_list_by_network_version;
+    use fvm_shared::address::Address;
+    use fvm_shared::crypto::signature::{Signature, SignatureType};
+    use fvm_shared::version::NetworkVersion;
+    use rand::{thread_rng, RngCore};
+
+    let iterations = 10;
+    let price_list = price_list_by_network_version(NetworkVersion::V21);
+    let mut rng = thread_rng();
+    let mut obs = Vec::new();
+
+    let secp_sk = libsecp256k1::SecretKey::random(&mut rng);
+    let secp_signer =
+        Address::new_secp256k1(&libsecp256k1::PublicKey::from_secret_key(&secp_sk).serialize())
+            .unwrap();
+    let bls_sk = bls_signatures::PrivateKey::generate(&mut rng);
+    let bls_signer = Address::new_bls(&bls_sk.public_key().as_bytes()).unwrap();
+
+    for sig_type in [SignatureType::BLS, SignatureType::Secp256k1] {
+        let label = format!("{sig_type:?}-native");
+        for size in common_sizes() {
+            let mut data = vec![0u8; size];
+            rng.fill_bytes(&mut data);
+
+            let (signer, signature) = match sig_type {
+                SignatureType::Secp256k1 => (
+                    secp_signer,
+                    Signature::new_secp256k1(secp_sign(&secp_sk, &data).to_vec()),
+                ),
+                SignatureType::BLS => (
+                    bls_signer,
+                    Signature::new_bls(bls_sk.sign(&data).as_bytes()),
+                ),
+            };
+
+            let charge = price_list.on_verify_signature(sig_type, size);
+            let series = collect_native_obs(&charge, &label, size, iterations, || {
+                signature
+                    .verify(std::hint::black_box(&data), &signer)
+                    .expect("signature must be valid")
+            });
+            obs.extend(eliminate_outliers(series, 0.02, Eliminate::Top));
+        }
+    }
+
+    let regression = run_linear_regression(&obs);
+
+    export("syscall/crypto/verify_signature/native", &obs, &regression).unwrap();
+}
+
+// Recover public keys natively (outside of the actor). As with the syscall, the input size only
+// affects the hashing done ahead of time, so we expect a flat cost.
+#[test]
+#[cfg(feature = "calibration")]
+fn native_recover_secp_public_key() {
+    use fvm::gas::price_list_by_network_version;
+    use fvm_shared::crypto::signature::ops::recover_secp_public_key;
+    use fvm_shared::version::NetworkVersion;
+    use rand::{thread_rng, RngCore};
+
+    let iterations = 10;
+    let price_list = price_list_by_network_version(NetworkVersion::V21);
+    let mut rng = thread_rng();
+    let mut obs = Vec::new();
+
+    let sk = libsecp256k1::SecretKey::random(&mut rng);
+    let charge = price_list.on_recover_secp_public_key();
+
+    for size in common_sizes() {
+        let mut data = vec![0u8; size];
+        rng.fill_bytes(&mut data);
+
+        let sig = secp_sign(&sk, &data);
+        let hash: [u8; 32] = blake2b_simd::Params::new()
+            .hash_length(32)
+            .to_state()
+            .update(&data)
+            .finalize()
+            .as_bytes()
+            .try_into()
+            .unwrap();
+
+        let series = collect_native_obs(&charge, "native", size, iterations, || {
+            recover_secp_public_key(std::hint::black_box(&hash), &sig)
+                .expect("signature must be valid")
+        });
+        obs.extend(series);
+    }
+
+    let regs = vec![least_squares("native".into(), &obs, 0)];
+
+    export("syscall/crypto/recover_secp_public_key/native", &obs, &regs).unwrap();
+}
+
 // Scan CBOR Fields with no links.
 #[test]
 #[cfg(feature = "calibration")]
@@ -479,7 +628,7 @@ fn on_scan_cbor_fields() {
 
         for event in ret.exec_trace {
             if let ExecutionEvent::GasCharge(charge) = event {
-                if charge.name.starts_with("OnScanIpldLinks") {
+                if charge.name == "syscall/ipld/scan_links" {
                     if let Some(t) = charge.elapsed.get() {
                         let ob = Obs {
                             charge: charge.name.into(),
@@ -489,7 +638,7 @@ fn on_scan_cbor_fields() {
                             compute_gas: charge.compute_gas.as_milligas(),
                         };
                         iter_obs
-                            .entry("OnScanCborFields".into())
+                            .entry("syscall/ipld/scan_links/fields".into())
                             .or_default()
                             .push(ob);
                     }
@@ -552,9 +701,9 @@ fn on_scan_cbor_links() {
         for event in ret.exec_trace {
             let ExecutionEvent::GasCharge(charge) = event else { continue };
             for (key, name) in [
-                ("OnScanIpldLinks", "OnScanIpldLinks"),
-                ("OnTrackLinks", "OnBlockOpen"),
-                ("OnCheckLinks", "OnBlockCreate"),
+                ("syscall/ipld/scan_links", "syscall/ipld/scan_links"),
+                ("syscall/ipld/track_links", "syscall/ipld/block_open"),
+                ("syscall/ipld/check_links", "syscall/ipld/block_create"),
             ] {
                 if charge.name != name {
                     continue;
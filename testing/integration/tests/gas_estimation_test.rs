@@ -0,0 +1,75 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::executor::{ApplyKind, ExecutionOptions, Executor};
+use fvm::machine::Machine;
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_integration_tests::tester::INITIAL_ACCOUNT_BALANCE;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+
+#[test]
+fn estimate_gas() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender), (_, receiver)] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let message = |sequence, gas_limit| Message {
+        from: sender,
+        to: receiver,
+        value: TokenAmount::from_atto(1),
+        sequence,
+        gas_limit,
+        ..Message::default()
+    };
+
+    // Without estimation, a zero gas limit fails.
+    let res = executor
+        .execute_message(message(0, 0), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::SYS_OUT_OF_GAS);
+    assert_eq!(res.gas_estimate, None);
+
+    // With estimation, the message's gas limit is ignored, and the sender needn't cover the fee
+    // cap for the block gas limit.
+    executor.set_options(ExecutionOptions::new().estimate_gas(1.25));
+    let res = executor
+        .execute_message(
+            Message {
+                gas_fee_cap: TokenAmount::from_atto(1),
+                ..message(0, 0)
+            },
+            ApplyKind::Explicit,
+            100,
+        )
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+    let gas_used = res.msg_receipt.gas_used;
+    let estimate = res.gas_estimate.expect("expected a gas estimate");
+    assert_eq!(estimate, (gas_used as f64 * 1.25).ceil() as u64);
+
+    // Nothing was persisted.
+    let sender_state = executor.state_tree().get_actor(sender_id).unwrap().unwrap();
+    assert_eq!(sender_state.sequence, 0);
+    assert_eq!(sender_state.balance, *INITIAL_ACCOUNT_BALANCE);
+
+    // The estimate is sufficient to execute the message.
+    executor.set_options(ExecutionOptions::new());
+    let res = executor
+        .execute_message(message(0, estimate), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+    assert_eq!(res.msg_receipt.gas_used, gas_used);
+}
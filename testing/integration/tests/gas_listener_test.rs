@@ -0,0 +1,82 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use std::sync::{Arc, Mutex};
+
+use bundles::*;
+use fvm::executor::{ApplyKind, Executor};
+use fvm::gas::{Gas, GasChargeListener};
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+
+#[derive(Default)]
+struct RecordingListener {
+    charges: Mutex<Vec<(String, Gas, Gas, Gas)>>,
+}
+
+impl GasChargeListener for RecordingListener {
+    fn on_charge(&self, name: &str, compute: Gas, other: Gas, remaining: Gas) {
+        self.charges
+            .lock()
+            .unwrap()
+            .push((name.to_owned(), compute, other, remaining));
+    }
+}
+
+#[test]
+fn gas_listener() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_, sender), (_, receiver)] = tester.create_accounts().unwrap();
+
+    let listener = Arc::new(RecordingListener::default());
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |_| (),
+            |mc| {
+                mc.set_gas_listener(listener.clone());
+            },
+        )
+        .unwrap();
+
+    let gas_limit = 10_000_000;
+    let message = Message {
+        from: sender,
+        to: receiver,
+        value: TokenAmount::from_atto(1),
+        gas_limit,
+        ..Message::default()
+    };
+    let res = tester
+        .executor
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+
+    let charges = listener.charges.lock().unwrap();
+    assert_eq!(charges[0].0, "message/inclusion");
+    assert!(charges
+        .iter()
+        .any(|(name, ..)| name == "call/value_transfer"));
+
+    // The listener observes every charge, so the charges add up to the gas used.
+    let total = charges
+        .iter()
+        .fold(Gas::default(), |total, (_, compute, other, _)| {
+            total + *compute + *other
+        });
+    assert_eq!(total.round_up(), res.msg_receipt.gas_used);
+    let (.., remaining) = charges.last().unwrap();
+    assert_eq!(*remaining, Gas::new(gas_limit) - total);
+}
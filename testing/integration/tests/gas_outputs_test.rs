@@ -0,0 +1,96 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use std::sync::Arc;
+
+use bundles::*;
+use fvm::executor::{ApplyKind, ApplyRet, Executor};
+use fvm::gas::{GasOutputs, GasOutputsStrategy};
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+
+/// Burns the miner tip along with the base fee.
+struct BurnTips;
+
+impl GasOutputsStrategy for BurnTips {
+    fn compute(
+        &self,
+        gas_used: u64,
+        refund_credit: u64,
+        gas_limit: u64,
+        base_fee: &TokenAmount,
+        fee_cap: &TokenAmount,
+        gas_premium: &TokenAmount,
+    ) -> GasOutputs {
+        let mut out = GasOutputs::compute(
+            gas_used,
+            refund_credit,
+            gas_limit,
+            base_fee,
+            fee_cap,
+            gas_premium,
+        );
+        out.base_fee_burn += std::mem::take(&mut out.miner_tip);
+        out
+    }
+}
+
+/// Sends a value transfer with a gas premium, with the given gas fee rules.
+fn send(strategy: Option<Arc<dyn GasOutputsStrategy>>) -> ApplyRet {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_, sender), (_, receiver)] = tester.create_accounts().unwrap();
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |_| (),
+            |mc| {
+                if let Some(strategy) = strategy {
+                    mc.set_gas_outputs_strategy(strategy);
+                }
+            },
+        )
+        .unwrap();
+
+    let message = Message {
+        from: sender,
+        to: receiver,
+        value: TokenAmount::from_atto(1),
+        gas_limit: 10_000_000,
+        gas_fee_cap: TokenAmount::from_atto(200),
+        gas_premium: TokenAmount::from_atto(10),
+        ..Message::default()
+    };
+    let res = tester
+        .executor
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+    res
+}
+
+#[test]
+fn gas_outputs_strategy() {
+    let baseline = send(None);
+    assert!(baseline.miner_tip.is_positive());
+
+    let res = send(Some(Arc::new(BurnTips)));
+    assert!(res.miner_tip.is_zero());
+    assert_eq!(
+        res.base_fee_burn,
+        &baseline.base_fee_burn + &baseline.miner_tip
+    );
+    assert_eq!(res.refund, baseline.refund);
+    assert_eq!(res.msg_receipt.gas_used, baseline.msg_receipt.gas_used);
+}
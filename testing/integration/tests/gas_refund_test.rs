@@ -0,0 +1,97 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::executor::{ApplyKind, ApplyRet, Executor};
+use fvm::gas::PriceList;
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+use fvm_test_actors::wasm_bin::SSELF_ACTOR_BINARY;
+
+/// Runs the sself actor (which self-destructs twice) with the given price list.
+fn self_destruct(price_list: Option<&'static PriceList>) -> ApplyRet {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_, sender)] = tester.create_accounts().unwrap();
+    let actor_address = Address::new_id(10000);
+    let state_cid = tester.set_state(&[(); 0]).unwrap();
+    tester
+        .set_actor_from_bin(
+            SSELF_ACTOR_BINARY,
+            state_cid,
+            actor_address,
+            TokenAmount::from_nano(1_000_000),
+        )
+        .unwrap();
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |_| (),
+            |mc| {
+                if let Some(price_list) = price_list {
+                    mc.set_price_list(price_list);
+                }
+            },
+        )
+        .unwrap();
+
+    let message = Message {
+        from: sender,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 1,
+        ..Message::default()
+    };
+    let res = tester
+        .executor
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+    res
+}
+
+fn price_list(json: &str) -> &'static PriceList {
+    PriceList::from_json(json).unwrap().intern()
+}
+
+#[test]
+fn self_destruct_refund() {
+    // No refunds by default.
+    let baseline = self_destruct(None);
+    assert_eq!(baseline.refund_credit, 0);
+    let gas_used = baseline.msg_receipt.gas_used;
+
+    // A small refund is credited once, even though the actor self-destructs twice.
+    let res = self_destruct(Some(price_list(
+        r#"{"version": 1, "base": 21, "prices": {"self_destruct_refund": 1000000}}"#,
+    )));
+    assert_eq!(res.refund_credit, 1000);
+    assert_eq!(res.msg_receipt.gas_used, gas_used - 1000);
+
+    // Large refunds are capped at a fifth of the gas used.
+    let res = self_destruct(Some(price_list(
+        r#"{"version": 1, "base": 21, "prices": {"self_destruct_refund": 1000000000000}}"#,
+    )));
+    assert!(res.refund_credit > 1000);
+    assert!(res.refund_credit <= gas_used / 5);
+    assert_eq!(res.msg_receipt.gas_used, gas_used - res.refund_credit);
+
+    // Refunds can be disabled entirely.
+    let res = self_destruct(Some(price_list(
+        r#"{"version": 1, "base": 21, "prices": {"self_destruct_refund": 1000000, "max_refund_quotient": 0}}"#,
+    )));
+    assert_eq!(res.refund_credit, 0);
+    assert_eq!(res.msg_receipt.gas_used, gas_used);
+}
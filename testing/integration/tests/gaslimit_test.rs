@@ -157,3 +157,182 @@ fn gaslimit_test() {
             .balance
     );
 }
+
+#[test]
+fn reentrancy_policy() {
+    use fvm::call_manager::ReentrancyPolicy;
+    use fvm::trace::ExecutionEvent;
+
+    #[derive(Serialize_tuple, Deserialize_tuple, PartialEq, Eq, Clone, Debug)]
+    struct Params {
+        dest: Address,
+        inner_gas_limit: u64,
+        exhaust: bool,
+        expect_err: bool,
+    }
+
+    for policy in [ReentrancyPolicy::Trace, ReentrancyPolicy::Reject] {
+        let mut tester = new_tester(
+            NetworkVersion::V21,
+            StateTreeVersion::V5,
+            MemoryBlockstore::default(),
+        )
+        .unwrap();
+
+        let [(_sender_id, sender_address), (_dest_id, dest_address)] =
+            tester.create_accounts().unwrap();
+
+        let actor_id = 10000;
+        let state_cid = tester.set_state(&[(); 0]).unwrap();
+        tester
+            .set_actor_from_bin(
+                GASLIMIT_ACTOR_BINARY,
+                state_cid,
+                Address::new_id(actor_id),
+                TokenAmount::zero(),
+            )
+            .unwrap();
+
+        tester
+            .instantiate_machine_with_config(
+                DummyExterns,
+                |nc| {
+                    nc.set_reentrancy_policy(policy);
+                },
+                |mc| {
+                    mc.enable_tracing();
+                },
+            )
+            .unwrap();
+
+        // The actor calls itself, which is a reentrant call.
+        let params = Params {
+            dest: dest_address,
+            inner_gas_limit: 0,
+            exhaust: false,
+            expect_err: false,
+        };
+        let message = Message {
+            from: sender_address,
+            to: Address::new_id(actor_id),
+            gas_limit: 1000000000,
+            method_num: 2,
+            value: TokenAmount::from_atto(100),
+            params: to_vec(&params).unwrap().into(),
+            ..Message::default()
+        };
+        let res = tester
+            .executor
+            .unwrap()
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+
+        let flagged = res
+            .exec_trace
+            .iter()
+            .any(|evt| matches!(evt, ExecutionEvent::Reentrancy(id) if *id == actor_id));
+        match policy {
+            ReentrancyPolicy::Trace => {
+                assert_eq!(ExitCode::OK, res.msg_receipt.exit_code);
+                assert!(flagged);
+            }
+            // The actor doesn't expect its self-call to fail.
+            _ => {
+                assert!(!res.msg_receipt.exit_code.is_success());
+                assert!(!flagged);
+            }
+        }
+    }
+}
+
+#[test]
+fn call_gas_attribution() {
+    use fvm::gas::Gas;
+    use fvm::trace::ExecutionEvent;
+
+    #[derive(Serialize_tuple, Deserialize_tuple, PartialEq, Eq, Clone, Debug)]
+    struct Params {
+        dest: Address,
+        inner_gas_limit: u64,
+        exhaust: bool,
+        expect_err: bool,
+    }
+
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_sender_id, sender_address), (_dest_id, dest_address)] =
+        tester.create_accounts().unwrap();
+
+    let actor_address = Address::new_id(10000);
+    let state_cid = tester.set_state(&[(); 0]).unwrap();
+    tester
+        .set_actor_from_bin(
+            GASLIMIT_ACTOR_BINARY,
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |_| (),
+            |mc| {
+                mc.enable_tracing();
+            },
+        )
+        .unwrap();
+
+    // The actor sends to the destination, calls itself, and sends to the destination again.
+    let params = Params {
+        dest: dest_address,
+        inner_gas_limit: 0,
+        exhaust: false,
+        expect_err: false,
+    };
+    let message = Message {
+        from: sender_address,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 2,
+        value: TokenAmount::from_atto(100),
+        params: to_vec(&params).unwrap().into(),
+        ..Message::default()
+    };
+    let res = tester
+        .executor
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(ExitCode::OK, res.msg_receipt.exit_code);
+
+    let calls: Vec<_> = res
+        .exec_trace
+        .iter()
+        .filter_map(|evt| match evt {
+            ExecutionEvent::CallGas {
+                gas_at_entry,
+                gas_at_exit,
+                exclusive_gas_used,
+            } => Some((*gas_at_entry, *gas_at_exit, *exclusive_gas_used)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(calls.len(), 4);
+
+    // The top-level call finishes last, and its gas is fully attributed to the calls.
+    let (entry, exit, _) = *calls.last().unwrap();
+    let exclusive_total = calls
+        .iter()
+        .fold(Gas::zero(), |total, (_, _, exclusive)| total + *exclusive);
+    assert_eq!(exclusive_total, exit - entry);
+    assert!(calls
+        .iter()
+        .all(|(entry, exit, exclusive)| *exclusive <= *exit - *entry));
+}
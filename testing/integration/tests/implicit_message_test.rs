@@ -0,0 +1,83 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::executor::{ApplyFailure, ApplyKind, Executor, PreValidationFailure};
+use fvm::machine::Machine;
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_integration_tests::tester::INITIAL_ACCOUNT_BALANCE;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+use num_traits::Zero;
+
+#[test]
+fn apply_implicit_message() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender), (receiver_id, receiver)] = tester.create_accounts().unwrap();
+
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    // Cron messages are metered against the block gas limit, whatever their own gas limit.
+    let message = Message {
+        from: sender,
+        to: receiver,
+        sequence: 10,
+        value: TokenAmount::from_atto(1),
+        gas_limit: 0,
+        ..Message::default()
+    };
+    let before = executor.state_tree().get_actor(sender_id).unwrap().unwrap();
+
+    // Implicit messages are still validated.
+    assert!(executor
+        .execute_message(message.clone(), ApplyKind::Implicit, 0)
+        .is_err());
+
+    let res = executor.apply_implicit_message(message).unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+    assert!(res.msg_receipt.gas_used > 0);
+    assert_eq!(res.base_fee_burn, TokenAmount::zero());
+    assert_eq!(res.miner_tip, TokenAmount::zero());
+
+    // The sender only pays the value, and its nonce doesn't change.
+    let after = executor.state_tree().get_actor(sender_id).unwrap().unwrap();
+    assert_eq!(after.sequence, before.sequence);
+    assert_eq!(after.balance, before.balance - TokenAmount::from_atto(1));
+    let receiver = executor
+        .state_tree()
+        .get_actor(receiver_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        receiver.balance,
+        INITIAL_ACCOUNT_BALANCE.clone() + TokenAmount::from_atto(1)
+    );
+
+    // Like implicit messages, cron messages from actors that don't exist fail with a receipt.
+    let res = executor
+        .apply_implicit_message(Message {
+            from: Address::new_id(1000),
+            to: receiver,
+            ..Message::default()
+        })
+        .unwrap();
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::SYS_SENDER_INVALID);
+    assert!(matches!(
+        res.failure_info,
+        Some(ApplyFailure::PreValidation(
+            PreValidationFailure::SenderNotFound
+        ))
+    ));
+}
@@ -0,0 +1,61 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::executor::{ApplyKind, Executor};
+use fvm::machine::Machine;
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_integration_tests::tester::INITIAL_ACCOUNT_BALANCE;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+
+#[test]
+fn machine_snapshot() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender), (_, receiver)] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let message = |sequence| Message {
+        from: sender,
+        to: receiver,
+        sequence,
+        value: TokenAmount::from_atto(1),
+        gas_limit: 10_000_000,
+        ..Message::default()
+    };
+
+    let snapshot = executor.snapshot().unwrap();
+    let buffered = executor.buffered_blocks().len();
+
+    // Speculatively apply two messages, twice.
+    for _ in 0..2 {
+        for sequence in 0..2 {
+            let res = executor
+                .execute_message(message(sequence), ApplyKind::Explicit, 100)
+                .unwrap();
+            assert!(res.msg_receipt.exit_code.is_success());
+        }
+        assert_ne!(executor.flush().unwrap(), snapshot.state_root());
+
+        executor.restore(&snapshot).unwrap();
+        let sender_state = executor.state_tree().get_actor(sender_id).unwrap().unwrap();
+        assert_eq!(sender_state.sequence, 0);
+        assert_eq!(sender_state.balance, *INITIAL_ACCOUNT_BALANCE);
+    }
+
+    assert_eq!(
+        executor.snapshot().unwrap().state_root(),
+        snapshot.state_root()
+    );
+    assert_eq!(executor.buffered_blocks().len(), buffered);
+}
@@ -86,6 +86,694 @@ fn hello_world() {
     assert_eq!(res.msg_receipt.exit_code.value(), 16)
 }
 
+#[test]
+fn invalid_machine_context() {
+    let mut tester = new_tester::<_, DummyExterns>(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    // The machine validates its context, even if it wasn't built with a builder.
+    let err = tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |_| {},
+            |mc| {
+                mc.set_circulating_supply(TokenAmount::from_whole(3_000_000_000));
+            },
+        )
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("invalid machine context"),
+        "{err:#}"
+    );
+}
+
+#[test]
+fn reload_actor() {
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            HELLO_WORLD_ACTOR_BINARY,
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    assert_eq!(
+        send_to(&mut tester, sender[0].1, actor_address, 0).value(),
+        16
+    );
+
+    // Swap in new code between messages.
+    let wasm_bin = wat::parse_str(
+        r#"(module
+             (memory (export "memory") 1)
+             (func (export "invoke") (param $x i32) (result i32)
+               (unreachable)))"#,
+    )
+    .unwrap();
+    let code_cid = tester
+        .reload_actor_from_bin(&wasm_bin, actor_address)
+        .unwrap();
+    assert_eq!(
+        send_to(&mut tester, sender[0].1, actor_address, 1),
+        ExitCode::SYS_ILLEGAL_INSTRUCTION
+    );
+
+    // The actor's state is preserved.
+    let actor = tester
+        .executor
+        .as_ref()
+        .unwrap()
+        .state_tree()
+        .get_actor(10000)
+        .unwrap()
+        .unwrap();
+    assert_eq!(actor.code, code_cid);
+    assert_eq!(actor.state, state_cid);
+}
+
+#[test]
+fn module_stats() {
+    use fvm::engine::EnginePool;
+
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    let code_cid = tester
+        .set_actor_from_bin(
+            HELLO_WORLD_ACTOR_BINARY,
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    // Execute on a fresh engine so that we observe the compilation.
+    let machine = tester.executor.take().unwrap().into_machine().unwrap();
+    let engine_pool = EnginePool::new_default((&machine.context().network).into()).unwrap();
+    let mut executor =
+        IntegrationExecutor::<MemoryBlockstore, DummyExterns>::new(engine_pool.clone(), machine)
+            .unwrap();
+
+    for sequence in 0..2 {
+        let message = Message {
+            from: sender[0].1,
+            to: actor_address,
+            gas_limit: 1000000000,
+            method_num: 1,
+            sequence,
+            ..Message::default()
+        };
+        let res = executor
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert_eq!(res.msg_receipt.exit_code.value(), 16);
+    }
+
+    let stats = engine_pool.acquire().module_stats();
+    let stats = stats.get(&code_cid).unwrap();
+    assert_eq!(stats.code_size, HELLO_WORLD_ACTOR_BINARY.len());
+    assert_eq!(stats.instantiations, 2);
+}
+
+fn send_to(
+    tester: &mut Tester<MemoryBlockstore, DummyExterns>,
+    from: Address,
+    to: Address,
+    sequence: u64,
+) -> ExitCode {
+    let message = Message {
+        from,
+        to,
+        gas_limit: 1000000000,
+        method_num: 1,
+        sequence,
+        ..Message::default()
+    };
+    tester
+        .executor
+        .as_mut()
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap()
+        .msg_receipt
+        .exit_code
+}
+
+#[test]
+fn syscall_allowlist() {
+    use fvm::syscalls::{SyscallAllowlist, SyscallSet};
+
+    for (permitted, exit_code) in [
+        (SyscallSet::from_iter([("vm", "exit")]), ExitCode::new(16)),
+        (SyscallSet::new(), ExitCode::SYS_ILLEGAL_INSTRUCTION),
+    ] {
+        let mut tester = new_tester(
+            NV_FOR_TEST,
+            StateTreeVersion::V5,
+            MemoryBlockstore::default(),
+        )
+        .unwrap();
+
+        let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+        let state_cid = tester.set_state(&State::default()).unwrap();
+        let actor_address = Address::new_id(10000);
+        tester
+            .set_actor_from_bin(
+                HELLO_WORLD_ACTOR_BINARY,
+                state_cid,
+                actor_address,
+                TokenAmount::zero(),
+            )
+            .unwrap();
+
+        // Restrict the (non-builtin) hello world actor.
+        let mut allowlist = SyscallAllowlist::default();
+        allowlist.restrict_non_builtin(permitted);
+        tester
+            .instantiate_machine_with_config(
+                DummyExterns,
+                |nc| {
+                    nc.restrict_syscalls(allowlist);
+                },
+                |_| {},
+            )
+            .unwrap();
+
+        let message = Message {
+            from: sender[0].1,
+            to: actor_address,
+            gas_limit: 1000000000,
+            method_num: 1,
+            ..Message::default()
+        };
+
+        let res = tester
+            .executor
+            .unwrap()
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+
+        assert_eq!(res.msg_receipt.exit_code, exit_code);
+    }
+}
+
+#[test]
+fn disabled_syscalls() {
+    use fvm::syscalls::SyscallSet;
+
+    for (disabled, exit_code) in [
+        (
+            SyscallSet::from_iter([("actor", "create_actor")]),
+            ExitCode::new(16),
+        ),
+        (
+            SyscallSet::from_iter([("vm", "exit")]),
+            ExitCode::SYS_ILLEGAL_INSTRUCTION,
+        ),
+    ] {
+        let mut tester = new_tester(
+            NV_FOR_TEST,
+            StateTreeVersion::V5,
+            MemoryBlockstore::default(),
+        )
+        .unwrap();
+
+        let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+        let state_cid = tester.set_state(&State::default()).unwrap();
+        let actor_address = Address::new_id(10000);
+        tester
+            .set_actor_from_bin(
+                HELLO_WORLD_ACTOR_BINARY,
+                state_cid,
+                actor_address,
+                TokenAmount::zero(),
+            )
+            .unwrap();
+
+        tester
+            .instantiate_machine_with_config(
+                DummyExterns,
+                |_| {},
+                |mc| {
+                    mc.disable_syscalls(disabled);
+                },
+            )
+            .unwrap();
+
+        let message = Message {
+            from: sender[0].1,
+            to: actor_address,
+            gas_limit: 1000000000,
+            method_num: 1,
+            ..Message::default()
+        };
+
+        let res = tester
+            .executor
+            .unwrap()
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+
+        assert_eq!(res.msg_receipt.exit_code, exit_code);
+    }
+}
+
+#[test]
+fn execution_metrics() {
+    use fvm::executor::ExecutionOptions;
+
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            HELLO_WORLD_ACTOR_BINARY,
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let message = |sequence| Message {
+        from: sender[0].1,
+        to: actor_address,
+        sequence,
+        gas_limit: 1000000000,
+        method_num: 1,
+        ..Message::default()
+    };
+
+    // Metrics are only reported when requested.
+    let res = executor
+        .execute_message(message(0), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(res.metrics, None);
+    // Nor are module instantiations and syscalls counted.
+    let machine_metrics = executor.metrics();
+    assert_eq!(
+        machine_metrics.module_cache.hits + machine_metrics.module_cache.misses,
+        0
+    );
+    assert!(machine_metrics.syscalls.is_empty());
+
+    executor.set_options(ExecutionOptions::new().with_metrics());
+    let res = executor
+        .execute_message(message(1), ApplyKind::Explicit, 100)
+        .unwrap();
+    let metrics = res.metrics.expect("expected metrics");
+    assert_eq!(metrics.module_cache.hits, 1);
+    assert_eq!(metrics.module_cache.misses, 0);
+    assert!(metrics.actor_cache.hits > 0);
+    assert!(metrics.blockstore_gets > 0);
+    assert!(metrics.syscalls.get(&("vm", "exit")).is_some());
+
+    // The message's metrics are the difference between the machine's metrics.
+    assert_eq!(executor.metrics().since(&machine_metrics), metrics);
+}
+
+#[test]
+fn apply_hooks() {
+    use std::sync::{Arc, Mutex};
+
+    use fvm::executor::{ApplyHook, ApplyRet, ExecutionOptions};
+    use fvm_shared::clock::ChainEpoch;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(&'static str, u64, Option<ExitCode>)>>);
+
+    impl ApplyHook for Recorder {
+        fn pre_apply(&self, _: ChainEpoch, msg: &Message, _: ApplyKind) {
+            self.0.lock().unwrap().push(("pre", msg.sequence, None));
+        }
+
+        fn post_apply(&self, _: ChainEpoch, msg: &Message, _: ApplyKind, ret: &ApplyRet) {
+            let exit_code = ret.msg_receipt.exit_code;
+            self.0
+                .lock()
+                .unwrap()
+                .push(("post", msg.sequence, Some(exit_code)));
+        }
+    }
+
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            HELLO_WORLD_ACTOR_BINARY,
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let recorder = Arc::new(Recorder::default());
+    executor.set_options(ExecutionOptions::new().with_hook(recorder.clone()));
+
+    let message = |sequence| Message {
+        from: sender[0].1,
+        to: actor_address,
+        sequence,
+        gas_limit: 1000000000,
+        method_num: 1,
+        ..Message::default()
+    };
+    executor
+        .execute_message(message(0), ApplyKind::Explicit, 100)
+        .unwrap();
+    // Wrong nonce.
+    executor
+        .execute_message(message(5), ApplyKind::Explicit, 100)
+        .unwrap();
+    // Views don't call the hooks.
+    executor.execute_view(message(1)).unwrap();
+
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        [
+            ("pre", 0, None),
+            ("post", 0, Some(ExitCode::OK)),
+            ("pre", 5, None),
+            ("post", 5, Some(ExitCode::SYS_SENDER_STATE_INVALID)),
+        ]
+    );
+}
+
+#[test]
+fn instruction_budget() {
+    use fvm::trace::ExecutionEvent;
+
+    for (budget, exit_code) in [(1 << 40, ExitCode::new(16)), (1, ExitCode::SYS_OUT_OF_GAS)] {
+        let mut tester = new_tester(
+            NV_FOR_TEST,
+            StateTreeVersion::V5,
+            MemoryBlockstore::default(),
+        )
+        .unwrap();
+
+        let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+        let state_cid = tester.set_state(&State::default()).unwrap();
+        let actor_address = Address::new_id(10000);
+        tester
+            .set_actor_from_bin(
+                HELLO_WORLD_ACTOR_BINARY,
+                state_cid,
+                actor_address,
+                TokenAmount::zero(),
+            )
+            .unwrap();
+
+        tester
+            .instantiate_machine_with_config(
+                DummyExterns,
+                |nc| {
+                    nc.set_instruction_budget(budget);
+                },
+                |mc| {
+                    mc.enable_tracing();
+                },
+            )
+            .unwrap();
+
+        let message = Message {
+            from: sender[0].1,
+            to: actor_address,
+            gas_limit: 1000000000,
+            method_num: 1,
+            ..Message::default()
+        };
+
+        let res = tester
+            .executor
+            .unwrap()
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+
+        assert_eq!(res.msg_receipt.exit_code, exit_code);
+
+        // Both the gas and the instructions consumed by the call are reported in the trace.
+        let (gas_used, instructions) = res
+            .exec_trace
+            .iter()
+            .find_map(|evt| match evt {
+                ExecutionEvent::CallUsage {
+                    gas_used,
+                    instructions,
+                } => Some((*gas_used, *instructions)),
+                _ => None,
+            })
+            .expect("no call usage in trace");
+        assert!(!gas_used.is_zero());
+        assert!(instructions > 0);
+        assert!(instructions <= budget);
+    }
+}
+
+#[test]
+fn memory_high_water_mark() {
+    use fvm::trace::ExecutionEvent;
+
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            HELLO_WORLD_ACTOR_BINARY,
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |_| (),
+            |mc| {
+                mc.enable_tracing();
+            },
+        )
+        .unwrap();
+
+    let message = Message {
+        from: sender[0].1,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 1,
+        ..Message::default()
+    };
+
+    let res = tester
+        .executor
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+
+    assert_eq!(res.msg_receipt.exit_code.value(), 16);
+
+    // The actor's peak memory is reported right when the invocation finishes.
+    let mut events = res
+        .exec_trace
+        .iter()
+        .filter(|evt| !matches!(evt, ExecutionEvent::GasCharge(_)))
+        .skip_while(|evt| !matches!(evt, ExecutionEvent::InvokeMemory { .. }));
+    match (events.next(), events.next()) {
+        (
+            Some(ExecutionEvent::InvokeMemory { peak_pages }),
+            Some(ExecutionEvent::CallGas { .. }),
+        ) => assert!(*peak_pages > 0),
+        events => panic!("unexpected events: {:?}", events),
+    }
+}
+
+#[test]
+fn syscall_timing() {
+    use fvm::trace::ExecutionEvent;
+
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            HELLO_WORLD_ACTOR_BINARY,
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |_| (),
+            |mc| {
+                mc.enable_tracing();
+            },
+        )
+        .unwrap();
+
+    let message = Message {
+        from: sender[0].1,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 1,
+        ..Message::default()
+    };
+
+    let res = tester
+        .executor
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+
+    // Every syscall made by the actor is timed, and follows its syscall overhead charge.
+    let mut overhead_charges = 0;
+    let mut syscalls = 0;
+    for evt in &res.exec_trace {
+        match evt {
+            ExecutionEvent::GasCharge(charge) if charge.name == "syscall/overhead" => {
+                overhead_charges += 1
+            }
+            ExecutionEvent::Syscall { module, name, .. } => {
+                assert!(!module.is_empty() && !name.is_empty());
+                assert!(syscalls < overhead_charges);
+                syscalls += 1;
+            }
+            _ => {}
+        }
+    }
+    assert!(syscalls > 0);
+    assert_eq!(syscalls, overhead_charges);
+}
+
+#[test]
+fn actor_gas_usage() {
+    use fvm::trace::ExecutionEvent;
+
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            HELLO_WORLD_ACTOR_BINARY,
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    let message = Message {
+        from: sender[0].1,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 1,
+        ..Message::default()
+    };
+
+    let res = tester
+        .executor
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+
+    // The only call is to the actor, so all the gas used by calls is attributed to it.
+    let actor_gas = res.actor_gas.expect("tracing is enabled");
+    let call_gas = res
+        .exec_trace
+        .iter()
+        .find_map(|evt| match evt {
+            ExecutionEvent::CallGas {
+                exclusive_gas_used, ..
+            } => Some(*exclusive_gas_used),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(
+        actor_gas.into_iter().collect::<Vec<_>>(),
+        [((10000, 1), call_gas)]
+    );
+    assert!(call_gas.round_up() <= res.msg_receipt.gas_used);
+}
+
 #[test]
 fn ipld() {
     // Instantiate tester
@@ -525,25 +1213,100 @@ fn native_stack_overflow() {
 
             eprintln!("STACKOVERFLOW RESULT = {:?}", res);
 
-            res.msg_receipt.exit_code.value()
+            res
         };
 
     let mut executor = ThreadedExecutor(tester.executor.unwrap());
 
     // on method 0 the test actor should run out of stack
     assert_eq!(
-        exec_test(&mut executor, 1),
+        exec_test(&mut executor, 1).msg_receipt.exit_code.value(),
         ExitCode::SYS_ILLEGAL_INSTRUCTION.value()
     );
 
     // on method 1 the test actor should run out of recursive call limit
+    let res = exec_test(&mut executor, 2);
     assert_eq!(
-        exec_test(&mut executor, 2),
+        res.msg_receipt.exit_code.value(),
         0xc0000000 + (ErrorNumber::LimitExceeded as u32)
     );
+    // The call depth limit is recorded in the backtrace.
+    let max_call_depth = executor.0.context().max_call_depth;
+    assert!(res
+        .failure_info
+        .unwrap()
+        .to_string()
+        .contains(&format!("call depth limit of {max_call_depth}")));
 
     // on method 2 the test actor should finish successfully
-    assert_eq!(exec_test(&mut executor, 3), 0x80000042);
+    assert_eq!(
+        exec_test(&mut executor, 3).msg_receipt.exit_code.value(),
+        0x80000042
+    );
+}
+
+#[test]
+fn threaded_executor_reuses_threads() {
+    /// Records the thread each message is executed on.
+    struct ThreadRecorder<E> {
+        executor: E,
+        threads: Vec<std::thread::ThreadId>,
+    }
+
+    impl<E: Executor> Executor for ThreadRecorder<E> {
+        type Kernel = E::Kernel;
+
+        fn execute_message(
+            &mut self,
+            msg: Message,
+            apply_kind: ApplyKind,
+            raw_length: usize,
+        ) -> anyhow::Result<fvm::executor::ApplyRet> {
+            let thread = std::thread::current();
+            assert!(thread
+                .name()
+                .unwrap_or_default()
+                .starts_with("fvm-executor"));
+            self.threads.push(thread.id());
+            self.executor.execute_message(msg, apply_kind, raw_length)
+        }
+
+        fn flush(&mut self) -> anyhow::Result<Cid> {
+            self.executor.flush()
+        }
+    }
+
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+    let [(_, sender), (_, receiver)] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    let mut executor = ThreadedExecutor(ThreadRecorder {
+        executor: tester.executor.unwrap(),
+        threads: Vec::new(),
+    });
+    for sequence in 0..32 {
+        let message = Message {
+            from: sender,
+            to: receiver,
+            gas_limit: 10_000_000,
+            sequence,
+            ..Message::default()
+        };
+        let res = executor
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert!(res.msg_receipt.exit_code.is_success());
+    }
+
+    // All messages ran off the caller's thread, on the pool's 8 threads.
+    let threads: HashSet<_> = executor.0.threads.iter().collect();
+    assert!(!threads.contains(&std::thread::current().id()));
+    assert!(threads.len() <= 8, "{} threads were used", threads.len());
 }
 
 fn test_exitcode(wat: &str, code: ExitCode) {
@@ -603,6 +1366,130 @@ fn out_of_gas() {
     )
 }
 
+#[test]
+fn execution_timeout() {
+    use std::time::Duration;
+
+    use fvm::executor::ApplyFailure;
+
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+    let wasm_bin = wat::parse_str(
+        r#"(module
+             (memory (export "memory") 1)
+             (func (export "invoke") (param $x i32) (result i32)
+               (loop (br 0))
+               (i32.const 1)))"#,
+    )
+    .unwrap();
+    let state_cid = tester.set_state(&State::default()).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(&wasm_bin, state_cid, actor_address, TokenAmount::zero())
+        .unwrap();
+
+    let limit = Duration::from_millis(100);
+    tester
+        .instantiate_machine_with_engine_config(
+            DummyExterns,
+            |_| {},
+            |_| {},
+            |ec| ec.execution_timeout = Some(limit),
+        )
+        .unwrap();
+
+    // The loop would run for much longer than the limit before running out of gas.
+    let message = Message {
+        from: sender[0].1,
+        to: actor_address,
+        gas_limit: fvm_shared::BLOCK_GAS_LIMIT,
+        method_num: 1,
+        ..Message::default()
+    };
+    let res = tester
+        .executor
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+
+    assert_eq!(res.msg_receipt.exit_code, ExitCode::SYS_ASSERTION_FAILED);
+    assert!(res.msg_receipt.gas_used < fvm_shared::BLOCK_GAS_LIMIT);
+    assert!(matches!(
+        res.failure_info,
+        Some(ApplyFailure::Timeout { limit: l }) if l == limit
+    ));
+}
+
+#[test]
+fn fuel_metering() {
+    use fvm::engine::GasMetering;
+
+    // Counts down from 1000, without using bulk memory instructions (whose per-byte costs can't be
+    // metered with fuel).
+    let wasm_bin = wat::parse_str(
+        r#"(module
+             (memory (export "memory") 1)
+             (func (export "invoke") (param $x i32) (result i32)
+               (local $i i32)
+               (local.set $i (i32.const 1000))
+               (loop
+                 (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+                 (br_if 0 (local.get $i)))
+               (i32.const 0)))"#,
+    )
+    .unwrap();
+
+    let exec_gas = |gas_metering| {
+        let mut tester = new_tester(
+            NV_FOR_TEST,
+            StateTreeVersion::V5,
+            MemoryBlockstore::default(),
+        )
+        .unwrap();
+        let sender: [Account; 1] = tester.create_accounts().unwrap();
+        let state_cid = tester.set_state(&State::default()).unwrap();
+        let actor_address = Address::new_id(10000);
+        tester
+            .set_actor_from_bin(&wasm_bin, state_cid, actor_address, TokenAmount::zero())
+            .unwrap();
+        tester
+            .instantiate_machine_with_engine_config(
+                DummyExterns,
+                |_| {},
+                |_| {},
+                |ec| ec.gas_metering = gas_metering,
+            )
+            .unwrap();
+
+        let message = Message {
+            from: sender[0].1,
+            to: actor_address,
+            gas_limit: 1000000000,
+            method_num: 1,
+            ..Message::default()
+        };
+        let res = tester
+            .executor
+            .unwrap()
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert_eq!(res.msg_receipt.exit_code, ExitCode::OK);
+        (res.gas_breakdown["wasm/exec"], res.msg_receipt.gas_used)
+    };
+
+    // Both backends charge the same gas for the same instructions.
+    let instrumented = exec_gas(GasMetering::Instrumentation);
+    assert!(!instrumented.0.is_zero());
+    assert_eq!(exec_gas(GasMetering::Fuel), instrumented);
+}
+
 #[test]
 fn unreachable() {
     test_exitcode(
@@ -921,7 +1808,10 @@ fn test_oom3() {
         .instantiate_machine_with_config(
             DummyExterns,
             //
-            |ncfg| ncfg.max_memory_bytes = 65536,
+            |ncfg| {
+                ncfg.max_memory_bytes = 65536;
+                ncfg.max_inst_memory_bytes = 65536;
+            },
             |_| {},
         )
         .unwrap();
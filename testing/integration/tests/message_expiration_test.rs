@@ -0,0 +1,103 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::executor::{ApplyFailure, ApplyKind, Executor, PreValidationFailure};
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+
+#[test]
+fn message_expiration() {
+    let mut tester = new_tester(
+        NetworkVersion::V22,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_, sender), (_, receiver)] = tester.create_accounts().unwrap();
+
+    tester
+        .instantiate_machine_with_config(DummyExterns, |_| (), |mc| mc.epoch = 10)
+        .unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let message = |valid_until_epoch| Message {
+        from: sender,
+        to: receiver,
+        value: TokenAmount::from_atto(1),
+        gas_limit: 10_000_000,
+        valid_until_epoch,
+        ..Message::default()
+    };
+
+    // Expired messages fail pre-validation, without bumping the sender's nonce.
+    let res = executor
+        .execute_message(message(Some(9)), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(
+        res.msg_receipt.exit_code,
+        ExitCode::SYS_SENDER_STATE_INVALID
+    );
+    assert!(matches!(
+        res.failure_info,
+        Some(ApplyFailure::Expired {
+            valid_until_epoch: 9,
+            epoch: 10
+        })
+    ));
+
+    // Messages are valid up to and including their last epoch.
+    let res = executor
+        .execute_message(message(Some(10)), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+
+    // Implicit messages never expire.
+    let res = executor
+        .execute_message(message(Some(0)), ApplyKind::Implicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+}
+
+#[test]
+fn message_expiration_not_supported() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_, sender), (_, receiver)] = tester.create_accounts().unwrap();
+
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    // Before the expiration network version, messages with an expiration are rejected.
+    let message = Message {
+        from: sender,
+        to: receiver,
+        gas_limit: 10_000_000,
+        valid_until_epoch: Some(i64::MAX),
+        ..Message::default()
+    };
+    let res = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(
+        res.msg_receipt.exit_code,
+        ExitCode::SYS_SENDER_STATE_INVALID
+    );
+    assert!(matches!(
+        res.failure_info,
+        Some(ApplyFailure::PreValidation(
+            PreValidationFailure::ExpirationNotSupported
+        ))
+    ));
+}
@@ -0,0 +1,126 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use std::sync::{Arc, Mutex};
+
+use bundles::*;
+use fvm::engine::EnginePool;
+use fvm::executor::{
+    ApplyHook, ApplyKind, ApplyRet, DefaultExecutor, ExecutionOptions, Executor, ParallelExecutor,
+};
+use fvm::machine::{DefaultMachine, Machine, MachineContext};
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_integration_tests::tester::{Account, IntegrationExecutor, Tester};
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::message::Message;
+use fvm_shared::receipt::Receipt;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+
+type Store = Arc<MemoryBlockstore>;
+
+/// Records the receipts of the messages applied, and counts the messages about to be applied.
+#[derive(Default)]
+struct RecordingHook {
+    pre_applied: Mutex<usize>,
+    receipts: Mutex<Vec<Receipt>>,
+}
+
+impl ApplyHook for RecordingHook {
+    fn pre_apply(&self, _: ChainEpoch, _: &Message, _: ApplyKind) {
+        *self.pre_applied.lock().unwrap() += 1;
+    }
+
+    fn post_apply(&self, _: ChainEpoch, _: &Message, _: ApplyKind, ret: &ApplyRet) {
+        self.receipts.lock().unwrap().push(ret.msg_receipt.clone());
+    }
+}
+
+fn setup() -> (Store, Tester<Store, DummyExterns>, [Account; 4]) {
+    let store = Store::default();
+    let mut tester = new_tester(NetworkVersion::V21, StateTreeVersion::V5, store.clone()).unwrap();
+    let accounts = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+    (store, tester, accounts)
+}
+
+/// Messages touching disjoint and overlapping actors.
+fn messages(accounts: &[Account; 4]) -> Vec<(Message, usize)> {
+    let [(_, a), (_, b), (_, c), (_, d)] = *accounts;
+    let send = |from, to, sequence| {
+        let message = Message {
+            from,
+            to,
+            sequence,
+            value: TokenAmount::from_atto(1),
+            gas_limit: 10_000_000,
+            gas_fee_cap: TokenAmount::from_atto(200),
+            gas_premium: TokenAmount::from_atto(10),
+            ..Message::default()
+        };
+        (message, 100)
+    };
+    vec![
+        // Independent transfers.
+        send(a, b, 0),
+        send(c, d, 0),
+        // Conflicts with both: a and c were written.
+        send(a, c, 1),
+        // Creates an account, writing the init actor.
+        send(d, Address::new_bls(&[1; 48]).unwrap(), 0),
+        // Conflicts: b was written, and resolving addresses reads the init actor.
+        send(b, a, 0),
+        // A stale nonce, failing prevalidation.
+        send(c, a, 0),
+    ]
+}
+
+#[test]
+fn parallel_executor() {
+    // Execute serially.
+    let (_, mut tester, accounts) = setup();
+    let mut executor = tester.executor.unwrap();
+    let serial: Vec<Receipt> = messages(&accounts)
+        .into_iter()
+        .map(|(msg, len)| {
+            executor
+                .execute_message(msg, ApplyKind::Explicit, len)
+                .unwrap()
+                .msg_receipt
+        })
+        .collect();
+    let serial_root = executor.flush().unwrap();
+
+    // Execute in parallel, from the same state.
+    let (store, tester, _) = setup();
+    let mut executor = tester.executor.unwrap();
+    let hook = Arc::new(RecordingHook::default());
+    executor.set_options(ExecutionOptions::new().with_hook(hook.clone()));
+    let engine = EnginePool::new_default((&executor.context().network).into()).unwrap();
+    let mut executor = ParallelExecutor::new(
+        executor,
+        |mc: &MachineContext| -> anyhow::Result<IntegrationExecutor<Store, DummyExterns>> {
+            let machine = DefaultMachine::new(mc, store.clone(), DummyExterns)?;
+            DefaultExecutor::new(engine.clone(), machine)
+        },
+    );
+    let parallel: Vec<Receipt> = executor
+        .execute_messages(messages(&accounts))
+        .unwrap()
+        .into_iter()
+        .map(|ret| ret.msg_receipt)
+        .collect();
+
+    assert!(serial[..5].iter().all(|r| r.exit_code.is_success()));
+    assert!(!serial[5].exit_code.is_success());
+    assert_eq!(parallel, serial);
+    assert_eq!(executor.flush().unwrap(), serial_root);
+
+    // The wrapped executor's hooks see every message once, whether it was committed or
+    // re-executed.
+    assert_eq!(*hook.pre_applied.lock().unwrap(), serial.len());
+    assert_eq!(*hook.receipts.lock().unwrap(), serial);
+}
@@ -0,0 +1,82 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::executor::{ApplyFailure, ApplyKind, Executor, PreValidationFailure};
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+
+#[test]
+fn prevalidation_failures() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_, sender), (_, receiver)] = tester.create_accounts().unwrap();
+
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let message = Message {
+        from: sender,
+        to: receiver,
+        gas_limit: 10_000_000,
+        ..Message::default()
+    };
+    let mut apply = |message: Message| {
+        let res = executor
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+        match res.failure_info {
+            Some(ApplyFailure::PreValidation(reason)) => {
+                assert_eq!(res.msg_receipt.exit_code, reason.exit_code());
+                reason
+            }
+            other => panic!("expected a pre-validation failure, got {:?}", other),
+        }
+    };
+
+    assert_eq!(
+        apply(Message {
+            sequence: 3,
+            ..message.clone()
+        }),
+        PreValidationFailure::NonceMismatch {
+            expected: 0,
+            actual: 3
+        }
+    );
+    assert_eq!(
+        apply(Message {
+            from: Address::new_id(1000),
+            ..message.clone()
+        }),
+        PreValidationFailure::SenderNotFound
+    );
+    let reason = apply(Message {
+        gas_fee_cap: TokenAmount::from_atto(1),
+        gas_limit: 1 << 40,
+        ..message.clone()
+    });
+    assert!(matches!(
+        reason,
+        PreValidationFailure::InsufficientFunds { .. }
+    ));
+    assert_eq!(reason.exit_code(), ExitCode::SYS_SENDER_STATE_INVALID);
+    assert!(matches!(
+        apply(Message {
+            gas_limit: 1,
+            ..message
+        }),
+        PreValidationFailure::InclusionOutOfGas { gas_limit: 1, .. }
+    ));
+}
@@ -0,0 +1,76 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::executor::{ApplyKind, Executor};
+use fvm::machine::Machine;
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_integration_tests::tester::INITIAL_ACCOUNT_BALANCE;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+
+#[test]
+fn read_only_machine() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender), (_, receiver)] = tester.create_accounts().unwrap();
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |_| (),
+            |mc| {
+                mc.enable_read_only();
+            },
+        )
+        .unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+    let root = executor.context().initial_state_root;
+
+    let message = |sequence| Message {
+        from: sender,
+        to: receiver,
+        sequence,
+        value: TokenAmount::from_atto(1),
+        gas_limit: 10_000_000,
+        ..Message::default()
+    };
+
+    // Explicit messages are executed, but their changes are reverted.
+    let res = executor
+        .execute_message(message(0), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+    assert!(!executor.state_tree().in_transaction());
+
+    // So the sender's nonce didn't advance.
+    let res = executor
+        .execute_message(message(1), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert_eq!(
+        res.msg_receipt.exit_code,
+        ExitCode::SYS_SENDER_STATE_INVALID
+    );
+    let res = executor
+        .execute_message(message(0), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+
+    // Views behave the same.
+    let res = executor.execute_view(message(0)).unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+
+    // Nothing was persisted.
+    let sender_state = executor.state_tree().get_actor(sender_id).unwrap().unwrap();
+    assert_eq!(sender_state.sequence, 0);
+    assert_eq!(sender_state.balance, *INITIAL_ACCOUNT_BALANCE);
+    assert_eq!(executor.flush().unwrap(), root);
+}
@@ -0,0 +1,103 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::call_manager::{CallManager, DefaultCallManager};
+use fvm::engine::EnginePool;
+use fvm::kernel::default::DefaultKernel;
+use fvm::kernel::{ActorOps, BlockRegistry, IpldBlockOps, MessageOps};
+use fvm::machine::Machine;
+use fvm::Kernel;
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_integration_tests::recording::{replay, KernelOp, OpError, OpValue, RecordingKernel};
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_ipld_encoding::IPLD_RAW;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ErrorNumber;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+use multihash::Code;
+
+#[test]
+fn record_and_replay() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender), (receiver_id, receiver)] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    // Build a kernel directly on top of the machine, without executing a message.
+    let machine = tester.executor.take().unwrap().into_machine().unwrap();
+    let engine = EnginePool::new_default((&machine.context().network).into())
+        .unwrap()
+        .acquire();
+    let call_manager = DefaultCallManager::new(
+        machine,
+        engine,
+        1_000_000_000,
+        sender_id,
+        sender,
+        Some(receiver_id),
+        receiver,
+        0,
+        TokenAmount::default(),
+    );
+    let mut kernel = RecordingKernel::<DefaultKernel<_>>::new(
+        call_manager,
+        BlockRegistry::default(),
+        sender_id,
+        receiver_id,
+        0,
+        TokenAmount::default(),
+        false,
+    );
+
+    assert_eq!(kernel.resolve_address(&sender).unwrap(), sender_id);
+    assert!(kernel
+        .resolve_address(&Address::new_actor(b"nope"))
+        .is_err());
+    let id = kernel.block_create(IPLD_RAW, b"foobar").unwrap();
+    kernel.block_link(id, Code::Blake2b256.into(), 32).unwrap();
+    let mut buf = [0u8; 3];
+    kernel.block_read(id, 3, &mut buf).unwrap();
+    kernel.msg_context().unwrap();
+
+    let ops = kernel.take_ops();
+    assert_eq!(ops.len(), 5);
+    assert_eq!(ops[0].op, KernelOp::ResolveAddress(sender));
+    assert_eq!(ops[0].result, Ok(OpValue::ActorId(sender_id)));
+    assert_eq!(ops[1].result, Err(OpError::Syscall(ErrorNumber::NotFound)));
+    assert_eq!(
+        ops[3].result,
+        Ok(OpValue::BlockRead {
+            remaining: 0,
+            data: b"bar".to_vec()
+        })
+    );
+    assert!(kernel.ops().is_empty());
+
+    // Replay the operations on a fresh kernel.
+    let (call_manager, _) = kernel.into_inner();
+    let mut kernel = DefaultKernel::new(
+        call_manager,
+        BlockRegistry::default(),
+        sender_id,
+        receiver_id,
+        0,
+        TokenAmount::default(),
+        false,
+    );
+    replay(&mut kernel, &ops).unwrap();
+
+    // Replay detects diverging results.
+    let mut ops = ops;
+    ops[0].result = Ok(OpValue::ActorId(receiver_id));
+    let mismatch = replay(&mut kernel, &ops).unwrap_err();
+    assert_eq!(mismatch.index, 0);
+    assert_eq!(mismatch.actual, Ok(OpValue::ActorId(sender_id)));
+}
@@ -0,0 +1,45 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use futures::io::AllowStdIo;
+use fvm::machine::Machine;
+use fvm::state_tree::StateTree;
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_integration_tests::tester::INITIAL_ACCOUNT_BALANCE;
+use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+
+#[test]
+fn export_and_import_snapshot() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, _), _] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let mut car = AllowStdIo::new(Vec::new());
+    let root = executor.export_snapshot(&mut car).unwrap();
+    let car = car.into_inner();
+
+    // The snapshot is self-contained: the state, the manifest and the actors' code can be loaded
+    // from it alone.
+    let imported = MemoryBlockstore::default();
+    let roots =
+        futures::executor::block_on(fvm_ipld_car::load_car(&imported, car.as_slice())).unwrap();
+    assert_eq!(roots, vec![root]);
+
+    let state_tree = StateTree::new_from_root(&imported, &root).unwrap();
+    let sender = state_tree.get_actor(sender_id).unwrap().unwrap();
+    assert_eq!(sender.balance, *INITIAL_ACCOUNT_BALANCE);
+    assert!(imported.has(&sender.code).unwrap());
+    for code in executor.builtin_actors().builtin_actor_codes() {
+        assert!(imported.has(code).unwrap());
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::call_manager::{CallManager, DefaultCallManager};
+use fvm::engine::EnginePool;
+use fvm::kernel::ExecutionError;
+use fvm::machine::Machine;
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+use fvm_shared::ActorID;
+
+#[test]
+fn nested_transactions() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender), (receiver_id, receiver)] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    let machine = tester.executor.take().unwrap().into_machine().unwrap();
+    let engine = EnginePool::new_default((&machine.context().network).into())
+        .unwrap()
+        .acquire();
+    let mut cm = DefaultCallManager::new(
+        machine,
+        engine,
+        1_000_000_000,
+        sender_id,
+        sender,
+        Some(receiver_id),
+        receiver,
+        0,
+        TokenAmount::default(),
+    );
+
+    let initial = balance(&cm, receiver_id);
+    let value = TokenAmount::from_atto(100);
+
+    cm.begin_transaction();
+    cm.transfer(sender_id, receiver_id, &value).unwrap();
+
+    // Reverting the inner transaction only discards the inner transfer.
+    cm.begin_transaction();
+    cm.transfer(sender_id, receiver_id, &value).unwrap();
+    assert_eq!(balance(&cm, receiver_id), &initial + &value * 2);
+    cm.end_transaction(true).unwrap();
+    assert_eq!(balance(&cm, receiver_id), &initial + &value);
+
+    // Committing the inner transaction merges it into the outer transaction.
+    cm.begin_transaction();
+    cm.transfer(sender_id, receiver_id, &value).unwrap();
+    cm.end_transaction(false).unwrap();
+    assert_eq!(balance(&cm, receiver_id), &initial + &value * 2);
+
+    cm.end_transaction(false).unwrap();
+    assert_eq!(balance(&cm, receiver_id), &initial + &value * 2);
+
+    // Transactions must be balanced.
+    assert!(matches!(
+        cm.end_transaction(false),
+        Err(ExecutionError::Fatal(_))
+    ));
+}
+
+fn balance(cm: &impl CallManager, id: ActorID) -> TokenAmount {
+    cm.get_actor(id).unwrap().unwrap().balance
+}
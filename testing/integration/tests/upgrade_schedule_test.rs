@@ -0,0 +1,89 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::executor::{ApplyKind, Executor};
+use fvm::gas::PriceList;
+use fvm::machine::{Machine, NetworkUpgrade, UpgradeSchedule};
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_integration_tests::tester::IntegrationExecutor;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+
+/// Sends a value transfer, returning the gas used.
+fn send(
+    executor: &mut IntegrationExecutor<MemoryBlockstore, DummyExterns>,
+    from: Address,
+    to: Address,
+    sequence: u64,
+) -> u64 {
+    let message = Message {
+        from,
+        to,
+        value: TokenAmount::from_atto(1),
+        gas_limit: 10_000_000,
+        sequence,
+        ..Message::default()
+    };
+    let res = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(res.msg_receipt.exit_code.is_success());
+    res.msg_receipt.gas_used
+}
+
+#[test]
+fn upgrade_schedule() {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_, sender), (_, receiver)] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let repriced = PriceList::from_json(
+        r#"{"version": 1, "base": 21, "prices": {"send_transfer_funds": 7000000}}"#,
+    )
+    .unwrap()
+    .intern();
+    executor.set_upgrade_schedule(
+        UpgradeSchedule::new([
+            NetworkUpgrade::new(10, NetworkVersion::V21).with_price_list(repriced)
+        ])
+        .unwrap(),
+    );
+
+    let before = send(executor, sender, receiver, 0);
+
+    // No upgrade before epoch 10.
+    let base_fee = TokenAmount::from_atto(100);
+    let supply = fvm_shared::TOTAL_FILECOIN.clone();
+    let upgrade = executor
+        .advance_epoch(5, 5, base_fee.clone(), supply.clone())
+        .unwrap();
+    assert_eq!(upgrade, None);
+    assert_eq!(executor.context().epoch, 5);
+
+    // The upgrade is applied when crossing epoch 10, even if that epoch is skipped.
+    let upgrade = executor
+        .advance_epoch(12, 12, base_fee.clone(), supply.clone())
+        .unwrap();
+    assert_eq!(upgrade.map(|u| u.epoch), Some(10));
+    assert_eq!(executor.context().epoch, 12);
+    assert!(std::ptr::eq(executor.context().price_list, repriced));
+
+    // State carries over, and the new prices apply.
+    let after = send(executor, sender, receiver, 1);
+    assert_eq!(after, before + 1000);
+
+    // Epochs only advance.
+    assert!(executor.advance_epoch(12, 12, base_fee, supply).is_err());
+}